@@ -40,7 +40,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             MEMBER_READ,
         ))
         // Rule: Explicitly deny if tenant_id doesn't match resource owner (ABAC pattern)
-        // In a real app, you might compare request.tenant_id to resource.tenant_id
+        // The documents here belong to tenant-1; in a real app, the adapter
+        // would compare request.tenant_id to resource.tenant_id
+        .rule(Rule::new(
+            Effect::Deny,
+            Target {
+                principal: Matcher::Any,
+                action: Matcher::Any,
+                resource: Matcher::Exact("doc-123"),
+            },
+            Some(Condition::NotEquals {
+                attr: "tenant_id",
+                value: Value::String("tenant-1"),
+            }),
+            CROSS_TENANT_DENY,
+        ))
         .build()?;
 
     println!("--- Gate0 SaaS API Example ---");
@@ -71,5 +85,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Bob (Member) read doc-123: {:?}", dec_c.effect);
     assert!(dec_c.is_allow());
 
+    // Scenario D: Admin of another tenant trying to read it (Denied)
+    let carol_ctx: &[(&str, Value)] = &[
+        ("role", Value::String("admin")),
+        ("tenant_id", Value::String("tenant-2")),
+    ];
+    let req_d = Request::with_context("carol", "read", "doc-123", carol_ctx);
+    let dec_d = policy.evaluate(&req_d)?;
+    println!("Carol (Admin, tenant-2) read doc-123: {:?}", dec_d.effect);
+    assert!(dec_d.is_deny());
+    assert_eq!(dec_d.reason, CROSS_TENANT_DENY);
+
     Ok(())
 }
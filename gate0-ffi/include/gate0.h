@@ -0,0 +1,103 @@
+/*
+ * C API for embedding Gate0.
+ *
+ * Policies are loaded from the JSON form of gate0::OwnedPolicy. Strings are
+ * passed as pointer and length (not NUL-terminated) and must be UTF-8.
+ * Every function returns a GATE0_* status instead of unwinding; the message
+ * of the last failure on the calling thread is read with gate0_last_error.
+ *
+ * A Gate0Policy is immutable once loaded and may be evaluated from several
+ * threads at once. Release it exactly once with gate0_policy_free.
+ */
+#ifndef GATE0_H
+#define GATE0_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define GATE0_OK 0
+#define GATE0_ERR_NULL 1
+#define GATE0_ERR_UTF8 2
+#define GATE0_ERR_PARSE 3
+#define GATE0_ERR_POLICY 4
+#define GATE0_ERR_EVAL 5
+#define GATE0_ERR_VALUE_KIND 6
+#define GATE0_ERR_PANIC 7
+
+#define GATE0_VALUE_BOOL 0
+#define GATE0_VALUE_INT 1
+#define GATE0_VALUE_STRING 2
+#define GATE0_VALUE_IP 3
+#define GATE0_VALUE_TIMESTAMP 4
+#define GATE0_VALUE_DURATION 5
+
+#define GATE0_EFFECT_ALLOW 0
+#define GATE0_EFFECT_DENY 1
+
+typedef struct Gate0Policy Gate0Policy;
+
+/* ptr may be NULL only when len is 0. */
+typedef struct Gate0Str {
+    const uint8_t *ptr;
+    size_t len;
+} Gate0Str;
+
+/*
+ * int_value holds BOOL (0 is false), INT, TIMESTAMP (seconds since the Unix
+ * epoch, UTC), and DURATION (seconds, not negative) values; str_value holds
+ * STRING values and IP addresses as text ("10.0.0.1", "::1").
+ */
+typedef struct Gate0Attr {
+    Gate0Str key;
+    uint32_t kind;
+    int64_t int_value;
+    Gate0Str str_value;
+} Gate0Attr;
+
+typedef struct Gate0Request {
+    Gate0Str principal;
+    Gate0Str action;
+    Gate0Str resource;
+    const Gate0Attr *context;
+    size_t context_len;
+} Gate0Request;
+
+typedef struct Gate0Decision {
+    uint32_t effect;
+    uint32_t reason;
+    uint16_t rules_checked;
+    uint16_t condition_evals;
+    uint16_t matcher_comparisons;
+    uint16_t context_lookups;
+    uint32_t steps;
+    uint16_t ancestors_walked;
+    uint8_t max_depth_reached;
+    uint8_t indexed;
+} Gate0Decision;
+
+/* Load a policy; *out is NULL on failure. */
+int32_t gate0_policy_from_json(const uint8_t *json, size_t len, Gate0Policy **out);
+
+/* Release a policy. NULL is ignored. */
+void gate0_policy_free(Gate0Policy *policy);
+
+/* The policy's fingerprint, or 0 for NULL. */
+uint64_t gate0_policy_fingerprint(const Gate0Policy *policy);
+
+int32_t gate0_evaluate(const Gate0Policy *policy, const Gate0Request *request, Gate0Decision *out);
+
+/*
+ * Copy the last error message into buf (NUL-terminated, truncated to cap - 1
+ * bytes) and return its full length. Pass buf = NULL, cap = 0 to query.
+ */
+size_t gate0_last_error(uint8_t *buf, size_t cap);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* GATE0_H */
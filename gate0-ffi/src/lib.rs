@@ -0,0 +1,584 @@
+//! C API for embedding Gate0.
+//!
+//! Policies are loaded from the JSON form of `OwnedPolicy`, requests are
+//! passed as plain structs of length-delimited strings, and decisions come
+//! back as a struct of effect, reason, and stats. The header is
+//! `include/gate0.h`.
+//!
+//! # Safety across the boundary
+//!
+//! Every function returns a status code instead of panicking: null pointers,
+//! invalid UTF-8, and bad value kinds are reported as errors, and any panic
+//! is caught before it reaches C and reported as `GATE0_ERR_PANIC`. The
+//! message for the last error on the calling thread is available through
+//! `gate0_last_error`.
+//!
+//! A loaded policy is immutable, so one handle may be evaluated from many
+//! threads at once. It must be released exactly once with
+//! `gate0_policy_free`.
+
+use std::cell::RefCell;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+use std::slice;
+
+use gate0::{Effect, EvaluationStats, OwnedPolicy, Request, SharedPolicy, Termination, Value};
+
+/// Success.
+pub const GATE0_OK: i32 = 0;
+/// A required pointer was null.
+pub const GATE0_ERR_NULL: i32 = 1;
+/// A string was not valid UTF-8.
+pub const GATE0_ERR_UTF8: i32 = 2;
+/// The policy JSON could not be parsed.
+pub const GATE0_ERR_PARSE: i32 = 3;
+/// The policy violates its configured limits.
+pub const GATE0_ERR_POLICY: i32 = 4;
+/// Evaluation returned an error.
+pub const GATE0_ERR_EVAL: i32 = 5;
+/// A context attribute has an unknown value kind, or a value its kind
+/// cannot hold (an unparsable address or a negative duration).
+pub const GATE0_ERR_VALUE_KIND: i32 = 6;
+/// A panic was caught at the boundary.
+pub const GATE0_ERR_PANIC: i32 = 7;
+
+/// Attribute value kind: boolean, read from `int_value` (0 is false).
+pub const GATE0_VALUE_BOOL: u32 = 0;
+/// Attribute value kind: integer, read from `int_value`.
+pub const GATE0_VALUE_INT: u32 = 1;
+/// Attribute value kind: string, read from `str_value`.
+pub const GATE0_VALUE_STRING: u32 = 2;
+/// Attribute value kind: IP address, parsed from the text in `str_value`.
+pub const GATE0_VALUE_IP: u32 = 3;
+/// Attribute value kind: seconds since the Unix epoch (UTC), read from
+/// `int_value`.
+pub const GATE0_VALUE_TIMESTAMP: u32 = 4;
+/// Attribute value kind: a length of time in whole seconds, read from
+/// `int_value`; must not be negative.
+pub const GATE0_VALUE_DURATION: u32 = 5;
+
+/// Decision effect: allowed.
+pub const GATE0_EFFECT_ALLOW: u32 = 0;
+/// Decision effect: denied.
+pub const GATE0_EFFECT_DENY: u32 = 1;
+
+/// A UTF-8 string given as pointer and length, not NUL-terminated.
+///
+/// `ptr` may be null only when `len` is 0.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Gate0Str {
+    /// First byte.
+    pub ptr: *const u8,
+    /// Length in bytes.
+    pub len: usize,
+}
+
+/// One context attribute.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Gate0Attr {
+    /// Attribute name.
+    pub key: Gate0Str,
+    /// One of the `GATE0_VALUE_*` kinds.
+    pub kind: u32,
+    /// Value for `GATE0_VALUE_BOOL`, `GATE0_VALUE_INT`,
+    /// `GATE0_VALUE_TIMESTAMP`, and `GATE0_VALUE_DURATION`.
+    pub int_value: i64,
+    /// Value for `GATE0_VALUE_STRING` and `GATE0_VALUE_IP`.
+    pub str_value: Gate0Str,
+}
+
+/// A request to evaluate.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Gate0Request {
+    /// The principal making the request.
+    pub principal: Gate0Str,
+    /// The action being requested.
+    pub action: Gate0Str,
+    /// The resource being accessed.
+    pub resource: Gate0Str,
+    /// Context attributes; may be null when `context_len` is 0.
+    pub context: *const Gate0Attr,
+    /// Number of context attributes.
+    pub context_len: usize,
+}
+
+/// A decision and the stats of the evaluation that made it.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Gate0Decision {
+    /// `GATE0_EFFECT_ALLOW` or `GATE0_EFFECT_DENY`.
+    pub effect: u32,
+    /// The reason code.
+    pub reason: u32,
+    /// Rules checked.
+    pub rules_checked: u16,
+    /// Conditions evaluated.
+    pub condition_evals: u16,
+    /// Matchers checked against request fields.
+    pub matcher_comparisons: u16,
+    /// Context attribute lookups.
+    pub context_lookups: u16,
+    /// Evaluation steps consumed.
+    pub steps: u32,
+    /// Resource ancestors walked.
+    pub ancestors_walked: u16,
+    /// Deepest condition stack reached.
+    pub max_depth_reached: u8,
+    /// 1 if the target index excluded some rules, 0 if every rule was checked.
+    pub indexed: u8,
+}
+
+/// A loaded policy.
+pub struct Gate0Policy {
+    shared: SharedPolicy,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+/// An error status with its message.
+struct Failure(i32, String);
+
+fn fail(status: i32, message: impl Into<String>) -> Failure {
+    Failure(status, message.into())
+}
+
+/// Run `body` with panics caught, recording the message of any failure.
+fn guard(body: impl FnOnce() -> Result<(), Failure>) -> i32 {
+    let result = catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|_| Err(fail(GATE0_ERR_PANIC, "panic caught at the C boundary")));
+    match result {
+        Ok(()) => GATE0_OK,
+        Err(Failure(status, message)) => {
+            LAST_ERROR.with(|last| *last.borrow_mut() = message);
+            status
+        }
+    }
+}
+
+/// Borrow a `Gate0Str` as `&str`.
+///
+/// # Safety
+///
+/// `s.ptr` must point to `s.len` readable bytes that outlive `'a`.
+unsafe fn as_str<'a>(s: Gate0Str, what: &str) -> Result<&'a str, Failure> {
+    let bytes = as_bytes(s.ptr, s.len, what)?;
+    std::str::from_utf8(bytes).map_err(|_| fail(GATE0_ERR_UTF8, format!("{} is not UTF-8", what)))
+}
+
+/// # Safety
+///
+/// `ptr` must point to `len` readable bytes that outlive `'a`.
+unsafe fn as_bytes<'a>(ptr: *const u8, len: usize, what: &str) -> Result<&'a [u8], Failure> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(fail(GATE0_ERR_NULL, format!("{} is null", what)));
+    }
+    Ok(slice::from_raw_parts(ptr, len))
+}
+
+/// Load a policy from the JSON form of `OwnedPolicy`.
+///
+/// On success, stores a new handle in `*out`; release it with
+/// `gate0_policy_free`. On failure, `*out` is set to null.
+///
+/// # Safety
+///
+/// `json` must point to `len` readable bytes and `out` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn gate0_policy_from_json(
+    json: *const u8,
+    len: usize,
+    out: *mut *mut Gate0Policy,
+) -> i32 {
+    guard(|| {
+        if out.is_null() {
+            return Err(fail(GATE0_ERR_NULL, "out is null"));
+        }
+        *out = ptr::null_mut();
+        let bytes = as_bytes(json, len, "json")?;
+        let owned: OwnedPolicy = serde_json::from_slice(bytes)
+            .map_err(|e| fail(GATE0_ERR_PARSE, format!("invalid policy JSON: {}", e)))?;
+        let shared = SharedPolicy::new(owned)
+            .map_err(|e| fail(GATE0_ERR_POLICY, format!("invalid policy: {}", e)))?;
+        *out = Box::into_raw(Box::new(Gate0Policy { shared }));
+        Ok(())
+    })
+}
+
+/// Release a policy. Null is ignored.
+///
+/// # Safety
+///
+/// `policy` must be null or a handle from `gate0_policy_from_json` that has
+/// not been freed, and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn gate0_policy_free(policy: *mut Gate0Policy) {
+    if !policy.is_null() {
+        // A panicking destructor must not unwind into C
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(policy))));
+    }
+}
+
+/// The policy's fingerprint (see `Policy::fingerprint`), or 0 for null.
+///
+/// # Safety
+///
+/// `policy` must be null or a live handle.
+#[no_mangle]
+pub unsafe extern "C" fn gate0_policy_fingerprint(policy: *const Gate0Policy) -> u64 {
+    policy
+        .as_ref()
+        .map_or(0, |p| p.shared.policy().fingerprint())
+}
+
+/// Evaluate a request, writing the decision to `*out`.
+///
+/// # Safety
+///
+/// `policy` must be a live handle, `request` must point to a valid
+/// `Gate0Request` whose strings and context array are readable, and `out`
+/// must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn gate0_evaluate(
+    policy: *const Gate0Policy,
+    request: *const Gate0Request,
+    out: *mut Gate0Decision,
+) -> i32 {
+    guard(|| {
+        let policy = policy
+            .as_ref()
+            .ok_or_else(|| fail(GATE0_ERR_NULL, "policy is null"))?;
+        let request = request
+            .as_ref()
+            .ok_or_else(|| fail(GATE0_ERR_NULL, "request is null"))?;
+        if out.is_null() {
+            return Err(fail(GATE0_ERR_NULL, "out is null"));
+        }
+
+        let attrs: &[Gate0Attr] = if request.context_len == 0 {
+            &[]
+        } else if request.context.is_null() {
+            return Err(fail(GATE0_ERR_NULL, "context is null"));
+        } else {
+            slice::from_raw_parts(request.context, request.context_len)
+        };
+        let mut context = Vec::with_capacity(attrs.len());
+        for attr in attrs {
+            let key = as_str(attr.key, "attribute name")?;
+            let value = match attr.kind {
+                GATE0_VALUE_BOOL => Value::Bool(attr.int_value != 0),
+                GATE0_VALUE_INT => Value::Int(attr.int_value),
+                GATE0_VALUE_STRING => Value::String(as_str(attr.str_value, "attribute value")?),
+                GATE0_VALUE_IP => {
+                    let text = as_str(attr.str_value, "attribute value")?;
+                    Value::Ip(text.parse().map_err(|_| {
+                        fail(
+                            GATE0_ERR_VALUE_KIND,
+                            format!("attribute '{}' is not an IP address: '{}'", key, text),
+                        )
+                    })?)
+                }
+                GATE0_VALUE_TIMESTAMP => Value::Timestamp(attr.int_value),
+                GATE0_VALUE_DURATION => {
+                    Value::Duration(u64::try_from(attr.int_value).map_err(|_| {
+                        fail(
+                            GATE0_ERR_VALUE_KIND,
+                            format!("attribute '{}' is a negative duration", key),
+                        )
+                    })?)
+                }
+                kind => {
+                    return Err(fail(
+                        GATE0_ERR_VALUE_KIND,
+                        format!("attribute '{}' has unknown value kind {}", key, kind),
+                    ))
+                }
+            };
+            context.push((key, value));
+        }
+
+        let request = Request::with_context(
+            as_str(request.principal, "principal")?,
+            as_str(request.action, "action")?,
+            as_str(request.resource, "resource")?,
+            &context,
+        );
+        let (decision, stats) = policy
+            .shared
+            .policy()
+            .evaluate_with_stats(&request)
+            .map_err(|e| fail(GATE0_ERR_EVAL, e.to_string()))?;
+        *out = to_c(decision.effect, decision.reason.value(), &stats);
+        Ok(())
+    })
+}
+
+fn to_c(effect: Effect, reason: u32, stats: &EvaluationStats) -> Gate0Decision {
+    Gate0Decision {
+        effect: match effect {
+            Effect::Allow => GATE0_EFFECT_ALLOW,
+            Effect::Deny => GATE0_EFFECT_DENY,
+        },
+        reason,
+        rules_checked: stats.rules_checked,
+        condition_evals: stats.condition_evals,
+        matcher_comparisons: stats.matcher_comparisons,
+        context_lookups: stats.context_lookups,
+        steps: stats.steps,
+        ancestors_walked: stats.ancestors_walked,
+        max_depth_reached: stats.max_depth_reached,
+        indexed: u8::from(stats.termination == Termination::ExhaustedCandidates),
+    }
+}
+
+/// Copy the last error message on this thread into `buf`.
+///
+/// Writes at most `cap - 1` bytes plus a NUL terminator and returns the full
+/// message length, so a return value of `cap` or more means the message was
+/// truncated. `buf` may be null when `cap` is 0, to query the length.
+///
+/// # Safety
+///
+/// `buf` must be null or point to `cap` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn gate0_last_error(buf: *mut u8, cap: usize) -> usize {
+    LAST_ERROR.with(|last| {
+        let message = last.borrow();
+        if !buf.is_null() && cap > 0 {
+            let n = message.len().min(cap - 1);
+            ptr::copy_nonoverlapping(message.as_ptr(), buf, n);
+            *buf.add(n) = 0;
+        }
+        message.len()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"{
+        "rules": [
+            {
+                "effect": "deny",
+                "target": { "principal": { "exact": "mallory" }, "action": "any", "resource": "any" },
+                "reason": 2
+            },
+            {
+                "effect": "allow",
+                "target": { "principal": "any", "action": { "exact": "read" }, "resource": "any" },
+                "condition": { "equals": { "attr": "mfa", "value": true } },
+                "reason": 1
+            }
+        ]
+    }"#;
+
+    fn s(text: &str) -> Gate0Str {
+        Gate0Str {
+            ptr: text.as_ptr(),
+            len: text.len(),
+        }
+    }
+
+    fn load(json: &str) -> (i32, *mut Gate0Policy) {
+        let mut policy = ptr::null_mut();
+        let status = unsafe { gate0_policy_from_json(json.as_ptr(), json.len(), &mut policy) };
+        (status, policy)
+    }
+
+    fn last_error() -> String {
+        let mut buf = [0u8; 256];
+        let len = unsafe { gate0_last_error(buf.as_mut_ptr(), buf.len()) };
+        String::from_utf8(buf[..len.min(255)].to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_load_and_evaluate() {
+        let (status, policy) = load(POLICY);
+        assert_eq!(status, GATE0_OK);
+        assert_ne!(unsafe { gate0_policy_fingerprint(policy) }, 0);
+
+        let attrs = [Gate0Attr {
+            key: s("mfa"),
+            kind: GATE0_VALUE_BOOL,
+            int_value: 1,
+            str_value: s(""),
+        }];
+        let request = Gate0Request {
+            principal: s("alice"),
+            action: s("read"),
+            resource: s("doc"),
+            context: attrs.as_ptr(),
+            context_len: attrs.len(),
+        };
+        let mut decision = Gate0Decision::default();
+        assert_eq!(
+            unsafe { gate0_evaluate(policy, &request, &mut decision) },
+            GATE0_OK
+        );
+        assert_eq!(decision.effect, GATE0_EFFECT_ALLOW);
+        assert_eq!(decision.reason, 1);
+        assert_eq!(decision.rules_checked, 2);
+        assert_eq!(decision.context_lookups, 1);
+
+        let request = Gate0Request {
+            principal: s("mallory"),
+            context: ptr::null(),
+            context_len: 0,
+            ..request
+        };
+        assert_eq!(
+            unsafe { gate0_evaluate(policy, &request, &mut decision) },
+            GATE0_OK
+        );
+        assert_eq!((decision.effect, decision.reason), (GATE0_EFFECT_DENY, 2));
+
+        unsafe { gate0_policy_free(policy) };
+    }
+
+    #[test]
+    fn test_typed_values() {
+        let (status, policy) = load(
+            r#"{
+                "rules": [
+                    {
+                        "effect": "allow",
+                        "target": { "principal": "any", "action": "any", "resource": "any" },
+                        "condition": { "and": [
+                            { "ip_in_cidr": { "attr": "source_ip", "cidr": "10.0.0.0/8" } },
+                            { "and": [
+                                { "time_between": { "attr": "now", "start": 32400, "end": 61200 } },
+                                { "less_than": { "attr": "session", "value": { "duration": 3600 } } }
+                            ] }
+                        ] },
+                        "reason": 1
+                    }
+                ]
+            }"#,
+        );
+        assert_eq!(status, GATE0_OK);
+
+        let evaluate = |ip: &str, now: i64, session: i64| {
+            let attrs = [
+                Gate0Attr {
+                    key: s("source_ip"),
+                    kind: GATE0_VALUE_IP,
+                    int_value: 0,
+                    str_value: s(ip),
+                },
+                Gate0Attr {
+                    key: s("now"),
+                    kind: GATE0_VALUE_TIMESTAMP,
+                    int_value: now,
+                    str_value: s(""),
+                },
+                Gate0Attr {
+                    key: s("session"),
+                    kind: GATE0_VALUE_DURATION,
+                    int_value: session,
+                    str_value: s(""),
+                },
+            ];
+            let request = Gate0Request {
+                principal: s("alice"),
+                action: s("read"),
+                resource: s("doc"),
+                context: attrs.as_ptr(),
+                context_len: attrs.len(),
+            };
+            let mut decision = Gate0Decision::default();
+            let status = unsafe { gate0_evaluate(policy, &request, &mut decision) };
+            (status, decision.effect)
+        };
+
+        // 2023-11-14 10:13:20 UTC
+        let morning = 1_699_956_800;
+        assert_eq!(
+            evaluate("10.1.2.3", morning, 600),
+            (GATE0_OK, GATE0_EFFECT_ALLOW)
+        );
+        assert_eq!(evaluate("192.168.0.1", morning, 600).1, GATE0_EFFECT_DENY);
+        assert_eq!(
+            evaluate("10.1.2.3", morning + 12 * 3600, 600).1,
+            GATE0_EFFECT_DENY
+        );
+        assert_eq!(evaluate("10.1.2.3", morning, 7200).1, GATE0_EFFECT_DENY);
+
+        assert_eq!(evaluate("10.1.2", morning, 600).0, GATE0_ERR_VALUE_KIND);
+        assert_eq!(
+            last_error(),
+            "attribute 'source_ip' is not an IP address: '10.1.2'"
+        );
+        assert_eq!(evaluate("10.1.2.3", morning, -1).0, GATE0_ERR_VALUE_KIND);
+        assert_eq!(last_error(), "attribute 'session' is a negative duration");
+
+        unsafe { gate0_policy_free(policy) };
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let (status, policy) = load("{ not json");
+        assert_eq!(status, GATE0_ERR_PARSE);
+        assert!(policy.is_null());
+        assert!(last_error().starts_with("invalid policy JSON"));
+
+        let too_many = r#"{ "rules": [], "config": { "max_condition_depth": 1000 } }"#;
+        assert_eq!(load(too_many).0, GATE0_ERR_POLICY);
+
+        let (_, policy) = load(POLICY);
+        let mut decision = Gate0Decision::default();
+        let bad_utf8 = [0xffu8];
+        let request = Gate0Request {
+            principal: Gate0Str {
+                ptr: bad_utf8.as_ptr(),
+                len: 1,
+            },
+            action: s("read"),
+            resource: s("doc"),
+            context: ptr::null(),
+            context_len: 0,
+        };
+        assert_eq!(
+            unsafe { gate0_evaluate(policy, &request, &mut decision) },
+            GATE0_ERR_UTF8
+        );
+        assert_eq!(last_error(), "principal is not UTF-8");
+
+        let attrs = [Gate0Attr {
+            key: s("mfa"),
+            kind: 9,
+            int_value: 0,
+            str_value: s(""),
+        }];
+        let request = Gate0Request {
+            principal: s("alice"),
+            context: attrs.as_ptr(),
+            context_len: 1,
+            ..request
+        };
+        assert_eq!(
+            unsafe { gate0_evaluate(policy, &request, &mut decision) },
+            GATE0_ERR_VALUE_KIND
+        );
+        assert_eq!(
+            unsafe { gate0_evaluate(ptr::null(), &request, &mut decision) },
+            GATE0_ERR_NULL
+        );
+        assert_eq!(
+            unsafe { gate0_last_error(ptr::null_mut(), 0) },
+            "policy is null".len()
+        );
+
+        unsafe {
+            gate0_policy_free(policy);
+            gate0_policy_free(ptr::null_mut());
+        }
+    }
+}
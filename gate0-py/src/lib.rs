@@ -0,0 +1,404 @@
+//! Python bindings for Gate0.
+//!
+//! Exposes `Policy`, `Request`, and `Decision` to Python as the `gate0`
+//! module. The Python `Policy` wraps the same evaluator as the Rust one, so
+//! replaying logged requests against a candidate policy in a notebook gives
+//! exactly the decisions production would.
+//!
+//! Policies are loaded from the JSON form of `OwnedPolicy` and requests can
+//! be built from keyword arguments or from `RequestData` JSON, the shape
+//! request logs are usually kept in.
+
+use std::net::IpAddr;
+
+use gate0::{Effect, OwnedPolicy, OwnedValue, RequestData, SharedPolicy};
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyInt, PyString};
+
+create_exception!(
+    gate0,
+    PolicyError,
+    PyException,
+    "Evaluation failed (budget exceeded, context too large, ...)."
+);
+
+/// A loaded policy.
+#[pyclass(module = "gate0", name = "Policy", frozen)]
+pub struct PyPolicy {
+    shared: SharedPolicy,
+}
+
+impl PyPolicy {
+    fn decide(&self, data: &RequestData) -> PyResult<PyDecision> {
+        let builder = data.builder();
+        let request = builder
+            .build()
+            .map_err(|e| PolicyError::new_err(e.to_string()))?;
+        let (decision, stats) = self
+            .shared
+            .policy()
+            .evaluate_with_stats(&request)
+            .map_err(|e| PolicyError::new_err(e.to_string()))?;
+        Ok(PyDecision {
+            effect: match decision.effect {
+                Effect::Allow => "allow",
+                Effect::Deny => "deny",
+            }
+            .to_string(),
+            reason: decision.reason.value(),
+            rules_checked: stats.rules_checked,
+            condition_evals: stats.condition_evals,
+            matcher_comparisons: stats.matcher_comparisons,
+            context_lookups: stats.context_lookups,
+            steps: stats.steps,
+            ancestors_walked: stats.ancestors_walked,
+            max_depth_reached: stats.max_depth_reached,
+        })
+    }
+}
+
+#[pymethods]
+impl PyPolicy {
+    /// Load a policy from the JSON form of `OwnedPolicy`.
+    ///
+    /// Raises `ValueError` if the JSON is malformed or the policy violates
+    /// its limits.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let owned: OwnedPolicy = serde_json::from_str(json)
+            .map_err(|e| PyValueError::new_err(format!("invalid policy JSON: {}", e)))?;
+        let shared = SharedPolicy::new(owned)
+            .map_err(|e| PyValueError::new_err(format!("invalid policy: {}", e)))?;
+        Ok(PyPolicy { shared })
+    }
+
+    /// The policy as JSON.
+    fn to_json(&self) -> String {
+        serde_json::to_string(self.shared.owned()).expect("owned policies always serialize")
+    }
+
+    /// Evaluate one request.
+    fn evaluate(&self, request: &Bound<'_, PyRequest>) -> PyResult<PyDecision> {
+        self.decide(&request.get().data)
+    }
+
+    /// Evaluate requests in order, e.g. a replayed request log.
+    fn evaluate_many(&self, requests: Vec<Bound<'_, PyRequest>>) -> PyResult<Vec<PyDecision>> {
+        requests
+            .iter()
+            .map(|request| self.decide(&request.get().data))
+            .collect()
+    }
+
+    /// The policy fingerprint; equal fingerprints mean equal decisions.
+    ///
+    /// Policies from this module cannot call custom predicates, whose
+    /// implementations the fingerprint does not cover.
+    #[getter]
+    fn fingerprint(&self) -> u64 {
+        self.shared.policy().fingerprint()
+    }
+
+    /// Number of rules.
+    #[getter]
+    fn rule_count(&self) -> usize {
+        self.shared.policy().rule_count()
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Policy(rules={}, fingerprint={:#018x})",
+            self.shared.policy().rule_count(),
+            self.shared.policy().fingerprint()
+        )
+    }
+}
+
+/// A request: principal, action, resource, and a context dict of `bool`,
+/// `int`, and `str` values.
+#[pyclass(module = "gate0", name = "Request", frozen, eq)]
+#[derive(Clone, PartialEq)]
+pub struct PyRequest {
+    data: RequestData,
+}
+
+#[pymethods]
+impl PyRequest {
+    #[new]
+    #[pyo3(signature = (principal, action, resource, context = None))]
+    fn new(
+        principal: &str,
+        action: &str,
+        resource: &str,
+        context: Option<&Bound<'_, PyDict>>,
+    ) -> PyResult<Self> {
+        let mut data = RequestData::new(principal, action, resource);
+        if let Some(context) = context {
+            for (key, value) in context.iter() {
+                let key: String = key
+                    .extract()
+                    .map_err(|_| PyTypeError::new_err("context keys must be str"))?;
+                let value = to_value(&key, &value)?;
+                data.context.push((key, value));
+            }
+        }
+        Ok(PyRequest { data })
+    }
+
+    /// Parse a request from `RequestData` JSON.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        serde_json::from_str(json)
+            .map(|data| PyRequest { data })
+            .map_err(|e| PyValueError::new_err(format!("invalid request JSON: {}", e)))
+    }
+
+    /// The request as `RequestData` JSON.
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.data).expect("requests always serialize")
+    }
+
+    #[getter]
+    fn principal(&self) -> &str {
+        &self.data.principal
+    }
+
+    #[getter]
+    fn action(&self) -> &str {
+        &self.data.action
+    }
+
+    #[getter]
+    fn resource(&self) -> &str {
+        &self.data.resource
+    }
+
+    /// The context as a new dict.
+    #[getter]
+    fn context<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        for (key, value) in &self.data.context {
+            match value {
+                OwnedValue::Bool(b) => dict.set_item(key, b)?,
+                OwnedValue::Int(i) => dict.set_item(key, i)?,
+                OwnedValue::String(s) => dict.set_item(key, s)?,
+                OwnedValue::Ip(ip) => dict.set_item(key, ip)?,
+                OwnedValue::Timestamp(t) => dict.set_item(key, to_datetime(py, *t)?)?,
+                OwnedValue::Duration(d) => dict.set_item(key, to_timedelta(py, *d)?)?,
+            }
+        }
+        Ok(dict)
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Request(principal={:?}, action={:?}, resource={:?}, context={{{} keys}})",
+            self.data.principal,
+            self.data.action,
+            self.data.resource,
+            self.data.context.len()
+        )
+    }
+}
+
+fn to_value(key: &str, value: &Bound<'_, PyAny>) -> PyResult<OwnedValue> {
+    // `bool` is a subclass of `int`, so it must be checked first
+    if let Ok(b) = value.downcast::<PyBool>() {
+        Ok(OwnedValue::Bool(b.is_true()))
+    } else if value.is_instance_of::<PyInt>() {
+        Ok(OwnedValue::Int(value.extract()?))
+    } else if let Ok(s) = value.downcast::<PyString>() {
+        Ok(OwnedValue::String(s.to_str()?.to_string()))
+    } else if value.is_instance(&py_datetime_class(value.py(), "datetime")?)? {
+        // Naive datetimes would be read in the local time zone
+        if value.getattr("tzinfo")?.is_none() {
+            return Err(PyTypeError::new_err(format!(
+                "context datetime for '{}' must be timezone-aware",
+                key
+            )));
+        }
+        let seconds: f64 = value.call_method0("timestamp")?.extract()?;
+        Ok(OwnedValue::Timestamp(seconds.floor() as i64))
+    } else if value.is_instance(&py_datetime_class(value.py(), "timedelta")?)? {
+        let seconds: f64 = value.call_method0("total_seconds")?.extract()?;
+        if seconds < 0.0 {
+            return Err(PyValueError::new_err(format!(
+                "context duration for '{}' must not be negative",
+                key
+            )));
+        }
+        Ok(OwnedValue::Duration(seconds.floor() as u64))
+    } else if let Ok(ip) = value.extract::<IpAddr>() {
+        // `ipaddress.IPv4Address` and `IPv6Address`
+        Ok(OwnedValue::Ip(ip))
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "context value for '{}' must be bool, int, str, datetime, timedelta, or an ipaddress address",
+            key
+        )))
+    }
+}
+
+fn py_datetime_class<'py>(py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyAny>> {
+    py.import("datetime")?.getattr(name)
+}
+
+/// A timestamp as a timezone-aware UTC `datetime`.
+fn to_datetime(py: Python<'_>, timestamp: i64) -> PyResult<Bound<'_, PyAny>> {
+    let utc = py.import("datetime")?.getattr("timezone")?.getattr("utc")?;
+    py_datetime_class(py, "datetime")?.call_method1("fromtimestamp", (timestamp, utc))
+}
+
+/// A duration as a `timedelta`.
+fn to_timedelta(py: Python<'_>, seconds: u64) -> PyResult<Bound<'_, PyAny>> {
+    py_datetime_class(py, "timedelta")?.call1((0, seconds))
+}
+
+/// The outcome of an evaluation and its stats.
+#[pyclass(module = "gate0", name = "Decision", frozen, eq, get_all)]
+#[derive(Clone, PartialEq)]
+pub struct PyDecision {
+    /// `"allow"` or `"deny"`.
+    effect: String,
+    /// The reason code.
+    reason: u32,
+    rules_checked: u16,
+    condition_evals: u16,
+    matcher_comparisons: u16,
+    context_lookups: u16,
+    steps: u32,
+    ancestors_walked: u16,
+    max_depth_reached: u8,
+}
+
+#[pymethods]
+impl PyDecision {
+    fn is_allow(&self) -> bool {
+        self.effect == "allow"
+    }
+
+    fn is_deny(&self) -> bool {
+        self.effect == "deny"
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Decision(effect={:?}, reason={})", self.effect, self.reason)
+    }
+}
+
+/// The `gate0` Python module.
+#[pymodule]
+#[pyo3(name = "gate0")]
+fn gate0_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyPolicy>()?;
+    m.add_class::<PyRequest>()?;
+    m.add_class::<PyDecision>()?;
+    m.add("PolicyError", m.py().get_type::<PolicyError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    const POLICY: &str = r#"{
+        "rules": [
+            {
+                "effect": "deny",
+                "target": { "principal": { "exact": "mallory" }, "action": "any", "resource": "any" },
+                "reason": 2
+            },
+            {
+                "effect": "allow",
+                "target": { "principal": "any", "action": { "exact": "read" }, "resource": "any" },
+                "condition": { "equals": { "attr": "mfa", "value": true } },
+                "reason": 1
+            }
+        ]
+    }"#;
+
+    fn with_module(script: &str) {
+        Python::with_gil(|py| {
+            let gate0 = PyModule::new(py, "gate0").unwrap();
+            gate0_module(&gate0).unwrap();
+            // Run as a module, so lambdas and comprehensions see the names
+            let globals = PyDict::new(py);
+            globals.set_item("gate0", gate0).unwrap();
+            globals.set_item("policy", POLICY).unwrap();
+            let script = CString::new(script).unwrap();
+            if let Err(error) = py.run(&script, Some(&globals), None) {
+                error.print(py);
+                panic!("script failed");
+            }
+        });
+    }
+
+    #[test]
+    fn test_evaluate() {
+        with_module(
+            r#"
+p = gate0.Policy.from_json(policy)
+assert p.rule_count == 2
+d = p.evaluate(gate0.Request("alice", "read", "doc", {"mfa": True}))
+assert d.is_allow() and d.reason == 1 and d.effect == "allow"
+assert d.rules_checked == 2 and d.context_lookups == 1
+
+# Replaying a log gives the same decisions as one-by-one evaluation
+log = [
+    '{"principal":"alice","action":"read","resource":"doc","context":{"mfa":true}}',
+    '{"principal":"alice","action":"read","resource":"doc"}',
+    '{"principal":"mallory","action":"read","resource":"doc","context":{"mfa":true}}',
+]
+requests = [gate0.Request.from_json(line) for line in log]
+decisions = p.evaluate_many(requests)
+assert [(d.effect, d.reason) for d in decisions] == [("allow", 1), ("deny", 0), ("deny", 2)]
+assert decisions == [p.evaluate(r) for r in requests]
+
+# Round trips
+assert gate0.Policy.from_json(p.to_json()).fingerprint == p.fingerprint
+assert gate0.Request.from_json(requests[0].to_json()) == requests[0]
+assert requests[0].context == {"mfa": True}
+
+import ipaddress
+addr = ipaddress.ip_address("10.0.0.1")
+assert gate0.Request("a", "b", "c", {"ip": addr}).context == {"ip": addr}
+
+from datetime import datetime, timezone
+now = datetime(2024, 1, 6, 9, 0, tzinfo=timezone.utc)
+assert gate0.Request("a", "b", "c", {"now": now}).context == {"now": now}
+from datetime import timedelta
+assert gate0.Request("a", "b", "c", {"ttl": timedelta(hours=1)}).context == {"ttl": timedelta(seconds=3600)}
+"#,
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        with_module(
+            r#"
+def raises(exc, f):
+    try:
+        f()
+    except exc as e:
+        return str(e)
+    raise AssertionError("no exception")
+
+assert raises(ValueError, lambda: gate0.Policy.from_json("{")).startswith("invalid policy JSON")
+assert raises(ValueError, lambda: gate0.Request.from_json("{}")).startswith("invalid request JSON")
+assert "must be bool, int, str, datetime, timedelta, or an ipaddress address" in raises(TypeError, lambda: gate0.Request("a", "b", "c", {"x": 1.5}))
+raises(TypeError, lambda: gate0.Request("a", "b", "c", {1: True}))
+
+from datetime import datetime
+assert "timezone-aware" in raises(TypeError, lambda: gate0.Request("a", "b", "c", {"now": datetime(2024, 1, 6)}))
+raises(OverflowError, lambda: gate0.Request("a", "b", "c", {"x": 2**64}))
+
+p = gate0.Policy.from_json(policy)
+big = gate0.Request("a", "b", "c", {str(i): i for i in range(1000)})
+raises(gate0.PolicyError, lambda: p.evaluate(big))
+"#,
+        );
+    }
+}
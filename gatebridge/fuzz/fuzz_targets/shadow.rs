@@ -0,0 +1,30 @@
+//! Coverage-guided differential fuzzing: the reference evaluator and Gate0
+//! must agree on every generated case.
+//!
+//! Run with `cargo fuzz run shadow` from `gatebridge/`.
+
+#![no_main]
+
+use gatebridge::{shadow_evaluate, FuzzCase};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|case: FuzzCase| {
+    let FuzzCase { policy, request } = case;
+    // Print the case in the files `gatebridge shadow` reads
+    let describe = || {
+        format!(
+            "policy.yaml:\n{}\nrequest.json:\n{}",
+            serde_yaml::to_string(&policy).unwrap(),
+            serde_json::to_string_pretty(&request).unwrap()
+        )
+    };
+    match shadow_evaluate(&policy, &request) {
+        Ok(result) => assert!(
+            result.decisions_match,
+            "evaluators disagree: {:?}\n{}",
+            result,
+            describe()
+        ),
+        Err(e) => panic!("shadow evaluation failed: {}\n{}", e, describe()),
+    }
+});
@@ -3,31 +3,296 @@
 //! These types represent the parsed YAML policy structure.
 //! Kept deliberately simple - this is data, not behavior.
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
 /// Root of a policy file.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PolicyFile {
     pub default: DefaultPolicy,
     #[serde(default)]
     pub policies: Vec<Policy>,
+    #[serde(default, skip_serializing_if = "ExcessDuration::is_clamp")]
+    pub on_excess_duration: ExcessDuration,
+}
+
+/// What to do with a request for longer than the grant's `max_duration`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExcessDuration {
+    /// Grant `max_duration` instead.
+    #[default]
+    Clamp,
+    /// Deny the request.
+    Deny,
+}
+
+impl ExcessDuration {
+    fn is_clamp(&self) -> bool {
+        *self == ExcessDuration::Clamp
+    }
 }
 
 /// Fallback when no policy matches.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct DefaultPolicy {
     pub principals: Vec<String>,
-    pub max_duration: String,
+    pub max_duration: MaxDuration,
 }
 
 /// A single policy entry.
-#[derive(Debug, Clone, Deserialize)]
+///
+/// Allow policies grant `principals` for up to `max_duration`, and must
+/// set both. Deny policies grant nothing and may leave them out. A policy
+/// with `expires` stops matching after that date.
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Policy {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "PolicyEffect::is_allow")]
+    pub effect: PolicyEffect,
+    #[serde(default, rename = "match")]
     pub match_block: MatchBlock,
+    #[serde(default)]
     pub principals: Vec<String>,
-    pub max_duration: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_duration: Option<MaxDuration>,
+
+    // Session permissions, see `Permissions`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub port_forwarding: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub agent_forwarding: bool,
+    #[serde(default = "yes", skip_serializing_if = "is_true")]
+    pub pty: bool,
+
+    /// Last date the policy matches on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<Date>,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn is_true(b: &bool) -> bool {
+    *b
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+/// What an SSH session under a grant may do, as certificate extensions:
+/// forward ports, forward the agent, and allocate a terminal. Policies
+/// grant a terminal and no forwarding unless they say otherwise; the
+/// default block always grants just that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Permissions {
+    pub port_forwarding: bool,
+    pub agent_forwarding: bool,
+    pub pty: bool,
+}
+
+impl Default for Permissions {
+    fn default() -> Self {
+        Permissions {
+            port_forwarding: false,
+            agent_forwarding: false,
+            pty: true,
+        }
+    }
+}
+
+/// Whether a matching policy grants access or blocks it.
+///
+/// A matching deny policy wins over every allow policy, wherever it is in
+/// the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyEffect {
+    #[default]
+    Allow,
+    Deny,
+}
+
+impl PolicyEffect {
+    fn is_allow(&self) -> bool {
+        *self == PolicyEffect::Allow
+    }
+
+    /// `"allow"` or `"deny"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyEffect::Allow => "allow",
+            PolicyEffect::Deny => "deny",
+        }
+    }
+}
+
+/// A certificate validity ceiling such as `"15m"` or `"1h30m"`, checked
+/// and converted to seconds when the policy is loaded.
+///
+/// The format is one or more `<number><unit>` pairs, with units `s`, `m`,
+/// `h`, and `d`. The total must be positive. The original text is kept so
+/// policies serialize back as written.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MaxDuration {
+    text: String,
+    seconds: u64,
+}
+
+impl MaxDuration {
+    /// Parse a duration, or say why it is malformed.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = |why: &str| format!("invalid duration \"{}\": {}", text, why);
+        let mut seconds: u64 = 0;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+            if digits == 0 {
+                return Err(invalid("expected a number"));
+            }
+            let number: u64 = rest[..digits]
+                .parse()
+                .map_err(|_| invalid("number too large"))?;
+            let unit = match rest[digits..].chars().next() {
+                Some('s') => 1,
+                Some('m') => 60,
+                Some('h') => 60 * 60,
+                Some('d') => 24 * 60 * 60,
+                Some(_) => return Err(invalid("unit must be s, m, h, or d")),
+                None => return Err(invalid("missing unit")),
+            };
+            seconds = number
+                .checked_mul(unit)
+                .and_then(|s| seconds.checked_add(s))
+                .ok_or_else(|| invalid("too long"))?;
+            rest = &rest[digits + 1..];
+        }
+        if seconds == 0 {
+            return Err(invalid("must be positive"));
+        }
+        Ok(MaxDuration {
+            text: text.to_string(),
+            seconds,
+        })
+    }
+
+    /// The duration in seconds.
+    pub fn seconds(&self) -> u64 {
+        self.seconds
+    }
+
+    /// The duration as written in the policy.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+}
+
+impl TryFrom<String> for MaxDuration {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        MaxDuration::parse(&text)
+    }
+}
+
+impl From<MaxDuration> for String {
+    fn from(duration: MaxDuration) -> String {
+        duration.text
+    }
+}
+
+impl fmt::Display for MaxDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl PartialEq<&str> for MaxDuration {
+    fn eq(&self, other: &&str) -> bool {
+        self.text == *other
+    }
+}
+
+/// A calendar date written `YYYY-MM-DD`, such as `"2026-12-31"`, checked
+/// when the policy is loaded. The original text is kept so policies
+/// serialize back as written.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Date {
+    // Year, month, day first, so dates order by them
+    ymd: (u16, u8, u8),
+    text: String,
+}
+
+impl Date {
+    /// Parse a date, or say why it is malformed.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = |why: &str| format!("invalid date \"{}\": {}", text, why);
+        let bytes = text.as_bytes();
+        let shape = bytes.len() == 10
+            && bytes[4] == b'-'
+            && bytes[7] == b'-'
+            && bytes
+                .iter()
+                .enumerate()
+                .all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit());
+        if !shape {
+            return Err(invalid("expected YYYY-MM-DD"));
+        }
+        let number =
+            |range: std::ops::Range<usize>| -> u16 { text[range].parse().expect("checked digits") };
+        let (year, month, day) = (number(0..4), number(5..7) as u8, number(8..10) as u8);
+        let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+        let days = match month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 if leap => 29,
+            2 => 28,
+            _ => return Err(invalid("month must be 01 to 12")),
+        };
+        if day == 0 || day > days {
+            return Err(invalid("no such day in that month"));
+        }
+        Ok(Date {
+            ymd: (year, month, day),
+            text: text.to_string(),
+        })
+    }
+
+    /// The date as written.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// The date as the integer `YYYYMMDD`, which orders like the date.
+    pub fn number(&self) -> i64 {
+        let (year, month, day) = self.ymd;
+        i64::from(year) * 10_000 + i64::from(month) * 100 + i64::from(day)
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, String> {
+        Date::parse(&text)
+    }
+}
+
+impl From<Date> for String {
+    fn from(date: Date) -> String {
+        date.text
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
 }
 
 // serde expects "match" but that's a keyword, so we rename it
@@ -35,80 +300,111 @@ impl Policy {
     pub fn match_conditions(&self) -> &MatchBlock {
         &self.match_block
     }
+
+    /// The session permissions the policy grants.
+    pub fn permissions(&self) -> Permissions {
+        Permissions {
+            port_forwarding: self.port_forwarding,
+            agent_forwarding: self.agent_forwarding,
+            pty: self.pty,
+        }
+    }
 }
 
 /// Match conditions for a policy.
-/// First three are OR triggers, last three are AND filters.
-#[derive(Debug, Clone, Default, Deserialize)]
+/// First three are OR triggers, next three are AND filters. `any` and
+/// `all` nest further blocks: at least one `any` block, and every `all`
+/// block, must match too.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
 pub struct MatchBlock {
     // OR triggers - at least one must match
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub oidc_groups: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub emails: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub local_usernames: Vec<String>,
 
     // AND filters - all specified must match
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub source_ip: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub hours: Vec<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub webauthn_ids: Vec<String>,
+
+    // Nesting
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub any: Vec<MatchBlock>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub all: Vec<MatchBlock>,
 }
 
 impl MatchBlock {
     /// True if any OR trigger is specified.
     pub fn has_triggers(&self) -> bool {
-        !self.oidc_groups.is_empty()
-            || !self.emails.is_empty()
-            || !self.local_usernames.is_empty()
+        !self.oidc_groups.is_empty() || !self.emails.is_empty() || !self.local_usernames.is_empty()
     }
 
     /// True if any AND filter is specified.
     pub fn has_filters(&self) -> bool {
-        !self.source_ip.is_empty()
-            || !self.hours.is_empty()
-            || !self.webauthn_ids.is_empty()
+        !self.source_ip.is_empty() || !self.hours.is_empty() || !self.webauthn_ids.is_empty()
+    }
+
+    /// This block and every block nested in it, outermost first.
+    pub fn blocks(&self) -> Vec<&MatchBlock> {
+        let mut blocks = vec![self];
+        for nested in self.any.iter().chain(&self.all) {
+            blocks.extend(nested.blocks());
+        }
+        blocks
     }
 }
 
 /// A request to evaluate against the policy.
-#[derive(Debug, Clone, serde::Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct EvalRequest {
     // Identity
     pub oidc_groups: Vec<String>,
     pub email: Option<String>,
     pub local_username: Option<String>,
+    /// The OIDC subject, for `{oidc_sub}` in principals.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oidc_sub: Option<String>,
 
     // Context
     pub source_ip: Option<String>,
     pub current_time: Option<String>, // HH:MM format
+    /// Today's date, `YYYY-MM-DD`, for policies that expire.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_date: Option<String>,
     pub webauthn_id: Option<String>,
-}
 
-impl Default for EvalRequest {
-    fn default() -> Self {
-        EvalRequest {
-            oidc_groups: vec![],
-            email: None,
-            local_username: None,
-            source_ip: None,
-            current_time: None,
-            webauthn_id: None,
-        }
-    }
+    // Grant
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested_duration: Option<u64>, // seconds
 }
 
 /// Result of policy evaluation.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct EvalResult {
     pub matched: bool,
+    #[serde(default)]
     pub policy_name: Option<String>,
+    #[serde(default)]
     pub policy_index: Option<usize>,
+    /// The effect of the matched policy; the default allows.
+    pub effect: PolicyEffect,
+    #[serde(default)]
     pub principals: Vec<String>,
-    pub max_duration: String,
+    #[serde(default)]
+    pub max_duration: Option<MaxDuration>,
+    /// Seconds granted, or `None` if the request was denied, by a deny
+    /// policy or for asking for longer than `max_duration`.
+    #[serde(default)]
+    pub granted_duration: Option<u64>,
+    #[serde(default)]
+    pub permissions: Permissions,
 }
 
 impl EvalResult {
@@ -117,8 +413,11 @@ impl EvalResult {
             matched: false,
             policy_name: None,
             policy_index: None,
+            effect: PolicyEffect::Allow,
             principals: default.principals.clone(),
-            max_duration: default.max_duration.clone(),
+            max_duration: Some(default.max_duration.clone()),
+            granted_duration: Some(default.max_duration.seconds()),
+            permissions: Permissions::default(),
         }
     }
 
@@ -127,8 +426,38 @@ impl EvalResult {
             matched: true,
             policy_name: Some(policy.name.clone()),
             policy_index: Some(index),
+            effect: policy.effect,
             principals: policy.principals.clone(),
             max_duration: policy.max_duration.clone(),
+            granted_duration: policy.max_duration.as_ref().map(MaxDuration::seconds),
+            permissions: policy.permissions(),
         }
     }
+
+    /// Apply a requested duration to the grant: requests within
+    /// `max_duration` get what they asked for, longer ones are clamped or
+    /// denied as `excess` says, and requests without one get the ceiling.
+    /// Denials stay denied.
+    pub fn with_requested_duration(
+        mut self,
+        requested: Option<u64>,
+        excess: ExcessDuration,
+    ) -> Self {
+        let ceiling = match (&self.effect, &self.max_duration) {
+            (PolicyEffect::Allow, Some(max)) => max.seconds(),
+            _ => {
+                self.granted_duration = None;
+                return self;
+            }
+        };
+        self.granted_duration = match requested {
+            None => Some(ceiling),
+            Some(r) if r <= ceiling => Some(r),
+            Some(_) => match excess {
+                ExcessDuration::Clamp => Some(ceiling),
+                ExcessDuration::Deny => None,
+            },
+        };
+        self
+    }
 }
@@ -0,0 +1,281 @@
+//! Conformance corpus runner.
+//!
+//! A corpus is a directory of fixture files, each a request and the
+//! decision it must get. Fixtures are the contract between the YAML
+//! semantics and Gate0: every fixture is decided by both evaluators, which
+//! must agree with each other and with the fixture.
+//!
+//! A fixture is a `.yaml`, `.yml`, or `.json` file:
+//!
+//! ```yaml
+//! description: A matching deny policy wins over an earlier allow
+//! policy: policies/teams.yaml   # or an inline policy file
+//! request:
+//!   oidc_groups: ["admins", "terminated-employees"]
+//! expect:
+//!   effect: deny
+//!   policy: Terminated          # "default" for the default block
+//!   principals: []
+//! ```
+//!
+//! `policy` is a path relative to the fixture, or a policy file written
+//! inline. Without it, the fixture is decided against the policy passed to
+//! `run_corpus`. Only the expectations given are checked, except `effect`,
+//! which every fixture must state. Subdirectories are not searched, so
+//! shared policy files can live in one.
+
+use crate::ast::{EvalRequest, MaxDuration, PolicyEffect, PolicyFile};
+use crate::decide::{decide, EvalDecision};
+use crate::loader::{load_policy, parse_policy};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// One fixture file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Fixture {
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    policy: Option<serde_yaml::Value>,
+    request: EvalRequest,
+    expect: Expected,
+}
+
+/// The decision a fixture expects.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Expected {
+    effect: PolicyEffect,
+    #[serde(default)]
+    policy: Option<String>,
+    #[serde(default)]
+    principals: Option<Vec<String>>,
+    #[serde(default)]
+    granted_duration: Option<MaxDuration>,
+}
+
+/// Outcome of a corpus run.
+#[derive(Debug, Default)]
+pub struct CorpusReport {
+    /// Every fixture, in file name order.
+    pub cases: Vec<CaseResult>,
+}
+
+impl CorpusReport {
+    /// True if every fixture passed.
+    pub fn passed(&self) -> bool {
+        self.cases.iter().all(CaseResult::passed)
+    }
+
+    /// Fixtures that failed.
+    pub fn failed(&self) -> impl Iterator<Item = &CaseResult> {
+        self.cases.iter().filter(|c| !c.passed())
+    }
+}
+
+/// Outcome of one fixture.
+#[derive(Debug)]
+pub struct CaseResult {
+    pub path: PathBuf,
+    pub description: Option<String>,
+    /// Why the fixture failed: each unmet expectation, a disagreement
+    /// between the evaluators, or an error loading or deciding it. Empty
+    /// if it passed.
+    pub failures: Vec<String>,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Run every fixture in `dir`, deciding those without a policy of their
+/// own against `policy`.
+///
+/// Fails only if `dir` cannot be read; a fixture that cannot be loaded or
+/// decided is reported as a failed case.
+pub fn run_corpus(dir: &Path, policy: Option<&PolicyFile>) -> io::Result<CorpusReport> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let extension = path.extension().and_then(|e| e.to_str());
+        if matches!(extension, Some("yaml" | "yml" | "json")) && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut report = CorpusReport::default();
+    for path in paths {
+        let (description, failures) = match run_fixture(&path, policy) {
+            Ok(result) => result,
+            Err(e) => (None, vec![e]),
+        };
+        report.cases.push(CaseResult {
+            path,
+            description,
+            failures,
+        });
+    }
+    Ok(report)
+}
+
+/// Load and decide one fixture, returning its description and failures.
+fn run_fixture(
+    path: &Path,
+    shared: Option<&PolicyFile>,
+) -> Result<(Option<String>, Vec<String>), String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read fixture: {}", e))?;
+    let fixture: Fixture =
+        serde_yaml::from_str(&text).map_err(|e| format!("invalid fixture: {}", e))?;
+
+    let own;
+    let policy = match &fixture.policy {
+        None => shared.ok_or("no policy: the fixture has none and none was given")?,
+        Some(serde_yaml::Value::String(file)) => {
+            let file = path.parent().unwrap_or(Path::new(".")).join(file);
+            own = load_policy(&file).map_err(|e| format!("invalid policy: {}", e))?;
+            &own
+        }
+        Some(inline) => {
+            let yaml = serde_yaml::to_string(inline).map_err(|e| e.to_string())?;
+            own = parse_policy(&yaml).map_err(|e| format!("invalid policy: {}", e))?;
+            &own
+        }
+    };
+
+    let decision = decide(policy, &fixture.request).map_err(|e| e.to_string())?;
+    Ok((fixture.description, check(&fixture.expect, &decision)))
+}
+
+/// Each way `decision` falls short of `expected`.
+fn check(expected: &Expected, decision: &EvalDecision) -> Vec<String> {
+    let mut failures = Vec::new();
+    if !decision.decisions_match {
+        failures.push(format!(
+            "evaluators disagree: Gate0 decided {} with reason code {}",
+            decision.effect, decision.reason_code
+        ));
+    }
+    if expected.effect.as_str() != decision.effect {
+        failures.push(format!(
+            "effect: expected {}, got {}",
+            expected.effect.as_str(),
+            decision.effect
+        ));
+    }
+    let policy = decision.policy_name.as_deref().unwrap_or("default");
+    if let Some(name) = expected.policy.as_deref().filter(|&name| name != policy) {
+        failures.push(format!("policy: expected {:?}, got {:?}", name, policy));
+    }
+    if let Some(principals) = expected
+        .principals
+        .as_ref()
+        .filter(|&p| *p != decision.principals)
+    {
+        failures.push(format!(
+            "principals: expected {:?}, got {:?}",
+            principals, decision.principals
+        ));
+    }
+    if let Some(duration) = &expected.granted_duration {
+        if decision.granted_duration != Some(duration.seconds()) {
+            let granted = match decision.granted_duration {
+                Some(seconds) => format!("{}s", seconds),
+                None => "none".to_string(),
+            };
+            failures.push(format!(
+                "granted_duration: expected {} ({}s), got {}",
+                duration,
+                duration.seconds(),
+                granted
+            ));
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh scratch directory holding `files`.
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gatebridge-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_golden_corpus() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus");
+        let report = run_corpus(&dir, None).unwrap();
+        assert!(!report.cases.is_empty());
+        let failed: Vec<_> = report
+            .failed()
+            .map(|c| format!("{}: {:?}", c.path.display(), c.failures))
+            .collect();
+        assert!(failed.is_empty(), "{:#?}", failed);
+    }
+
+    #[test]
+    fn test_reports_unmet_expectations() {
+        let admin = r#"
+request:
+  oidc_groups: ["infrastructure"]
+  source_ip: "10.1.2.3"
+  current_time: "14:30"
+expect:
+  effect: allow
+  policy: "DeveloperAccess"
+  principals: ["root"]
+  granted_duration: "1h"
+"#;
+        let dir = scratch(
+            "corpus-unmet",
+            &[
+                ("admin.yaml", admin),
+                ("broken.json", r#"{"request": {}}"#),
+                ("notes.txt", "not a fixture"),
+            ],
+        );
+
+        let policy = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let report = run_corpus(&dir, Some(&policy)).unwrap();
+        assert!(!report.passed());
+        assert_eq!(report.cases.len(), 2);
+        assert_eq!(
+            report.cases[0].failures,
+            [
+                "policy: expected \"DeveloperAccess\", got \"AdminAccess\"",
+                "principals: expected [\"root\"], got [\"root\", \"admin\"]",
+            ]
+        );
+        assert!(report.cases[1].failures[0].starts_with("invalid fixture: "));
+    }
+
+    #[test]
+    fn test_fixture_needs_a_policy() {
+        let dir = scratch(
+            "corpus-no-policy",
+            &[(
+                "case.yaml",
+                "request: {oidc_groups: []}\nexpect: {effect: allow}\n",
+            )],
+        );
+        let report = run_corpus(&dir, None).unwrap();
+        assert_eq!(
+            report.cases[0].failures,
+            ["no policy: the fixture has none and none was given"]
+        );
+    }
+}
@@ -0,0 +1,313 @@
+//! Decisions for callers: Gate0's verdict plus the grant it stands for.
+//!
+//! Gate0 decides by reason code, which names the policy whose principals
+//! are granted, templates resolved from the request; the reference
+//! evaluator supplies the duration. `decisions_match`
+//! reports whether the two agreed, as in shadow evaluation. A request for
+//! longer than the grant allows is denied when the policy file says
+//! `on_excess_duration: deny`, whatever Gate0 decided. Denials grant no
+//! principals and no session permissions. `decide_many` decides a batch
+//! of requests, translating the policy once; `evaluate_many` is the
+//! reference evaluator alone over a batch, giving `EvalResult`s without
+//! Gate0's verdict or the agreement check.
+
+use crate::ast::{EvalRequest, MaxDuration, Permissions, PolicyFile};
+use crate::reference_eval::RustReference;
+use crate::shadow::{compare, ShadowError, ShadowResult};
+use crate::translate::{gate0_permissions, gate0_principals};
+use crate::{reference_evaluate, shadow_evaluate, to_gate0};
+use gate0::Policy;
+use serde::Serialize;
+
+/// The decision for one request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EvalDecision {
+    pub effect: String,
+    pub reason_code: u32,
+    pub policy_name: Option<String>,
+    pub principals: Vec<String>,
+    pub max_duration: Option<MaxDuration>,
+    /// Seconds granted; `None` when denied.
+    pub granted_duration: Option<u64>,
+    /// Session permissions granted; `None` when denied.
+    pub permissions: Option<Permissions>,
+    #[serde(rename = "match")]
+    pub decisions_match: bool,
+}
+
+/// Decide `request` against `policy_file`.
+pub fn decide(
+    policy_file: &PolicyFile,
+    request: &EvalRequest,
+) -> Result<EvalDecision, ShadowError> {
+    let shadow = shadow_evaluate(policy_file, request)?;
+    Ok(decision(policy_file, request, shadow))
+}
+
+/// Decide each of `requests` against `policy_file`, in order.
+///
+/// Fails only if the policy does not translate; a request Gate0 cannot
+/// evaluate gets an error of its own.
+pub fn decide_many(
+    policy_file: &PolicyFile,
+    requests: &[EvalRequest],
+) -> Result<Vec<Result<EvalDecision, ShadowError>>, ShadowError> {
+    let (owned_policy, _) =
+        to_gate0(policy_file).map_err(|e| ShadowError::Translation(e.to_string()))?;
+    let gate0_policy = owned_policy
+        .policy()
+        .map_err(|e| ShadowError::Translation(format!("{:?}", e)))?;
+    Ok(requests
+        .iter()
+        .map(|request| decide_translated(policy_file, &gate0_policy, request))
+        .collect())
+}
+
+/// Decide `request` against `policy_file`, given its Gate0 translation.
+pub(crate) fn decide_translated(
+    policy_file: &PolicyFile,
+    gate0_policy: &Policy<'_>,
+    request: &EvalRequest,
+) -> Result<EvalDecision, ShadowError> {
+    let shadow = compare(&RustReference, policy_file, gate0_policy, request)?;
+    Ok(decision(policy_file, request, shadow))
+}
+
+/// The decision for `request` given its shadow evaluation.
+fn decision(policy_file: &PolicyFile, request: &EvalRequest, shadow: ShadowResult) -> EvalDecision {
+    let grant = reference_evaluate(policy_file, request);
+
+    // Denied by Gate0 (a deny policy) or by the duration ceiling
+    let denied = shadow.gate0_decision.effect == "deny" || grant.granted_duration.is_none();
+    let reason_code = shadow.gate0_decision.reason_code;
+    let (effect, principals, permissions) = if denied {
+        ("deny", Vec::new(), None)
+    } else {
+        (
+            "allow",
+            gate0_principals(policy_file, reason_code, request),
+            gate0_permissions(policy_file, reason_code),
+        )
+    };
+
+    EvalDecision {
+        effect: effect.to_string(),
+        reason_code,
+        policy_name: grant.policy_name,
+        principals,
+        max_duration: grant.max_duration,
+        granted_duration: grant.granted_duration,
+        permissions,
+        decisions_match: shadow.decisions_match,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::parse_policy;
+
+    #[test]
+    fn test_decide_default() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "AdminAccess"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let decision = decide(&policy, &EvalRequest::default()).unwrap();
+
+        assert_eq!(decision.policy_name, None);
+        assert_eq!(decision.principals, vec!["sandbox"]);
+        assert_eq!(decision.max_duration.unwrap(), "15m");
+        assert_eq!(decision.reason_code, u32::MAX - 1);
+    }
+
+    #[test]
+    fn test_decide_requested_duration() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+"#;
+        let clamp = parse_policy(yaml).unwrap();
+        let deny = parse_policy(&format!("{}on_excess_duration: deny\n", yaml)).unwrap();
+        let request = |seconds| EvalRequest {
+            requested_duration: Some(seconds),
+            ..Default::default()
+        };
+
+        let decision = decide(&clamp, &request(600)).unwrap();
+        assert_eq!(decision.granted_duration, Some(600));
+        let decision = decide(&clamp, &request(3600)).unwrap();
+        assert_eq!(
+            (decision.effect.as_str(), decision.granted_duration),
+            ("allow", Some(900))
+        );
+        let decision = decide(&deny, &request(3600)).unwrap();
+        assert_eq!(
+            (decision.effect.as_str(), decision.granted_duration),
+            ("deny", None)
+        );
+        assert!(decision.decisions_match);
+        let decision = decide(&deny, &EvalRequest::default()).unwrap();
+        assert_eq!(decision.granted_duration, Some(900));
+    }
+
+    #[test]
+    fn test_decide_deny_policy() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Contractors"
+    effect: deny
+    match:
+      emails: ["*@contractor.example.com"]
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = EvalRequest {
+            email: Some("eve@contractor.example.com".to_string()),
+            ..Default::default()
+        };
+        let decision = decide(&policy, &request).unwrap();
+
+        assert_eq!(decision.effect, "deny");
+        assert_eq!(decision.policy_name.as_deref(), Some("Contractors"));
+        assert!(decision.principals.is_empty());
+        assert_eq!(decision.granted_duration, None);
+        assert!(decision.decisions_match);
+    }
+
+    #[test]
+    fn test_decide_principal_templates() {
+        let yaml = r#"
+default:
+  principals: ["guest-{email_local}"]
+  max_duration: "15m"
+policies:
+  - name: "Developers"
+    match:
+      oidc_groups: ["developers"]
+    principals: ["{local_username}", "dev-{oidc_sub}"]
+    max_duration: "1h"
+  - name: "Contractors"
+    effect: deny
+    match:
+      emails: ["*@contractor.example.com"]
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = EvalRequest {
+            oidc_groups: vec!["developers".to_string()],
+            local_username: Some("bob".to_string()),
+            oidc_sub: Some("42".to_string()),
+            ..Default::default()
+        };
+        let decision = decide(&policy, &request).unwrap();
+        assert_eq!(decision.principals, ["bob", "dev-42"]);
+        assert_eq!(
+            reference_evaluate(&policy, &request).principals,
+            ["bob", "dev-42"]
+        );
+
+        let request = EvalRequest {
+            email: Some("carol@example.com".to_string()),
+            ..Default::default()
+        };
+        let decision = decide(&policy, &request).unwrap();
+        assert_eq!(decision.principals, ["guest-carol"]);
+        assert!(decide(&policy, &EvalRequest::default())
+            .unwrap()
+            .principals
+            .is_empty());
+
+        let request = EvalRequest {
+            email: Some("dave@contractor.example.com".to_string()),
+            ..request
+        };
+        assert!(decide(&policy, &request).unwrap().principals.is_empty());
+    }
+
+    #[test]
+    fn test_decide_session_fields() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Contractors"
+    match:
+      oidc_groups: ["contractors"]
+    principals: ["deploy"]
+    max_duration: "1h"
+    agent_forwarding: true
+    pty: false
+    expires: "2026-06-30"
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = |date: Option<&str>| EvalRequest {
+            oidc_groups: vec!["contractors".to_string()],
+            current_date: date.map(str::to_string),
+            ..Default::default()
+        };
+
+        let decision = decide(&policy, &request(Some("2026-06-30"))).unwrap();
+        assert!(decision.decisions_match);
+        assert_eq!(decision.policy_name.as_deref(), Some("Contractors"));
+        assert_eq!(
+            decision.permissions,
+            Some(Permissions {
+                port_forwarding: false,
+                agent_forwarding: true,
+                pty: false,
+            })
+        );
+
+        // Expired, or no date to tell: the default applies
+        for date in [Some("2026-07-01"), Some("30.06.2026"), None] {
+            let decision = decide(&policy, &request(date)).unwrap();
+            assert!(decision.decisions_match, "{:?}", date);
+            assert_eq!(decision.policy_name, None, "{:?}", date);
+            assert_eq!(decision.permissions, Some(Permissions::default()));
+        }
+    }
+
+    #[test]
+    fn test_decide_many() {
+        let policy = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let requests: Vec<EvalRequest> = [
+            vec!["admins"],
+            vec!["developers"],
+            vec!["contractors", "admins"],
+            vec![],
+        ]
+        .into_iter()
+        .map(|groups| EvalRequest {
+            oidc_groups: groups.into_iter().map(str::to_string).collect(),
+            source_ip: Some("10.1.2.3".to_string()),
+            current_time: Some("14:30".to_string()),
+            ..Default::default()
+        })
+        .collect();
+
+        let decisions = decide_many(&policy, &requests).unwrap();
+        assert_eq!(decisions.len(), requests.len());
+        for (request, decision) in requests.iter().zip(decisions) {
+            assert_eq!(decision.unwrap(), decide(&policy, request).unwrap());
+        }
+        let results = crate::evaluate_many(&policy, &requests);
+        let expected: Vec<_> = requests
+            .iter()
+            .map(|r| reference_evaluate(&policy, r))
+            .collect();
+        assert_eq!(results, expected);
+        assert!(decide_many(&policy, &[]).unwrap().is_empty());
+    }
+}
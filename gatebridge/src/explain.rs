@@ -1,31 +1,86 @@
 //! Step-by-step policy evaluation for debugging.
+//!
+//! `format_explain` renders a trace as text for terminals, as Markdown for
+//! tickets, as one line for chat, or as JSON for dashboards. The JSON keeps
+//! its shape across releases; its `version` changes if it ever must not.
 
-use crate::ast::{EvalRequest, MatchBlock, Policy, PolicyFile};
-use crate::reference_eval::{check_cidr, check_exact, check_fnmatch, check_oidc_groups, check_time_range};
+use crate::ast::{Date, EvalRequest, MatchBlock, Permissions, Policy, PolicyEffect, PolicyFile};
+use crate::reference_eval::{
+    check_cidr, check_exact, check_expires, check_fnmatch, check_oidc_groups, check_time_range,
+    matches_block,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Version of the JSON explain format.
+pub const EXPLAIN_JSON_VERSION: u32 = 1;
+
+/// How `format_explain` renders a trace.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExplainFormat {
+    /// Multi-line text with a section per policy.
+    #[default]
+    Text,
+    /// Stable JSON, as `explain_json` returns it.
+    Json,
+    /// A Markdown table with a row per policy.
+    Markdown,
+    /// One line naming the decision and why each policy failed.
+    Compact,
+}
+
+impl FromStr for ExplainFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(ExplainFormat::Text),
+            "json" => Ok(ExplainFormat::Json),
+            "markdown" | "md" => Ok(ExplainFormat::Markdown),
+            "compact" => Ok(ExplainFormat::Compact),
+            _ => Err(format!(
+                "unknown explain format {:?} (expected text, json, markdown, or compact)",
+                s
+            )),
+        }
+    }
+}
 
 /// Result of explaining a single condition check.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ConditionExplain {
     pub field: String,
     pub pattern: String,
     pub request_value: String,
     pub matched: bool,
+    /// Why the check failed, with the observed and expected values, such
+    /// as `source_ip 10.1.2.3 not in 192.168.0.0/16`. `None` if it matched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<String>,
 }
 
 /// Result of explaining a single policy.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct PolicyExplain {
     pub name: String,
     pub index: usize,
+    pub effect: PolicyEffect,
+    /// The session permissions the policy grants if it decides.
+    pub permissions: Permissions,
     pub triggers: Vec<ConditionExplain>,
     pub filters: Vec<ConditionExplain>,
     pub trigger_passed: bool,
     pub filter_passed: bool,
     pub overall_matched: bool,
+    /// Why the policy did not match: each trigger if none matched, then
+    /// each failed filter. Empty if it matched.
+    pub failures: Vec<String>,
 }
 
 /// Result of explaining the full evaluation.
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ExplainResult {
     pub policies: Vec<PolicyExplain>,
     pub matched_policy: Option<String>,
@@ -36,16 +91,24 @@ pub struct ExplainResult {
 pub fn explain(policy_file: &PolicyFile, request: &EvalRequest) -> ExplainResult {
     let mut policies = Vec::new();
     let mut matched_policy = None;
-    let mut matched_index = None;
+    let mut matched_index: Option<usize> = None;
 
     for (index, policy) in policy_file.policies.iter().enumerate() {
         let policy_explain = explain_policy(index, policy, request);
-        
-        if policy_explain.overall_matched && matched_policy.is_none() {
+
+        // A matching deny policy replaces an earlier allow match
+        let decisive = match matched_index {
+            None => true,
+            Some(i) => {
+                policy.effect == PolicyEffect::Deny
+                    && policy_file.policies[i].effect == PolicyEffect::Allow
+            }
+        };
+        if policy_explain.overall_matched && decisive {
             matched_policy = Some(policy.name.clone());
             matched_index = Some(index);
         }
-        
+
         policies.push(policy_explain);
     }
 
@@ -57,101 +120,426 @@ pub fn explain(policy_file: &PolicyFile, request: &EvalRequest) -> ExplainResult
 }
 
 fn explain_policy(index: usize, policy: &Policy, request: &EvalRequest) -> PolicyExplain {
-    let m = &policy.match_block;
-    
+    let (triggers, mut filters) = explain_block(&policy.match_block, request);
+
+    // Expiry is a filter on the whole policy
+    if let Some(expires) = &policy.expires {
+        let current = request.current_date.as_deref();
+        let matched = check_expires(Some(expires), current);
+        filters.push(ConditionExplain {
+            field: "expires".to_string(),
+            pattern: expires.to_string(),
+            request_value: or_none(&request.current_date),
+            matched,
+            failure: (!matched).then(|| match current {
+                None => "request has no current_date".to_string(),
+                Some(date) if Date::parse(date).is_err() => {
+                    format!("current_date {:?} is not a date", date)
+                }
+                Some(date) => format!("current_date {} is after expires {}", date, expires),
+            }),
+        });
+    }
+
+    // Compute pass/fail
+    let trigger_passed = if triggers.is_empty() {
+        true // No triggers = open policy
+    } else {
+        triggers.iter().any(|t| t.matched)
+    };
+
+    let filter_passed = filters.iter().all(|f| f.matched);
+
+    let overall_matched = trigger_passed && filter_passed;
+
+    PolicyExplain {
+        name: policy.name.clone(),
+        index,
+        effect: policy.effect,
+        permissions: policy.permissions(),
+        failures: failures(&triggers, &filters),
+        triggers,
+        filters,
+        trigger_passed,
+        filter_passed,
+        overall_matched,
+    }
+}
+
+/// The trigger and filter checks of one match block.
+fn explain_block(
+    m: &MatchBlock,
+    request: &EvalRequest,
+) -> (Vec<ConditionExplain>, Vec<ConditionExplain>) {
     let mut triggers = Vec::new();
     let mut filters = Vec::new();
 
     // Explain OR triggers
     if !m.oidc_groups.is_empty() {
         let matched = check_oidc_groups(&m.oidc_groups, &request.oidc_groups);
-        triggers.push(ConditionExplain {
-            field: "oidc_groups".to_string(),
-            pattern: format!("{:?}", m.oidc_groups),
-            request_value: format!("{:?}", request.oidc_groups),
+        triggers.push(check(
+            "oidc_groups",
+            &m.oidc_groups,
+            format!("{:?}", request.oidc_groups),
             matched,
-        });
+            || match request.oidc_groups.as_slice() {
+                [] => "request has no oidc_groups".to_string(),
+                groups => format!(
+                    "oidc_groups {} not in {}",
+                    groups.join(", "),
+                    alternatives(&m.oidc_groups)
+                ),
+            },
+        ));
     }
 
     if !m.emails.is_empty() {
         let matched = check_fnmatch(&m.emails, request.email.as_deref());
-        triggers.push(ConditionExplain {
-            field: "emails".to_string(),
-            pattern: format!("{:?}", m.emails),
-            request_value: request.email.clone().unwrap_or_else(|| "(none)".to_string()),
+        triggers.push(check(
+            "emails",
+            &m.emails,
+            or_none(&request.email),
             matched,
-        });
+            || match &request.email {
+                None => "request has no email".to_string(),
+                Some(email) => {
+                    format!("email {} does not match {}", email, alternatives(&m.emails))
+                }
+            },
+        ));
     }
 
     if !m.local_usernames.is_empty() {
         let matched = check_fnmatch(&m.local_usernames, request.local_username.as_deref());
-        triggers.push(ConditionExplain {
-            field: "local_usernames".to_string(),
-            pattern: format!("{:?}", m.local_usernames),
-            request_value: request.local_username.clone().unwrap_or_else(|| "(none)".to_string()),
+        triggers.push(check(
+            "local_usernames",
+            &m.local_usernames,
+            or_none(&request.local_username),
             matched,
-        });
+            || match &request.local_username {
+                None => "request has no local_username".to_string(),
+                Some(name) => format!(
+                    "local_username {} does not match {}",
+                    name,
+                    alternatives(&m.local_usernames)
+                ),
+            },
+        ));
     }
 
     // Explain AND filters
     if !m.source_ip.is_empty() {
         let matched = check_cidr(&m.source_ip, request.source_ip.as_deref());
-        filters.push(ConditionExplain {
-            field: "source_ip".to_string(),
-            pattern: format!("{:?}", m.source_ip),
-            request_value: request.source_ip.clone().unwrap_or_else(|| "(none)".to_string()),
+        filters.push(check(
+            "source_ip",
+            &m.source_ip,
+            or_none(&request.source_ip),
             matched,
-        });
+            || match &request.source_ip {
+                None => "request has no source_ip".to_string(),
+                Some(ip) if ip.parse::<IpAddr>().is_err() => {
+                    format!("source_ip {:?} is not an IP address", ip)
+                }
+                Some(ip) => format!("source_ip {} not in {}", ip, alternatives(&m.source_ip)),
+            },
+        ));
     }
 
     if !m.hours.is_empty() {
         let matched = check_time_range(&m.hours, request.current_time.as_deref());
-        filters.push(ConditionExplain {
-            field: "hours".to_string(),
-            pattern: format!("{:?}", m.hours),
-            request_value: request.current_time.clone().unwrap_or_else(|| "(none)".to_string()),
+        filters.push(check(
+            "hours",
+            &m.hours,
+            or_none(&request.current_time),
             matched,
-        });
+            || match &request.current_time {
+                None => "request has no current_time".to_string(),
+                Some(time) if gate0::parse_time_of_day(time).is_err() => {
+                    format!("current_time {:?} is not a time of day", time)
+                }
+                Some(time) => format!("current_time {} not in {}", time, alternatives(&m.hours)),
+            },
+        ));
     }
 
     if !m.webauthn_ids.is_empty() {
         let matched = check_exact(&m.webauthn_ids, request.webauthn_id.as_deref());
+        filters.push(check(
+            "webauthn_ids",
+            &m.webauthn_ids,
+            or_none(&request.webauthn_id),
+            matched,
+            || match &request.webauthn_id {
+                None => "request has no webauthn_id".to_string(),
+                Some(id) => format!(
+                    "webauthn_id {} does not equal {}",
+                    id,
+                    alternatives(&m.webauthn_ids)
+                ),
+            },
+        ));
+    }
+
+    // Nested blocks count as filters, each group reported as a whole, with
+    // the failures of the blocks that failed it
+    if !m.any.is_empty() {
+        let matched = m.any.iter().any(|b| matches_block(b, request));
         filters.push(ConditionExplain {
-            field: "webauthn_ids".to_string(),
-            pattern: format!("{:?}", m.webauthn_ids),
-            request_value: request.webauthn_id.clone().unwrap_or_else(|| "(none)".to_string()),
+            field: "any".to_string(),
+            pattern: format!("{} nested blocks", m.any.len()),
+            request_value: format!("{} matched", count_matching(&m.any, request)),
             matched,
+            failure: (!matched).then(|| {
+                let failures = nested_failures("any", &m.any, request);
+                format!("no any block matched: {}", failures.join("; "))
+            }),
         });
     }
 
-    // Compute pass/fail
-    let trigger_passed = if triggers.is_empty() {
-        true // No triggers = open policy
-    } else {
-        triggers.iter().any(|t| t.matched)
-    };
+    if !m.all.is_empty() {
+        let matched = m.all.iter().all(|b| matches_block(b, request));
+        filters.push(ConditionExplain {
+            field: "all".to_string(),
+            pattern: format!("{} nested blocks", m.all.len()),
+            request_value: format!("{} matched", count_matching(&m.all, request)),
+            matched,
+            failure: (!matched).then(|| nested_failures("all", &m.all, request).join("; ")),
+        });
+    }
 
-    let filter_passed = filters.iter().all(|f| f.matched);
+    (triggers, filters)
+}
 
-    let overall_matched = trigger_passed && filter_passed;
+fn check(
+    field: &str,
+    patterns: &[String],
+    request_value: String,
+    matched: bool,
+    failure: impl FnOnce() -> String,
+) -> ConditionExplain {
+    ConditionExplain {
+        field: field.to_string(),
+        pattern: format!("{:?}", patterns),
+        request_value,
+        matched,
+        failure: (!matched).then(failure),
+    }
+}
 
-    PolicyExplain {
-        name: policy.name.clone(),
-        index,
-        triggers,
-        filters,
-        trigger_passed,
-        filter_passed,
-        overall_matched,
+fn or_none(value: &Option<String>) -> String {
+    value.clone().unwrap_or_else(|| "(none)".to_string())
+}
+
+/// `a` for one pattern, `any of a, b` for more.
+fn alternatives(patterns: &[String]) -> String {
+    match patterns {
+        [one] => one.clone(),
+        _ => format!("any of {}", patterns.join(", ")),
+    }
+}
+
+/// Why a block did not match: every trigger if none matched, then each
+/// failed filter. Empty if it matched.
+fn failures(triggers: &[ConditionExplain], filters: &[ConditionExplain]) -> Vec<String> {
+    let trigger_passed = triggers.is_empty() || triggers.iter().any(|t| t.matched);
+    let triggers = triggers.iter().filter(|_| !trigger_passed);
+    triggers
+        .chain(filters)
+        .filter_map(|c| c.failure.clone())
+        .collect()
+}
+
+/// The failures of each failed block in `blocks`, prefixed with its path
+/// (`all[1]: ...`).
+fn nested_failures(group: &str, blocks: &[MatchBlock], request: &EvalRequest) -> Vec<String> {
+    let mut out = Vec::new();
+    for (i, block) in blocks.iter().enumerate() {
+        let (triggers, filters) = explain_block(block, request);
+        for failure in failures(&triggers, &filters) {
+            out.push(format!("{}[{}]: {}", group, i, failure));
+        }
     }
+    out
+}
+
+fn count_matching(blocks: &[MatchBlock], request: &EvalRequest) -> usize {
+    blocks.iter().filter(|b| matches_block(b, request)).count()
 }
 
 /// Format explain result for display.
-pub fn format_explain(result: &ExplainResult) -> String {
+pub fn format_explain(result: &ExplainResult, format: ExplainFormat) -> String {
+    match format {
+        ExplainFormat::Text => format_text(result),
+        ExplainFormat::Json => {
+            serde_json::to_string_pretty(&explain_json(result)).expect("JSON values serialize")
+        }
+        ExplainFormat::Markdown => format_markdown(result),
+        ExplainFormat::Compact => format_compact(result),
+    }
+}
+
+/// The trace in the stable JSON format:
+///
+/// ```json
+/// {
+///   "version": 1,
+///   "matched": { "index": 0, "name": "AdminAccess", "effect": "allow" },
+///   "policies": [{
+///     "index": 0, "name": "AdminAccess", "effect": "allow", "matched": true,
+///     "permissions": { "port_forwarding": false, "agent_forwarding": false, "pty": true },
+///     "triggers": { "passed": true, "checks": [
+///       { "field": "oidc_groups", "pattern": "[\"admins\"]", "value": "[\"admins\"]",
+///         "matched": true, "failure": null }
+///     ] },
+///     "filters": { "passed": true, "checks": [] },
+///     "failures": []
+///   }]
+/// }
+/// ```
+///
+/// `matched` is `null` when the default policy applies. Fields may be added
+/// within a version, but not renamed or removed.
+pub fn explain_json(result: &ExplainResult) -> Value {
+    let checks = |checks: &[ConditionExplain], passed: bool| {
+        let checks: Vec<Value> = checks
+            .iter()
+            .map(|c| {
+                json!({
+                    "field": c.field,
+                    "pattern": c.pattern,
+                    "value": c.request_value,
+                    "matched": c.matched,
+                    "failure": c.failure,
+                })
+            })
+            .collect();
+        json!({ "passed": passed, "checks": checks })
+    };
+    let policies: Vec<Value> = result
+        .policies
+        .iter()
+        .map(|p| {
+            json!({
+                "index": p.index,
+                "name": p.name,
+                "effect": p.effect.as_str(),
+                "matched": p.overall_matched,
+                "permissions": p.permissions,
+                "triggers": checks(&p.triggers, p.trigger_passed),
+                "filters": checks(&p.filters, p.filter_passed),
+                "failures": p.failures,
+            })
+        })
+        .collect();
+    let matched = result.matched_index.map(|i| {
+        let p = &result.policies[i];
+        json!({ "index": i, "name": p.name, "effect": p.effect.as_str() })
+    });
+    json!({
+        "version": EXPLAIN_JSON_VERSION,
+        "matched": matched,
+        "policies": policies,
+    })
+}
+
+/// The session permissions granted, as `pty, agent_forwarding`, or `none`.
+fn permissions(p: &Permissions) -> String {
+    let granted: Vec<&str> = [
+        ("pty", p.pty),
+        ("port_forwarding", p.port_forwarding),
+        ("agent_forwarding", p.agent_forwarding),
+    ]
+    .iter()
+    .filter(|(_, granted)| *granted)
+    .map(|(name, _)| *name)
+    .collect();
+    match granted.as_slice() {
+        [] => "none".to_string(),
+        names => names.join(", "),
+    }
+}
+
+/// The decision: `AdminAccess (ReasonCode: 0)` or `default policy`.
+fn decision(result: &ExplainResult) -> String {
+    match result.matched_index {
+        Some(i) => {
+            let policy = &result.policies[i];
+            let effect = match policy.effect {
+                PolicyEffect::Allow => "",
+                PolicyEffect::Deny => ", deny",
+            };
+            format!("{} (ReasonCode: {}{})", policy.name, i, effect)
+        }
+        None => "default policy".to_string(),
+    }
+}
+
+fn format_compact(result: &ExplainResult) -> String {
+    let mut out = format!("matched {}", decision(result));
+    for policy in &result.policies {
+        if policy.overall_matched {
+            out.push_str(&format!(" | ✓ {}", policy.name));
+        } else {
+            out.push_str(&format!(
+                " | ✗ {}: {}",
+                policy.name,
+                policy.failures.join("; ")
+            ));
+        }
+    }
+    out
+}
+
+fn format_markdown(result: &ExplainResult) -> String {
+    let cell = |checks: &[ConditionExplain]| {
+        if checks.is_empty() {
+            return "(none)".to_string();
+        }
+        let checks: Vec<String> = checks
+            .iter()
+            .map(|c| {
+                let mark = if c.matched { "✓" } else { "✗" };
+                let check = format!("{} {}: {} → {}", mark, c.field, c.pattern, c.request_value);
+                check.replace('|', "\\|")
+            })
+            .collect();
+        checks.join("<br>")
+    };
+
+    let mut out = String::from("| # | Policy | Effect | Triggers | Filters | Result |\n");
+    out.push_str("|---|--------|--------|----------|---------|--------|\n");
+    for policy in &result.policies {
+        let overall = if policy.overall_matched {
+            "**match**"
+        } else {
+            "no match"
+        };
+        out.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            policy.index,
+            policy.name.replace('|', "\\|"),
+            policy.effect.as_str(),
+            cell(&policy.triggers),
+            cell(&policy.filters),
+            overall
+        ));
+    }
+    out.push_str(&format!("\n**Matched:** {}\n", decision(result)));
+    out
+}
+
+fn format_text(result: &ExplainResult) -> String {
     let mut out = String::new();
 
     for policy in &result.policies {
-        out.push_str(&format!("━━━ Policy [{}]: {} ━━━\n", policy.index, policy.name));
+        let effect = match policy.effect {
+            PolicyEffect::Allow => "",
+            PolicyEffect::Deny => " (deny)",
+        };
+        out.push_str(&format!(
+            "━━━ Policy [{}]: {}{} ━━━\n",
+            policy.index, policy.name, effect
+        ));
 
         // Triggers
         if policy.triggers.is_empty() {
@@ -165,7 +553,11 @@ pub fn format_explain(result: &ExplainResult) -> String {
                     mark, t.field, t.pattern, t.request_value
                 ));
             }
-            let trigger_result = if policy.trigger_passed { "PASSED" } else { "FAILED" };
+            let trigger_result = if policy.trigger_passed {
+                "PASSED"
+            } else {
+                "FAILED"
+            };
             out.push_str(&format!("  Trigger result: {}\n", trigger_result));
         }
 
@@ -181,29 +573,186 @@ pub fn format_explain(result: &ExplainResult) -> String {
                     mark, f.field, f.pattern, f.request_value
                 ));
             }
-            let filter_result = if policy.filter_passed { "PASSED" } else { "FAILED" };
+            let filter_result = if policy.filter_passed {
+                "PASSED"
+            } else {
+                "FAILED"
+            };
             out.push_str(&format!("  Filter result: {}\n", filter_result));
         }
 
+        if policy.effect == PolicyEffect::Allow {
+            out.push_str(&format!(
+                "  Permissions: {}\n",
+                permissions(&policy.permissions)
+            ));
+        }
+
         // Overall
-        let overall = if policy.overall_matched { "MATCH ✓" } else { "NO MATCH" };
-        out.push_str(&format!("  Overall: {}\n\n", overall));
+        let overall = if policy.overall_matched {
+            "MATCH ✓"
+        } else {
+            "NO MATCH"
+        };
+        out.push_str(&format!("  Overall: {}\n", overall));
+        for failure in &policy.failures {
+            out.push_str(&format!("    - {}\n", failure));
+        }
+        out.push('\n');
     }
 
     // Final result
     out.push_str("━━━ Result ━━━\n");
-    match &result.matched_policy {
-        Some(name) => {
-            out.push_str(&format!(
-                "Matched: {} (ReasonCode: {})\n",
-                name,
-                result.matched_index.unwrap()
-            ));
-        }
-        None => {
-            out.push_str("Matched: (default policy)\n");
-        }
+    match result.matched_index {
+        Some(_) => out.push_str(&format!("Matched: {}\n", decision(result))),
+        None => out.push_str("Matched: (default policy)\n"),
     }
 
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::parse_policy;
+
+    fn result() -> ExplainResult {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Admins | Ops"
+    match:
+      oidc_groups: ["admins"]
+      source_ip: ["10.0.0.0/8"]
+    principals: ["root"]
+    max_duration: "60m"
+  - name: "Blocked"
+    effect: deny
+    match:
+      emails: ["*@example.com"]
+"#;
+        let request = EvalRequest {
+            oidc_groups: vec!["admins".to_string()],
+            email: Some("eve@example.com".to_string()),
+            source_ip: Some("192.168.1.1".to_string()),
+            ..Default::default()
+        };
+        explain(&parse_policy(yaml).unwrap(), &request)
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!(
+            format_explain(&result(), ExplainFormat::Compact),
+            "matched Blocked (ReasonCode: 1, deny) | ✗ Admins | Ops: \
+             source_ip 192.168.1.1 not in 10.0.0.0/8 | ✓ Blocked"
+        );
+    }
+
+    #[test]
+    fn test_markdown() {
+        assert_eq!(
+            format_explain(&result(), ExplainFormat::Markdown),
+            "| # | Policy | Effect | Triggers | Filters | Result |\n\
+             |---|--------|--------|----------|---------|--------|\n\
+             | 0 | Admins \\| Ops | allow | ✓ oidc_groups: [\"admins\"] → [\"admins\"] \
+             | ✗ source_ip: [\"10.0.0.0/8\"] → 192.168.1.1 | no match |\n\
+             | 1 | Blocked | deny | ✓ emails: [\"*@example.com\"] → eve@example.com \
+             | (none) | **match** |\n\
+             \n\
+             **Matched:** Blocked (ReasonCode: 1, deny)\n"
+        );
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let json: Value =
+            serde_json::from_str(&format_explain(&result(), ExplainFormat::Json)).unwrap();
+        assert_eq!(json["version"], EXPLAIN_JSON_VERSION);
+        assert_eq!(
+            json["matched"],
+            json!({ "index": 1, "name": "Blocked", "effect": "deny" })
+        );
+        assert_eq!(
+            json["policies"][0]["filters"],
+            json!({
+                "passed": false,
+                "checks": [{
+                    "field": "source_ip",
+                    "pattern": "[\"10.0.0.0/8\"]",
+                    "value": "192.168.1.1",
+                    "matched": false,
+                    "failure": "source_ip 192.168.1.1 not in 10.0.0.0/8",
+                }],
+            })
+        );
+        assert_eq!(
+            json["policies"][0]["failures"],
+            json!(["source_ip 192.168.1.1 not in 10.0.0.0/8"])
+        );
+        assert_eq!(json["policies"][1]["triggers"]["passed"], true);
+    }
+
+    #[test]
+    fn test_failures() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Office"
+    match:
+      oidc_groups: ["admins", "ops"]
+      emails: ["*@example.com"]
+      hours: ["09:00-17:00"]
+      all:
+        - source_ip: ["10.0.0.0/8"]
+        - any:
+            - webauthn_ids: ["yubi-1"]
+            - local_usernames: ["deploy"]
+    principals: ["root"]
+    max_duration: "60m"
+    expires: "2026-06-30"
+"#;
+        let request = EvalRequest {
+            oidc_groups: vec!["developers".to_string()],
+            source_ip: Some("not-an-ip".to_string()),
+            current_time: Some("18:30".to_string()),
+            current_date: Some("2026-07-01".to_string()),
+            webauthn_id: Some("yubi-2".to_string()),
+            ..Default::default()
+        };
+        let result = explain(&parse_policy(yaml).unwrap(), &request);
+        assert_eq!(
+            result.policies[0].failures,
+            [
+                "oidc_groups developers not in any of admins, ops",
+                "request has no email",
+                "current_time 18:30 not in 09:00-17:00",
+                "all[0]: source_ip \"not-an-ip\" is not an IP address; \
+                 all[1]: no any block matched: \
+                 any[0]: webauthn_id yubi-2 does not equal yubi-1; \
+                 any[1]: request has no local_username",
+                "current_date 2026-07-01 is after expires 2026-06-30",
+            ]
+        );
+        assert!(result.policies[0].triggers[0].failure.is_some());
+
+        let request = EvalRequest {
+            oidc_groups: vec!["ops".to_string()],
+            ..request
+        };
+        let result = explain(&parse_policy(yaml).unwrap(), &request);
+        assert!(result.policies[0].triggers[0].failure.is_none());
+        assert_eq!(result.policies[0].failures.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!("md".parse(), Ok(ExplainFormat::Markdown));
+        assert_eq!("compact".parse(), Ok(ExplainFormat::Compact));
+        assert!("xml".parse::<ExplainFormat>().is_err());
+    }
+}
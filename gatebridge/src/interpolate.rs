@@ -0,0 +1,130 @@
+//! `${VAR}` interpolation in policy values.
+//!
+//! Lets one policy file be promoted from staging to production with the
+//! differences in the environment. Only variables on the allowlist may be
+//! referenced, so a policy can't read arbitrary process state. `$${` is a
+//! literal `${`.
+
+use std::collections::HashMap;
+
+/// Which variables policy values may reference, and how to expand them.
+///
+/// The default allows none: any `${VAR}` is an error.
+#[derive(Debug, Clone, Default)]
+pub struct Interpolation {
+    allowed: Vec<String>,
+    strict: bool,
+    vars: Option<HashMap<String, String>>,
+}
+
+impl Interpolation {
+    /// Allow nothing; add variables with `allow`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allow `${name}` in policy values.
+    pub fn allow(mut self, name: impl Into<String>) -> Self {
+        self.allowed.push(name.into());
+        self
+    }
+
+    /// In strict mode an allowed variable that is not set is an error;
+    /// otherwise it expands to nothing.
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Read variables from `vars` instead of the process environment.
+    pub fn vars(mut self, vars: HashMap<String, String>) -> Self {
+        self.vars = Some(vars);
+        self
+    }
+
+    /// Expand every `${VAR}` in `value`.
+    pub fn expand(&self, value: &str) -> Result<String, String> {
+        let mut out = String::with_capacity(value.len());
+        let mut rest = value;
+        while let Some(i) = rest.find('$') {
+            out.push_str(&rest[..i]);
+            rest = &rest[i..];
+            if let Some(after) = rest.strip_prefix("$${") {
+                out.push_str("${");
+                rest = after;
+                continue;
+            }
+            let Some(after) = rest.strip_prefix("${") else {
+                out.push('$');
+                rest = &rest[1..];
+                continue;
+            };
+            let Some(end) = after.find('}') else {
+                return Err(format!("unterminated ${{ in {:?}", value));
+            };
+            let name = &after[..end];
+            if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+                return Err(format!("invalid variable name {:?} in {:?}", name, value));
+            }
+            if !self.allowed.iter().any(|a| a == name) {
+                return Err(format!("${{{}}} is not an allowed variable", name));
+            }
+            match self.lookup(name) {
+                Some(v) => out.push_str(&v),
+                None if self.strict => return Err(format!("${{{}}} is not set", name)),
+                None => {}
+            }
+            rest = &after[end + 1..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+
+    fn lookup(&self, name: &str) -> Option<String> {
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env() -> Interpolation {
+        let vars = HashMap::from([("STAGE".to_string(), "prod".to_string())]);
+        Interpolation::new()
+            .allow("STAGE")
+            .allow("REGION")
+            .vars(vars)
+    }
+
+    #[test]
+    fn test_expand() {
+        let env = env();
+        assert_eq!(env.expand("deploy-${STAGE}").unwrap(), "deploy-prod");
+        assert_eq!(env.expand("${STAGE}-${STAGE}").unwrap(), "prod-prod");
+        assert_eq!(env.expand("$5 and $${STAGE}").unwrap(), "$5 and ${STAGE}");
+        assert_eq!(env.expand("${REGION}x").unwrap(), "x");
+    }
+
+    #[test]
+    fn test_expand_errors() {
+        let env = env();
+        assert_eq!(
+            env.expand("${HOME}").unwrap_err(),
+            "${HOME} is not an allowed variable"
+        );
+        assert!(env.expand("${STAGE").unwrap_err().contains("unterminated"));
+        assert!(env
+            .expand("${}")
+            .unwrap_err()
+            .contains("invalid variable name"));
+        assert_eq!(
+            env.strict(true).expand("${REGION}").unwrap_err(),
+            "${REGION} is not set"
+        );
+        assert!(Interpolation::default().expand("${STAGE}").is_err());
+    }
+}
@@ -4,16 +4,55 @@
 //! and provides shadow evaluation for validation.
 
 mod ast;
+mod corpus;
+mod decide;
 mod explain;
+pub mod fuzz;
+mod interpolate;
+mod lint;
 mod loader;
 pub mod reference_eval;
+mod schema;
+mod server;
 mod shadow;
+mod template;
 mod translate;
+#[cfg(feature = "watch")]
+mod watch;
 
 pub use ast::*;
-pub use explain::{explain, format_explain, ExplainResult};
-pub use loader::{load_policy_file, parse_policy};
-pub use reference_eval::evaluate as reference_evaluate;
-pub use shadow::{shadow_evaluate, ShadowResult};
-pub use translate::to_gate0;
-
+pub use corpus::{run_corpus, CaseResult, CorpusReport};
+pub use decide::{decide, decide_many, EvalDecision};
+pub use explain::{
+    explain, explain_json, format_explain, ExplainFormat, ExplainResult, EXPLAIN_JSON_VERSION,
+};
+pub use fuzz::{
+    run_fuzz, run_fuzz_with, run_replay, run_replay_with, FuzzCase, FuzzConfig, FuzzReport,
+    ReplayReport,
+};
+pub use interpolate::Interpolation;
+pub use lint::{lint, LintKind, LintWarning, LONG_DURATION};
+pub use loader::{
+    load_policy, load_policy_dir, load_policy_file, load_policy_file_as, load_policy_with,
+    parse_policy, parse_policy_as, parse_policy_as_with, parse_policy_with, LoadError,
+    PolicyFormat, SourceLocation,
+};
+pub use reference_eval::{
+    evaluate as reference_evaluate, evaluate_many, evaluate_many as reference_evaluate_many,
+    CommandReference, ReferenceEvaluator, RustReference,
+};
+pub use schema::{policy_schema, SCHEMA_DIALECT};
+pub use server::{Response, Server, IO_TIMEOUT, MAX_BODY_LEN, WORKERS};
+pub use shadow::{
+    shadow_evaluate, shadow_evaluate_many, shadow_evaluate_many_with, shadow_evaluate_with,
+    Divergence, EntryStats, ShadowError, ShadowReport, ShadowResult, ShadowTotals, MAX_DIVERGENCES,
+};
+pub use template::{check_principal, resolve_principals, PLACEHOLDERS};
+pub use translate::{
+    from_gate0, gate0_context, gate0_permissions, gate0_principals, to_gate0, to_rust_source,
+    PolicyMapping, TranslationMap,
+};
+#[cfg(feature = "watch")]
+pub use watch::{
+    PolicyWatcher, ReloadError, ReloadEvent, WatchHandle, WatchedPolicy, DEFAULT_INTERVAL,
+};
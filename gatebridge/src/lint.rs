@@ -0,0 +1,352 @@
+//! Policy linter.
+//!
+//! Finds policies that load and translate but probably do not say what
+//! their author meant: repeated names, policies that match everything or
+//! never decide anything, grants of no principals, and odd durations. Meant
+//! as a pre-merge check; nothing here stops a policy from loading.
+//!
+//! Reachability is judged from the patterns alone and errs towards
+//! silence: a policy is only reported unreachable when an earlier one
+//! certainly matches every request it does.
+
+use crate::ast::{MatchBlock, Policy, PolicyEffect, PolicyFile};
+use std::fmt;
+
+/// `max_duration` values over this many seconds (a day) are reported.
+pub const LONG_DURATION: u64 = 24 * 60 * 60;
+
+/// A lint finding about one policy, or about the default block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    /// Index of the policy, or `None` for the default block.
+    pub policy: Option<usize>,
+    /// Name of the policy, or `"default"`.
+    pub name: String,
+    pub kind: LintKind,
+}
+
+/// What a `LintWarning` found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintKind {
+    /// An earlier policy has the same name.
+    DuplicateName { first: usize },
+    /// The policy has neither triggers nor filters, so it matches every
+    /// request.
+    MatchesEverything,
+    /// The allow policy, or the default, grants no principals.
+    NoPrincipals,
+    /// The policy never decides a request: an earlier policy with the same
+    /// effect, or a deny policy anywhere, matches every request it does.
+    Unreachable { by: usize },
+    /// `max_duration` is over `LONG_DURATION`.
+    LongDuration { max_duration: String },
+    /// The allow policy grants less time than the default, so matching it
+    /// shortens access.
+    ShorterThanDefault {
+        max_duration: String,
+        default: String,
+    },
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.policy {
+            Some(index) => write!(f, "policies[{}] {:?}: ", index, self.name)?,
+            None => write!(f, "default: ")?,
+        }
+        match &self.kind {
+            LintKind::DuplicateName { first } => {
+                write!(f, "same name as policies[{}]", first)
+            }
+            LintKind::MatchesEverything => {
+                write!(f, "matches every request (no triggers or filters)")
+            }
+            LintKind::NoPrincipals => write!(f, "grants no principals"),
+            LintKind::Unreachable { by } => {
+                write!(
+                    f,
+                    "never decides: policies[{}] matches every request it does",
+                    by
+                )
+            }
+            LintKind::LongDuration { max_duration } => write!(
+                f,
+                "max_duration {} is over {}h",
+                max_duration,
+                LONG_DURATION / 3600
+            ),
+            LintKind::ShorterThanDefault {
+                max_duration,
+                default,
+            } => write!(
+                f,
+                "max_duration {} is shorter than the default's {}, so matching shortens access",
+                max_duration, default
+            ),
+        }
+    }
+}
+
+/// Lint a policy file, returning warnings in policy order, the default
+/// block's first.
+pub fn lint(policy_file: &PolicyFile) -> Vec<LintWarning> {
+    let default = &policy_file.default;
+    let mut warnings = Vec::new();
+    let mut warn = |policy: Option<usize>, kind| {
+        let name = match policy {
+            Some(i) => policy_file.policies[i].name.clone(),
+            None => "default".to_string(),
+        };
+        warnings.push(LintWarning { policy, name, kind });
+    };
+
+    if default.principals.is_empty() {
+        warn(None, LintKind::NoPrincipals);
+    }
+    if default.max_duration.seconds() > LONG_DURATION {
+        let max_duration = default.max_duration.to_string();
+        warn(None, LintKind::LongDuration { max_duration });
+    }
+
+    let policies = &policy_file.policies;
+    for (index, policy) in policies.iter().enumerate() {
+        if let Some(first) = policies[..index].iter().position(|p| p.name == policy.name) {
+            warn(Some(index), LintKind::DuplicateName { first });
+        }
+        let m = &policy.match_block;
+        if !m.has_triggers() && !m.has_filters() && m.any.is_empty() && m.all.is_empty() {
+            warn(Some(index), LintKind::MatchesEverything);
+        }
+
+        // Deny policies win wherever they are; otherwise the first wins
+        let by = policies.iter().enumerate().position(|(i, earlier)| {
+            let wins = match (earlier.effect, policy.effect) {
+                (PolicyEffect::Deny, PolicyEffect::Allow) => i != index,
+                (a, b) => i < index && a == b,
+            };
+            wins && covers(&earlier.match_block, m) && outlasts(earlier, policy)
+        });
+        if let Some(by) = by {
+            warn(Some(index), LintKind::Unreachable { by });
+        }
+
+        if policy.effect == PolicyEffect::Deny {
+            continue;
+        }
+        if policy.principals.is_empty() {
+            warn(Some(index), LintKind::NoPrincipals);
+        }
+        if let Some(max_duration) = &policy.max_duration {
+            if max_duration.seconds() > LONG_DURATION {
+                let max_duration = max_duration.to_string();
+                warn(Some(index), LintKind::LongDuration { max_duration });
+            } else if max_duration.seconds() < default.max_duration.seconds() {
+                warn(
+                    Some(index),
+                    LintKind::ShorterThanDefault {
+                        max_duration: max_duration.to_string(),
+                        default: default.max_duration.to_string(),
+                    },
+                );
+            }
+        }
+    }
+
+    warnings
+}
+
+/// True if policy `a` matches on every date `b` does: `a` never expires,
+/// or `b` expires no later.
+fn outlasts(a: &Policy, b: &Policy) -> bool {
+    match (&a.expires, &b.expires) {
+        (None, _) => true,
+        (Some(a), Some(b)) => b <= a,
+        (Some(_), None) => false,
+    }
+}
+
+/// True if block `a` certainly matches every request `b` matches: each of
+/// `a`'s checks lists every pattern of `b`'s, and `a`'s nested blocks, if
+/// any, are `b`'s.
+fn covers(a: &MatchBlock, b: &MatchBlock) -> bool {
+    let superset = |a: &[String], b: &[String]| b.iter().all(|p| a.contains(p));
+    let triggers = !a.has_triggers()
+        || (b.has_triggers()
+            && superset(&a.oidc_groups, &b.oidc_groups)
+            && superset(&a.emails, &b.emails)
+            && superset(&a.local_usernames, &b.local_usernames));
+    let filter = |a: &[String], b: &[String]| a.is_empty() || (!b.is_empty() && superset(a, b));
+    triggers
+        && filter(&a.source_ip, &b.source_ip)
+        && filter(&a.hours, &b.hours)
+        && filter(&a.webauthn_ids, &b.webauthn_ids)
+        && (a.any.is_empty() || a.any == b.any)
+        && (a.all.is_empty() || a.all == b.all)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::parse_policy;
+
+    fn kinds(yaml: &str) -> Vec<(Option<usize>, LintKind)> {
+        lint(&parse_policy(yaml).unwrap())
+            .into_iter()
+            .map(|w| (w.policy, w.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_clean_policy() {
+        assert_eq!(
+            lint(&parse_policy(include_str!("../example_policy.yaml")).unwrap()),
+            []
+        );
+    }
+
+    #[test]
+    fn test_findings() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "30m"
+policies:
+  - name: "Admins"
+    match:
+      oidc_groups: ["admins"]
+      source_ip: ["10.0.0.0/8"]
+    principals: ["root"]
+    max_duration: "2d"
+  - name: "Office"
+    match:
+      oidc_groups: ["admins"]
+      source_ip: ["10.0.0.0/8"]
+      hours: ["09:00-17:00"]
+    principals: ["developer"]
+    max_duration: "15m"
+  - name: "Everyone"
+    principals: ["readonly"]
+    max_duration: "1h"
+  - name: "Contractors"
+    effect: deny
+    match:
+      emails: ["*@contractor.example.com"]
+  - name: "Vendors"
+    match:
+      emails: ["*@contractor.example.com", "*@vendor.example.com"]
+    principals: ["readonly"]
+    max_duration: "1h"
+"#;
+        assert_eq!(
+            kinds(yaml),
+            [
+                (
+                    Some(0),
+                    LintKind::LongDuration {
+                        max_duration: "2d".to_string()
+                    }
+                ),
+                (Some(1), LintKind::Unreachable { by: 0 }),
+                (
+                    Some(1),
+                    LintKind::ShorterThanDefault {
+                        max_duration: "15m".to_string(),
+                        default: "30m".to_string(),
+                    }
+                ),
+                (Some(2), LintKind::MatchesEverything),
+                (Some(4), LintKind::Unreachable { by: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_names() {
+        // The loader rejects these, but files built in code can have them
+        let mut policy_file = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let mut copy = policy_file.policies[1].clone();
+        copy.match_block.hours.clear();
+        policy_file.policies.push(copy);
+        let warnings = lint(&policy_file);
+        assert_eq!(warnings[0].kind, LintKind::DuplicateName { first: 1 });
+        assert_eq!(
+            warnings[0].to_string(),
+            "policies[4] \"DeveloperAccess\": same name as policies[1]"
+        );
+    }
+
+    #[test]
+    fn test_deny_policies_shadow_allows_anywhere() {
+        let yaml = r#"
+default:
+  principals: []
+  max_duration: "15m"
+policies:
+  - name: "Night"
+    match:
+      oidc_groups: ["ops"]
+      hours: ["22:00-06:00"]
+    principals: ["root"]
+    max_duration: "15m"
+  - name: "Ops"
+    effect: deny
+    match:
+      oidc_groups: ["ops", "contractors"]
+"#;
+        let warnings = lint(&parse_policy(yaml).unwrap());
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "default: grants no principals",
+                "policies[0] \"Night\": never decides: policies[1] matches every request it does",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_covers_is_conservative() {
+        let block = |yaml: &str| serde_yaml::from_str::<MatchBlock>(yaml).unwrap();
+        let office = block("{oidc_groups: [admins], source_ip: [10.0.0.0/8]}");
+        assert!(covers(&block("{}"), &office));
+        assert!(covers(&block("{oidc_groups: [admins, ops]}"), &office));
+        assert!(!covers(&office, &block("{oidc_groups: [admins]}")));
+        assert!(!covers(&block("{emails: [\"*\"]}"), &office));
+        assert!(!covers(
+            &block("{all: [{hours: [\"09:00-17:00\"]}]}"),
+            &office
+        ));
+    }
+
+    #[test]
+    fn test_expiring_policies_shadow_less() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Contract"
+    match:
+      oidc_groups: ["contractors"]
+    principals: ["deploy"]
+    max_duration: "1h"
+    expires: "2026-06-30"
+  - name: "Extension"
+    match:
+      oidc_groups: ["contractors"]
+    principals: ["deploy"]
+    max_duration: "1h"
+    expires: "2026-12-31"
+  - name: "Stale"
+    match:
+      oidc_groups: ["contractors"]
+    principals: ["deploy"]
+    max_duration: "1h"
+    expires: "2026-01-31"
+"#;
+        let warnings = lint(&parse_policy(yaml).unwrap());
+        assert_eq!(
+            warnings.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            ["policies[2] \"Stale\": never decides: policies[0] matches every request it does"]
+        );
+    }
+}
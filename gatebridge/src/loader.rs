@@ -1,47 +1,592 @@
-//! YAML policy loader
+//! Policy loader
 //!
-//! Reads and parses policy files. Nothing fancy.
+//! Reads and parses policy files, written in YAML, TOML, or JSON; the
+//! schema is the same in each (see `PolicyFormat`). A policy can be split across files, such
+//! as per-team fragments: the root file (the one with the `default` block)
+//! lists others under `include:`, or `load_policy_dir` loads a whole
+//! directory. Other files hold only `policies` and their own `include`s.
+//! Files merge in a fixed order, and policy names must be unique across
+//! all of them.
+//!
+//! Principals and match values may reference environment variables as
+//! `${VAR}`, expanded as the `Interpolation` passed to the `_with` loaders
+//! allows. The other loaders allow no variables.
+
+use crate::ast::{DefaultPolicy, ExcessDuration, MatchBlock, Policy, PolicyEffect, PolicyFile};
+use crate::interpolate::Interpolation;
+use crate::template::check_principal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// One file, before merging.
+#[derive(Debug, Deserialize)]
+struct Document {
+    default: Option<DefaultPolicy>,
+    on_excess_duration: Option<ExcessDuration>,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    policies: Vec<Policy>,
+}
+
+/// The syntax of a policy file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PolicyFormat {
+    /// `.yaml` or `.yml`, and any file with another extension.
+    #[default]
+    Yaml,
+    /// `.toml`, with policies as an array of tables (`[[policies]]`).
+    Toml,
+    /// `.json`.
+    Json,
+}
+
+impl PolicyFormat {
+    /// The format of `path` by its extension, or `None` if it has none of
+    /// the policy extensions.
+    pub fn from_path(path: &Path) -> Option<PolicyFormat> {
+        match path.extension()?.to_str()? {
+            "yaml" | "yml" => Some(PolicyFormat::Yaml),
+            "toml" => Some(PolicyFormat::Toml),
+            "json" => Some(PolicyFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for PolicyFormat {
+    type Err = String;
 
-use std::path::Path;
-use crate::ast::PolicyFile;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "yaml" | "yml" => Ok(PolicyFormat::Yaml),
+            "toml" => Ok(PolicyFormat::Toml),
+            "json" => Ok(PolicyFormat::Json),
+            _ => Err(format!(
+                "unknown policy format {:?} (expected yaml, toml, or json)",
+                s
+            )),
+        }
+    }
+}
 
-/// Load a policy file from disk.
+/// Load a policy file from disk, followed by the files it includes.
+///
+/// Includes are relative to the including file and are read depth first,
+/// in the order listed, after the including file's own policies. Each
+/// file's format is detected from its extension, falling back to YAML.
 pub fn load_policy_file(path: &Path) -> Result<PolicyFile, LoadError> {
-    let contents = std::fs::read_to_string(path)
-        .map_err(|e| LoadError::Io(e.to_string()))?;
-    
-    parse_policy(&contents)
+    load_file(path, None, &mut Vec::new(), &Interpolation::default())
 }
 
-/// Parse policy from a YAML string.
+/// `load_policy_file`, reading `path` as `format` whatever its extension.
+/// The files it includes are still detected from theirs.
+pub fn load_policy_file_as(path: &Path, format: PolicyFormat) -> Result<PolicyFile, LoadError> {
+    load_file(
+        path,
+        Some(format),
+        &mut Vec::new(),
+        &Interpolation::default(),
+    )
+}
+
+fn load_file(
+    path: &Path,
+    format: Option<PolicyFormat>,
+    seen: &mut Vec<PathBuf>,
+    env: &Interpolation,
+) -> Result<PolicyFile, LoadError> {
+    let mut documents = Vec::new();
+    read_with_includes(path, format, seen, &mut documents)?;
+    merge(documents, env)
+}
+
+/// Load every `.yaml`, `.yml`, `.toml`, and `.json` file in `dir`, in file
+/// name order, each followed by the files it includes. Exactly one file
+/// must have the `default` block.
+pub fn load_policy_dir(dir: &Path) -> Result<PolicyFile, LoadError> {
+    load_dir(dir, &mut Vec::new(), &Interpolation::default())
+}
+
+fn load_dir(
+    dir: &Path,
+    seen: &mut Vec<PathBuf>,
+    env: &Interpolation,
+) -> Result<PolicyFile, LoadError> {
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(dir).map_err(|e| io_error(dir, e))? {
+        let path = entry.map_err(|e| io_error(dir, e))?.path();
+        if PolicyFormat::from_path(&path).is_some() && path.is_file() {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    let mut documents = Vec::new();
+    for path in &paths {
+        read_with_includes(path, None, seen, &mut documents)?;
+    }
+    merge(documents, env)
+}
+
+/// Load a policy from a directory with `load_policy_dir`, or from a file
+/// with `load_policy_file`.
+pub fn load_policy(path: &Path) -> Result<PolicyFile, LoadError> {
+    load_policy_with(path, &Interpolation::default())
+}
+
+/// `load_policy`, expanding `${VAR}` references as `env` allows.
+pub fn load_policy_with(path: &Path, env: &Interpolation) -> Result<PolicyFile, LoadError> {
+    load_tracked(path, env, &mut Vec::new())
+}
+
+/// `load_policy_with`, pushing each file read, canonicalized, to `seen`,
+/// including those read before an error.
+pub(crate) fn load_tracked(
+    path: &Path,
+    env: &Interpolation,
+    seen: &mut Vec<PathBuf>,
+) -> Result<PolicyFile, LoadError> {
+    if path.is_dir() {
+        load_dir(path, seen, env)
+    } else {
+        load_file(path, None, seen, env)
+    }
+}
+
+/// Parse policy from a YAML string. The string cannot include files.
 pub fn parse_policy(yaml: &str) -> Result<PolicyFile, LoadError> {
-    // Handle the "match" keyword issue - serde can't use it directly
-    let yaml = yaml.replace("match:", "match_block:");
-    
-    serde_yaml::from_str(&yaml)
-        .map_err(|e| LoadError::Parse(e.to_string()))
+    parse_policy_as_with(yaml, PolicyFormat::Yaml, &Interpolation::default())
+}
+
+/// `parse_policy`, expanding `${VAR}` references as `env` allows.
+pub fn parse_policy_with(yaml: &str, env: &Interpolation) -> Result<PolicyFile, LoadError> {
+    parse_policy_as_with(yaml, PolicyFormat::Yaml, env)
+}
+
+/// Parse policy from a string in `format`. The string cannot include
+/// files.
+pub fn parse_policy_as(text: &str, format: PolicyFormat) -> Result<PolicyFile, LoadError> {
+    parse_policy_as_with(text, format, &Interpolation::default())
+}
+
+/// `parse_policy_as`, expanding `${VAR}` references as `env` allows.
+pub fn parse_policy_as_with(
+    text: &str,
+    format: PolicyFormat,
+    env: &Interpolation,
+) -> Result<PolicyFile, LoadError> {
+    let document = parse_document(text, format, None)?;
+    if !document.include.is_empty() {
+        return Err(LoadError::Invalid(
+            "include needs a policy loaded from a file".to_string(),
+        ));
+    }
+    merge(vec![("<input>".to_string(), document)], env)
+}
+
+/// Parse one file, from `file` if it was read from disk, rejecting unknown
+/// keys and allow policies that don't say what they grant.
+fn parse_document(
+    text: &str,
+    format: PolicyFormat,
+    file: Option<&str>,
+) -> Result<Document, LoadError> {
+    let at = |path: String| SourceLocation {
+        file: file.map(str::to_string),
+        path,
+        line: None,
+        column: None,
+    };
+    let parse_error =
+        |message: String, position: Option<(usize, usize)>, path: String| LoadError::Parse {
+            message: error_message(message, position, &path),
+            at: SourceLocation {
+                line: position.map(|(line, _)| line),
+                column: position.map(|(_, column)| column),
+                ..at(path)
+            },
+        };
+
+    let mut unknown = Vec::new();
+    let result = match format {
+        PolicyFormat::Yaml => {
+            let de = serde_yaml::Deserializer::from_str(text);
+            deserialize(de, &mut unknown).map_err(|(e, path)| {
+                let position = e.location().map(|l| (l.line(), l.column()));
+                parse_error(e.to_string(), position, path)
+            })
+        }
+        PolicyFormat::Toml => {
+            let de = toml::Deserializer::new(text);
+            deserialize(de, &mut unknown).map_err(|(e, path)| {
+                let position = e.span().map(|span| line_column(text, span.start));
+                parse_error(e.message().to_string(), position, path)
+            })
+        }
+        PolicyFormat::Json => {
+            let mut de = serde_json::Deserializer::from_str(text);
+            deserialize(&mut de, &mut unknown)
+                .and_then(|d| de.end().map(|()| d).map_err(|e| (e, String::new())))
+                .map_err(|(e, path)| {
+                    // serde_json reports line 0 when it has no position
+                    let position = (e.line() > 0).then(|| (e.line(), e.column()));
+                    parse_error(e.to_string(), position, path)
+                })
+        }
+    };
+    let document = result?;
+    if let Some(path) = unknown.into_iter().next() {
+        return Err(LoadError::UnknownKey { at: at(path) });
+    }
+
+    // Check what serde can't: allow policies must say what they grant
+    for (i, policy) in document.policies.iter().enumerate() {
+        if policy.effect != PolicyEffect::Allow {
+            continue;
+        }
+        if policy.principals.is_empty() {
+            return Err(LoadError::EmptyPrincipals {
+                at: at(format!("policies[{}].principals", i)),
+                policy: policy.name.clone(),
+            });
+        }
+        if policy.max_duration.is_none() {
+            return Err(LoadError::MissingMaxDuration {
+                at: at(format!("policies[{}].max_duration", i)),
+                policy: policy.name.clone(),
+            });
+        }
+    }
+    Ok(document)
+}
+
+/// Deserialize a document from `de`, pushing the path of each unknown key
+/// to `unknown`. An error comes with the path being deserialized, empty for
+/// the whole document.
+fn deserialize<'de, D: serde::Deserializer<'de>>(
+    de: D,
+    unknown: &mut Vec<String>,
+) -> Result<Document, (D::Error, String)> {
+    let mut track = serde_path_to_error::Track::new();
+    let mut ignored = |path: serde_ignored::Path| unknown.push(ignored_path(&path));
+    let de = serde_ignored::Deserializer::new(de, &mut ignored);
+    Document::deserialize(serde_path_to_error::Deserializer::new(de, &mut track)).map_err(|e| {
+        let path = match track.path().to_string() {
+            root if root == "." => String::new(),
+            path => path,
+        };
+        (e, path)
+    })
+}
+
+/// An error message for `path`, without the location serde_yaml and
+/// serde_json append, or the path serde_yaml prefixes (its path may be a
+/// parent of `path`).
+fn error_message(mut message: String, position: Option<(usize, usize)>, path: &str) -> String {
+    if let Some((line, column)) = position {
+        message = message.replacen(&format!(" at line {} column {}", line, column), "", 1);
+    }
+    let mut parent = path;
+    while !parent.is_empty() {
+        if let Some(rest) = message.strip_prefix(&format!("{}: ", parent)) {
+            return rest.to_string();
+        }
+        parent = &parent[..parent.rfind(['.', '[']).unwrap_or(0)];
+    }
+    message
+}
+
+/// A `serde_ignored` path in the same form as `serde_path_to_error`'s,
+/// such as `policies[3].match.oidc_group`.
+fn ignored_path(path: &serde_ignored::Path) -> String {
+    use serde_ignored::Path;
+    match path {
+        Path::Root => String::new(),
+        Path::Seq { parent, index } => format!("{}[{}]", ignored_path(parent), index),
+        Path::Map { parent, key } => match ignored_path(parent) {
+            parent if parent.is_empty() => key.clone(),
+            parent => format!("{}.{}", parent, key),
+        },
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => ignored_path(parent),
+    }
+}
+
+/// The 1-based line and column of byte `offset` in `text`.
+fn line_column(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Read `path`, as `format` or else as its extension says, and then,
+/// depth first, the files it includes, appending each to `documents`.
+/// `seen` holds every file read so far; reading one twice, through a cycle
+/// or two includes, is an error.
+fn read_with_includes(
+    path: &Path,
+    format: Option<PolicyFormat>,
+    seen: &mut Vec<PathBuf>,
+    documents: &mut Vec<(String, Document)>,
+) -> Result<(), LoadError> {
+    let canonical = path.canonicalize().map_err(|e| io_error(path, e))?;
+    if seen.contains(&canonical) {
+        return Err(LoadError::Invalid(format!(
+            "{} is included more than once",
+            path.display()
+        )));
+    }
+    seen.push(canonical);
+
+    let contents = fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+    let format = format
+        .or_else(|| PolicyFormat::from_path(path))
+        .unwrap_or_default();
+    let document = parse_document(&contents, format, Some(&path.display().to_string()))?;
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let includes: Vec<PathBuf> = document.include.iter().map(|i| dir.join(i)).collect();
+    documents.push((path.display().to_string(), document));
+
+    for include in includes {
+        read_with_includes(&include, None, seen, documents)?;
+    }
+    Ok(())
+}
+
+/// Merge documents, in order, into one policy file, and expand variables.
+/// Exactly one has the `default` block, and only that one may set
+/// `on_excess_duration`.
+fn merge(documents: Vec<(String, Document)>, env: &Interpolation) -> Result<PolicyFile, LoadError> {
+    let mut root: Option<(String, DefaultPolicy, ExcessDuration)> = None;
+    let mut policies = Vec::new();
+    let mut sources: HashMap<String, String> = HashMap::new();
+
+    for (source, document) in documents {
+        match (document.default, &root) {
+            (Some(_), Some((first, _, _))) => {
+                return Err(LoadError::Invalid(format!(
+                    "default block in both {} and {}",
+                    first, source
+                )));
+            }
+            (Some(default), None) => {
+                let excess = document.on_excess_duration.unwrap_or_default();
+                root = Some((source.clone(), default, excess));
+            }
+            (None, _) if document.on_excess_duration.is_some() => {
+                return Err(LoadError::Invalid(format!(
+                    "{} sets on_excess_duration without a default block",
+                    source
+                )));
+            }
+            (None, _) => {}
+        }
+
+        for policy in document.policies {
+            if let Some(first) = sources.insert(policy.name.clone(), source.clone()) {
+                return Err(LoadError::DuplicatePolicy {
+                    name: policy.name,
+                    first,
+                    second: source,
+                });
+            }
+            policies.push(policy);
+        }
+    }
+
+    let Some((_, default, on_excess_duration)) = root else {
+        return Err(LoadError::Invalid("no default block".to_string()));
+    };
+    let mut policy_file = PolicyFile {
+        default,
+        policies,
+        on_excess_duration,
+    };
+    interpolate(&mut policy_file, env)?;
+    check_templates(&policy_file)?;
+    Ok(policy_file)
+}
+
+/// Check principal templates, once variables are expanded.
+fn check_templates(policy_file: &PolicyFile) -> Result<(), LoadError> {
+    let policies = policy_file
+        .policies
+        .iter()
+        .map(|p| (&p.name, &p.principals));
+    let default = ("default".to_string(), &policy_file.default.principals);
+    for (name, principals) in policies.chain([(&default.0, default.1)]) {
+        for principal in principals {
+            check_principal(principal)
+                .map_err(|e| LoadError::Invalid(format!("policy {}: {}", name, e)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Expand variables in principals and match values.
+fn interpolate(policy_file: &mut PolicyFile, env: &Interpolation) -> Result<(), LoadError> {
+    let error = |place: &str, e: String| LoadError::Interpolation(format!("{}: {}", place, e));
+    expand_all(&mut policy_file.default.principals, env).map_err(|e| error("default", e))?;
+    for policy in &mut policy_file.policies {
+        let place = format!("policy {}", policy.name);
+        expand_all(&mut policy.principals, env).map_err(|e| error(&place, e))?;
+        expand_block(&mut policy.match_block, env).map_err(|e| error(&place, e))?;
+    }
+    Ok(())
+}
+
+fn expand_block(m: &mut MatchBlock, env: &Interpolation) -> Result<(), String> {
+    for values in [
+        &mut m.oidc_groups,
+        &mut m.emails,
+        &mut m.local_usernames,
+        &mut m.source_ip,
+        &mut m.hours,
+        &mut m.webauthn_ids,
+    ] {
+        expand_all(values, env)?;
+    }
+    for nested in m.any.iter_mut().chain(&mut m.all) {
+        expand_block(nested, env)?;
+    }
+    Ok(())
+}
+
+fn expand_all(values: &mut [String], env: &Interpolation) -> Result<(), String> {
+    for value in values {
+        *value = env.expand(value)?;
+    }
+    Ok(())
+}
+
+fn io_error(path: &Path, e: std::io::Error) -> LoadError {
+    LoadError::Io(format!("{}: {}", path.display(), e))
+}
+
+/// Where in a policy a load error is.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// The file, if the policy was read from disk.
+    pub file: Option<String>,
+    /// The field, such as `policies[3].match.oidc_groups`; empty for the
+    /// whole document.
+    pub path: String,
+    /// 1-based line, if known.
+    pub line: Option<usize>,
+    /// 1-based column, if known.
+    pub column: Option<usize>,
+}
+
+impl std::fmt::Display for SourceLocation {
+    /// `file:line:column: path`, leaving out what isn't known.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+        match (&self.file, self.line.zip(self.column)) {
+            (Some(file), Some((line, column))) => {
+                parts.push(format!("{}:{}:{}", file, line, column))
+            }
+            (Some(file), None) => parts.push(file.clone()),
+            (None, Some((line, column))) => parts.push(format!("line {} column {}", line, column)),
+            (None, None) => {}
+        }
+        if !self.path.is_empty() {
+            parts.push(self.path.clone());
+        }
+        write!(f, "{}", parts.join(": "))
+    }
 }
 
 #[derive(Debug)]
 pub enum LoadError {
     Io(String),
-    Parse(String),
+    /// Malformed YAML, TOML, or JSON, or a document that doesn't fit the
+    /// schema.
+    Parse {
+        at: SourceLocation,
+        message: String,
+    },
+    /// A key the schema doesn't have, often a typo. `at.path` ends with it.
+    UnknownKey {
+        at: SourceLocation,
+    },
+    /// An allow policy with no principals.
+    EmptyPrincipals {
+        at: SourceLocation,
+        policy: String,
+    },
+    /// An allow policy with no `max_duration`.
+    MissingMaxDuration {
+        at: SourceLocation,
+        policy: String,
+    },
+    /// Parsed, but not a usable policy.
+    Invalid(String),
+    /// A `${VAR}` reference could not be expanded.
+    Interpolation(String),
+    /// Two policies share a name. `first` and `second` are where they came
+    /// from, which may be the same file.
+    DuplicatePolicy {
+        name: String,
+        first: String,
+        second: String,
+    },
 }
 
 impl std::fmt::Display for LoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LoadError::Io(e) => write!(f, "IO error: {}", e),
-            LoadError::Parse(e) => write!(f, "Parse error: {}", e),
+            LoadError::Parse { at, message } => write!(f, "Parse error: {}{}", prefix(at), message),
+            LoadError::UnknownKey { at } => write!(f, "Unknown key: {}", at),
+            LoadError::EmptyPrincipals { at, policy } => write!(
+                f,
+                "Invalid policy: {}allow policy {} grants no principals",
+                prefix(at),
+                policy
+            ),
+            LoadError::MissingMaxDuration { at, policy } => write!(
+                f,
+                "Invalid policy: {}allow policy {} has no max_duration",
+                prefix(at),
+                policy
+            ),
+            LoadError::Invalid(e) => write!(f, "Invalid policy: {}", e),
+            LoadError::Interpolation(e) => write!(f, "Interpolation error: {}", e),
+            LoadError::DuplicatePolicy {
+                name,
+                first,
+                second,
+            } => write!(
+                f,
+                "Duplicate policy name {:?} in {} and {}",
+                name, first, second
+            ),
         }
     }
 }
 
 impl std::error::Error for LoadError {}
 
+/// `at` followed by `": "`, or nothing if `at` is empty.
+fn prefix(at: &SourceLocation) -> String {
+    match at.to_string() {
+        at if at.is_empty() => at,
+        at => format!("{}: ", at),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ast::Permissions;
 
     #[test]
     fn test_parse_minimal() {
@@ -74,4 +619,479 @@ policies:
         assert_eq!(policy.policies[0].name, "AdminAccess");
         assert_eq!(policy.policies[0].match_block.oidc_groups, vec!["admins"]);
     }
+
+    #[test]
+    fn test_parse_durations() {
+        let policy = |duration: &str| {
+            parse_policy(&format!(
+                "default:\n  principals: [\"sandbox\"]\n  max_duration: \"{}\"\n",
+                duration
+            ))
+        };
+        assert_eq!(policy("15m").unwrap().default.max_duration.seconds(), 900);
+        assert_eq!(
+            policy("1h30m").unwrap().default.max_duration.seconds(),
+            5400
+        );
+        assert_eq!(
+            policy("2d").unwrap().default.max_duration.seconds(),
+            172_800
+        );
+
+        for bad in [
+            "",
+            "15",
+            "m",
+            "15x",
+            "0m",
+            "1.5h",
+            "-5m",
+            "99999999999999999999s",
+        ] {
+            let err = policy(bad).unwrap_err().to_string();
+            assert!(err.contains("invalid duration"), "{}: {}", bad, err);
+        }
+    }
+
+    #[test]
+    fn test_parse_deny_policy() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Terminated"
+    effect: deny
+    match:
+      oidc_groups: ["terminated-employees"]
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        assert_eq!(policy.policies[0].effect, PolicyEffect::Deny);
+        assert!(policy.policies[0].max_duration.is_none());
+
+        // Allow policies still have to say what they grant
+        let allow = yaml.replace("    effect: deny\n", "");
+        let err = parse_policy(&allow).unwrap_err().to_string();
+        assert!(err.contains("grants no principals"), "{}", err);
+        let allow = allow.replace("    match:", "    principals: [\"root\"]\n    match:");
+        let err = parse_policy(&allow).unwrap_err().to_string();
+        assert!(err.contains("no max_duration"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_session_fields() {
+        let policy = |fields: &str| {
+            parse_policy(&format!(
+                "default:\n  principals: [\"sandbox\"]\n  max_duration: \"15m\"\n\
+                 policies:\n  - name: \"Ops\"\n    principals: [\"root\"]\n    \
+                 max_duration: \"1h\"\n{}",
+                fields
+            ))
+        };
+        let ops = policy("").unwrap().policies.remove(0);
+        assert_eq!(ops.permissions(), Permissions::default());
+        assert_eq!(ops.expires, None);
+
+        let ops =
+            policy("    port_forwarding: true\n    pty: false\n    expires: \"2024-02-29\"\n")
+                .unwrap()
+                .policies
+                .remove(0);
+        assert_eq!(
+            ops.permissions(),
+            Permissions {
+                port_forwarding: true,
+                agent_forwarding: false,
+                pty: false,
+            }
+        );
+        assert_eq!(ops.expires.unwrap().as_str(), "2024-02-29");
+
+        for bad in [
+            "2026-02-29",
+            "2026-13-01",
+            "2026-1-1",
+            "+026-01-01",
+            "tomorrow",
+        ] {
+            let err = policy(&format!("    expires: \"{}\"\n", bad))
+                .unwrap_err()
+                .to_string();
+            assert!(err.contains("invalid date"), "{}: {}", bad, err);
+        }
+    }
+
+    /// A fresh scratch directory holding `files`.
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gatebridge-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    const ROOT: &str = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+include: ["teams/infra.yaml", "teams/dev.yaml"]
+policies:
+  - name: "Root"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+
+    fn fragment(name: &str) -> String {
+        format!(
+            "policies:\n  - name: \"{}\"\n    principals: [\"readonly\"]\n    max_duration: \"15m\"\n",
+            name
+        )
+    }
+
+    fn names(policy: &PolicyFile) -> Vec<&str> {
+        policy.policies.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_includes() {
+        let infra = format!("include: [\"shared.yaml\"]\n{}", fragment("Infra"));
+        let dir = scratch(
+            "includes",
+            &[
+                ("root.yaml", ROOT),
+                ("teams/infra.yaml", &infra),
+                ("teams/shared.yaml", &fragment("Shared")),
+                ("teams/dev.yaml", &fragment("Dev")),
+            ],
+        );
+        let policy = load_policy_file(&dir.join("root.yaml")).unwrap();
+        assert_eq!(names(&policy), ["Root", "Infra", "Shared", "Dev"]);
+
+        // parse_policy has nowhere to include from
+        let err = parse_policy(ROOT).unwrap_err().to_string();
+        assert!(
+            err.contains("include needs a policy loaded from a file"),
+            "{}",
+            err
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_include_errors() {
+        let cycle = format!("include: [\"../root.yaml\"]\n{}", fragment("Infra"));
+        let dir = scratch(
+            "include-errors",
+            &[
+                ("root.yaml", ROOT),
+                ("teams/infra.yaml", &cycle),
+                ("teams/dev.yaml", &fragment("Root")),
+            ],
+        );
+        let err = load_policy_file(&dir.join("root.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(
+            err.contains("root.yaml is included more than once"),
+            "{}",
+            err
+        );
+
+        fs::write(dir.join("teams/infra.yaml"), fragment("Infra")).unwrap();
+        let err = load_policy_file(&dir.join("root.yaml")).unwrap_err();
+        assert!(matches!(
+            &err,
+            LoadError::DuplicatePolicy { name, first, second }
+                if name == "Root" && first.ends_with("root.yaml") && second.ends_with("dev.yaml")
+        ));
+
+        let second_root = format!(
+            "default:\n  principals: [\"sandbox\"]\n  max_duration: \"15m\"\n{}",
+            fragment("Dev")
+        );
+        fs::write(dir.join("teams/dev.yaml"), second_root).unwrap();
+        let err = load_policy_file(&dir.join("root.yaml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("default block in both"), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_policy_dir() {
+        let root = ROOT.replace("include: [\"teams/infra.yaml\", \"teams/dev.yaml\"]\n", "");
+        let dir = scratch(
+            "dir",
+            &[
+                ("b-root.yaml", &root),
+                ("a-infra.yml", &fragment("Infra")),
+                ("c-dev.yaml", &fragment("Dev")),
+                ("notes.txt", "not a policy"),
+            ],
+        );
+        let policy = load_policy_dir(&dir).unwrap();
+        assert_eq!(names(&policy), ["Infra", "Root", "Dev"]);
+        assert_eq!(names(&load_policy(&dir).unwrap()), names(&policy));
+
+        fs::remove_file(dir.join("b-root.yaml")).unwrap();
+        let err = load_policy_dir(&dir).unwrap_err().to_string();
+        assert!(err.contains("no default block"), "{}", err);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let yaml = r#"
+default:
+  principals: ["sandbox-${STAGE}"]
+  max_duration: "15m"
+policies:
+  - name: "Deploy"
+    match:
+      any:
+        - emails: ["*@${DOMAIN}"]
+    principals: ["deploy"]
+    max_duration: "60m"
+"#;
+        let vars = HashMap::from([
+            ("STAGE".to_string(), "prod".to_string()),
+            ("DOMAIN".to_string(), "example.com".to_string()),
+        ]);
+        let env = Interpolation::new().allow("STAGE").allow("DOMAIN");
+        let policy = parse_policy_with(yaml, &env.clone().vars(vars)).unwrap();
+        assert_eq!(policy.default.principals, vec!["sandbox-prod"]);
+        assert_eq!(
+            policy.policies[0].match_block.any[0].emails,
+            vec!["*@example.com"]
+        );
+
+        let err = parse_policy(yaml).unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "Interpolation error: default: ${STAGE} is not an allowed variable"
+        );
+        let strict = env.strict(true).vars(HashMap::new());
+        let err = parse_policy_with(yaml, &strict).unwrap_err().to_string();
+        assert_eq!(err, "Interpolation error: default: ${STAGE} is not set");
+    }
+
+    #[test]
+    fn test_principal_templates() {
+        let yaml = r#"
+default:
+  principals: ["${PREFIX}{email_local}"]
+  max_duration: "15m"
+"#;
+        let env = Interpolation::new()
+            .allow("PREFIX")
+            .vars(HashMap::from([("PREFIX".to_string(), "u-".to_string())]));
+        let policy = parse_policy_with(yaml, &env).unwrap();
+        assert_eq!(policy.default.principals, ["u-{email_local}"]);
+
+        let typo = yaml.replace("{email_local}", "{email_user}");
+        let err = parse_policy_with(&typo, &env).unwrap_err().to_string();
+        assert!(
+            err.starts_with("Invalid policy: policy default: unknown placeholder {email_user}"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_error_locations() {
+        let yaml = r#"default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "A"
+    match:
+      oidc_groups: "admins"
+"#;
+        let err = parse_policy(yaml).unwrap_err();
+        let LoadError::Parse { at, message } = &err else {
+            panic!("{:?}", err);
+        };
+        assert_eq!(at.path, "policies[0].match.oidc_groups");
+        assert_eq!((at.line, at.column), (Some(7), Some(20)));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Parse error: line 7 column 20: policies[0].match.oidc_groups: {}",
+                message
+            )
+        );
+        assert!(message.starts_with("invalid type: string"), "{}", message);
+
+        let typo = yaml.replace("oidc_groups: \"admins\"", "oidc_group: [\"admins\"]");
+        let err = parse_policy(&typo).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown key: policies[0].match.oidc_group");
+
+        let dir = scratch("locations", &[("policy.yaml", &typo)]);
+        let err = load_policy_file(&dir.join("policy.yaml")).unwrap_err();
+        let LoadError::UnknownKey { at } = err else {
+            panic!("{:?}", err);
+        };
+        assert!(at.file.unwrap().ends_with("policy.yaml"));
+        fs::remove_dir_all(&dir).unwrap();
+
+        let empty = yaml.replace(
+            "oidc_groups: \"admins\"",
+            "oidc_groups: [\"admins\"]\n    principals: []\n    max_duration: \"1h\"",
+        );
+        let err = parse_policy(&empty).unwrap_err();
+        assert!(
+            matches!(&err, LoadError::EmptyPrincipals { at, policy } if at.path == "policies[0].principals" && policy == "A"),
+            "{:?}",
+            err
+        );
+    }
+
+    const TOML: &str = r#"
+[default]
+principals = ["sandbox"]
+max_duration = "15m"
+
+[[policies]]
+name = "AdminAccess"
+principals = ["root", "admin"]
+max_duration = "60m"
+match.oidc_groups = ["infrastructure", "security-team"]
+match.emails = ["*@admin.example.com"]
+
+[[policies]]
+name = "DeveloperAccess"
+principals = ["developer"]
+max_duration = "30m"
+
+[policies.match]
+oidc_groups = ["developers"]
+source_ip = ["10.0.0.0/8"]
+hours = ["09:00-18:00"]
+
+[[policies]]
+name = "ContractorAccess"
+principals = ["readonly"]
+max_duration = "15m"
+match = { emails = ["*@contractor.example.com"], webauthn_ids = ["yubi-*"] }
+
+[[policies]]
+name = "TerminatedEmployees"
+effect = "deny"
+match.oidc_groups = ["terminated-employees"]
+"#;
+
+    #[test]
+    fn test_formats() {
+        let yaml = include_str!("../example_policy.yaml");
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml).unwrap();
+        let json = serde_json::to_string_pretty(&value).unwrap();
+        let expected = serde_json::to_value(parse_policy(yaml).unwrap()).unwrap();
+        for (text, format) in [(TOML, PolicyFormat::Toml), (&json, PolicyFormat::Json)] {
+            let policy = parse_policy_as(text, format).unwrap();
+            assert_eq!(
+                serde_json::to_value(policy).unwrap(),
+                expected,
+                "{:?}",
+                format
+            );
+        }
+
+        assert_eq!("yml".parse(), Ok(PolicyFormat::Yaml));
+        assert_eq!("toml".parse(), Ok(PolicyFormat::Toml));
+        assert!("ini".parse::<PolicyFormat>().is_err());
+        let format = |path: &str| PolicyFormat::from_path(Path::new(path));
+        assert_eq!(format("teams/dev.json"), Some(PolicyFormat::Json));
+        assert_eq!(format("policy.toml"), Some(PolicyFormat::Toml));
+        assert_eq!(format("policy.conf"), None);
+        assert_eq!(format("policy"), None);
+    }
+
+    #[test]
+    fn test_mixed_formats() {
+        let default = "[default]\nprincipals = [\"sandbox\"]\nmax_duration = \"15m\"\n";
+        let root = format!("include = [\"teams/infra.json\"]\n\n{}", default);
+        let infra = r#"{"include": ["dev.yaml"], "policies": [{"name": "Infra", "principals": ["ops"], "max_duration": "1h"}]}"#;
+        let dir = scratch(
+            "formats",
+            &[
+                ("root.toml", &root),
+                ("teams/infra.json", infra),
+                ("teams/dev.yaml", &fragment("Dev")),
+                ("policy.conf", default),
+            ],
+        );
+        let policy = load_policy_file(&dir.join("root.toml")).unwrap();
+        assert_eq!(names(&policy), ["Infra", "Dev"]);
+        assert_eq!(policy.default.principals, ["sandbox"]);
+
+        // Unknown extensions are YAML unless told otherwise
+        let conf = dir.join("policy.conf");
+        let err = load_policy_file(&conf).unwrap_err();
+        assert!(matches!(err, LoadError::Parse { .. }), "{:?}", err);
+        let policy = load_policy_file_as(&conf, PolicyFormat::Toml).unwrap();
+        assert_eq!(policy.default.principals, ["sandbox"]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let dir = scratch(
+            "formats-dir",
+            &[
+                ("a-root.toml", default),
+                ("b-infra.json", &infra.replace("\"dev.yaml\"", "")),
+                ("c-dev.yml", &fragment("Dev")),
+            ],
+        );
+        assert_eq!(names(&load_policy_dir(&dir).unwrap()), ["Infra", "Dev"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_format_error_locations() {
+        let toml = TOML.replace(
+            "oidc_groups = [\"developers\"]",
+            "oidc_groups = \"developers\"",
+        );
+        let err = parse_policy_as(&toml, PolicyFormat::Toml).unwrap_err();
+        let LoadError::Parse { at, message } = &err else {
+            panic!("{:?}", err);
+        };
+        assert_eq!(at.path, "policies[1].match.oidc_groups");
+        assert_eq!((at.line, at.column), (Some(19), Some(15)));
+        assert!(message.starts_with("invalid type: string"), "{}", message);
+
+        let typo = TOML.replace("match.emails", "match.email");
+        let err = parse_policy_as(&typo, PolicyFormat::Toml).unwrap_err();
+        assert_eq!(err.to_string(), "Unknown key: policies[0].match.email");
+
+        let json = r#"{
+  "default": {"principals": ["sandbox"], "max_duration": "15m"},
+  "policies": [{"name": "A", "match": {"hours": 9}}]
+}"#;
+        let err = parse_policy_as(json, PolicyFormat::Json).unwrap_err();
+        let LoadError::Parse { at, message } = &err else {
+            panic!("{:?}", err);
+        };
+        assert_eq!(at.path, "policies[0].match.hours");
+        assert_eq!((at.line, at.column), (Some(3), Some(49)));
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Parse error: line 3 column 49: policies[0].match.hours: {}",
+                message
+            )
+        );
+        assert!(!message.contains(" at line "), "{}", message);
+
+        let trailing = format!("{} {{}}", json.replace("{\"hours\": 9}", "{}"));
+        let err = parse_policy_as(&trailing, PolicyFormat::Json).unwrap_err();
+        assert!(
+            matches!(&err, LoadError::Parse { at, .. } if at.path.is_empty() && at.line == Some(4)),
+            "{:?}",
+            err
+        );
+    }
 }
@@ -1,15 +1,39 @@
 //! GateBridge CLI
 //!
 //! Commands:
-//!   validate   - Check policy file syntax
-//!   translate  - Convert to Gate0 (outputs Rust code)
+//!   check      - Check policy file syntax and translation (alias: validate)
+//!   lint       - Report policies that load but look like mistakes
+//!   translate  - Convert to Gate0 and summarize the rules, or emit Rust
+//!   eval       - Decide a request
 //!   shadow     - Run dual evaluation and compare
 //!   explain    - Show step-by-step evaluation for debugging
+//!   fuzz       - Run the differential fuzzer
+//!   replay     - Rerun saved fuzz cases
+//!   corpus     - Run a directory of conformance fixtures
+//!   serve      - Run an HTTP policy decision point
+//!   schema     - Print the JSON Schema for policy files
 
 use std::env;
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
+use std::net::TcpListener;
 use std::path::Path;
 use std::process::ExitCode;
+use std::sync::Arc;
+
+/// Address `serve` listens on unless given one.
+const DEFAULT_ADDR: &str = "127.0.0.1:8181";
+
+/// Cases `fuzz` runs unless given a count.
+const DEFAULT_FUZZ_ITERATIONS: u64 = 1000;
+
+/// Directory `fuzz` saves failing cases to unless given one.
+const DEFAULT_FUZZ_DIR: &str = "fuzz_failures";
+
+/// Comma-separated variables policies may reference as `${VAR}`.
+const ALLOW_ENV_VAR: &str = "GATEBRIDGE_ALLOW_ENV";
+
+/// Set to `1` to fail on allowed variables that are not set.
+const STRICT_ENV_VAR: &str = "GATEBRIDGE_STRICT_ENV";
 
 fn main() -> ExitCode {
     let args: Vec<String> = env::args().collect();
@@ -20,34 +44,164 @@ fn main() -> ExitCode {
     }
 
     match args[1].as_str() {
-        "validate" => {
+        "check" | "validate" => {
             if args.len() < 3 {
-                eprintln!("Usage: gatebridge validate <policy.yaml>");
+                eprintln!("Usage: gatebridge check <policy.yaml>");
                 return ExitCode::from(2);
             }
-            cmd_validate(&args[2])
+            cmd_check(&args[2])
         }
-        "translate" => {
+        "lint" => {
             if args.len() < 3 {
-                eprintln!("Usage: gatebridge translate <policy.yaml>");
+                eprintln!("Usage: gatebridge lint <policy.yaml>");
                 return ExitCode::from(2);
             }
-            cmd_translate(&args[2])
+            cmd_lint(&args[2])
         }
-        "shadow" => {
-            if args.len() < 4 {
-                eprintln!("Usage: gatebridge shadow <policy.yaml> <request.json | ->");
+        "translate" => {
+            const USAGE: &str = "Usage: gatebridge translate <policy.yaml> [--rust | --map]";
+            let output = match args.get(3).map(String::as_str) {
+                None => TranslateOutput::Summary,
+                Some("--rust") => TranslateOutput::Rust,
+                Some("--map") => TranslateOutput::Map,
+                Some(_) => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
+            if args.len() < 3 || args.len() > 4 {
+                eprintln!("{}", USAGE);
                 return ExitCode::from(2);
             }
-            cmd_shadow(&args[2], &args[3])
+            cmd_translate(&args[2], output)
+        }
+        "eval" | "shadow" => {
+            let jsonl = match args.get(4).map(String::as_str) {
+                None => args.get(3).is_some_and(|source| source.ends_with(".jsonl")),
+                Some("--jsonl") if args.len() == 5 => true,
+                Some(_) => false,
+            };
+            let usage = args.len() < 4 || (args.len() > 4 && !jsonl);
+            match (args[1].as_str(), usage) {
+                (command, true) => {
+                    eprintln!(
+                        "Usage: gatebridge {} <policy.yaml> <request.json | requests.jsonl | -> \
+                         [--jsonl]",
+                        command
+                    );
+                    ExitCode::from(2)
+                }
+                ("eval", _) if jsonl => cmd_eval_many(&args[2], &args[3]),
+                ("eval", _) => cmd_eval(&args[2], &args[3]),
+                (_, _) if jsonl => cmd_shadow_many(&args[2], &args[3]),
+                (_, _) => cmd_shadow(&args[2], &args[3]),
+            }
         }
         "explain" => {
+            const USAGE: &str = "Usage: gatebridge explain <policy.yaml> <request.json | -> \
+                                 [--format text|json|markdown|compact]";
+            let format = match args.get(4..).unwrap_or_default() {
+                [] => gatebridge::ExplainFormat::default(),
+                [flag, format] if flag == "--format" => match format.parse() {
+                    Ok(f) => f,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return ExitCode::from(2);
+                    }
+                },
+                _ => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
             if args.len() < 4 {
-                eprintln!("Usage: gatebridge explain <policy.yaml> <request.json>");
+                eprintln!("{}", USAGE);
                 return ExitCode::from(2);
             }
-            cmd_explain(&args[2], &args[3])
+            cmd_explain(&args[2], &args[3], format)
+        }
+        "fuzz" => {
+            const USAGE: &str = "Usage: gatebridge fuzz [iterations] [seed] [out_dir] \
+                                 [--workers N] [--config fuzz.yaml] [--reference PROGRAM]";
+            let mut args = args[2..].to_vec();
+            let (workers, config, reference) = match (
+                take_option(&mut args, "--workers"),
+                take_option(&mut args, "--config"),
+                take_option(&mut args, "--reference"),
+            ) {
+                (Ok(workers), Ok(config), Ok(reference)) => (workers, config, reference),
+                _ => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
+            let workers = match workers.map(|n| n.parse()) {
+                None => 1,
+                Some(Ok(n)) if n > 0 => n,
+                Some(_) => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
+            let iterations = match args.first().map(|n| n.parse()) {
+                None => DEFAULT_FUZZ_ITERATIONS,
+                Some(Ok(n)) => n,
+                Some(Err(_)) => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
+            let seed = match args.get(1).map(|n| n.parse()) {
+                None => 0,
+                Some(Ok(n)) => n,
+                Some(Err(_)) => {
+                    eprintln!("{}", USAGE);
+                    return ExitCode::from(2);
+                }
+            };
+            let out_dir = args.get(2).map_or(DEFAULT_FUZZ_DIR, String::as_str);
+            let options = FuzzOptions {
+                workers,
+                config: config.as_deref(),
+                reference: reference.as_deref(),
+            };
+            cmd_fuzz(iterations, seed, out_dir, options)
         }
+        "replay" => {
+            let mut args = args[2..].to_vec();
+            let Ok(reference) = take_option(&mut args, "--reference") else {
+                eprintln!("Usage: gatebridge replay [dir...] [--reference PROGRAM]");
+                return ExitCode::from(2);
+            };
+            let dirs: Vec<&str> = args.iter().map(String::as_str).collect();
+            if dirs.is_empty() {
+                cmd_replay(&[DEFAULT_FUZZ_DIR], reference.as_deref())
+            } else {
+                cmd_replay(&dirs, reference.as_deref())
+            }
+        }
+        "corpus" => {
+            if args.len() < 3 {
+                eprintln!("Usage: gatebridge corpus <dir> [policy.yaml]");
+                return ExitCode::from(2);
+            }
+            cmd_corpus(&args[2], args.get(3).map(String::as_str))
+        }
+        "serve" => {
+            let mut args = args[2..].to_vec();
+            let allow_reload = args.iter().any(|a| a == "--allow-reload");
+            args.retain(|a| a != "--allow-reload");
+            if args.is_empty() || args.len() > 2 {
+                eprintln!("Usage: gatebridge serve <policy.yaml> [addr] [--allow-reload]");
+                return ExitCode::from(2);
+            }
+            cmd_serve(
+                &args[0],
+                args.get(1).map_or(DEFAULT_ADDR, String::as_str),
+                allow_reload,
+            )
+        }
+        "schema" => cmd_schema(),
         "help" | "--help" | "-h" => {
             print_usage();
             ExitCode::SUCCESS
@@ -64,24 +218,88 @@ fn print_usage() {
     eprintln!("GateBridge - Policy translator for Gate0");
     eprintln!();
     eprintln!("Usage:");
-    eprintln!("  gatebridge validate <policy.yaml>              Check policy syntax");
-    eprintln!("  gatebridge translate <policy.yaml>             Convert to Gate0");
+    eprintln!(
+        "  gatebridge check <policy.yaml>                 Check policy syntax and translation"
+    );
+    eprintln!("  gatebridge lint <policy.yaml>                  Report likely mistakes");
+    eprintln!("  gatebridge translate <policy.yaml> [--rust | --map]");
+    eprintln!("                                                 Convert to Gate0");
+    eprintln!(
+        "                                                 (--rust: print a static_policy! module)"
+    );
+    eprintln!(
+        "                                                 (--map: print the reason code map as JSON)"
+    );
+    eprintln!("  gatebridge eval <policy.yaml> <request.json>   Decide a request");
     eprintln!("  gatebridge shadow <policy.yaml> <request.json> Dual evaluation");
-    eprintln!("  gatebridge shadow <policy.yaml> -              Read request from stdin");
+    eprintln!(
+        "                                                 (eval, shadow: requests.jsonl or --jsonl)"
+    );
+    eprintln!("                                                 (for one request per line)");
     eprintln!("  gatebridge explain <policy.yaml> <request.json> Debug evaluation");
+    eprintln!(
+        "                                                 (--format text|json|markdown|compact)"
+    );
+    eprintln!("                                                 (request.json may be - for stdin)");
+    eprintln!("                                                 (policy.yaml may be a directory)");
+    eprintln!("  gatebridge fuzz [iterations] [seed] [out_dir]  Differential fuzzing");
+    eprintln!(
+        "                                                 (default {} cases, seed 0, {}/)",
+        DEFAULT_FUZZ_ITERATIONS, DEFAULT_FUZZ_DIR
+    );
+    eprintln!("                                                 (--workers N: run on N threads)");
+    eprintln!("                                                 (--config: generation settings)");
+    eprintln!(
+        "                                                 (--reference: also compare a subprocess)"
+    );
+    eprintln!("  gatebridge replay [dir...]                     Rerun saved fuzz cases");
+    eprintln!(
+        "                                                 (default {}/)",
+        DEFAULT_FUZZ_DIR
+    );
+    eprintln!(
+        "                                                 (--reference: also compare a subprocess)"
+    );
+    eprintln!("  gatebridge corpus <dir> [policy.yaml]          Run conformance fixtures");
+    eprintln!(
+        "                                                 (policy.yaml: for fixtures without one)"
+    );
+    eprintln!("  gatebridge serve <policy.yaml> [addr]          HTTP decision point");
+    eprintln!(
+        "                                                 (default addr {})",
+        DEFAULT_ADDR
+    );
+    eprintln!(
+        "                                                 (--allow-reload: accept POST /v1/policy)"
+    );
+    eprintln!("  gatebridge schema                              Print the policy JSON Schema");
     eprintln!("  gatebridge help                                Show this message");
     eprintln!();
+    eprintln!("Environment:");
+    eprintln!(
+        "  {}=VAR,...   Variables policies may reference as ${{VAR}}",
+        ALLOW_ENV_VAR
+    );
+    eprintln!(
+        "  {}=1        Fail on allowed variables that are not set",
+        STRICT_ENV_VAR
+    );
+    eprintln!();
     eprintln!("Exit codes:");
-    eprintln!("  0 = success (shadow: decisions match)");
-    eprintln!("  1 = mismatch (shadow: decisions differ)");
+    eprintln!("  0 = success (shadow, fuzz, replay, corpus: decisions match; lint: no warnings)");
+    eprintln!("  1 = mismatch (shadow, fuzz, replay, corpus: decisions differ; lint: warnings)");
     eprintln!("  2 = error");
 }
 
-fn cmd_validate(path: &str) -> ExitCode {
+fn cmd_check(path: &str) -> ExitCode {
     let path = Path::new(path);
-    
-    match gatebridge::load_policy_file(path) {
+
+    match gatebridge::load_policy_with(path, &interpolation()) {
         Ok(policy) => {
+            if let Err(e) = gatebridge::to_gate0(&policy) {
+                eprintln!("Translation failed: {}", e);
+                return ExitCode::from(2);
+            }
             println!("Policy valid.");
             println!("  Default principals: {:?}", policy.default.principals);
             println!("  Policy count: {}", policy.policies.len());
@@ -97,10 +315,41 @@ fn cmd_validate(path: &str) -> ExitCode {
     }
 }
 
-fn cmd_translate(path: &str) -> ExitCode {
+fn cmd_lint(path: &str) -> ExitCode {
+    let policy = match gatebridge::load_policy_with(Path::new(path), &interpolation()) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Validation failed: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let warnings = gatebridge::lint(&policy);
+    if warnings.is_empty() {
+        println!("No warnings.");
+        return ExitCode::SUCCESS;
+    }
+    for warning in &warnings {
+        println!("warning: {}", warning);
+    }
+    println!("{} warning(s)", warnings.len());
+    ExitCode::from(1)
+}
+
+/// What `translate` prints.
+enum TranslateOutput {
+    /// The rule count and reason code mapping.
+    Summary,
+    /// A Rust module declaring the policy with `gate0::static_policy!`.
+    Rust,
+    /// The `TranslationMap` as JSON.
+    Map,
+}
+
+fn cmd_translate(path: &str, output: TranslateOutput) -> ExitCode {
     let path = Path::new(path);
-    
-    let policy_file = match gatebridge::load_policy_file(path) {
+
+    let policy_file = match gatebridge::load_policy_with(path, &interpolation()) {
         Ok(p) => p,
         Err(e) => {
             eprintln!("Failed to load: {}", e);
@@ -108,17 +357,34 @@ fn cmd_translate(path: &str) -> ExitCode {
         }
     };
 
+    if let TranslateOutput::Rust = output {
+        return match gatebridge::to_rust_source(&policy_file) {
+            Ok(source) => {
+                print!("{}", source);
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("Translation failed: {}", e);
+                ExitCode::from(2)
+            }
+        };
+    }
+
     match gatebridge::to_gate0(&policy_file) {
-        Ok(gate0_policy) => {
+        Ok((_, map)) if matches!(output, TranslateOutput::Map) => {
+            println!("{}", serde_json::to_string_pretty(&map).unwrap());
+            ExitCode::SUCCESS
+        }
+        Ok((gate0_policy, map)) => {
             println!("Translation successful.");
             println!("Gate0 rule count: {}", gate0_policy.rule_count());
             println!();
             println!("// Generated Gate0 policy");
             println!("// ReasonCode mapping:");
-            for (i, p) in policy_file.policies.iter().enumerate() {
-                println!("//   ReasonCode({}) -> {}", i, p.name);
+            for p in &map.policies {
+                println!("//   ReasonCode({}) -> {}", p.reason_code, p.name);
             }
-            println!("//   ReasonCode({}) -> default", u32::MAX - 1);
+            println!("//   ReasonCode({}) -> default", map.default_reason_code);
             ExitCode::SUCCESS
         }
         Err(e) => {
@@ -130,7 +396,7 @@ fn cmd_translate(path: &str) -> ExitCode {
 
 fn cmd_shadow(policy_path: &str, request_source: &str) -> ExitCode {
     // Load policy
-    let policy_file = match gatebridge::load_policy_file(Path::new(policy_path)) {
+    let policy_file = match gatebridge::load_policy_with(Path::new(policy_path), &interpolation()) {
         Ok(p) => p,
         Err(e) => {
             eprintln!("{{\"error\": \"Failed to load policy: {}\"}}", e);
@@ -139,28 +405,10 @@ fn cmd_shadow(policy_path: &str, request_source: &str) -> ExitCode {
     };
 
     // Load request (from file or stdin)
-    let request_json = if request_source == "-" {
-        let mut buffer = String::new();
-        if let Err(e) = io::stdin().read_to_string(&mut buffer) {
-            eprintln!("{{\"error\": \"Failed to read stdin: {}\"}}", e);
-            return ExitCode::from(2);
-        }
-        buffer
-    } else {
-        match std::fs::read_to_string(request_source) {
-            Ok(s) => s,
-            Err(e) => {
-                eprintln!("{{\"error\": \"Failed to read request file: {}\"}}", e);
-                return ExitCode::from(2);
-            }
-        }
-    };
-
-    // Parse request
-    let request: gatebridge::EvalRequest = match serde_json::from_str(&request_json) {
+    let request = match read_request(request_source) {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("{{\"error\": \"Failed to parse request JSON: {}\"}}", e);
+            eprintln!("{{\"error\": \"{}\"}}", e);
             return ExitCode::from(2);
         }
     };
@@ -170,7 +418,7 @@ fn cmd_shadow(policy_path: &str, request_source: &str) -> ExitCode {
         Ok(result) => {
             let json = serde_json::to_string_pretty(&result).unwrap();
             println!("{}", json);
-            
+
             if result.decisions_match {
                 ExitCode::SUCCESS
             } else {
@@ -184,37 +432,372 @@ fn cmd_shadow(policy_path: &str, request_source: &str) -> ExitCode {
     }
 }
 
-fn cmd_explain(policy_path: &str, request_path: &str) -> ExitCode {
-    // Load policy
-    let policy_file = match gatebridge::load_policy_file(Path::new(policy_path)) {
+/// Shadow-evaluate every request in a JSONL batch and print the report.
+fn cmd_shadow_many(policy_path: &str, request_source: &str) -> ExitCode {
+    let policy_file = match gatebridge::load_policy_with(Path::new(policy_path), &interpolation()) {
         Ok(p) => p,
         Err(e) => {
             eprintln!("Failed to load policy: {}", e);
             return ExitCode::from(2);
         }
     };
+    let requests = match read_requests(request_source) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
 
-    // Load request
-    let request_json = match std::fs::read_to_string(request_path) {
-        Ok(s) => s,
+    match gatebridge::shadow_evaluate_many(&policy_file, &requests) {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            if report.passed() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::from(1)
+            }
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn cmd_eval(policy_path: &str, request_source: &str) -> ExitCode {
+    let policy_file = match gatebridge::load_policy_with(Path::new(policy_path), &interpolation()) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to load policy: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let request = match read_request(request_source) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    match gatebridge::decide(&policy_file, &request) {
+        Ok(decision) => {
+            println!("{}", serde_json::to_string_pretty(&decision).unwrap());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Decide every request in a JSONL batch, printing one decision per line,
+/// or `{"error": ...}` for a request that could not be decided.
+fn cmd_eval_many(policy_path: &str, request_source: &str) -> ExitCode {
+    let policy_file = match gatebridge::load_policy_with(Path::new(policy_path), &interpolation()) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to load policy: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let requests = match read_requests(request_source) {
+        Ok(r) => r,
         Err(e) => {
-            eprintln!("Failed to read request file: {}", e);
+            eprintln!("{}", e);
             return ExitCode::from(2);
         }
     };
 
-    let request: gatebridge::EvalRequest = match serde_json::from_str(&request_json) {
+    let decisions = match gatebridge::decide_many(&policy_file, &requests) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+    let mut failed = false;
+    let mut out = io::stdout().lock();
+    for decision in decisions {
+        let line = match decision {
+            Ok(decision) => serde_json::to_string(&decision).unwrap(),
+            Err(e) => {
+                failed = true;
+                serde_json::json!({ "error": e.to_string() }).to_string()
+            }
+        };
+        if writeln!(out, "{}", line).is_err() {
+            return ExitCode::from(2);
+        }
+    }
+
+    if failed {
+        ExitCode::from(2)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn cmd_explain(
+    policy_path: &str,
+    request_path: &str,
+    format: gatebridge::ExplainFormat,
+) -> ExitCode {
+    // Load policy
+    let policy_file = match gatebridge::load_policy_with(Path::new(policy_path), &interpolation()) {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("Failed to load policy: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    // Load request
+    let request = match read_request(request_path) {
         Ok(r) => r,
         Err(e) => {
-            eprintln!("Failed to parse request JSON: {}", e);
+            eprintln!("{}", e);
             return ExitCode::from(2);
         }
     };
 
     // Run explain
     let result = gatebridge::explain(&policy_file, &request);
-    let output = gatebridge::format_explain(&result);
+    let output = gatebridge::format_explain(&result, format);
     println!("{}", output);
 
     ExitCode::SUCCESS
 }
+
+/// Options of `gatebridge fuzz` beyond its positional arguments.
+struct FuzzOptions<'a> {
+    workers: usize,
+    config: Option<&'a str>,
+    reference: Option<&'a str>,
+}
+
+fn cmd_fuzz(iterations: u64, seed: u64, out_dir: &str, options: FuzzOptions<'_>) -> ExitCode {
+    let workers = options.workers;
+    let config = match options.config.map(read_fuzz_config) {
+        None => gatebridge::FuzzConfig::default(),
+        Some(Ok(config)) => config,
+        Some(Err(e)) => {
+            eprintln!("{}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let out_dir = Some(Path::new(out_dir));
+    let command = options.reference.map(gatebridge::CommandReference::new);
+    let references = references(command.as_ref());
+    let report =
+        match gatebridge::run_fuzz_with(&references, &config, iterations, seed, workers, out_dir) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Failed to save failing case: {}", e);
+                return ExitCode::from(2);
+            }
+        };
+
+    println!(
+        "Ran {} cases (seed {}, {} workers): {} mismatches, {} errors",
+        report.iterations, seed, workers, report.mismatches, report.errors
+    );
+    for case in &report.failures {
+        println!("  {}", case.display());
+    }
+
+    if report.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+fn cmd_replay(dirs: &[&str], reference: Option<&str>) -> ExitCode {
+    let command = reference.map(gatebridge::CommandReference::new);
+    let references = references(command.as_ref());
+    let mut passed = true;
+    for dir in dirs {
+        let report = match gatebridge::run_replay_with(&references, Path::new(dir)) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Failed to read {}: {}", dir, e);
+                return ExitCode::from(2);
+            }
+        };
+        println!(
+            "Replayed {} cases from {}: {} mismatches, {} errors",
+            report.cases, dir, report.mismatches, report.errors
+        );
+        for case in &report.failures {
+            println!("  {}", case.display());
+        }
+        passed &= report.passed();
+    }
+
+    if passed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+fn cmd_corpus(dir: &str, policy_path: Option<&str>) -> ExitCode {
+    let policy = match policy_path {
+        None => None,
+        Some(path) => match gatebridge::load_policy_with(Path::new(path), &interpolation()) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                eprintln!("Failed to load policy: {}", e);
+                return ExitCode::from(2);
+            }
+        },
+    };
+
+    let report = match gatebridge::run_corpus(Path::new(dir), policy.as_ref()) {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Failed to read corpus: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    for case in &report.cases {
+        let status = if case.passed() { "PASS" } else { "FAIL" };
+        match &case.description {
+            Some(description) => println!("{} {}: {}", status, case.path.display(), description),
+            None => println!("{} {}", status, case.path.display()),
+        }
+        for failure in &case.failures {
+            println!("    {}", failure);
+        }
+    }
+    let failed = report.failed().count();
+    println!(
+        "{} fixtures: {} passed, {} failed",
+        report.cases.len(),
+        report.cases.len() - failed,
+        failed
+    );
+
+    if report.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    }
+}
+
+fn cmd_serve(policy_path: &str, addr: &str, allow_reload: bool) -> ExitCode {
+    let server = match gatebridge::Server::new(policy_path, interpolation()) {
+        Ok(s) => s.allow_reload(allow_reload),
+        Err(e) => {
+            eprintln!("Failed to load policy: {}", e);
+            return ExitCode::from(2);
+        }
+    };
+
+    let listener = match TcpListener::bind(addr) {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to listen on {}: {}", addr, e);
+            return ExitCode::from(2);
+        }
+    };
+
+    eprintln!("Serving {} on http://{}", policy_path, addr);
+    match Arc::new(server).serve(listener) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Server failed: {}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn cmd_schema() -> ExitCode {
+    let schema = gatebridge::policy_schema();
+    println!("{}", serde_json::to_string_pretty(&schema).unwrap());
+    ExitCode::SUCCESS
+}
+
+/// The variables policies may reference, from `ALLOW_ENV_VAR` and
+/// `STRICT_ENV_VAR`.
+fn interpolation() -> gatebridge::Interpolation {
+    let allowed = env::var(ALLOW_ENV_VAR).unwrap_or_default();
+    allowed
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .fold(gatebridge::Interpolation::new(), |env, name| {
+            env.allow(name)
+        })
+        .strict(env::var(STRICT_ENV_VAR).is_ok_and(|v| v == "1"))
+}
+
+/// Read and parse a request from a file, or from stdin if `source` is `-`.
+/// Remove `--flag value` from `args`, returning the value. Fails if the
+/// flag has no value.
+/// The Rust reference evaluator, and `command` if given, for a three-way
+/// comparison.
+fn references(
+    command: Option<&gatebridge::CommandReference>,
+) -> Vec<&dyn gatebridge::ReferenceEvaluator> {
+    let mut references: Vec<&dyn gatebridge::ReferenceEvaluator> = vec![&gatebridge::RustReference];
+    references.extend(command.map(|c| c as &dyn gatebridge::ReferenceEvaluator));
+    references
+}
+
+fn take_option(args: &mut Vec<String>, flag: &str) -> Result<Option<String>, ()> {
+    match args.iter().position(|a| a == flag) {
+        None => Ok(None),
+        Some(i) if i + 1 < args.len() => Ok(args.drain(i..=i + 1).nth(1)),
+        Some(_) => Err(()),
+    }
+}
+
+fn read_fuzz_config(path: &str) -> Result<gatebridge::FuzzConfig, String> {
+    let yaml =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read fuzz config: {}", e))?;
+    let config: gatebridge::FuzzConfig =
+        serde_yaml::from_str(&yaml).map_err(|e| format!("Failed to parse fuzz config: {}", e))?;
+    config
+        .validate()
+        .map_err(|e| format!("Invalid fuzz config: {}", e))?;
+    Ok(config)
+}
+
+fn read_request(source: &str) -> Result<gatebridge::EvalRequest, String> {
+    let json = read_source(source)?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse request JSON: {}", e))
+}
+
+/// Requests from JSONL, one JSON object per line. Blank lines are skipped.
+fn read_requests(source: &str) -> Result<Vec<gatebridge::EvalRequest>, String> {
+    let jsonl = read_source(source)?;
+    jsonl
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| format!("Failed to parse request on line {}: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// The contents of a request file, or of stdin for `-`.
+fn read_source(source: &str) -> Result<String, String> {
+    if source == "-" {
+        let mut buffer = String::new();
+        io::stdin()
+            .read_to_string(&mut buffer)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        Ok(buffer)
+    } else {
+        std::fs::read_to_string(source).map_err(|e| format!("Failed to read request file: {}", e))
+    }
+}
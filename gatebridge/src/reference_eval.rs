@@ -1,27 +1,212 @@
 //! Reference policy evaluator. Correctness-first, not optimized.
+//!
+//! Shadow evaluation and fuzzing compare Gate0 against a
+//! `ReferenceEvaluator`: `RustReference`, this module's evaluator, by
+//! default, or another implementation, such as upstream Ephemera or an
+//! older build of this one, run as a subprocess by `CommandReference`.
 
-use crate::ast::{EvalRequest, EvalResult, MatchBlock, Policy, PolicyFile};
+use crate::ast::{Date, EvalRequest, EvalResult, MatchBlock, Policy, PolicyEffect, PolicyFile};
+use crate::template::resolve_principals;
+use serde::Serialize;
+use std::ffi::OsString;
+use std::io::Write;
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// An evaluator Gate0's decisions are checked against.
+pub trait ReferenceEvaluator: Send + Sync {
+    /// Name to tell this reference apart in reports.
+    fn name(&self) -> &str;
+
+    /// Evaluate a request against a policy file, as `evaluate` does.
+    fn evaluate(
+        &self,
+        policy_file: &PolicyFile,
+        request: &EvalRequest,
+    ) -> Result<EvalResult, String>;
+}
+
+/// The reference evaluator in this module.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RustReference;
+
+impl ReferenceEvaluator for RustReference {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn evaluate(
+        &self,
+        policy_file: &PolicyFile,
+        request: &EvalRequest,
+    ) -> Result<EvalResult, String> {
+        Ok(evaluate(policy_file, request))
+    }
+}
+
+/// A reference evaluator run as a subprocess, once per request.
+///
+/// The command reads `{"policy": ..., "request": ...}` as JSON on stdin,
+/// the policy file and request in gatebridge's own format, and writes the
+/// `EvalResult` as JSON to stdout. A nonzero exit status or output that
+/// does not parse is an error.
+#[derive(Debug, Clone)]
+pub struct CommandReference {
+    name: String,
+    program: OsString,
+    args: Vec<OsString>,
+}
+
+impl CommandReference {
+    /// Run `program`, named after its file name.
+    pub fn new(program: impl Into<OsString>) -> Self {
+        let program = program.into();
+        let name = Path::new(&program)
+            .file_name()
+            .unwrap_or(&program)
+            .to_string_lossy()
+            .into_owned();
+        CommandReference {
+            name,
+            program,
+            args: Vec::new(),
+        }
+    }
+
+    /// Pass `arg` to the command.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Name the reference `name` in reports.
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+}
+
+impl ReferenceEvaluator for CommandReference {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn evaluate(
+        &self,
+        policy_file: &PolicyFile,
+        request: &EvalRequest,
+    ) -> Result<EvalResult, String> {
+        #[derive(Serialize)]
+        struct Input<'a> {
+            policy: &'a PolicyFile,
+            request: &'a EvalRequest,
+        }
+
+        let input = serde_json::to_vec(&Input {
+            policy: policy_file,
+            request,
+        })
+        .map_err(|e| e.to_string())?;
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("{}: cannot run: {}", self.name, e))?;
+        // The command may exit without reading all of its input; its
+        // status says whether that is an error
+        if let Some(mut stdin) = child.stdin.take() {
+            let _ = stdin.write_all(&input);
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("{}: {}", self.name, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{}: {}: {}",
+                self.name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("{}: invalid result: {}", self.name, e))
+    }
+}
 
 /// Evaluate a request against a policy file.
 ///
-/// Returns the result with matched policy info or default.
+/// A matching deny policy wins over allow policies; otherwise the first
+/// matching policy does. Returns the result with matched policy info or
+/// default, with the
+/// request's duration applied to the grant and principal templates
+/// resolved from the request.
 pub fn evaluate(policy_file: &PolicyFile, request: &EvalRequest) -> EvalResult {
     // Try each policy in order
-    for (index, policy) in policy_file.policies.iter().enumerate() {
-        if matches_policy(policy, request) {
-            return EvalResult::from_policy(policy, index);
-        }
-    }
+    // The first matching deny policy wins, then the first matching policy
+    let matching: Vec<(usize, &Policy)> = policy_file
+        .policies
+        .iter()
+        .enumerate()
+        .filter(|(_, policy)| matches_policy(policy, request))
+        .collect();
+    let result = matching
+        .iter()
+        .find(|(_, policy)| policy.effect == PolicyEffect::Deny)
+        .or(matching.first())
+        .map(|&(index, policy)| EvalResult::from_policy(policy, index))
+        // No match - use default
+        .unwrap_or_else(|| EvalResult::default_policy(&policy_file.default));
+    let result = EvalResult {
+        principals: resolve_principals(&result.principals, request),
+        ..result
+    };
+
+    result.with_requested_duration(request.requested_duration, policy_file.on_excess_duration)
+}
 
-    // No match - use default
-    EvalResult::default_policy(&policy_file.default)
+/// Evaluate each of `requests` against a policy file, as `evaluate` does,
+/// in order.
+pub fn evaluate_many(policy_file: &PolicyFile, requests: &[EvalRequest]) -> Vec<EvalResult> {
+    requests
+        .iter()
+        .map(|request| evaluate(policy_file, request))
+        .collect()
 }
 
-/// Check if a request matches a policy's conditions.
+/// Check if a request matches a policy's conditions and the policy has
+/// not expired.
 fn matches_policy(policy: &Policy, request: &EvalRequest) -> bool {
-    let m = &policy.match_block;
+    matches_block(&policy.match_block, request)
+        && check_expires(policy.expires.as_ref(), request.current_date.as_deref())
+}
 
-    // If no triggers defined, policy matches anyone (open policy)
+/// Expiry: no date, or a current date on or before it. A request without
+/// a valid date cannot match a policy that expires.
+pub fn check_expires(expires: Option<&Date>, current: Option<&str>) -> bool {
+    let expires = match expires {
+        Some(date) => date,
+        None => return true,
+    };
+    match current.map(Date::parse) {
+        Some(Ok(today)) => today <= *expires,
+        _ => false,
+    }
+}
+
+/// Check a match block: its own triggers and filters, then its nested
+/// blocks. At least one `any` block and every `all` block must match.
+pub fn matches_block(m: &MatchBlock, request: &EvalRequest) -> bool {
+    matches_own(m, request)
+        && (m.any.is_empty() || m.any.iter().any(|b| matches_block(b, request)))
+        && m.all.iter().all(|b| matches_block(b, request))
+}
+
+/// Check a block's own triggers and filters, ignoring nested blocks.
+fn matches_own(m: &MatchBlock, request: &EvalRequest) -> bool {
+    // If no triggers defined, block matches anyone (open policy)
     if !m.has_triggers() {
         return check_filters(m, request);
     }
@@ -42,24 +227,18 @@ fn matches_policy(policy: &Policy, request: &EvalRequest) -> bool {
 /// Check AND filters (all must pass).
 fn check_filters(m: &MatchBlock, request: &EvalRequest) -> bool {
     // source_ip: CIDR match
-    if !m.source_ip.is_empty() {
-        if !check_cidr(&m.source_ip, request.source_ip.as_deref()) {
-            return false;
-        }
+    if !m.source_ip.is_empty() && !check_cidr(&m.source_ip, request.source_ip.as_deref()) {
+        return false;
     }
 
     // hours: time range
-    if !m.hours.is_empty() {
-        if !check_time_range(&m.hours, request.current_time.as_deref()) {
-            return false;
-        }
+    if !m.hours.is_empty() && !check_time_range(&m.hours, request.current_time.as_deref()) {
+        return false;
     }
 
     // webauthn_ids: exact match
-    if !m.webauthn_ids.is_empty() {
-        if !check_exact(&m.webauthn_ids, request.webauthn_id.as_deref()) {
-            return false;
-        }
+    if !m.webauthn_ids.is_empty() && !check_exact(&m.webauthn_ids, request.webauthn_id.as_deref()) {
+        return false;
     }
 
     true
@@ -140,53 +319,91 @@ pub fn fnmatch(pattern: &str, value: &str) -> bool {
     v_chars.next().is_none()
 }
 
-/// CIDR matching (simplified - just checks if IP starts with prefix).
-/// A proper implementation would parse IP addresses and check bit masks.
+/// CIDR matching: the request IP must be inside one of the blocks.
+///
+/// Malformed blocks, and a missing or malformed request IP, never match.
+/// Addresses of the other IP version never match.
 pub fn check_cidr(cidrs: &[String], ip: Option<&str>) -> bool {
-    let ip = match ip {
-        Some(v) => v,
-        None => return false,
+    let ip: IpAddr = match ip.map(str::parse) {
+        Some(Ok(ip)) => ip,
+        _ => return false,
     };
 
-    for cidr in cidrs {
-        // Simple prefix match for now
-        // TODO: proper CIDR parsing
-        let prefix = cidr.split('/').next().unwrap_or(cidr);
-        let prefix_parts: Vec<&str> = prefix.split('.').collect();
-        let ip_parts: Vec<&str> = ip.split('.').collect();
-
-        let mut matches = true;
-        for (i, pp) in prefix_parts.iter().enumerate() {
-            if *pp == "0" {
-                continue; // wildcard octet
-            }
-            if ip_parts.get(i) != Some(pp) {
-                matches = false;
-                break;
-            }
-        }
-        if matches {
-            return true;
+    cidrs.iter().any(|cidr| match parse_cidr(cidr) {
+        Some((IpAddr::V4(net), prefix)) => match ip {
+            IpAddr::V4(ip) => u32::from(ip) & mask(32, prefix) as u32 == u32::from(net),
+            IpAddr::V6(_) => false,
+        },
+        Some((IpAddr::V6(net), prefix)) => match ip {
+            IpAddr::V6(ip) => u128::from(ip) & mask(128, prefix) == u128::from(net),
+            IpAddr::V4(_) => false,
+        },
+        None => false,
+    })
+}
+
+/// Parse `address/prefix`, or a bare address as a single host, into the
+/// network address and prefix length. Blocks with host bits set
+/// (`10.1.2.3/8`) are malformed.
+pub fn parse_cidr(cidr: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match cidr.split_once('/') {
+        Some((addr, prefix)) => (addr.parse().ok()?, Some(prefix)),
+        None => (cidr.parse().ok()?, None),
+    };
+    let bits = match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    };
+    let prefix = match prefix {
+        // Only plain decimal digits, as in Python's `ipaddress`
+        Some(p) if !p.is_empty() && p.len() <= 3 && p.bytes().all(|b| b.is_ascii_digit()) => {
+            p.parse().ok().filter(|p| *p <= bits)?
         }
-    }
-    false
+        Some(_) => return None,
+        None => bits,
+    };
+    let host_bits = match addr {
+        IpAddr::V4(a) => u128::from(u32::from(a)) & !mask(32, prefix),
+        IpAddr::V6(a) => u128::from(a) & !mask(128, prefix),
+    };
+    (host_bits == 0).then_some((addr, prefix))
+}
+
+/// The network mask of a `prefix`-bit block in a `bits`-bit address.
+fn mask(bits: u8, prefix: u8) -> u128 {
+    let all = if bits == 128 {
+        u128::MAX
+    } else {
+        (1u128 << bits) - 1
+    };
+    all & !all.checked_shr(u32::from(prefix)).unwrap_or(0)
 }
 
 /// Time range check (HH:MM-HH:MM format).
+///
+/// Both ends are inclusive. A window whose start is after its end wraps
+/// past midnight (`22:00-06:00`). Malformed windows, and a missing or
+/// malformed current time, never match.
 pub fn check_time_range(ranges: &[String], current: Option<&str>) -> bool {
-    let current = match current {
-        Some(v) => v,
-        None => return false,
+    let current = match current.map(gate0::parse_time_of_day) {
+        Some(Ok(t)) => t,
+        _ => return false,
     };
 
-    for range in ranges {
-        if let Some((start, end)) = range.split_once('-') {
-            if current >= start && current <= end {
-                return true;
-            }
-        }
-    }
-    false
+    ranges.iter().any(|range| match parse_window(range) {
+        Some((start, end)) if start <= end => current >= start && current <= end,
+        Some((start, end)) => current >= start || current <= end,
+        None => false,
+    })
+}
+
+/// Parse an `HH:MM-HH:MM` window into its start and end in seconds since
+/// midnight.
+pub fn parse_window(range: &str) -> Option<(u32, u32)> {
+    let (start, end) = range.split_once('-')?;
+    let start = gate0::parse_time_of_day(start.trim()).ok()?;
+    let end = gate0::parse_time_of_day(end.trim()).ok()?;
+    Some((start, end))
 }
 
 /// Exact match check.
@@ -234,7 +451,7 @@ policies: []
         let policy = parse_policy(yaml).unwrap();
         let request = EvalRequest::default();
         let result = evaluate(&policy, &request);
-        
+
         assert!(!result.matched);
         assert_eq!(result.principals, vec!["sandbox"]);
     }
@@ -253,13 +470,89 @@ policies:
     max_duration: "60m"
 "#;
         let policy = parse_policy(yaml).unwrap();
-        let mut request = EvalRequest::default();
-        request.oidc_groups = vec!["admins".to_string()];
-        
+        let request = EvalRequest {
+            oidc_groups: vec!["admins".to_string()],
+            ..Default::default()
+        };
+
         let result = evaluate(&policy, &request);
-        
+
         assert!(result.matched);
         assert_eq!(result.policy_name, Some("AdminAccess".to_string()));
         assert_eq!(result.principals, vec!["root"]);
     }
+
+    #[test]
+    fn test_time_range() {
+        let ranges = |r: &[&str]| r.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let office = ranges(&["09:00-17:00"]);
+        assert!(check_time_range(&office, Some("09:00")));
+        assert!(check_time_range(&office, Some("17:00")));
+        assert!(!check_time_range(&office, Some("17:01")));
+        assert!(!check_time_range(&office, Some("8:59")));
+        assert!(!check_time_range(&office, None));
+
+        let night = ranges(&["22:00-06:00"]);
+        assert!(check_time_range(&night, Some("23:30")));
+        assert!(check_time_range(&night, Some("05:59")));
+        assert!(!check_time_range(&night, Some("12:00")));
+
+        assert!(!check_time_range(&ranges(&["9-17"]), Some("12:00")));
+        assert!(check_time_range(
+            &ranges(&["9-17", "12:00-13:00"]),
+            Some("12:00")
+        ));
+    }
+
+    #[test]
+    fn test_cidr() {
+        let blocks = |b: &[&str]| b.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let private = blocks(&["10.0.0.0/8", "192.168.1.0/31"]);
+        assert!(check_cidr(&private, Some("10.255.0.1")));
+        assert!(check_cidr(&private, Some("192.168.1.1")));
+        assert!(!check_cidr(&private, Some("192.168.1.2")));
+        assert!(!check_cidr(&private, Some("11.0.0.1")));
+        assert!(!check_cidr(&private, Some("10.0.0")));
+        assert!(!check_cidr(&private, None));
+
+        let v6 = blocks(&["2001:db8::/32", "::1"]);
+        assert!(check_cidr(&v6, Some("2001:db8::7")));
+        assert!(check_cidr(&v6, Some("::1")));
+        assert!(!check_cidr(&v6, Some("10.0.0.1")));
+        assert!(check_cidr(&blocks(&["0.0.0.0/0"]), Some("8.8.8.8")));
+
+        assert_eq!(
+            parse_cidr("10.1.2.3"),
+            Some(("10.1.2.3".parse().unwrap(), 32))
+        );
+        assert_eq!(parse_cidr("10.1.2.3/8"), None);
+        assert_eq!(parse_cidr("10.0.0.0/33"), None);
+        assert_eq!(parse_cidr("10.0.0.0/+8"), None);
+        assert_eq!(parse_cidr("10.0.0.0/"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_reference() {
+        let policy = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let request = EvalRequest::default();
+        let expected = evaluate(&policy, &request);
+        let echo = |output: &str| {
+            CommandReference::new("sh")
+                .arg("-c")
+                .arg(format!("cat >/dev/null; {}", output))
+        };
+
+        let json = serde_json::to_string(&expected).unwrap();
+        let reference = echo(&format!("echo '{}'", json)).named("echo");
+        assert_eq!(reference.name(), "echo");
+        assert_eq!(reference.evaluate(&policy, &request), Ok(expected));
+
+        let reference = echo("echo oops >&2; exit 3");
+        assert_eq!(reference.name(), "sh");
+        let err = reference.evaluate(&policy, &request).unwrap_err();
+        assert!(err.starts_with("sh: exit status: 3: oops"), "{}", err);
+        let err = echo("echo '{}'").evaluate(&policy, &request).unwrap_err();
+        assert!(err.starts_with("sh: invalid result: "), "{}", err);
+    }
 }
@@ -0,0 +1,192 @@
+//! JSON Schema for policy files.
+//!
+//! Describes what the loader accepts, so editors can complete and check
+//! policies as they are written and CI can reject malformed files early.
+//! The schema covers structure only: it cannot see across files, so a
+//! missing or repeated `default` block, duplicate policy names, bad CIDRs,
+//! and bad hours windows are still left to `gatebridge check`.
+
+use serde_json::{json, Value};
+
+/// The `$schema` dialect the schema is written in.
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A `max_duration`: one or more `<number><unit>` pairs.
+const DURATION_PATTERN: &str = "^([0-9]+[smhd])+$";
+
+/// An `expires` date; the loader also checks the month and day exist.
+const DATE_PATTERN: &str = "^[0-9]{4}-[0-9]{2}-[0-9]{2}$";
+
+/// JSON Schema for one policy file, root or included.
+///
+/// Every object is closed, as in the loader, which rejects unknown keys.
+/// `default` is optional because included files must not have one.
+pub fn policy_schema() -> Value {
+    let strings = |description: &str| {
+        json!({
+            "type": "array",
+            "items": { "type": "string" },
+            "description": description,
+        })
+    };
+
+    json!({
+        "$schema": SCHEMA_DIALECT,
+        "title": "GateBridge policy file",
+        "type": "object",
+        "additionalProperties": false,
+        "properties": {
+            "default": { "$ref": "#/$defs/default" },
+            "policies": {
+                "type": "array",
+                "items": { "$ref": "#/$defs/policy" },
+                "description": "Policies, in evaluation order.",
+            },
+            "include": strings("Files to load after this one, relative to it."),
+            "on_excess_duration": {
+                "enum": ["clamp", "deny"],
+                "default": "clamp",
+                "description": "What to do with a request for longer than max_duration. Root file only.",
+            },
+        },
+        "$defs": {
+            "duration": {
+                "type": "string",
+                "pattern": DURATION_PATTERN,
+                "description": "A positive duration such as \"15m\" or \"1h30m\"; units s, m, h, d.",
+            },
+            "default": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["principals", "max_duration"],
+                "properties": {
+                    "principals": strings("Principals granted when no policy matches."),
+                    "max_duration": { "$ref": "#/$defs/duration" },
+                },
+                "description": "Fallback when no policy matches. Exactly one per policy set.",
+            },
+            "policy": {
+                "type": "object",
+                "additionalProperties": false,
+                "required": ["name"],
+                "properties": {
+                    "name": { "type": "string", "description": "Unique policy name." },
+                    "effect": {
+                        "enum": ["allow", "deny"],
+                        "default": "allow",
+                        "description": "A matching deny policy wins over every allow policy.",
+                    },
+                    "match": { "$ref": "#/$defs/match" },
+                    "principals": strings("Principals granted. Required, and non-empty, for allow policies."),
+                    "max_duration": { "$ref": "#/$defs/duration" },
+                    "port_forwarding": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Grant SSH port forwarding.",
+                    },
+                    "agent_forwarding": {
+                        "type": "boolean",
+                        "default": false,
+                        "description": "Grant SSH agent forwarding.",
+                    },
+                    "pty": {
+                        "type": "boolean",
+                        "default": true,
+                        "description": "Grant a terminal.",
+                    },
+                    "expires": {
+                        "type": "string",
+                        "pattern": DATE_PATTERN,
+                        "description": "Last date, YYYY-MM-DD, the policy matches on.",
+                    },
+                },
+                "if": {
+                    "not": {
+                        "required": ["effect"],
+                        "properties": { "effect": { "const": "deny" } },
+                    },
+                },
+                "then": {
+                    "required": ["principals", "max_duration"],
+                    "properties": { "principals": { "minItems": 1 } },
+                },
+            },
+            "match": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "oidc_groups": strings("Trigger: any listed group."),
+                    "emails": strings("Trigger: fnmatch patterns on the email."),
+                    "local_usernames": strings("Trigger: fnmatch patterns on the local username."),
+                    "source_ip": strings("Filter: CIDRs or addresses."),
+                    "hours": strings("Filter: HH:MM-HH:MM windows."),
+                    "webauthn_ids": strings("Filter: fnmatch patterns on the WebAuthn credential."),
+                    "any": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/match" },
+                        "description": "At least one of these blocks must match.",
+                    },
+                    "all": {
+                        "type": "array",
+                        "items": { "$ref": "#/$defs/match" },
+                        "description": "Every one of these blocks must match.",
+                    },
+                },
+            },
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzz::random_policy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn validate(yaml: &str) -> Result<(), String> {
+        let instance: Value = serde_yaml::from_str(yaml).unwrap();
+        let validator = jsonschema::validator_for(&policy_schema()).unwrap();
+        validator.validate(&instance).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_accepts_what_the_loader_accepts() {
+        let example = include_str!("../example_policy.yaml");
+        crate::parse_policy(example).unwrap();
+        validate(example).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..50 {
+            let yaml = serde_yaml::to_string(&random_policy(&mut rng)).unwrap();
+            validate(&yaml).unwrap();
+        }
+        validate("include: [\"teams.yaml\"]\npolicies: []\n").unwrap();
+    }
+
+    #[test]
+    fn test_rejects_what_the_loader_rejects() {
+        let policy = |entry: &str| {
+            format!(
+                "default:\n  principals: [\"sandbox\"]\n  max_duration: \"15m\"\npolicies:\n  - {}\n",
+                entry
+            )
+        };
+        for entry in [
+            r#"{name: "A", principals: ["root"]}"#,
+            r#"{name: "A", principals: [], max_duration: "1h"}"#,
+            r#"{name: "A", principals: ["root"], max_duration: "1 hour"}"#,
+            r#"{name: "A", effect: "block"}"#,
+            r#"{name: "A", effect: "allow"}"#,
+            r#"{name: "A", effect: "deny", match: {oidc_group: ["x"]}}"#,
+            r#"{name: "A", effect: "deny", match: {any: [{all: [{email: ["x"]}]}]}}"#,
+            r#"{name: "A", effect: "deny", expires: "31.12.2026"}"#,
+            r#"{name: "A", effect: "deny", pty: "yes"}"#,
+        ] {
+            let yaml = policy(entry);
+            assert!(crate::parse_policy(&yaml).is_err(), "{}", entry);
+            assert!(validate(&yaml).is_err(), "{}", entry);
+        }
+        validate(&policy(r#"{name: "A", effect: "deny"}"#)).unwrap();
+    }
+}
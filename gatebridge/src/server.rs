@@ -0,0 +1,585 @@
+//! HTTP policy decision point.
+//!
+//! A deliberately small HTTP/1.1 server on `std::net`, so non-Rust services
+//! can ask for decisions over loopback without linking Gate0:
+//!
+//! - `POST /v1/evaluate`: an `EvalRequest` JSON body in, the decision,
+//!   reason code, grant, and explain trace out.
+//! - `GET /v1/policy`: the names of the loaded policies.
+//! - `POST /v1/policy`: replace the policy with the YAML body (sent as
+//!   `application/yaml`), or reload it from the file or directory the
+//!   server was started with if the body is empty. Off unless enabled with
+//!   `Server::allow_reload`, since anyone who can reach the server could
+//!   otherwise swap its policy.
+//!
+//! A new policy is parsed and translated before it replaces the old one, so
+//! a bad reload leaves the server on its last good policy. The translation
+//! is kept with the policy, so requests only evaluate. One connection is
+//! one request; responses close the connection. Bodies must be sent with
+//! `Content-Length`; a `Transfer-Encoding` is refused with `501`.
+//!
+//! `WORKERS` threads serve connections, and accepted connections beyond
+//! those wait in a queue of the same length before the server stops
+//! accepting. A connection that fails while being accepted is skipped, and
+//! running out of file descriptors pauses accepting briefly rather than
+//! spinning. Once a worker picks a connection up, the client has
+//! `IO_TIMEOUT` to send its whole request, however slowly it trickles in,
+//! before it is answered `408`, and another `IO_TIMEOUT` to take the
+//! response, so no client holds a worker for much longer than twice that.
+
+use crate::ast::{EvalRequest, PolicyFile};
+use crate::decide::decide_translated;
+use crate::{explain, load_policy_with, parse_policy_with, to_gate0, Interpolation};
+use gate0::SharedPolicy;
+use serde_json::{json, Value};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Largest request body accepted, in bytes.
+pub const MAX_BODY_LEN: usize = 1 << 20;
+
+/// Largest request line or header line accepted, in bytes.
+const MAX_LINE_LEN: usize = 8 << 10;
+
+/// Most headers accepted per request.
+const MAX_HEADERS: usize = 100;
+
+/// Threads serving connections.
+pub const WORKERS: usize = 16;
+
+/// Longest a client may take to send its request, and again to read the
+/// response.
+pub const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long to stop accepting when the process is out of file descriptors.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// A policy decision point serving one policy file or directory.
+#[derive(Debug)]
+pub struct Server {
+    path: PathBuf,
+    env: Interpolation,
+    policy: RwLock<Loaded>,
+    allow_reload: bool,
+}
+
+/// A policy file and its Gate0 translation.
+#[derive(Debug)]
+struct Loaded {
+    file: PolicyFile,
+    gate0: SharedPolicy,
+}
+
+impl Loaded {
+    fn translate(file: PolicyFile) -> Result<Self, String> {
+        let (owned, _) = to_gate0(&file).map_err(|e| e.to_string())?;
+        let gate0 = SharedPolicy::new(owned).map_err(|e| e.to_string())?;
+        Ok(Loaded { file, gate0 })
+    }
+}
+
+/// An HTTP response: status code and JSON body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl std::fmt::Display) -> Self {
+        Response {
+            status,
+            body: json!({ "error": message.to_string() }),
+        }
+    }
+}
+
+impl Server {
+    /// Load and translate the policy at `path`, a file or directory,
+    /// expanding variables as `env` allows, now and on every reload.
+    pub fn new(path: impl Into<PathBuf>, env: Interpolation) -> Result<Self, String> {
+        let path = path.into();
+        let policy = load_policy_with(&path, &env).map_err(|e| e.to_string())?;
+        Ok(Server {
+            path,
+            env,
+            policy: RwLock::new(Loaded::translate(policy)?),
+            allow_reload: false,
+        })
+    }
+
+    /// Serve `POST /v1/policy`. Off by default; when off, it answers `403`.
+    pub fn allow_reload(mut self, allow: bool) -> Self {
+        self.allow_reload = allow;
+        self
+    }
+
+    /// Accept connections on `listener` forever, serving them on `WORKERS`
+    /// threads. Failed accepts are skipped, so this only returns if the
+    /// workers are gone.
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        let (queue, connections) = mpsc::sync_channel::<TcpStream>(WORKERS);
+        let connections = Arc::new(Mutex::new(connections));
+        for _ in 0..WORKERS {
+            let server = Arc::clone(&self);
+            let connections = Arc::clone(&connections);
+            thread::spawn(move || loop {
+                let next = connections
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .recv();
+                let Ok(stream) = next else {
+                    return;
+                };
+                // The client is gone; nothing left to tell it
+                let _ = server.handle_connection(stream);
+            });
+        }
+
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(s) => s,
+                Err(e) if out_of_descriptors(&e) => {
+                    // Wait for open connections to finish and free some
+                    thread::sleep(ACCEPT_BACKOFF);
+                    continue;
+                }
+                // Only this connection is lost, e.g. reset before accept
+                Err(_) => continue,
+            };
+            // Blocks while every worker is busy and the queue is full
+            if queue.send(stream).is_err() {
+                return Err(io::Error::other("no workers left"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Read one request from `stream` and write the response, giving the
+    /// client `IO_TIMEOUT` for each.
+    pub fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let response = self.respond(&stream, Instant::now() + IO_TIMEOUT);
+        write_response(
+            &mut Deadline {
+                stream: &stream,
+                deadline: Instant::now() + IO_TIMEOUT,
+            },
+            &response,
+        )
+    }
+
+    /// Read one request from `stream` before `deadline` and answer it.
+    fn respond(&self, stream: &TcpStream, deadline: Instant) -> Response {
+        match read_request(&mut BufReader::new(Deadline { stream, deadline })) {
+            Ok(request) => self.handle(
+                &request.method,
+                &request.path,
+                request.content_type.as_deref(),
+                &request.body,
+            ),
+            Err(response) => response,
+        }
+    }
+
+    /// Route a request with its `Content-Type`, if it had one.
+    pub fn handle(
+        &self,
+        method: &str,
+        path: &str,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Response {
+        match (method, path) {
+            ("POST", "/v1/evaluate") => self.evaluate(body),
+            ("GET", "/v1/policy") => self.describe(),
+            ("POST", "/v1/policy") => self.reload(content_type, body),
+            (_, "/v1/evaluate" | "/v1/policy") => Response::error(405, "method not allowed"),
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    fn evaluate(&self, body: &[u8]) -> Response {
+        let request: EvalRequest = match serde_json::from_slice(body) {
+            Ok(r) => r,
+            Err(e) => return Response::error(400, format!("invalid request: {}", e)),
+        };
+        let loaded = self.policy.read().unwrap_or_else(PoisonError::into_inner);
+
+        let decision = match decide_translated(&loaded.file, loaded.gate0.policy(), &request) {
+            Ok(d) => d,
+            Err(e) => return Response::error(500, e),
+        };
+        let mut body = json!(decision);
+        body["explain"] = json!(explain(&loaded.file, &request));
+        Response::ok(body)
+    }
+
+    fn describe(&self) -> Response {
+        let loaded = self.policy.read().unwrap_or_else(PoisonError::into_inner);
+        let names: Vec<&str> = loaded
+            .file
+            .policies
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        Response::ok(json!({ "policies": names }))
+    }
+
+    fn reload(&self, content_type: Option<&str>, body: &[u8]) -> Response {
+        if !self.allow_reload {
+            return Response::error(403, "policy reload is not enabled");
+        }
+        let media_type = content_type.map(|t| t.split(';').next().unwrap_or_default().trim());
+        if !body.is_empty()
+            && !media_type.is_some_and(|t| t.eq_ignore_ascii_case("application/yaml"))
+        {
+            return Response::error(415, "a policy body must be sent as application/yaml");
+        }
+
+        let loaded = if body.is_empty() {
+            load_policy_with(&self.path, &self.env).map_err(|e| e.to_string())
+        } else {
+            match std::str::from_utf8(body) {
+                Ok(yaml) => parse_policy_with(yaml, &self.env).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            }
+        };
+        let loaded = match loaded.and_then(Loaded::translate) {
+            Ok(l) => l,
+            Err(e) => return Response::error(400, e),
+        };
+
+        let count = loaded.file.policies.len();
+        *self.policy.write().unwrap_or_else(PoisonError::into_inner) = loaded;
+        Response::ok(json!({ "reloaded": true, "policy_count": count }))
+    }
+}
+
+/// A connection whose reads and writes fail with `TimedOut` once `deadline`
+/// passes, however they are spread out.
+struct Deadline<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Deadline<'_> {
+    fn left(&self) -> io::Result<Duration> {
+        match self.deadline.saturating_duration_since(Instant::now()) {
+            Duration::ZERO => Err(io::ErrorKind::TimedOut.into()),
+            left => Ok(left),
+        }
+    }
+}
+
+impl Read for Deadline<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream.set_read_timeout(Some(self.left()?))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for Deadline<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.set_write_timeout(Some(self.left()?))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// The response to a failed read: `408` if the client ran out of time.
+fn read_error(error: io::Error) -> Response {
+    match error.kind() {
+        // A read timeout is `WouldBlock` on Unix
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+            Response::error(408, "request timeout")
+        }
+        _ => Response::error(400, error),
+    }
+}
+
+/// Whether `error` means the process or system has no file descriptors
+/// left (`EMFILE`, `ENFILE`).
+fn out_of_descriptors(error: &io::Error) -> bool {
+    cfg!(unix) && matches!(error.raw_os_error(), Some(23 | 24))
+}
+
+/// Read one line, without its line ending, of at most `MAX_LINE_LEN` bytes.
+fn read_line(reader: &mut impl BufRead) -> Result<String, Response> {
+    let mut line = String::new();
+    reader
+        .take(MAX_LINE_LEN as u64 + 1)
+        .read_line(&mut line)
+        .map_err(read_error)?;
+    if line.len() > MAX_LINE_LEN {
+        return Err(Response::error(431, "line too long"));
+    }
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// The parts of a request the server looks at.
+struct HttpRequest {
+    method: String,
+    path: String,
+    content_type: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Read the method, path, content type, and body of one request.
+fn read_request(reader: &mut impl BufRead) -> Result<HttpRequest, Response> {
+    let request_line = read_line(reader)?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(path), Some(_version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(Response::error(400, "malformed request line"));
+    };
+
+    let mut content_length = 0;
+    let mut content_type = None;
+    for _ in 0..=MAX_HEADERS {
+        let line = read_line(reader)?;
+        if line.is_empty() {
+            if content_length > MAX_BODY_LEN {
+                return Err(Response::error(413, "body too large"));
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).map_err(read_error)?;
+            return Ok(HttpRequest {
+                method: method.to_string(),
+                path: path.to_string(),
+                content_type,
+                body,
+            });
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                // Reading a chunked body as empty would desync the connection
+                return Err(Response::error(501, "transfer-encoding is not supported"));
+            } else if name.eq_ignore_ascii_case("content-type") {
+                content_type = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| Response::error(400, "invalid content-length"))?;
+            }
+        }
+    }
+    Err(Response::error(431, "too many headers"))
+}
+
+fn write_response(stream: &mut impl Write, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        431 => "Request Header Fields Too Large",
+        501 => "Not Implemented",
+        _ => "Internal Server Error",
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_policy;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const POLICY: &str = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "AdminAccess"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+
+    const YAML: Option<&str> = Some("application/yaml");
+
+    fn server() -> Server {
+        Server {
+            path: PathBuf::from("missing.yaml"),
+            env: Interpolation::default(),
+            policy: RwLock::new(Loaded::translate(parse_policy(POLICY).unwrap()).unwrap()),
+            allow_reload: true,
+        }
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let server = server();
+        let response = server.handle(
+            "POST",
+            "/v1/evaluate",
+            None,
+            br#"{"oidc_groups": ["admins"]}"#,
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body["policy_name"], "AdminAccess");
+        assert_eq!(response.body["principals"], json!(["root"]));
+        assert_eq!(response.body["max_duration"], "60m");
+        assert_eq!(response.body["explain"]["matched_index"], 0);
+
+        let response = server.handle("POST", "/v1/evaluate", None, b"not json");
+        assert_eq!(response.status, 400);
+        let response = server.handle("POST", "/v1/evaluate", None, br#"{"oidc_groups": []}"#);
+        assert_eq!(response.body["principals"], json!(["sandbox"]));
+        assert_eq!(response.body["reason_code"], u32::MAX - 1);
+    }
+
+    #[test]
+    fn test_reload() {
+        let server = server();
+        let replacement = POLICY.replace("AdminAccess", "Admins");
+        let response = server.handle("POST", "/v1/policy", YAML, replacement.as_bytes());
+        assert_eq!(
+            response.body,
+            json!({ "reloaded": true, "policy_count": 1 })
+        );
+        let response = server.handle("GET", "/v1/policy", None, b"");
+        assert_eq!(response.body, json!({ "policies": ["Admins"] }));
+
+        // Bad input, or a missing file, keeps the last good policy
+        assert_eq!(
+            server
+                .handle("POST", "/v1/policy", YAML, b"default: [")
+                .status,
+            400
+        );
+        assert_eq!(server.handle("POST", "/v1/policy", None, b"").status, 400);
+        let response = server.handle("GET", "/v1/policy", None, b"");
+        assert_eq!(response.body, json!({ "policies": ["Admins"] }));
+
+        // A body must be labelled as YAML
+        let body = replacement.as_bytes();
+        assert_eq!(server.handle("POST", "/v1/policy", None, body).status, 415);
+        let form = Some("application/x-www-form-urlencoded");
+        assert_eq!(server.handle("POST", "/v1/policy", form, body).status, 415);
+        let yaml = Some("Application/YAML; charset=utf-8");
+        assert_eq!(server.handle("POST", "/v1/policy", yaml, body).status, 200);
+    }
+
+    #[test]
+    fn test_reload_disabled() {
+        let server = server().allow_reload(false);
+        let body = POLICY.as_bytes();
+        assert_eq!(server.handle("POST", "/v1/policy", YAML, body).status, 403);
+        assert_eq!(server.handle("POST", "/v1/policy", None, b"").status, 403);
+        let response = server.handle("GET", "/v1/policy", None, b"");
+        assert_eq!(response.body, json!({ "policies": ["AdminAccess"] }));
+    }
+
+    #[test]
+    fn test_routing() {
+        let server = server();
+        assert_eq!(server.handle("GET", "/v1/evaluate", None, b"").status, 405);
+        assert_eq!(server.handle("GET", "/", None, b"").status, 404);
+    }
+
+    #[test]
+    fn test_request_deadline() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let done = Arc::new(AtomicBool::new(false));
+        let client = {
+            let done = Arc::clone(&done);
+            thread::spawn(move || {
+                // Each header byte arrives well within any per-read timeout
+                let mut stream = TcpStream::connect(addr).unwrap();
+                let _ = stream.write_all(b"POST /v1/evaluate HTTP/1.1\r\nX-Slow: ");
+                while !done.load(Ordering::Relaxed) {
+                    let _ = stream.write_all(b"x");
+                    thread::sleep(Duration::from_millis(20));
+                }
+            })
+        };
+        let (stream, _) = listener.accept().unwrap();
+
+        let start = Instant::now();
+        let response = server().respond(&stream, start + Duration::from_millis(200));
+        let elapsed = start.elapsed();
+        done.store(true, Ordering::Relaxed);
+        client.join().unwrap();
+
+        assert_eq!(response.status, 408);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_out_of_descriptors() {
+        assert!(out_of_descriptors(&io::Error::from_raw_os_error(24)));
+        assert!(out_of_descriptors(&io::Error::from_raw_os_error(23)));
+        assert!(!out_of_descriptors(&io::Error::from(
+            io::ErrorKind::ConnectionAborted
+        )));
+    }
+
+    #[test]
+    fn test_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || Arc::new(server()).serve(listener));
+
+        // A client that never sends only ties up its own worker
+        let _idle = TcpStream::connect(addr).unwrap();
+
+        let body = r#"{"oidc_groups": ["admins"]}"#;
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /v1/evaluate HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, json) = response.split_once("\r\n\r\n").unwrap();
+        let json: Value = serde_json::from_str(json).unwrap();
+        assert_eq!(json["policy_name"], "AdminAccess");
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "POST /v1/evaluate HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+    }
+}
@@ -2,11 +2,22 @@
 //!
 //! Runs both the reference evaluator and Gate0 on the same request,
 //! then compares results. This is the core validation mechanism.
+//! `shadow_evaluate_many` does the same over a corpus of requests and
+//! aggregates the mismatches. Both time each evaluator, to weigh the cost
+//! of the translation before switching traffic to Gate0. The `_with`
+//! variants compare Gate0 against another `ReferenceEvaluator`.
 
 use crate::ast::{EvalRequest, PolicyFile};
-use crate::{reference_evaluate, to_gate0};
-use gate0::{Request, Value};
+use crate::reference_eval::{ReferenceEvaluator, RustReference};
+use crate::to_gate0;
+use crate::translate::gate0_context;
+use gate0::{Policy, Request, Value};
 use serde::Serialize;
+use std::borrow::Borrow;
+use std::time::Instant;
+
+/// Most distinct divergences a `ShadowReport` keeps examples of.
+pub const MAX_DIVERGENCES: usize = 20;
 
 /// Shadow evaluation result.
 #[derive(Debug, Serialize)]
@@ -18,14 +29,14 @@ pub struct ShadowResult {
     pub stats: ShadowStats,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct ReferenceDecision {
     pub effect: String,
     pub policy_name: Option<String>,
     pub policy_index: Option<usize>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct Gate0Decision {
     pub effect: String,
     pub reason_code: u32,
@@ -35,46 +46,75 @@ pub struct Gate0Decision {
 pub struct ShadowStats {
     pub rules_evaluated: u16,
     pub condition_evals: u16,
+    /// Attributes in the Gate0 request's context.
+    pub context_attributes: usize,
+    /// Time the reference evaluator took.
+    pub reference_nanos: u64,
+    /// Time deriving the Gate0 request's context from the request took.
+    pub context_nanos: u64,
+    /// Time Gate0 took to evaluate the derived request.
+    pub gate0_nanos: u64,
 }
 
 /// Run shadow evaluation.
 pub fn shadow_evaluate(
     policy_file: &PolicyFile,
     request: &EvalRequest,
+) -> Result<ShadowResult, ShadowError> {
+    shadow_evaluate_with(&RustReference, policy_file, request)
+}
+
+/// Run shadow evaluation against `reference`.
+pub fn shadow_evaluate_with(
+    reference: &dyn ReferenceEvaluator,
+    policy_file: &PolicyFile,
+    request: &EvalRequest,
+) -> Result<ShadowResult, ShadowError> {
+    let (owned_policy, _) =
+        to_gate0(policy_file).map_err(|e| ShadowError::Translation(e.to_string()))?;
+    let gate0_policy = owned_policy
+        .policy()
+        .map_err(|e| ShadowError::Translation(format!("{:?}", e)))?;
+    compare(reference, policy_file, &gate0_policy, request)
+}
+
+/// Shadow-evaluate `request` against `policy_file` with `reference` and
+/// against its translation with Gate0.
+pub(crate) fn compare(
+    reference: &dyn ReferenceEvaluator,
+    policy_file: &PolicyFile,
+    gate0_policy: &Policy<'_>,
+    request: &EvalRequest,
 ) -> Result<ShadowResult, ShadowError> {
     // Run reference evaluator
-    let ref_result = reference_evaluate(policy_file, request);
-
-    // Translate to Gate0 and evaluate
-    let gate0_policy = to_gate0(policy_file)
-        .map_err(|e| ShadowError::Translation(e.to_string()))?;
-
-    // The adapter pattern: we pre-compute complex matching into booleans.
-    // For shadow evaluation, we use the reference result to set context.
-    let trigger_matched = ref_result.matched || !policy_file.policies.is_empty();
-    
-    // Build context with static lifetime strings
-    let context: [(&str, Value); 4] = [
-        ("trigger_matched", Value::Bool(trigger_matched)),
-        ("source_ip_allowed", Value::Bool(true)),
-        ("within_hours", Value::Bool(true)),
-        ("webauthn_verified", Value::Bool(true)),
-    ];
+    let start = Instant::now();
+    let ref_result = reference
+        .evaluate(policy_file, request)
+        .map_err(ShadowError::Reference)?;
+    let reference_nanos = nanos_since(start);
+
+    // The adapter pattern: request fields pass through for Gate0 to match
+    // itself, and only wildcard patterns are matched here, from the request
+    // alone
+    let start = Instant::now();
+    let attrs = gate0_context(policy_file, request);
+    let context: Vec<(&str, Value)> = attrs
+        .iter()
+        .map(|(attr, value)| (attr.as_str(), value.as_value()))
+        .collect();
 
     // Build request - use static strings for principal/action/resource
-    let gate0_request = Request::with_context(
-        "shadow_user",
-        "ssh_login",
-        "default",
-        &context,
-    );
-    
+    let gate0_request = Request::with_context("shadow_user", "ssh_login", "default", &context);
+    let context_nanos = nanos_since(start);
+
+    let start = Instant::now();
     let (gate0_decision, stats) = gate0_policy
         .evaluate_with_stats(&gate0_request)
         .map_err(|e| ShadowError::Evaluation(format!("{:?}", e)))?;
+    let gate0_nanos = nanos_since(start);
 
-    // Compare effects
-    let ref_effect = "allow"; // In grant model, everything is "allow" with different principals
+    // Compare effects: the matched policy's, not the duration check's
+    let ref_effect = ref_result.effect.as_str();
     let gate0_effect = match gate0_decision.effect {
         gate0::Effect::Allow => "allow",
         gate0::Effect::Deny => "deny",
@@ -87,7 +127,8 @@ pub fn shadow_evaluate(
         u32::MAX - 1 // default
     };
 
-    let decisions_match = gate0_decision.reason.value() == expected_reason;
+    let decisions_match =
+        gate0_decision.reason.value() == expected_reason && gate0_effect == ref_effect;
 
     Ok(ShadowResult {
         reference_decision: ReferenceDecision {
@@ -103,14 +144,225 @@ pub fn shadow_evaluate(
         stats: ShadowStats {
             rules_evaluated: stats.rules_checked,
             condition_evals: stats.condition_evals,
+            context_attributes: context.len(),
+            reference_nanos,
+            context_nanos,
+            gate0_nanos,
         },
     })
 }
 
+fn nanos_since(start: Instant) -> u64 {
+    u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// Aggregate of shadow evaluation over many requests.
+#[derive(Debug, Serialize)]
+pub struct ShadowReport {
+    /// Name of the reference evaluator Gate0 was compared against.
+    pub reference: String,
+    /// Requests evaluated, including those that failed.
+    pub requests: u64,
+    /// Requests where the evaluators disagreed.
+    pub mismatches: u64,
+    /// Requests either evaluator failed to evaluate.
+    pub errors: u64,
+    /// One entry per policy, in file order, then the default.
+    pub entries: Vec<EntryStats>,
+    /// Each distinct way the evaluators disagreed, first seen first, up to
+    /// `MAX_DIVERGENCES`.
+    pub divergences: Vec<Divergence>,
+    /// Timing and context size, summed over the requests Gate0 evaluated.
+    pub totals: ShadowTotals,
+}
+
+/// Sums of `ShadowStats` over a report's evaluated requests.
+#[derive(Debug, Default, Serialize)]
+pub struct ShadowTotals {
+    pub context_attributes: u64,
+    pub reference_nanos: u64,
+    pub context_nanos: u64,
+    pub gate0_nanos: u64,
+}
+
+/// Shadow results for the requests the reference evaluator decided with
+/// one policy entry.
+#[derive(Debug, Serialize)]
+pub struct EntryStats {
+    /// Policy name, or `"default"`.
+    pub name: String,
+    /// Policy index; `None` for the default.
+    pub index: Option<usize>,
+    pub requests: u64,
+    pub mismatches: u64,
+    /// `mismatches / requests`, or 0 if the entry decided no requests.
+    pub mismatch_rate: f64,
+}
+
+/// A pair of disagreeing decisions, with how often it occurred and the
+/// first request that produced it.
+#[derive(Debug, Serialize)]
+pub struct Divergence {
+    pub reference_decision: ReferenceDecision,
+    pub gate0_decision: Gate0Decision,
+    pub count: u64,
+    pub example: EvalRequest,
+}
+
+impl ShadowReport {
+    /// True if every request was evaluated and the evaluators agreed.
+    pub fn passed(&self) -> bool {
+        self.mismatches == 0 && self.errors == 0
+    }
+
+    /// `mismatches / requests`, or 0 for no requests.
+    pub fn mismatch_rate(&self) -> f64 {
+        rate(self.mismatches, self.requests)
+    }
+
+    /// Gate0's time, deriving the context and evaluating, over the
+    /// reference evaluator's: below 1 when the translation is faster, or 0
+    /// if nothing was timed.
+    pub fn gate0_time_ratio(&self) -> f64 {
+        let t = &self.totals;
+        match t.reference_nanos {
+            0 => 0.0,
+            reference => t.context_nanos.saturating_add(t.gate0_nanos) as f64 / reference as f64,
+        }
+    }
+
+    /// An empty report against `reference` with an entry for each of
+    /// `policy_file`'s policies and its default.
+    fn new(reference: &str, policy_file: &PolicyFile) -> Self {
+        let entry = |name: &str, index| EntryStats {
+            name: name.to_string(),
+            index,
+            requests: 0,
+            mismatches: 0,
+            mismatch_rate: 0.0,
+        };
+        let mut entries: Vec<EntryStats> = policy_file
+            .policies
+            .iter()
+            .enumerate()
+            .map(|(index, policy)| entry(&policy.name, Some(index)))
+            .collect();
+        entries.push(entry("default", None));
+        ShadowReport {
+            reference: reference.to_string(),
+            requests: 0,
+            mismatches: 0,
+            errors: 0,
+            entries,
+            divergences: Vec::new(),
+            totals: ShadowTotals::default(),
+        }
+    }
+
+    /// Count one request's result, leaving the rates for the caller.
+    fn record(&mut self, request: &EvalRequest, result: Result<ShadowResult, ShadowError>) {
+        self.requests += 1;
+        let result = match result {
+            Ok(result) => result,
+            Err(_) => {
+                self.errors += 1;
+                return;
+            }
+        };
+        let (totals, stats) = (&mut self.totals, &result.stats);
+        totals.context_attributes += stats.context_attributes as u64;
+        totals.reference_nanos = totals.reference_nanos.saturating_add(stats.reference_nanos);
+        totals.context_nanos = totals.context_nanos.saturating_add(stats.context_nanos);
+        totals.gate0_nanos = totals.gate0_nanos.saturating_add(stats.gate0_nanos);
+
+        let default = self.entries.len() - 1;
+        let index = result.reference_decision.policy_index.unwrap_or(default);
+        let entry = &mut self.entries[index];
+        entry.requests += 1;
+        if result.decisions_match {
+            return;
+        }
+        entry.mismatches += 1;
+        self.mismatches += 1;
+
+        let divergences = &mut self.divergences;
+        let seen = divergences.iter().position(|d| {
+            d.reference_decision == result.reference_decision
+                && d.gate0_decision == result.gate0_decision
+        });
+        match seen {
+            Some(i) => divergences[i].count += 1,
+            None if divergences.len() < MAX_DIVERGENCES => divergences.push(Divergence {
+                reference_decision: result.reference_decision,
+                gate0_decision: result.gate0_decision,
+                count: 1,
+                example: request.clone(),
+            }),
+            None => {}
+        }
+    }
+}
+
+fn rate(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        count as f64 / total as f64
+    }
+}
+
+/// Run shadow evaluation on every request in `requests`, translating the
+/// policy once.
+///
+/// Mismatches are attributed to the entry the reference evaluator decided
+/// with. Fails only if the policy does not translate; requests either
+/// evaluator cannot evaluate are counted as errors.
+pub fn shadow_evaluate_many<I>(
+    policy_file: &PolicyFile,
+    requests: I,
+) -> Result<ShadowReport, ShadowError>
+where
+    I: IntoIterator,
+    I::Item: Borrow<EvalRequest>,
+{
+    shadow_evaluate_many_with(&RustReference, policy_file, requests)
+}
+
+/// Run shadow evaluation against `reference` on every request in
+/// `requests`, as `shadow_evaluate_many` does.
+pub fn shadow_evaluate_many_with<I>(
+    reference: &dyn ReferenceEvaluator,
+    policy_file: &PolicyFile,
+    requests: I,
+) -> Result<ShadowReport, ShadowError>
+where
+    I: IntoIterator,
+    I::Item: Borrow<EvalRequest>,
+{
+    let (owned_policy, _) =
+        to_gate0(policy_file).map_err(|e| ShadowError::Translation(e.to_string()))?;
+    let gate0_policy = owned_policy
+        .policy()
+        .map_err(|e| ShadowError::Translation(format!("{:?}", e)))?;
+
+    let mut report = ShadowReport::new(reference.name(), policy_file);
+    for request in requests {
+        let request = request.borrow();
+        let result = compare(reference, policy_file, &gate0_policy, request);
+        report.record(request, result);
+    }
+    for entry in &mut report.entries {
+        entry.mismatch_rate = rate(entry.mismatches, entry.requests);
+    }
+    Ok(report)
+}
+
 #[derive(Debug)]
 pub enum ShadowError {
     Translation(String),
     Evaluation(String),
+    /// The reference evaluator failed.
+    Reference(String),
 }
 
 impl std::fmt::Display for ShadowError {
@@ -118,6 +370,7 @@ impl std::fmt::Display for ShadowError {
         match self {
             ShadowError::Translation(e) => write!(f, "Translation error: {}", e),
             ShadowError::Evaluation(e) => write!(f, "Evaluation error: {}", e),
+            ShadowError::Reference(e) => write!(f, "Reference error: {}", e),
         }
     }
 }
@@ -127,8 +380,8 @@ impl std::error::Error for ShadowError {}
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::loader::parse_policy;
     use crate::ast::EvalRequest;
+    use crate::loader::parse_policy;
 
     #[test]
     fn test_shadow_default() {
@@ -141,7 +394,7 @@ policies: []
         let policy = parse_policy(yaml).unwrap();
         let request = EvalRequest::default();
         let result = shadow_evaluate(&policy, &request).unwrap();
-        
+
         assert!(result.decisions_match);
         assert_eq!(result.gate0_decision.reason_code, u32::MAX - 1);
     }
@@ -160,13 +413,293 @@ policies:
     max_duration: "60m"
 "#;
         let policy = parse_policy(yaml).unwrap();
-        let mut request = EvalRequest::default();
-        request.oidc_groups = vec!["admins".to_string()];
-        
+        let request = EvalRequest {
+            oidc_groups: vec!["admins".to_string()],
+            ..Default::default()
+        };
+
         let result = shadow_evaluate(&policy, &request).unwrap();
-        
+
+        assert!(result.decisions_match);
+        assert_eq!(
+            result.reference_decision.policy_name,
+            Some("AdminAccess".to_string())
+        );
+        assert_eq!(result.gate0_decision.reason_code, 0);
+    }
+
+    #[test]
+    fn test_shadow_filters_from_request() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "OfficeAdmins"
+    match:
+      oidc_groups: ["admins"]
+      source_ip: ["10.0.0.0/8"]
+    principals: ["root"]
+    max_duration: "60m"
+  - name: "Admins"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["readonly"]
+    max_duration: "15m"
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = |ip: &str| EvalRequest {
+            oidc_groups: vec!["admins".to_string()],
+            source_ip: Some(ip.to_string()),
+            ..Default::default()
+        };
+
+        let result = shadow_evaluate(&policy, &request("10.1.2.3")).unwrap();
+        assert!(result.decisions_match);
+        assert_eq!(result.gate0_decision.reason_code, 0);
+
+        // The IP filter fails, so both evaluators fall through to policy 1
+        let result = shadow_evaluate(&policy, &request("192.168.1.1")).unwrap();
+        assert!(result.decisions_match);
+        assert_eq!(result.gate0_decision.reason_code, 1);
+    }
+
+    #[test]
+    fn test_shadow_webauthn() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "HardwareKey"
+    match:
+      oidc_groups: ["admins"]
+      webauthn_ids: ["yubi-1", "yubi-2"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = |id: Option<&str>| EvalRequest {
+            oidc_groups: vec!["admins".to_string()],
+            webauthn_id: id.map(str::to_string),
+            ..Default::default()
+        };
+
+        let result = shadow_evaluate(&policy, &request(Some("yubi-2"))).unwrap();
         assert!(result.decisions_match);
-        assert_eq!(result.reference_decision.policy_name, Some("AdminAccess".to_string()));
         assert_eq!(result.gate0_decision.reason_code, 0);
+
+        // The filter is specified but the ID is wrong or missing: no grant
+        for id in [Some("yubi-3"), Some("YUBI-1"), None] {
+            let result = shadow_evaluate(&policy, &request(id)).unwrap();
+            assert!(result.decisions_match);
+            assert_eq!(result.reference_decision.policy_index, None);
+            assert_eq!(result.gate0_decision.reason_code, u32::MAX - 1);
+        }
+
+        // Too long for a Gate0 value: still no grant, not an error
+        let long = "y".repeat(1000);
+        let result = shadow_evaluate(&policy, &request(Some(&long))).unwrap();
+        assert!(result.decisions_match);
+    }
+
+    #[test]
+    fn test_shadow_deny_policy() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Admins"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["root"]
+    max_duration: "60m"
+  - name: "Terminated"
+    effect: deny
+    match:
+      oidc_groups: ["terminated-employees"]
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = |groups: &[&str]| EvalRequest {
+            oidc_groups: groups.iter().map(|g| g.to_string()).collect(),
+            ..Default::default()
+        };
+
+        // The deny policy wins even though the allow policy comes first
+        let result =
+            shadow_evaluate(&policy, &request(&["admins", "terminated-employees"])).unwrap();
+        assert!(result.decisions_match);
+        assert_eq!(result.reference_decision.effect, "deny");
+        assert_eq!(result.reference_decision.policy_index, Some(1));
+        assert_eq!(result.gate0_decision.effect, "deny");
+        assert_eq!(result.gate0_decision.reason_code, 1);
+
+        let result = shadow_evaluate(&policy, &request(&["admins"])).unwrap();
+        assert!(result.decisions_match);
+        assert_eq!(result.gate0_decision.effect, "allow");
+    }
+
+    #[test]
+    fn test_shadow_nested_blocks() {
+        // (admins AND office network) OR (security-team AND a YubiKey)
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Privileged"
+    match:
+      any:
+        - oidc_groups: ["admins"]
+          source_ip: ["10.0.0.0/8"]
+        - oidc_groups: ["security-team"]
+          webauthn_ids: ["yubi-1"]
+      all:
+        - hours: ["08:00-18:00"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+        let policy = parse_policy(yaml).unwrap();
+        let request = |group: &str, ip: &str, key: Option<&str>| EvalRequest {
+            oidc_groups: vec![group.to_string()],
+            source_ip: Some(ip.to_string()),
+            webauthn_id: key.map(str::to_string),
+            current_time: Some("09:00".to_string()),
+            ..Default::default()
+        };
+
+        let cases = [
+            (request("admins", "10.1.2.3", None), Some(0)),
+            (request("admins", "8.8.8.8", Some("yubi-1")), None),
+            (request("security-team", "8.8.8.8", Some("yubi-1")), Some(0)),
+            (request("security-team", "10.1.2.3", None), None),
+        ];
+        for (request, expected) in cases {
+            let result = shadow_evaluate(&policy, &request).unwrap();
+            assert!(result.decisions_match, "{:?}", request);
+            assert_eq!(result.reference_decision.policy_index, expected);
+        }
+
+        let mut late = request("admins", "10.1.2.3", None);
+        late.current_time = Some("20:00".to_string());
+        let result = shadow_evaluate(&policy, &late).unwrap();
+        assert!(result.decisions_match);
+        assert_eq!(result.reference_decision.policy_index, None);
+    }
+
+    #[test]
+    fn test_shadow_evaluate_many() {
+        let policy = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let request = |groups: &[&str]| EvalRequest {
+            oidc_groups: groups.iter().map(|g| g.to_string()).collect(),
+            ..Default::default()
+        };
+        let requests = [
+            request(&["infrastructure"]),
+            request(&["security-team"]),
+            request(&["terminated-employees"]),
+            request(&[]),
+        ];
+
+        let report = shadow_evaluate_many(&policy, &requests).unwrap();
+        assert!(report.passed());
+        assert_eq!(report.requests, 4);
+        assert_eq!(report.mismatch_rate(), 0.0);
+        let counts: Vec<_> = report
+            .entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.requests))
+            .collect();
+        assert_eq!(
+            counts,
+            [
+                ("AdminAccess", 2),
+                ("DeveloperAccess", 0),
+                ("ContractorAccess", 0),
+                ("TerminatedEmployees", 1),
+                ("default", 1),
+            ]
+        );
+
+        // Owned requests from an iterator work too
+        let report = shadow_evaluate_many(&policy, requests.into_iter().take(2)).unwrap();
+        assert_eq!(report.entries[0].requests, 2);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["entries"][4]["index"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_shadow_stats() {
+        let policy = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let request = EvalRequest {
+            oidc_groups: vec!["developers".to_string()],
+            source_ip: Some("10.1.2.3".to_string()),
+            current_time: Some("10:00".to_string()),
+            ..Default::default()
+        };
+        let result = shadow_evaluate(&policy, &request).unwrap();
+        assert!(result.decisions_match);
+        // oidc_groups:developers, current_time, and source_ip
+        assert_eq!(result.stats.context_attributes, 3);
+
+        let report = shadow_evaluate_many(&policy, [&request, &EvalRequest::default()]).unwrap();
+        assert_eq!(report.totals.context_attributes, 3);
+    }
+
+    #[test]
+    fn test_shadow_report_groups_divergences() {
+        let policy = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let mut report = ShadowReport::new("rust", &policy);
+        let result = |policy_index: Option<usize>, reason_code: u32| {
+            Ok(ShadowResult {
+                reference_decision: ReferenceDecision {
+                    effect: "allow".to_string(),
+                    policy_name: None,
+                    policy_index,
+                },
+                gate0_decision: Gate0Decision {
+                    effect: "allow".to_string(),
+                    reason_code,
+                },
+                decisions_match: policy_index.map(|i| i as u32) == Some(reason_code),
+                stats: ShadowStats {
+                    rules_evaluated: 1,
+                    condition_evals: 1,
+                    context_attributes: 2,
+                    reference_nanos: 300,
+                    context_nanos: 100,
+                    gate0_nanos: 50,
+                },
+            })
+        };
+        let first = EvalRequest {
+            email: Some("first@example.com".to_string()),
+            ..Default::default()
+        };
+        report.record(&first, result(Some(0), 0));
+        report.record(&first, result(Some(0), 1));
+        report.record(&EvalRequest::default(), result(Some(0), 1));
+        report.record(&EvalRequest::default(), result(None, 0));
+        report.record(
+            &EvalRequest::default(),
+            Err(ShadowError::Evaluation("limit".to_string())),
+        );
+
+        assert!(!report.passed());
+        assert_eq!(
+            (report.requests, report.mismatches, report.errors),
+            (5, 3, 1)
+        );
+        assert_eq!(report.entries[0].requests, 3);
+        assert_eq!(report.entries[0].mismatches, 2);
+        assert_eq!(report.entries[4].mismatches, 1);
+        assert_eq!(report.divergences.len(), 2);
+        assert_eq!(report.divergences[0].count, 2);
+        assert_eq!(report.divergences[0].example.email, first.email);
+        assert_eq!(report.divergences[1].reference_decision.policy_index, None);
+        assert_eq!(report.totals.context_attributes, 8);
+        assert_eq!(report.totals.reference_nanos, 1200);
+        assert_eq!(report.gate0_time_ratio(), 0.5);
     }
 }
@@ -0,0 +1,148 @@
+//! Principal templates.
+//!
+//! A principal may name parts of the request in braces, such as
+//! `"{email_local}"` or `"dev-{local_username}"`, so one policy can grant
+//! each user their own login. Placeholders are resolved per request, after
+//! a policy decides; `{{` and `}}` are literal braces. A principal whose
+//! placeholders the request has no value for is left out of the grant.
+//! Placeholder names are checked when the policy loads.
+
+use crate::ast::EvalRequest;
+
+/// The placeholders a principal may use.
+pub const PLACEHOLDERS: &[&str] = &["email", "email_local", "local_username", "oidc_sub"];
+
+/// Check that `principal` is a well-formed template using only known
+/// placeholders.
+pub fn check_principal(principal: &str) -> Result<(), String> {
+    render(principal, |name| {
+        PLACEHOLDERS.contains(&name).then_some(None)
+    })
+    .map(|_| ())
+}
+
+/// `principals` with their placeholders resolved from `request`, leaving
+/// out those that cannot be.
+pub fn resolve_principals(principals: &[String], request: &EvalRequest) -> Vec<String> {
+    principals
+        .iter()
+        .filter_map(|p| render(p, |name| placeholder(name, request)).ok()?)
+        .collect()
+}
+
+/// The value of placeholder `name` for `request`: `None` for an unknown
+/// placeholder, `Some(None)` if the request has no value for it.
+fn placeholder<'a>(name: &str, request: &'a EvalRequest) -> Option<Option<&'a str>> {
+    let email = request.email.as_deref();
+    Some(match name {
+        "email" => email,
+        "email_local" => email
+            .and_then(|e| e.split_once('@'))
+            .map(|(local, _)| local),
+        "local_username" => request.local_username.as_deref(),
+        "oidc_sub" => request.oidc_sub.as_deref(),
+        _ => return None,
+    })
+    .map(|value| value.filter(|v| !v.is_empty()))
+}
+
+/// Render `template`, replacing each `{name}` with `value(name)`. Fails on
+/// a malformed template or unknown placeholder; `Ok(None)` if a placeholder
+/// has no value.
+fn render<'a>(
+    template: &str,
+    value: impl Fn(&str) -> Option<Option<&'a str>>,
+) -> Result<Option<String>, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut missing = false;
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if rest[i..].starts_with("{{") || rest[i..].starts_with("}}") {
+            out.push_str(&rest[i..=i]);
+            rest = &after[1..];
+            continue;
+        }
+        if rest[i..].starts_with('}') {
+            return Err(format!("unmatched }} in principal {:?}", template));
+        }
+        let Some(end) = after.find('}') else {
+            return Err(format!("unterminated {{ in principal {:?}", template));
+        };
+        let name = &after[..end];
+        match value(name) {
+            Some(Some(v)) => out.push_str(v),
+            Some(None) => missing = true,
+            None => {
+                return Err(format!(
+                    "unknown placeholder {{{}}} in principal {:?} (expected one of {})",
+                    name,
+                    template,
+                    PLACEHOLDERS.join(", ")
+                ))
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok((!missing).then_some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> EvalRequest {
+        EvalRequest {
+            email: Some("alice@example.com".to_string()),
+            oidc_sub: Some("248289761001".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let principals: Vec<String> = [
+            "root",
+            "{email_local}",
+            "dev-{email_local}",
+            "{oidc_sub}@{email}",
+            "{local_username}",
+            "{{literal}}",
+        ]
+        .iter()
+        .map(|p| p.to_string())
+        .collect();
+        assert_eq!(
+            resolve_principals(&principals, &request()),
+            [
+                "root",
+                "alice",
+                "dev-alice",
+                "248289761001@alice@example.com",
+                "{literal}"
+            ]
+        );
+        assert_eq!(
+            resolve_principals(&principals, &EvalRequest::default()),
+            ["root", "{literal}"]
+        );
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(check_principal("u-{email_local}-{{x}}").is_ok());
+        assert_eq!(
+            check_principal("{email_user}").unwrap_err(),
+            "unknown placeholder {email_user} in principal \"{email_user}\" \
+             (expected one of email, email_local, local_username, oidc_sub)"
+        );
+        assert!(check_principal("{email")
+            .unwrap_err()
+            .contains("unterminated {"));
+        assert!(check_principal("email}")
+            .unwrap_err()
+            .contains("unmatched }"));
+    }
+}
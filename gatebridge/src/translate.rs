@@ -1,11 +1,22 @@
 //! Converts YAML policy AST into Gate0 rules.
 //!
-//! Each policy maps to a Gate0 rule where ReasonCode = policy index.
+//! Each policy maps to a Gate0 rule where ReasonCode = policy index, with
+//! the policy's effect. Gate0's deny-overrides resolution gives deny
+//! policies precedence, as in the reference evaluator.
+//!
+//! Match blocks become conditions on request fields that `gate0_context`
+//! passes through, and a policy's expiry a comparison on the request's
+//! date. Session permissions are obligations Gate0 does not decide: they
+//! go with the reason code, as principals do.
 
-use crate::ast::{MatchBlock, PolicyFile};
+use crate::ast::{Date, EvalRequest, MatchBlock, Permissions, Policy, PolicyEffect, PolicyFile};
+use crate::reference_eval::{parse_cidr, parse_window};
+use crate::template::resolve_principals;
 use gate0::{
-    Condition, Effect, Policy, ReasonCode, Rule, Target, Value,
+    Effect, OwnedCondition, OwnedPolicy, OwnedRule, OwnedTarget, OwnedValue, PolicyConfig,
+    ReasonCode, Weekdays, SECONDS_PER_DAY,
 };
+use serde::{Deserialize, Serialize};
 
 /// Translation error.
 #[derive(Debug)]
@@ -14,109 +25,1043 @@ pub enum TranslateError {
     BuildFailed(String),
     /// Feature not yet supported in translation.
     Unsupported(String),
+    /// An `hours` entry is not an `HH:MM-HH:MM` window.
+    InvalidHours { policy: String, window: String },
+    /// A `source_ip` entry is not a CIDR block or an address.
+    InvalidCidr { policy: String, cidr: String },
 }
 
+/// The context attribute carrying the request's source address.
+pub const SOURCE_IP_ATTR: &str = "source_ip";
+
+/// The context attribute carrying the request's WebAuthn credential ID.
+pub const WEBAUTHN_ID_ATTR: &str = "webauthn_id";
+
+/// The context attribute carrying the request's email, for `emails`
+/// patterns without wildcards.
+pub const EMAIL_ATTR: &str = "email";
+
+/// The context attribute carrying the request's local username, for
+/// `local_usernames` patterns without wildcards.
+pub const LOCAL_USERNAME_ATTR: &str = "local_username";
+
+/// The prefix of the context attributes naming the request's OIDC groups:
+/// `oidc_groups:admins` is true if the request is in `admins`.
+pub const OIDC_GROUPS_ATTR: &str = "oidc_groups";
+
+/// The context attribute carrying the request's `current_time`, as a
+/// timestamp on 1970-01-01 (`24:00` is midnight after it).
+pub const CURRENT_TIME_ATTR: &str = "current_time";
+
+/// The context attribute carrying the request's `current_date`, as the
+/// integer `YYYYMMDD`.
+pub const CURRENT_DATE_ATTR: &str = "current_date";
+
 impl std::fmt::Display for TranslateError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TranslateError::BuildFailed(e) => write!(f, "Build failed: {}", e),
             TranslateError::Unsupported(e) => write!(f, "Unsupported: {}", e),
+            TranslateError::InvalidHours { policy, window } => {
+                write!(f, "Invalid hours window {:?} in policy {}", window, policy)
+            }
+            TranslateError::InvalidCidr { policy, cidr } => {
+                write!(f, "Invalid source_ip block {:?} in policy {}", cidr, policy)
+            }
         }
     }
 }
 
 impl std::error::Error for TranslateError {}
 
-/// Convert a PolicyFile to a Gate0 Policy.
+/// How a translated policy's reason codes relate to the policy file, for
+/// consumers of Gate0 decisions that log or alert by policy name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranslationMap {
+    /// One entry per policy, in file order.
+    pub policies: Vec<PolicyMapping>,
+    /// The reason code of the default rule.
+    pub default_reason_code: u32,
+}
+
+/// One policy's rule in a translation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyMapping {
+    pub name: String,
+    pub index: usize,
+    pub reason_code: u32,
+    /// The context attributes the rule reads, sorted, such as
+    /// `current_time`, `oidc_groups:admins`, and `source_ip` (see
+    /// `gate0_context`). Empty for a policy that matches everything.
+    pub attributes: Vec<String>,
+    /// The session permissions to grant when the rule allows.
+    pub permissions: Permissions,
+    /// The policy's expiry, checked against `current_date`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<Date>,
+}
+
+impl TranslationMap {
+    /// The policy deciding with `reason_code`, or `None` for the default
+    /// rule and unknown codes.
+    pub fn policy(&self, reason_code: u32) -> Option<&PolicyMapping> {
+        self.policies.iter().find(|p| p.reason_code == reason_code)
+    }
+
+    /// The name of the policy deciding with `reason_code`, `"default"` for
+    /// the default rule.
+    pub fn name(&self, reason_code: u32) -> Option<&str> {
+        match self.policy(reason_code) {
+            Some(policy) => Some(&policy.name),
+            None if reason_code == self.default_reason_code => Some("default"),
+            None => None,
+        }
+    }
+
+    /// The reason code of the policy named `name`.
+    pub fn reason_code(&self, name: &str) -> Option<u32> {
+        self.policies
+            .iter()
+            .find(|p| p.name == name)
+            .map(|p| p.reason_code)
+    }
+}
+
+/// Convert a PolicyFile to a Gate0 policy, and the map from its reason
+/// codes back to the policy file.
 ///
 /// Each Ephemera policy maps to a Gate0 rule with:
 /// - ReasonCode = policy index (0, 1, 2, ...)
 /// - Default policy = ReasonCode(u32::MAX - 1)
-pub fn to_gate0(policy_file: &PolicyFile) -> Result<Policy<'static>, TranslateError> {
-    let mut builder = Policy::builder();
+///
+/// The result owns its attribute names; borrow an evaluable policy from it
+/// with `OwnedPolicy::policy()`.
+pub fn to_gate0(policy_file: &PolicyFile) -> Result<(OwnedPolicy, TranslationMap), TranslateError> {
+    let mut rules = Vec::new();
+    let mut mappings = Vec::new();
 
     // Add each policy as a rule
     for (index, policy) in policy_file.policies.iter().enumerate() {
         let reason = ReasonCode(index as u32);
-        let condition = build_condition(index, &policy.match_block)?;
+        let blocks = policy.match_block.blocks();
+        if let Some(window) = blocks
+            .iter()
+            .flat_map(|b| &b.hours)
+            .find(|w| parse_window(w).is_none())
+        {
+            return Err(TranslateError::InvalidHours {
+                policy: policy.name.clone(),
+                window: window.clone(),
+            });
+        }
+        if let Some(cidr) = blocks
+            .iter()
+            .flat_map(|b| &b.source_ip)
+            .find(|c| parse_cidr(c).is_none())
+        {
+            return Err(TranslateError::InvalidCidr {
+                policy: policy.name.clone(),
+                cidr: cidr.clone(),
+            });
+        }
+        let mut conditions: Vec<OwnedCondition> =
+            build_condition(&policy.match_block).into_iter().collect();
+        if let Some(expires) = &policy.expires {
+            // On or before the date: before the day after it
+            conditions.push(OwnedCondition::LessThan {
+                attr: CURRENT_DATE_ATTR.to_string(),
+                value: OwnedValue::Int(expires.number() + 1),
+            });
+        }
+        let condition = all_of(conditions);
+        mappings.push(PolicyMapping {
+            name: policy.name.clone(),
+            index,
+            reason_code: reason.value(),
+            attributes: condition.as_ref().map_or_else(Vec::new, |c| {
+                let attrs = c.as_condition().referenced_attrs();
+                attrs.into_iter().map(str::to_string).collect()
+            }),
+            permissions: policy.permissions(),
+            expires: policy.expires.clone(),
+        });
 
-        let rule = match condition {
-            Some(cond) => Rule::new(Effect::Allow, Target::any(), Some(cond), reason),
-            None => Rule::allow(Target::any(), reason),
+        let effect = match policy.effect {
+            PolicyEffect::Allow => Effect::Allow,
+            PolicyEffect::Deny => Effect::Deny,
         };
-
-        builder = builder.rule(rule);
+        rules.push(OwnedRule::new(
+            effect,
+            OwnedTarget::any(),
+            condition,
+            reason,
+        ));
     }
 
     // Default deny at the end - will match if nothing else did
     // Use a distinctive reason code
     let default_reason = ReasonCode(u32::MAX - 1);
-    builder = builder.rule(Rule::allow(Target::any(), default_reason));
+    rules.push(OwnedRule::allow(OwnedTarget::any(), default_reason));
+
+    let policy = OwnedPolicy::new(rules);
+
+    // Surface limit violations here rather than at first evaluation
+    policy
+        .policy()
+        .map_err(|e| TranslateError::BuildFailed(format!("{:?}", e)))?;
+
+    let map = TranslationMap {
+        policies: mappings,
+        default_reason_code: default_reason.value(),
+    };
+    Ok((policy, map))
+}
 
-    builder
-        .build()
-        .map_err(|e| TranslateError::BuildFailed(format!("{:?}", e)))
+/// The principals the rule with `reason` grants: those of the policy at
+/// that index, or the default's for `ReasonCode(u32::MAX - 1)`, with
+/// templates resolved from `request`. Empty for any other reason code.
+pub fn gate0_principals(
+    policy_file: &PolicyFile,
+    reason: u32,
+    request: &EvalRequest,
+) -> Vec<String> {
+    let principals = match policy_file.policies.get(reason as usize) {
+        Some(policy) => &policy.principals,
+        None if reason == u32::MAX - 1 => &policy_file.default.principals,
+        None => return Vec::new(),
+    };
+    resolve_principals(principals, request)
 }
 
-/// Build a Gate0 Condition from a MatchBlock.
-fn build_condition(index: usize, m: &MatchBlock) -> Result<Option<Condition<'static>>, TranslateError> {
-    if !m.has_triggers() && !m.has_filters() {
-        return Ok(None); // No conditions = match all
+/// The session permissions the rule with `reason` grants: those of the
+/// policy at that index, or the default's for `ReasonCode(u32::MAX - 1)`.
+/// `None` for any other reason code.
+pub fn gate0_permissions(policy_file: &PolicyFile, reason: u32) -> Option<Permissions> {
+    match policy_file.policies.get(reason as usize) {
+        Some(policy) => Some(policy.permissions()),
+        None if reason == u32::MAX - 1 => Some(Permissions::default()),
+        None => None,
     }
+}
 
-    let mut conditions: Vec<Condition<'static>> = Vec::new();
+/// Convert a Gate0 policy back to a policy file: the reverse of `to_gate0`,
+/// for the subset of Gate0 it produces.
+///
+/// A Gate0 policy carries no names or grants, so those come from `grants`:
+/// the rule with `ReasonCode(i)` takes the name, principals, `max_duration`,
+/// and permissions of `grants.policies[i]`, and the default rule takes
+/// `grants.default`. Effects, match blocks, and expiry come from the rules,
+/// so for a policy written with the Rust builder, `grants` can be a
+/// skeleton with empty match blocks.
+///
+/// Rules must match any target, and conditions must be `AND`/`OR` trees of
+/// the conditions `to_gate0` builds on the `gate0_context` attributes. The
+/// default rule (an unconditional allow with `ReasonCode(u32::MAX - 1)`)
+/// must come last. Nested blocks and `hours` windows may come back in a
+/// different shape that matches the same requests.
+pub fn from_gate0(policy: &OwnedPolicy, grants: &PolicyFile) -> Result<PolicyFile, TranslateError> {
+    let default_reason = ReasonCode(u32::MAX - 1);
+    let rules = policy.rules();
+    let mut policies = Vec::new();
 
-    // OR triggers: use rule-specific attribute
-    if m.has_triggers() {
-        let attr = format!("p{}_trigger", index);
-        conditions.push(Condition::Equals {
-            // Leak is fine here since this is a CLI tool, not a long-running service.
-            attr: Box::leak(attr.into_boxed_str()),
-            value: Value::Bool(true),
+    for (index, rule) in rules.iter().enumerate() {
+        let unsupported =
+            |why: String| TranslateError::Unsupported(format!("rule {}: {}", index, why));
+        if rule.target != OwnedTarget::any() {
+            return Err(unsupported(
+                "policies cannot match on the target".to_string(),
+            ));
+        }
+        if rule.reason == default_reason {
+            if rule.effect != Effect::Allow || rule.condition.is_some() || index + 1 != rules.len()
+            {
+                return Err(unsupported(
+                    "the default rule must be an unconditional allow and come last".to_string(),
+                ));
+            }
+            continue;
+        }
+
+        let grant = grants
+            .policies
+            .get(rule.reason.value() as usize)
+            .ok_or_else(|| {
+                unsupported(format!("no policy for reason code {}", rule.reason.value()))
+            })?;
+        let effect = match rule.effect {
+            Effect::Allow => PolicyEffect::Allow,
+            Effect::Deny => PolicyEffect::Deny,
+        };
+        if effect == PolicyEffect::Allow
+            && (grant.principals.is_empty() || grant.max_duration.is_none())
+        {
+            return Err(unsupported(format!("{} grants nothing", grant.name)));
+        }
+        // The expiry is a comparison on the date at the top of the condition
+        let mut parts: Vec<OwnedCondition> = rule
+            .condition
+            .iter()
+            .flat_map(|c| operands(c, true))
+            .cloned()
+            .collect();
+        let expiry = parts.iter().position(
+            |c| matches!(c, OwnedCondition::LessThan { attr, .. } if attr == CURRENT_DATE_ATTR),
+        );
+        let expires = match expiry {
+            Some(i) => Some(expiry_from(&parts.remove(i)).map_err(unsupported)?),
+            None => None,
+        };
+        let match_block = match all_of(parts) {
+            Some(condition) => block_from(&condition).map_err(unsupported)?,
+            None => MatchBlock::default(),
+        };
+        policies.push(Policy {
+            name: grant.name.clone(),
+            effect,
+            match_block,
+            principals: grant.principals.clone(),
+            max_duration: grant.max_duration.clone(),
+            port_forwarding: grant.port_forwarding,
+            agent_forwarding: grant.agent_forwarding,
+            pty: grant.pty,
+            expires,
         });
     }
 
-    // AND filters: use rule-specific attributes
-    if !m.source_ip.is_empty() {
-        let attr = format!("p{}_ip", index);
-        conditions.push(Condition::Equals {
-            attr: Box::leak(attr.into_boxed_str()),
-            value: Value::Bool(true),
-        });
+    if rules.last().map(|r| r.reason) != Some(default_reason) {
+        return Err(TranslateError::Unsupported("no default rule".to_string()));
     }
-    if !m.hours.is_empty() {
-        let attr = format!("p{}_time", index);
-        conditions.push(Condition::Equals {
-            attr: Box::leak(attr.into_boxed_str()),
-            value: Value::Bool(true),
-        });
+    Ok(PolicyFile {
+        default: grants.default.clone(),
+        policies,
+        on_excess_duration: grants.on_excess_duration,
+    })
+}
+
+/// The match block for a condition `to_gate0` built, or why there is none.
+///
+/// Each operand of the top `AND` fills a free field of the block; an
+/// operand whose field is taken goes in an `all` block of its own, and an
+/// `OR` across fields becomes `any` blocks.
+fn block_from(condition: &OwnedCondition) -> Result<MatchBlock, String> {
+    let mut block = MatchBlock::default();
+    for operand in operands(condition, true) {
+        let mut part = MatchBlock::default();
+        let leaves = operands(operand, false);
+        let triggers: Option<Vec<(&str, &str)>> = leaves.iter().map(|c| trigger_from(c)).collect();
+        let windows: Option<Vec<String>> = leaves.iter().map(|c| window_from(c)).collect();
+        let cidrs: Option<Vec<String>> = leaves
+            .iter()
+            .map(|c| match c {
+                OwnedCondition::IpInCidr { attr, cidr } if attr == SOURCE_IP_ATTR => {
+                    Some(cidr.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        let ids: Option<Vec<String>> = leaves
+            .iter()
+            .map(|c| match c {
+                OwnedCondition::Equals {
+                    attr,
+                    value: OwnedValue::String(id),
+                } if attr == WEBAUTHN_ID_ATTR => Some(id.clone()),
+                _ => None,
+            })
+            .collect();
+        if let Some(triggers) = triggers {
+            for (field, entry) in triggers {
+                let list = match field {
+                    OIDC_GROUPS_ATTR => &mut part.oidc_groups,
+                    EMAIL_ATTR => &mut part.emails,
+                    _ => &mut part.local_usernames,
+                };
+                list.push(entry.to_string());
+            }
+        } else if let Some(windows) = windows {
+            part.hours = windows;
+        } else if let Some(cidrs) = cidrs {
+            part.source_ip = cidrs;
+        } else if let Some(ids) = ids {
+            part.webauthn_ids = ids;
+        } else if leaves.len() > 1 {
+            part.any = leaves
+                .iter()
+                .map(|c| block_from(c))
+                .collect::<Result<_, _>>()?;
+        } else {
+            return Err(format!("no policy field for condition {:?}", operand));
+        }
+
+        let taken = (block.has_triggers() && part.has_triggers())
+            || (!block.hours.is_empty() && !part.hours.is_empty())
+            || (!block.source_ip.is_empty() && !part.source_ip.is_empty())
+            || (!block.webauthn_ids.is_empty() && !part.webauthn_ids.is_empty())
+            || (!block.any.is_empty() && !part.any.is_empty());
+        if taken {
+            block.all.push(part);
+        } else {
+            block.oidc_groups.extend(part.oidc_groups);
+            block.emails.extend(part.emails);
+            block.local_usernames.extend(part.local_usernames);
+            block.hours.extend(part.hours);
+            block.source_ip.extend(part.source_ip);
+            block.webauthn_ids.extend(part.webauthn_ids);
+            block.any.extend(part.any);
+        }
     }
-    if !m.webauthn_ids.is_empty() {
-        let attr = format!("p{}_webauthn", index);
-        conditions.push(Condition::Equals {
-            attr: Box::leak(attr.into_boxed_str()),
-            value: Value::Bool(true),
-        });
+    Ok(block)
+}
+
+/// The trigger field (`OIDC_GROUPS_ATTR`, `EMAIL_ATTR`, or
+/// `LOCAL_USERNAME_ATTR`) and entry a trigger condition `to_gate0` built
+/// checks.
+fn trigger_from(condition: &OwnedCondition) -> Option<(&str, &str)> {
+    let OwnedCondition::Equals { attr, value } = condition else {
+        return None;
+    };
+    match (attr.split_once(':'), value) {
+        (None, OwnedValue::String(pattern))
+            if attr == EMAIL_ATTR || attr == LOCAL_USERNAME_ATTR =>
+        {
+            Some((attr, pattern))
+        }
+        (Some((OIDC_GROUPS_ATTR, group)), OwnedValue::Bool(true)) => {
+            Some((OIDC_GROUPS_ATTR, group))
+        }
+        (Some((field, pattern)), OwnedValue::Bool(true))
+            if (field == EMAIL_ATTR || field == LOCAL_USERNAME_ATTR) && is_wildcard(pattern) =>
+        {
+            Some((field, pattern))
+        }
+        _ => None,
+    }
+}
+
+/// The `hours` window a condition `window_condition` built matches, as
+/// text.
+fn window_from(condition: &OwnedCondition) -> Option<String> {
+    let (start, end) = match condition {
+        OwnedCondition::TimeBetween { attr, start, end } if attr == CURRENT_TIME_ATTR => {
+            match (*start, *end) {
+                (start, end) if start == end => return None,
+                (0, SECONDS_PER_DAY) => (0, SECONDS_PER_DAY),
+                // 24:00 reads as 00:00, so it is in any window holding 00:00
+                (0, end) => (SECONDS_PER_DAY, end - 1),
+                (start, 0) => (start, SECONDS_PER_DAY - 1),
+                (start, end) => (start, end - 1),
+            }
+        }
+        c if *c == day_condition(SECONDS_PER_DAY) => (SECONDS_PER_DAY, SECONDS_PER_DAY),
+        c if *c == day_condition(0) => (0, SECONDS_PER_DAY - 1),
+        OwnedCondition::And(within, day) => match &**within {
+            OwnedCondition::TimeBetween {
+                attr,
+                start: 0,
+                end,
+            } if attr == CURRENT_TIME_ATTR && *end > 0 && **day == day_condition(0) => (0, end - 1),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(format!("{}-{}", time_text(start), time_text(end)))
+}
+
+/// The date an expiry comparison `to_gate0` built allows requests up to.
+fn expiry_from(condition: &OwnedCondition) -> Result<Date, String> {
+    match condition {
+        OwnedCondition::LessThan {
+            value: OwnedValue::Int(next),
+            ..
+        } => {
+            let n = next.saturating_sub(1);
+            Date::parse(&format!(
+                "{:04}-{:02}-{:02}",
+                n / 10_000,
+                n / 100 % 100,
+                n % 100
+            ))
+        }
+        other => Err(format!("no date in condition {:?}", other)),
+    }
+}
+
+/// The operands of the `AND` (or `OR`) chain at the root of `condition`,
+/// or `condition` itself if it is not one.
+fn operands(condition: &OwnedCondition, and: bool) -> Vec<&OwnedCondition> {
+    let mut operands = Vec::new();
+    match condition {
+        OwnedCondition::And(..) if and => chain(condition, &mut operands),
+        OwnedCondition::Or(..) if !and => chain(condition, &mut operands),
+        _ => operands.push(condition),
     }
+    operands
+}
 
-    if conditions.is_empty() {
-        Ok(None)
-    } else if conditions.len() == 1 {
-        Ok(Some(conditions.remove(0)))
+/// Render a policy file as a Rust module declaring it with
+/// `gate0::static_policy!`, for deployments that build policies in.
+///
+/// The module has one `ReasonCode` constant per policy, named after it in
+/// upper snake case (`AdminAccess` becomes `ADMIN_ACCESS`), `DEFAULT` for
+/// the default rule, and the policy itself as `POLICY`. Names that would
+/// collide get the policy index appended. Like `to_gate0`'s policy,
+/// `POLICY` decides requests carrying the `gate0_context` attributes.
+///
+/// The macro rejects rules that can never decide a request, such as an
+/// allow policy after one that matches everything, so those are left out
+/// with a comment. Decisions are unchanged.
+pub fn to_rust_source(policy_file: &PolicyFile) -> Result<String, TranslateError> {
+    let (policy, _) = to_gate0(policy_file)?;
+    let default_reason = u32::MAX - 1;
+    let (_, warnings) = policy
+        .rules()
+        .iter()
+        .fold(gate0::Policy::builder(), |builder, rule| {
+            builder.rule(rule.as_rule())
+        })
+        .build_with_warnings()
+        .map_err(|e| TranslateError::BuildFailed(format!("{:?}", e)))?;
+
+    let mut out = String::new();
+    out.push_str("// Generated by gatebridge from a YAML policy file. Do not edit.\n\n");
+    out.push_str("use gate0::ReasonCode;\n\n");
+
+    let mut used = vec!["DEFAULT".to_string(), "POLICY".to_string()];
+    for (index, p) in policy_file.policies.iter().enumerate() {
+        let mut name = constant_name(&p.name);
+        if used.contains(&name) {
+            name = format!("{}_{}", name, index);
+        }
+        out.push_str(&format!("/// Policy {:?}.\n", p.name));
+        out.push_str(&format!(
+            "pub const {}: ReasonCode = ReasonCode({});\n",
+            name, index
+        ));
+        used.push(name);
+    }
+    out.push_str("/// No policy matched; the default grant applies.\n");
+    out.push_str(&format!(
+        "pub const DEFAULT: ReasonCode = ReasonCode({});\n\n",
+        default_reason
+    ));
+
+    out.push_str("gate0::static_policy! {\n");
+    out.push_str("    /// The translated policy.\n");
+    out.push_str("    pub static POLICY = {\n");
+    for (index, rule) in policy.rules().iter().enumerate() {
+        if let Some(warning) = warnings.iter().find(|w| w.rule() == index) {
+            let name = policy_file
+                .policies
+                .get(index)
+                .map_or("default", |p| p.name.as_str());
+            out.push_str(&format!(
+                "        // {:?} never decides: {}\n",
+                name, warning
+            ));
+            continue;
+        }
+        let effect = match rule.effect {
+            Effect::Allow => "ALLOW",
+            Effect::Deny => "DENY",
+        };
+        out.push_str(&format!("        {} (* * *)", effect));
+        if let Some(condition) = &rule.condition {
+            out.push_str(&format!(" WHERE {{ {} }}", condition_source(condition)?));
+        }
+        out.push_str(&format!(" => {};\n", rule.reason.value()));
+    }
+    out.push_str("    };\n}\n");
+    Ok(out)
+}
+
+/// `name` in upper snake case, as a Rust identifier.
+fn constant_name(name: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_uppercase());
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+    let out = out.trim_end_matches('_');
+    match out.chars().next() {
+        None => "POLICY_".to_string(),
+        Some(c) if c.is_ascii_digit() => format!("POLICY_{}", out),
+        Some(_) => out.to_string(),
+    }
+}
+
+/// `condition` in the text policy syntax. Each `AND` and `OR` keeps its
+/// operands in parentheses, so the macro builds the same tree and the
+/// same depth.
+fn condition_source(condition: &OwnedCondition) -> Result<String, TranslateError> {
+    match condition {
+        OwnedCondition::And(left, right) | OwnedCondition::Or(left, right) => {
+            let keyword = match condition {
+                OwnedCondition::And(..) => "AND",
+                _ => "OR",
+            };
+            let operand = |c: &OwnedCondition| {
+                let source = condition_source(c)?;
+                Ok::<_, TranslateError>(match c {
+                    OwnedCondition::And(..) | OwnedCondition::Or(..) => format!("({})", source),
+                    _ => source,
+                })
+            };
+            Ok(format!(
+                "{} {} {}",
+                operand(left)?,
+                keyword,
+                operand(right)?
+            ))
+        }
+        OwnedCondition::Equals { attr, value } => Ok(format!(
+            "({} EQ {})",
+            attr_source(attr)?,
+            value_source(value)?
+        )),
+        OwnedCondition::LessThan { attr, value } => Ok(format!(
+            "({} LT {})",
+            attr_source(attr)?,
+            value_source(value)?
+        )),
+        OwnedCondition::IpInCidr { attr, cidr } => Ok(format!(
+            "({} IN_CIDR {})",
+            attr_source(attr)?,
+            string_literal(cidr)?
+        )),
+        OwnedCondition::TimeBetween { attr, start, end } => Ok(format!(
+            "({} BETWEEN \"{}\" \"{}\")",
+            attr_source(attr)?,
+            time_text(*start),
+            time_text(*end)
+        )),
+        OwnedCondition::DayOfWeek { attr, days } => {
+            let days: Vec<String> = days.names().map(|d| format!("{:?}", d)).collect();
+            Ok(format!("({} ON [{}])", attr_source(attr)?, days.join(" ")))
+        }
+        other => Err(TranslateError::Unsupported(format!(
+            "no Rust source for condition {:?}",
+            other
+        ))),
+    }
+}
+
+/// The operands of the chain of `condition`'s own kind at its root.
+fn chain<'a>(condition: &'a OwnedCondition, operands: &mut Vec<&'a OwnedCondition>) {
+    let (left, right) = match condition {
+        OwnedCondition::And(left, right) | OwnedCondition::Or(left, right) => (left, right),
+        _ => return operands.push(condition),
+    };
+    for side in [left, right] {
+        if std::mem::discriminant(&**side) == std::mem::discriminant(condition) {
+            chain(side, operands);
+        } else {
+            operands.push(side);
+        }
+    }
+}
+
+/// `attr` as written in a comparison: bare if it is an identifier, or
+/// else as a string literal.
+fn attr_source(attr: &str) -> Result<String, TranslateError> {
+    let mut chars = attr.chars();
+    let identifier = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if identifier {
+        Ok(attr.to_string())
+    } else {
+        string_literal(attr)
+    }
+}
+
+fn value_source(value: &OwnedValue) -> Result<String, TranslateError> {
+    match value {
+        OwnedValue::Bool(b) => Ok(b.to_string()),
+        OwnedValue::Int(n) => Ok(n.to_string()),
+        OwnedValue::String(s) => string_literal(s),
+        other => Err(TranslateError::Unsupported(format!(
+            "no Rust source for value {:?}",
+            other
+        ))),
+    }
+}
+
+/// `s` as a string literal that Rust and the text policy syntax both read
+/// back as `s`.
+fn string_literal(s: &str) -> Result<String, TranslateError> {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                return Err(TranslateError::Unsupported(format!(
+                    "no Rust source for control character {:?} in {:?}",
+                    c, s
+                )))
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    Ok(out)
+}
+
+/// Build a Gate0 Condition from a MatchBlock, or `None` if it matches
+/// everything.
+fn build_condition(m: &MatchBlock) -> Option<OwnedCondition> {
+    all_of(block_conditions(m))
+}
+
+/// The conditions a request must meet to match `m`.
+fn block_conditions(m: &MatchBlock) -> Vec<OwnedCondition> {
+    let mut conditions: Vec<OwnedCondition> = Vec::new();
+
+    // Triggers: the request must be in one of the groups or match one of
+    // the patterns
+    let groups = m
+        .oidc_groups
+        .iter()
+        .map(|g| flag(entry_attr(OIDC_GROUPS_ATTR, g)));
+    let emails = m.emails.iter().map(|p| pattern_condition(EMAIL_ATTR, p));
+    let usernames = m
+        .local_usernames
+        .iter()
+        .map(|p| pattern_condition(LOCAL_USERNAME_ATTR, p));
+    conditions.extend(any_of(groups.chain(emails).chain(usernames).collect()));
+
+    // hours: the time must be in one of the windows
+    let windows = m.hours.iter().filter_map(|w| parse_window(w));
+    conditions.extend(any_of(
+        windows
+            .map(|(start, end)| window_condition(start, end))
+            .collect(),
+    ));
+
+    // source_ip: the address must be in any of the blocks
+    let blocks = m.source_ip.iter().filter_map(|c| parse_cidr(c));
+    conditions.extend(any_of(
+        blocks
+            .map(|(addr, prefix)| OwnedCondition::IpInCidr {
+                attr: SOURCE_IP_ATTR.to_string(),
+                cidr: format!("{}/{}", addr, prefix),
+            })
+            .collect(),
+    ));
+
+    // webauthn_ids: the credential ID must equal one of the IDs
+    conditions.extend(any_of(
+        m.webauthn_ids
+            .iter()
+            .map(|id| OwnedCondition::Equals {
+                attr: WEBAUTHN_ID_ATTR.to_string(),
+                value: OwnedValue::String(id.clone()),
+            })
+            .collect(),
+    ));
+
+    // any: one nested block must match, unless one matches everything
+    let any: Option<Vec<OwnedCondition>> = m.any.iter().map(build_condition).collect();
+    conditions.extend(any.and_then(any_of));
+
+    // all: every nested block must match, so its conditions join ours
+    conditions.extend(m.all.iter().flat_map(block_conditions));
+
+    conditions
+}
+
+/// A condition matching `field` against an `emails` or `local_usernames`
+/// pattern: equality for a plain pattern, and for one with wildcards the
+/// attribute `gate0_context` sets when the pattern matches.
+fn pattern_condition(field: &str, pattern: &str) -> OwnedCondition {
+    if is_wildcard(pattern) {
+        flag(entry_attr(field, pattern))
     } else {
-        // AND all conditions by chaining
-        let mut result = conditions.pop().unwrap();
-        while let Some(c) = conditions.pop() {
-            result = Condition::And(Box::new(c), Box::new(result));
+        OwnedCondition::Equals {
+            attr: field.to_string(),
+            value: OwnedValue::String(pattern.to_string()),
         }
-        Ok(Some(result))
+    }
+}
+
+/// A condition matching `current_time` in the window from `start` to `end`
+/// inclusive, as `parse_window` reads it.
+fn window_condition(start: u32, end: u32) -> OwnedCondition {
+    let between = |start: u32, end: u32| OwnedCondition::TimeBetween {
+        attr: CURRENT_TIME_ATTR.to_string(),
+        start,
+        end,
+    };
+
+    // A window past midnight holds 24:00 as well as 00:00
+    if start > end {
+        return match start % SECONDS_PER_DAY {
+            start if start == end + 1 => between(0, SECONDS_PER_DAY),
+            start => between(start, end + 1),
+        };
+    }
+    // Otherwise 24:00, which is 00:00 the next day, is told apart by the day
+    let within = between(start, (end + 1).min(SECONDS_PER_DAY));
+    match (start, end) {
+        (SECONDS_PER_DAY, _) => day_condition(SECONDS_PER_DAY),
+        (0, end) if end < SECONDS_PER_DAY => {
+            OwnedCondition::And(Box::new(within), Box::new(day_condition(0)))
+        }
+        (start, SECONDS_PER_DAY) if start > 0 => {
+            OwnedCondition::Or(Box::new(within), Box::new(day_condition(SECONDS_PER_DAY)))
+        }
+        _ => within,
+    }
+}
+
+/// A condition matching `current_time` on the day of `time`: 1970-01-01
+/// for times before 24:00, and 1970-01-02 for 24:00.
+fn day_condition(time: u32) -> OwnedCondition {
+    OwnedCondition::DayOfWeek {
+        attr: CURRENT_TIME_ATTR.to_string(),
+        days: Weekdays::of(i64::from(time)),
+    }
+}
+
+/// The context attributes a request carries for the translated policy.
+///
+/// Request fields are passed through for Gate0 to match itself, where a
+/// policy reads them: each of the request's `oidc_groups` some block names
+/// as a true `oidc_groups:<group>` attribute, `email` and `local_username`
+/// as strings, `current_time` (if it parses) as a timestamp on 1970-01-01,
+/// `current_date` (if it parses) as the integer `YYYYMMDD`, `source_ip`
+/// (if it parses) as an address, and `webauthn_id` as a string.
+///
+/// Gate0 has no wildcard matching, so each `emails` or `local_usernames`
+/// pattern with `*` or `?` that matches the request is set as a true
+/// attribute such as `email:*@example.com`.
+pub fn gate0_context(policy_file: &PolicyFile, request: &EvalRequest) -> Vec<(String, OwnedValue)> {
+    let blocks: Vec<&MatchBlock> = policy_file
+        .policies
+        .iter()
+        .flat_map(|p| p.match_block.blocks())
+        .collect();
+    let mut context: Vec<(String, OwnedValue)> = Vec::new();
+
+    for group in &request.oidc_groups {
+        let attr = entry_attr(OIDC_GROUPS_ATTR, group);
+        let named = blocks.iter().any(|b| b.oidc_groups.contains(group));
+        if named && !context.iter().any(|(a, _)| *a == attr) {
+            context.push((attr, OwnedValue::Bool(true)));
+        }
+    }
+    let emails: Vec<&String> = blocks.iter().flat_map(|b| &b.emails).collect();
+    context.extend(pattern_attrs(EMAIL_ATTR, &emails, request.email.as_deref()));
+    let usernames: Vec<&String> = blocks.iter().flat_map(|b| &b.local_usernames).collect();
+    context.extend(pattern_attrs(
+        LOCAL_USERNAME_ATTR,
+        &usernames,
+        request.local_username.as_deref(),
+    ));
+
+    let filters_time = blocks.iter().any(|b| !b.hours.is_empty());
+    let time = request
+        .current_time
+        .as_deref()
+        .and_then(|t| gate0::parse_time_of_day(t).ok());
+    if let (true, Some(time)) = (filters_time, time) {
+        context.push((
+            CURRENT_TIME_ATTR.to_string(),
+            OwnedValue::Timestamp(i64::from(time)),
+        ));
+    }
+
+    let expiring = policy_file.policies.iter().any(|p| p.expires.is_some());
+    let date = request
+        .current_date
+        .as_deref()
+        .and_then(|d| Date::parse(d).ok());
+    if let (true, Some(date)) = (expiring, date) {
+        context.push((
+            CURRENT_DATE_ATTR.to_string(),
+            OwnedValue::Int(date.number()),
+        ));
+    }
+
+    let filters_ip = blocks.iter().any(|b| !b.source_ip.is_empty());
+    let ip = request.source_ip.as_deref().and_then(|ip| ip.parse().ok());
+    if let (true, Some(ip)) = (filters_ip, ip) {
+        context.push((SOURCE_IP_ATTR.to_string(), OwnedValue::Ip(ip)));
+    }
+
+    // An ID over Gate0's value limit cannot equal any ID in a policy that
+    // translated, so leaving it out (no match) keeps evaluation total
+    let filters_webauthn = blocks.iter().any(|b| !b.webauthn_ids.is_empty());
+    let id = request
+        .webauthn_id
+        .as_ref()
+        .filter(|id| id.len() <= PolicyConfig::DEFAULT.max_attr_value_len);
+    if let (true, Some(id)) = (filters_webauthn, id) {
+        context.push((WEBAUTHN_ID_ATTR.to_string(), OwnedValue::String(id.clone())));
+    }
+    context
+}
+
+/// The attributes for matching `value` against `patterns` of `field`: the
+/// value itself if a plain pattern could equal it, and a true attribute
+/// for each wildcard pattern it matches.
+fn pattern_attrs(
+    field: &str,
+    patterns: &[&String],
+    value: Option<&str>,
+) -> Vec<(String, OwnedValue)> {
+    let mut attrs = Vec::new();
+    let value = match value {
+        Some(value) => value,
+        None => return attrs,
+    };
+    // As with webauthn_id, a value over the limit equals no plain pattern
+    let plain = patterns.iter().any(|p| !is_wildcard(p));
+    if plain && value.len() <= PolicyConfig::DEFAULT.max_attr_value_len {
+        attrs.push((field.to_string(), OwnedValue::String(value.to_string())));
+    }
+    for pattern in patterns.iter().filter(|p| is_wildcard(p) && glob(p, value)) {
+        let attr = entry_attr(field, pattern);
+        if !attrs.iter().any(|(a, _)| *a == attr) {
+            attrs.push((attr, OwnedValue::Bool(true)));
+        }
+    }
+    attrs
+}
+
+/// True if `value` matches the wildcard `pattern`, where `*` matches any
+/// run of characters and `?` any one.
+///
+/// This is deliberately not the reference evaluator's `fnmatch`, so the
+/// two evaluators agreeing checks both.
+fn glob(pattern: &str, value: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let value: Vec<char> = value.chars().collect();
+    let (mut p, mut v) = (0, 0);
+    // The last `*` and where in the value its match ends so far
+    let mut star: Option<(usize, usize)> = None;
+    while v < value.len() {
+        match pattern.get(p).copied() {
+            Some('*') => {
+                star = Some((p, v));
+                p += 1;
+            }
+            Some(c) if c == '?' || c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            // Backtrack: let the last `*` take one more character
+            _ => match star {
+                Some((star_p, star_v)) => {
+                    star = Some((star_p, star_v + 1));
+                    p = star_p + 1;
+                    v = star_v + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// True if `pattern` has wildcards, so Gate0 cannot match it with `EQ`.
+fn is_wildcard(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// The attribute for `entry` of the request field `field`, such as
+/// `oidc_groups:admins`.
+fn entry_attr(field: &str, entry: &str) -> String {
+    format!("{}:{}", field, entry)
+}
+
+/// `seconds` since midnight as `HH:MM`, or `HH:MM:SS` when it has seconds.
+fn time_text(seconds: u32) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+    match seconds % 60 {
+        0 => format!("{:02}:{:02}", hours, minutes),
+        s => format!("{:02}:{:02}:{:02}", hours, minutes, s),
+    }
+}
+
+/// The OR of `conditions`, or `None` if there are none.
+fn any_of(conditions: Vec<OwnedCondition>) -> Option<OwnedCondition> {
+    balanced(conditions, OwnedCondition::Or)
+}
+
+/// The AND of `conditions`, or `None` if there are none.
+fn all_of(conditions: Vec<OwnedCondition>) -> Option<OwnedCondition> {
+    balanced(conditions, OwnedCondition::And)
+}
+
+/// Join `conditions` into a balanced tree, keeping nested blocks within
+/// Gate0's condition depth limit.
+///
+/// A condition `d` deep weighs `2^d`, and each join splits its operands
+/// where the halves weigh closest to the same, so deep nested blocks get
+/// the short paths. Operands keep their order.
+fn balanced(
+    mut conditions: Vec<OwnedCondition>,
+    join: fn(Box<OwnedCondition>, Box<OwnedCondition>) -> OwnedCondition,
+) -> Option<OwnedCondition> {
+    if conditions.len() <= 1 {
+        return conditions.pop();
+    }
+    let weights: Vec<u64> = conditions.iter().map(|c| 1 << depth(c).min(62)).collect();
+    let total: u64 = weights.iter().sum();
+    let mut left_weight = 0;
+    let split = (1..conditions.len())
+        .min_by_key(|&i| {
+            left_weight += weights[i - 1];
+            (2 * left_weight).abs_diff(total)
+        })
+        .expect("two or more conditions");
+    let right = conditions.split_off(split);
+    let left = balanced(conditions, join)?;
+    Some(join(Box::new(left), Box::new(balanced(right, join)?)))
+}
+
+/// The levels of `condition`'s tree, 1 for a comparison.
+fn depth(condition: &OwnedCondition) -> u32 {
+    match condition {
+        OwnedCondition::And(left, right) | OwnedCondition::Or(left, right) => {
+            1 + depth(left).max(depth(right))
+        }
+        OwnedCondition::Not(inner) => 1 + depth(inner),
+        _ => 1,
+    }
+}
+
+/// A condition requiring the boolean attribute `attr` to be true.
+fn flag(attr: String) -> OwnedCondition {
+    OwnedCondition::Equals {
+        attr,
+        value: OwnedValue::Bool(true),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fuzz::{random_policy, random_request};
     use crate::loader::parse_policy;
+    use gate0::Request;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
 
     #[test]
     fn test_translate_empty() {
@@ -127,8 +1072,8 @@ default:
 policies: []
 "#;
         let policy_file = parse_policy(yaml).unwrap();
-        let gate0_policy = to_gate0(&policy_file).unwrap();
-        
+        let (gate0_policy, _) = to_gate0(&policy_file).unwrap();
+
         // Should have just the default rule
         assert_eq!(gate0_policy.rule_count(), 1);
     }
@@ -147,9 +1092,553 @@ policies:
     max_duration: "60m"
 "#;
         let policy_file = parse_policy(yaml).unwrap();
-        let gate0_policy = to_gate0(&policy_file).unwrap();
-        
+        let (gate0_policy, _) = to_gate0(&policy_file).unwrap();
+
         // Policy rule + default rule
         assert_eq!(gate0_policy.rule_count(), 2);
     }
+
+    #[test]
+    fn test_translation_map() {
+        let policy_file = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let (_, map) = to_gate0(&policy_file).unwrap();
+        let attributes: Vec<(&str, u32, Vec<&str>)> = map
+            .policies
+            .iter()
+            .map(|p| {
+                let attrs = p.attributes.iter().map(String::as_str).collect();
+                (p.name.as_str(), p.reason_code, attrs)
+            })
+            .collect();
+        assert_eq!(
+            attributes,
+            [
+                (
+                    "AdminAccess",
+                    0,
+                    vec![
+                        "email:*@admin.example.com",
+                        "oidc_groups:infrastructure",
+                        "oidc_groups:security-team"
+                    ]
+                ),
+                (
+                    "DeveloperAccess",
+                    1,
+                    vec!["current_time", "oidc_groups:developers", "source_ip"]
+                ),
+                (
+                    "ContractorAccess",
+                    2,
+                    vec!["email:*@contractor.example.com", "webauthn_id"]
+                ),
+                (
+                    "TerminatedEmployees",
+                    3,
+                    vec!["oidc_groups:terminated-employees"]
+                ),
+            ]
+        );
+
+        assert_eq!(map.name(2), Some("ContractorAccess"));
+        assert_eq!(map.name(u32::MAX - 1), Some("default"));
+        assert_eq!(map.name(4), None);
+        assert_eq!(map.reason_code("TerminatedEmployees"), Some(3));
+        assert_eq!(map.reason_code("default"), None);
+
+        let json = serde_json::to_value(&map).unwrap();
+        assert_eq!(json["default_reason_code"], u32::MAX - 1);
+        assert_eq!(json["policies"][1]["name"], "DeveloperAccess");
+        assert_eq!(serde_json::from_value::<TranslationMap>(json).unwrap(), map);
+    }
+
+    #[test]
+    fn test_gate0_context() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Open"
+    principals: ["readonly"]
+    max_duration: "15m"
+    expires: "2026-12-31"
+  - name: "Office"
+    match:
+      oidc_groups: ["admins"]
+      emails: ["*@example.com", "bob@example.com", "a?ice@*"]
+      source_ip: ["10.0.0.0/8"]
+      hours: ["09:00-17:00"]
+      webauthn_ids: ["yubi-1"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+        let policy_file = parse_policy(yaml).unwrap();
+        let request = EvalRequest {
+            oidc_groups: vec!["admins".into(), "staff".into(), "admins".into()],
+            email: Some("alice@example.com".to_string()),
+            local_username: Some("alice".to_string()),
+            current_time: Some("10:30".to_string()),
+            current_date: Some("2026-07-01".to_string()),
+            source_ip: Some("192.168.1.1".to_string()),
+            webauthn_id: Some("yubi-1".to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            gate0_context(&policy_file, &request),
+            vec![
+                ("oidc_groups:admins".to_string(), OwnedValue::Bool(true)),
+                ("email".to_string(), OwnedValue::from("alice@example.com")),
+                ("email:*@example.com".to_string(), OwnedValue::Bool(true)),
+                ("email:a?ice@*".to_string(), OwnedValue::Bool(true)),
+                ("current_time".to_string(), OwnedValue::Timestamp(37_800)),
+                ("current_date".to_string(), OwnedValue::Int(20_260_701)),
+                (
+                    "source_ip".to_string(),
+                    OwnedValue::Ip("192.168.1.1".parse().unwrap())
+                ),
+                ("webauthn_id".to_string(), OwnedValue::from("yubi-1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_translate_rejects_bad_hours() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Night"
+    match:
+      hours: ["22:00-06:00", "9am-5pm"]
+    principals: ["oncall"]
+    max_duration: "60m"
+"#;
+        let policy_file = parse_policy(yaml).unwrap();
+        let err = to_gate0(&policy_file).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Invalid hours window "9am-5pm" in policy Night"#
+        );
+    }
+
+    #[test]
+    fn test_translate_rejects_bad_cidr() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Office"
+    match:
+      source_ip: ["10.0.0.0/8", "10.1.2.3/8"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+        let policy_file = parse_policy(yaml).unwrap();
+        let err = to_gate0(&policy_file).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"Invalid source_ip block "10.1.2.3/8" in policy Office"#
+        );
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(glob("*@example.com", "alice@example.com"));
+        assert!(glob("a?ice*", "alice"));
+        assert!(glob("*a*b*", "xxaxxbxx"));
+        assert!(glob("**", ""));
+        assert!(glob("é?", "éé"));
+        assert!(!glob("*@example.com", "alice@example.org"));
+        assert!(!glob("a?", "a"));
+        assert!(!glob("*ab", "aba"));
+    }
+
+    #[test]
+    fn test_hours_edges() {
+        // Windows touching midnight, and 24:00, against the reference
+        let windows = [
+            "00:00-09:00",
+            "17:00-24:00",
+            "00:00-24:00",
+            "24:00-24:00",
+            "24:00-06:00",
+            "22:00-06:00",
+            "12:00-11:59:59",
+            "00:00-23:59:59",
+            "09:00:30-17:00:15",
+        ];
+        let times = [
+            "00:00", "00:00:01", "06:00", "06:00:01", "09:00", "09:00:01", "11:59:59", "12:00",
+            "17:00", "22:00", "23:59:59", "24:00",
+        ];
+        for window in windows {
+            let yaml = format!(
+                "default: {{principals: [\"sandbox\"], max_duration: \"15m\"}}\n\
+                 policies: [{{name: \"Hours\", match: {{hours: [\"{}\"]}}, principals: [\"root\"], max_duration: \"1h\"}}]\n",
+                window
+            );
+            let policy_file = parse_policy(&yaml).unwrap();
+            let (translated, _) = to_gate0(&policy_file).unwrap();
+            let exported = from_gate0(&translated, &policy_file).unwrap();
+            for time in times {
+                let request = EvalRequest {
+                    current_time: Some(time.to_string()),
+                    ..Default::default()
+                };
+                let expected = crate::reference_evaluate(&policy_file, &request);
+                let context = gate0_context(&policy_file, &request);
+                let context: Vec<_> = context
+                    .iter()
+                    .map(|(attr, value)| (attr.as_str(), value.as_value()))
+                    .collect();
+                let gate0_request = Request::with_context("user", "ssh_login", "default", &context);
+                let decision = translated
+                    .policy()
+                    .unwrap()
+                    .evaluate(&gate0_request)
+                    .unwrap();
+                let matched = decision.reason == ReasonCode(0);
+                assert_eq!(
+                    matched,
+                    expected.policy_index == Some(0),
+                    "{} at {}",
+                    window,
+                    time
+                );
+                let back = crate::reference_evaluate(&exported, &request);
+                assert_eq!(
+                    back.policy_index, expected.policy_index,
+                    "{} at {}",
+                    window, time
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_gate0_round_trip() {
+        let policy_file = parse_policy(include_str!("../example_policy.yaml")).unwrap();
+        let exported = from_gate0(&to_gate0(&policy_file).unwrap().0, &policy_file).unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&exported).unwrap(),
+            serde_yaml::to_string(&policy_file).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_gate0_matches_same_requests() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let policy_file = random_policy(&mut rng);
+            let exported = from_gate0(&to_gate0(&policy_file).unwrap().0, &policy_file).unwrap();
+            for _ in 0..10 {
+                let request = random_request(&mut rng);
+                let expected = crate::reference_evaluate(&policy_file, &request);
+                let actual = crate::reference_evaluate(&exported, &request);
+                assert_eq!(actual.policy_index, expected.policy_index);
+                assert_eq!(actual.effect, expected.effect);
+                assert_eq!(actual.granted_duration, expected.granted_duration);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_gate0_builder_policy() {
+        let ip = |cidr: &str| OwnedCondition::IpInCidr {
+            attr: SOURCE_IP_ATTR.to_string(),
+            cidr: cidr.to_string(),
+        };
+        let key = |id: &str| OwnedCondition::Equals {
+            attr: WEBAUTHN_ID_ATTR.to_string(),
+            value: OwnedValue::String(id.to_string()),
+        };
+        let or = |a, b| OwnedCondition::Or(Box::new(a), Box::new(b));
+        let policy = OwnedPolicy::new(vec![
+            OwnedRule::deny(OwnedTarget::any(), ReasonCode(1)),
+            OwnedRule::new(
+                Effect::Allow,
+                OwnedTarget::any(),
+                Some(OwnedCondition::And(
+                    Box::new(or(ip("10.0.0.0/8"), ip("192.168.0.0/16"))),
+                    Box::new(or(key("yubi-1"), ip("172.16.0.0/12"))),
+                )),
+                ReasonCode(0),
+            ),
+            OwnedRule::allow(OwnedTarget::any(), ReasonCode(u32::MAX - 1)),
+        ]);
+        let grants = parse_policy(
+            r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Office"
+    principals: ["developer"]
+    max_duration: "1h"
+  - name: "Lockdown"
+    effect: deny
+"#,
+        )
+        .unwrap();
+
+        let exported = from_gate0(&policy, &grants).unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&exported).unwrap(),
+            r#"default:
+  principals:
+  - sandbox
+  max_duration: 15m
+policies:
+- name: Lockdown
+  effect: deny
+  match: {}
+  principals: []
+- name: Office
+  match:
+    source_ip:
+    - 10.0.0.0/8
+    - 192.168.0.0/16
+    any:
+    - webauthn_ids:
+      - yubi-1
+    - source_ip:
+      - 172.16.0.0/12
+  principals:
+  - developer
+  max_duration: 1h
+"#
+        );
+    }
+
+    #[test]
+    fn test_from_gate0_rejects_unsupported_rules() {
+        let grants = parse_policy(
+            "default: {principals: [\"sandbox\"], max_duration: \"15m\"}\n\
+             policies: [{name: \"Open\", principals: [\"root\"], max_duration: \"1h\"}]\n",
+        )
+        .unwrap();
+        let default = OwnedRule::allow(OwnedTarget::any(), ReasonCode(u32::MAX - 1));
+        let error = |rules| {
+            from_gate0(&OwnedPolicy::new(rules), &grants)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let mut target = OwnedTarget::any();
+        target.action = gate0::OwnedMatcher::Exact("ssh_login".to_string());
+        assert_eq!(
+            error(vec![
+                OwnedRule::allow(target, ReasonCode(0)),
+                default.clone()
+            ]),
+            "Unsupported: rule 0: policies cannot match on the target"
+        );
+        assert_eq!(
+            error(vec![
+                OwnedRule::allow(OwnedTarget::any(), ReasonCode(3)),
+                default.clone()
+            ]),
+            "Unsupported: rule 0: no policy for reason code 3"
+        );
+        let flag = Some(flag("p0_trigger".to_string()));
+        assert_eq!(
+            error(vec![
+                OwnedRule::new(Effect::Allow, OwnedTarget::any(), flag, ReasonCode(0)),
+                default.clone(),
+            ]),
+            "Unsupported: rule 0: no policy field for condition Equals { attr: \"p0_trigger\", value: Bool(true) }"
+        );
+        assert_eq!(
+            error(vec![
+                default,
+                OwnedRule::allow(OwnedTarget::any(), ReasonCode(0))
+            ]),
+            "Unsupported: rule 0: the default rule must be an unconditional allow and come last"
+        );
+        assert_eq!(
+            error(vec![OwnedRule::allow(OwnedTarget::any(), ReasonCode(0))]),
+            "Unsupported: no default rule"
+        );
+    }
+
+    #[test]
+    fn test_rust_source() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "AdminAccess"
+    match:
+      oidc_groups: ["admins"]
+      source_ip: ["10.0.0.0/8", "192.168.1.1"]
+    principals: ["root"]
+    max_duration: "60m"
+  - name: "policy"
+    effect: deny
+    match:
+      webauthn_ids: ["lost \"key\""]
+"#;
+        let source = to_rust_source(&parse_policy(yaml).unwrap()).unwrap();
+        assert_eq!(
+            source,
+            r#"// Generated by gatebridge from a YAML policy file. Do not edit.
+
+use gate0::ReasonCode;
+
+/// Policy "AdminAccess".
+pub const ADMIN_ACCESS: ReasonCode = ReasonCode(0);
+/// Policy "policy".
+pub const POLICY_1: ReasonCode = ReasonCode(1);
+/// No policy matched; the default grant applies.
+pub const DEFAULT: ReasonCode = ReasonCode(4294967294);
+
+gate0::static_policy! {
+    /// The translated policy.
+    pub static POLICY = {
+        ALLOW (* * *) WHERE { ("oidc_groups:admins" EQ true) AND ((source_ip IN_CIDR "10.0.0.0/8") OR (source_ip IN_CIDR "192.168.1.1/32")) } => 0;
+        DENY (* * *) WHERE { (webauthn_id EQ "lost \"key\"") } => 1;
+        ALLOW (* * *) => 4294967294;
+    };
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_rust_source_skips_rules_that_never_decide() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Open"
+    principals: ["readonly"]
+    max_duration: "15m"
+  - name: "Admins"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["root"]
+    max_duration: "60m"
+"#;
+        let source = to_rust_source(&parse_policy(yaml).unwrap()).unwrap();
+        assert!(source.contains("pub const ADMINS: ReasonCode = ReasonCode(1);"));
+        let rules: Vec<&str> = source
+            .lines()
+            .skip_while(|l| !l.contains("pub static POLICY"))
+            .skip(1)
+            .take(3)
+            .map(str::trim)
+            .collect();
+        assert_eq!(
+            rules,
+            [
+                "ALLOW (* * *) => 0;",
+                "// \"Admins\" never decides: rule 1 is shadowed by rule 0",
+                "// \"default\" never decides: rule 2 is shadowed by rule 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_constant_names() {
+        assert_eq!(constant_name("AdminAccess"), "ADMIN_ACCESS");
+        assert_eq!(constant_name("on-call SREs"), "ON_CALL_SRES");
+        assert_eq!(constant_name("2fa_required"), "POLICY_2FA_REQUIRED");
+        assert_eq!(constant_name("--"), "POLICY_");
+    }
+
+    #[test]
+    fn test_rust_source_decides_like_translation() {
+        // The macro takes the text policy syntax, so parsing the body at
+        // runtime stands in for compiling it
+        let mut rng = StdRng::seed_from_u64(6);
+        for _ in 0..100 {
+            let policy_file = random_policy(&mut rng);
+            let source = to_rust_source(&policy_file).unwrap();
+            let (_, body) = source.split_once("pub static POLICY = {").unwrap();
+            let (body, _) = body.rsplit_once("};").unwrap();
+            let generated = gate0::parse_policy(body).unwrap();
+            let (translated, _) = to_gate0(&policy_file).unwrap();
+
+            for _ in 0..10 {
+                let request = random_request(&mut rng);
+                let context = gate0_context(&policy_file, &request);
+                let context: Vec<_> = context
+                    .iter()
+                    .map(|(attr, value)| (attr.as_str(), value.as_value()))
+                    .collect();
+                let request = Request::with_context("user", "ssh_login", "default", &context);
+                assert_eq!(
+                    generated.policy().unwrap().evaluate(&request).unwrap(),
+                    translated.policy().unwrap().evaluate(&request).unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_expiry_and_permissions() {
+        let yaml = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Contractors"
+    match:
+      oidc_groups: ["contractors"]
+    principals: ["deploy"]
+    max_duration: "1h"
+    port_forwarding: true
+    expires: "2026-06-30"
+  - name: "Everyone"
+    principals: ["readonly"]
+    max_duration: "15m"
+    expires: "2026-12-31"
+"#;
+        let policy_file = parse_policy(yaml).unwrap();
+        let (gate0_policy, map) = to_gate0(&policy_file).unwrap();
+        assert_eq!(
+            map.policies[0].attributes,
+            ["current_date", "oidc_groups:contractors"]
+        );
+        assert_eq!(map.policies[1].attributes, ["current_date"]);
+        assert!(map.policies[0].permissions.port_forwarding);
+        assert_eq!(
+            map.policies[1].expires.as_ref().unwrap().as_str(),
+            "2026-12-31"
+        );
+        assert_eq!(
+            gate0_permissions(&policy_file, 1),
+            Some(Permissions::default())
+        );
+        assert_eq!(gate0_permissions(&policy_file, 7), None);
+
+        let request = EvalRequest {
+            current_date: Some("2026-07-01".to_string()),
+            ..Default::default()
+        };
+        let context = gate0_context(&policy_file, &request);
+        assert_eq!(
+            context,
+            [("current_date".to_string(), OwnedValue::Int(20_260_701))]
+        );
+        let context: Vec<_> = context
+            .iter()
+            .map(|(attr, value)| (attr.as_str(), value.as_value()))
+            .collect();
+        let request = Request::with_context("user", "ssh_login", "default", &context);
+        let decision = gate0_policy.policy().unwrap().evaluate(&request).unwrap();
+        assert_eq!(decision.reason, ReasonCode(1));
+
+        let back = from_gate0(&gate0_policy, &policy_file).unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&back).unwrap(),
+            serde_yaml::to_string(&policy_file).unwrap()
+        );
+    }
 }
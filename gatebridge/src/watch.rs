@@ -0,0 +1,441 @@
+//! Policy file watcher (feature `watch`).
+//!
+//! `PolicyWatcher` keeps a translated policy in step with its files on
+//! disk. It polls the files it loaded, and the directories holding them,
+//! for changes; after a change it loads and translates the policy again and
+//! shadow-evaluates it against a corpus of requests. The new policy replaces
+//! the current one only if both evaluators agree on every request, so a bad
+//! edit leaves the last good policy in place. Each attempt is reported to
+//! the `on_reload` callback.
+//!
+//! Polling keeps this on `std`; a change is seen within one interval.
+
+use crate::ast::{EvalRequest, PolicyFile};
+use crate::interpolate::Interpolation;
+use crate::loader::{load_tracked, LoadError};
+use crate::shadow::{shadow_evaluate_many, ShadowError, ShadowReport};
+use crate::translate::to_gate0;
+use gate0::OwnedPolicy;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+/// How often a spawned watcher polls, unless set with `interval`.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A policy that loaded, translated, and passed validation.
+#[derive(Debug)]
+pub struct WatchedPolicy {
+    /// 0 for the policy the watcher started with, then 1, 2, ... per
+    /// reload.
+    pub generation: u64,
+    pub policy_file: PolicyFile,
+    /// The translation; `policy()` lends a `gate0::Policy` to evaluate.
+    pub gate0: OwnedPolicy,
+}
+
+/// What happened when watched files changed.
+#[derive(Debug)]
+pub enum ReloadEvent {
+    /// The new policy is current.
+    Reloaded {
+        generation: u64,
+        /// Shadow evaluation of the corpus against the new policy.
+        report: ShadowReport,
+    },
+    /// The new policy was rejected; the previous one is still current.
+    Rejected(ReloadError),
+}
+
+/// Why a policy was rejected.
+#[derive(Debug)]
+pub enum ReloadError {
+    Load(LoadError),
+    Translation(String),
+    /// The evaluators disagreed on, or either failed to evaluate, part of the
+    /// corpus.
+    Validation(Box<ShadowReport>),
+}
+
+impl std::fmt::Display for ReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReloadError::Load(e) => write!(f, "{}", e),
+            ReloadError::Translation(e) => write!(f, "Translation error: {}", e),
+            ReloadError::Validation(report) => write!(
+                f,
+                "Validation failed: {} mismatches and {} errors in {} requests",
+                report.mismatches, report.errors, report.requests
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReloadError {}
+
+type Callback = Box<dyn Fn(&ReloadEvent) + Send + Sync>;
+
+/// A file or directory and its modification time and length, or `None` if
+/// it could not be read.
+type Stamp = (PathBuf, Option<(SystemTime, u64)>);
+
+/// Watches a policy file or directory, swapping in each valid change.
+pub struct PolicyWatcher {
+    path: PathBuf,
+    env: Interpolation,
+    requests: Vec<EvalRequest>,
+    interval: Duration,
+    on_reload: Option<Callback>,
+    current: RwLock<Arc<WatchedPolicy>>,
+    /// Also held for the whole of `check`, so reloads never overlap.
+    stamps: Mutex<Vec<Stamp>>,
+}
+
+impl std::fmt::Debug for PolicyWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PolicyWatcher")
+            .field("path", &self.path)
+            .field("requests", &self.requests.len())
+            .field("interval", &self.interval)
+            .field("current", &self.current)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PolicyWatcher {
+    /// Load the policy at `path`, a file or directory, expanding variables
+    /// as `env` allows, and validate it against `requests`. It fails as a
+    /// reload would be rejected.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        env: Interpolation,
+        requests: Vec<EvalRequest>,
+    ) -> Result<Self, ReloadError> {
+        let path = path.into();
+        let mut seen = Vec::new();
+        let started = SystemTime::now();
+        let (policy_file, gate0, _) = validate(&path, &env, &requests, &mut seen)?;
+        let stamps = watch_list(&path, seen, started);
+        Ok(PolicyWatcher {
+            path,
+            env,
+            requests,
+            interval: DEFAULT_INTERVAL,
+            on_reload: None,
+            current: RwLock::new(Arc::new(WatchedPolicy {
+                generation: 0,
+                policy_file,
+                gate0,
+            })),
+            stamps: Mutex::new(stamps),
+        })
+    }
+
+    /// Poll every `interval` once spawned.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Call `f` with every reload, accepted or rejected.
+    pub fn on_reload(mut self, f: impl Fn(&ReloadEvent) + Send + Sync + 'static) -> Self {
+        self.on_reload = Some(Box::new(f));
+        self
+    }
+
+    /// The current policy. It stays valid, and unchanged, after reloads.
+    pub fn current(&self) -> Arc<WatchedPolicy> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Reload if a watched file changed since the last check, returning
+    /// what happened, or `None` if nothing changed.
+    pub fn check(&self) -> Option<ReloadEvent> {
+        let mut stamps = self.stamps.lock().unwrap_or_else(PoisonError::into_inner);
+        if stamps.iter().all(|(path, stamp)| stamp_of(path) == *stamp) {
+            return None;
+        }
+
+        let mut seen = Vec::new();
+        let started = SystemTime::now();
+        let event = match validate(&self.path, &self.env, &self.requests, &mut seen) {
+            Ok((policy_file, gate0, report)) => {
+                let mut current = self.current.write().unwrap_or_else(PoisonError::into_inner);
+                let generation = current.generation + 1;
+                *current = Arc::new(WatchedPolicy {
+                    generation,
+                    policy_file,
+                    gate0,
+                });
+                ReloadEvent::Reloaded { generation, report }
+            }
+            Err(e) => {
+                // Keep watching the last good files too, in case the
+                // failure stopped the loader before reaching them
+                seen.extend(stamps.drain(..).map(|(path, _)| path));
+                ReloadEvent::Rejected(e)
+            }
+        };
+        *stamps = watch_list(&self.path, seen, started);
+
+        if let Some(on_reload) = &self.on_reload {
+            on_reload(&event);
+        }
+        Some(event)
+    }
+
+    /// `check` every `interval` on a new thread, until the handle is
+    /// stopped or dropped.
+    pub fn spawn(self: Arc<Self>) -> WatchHandle {
+        let (stop, stopped) = mpsc::channel::<()>();
+        let thread = thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(self.interval) {
+                self.check();
+            }
+        });
+        WatchHandle {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+/// A running watcher thread. Dropping it stops the thread.
+#[derive(Debug)]
+pub struct WatchHandle {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchHandle {
+    /// Stop the thread, waiting for a check in progress to finish.
+    pub fn stop(self) {}
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        // Disconnecting the channel wakes the thread
+        self.stop.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Load, translate, and shadow-evaluate the policy at `path`, pushing the
+/// files read to `seen`.
+fn validate(
+    path: &Path,
+    env: &Interpolation,
+    requests: &[EvalRequest],
+    seen: &mut Vec<PathBuf>,
+) -> Result<(PolicyFile, OwnedPolicy, ShadowReport), ReloadError> {
+    let policy_file = load_tracked(path, env, seen).map_err(ReloadError::Load)?;
+    let (gate0, _) = to_gate0(&policy_file).map_err(|e| ReloadError::Translation(e.to_string()))?;
+    gate0
+        .policy()
+        .map_err(|e| ReloadError::Translation(format!("{:?}", e)))?;
+    let report = match shadow_evaluate_many(&policy_file, requests) {
+        Ok(report) => report,
+        Err(
+            ShadowError::Translation(e) | ShadowError::Evaluation(e) | ShadowError::Reference(e),
+        ) => {
+            return Err(ReloadError::Translation(e));
+        }
+    };
+    if !report.passed() {
+        return Err(ReloadError::Validation(Box::new(report)));
+    }
+    Ok((policy_file, gate0, report))
+}
+
+/// Stamps for `root`, the files in `seen`, and the directories holding
+/// them, so new files are noticed too. A file modified since `started` may
+/// have changed after it was read, so it is stamped as unreadable to force
+/// another reload.
+fn watch_list(root: &Path, seen: Vec<PathBuf>, started: SystemTime) -> Vec<Stamp> {
+    let mut paths = seen.clone();
+    paths.extend(
+        seen.iter()
+            .filter_map(|p| p.parent())
+            .map(Path::to_path_buf),
+    );
+    paths.push(root.to_path_buf());
+    paths.sort();
+    paths.dedup();
+    paths
+        .into_iter()
+        .map(|path| {
+            let stamp = stamp_of(&path).filter(|&(modified, _)| modified < started);
+            (path, stamp)
+        })
+        .collect()
+}
+
+fn stamp_of(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    /// A fresh scratch directory holding `files`.
+    fn scratch(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gatebridge-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for (file, contents) in files {
+            let path = dir.join(file);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        dir
+    }
+
+    const POLICY: &str = r#"
+default:
+  principals: ["sandbox"]
+  max_duration: "15m"
+policies:
+  - name: "Admins"
+    match:
+      oidc_groups: ["admins"]
+    principals: ["root"]
+    max_duration: "1h"
+"#;
+
+    fn requests() -> Vec<EvalRequest> {
+        ["admins", "developers"]
+            .iter()
+            .map(|group| EvalRequest {
+                oidc_groups: vec![group.to_string()],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn names(watcher: &PolicyWatcher) -> Vec<String> {
+        let current = watcher.current();
+        current
+            .policy_file
+            .policies
+            .iter()
+            .map(|p| p.name.clone())
+            .collect()
+    }
+
+    /// Wait out the modification time resolution of coarse filesystems.
+    fn tick() {
+        thread::sleep(Duration::from_millis(20));
+    }
+
+    #[test]
+    fn test_reloads_valid_changes_only() {
+        let dir = scratch("watch", &[("policy.yaml", POLICY)]);
+        let path = dir.join("policy.yaml");
+        let events = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&events);
+        let watcher = PolicyWatcher::new(&path, Interpolation::default(), requests())
+            .unwrap()
+            .on_reload(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        assert_eq!(watcher.current().generation, 0);
+        assert!(watcher.check().is_none());
+
+        tick();
+        let developers = POLICY
+            .replace("Admins", "Developers")
+            .replace("admins", "developers");
+        fs::write(&path, &developers).unwrap();
+        let Some(ReloadEvent::Reloaded { generation, report }) = watcher.check() else {
+            panic!("not reloaded");
+        };
+        assert_eq!((generation, report.requests), (1, 2));
+        assert_eq!(names(&watcher), ["Developers"]);
+        assert!(watcher.check().is_none());
+
+        // A policy that doesn't load leaves the last good one
+        tick();
+        fs::write(&path, developers.replace("max_duration: \"1h\"", "")).unwrap();
+        let Some(ReloadEvent::Rejected(ReloadError::Load(_))) = watcher.check() else {
+            panic!("not rejected");
+        };
+        assert_eq!(watcher.current().generation, 1);
+        assert_eq!(names(&watcher), ["Developers"]);
+
+        let bad_hours = POLICY.replace("oidc_groups: [\"admins\"]", "hours: [\"9-5\"]");
+        tick();
+        fs::write(&path, bad_hours).unwrap();
+        let Some(ReloadEvent::Rejected(e)) = watcher.check() else {
+            panic!("not rejected");
+        };
+        assert!(e.to_string().starts_with("Translation error: "), "{}", e);
+
+        tick();
+        fs::write(&path, POLICY).unwrap();
+        assert!(matches!(
+            watcher.check(),
+            Some(ReloadEvent::Reloaded { generation: 2, .. })
+        ));
+        assert_eq!(events.load(Ordering::SeqCst), 4);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_watches_directories_and_includes() {
+        let fragment =
+            "policies:\n  - name: \"Ops\"\n    principals: [\"ops\"]\n    max_duration: \"1h\"\n";
+        let root = format!("include: [\"teams/ops.yaml\"]\n{}", POLICY);
+        let dir = scratch(
+            "watch-dir",
+            &[("root/root.yaml", &root), ("root/teams/ops.yaml", fragment)],
+        );
+        let watcher =
+            PolicyWatcher::new(dir.join("root"), Interpolation::default(), requests()).unwrap();
+        assert_eq!(names(&watcher), ["Admins", "Ops"]);
+
+        tick();
+        fs::write(
+            dir.join("root/teams/ops.yaml"),
+            fragment.replace("Ops", "Oncall"),
+        )
+        .unwrap();
+        assert!(watcher.check().is_some());
+        assert_eq!(names(&watcher), ["Admins", "Oncall"]);
+
+        tick();
+        fs::write(dir.join("root/dev.yaml"), fragment.replace("Ops", "Dev")).unwrap();
+        assert!(watcher.check().is_some());
+        assert_eq!(names(&watcher), ["Dev", "Admins", "Oncall"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_spawn() {
+        let dir = scratch("watch-spawn", &[("policy.yaml", POLICY)]);
+        let path = dir.join("policy.yaml");
+        let watcher = Arc::new(
+            PolicyWatcher::new(&path, Interpolation::default(), requests())
+                .unwrap()
+                .interval(Duration::from_millis(10)),
+        );
+        let handle = Arc::clone(&watcher).spawn();
+
+        tick();
+        fs::write(&path, POLICY.replace("Admins", "Operators")).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while watcher.current().generation == 0 && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(names(&watcher), ["Operators"]);
+        handle.stop();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
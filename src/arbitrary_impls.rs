@@ -0,0 +1,271 @@
+//! `Arbitrary` implementations for structured fuzzing.
+//!
+//! Generated values stay within `PolicyConfig::DEFAULT`: conditions within
+//! the depth limit, strings within the length limits, and matcher lists
+//! within the option limit. Every generated `Rule` passes validation and
+//! every generated `Policy` builds, so fuzz targets spend their time in
+//! evaluation rather than in rejected inputs.
+//!
+//! Strings borrow from the fuzzer's input. Two consequences:
+//!
+//! - `IpInCidr` blocks are picked from a fixed list of valid blocks, and
+//!   `Condition::Custom` is never generated (there is no registry).
+//! - A `Request` cannot borrow a context built during generation, so
+//!   generated requests carry none. Generate a `Vec<(&str, Value)>`
+//!   alongside and use `Request::with_context`.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+
+use crate::condition::Condition;
+use crate::policy::{Policy, PolicyConfig, Rule};
+use crate::target::{Matcher, Target};
+use crate::time::{Weekdays, SECONDS_PER_DAY};
+use crate::types::{CacheHint, Effect, ReasonCode, Request};
+use crate::value::Value;
+
+/// The limits generated values respect.
+const LIMITS: PolicyConfig = PolicyConfig::DEFAULT;
+
+/// Blocks for generated `IpInCidr` conditions.
+const CIDRS: &[&str] = &[
+    "0.0.0.0/0",
+    "10.0.0.0/8",
+    "172.16.0.0/12",
+    "192.168.1.0/24",
+    "203.0.113.7/32",
+    "::/0",
+    "2001:db8::/32",
+    "::1/128",
+];
+
+/// A string of at most `max` bytes, cut at a character boundary.
+fn bounded_str<'a>(u: &mut Unstructured<'a>, max: usize) -> Result<&'a str> {
+    let s = <&'a str>::arbitrary(u)?;
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    Ok(&s[..end])
+}
+
+/// A name or value for a matcher.
+fn target_str<'a>(u: &mut Unstructured<'a>) -> Result<&'a str> {
+    bounded_str(u, LIMITS.max_string_len)
+}
+
+/// An attribute name.
+fn attr_name<'a>(u: &mut Unstructured<'a>) -> Result<&'a str> {
+    bounded_str(u, LIMITS.max_string_len.min(LIMITS.max_attr_name_len))
+}
+
+/// A value `GreaterThan` and `LessThan` accept.
+fn ordered_value<'a>(u: &mut Unstructured<'a>) -> Result<Value<'a>> {
+    Ok(match u.choose_index(3)? {
+        0 => Value::Int(u.arbitrary()?),
+        1 => Value::Timestamp(u.arbitrary()?),
+        _ => Value::Duration(u.arbitrary()?),
+    })
+}
+
+/// A condition no deeper than `depth` (at least 1).
+///
+/// Recursive, but bounded by the depth limit.
+fn condition<'a>(u: &mut Unstructured<'a>, depth: usize) -> Result<Condition<'a>> {
+    // Only leaves fit at the last level
+    let kinds = if depth > 1 { 12 } else { 9 };
+    Ok(match u.choose_index(kinds)? {
+        0 => Condition::True,
+        1 => Condition::False,
+        2 => Condition::Equals {
+            attr: attr_name(u)?,
+            value: u.arbitrary()?,
+        },
+        3 => Condition::NotEquals {
+            attr: attr_name(u)?,
+            value: u.arbitrary()?,
+        },
+        4 => Condition::GreaterThan {
+            attr: attr_name(u)?,
+            value: ordered_value(u)?,
+        },
+        5 => Condition::LessThan {
+            attr: attr_name(u)?,
+            value: ordered_value(u)?,
+        },
+        6 => Condition::IpInCidr {
+            attr: attr_name(u)?,
+            cidr: u.choose(CIDRS)?,
+        },
+        7 => Condition::TimeBetween {
+            attr: attr_name(u)?,
+            start: u.int_in_range(0..=SECONDS_PER_DAY - 1)?,
+            end: u.int_in_range(0..=SECONDS_PER_DAY)?,
+        },
+        8 => Condition::DayOfWeek {
+            attr: attr_name(u)?,
+            days: Weekdays::from_bits(u.arbitrary()?),
+        },
+        9 => Condition::Not(Box::new(condition(u, depth - 1)?)),
+        10 => Condition::And(
+            Box::new(condition(u, depth - 1)?),
+            Box::new(condition(u, depth - 1)?),
+        ),
+        _ => Condition::Or(
+            Box::new(condition(u, depth - 1)?),
+            Box::new(condition(u, depth - 1)?),
+        ),
+    })
+}
+
+impl<'a> Arbitrary<'a> for Value<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(6)? {
+            0 => Value::Bool(u.arbitrary()?),
+            1 => Value::Int(u.arbitrary()?),
+            2 => Value::String(bounded_str(
+                u,
+                LIMITS.max_string_len.min(LIMITS.max_attr_value_len),
+            )?),
+            3 => Value::Ip(u.arbitrary()?),
+            4 => Value::Timestamp(u.arbitrary()?),
+            _ => Value::Duration(u.arbitrary()?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Condition<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        condition(u, LIMITS.max_condition_depth)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Effect {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(if u.arbitrary()? {
+            Effect::Allow
+        } else {
+            Effect::Deny
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Matcher<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(4)? {
+            0 => Matcher::Any,
+            1 => Matcher::Exact(target_str(u)?),
+            2 => {
+                let len = u.int_in_range(1..=LIMITS.max_matcher_options)?;
+                let mut options = Vec::with_capacity(len);
+                for _ in 0..len {
+                    options.push(target_str(u)?);
+                }
+                Matcher::OneOfVec(options)
+            }
+            _ => Matcher::Group(target_str(u)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Target<'a> {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Target {
+            principal: u.arbitrary()?,
+            action: u.arbitrary()?,
+            resource: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Rule<'a> {
+    /// Reason codes avoid `NO_MATCHING_RULE` and `DEFAULT_ALLOW`.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut rule = Rule::new(
+            u.arbitrary()?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+            ReasonCode(u.int_in_range(1..=u32::MAX - 1)?),
+        );
+        if u.arbitrary()? {
+            rule.name = Some(target_str(u)?);
+        }
+        if u.arbitrary()? {
+            rule.cache = Some(CacheHint {
+                ttl: u.arbitrary()?,
+                per_context: u.arbitrary()?,
+            });
+        }
+        Ok(rule)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Policy<'a> {
+    /// Adds rules while they fit the rule and condition node limits.
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut rules = Vec::new();
+        let mut nodes = 0;
+        for _ in 0..u.arbitrary_len::<Rule<'a>>()?.min(LIMITS.max_rules) {
+            let rule: Rule<'a> = u.arbitrary()?;
+            nodes += rule.condition.as_ref().map_or(0, Condition::node_count);
+            if nodes > LIMITS.max_condition_nodes {
+                break;
+            }
+            rules.push(rule);
+        }
+        Policy::with_config(rules, LIMITS).map_err(|_| Error::IncorrectFormat)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Request<'a> {
+    /// The context is always empty (see the module docs).
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Request::new(target_str(u)?, target_str(u)?, target_str(u)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random bytes.
+    fn bytes(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_generated_values_respect_limits() {
+        for seed in 0..200 {
+            let data = bytes(seed, 4096);
+            let mut u = Unstructured::new(&data);
+
+            let cond = Condition::arbitrary(&mut u).unwrap();
+            assert!(cond
+                .validate(LIMITS.max_condition_depth, LIMITS.max_string_len)
+                .is_ok());
+
+            let policy = Policy::arbitrary(&mut u).unwrap();
+            let context: Vec<(&str, Value)> = u.arbitrary().unwrap();
+            let request = Request::arbitrary(&mut u).unwrap();
+            let request = Request::with_context(
+                request.principal,
+                request.action,
+                request.resource,
+                &context,
+            );
+            // Evaluation may fail on oversized or duplicate contexts, but
+            // never panics
+            let _ = policy.evaluate(&request);
+        }
+    }
+}
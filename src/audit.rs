@@ -0,0 +1,194 @@
+//! Fixed-size in-memory audit ring.
+//!
+//! `AuditRing<N>` keeps the last `N` decisions in an inline array for
+//! post-incident inspection on devices without a logger. Recording never
+//! allocates: entries are small `Copy` records, and the request itself is
+//! kept only as its canonical hash (the same FNV-1a hash `DecisionCache`
+//! uses), so two entries with equal hashes came from the same request.
+//!
+//! The ring uses `Cell`s, so it records through `&self` without locking and
+//! without `unsafe`. It is not `Sync`: pass it to `evaluate_with_sink` on the
+//! evaluating thread rather than attaching it to a shared policy.
+
+use core::cell::Cell;
+
+use crate::fingerprint::request_hash;
+use crate::sink::DecisionSink;
+use crate::stats::EvaluationStats;
+use crate::types::{Decision, Effect, ReasonCode, Request};
+
+/// One recorded decision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Sequence number, counting every decision since the ring was created.
+    pub seq: u64,
+    /// The decision's effect.
+    pub effect: Effect,
+    /// The decision's reason code.
+    pub reason: ReasonCode,
+    /// Canonical hash of the request.
+    pub request_hash: u64,
+    /// Stats from the evaluation.
+    pub stats: EvaluationStats,
+}
+
+/// Ring buffer of the last `N` decisions.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{AuditRing, Policy, ReasonCode, Request, Rule, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(Target::any(), ReasonCode(1)))
+///     .build()
+///     .unwrap();
+/// let ring: AuditRing<16> = AuditRing::new();
+///
+/// policy
+///     .evaluate_with_sink(&Request::new("alice", "read", "doc"), &ring)
+///     .unwrap();
+/// assert_eq!(ring.latest().unwrap().reason, ReasonCode(1));
+/// ```
+#[derive(Debug)]
+pub struct AuditRing<const N: usize> {
+    slots: [Cell<Option<AuditEntry>>; N],
+    total: Cell<u64>,
+}
+
+impl<const N: usize> AuditRing<N> {
+    /// Create an empty ring.
+    pub fn new() -> Self {
+        AuditRing {
+            slots: core::array::from_fn(|_| Cell::new(None)),
+            total: Cell::new(0),
+        }
+    }
+
+    /// Record a decision, overwriting the oldest entry when full.
+    pub fn push(&self, request: &Request<'_>, decision: &Decision, stats: &EvaluationStats) {
+        if N == 0 {
+            return;
+        }
+        let seq = self.total.get();
+        let entry = AuditEntry {
+            seq,
+            effect: decision.effect,
+            reason: decision.reason,
+            request_hash: request_hash(request),
+            stats: *stats,
+        };
+        self.slots[(seq % N as u64) as usize].set(Some(entry));
+        self.total.set(seq.wrapping_add(1));
+    }
+
+    /// Number of entries currently held (at most `N`).
+    pub fn len(&self) -> usize {
+        self.total.get().min(N as u64) as usize
+    }
+
+    /// Returns `true` if nothing has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of decisions recorded since creation, including overwritten ones.
+    pub fn total(&self) -> u64 {
+        self.total.get()
+    }
+
+    /// The most recent entry.
+    pub fn latest(&self) -> Option<AuditEntry> {
+        let seq = self.total.get().checked_sub(1)?;
+        self.get_seq(seq)
+    }
+
+    /// Entries from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = AuditEntry> + '_ {
+        let end = self.total.get();
+        let start = end - self.len() as u64;
+        (start..end).filter_map(move |seq| self.get_seq(seq))
+    }
+
+    /// Remove all entries and reset the sequence counter.
+    pub fn clear(&self) {
+        for slot in &self.slots {
+            slot.set(None);
+        }
+        self.total.set(0);
+    }
+
+    fn get_seq(&self, seq: u64) -> Option<AuditEntry> {
+        if N == 0 {
+            return None;
+        }
+        self.slots[(seq % N as u64) as usize]
+            .get()
+            .filter(|e| e.seq == seq)
+    }
+}
+
+impl<const N: usize> Default for AuditRing<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> DecisionSink for AuditRing<N> {
+    fn record(&self, request: &Request<'_>, decision: &Decision, stats: &EvaluationStats) {
+        self.push(request, decision, stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, Rule, Target};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_ring_keeps_last_n_in_order() {
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .build()
+            .unwrap();
+        let ring: AuditRing<3> = AuditRing::new();
+        assert!(ring.is_empty());
+        assert_eq!(ring.latest(), None);
+
+        for principal in ["a", "b", "c", "d", "e"] {
+            policy
+                .evaluate_with_sink(&Request::new(principal, "read", "doc"), &ring)
+                .unwrap();
+        }
+
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.total(), 5);
+        let seqs: Vec<u64> = ring.iter().map(|e| e.seq).collect();
+        assert_eq!(seqs, [2, 3, 4]);
+
+        let latest = ring.latest().unwrap();
+        assert_eq!(latest.effect, Effect::Allow);
+        assert_eq!(latest.stats.rules_checked, 1);
+        assert_eq!(
+            latest.request_hash,
+            request_hash(&Request::new("e", "read", "doc"))
+        );
+
+        ring.clear();
+        assert!(ring.is_empty());
+        assert_eq!(ring.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let ring: AuditRing<0> = AuditRing::new();
+        ring.push(
+            &Request::new("a", "read", "doc"),
+            &Decision::allow(ReasonCode(1)),
+            &EvaluationStats::new(),
+        );
+        assert_eq!(ring.len(), 0);
+        assert_eq!(ring.latest(), None);
+    }
+}
@@ -0,0 +1,129 @@
+//! `gate0`: evaluate JSON Lines requests against a policy (feature `cli`).
+//!
+//! ```text
+//! gate0 [--summary] POLICY.json < requests.jsonl > decisions.jsonl
+//! ```
+//!
+//! `POLICY.json` is the JSON form of `OwnedPolicy`. Each input line is a
+//! `RequestData` object; each output line is the `JsonDecisionLogger` line
+//! for its decision, or `{"line":N,"error":"..."}` if the request could not
+//! be parsed or evaluated, so output line N always answers input line N.
+//! Blank lines are skipped. With `--summary`, totals are printed to stderr
+//! at the end.
+//!
+//! Exit status: 0 if every request was decided, 1 if any line failed, 2 if
+//! the arguments or the policy are invalid.
+
+use std::io::{self, BufRead, BufWriter, Write};
+use std::process::ExitCode;
+
+use gate0::{JsonDecisionLogger, OwnedPolicy, PolicyError, RequestData, StatsAggregator};
+
+const USAGE: &str = "usage: gate0 [--summary] POLICY.json < requests.jsonl";
+
+fn main() -> ExitCode {
+    let mut summary = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--summary" => summary = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => return usage_error(),
+        }
+    }
+    let Some(path) = path else {
+        return usage_error();
+    };
+
+    let owned: OwnedPolicy = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(owned) => owned,
+        Err(error) => {
+            eprintln!("gate0: cannot load {}: {}", path, error);
+            return ExitCode::from(2);
+        }
+    };
+    let policy = match owned.policy() {
+        Ok(policy) => policy,
+        Err(error) => {
+            eprintln!("gate0: invalid policy {}: {}", path, error);
+            return ExitCode::from(2);
+        }
+    };
+
+    let logger = JsonDecisionLogger::new(BufWriter::new(io::stdout().lock()), policy.fingerprint());
+    let mut totals = StatsAggregator::new();
+    let mut failures = 0u64;
+    for (index, line) in io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                eprintln!("gate0: cannot read stdin: {}", error);
+                return ExitCode::from(2);
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = serde_json::from_str::<RequestData>(&line)
+            .map_err(|e| e.to_string())
+            .and_then(|data| {
+                let builder = data.builder();
+                let request = builder.build().map_err(|e| e.to_string())?;
+                let result = policy.evaluate_with_stats(&request);
+                totals.record_result(&result);
+                let (decision, stats) = result.map_err(|e: PolicyError| e.to_string())?;
+                logger
+                    .write_decision(&request, &decision, &stats)
+                    .map_err(|e| e.to_string())
+            });
+        if let Err(error) = result {
+            failures += 1;
+            let line = format!(
+                "{{\"line\":{},\"error\":{}}}\n",
+                index + 1,
+                serde_json::Value::from(error)
+            );
+            // Decisions before this line are still buffered in the logger
+            if logger.flush().is_err() || io::stdout().write_all(line.as_bytes()).is_err() {
+                return ExitCode::from(2);
+            }
+        }
+    }
+    if logger.flush().is_err() {
+        return ExitCode::from(2);
+    }
+
+    if summary {
+        let totals = totals.snapshot();
+        eprintln!(
+            "{} evaluated: {} allowed, {} denied, {} errors; {} lines failed; {} rules checked",
+            totals.evaluations,
+            totals.allows,
+            totals.denies,
+            totals.errors,
+            failures,
+            totals.rules_checked.total
+        );
+        for (reason, count) in &totals.reasons {
+            eprintln!("  reason {}: {}", reason.value(), count);
+        }
+    }
+
+    if failures > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn usage_error() -> ExitCode {
+    eprintln!("{}", USAGE);
+    ExitCode::from(2)
+}
@@ -0,0 +1,329 @@
+//! Opt-in decision cache (requires the `cache` feature).
+//!
+//! Memoizes decisions for repeated requests. Entries are keyed on the
+//! FNV-1a hash of the request's canonical encoding, and each entry keeps the
+//! full encoding so a hash collision is a miss, never a wrong decision.
+//!
+//! # Invalidation
+//!
+//! The cache remembers the fingerprint of the policy that produced its
+//! entries. Evaluating against a policy with a different fingerprint clears
+//! the cache first, so swapping policies can never serve stale decisions.
+//! For policies calling custom predicates this relies on bumping the
+//! registry's version when an implementation changes (see
+//! `PredicateRegistry::with_version`).
+//!
+//! # Cache Hints
+//!
+//! The cache has no clock, so it never stores a decision whose
+//! `Decision::cache` hint sets a TTL; those are evaluated every time.
+//! Entries are keyed on the whole request, context included, so hints
+//! that ask for the same context are already honored.
+//!
+//! # Sinks
+//!
+//! A hit still reaches the policy's attached `DecisionSink`, with zeroed
+//! stats whose `termination` is `Termination::Cached`, so audit logs and
+//! metrics see every decision whether or not it was evaluated.
+//!
+//! # Eviction
+//!
+//! The cache holds at most `capacity` entries. When full, the oldest
+//! inserted entry is evicted (FIFO). Eviction depends only on the sequence
+//! of requests, never on timing or hash seeds.
+
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec::Vec;
+
+use crate::error::PolicyError;
+use crate::fingerprint::{fnv64, write_request};
+use crate::policy::Policy;
+use crate::types::{Decision, Request};
+
+/// A cached decision with the canonical request it was computed for.
+#[derive(Debug, Clone)]
+struct Entry {
+    key: Vec<u8>,
+    decision: Decision,
+}
+
+/// A bounded, deterministic cache of policy decisions.
+///
+/// Lookups take `&mut self`; wrap the cache in a `Mutex` (or keep one per
+/// thread) to share it.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{DecisionCache, Policy, ReasonCode, Request, Rule, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(Target::any(), ReasonCode(1)))
+///     .build()
+///     .unwrap();
+/// let mut cache = DecisionCache::new(1024);
+///
+/// let request = Request::new("alice", "read", "doc");
+/// let first = cache.evaluate(&policy, &request).unwrap();
+/// let second = cache.evaluate(&policy, &request).unwrap();
+/// assert_eq!(first, second);
+/// assert_eq!(cache.hits(), 1);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DecisionCache {
+    capacity: usize,
+    fingerprint: Option<u64>,
+    entries: BTreeMap<u64, Entry>,
+    order: VecDeque<u64>,
+    scratch: Vec<u8>,
+    hits: u64,
+    misses: u64,
+}
+
+impl DecisionCache {
+    /// Create a cache holding at most `capacity` decisions.
+    ///
+    /// A capacity of zero disables caching; every call evaluates.
+    pub fn new(capacity: usize) -> Self {
+        DecisionCache {
+            capacity,
+            fingerprint: None,
+            entries: BTreeMap::new(),
+            order: VecDeque::new(),
+            scratch: Vec::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Evaluate `request` against `policy`, returning a cached decision if
+    /// one exists.
+    ///
+    /// Errors, and decisions whose cache hint sets a TTL, are never cached.
+    pub fn evaluate(
+        &mut self,
+        policy: &Policy<'_>,
+        request: &Request<'_>,
+    ) -> Result<Decision, PolicyError> {
+        if self.fingerprint != Some(policy.fingerprint()) {
+            self.clear();
+            self.fingerprint = Some(policy.fingerprint());
+        }
+
+        self.scratch.clear();
+        write_request(&mut self.scratch, request);
+        let hash = fnv64(&self.scratch);
+
+        if let Some(entry) = self.entries.get(&hash) {
+            if entry.key == self.scratch {
+                self.hits += 1;
+                policy.record_cached(request, &entry.decision);
+                return Ok(entry.decision);
+            }
+        }
+
+        self.misses += 1;
+        let decision = policy.evaluate(request)?;
+        if decision.cache.and_then(|hint| hint.ttl).is_none() {
+            self.insert(hash, decision);
+        }
+        Ok(decision)
+    }
+
+    /// Store a decision for the request currently in `scratch`.
+    fn insert(&mut self, hash: u64, decision: Decision) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        let entry = Entry {
+            key: self.scratch.clone(),
+            decision,
+        };
+        // A colliding entry is replaced in place and keeps its queue slot
+        if self.entries.insert(hash, entry).is_some() {
+            return;
+        }
+
+        self.order.push_back(hash);
+        while self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+
+    /// Remove all cached decisions. Hit and miss counters are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    /// Number of cached decisions.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no decisions are cached.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Maximum number of cached decisions.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of lookups answered from the cache.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Number of lookups that had to evaluate the policy.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CacheHint, Matcher, ReasonCode, Rule, Target, Value};
+
+    fn policy(reason: u32) -> Policy<'static> {
+        Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(reason)))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hits_reach_the_sink() {
+        use crate::stats::{EvaluationStats, Termination};
+        use std::sync::Mutex;
+
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let log = std::sync::Arc::clone(&seen);
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .sink(move |_: &Request, d: &Decision, s: &EvaluationStats| {
+                log.lock()
+                    .unwrap()
+                    .push((*d, s.termination, s.rules_checked));
+            })
+            .build()
+            .unwrap();
+        let mut cache = DecisionCache::new(8);
+
+        let request = Request::new("alice", "read", "doc");
+        cache.evaluate(&policy, &request).unwrap();
+        cache.evaluate(&policy, &request).unwrap();
+
+        let allow = Decision::allow(ReasonCode(1));
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (allow, Termination::ExhaustedRules, 1),
+                (allow, Termination::Cached, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hits_and_misses() {
+        let policy = policy(1);
+        let mut cache = DecisionCache::new(8);
+
+        let ctx: &[(&str, Value)] = &[("mfa", Value::Bool(true))];
+        let with_ctx = Request::with_context("alice", "read", "doc", ctx);
+        let plain = Request::new("alice", "read", "doc");
+
+        cache.evaluate(&policy, &plain).unwrap();
+        cache.evaluate(&policy, &plain).unwrap();
+        cache.evaluate(&policy, &with_ctx).unwrap();
+
+        assert_eq!(cache.hits(), 1);
+        assert_eq!(cache.misses(), 2);
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_fifo_eviction() {
+        let policy = policy(1);
+        let mut cache = DecisionCache::new(2);
+
+        for principal in ["a", "b", "c"] {
+            cache
+                .evaluate(&policy, &Request::new(principal, "read", "doc"))
+                .unwrap();
+        }
+        assert_eq!(cache.len(), 2);
+
+        // "a" was evicted first; "c" is still cached
+        cache
+            .evaluate(&policy, &Request::new("c", "read", "doc"))
+            .unwrap();
+        assert_eq!(cache.hits(), 1);
+        cache
+            .evaluate(&policy, &Request::new("a", "read", "doc"))
+            .unwrap();
+        assert_eq!(cache.misses(), 4);
+    }
+
+    #[test]
+    fn test_policy_change_invalidates() {
+        let mut cache = DecisionCache::new(8);
+        let request = Request::new("alice", "read", "doc");
+
+        let first = cache.evaluate(&policy(1), &request).unwrap();
+        let second = cache.evaluate(&policy(2), &request).unwrap();
+
+        assert_eq!(first.reason, ReasonCode(1));
+        assert_eq!(second.reason, ReasonCode(2));
+        assert_eq!(cache.hits(), 0);
+    }
+
+    #[test]
+    fn test_zero_capacity_and_errors_not_cached() {
+        let mut cache = DecisionCache::new(0);
+        let request = Request::new("alice", "read", "doc");
+        cache.evaluate(&policy(1), &request).unwrap();
+        assert!(cache.is_empty());
+
+        // Principal exceeds max_string_len
+        let long = "x".repeat(300);
+        let mut cache = DecisionCache::new(8);
+        let request = Request::new(&long, "read", "doc");
+        assert!(cache.evaluate(&policy(1), &request).is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_ttl_decisions_not_cached() {
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)).cache(CacheHint::ttl(60)))
+            .rule(
+                Rule::deny(
+                    Target::new(Matcher::Exact("mallory"), Matcher::Any, Matcher::Any),
+                    ReasonCode(2),
+                )
+                .cache(CacheHint::PER_CONTEXT),
+            )
+            .build()
+            .unwrap();
+        let mut cache = DecisionCache::new(8);
+
+        let decision = cache
+            .evaluate(&policy, &Request::new("alice", "read", "doc"))
+            .unwrap();
+        assert_eq!(decision.cache, Some(CacheHint::ttl(60)));
+        assert!(cache.is_empty());
+
+        // Per-context hints need no clock
+        let decision = cache
+            .evaluate(&policy, &Request::new("mallory", "read", "doc"))
+            .unwrap();
+        assert_eq!(decision.cache, Some(CacheHint::PER_CONTEXT));
+        assert_eq!(cache.len(), 1);
+    }
+}
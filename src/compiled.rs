@@ -0,0 +1,582 @@
+//! Flat postfix bytecode for conditions.
+//!
+//! `Condition` trees are boxed, so evaluating them chases a pointer per
+//! node. At build time the policy flattens every rule's condition into one
+//! contiguous array of postfix instructions, and evaluation walks that array
+//! left to right with a single `FixedStack<bool>`.
+//!
+//! # Stack Bound
+//!
+//! Postfix evaluation of a tree of depth D never holds more than D values:
+//! a subtree's operands are reduced before its sibling is pushed. With
+//! `D <= ABSOLUTE_MAX_CONDITION_DEPTH`, `VALUE_STACK_SIZE` is sufficient.
+//!
+//! # Semantics
+//!
+//! Instructions are emitted in the same order the tree evaluator visits
+//! nodes, and leaves use the same lookup rules, so compiled and tree
+//! evaluation always agree.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::condition::{lookup_attr, Condition, VALUE_STACK_SIZE};
+use crate::error::PolicyError;
+use crate::fixed_stack::EvalStack;
+use crate::intern::{Interner, Symbol};
+use crate::net::Cidr;
+use crate::observer::Observer;
+#[cfg(feature = "custom-predicates")]
+use crate::predicates::PredicateRegistry;
+use crate::time::{self, Weekdays};
+use crate::value::Value;
+
+/// Where leaf instructions read context attributes from.
+pub(crate) trait AttrSource<'k> {
+    /// Apply `test` to attribute `attr`: `Some(result)`, or `None` if the
+    /// attribute is missing. `symbol` is `attr`'s symbol if the policy
+    /// interns.
+    fn test<F: FnOnce(&Value<'_>) -> bool>(
+        &mut self,
+        attr: &'k str,
+        symbol: Option<Symbol>,
+        test: F,
+    ) -> Result<Option<bool>, PolicyError>;
+
+    /// Compare attribute `attr` to `value`: `Some(equal)`, or `None` if the
+    /// attribute is missing.
+    fn compare(
+        &mut self,
+        attr: &'k str,
+        symbol: Option<Symbol>,
+        value: &Value<'_>,
+    ) -> Result<Option<bool>, PolicyError> {
+        self.test(attr, symbol, |v| v == value)
+    }
+
+    /// Number of attributes resolved lazily so far.
+    fn resolutions(&self) -> usize {
+        0
+    }
+
+    /// The request's principal, action, and resource symbols, if the
+    /// request was interned for the policy evaluating it.
+    fn fields(&self) -> Option<[Option<Symbol>; 3]> {
+        None
+    }
+}
+
+/// The request's inline context.
+impl<'k> AttrSource<'k> for &[(&str, Value<'_>)] {
+    #[inline]
+    fn test<F: FnOnce(&Value<'_>) -> bool>(
+        &mut self,
+        attr: &'k str,
+        _symbol: Option<Symbol>,
+        test: F,
+    ) -> Result<Option<bool>, PolicyError> {
+        Ok(lookup_attr(self, attr).map(test))
+    }
+}
+
+/// A single postfix instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Op<'a> {
+    /// Push `true`.
+    True,
+    /// Push `false`.
+    False,
+    /// Push whether `attr` equals `value` (missing = false).
+    Equals(&'a str, Value<'a>),
+    /// Push whether `attr` differs from `value` (missing = true).
+    NotEquals(&'a str, Value<'a>),
+    /// Push whether `attr` is greater than `value` (missing = false).
+    GreaterThan(&'a str, Value<'a>),
+    /// Push whether `attr` is less than `value` (missing = false).
+    LessThan(&'a str, Value<'a>),
+    /// Push whether `attr` is an address inside the block (missing = false).
+    IpInCidr(&'a str, Cidr),
+    /// Push whether `attr` is a timestamp in the window (missing = false).
+    TimeBetween(&'a str, u32, u32),
+    /// Push whether `attr` is a timestamp on one of the days (missing = false).
+    DayOfWeek(&'a str, Weekdays),
+    /// Push whether the named predicate holds for `attr` (missing = false).
+    #[cfg(feature = "custom-predicates")]
+    Custom(&'a str, &'a str),
+    /// Pop one value, push its negation.
+    Not,
+    /// Pop two values, push their conjunction.
+    And,
+    /// Pop two values, push their disjunction.
+    Or,
+}
+
+impl<'a> Op<'a> {
+    /// The attribute a leaf instruction reads.
+    fn attr(&self) -> Option<&'a str> {
+        match self {
+            Op::Equals(attr, _)
+            | Op::NotEquals(attr, _)
+            | Op::GreaterThan(attr, _)
+            | Op::LessThan(attr, _)
+            | Op::IpInCidr(attr, _)
+            | Op::TimeBetween(attr, ..)
+            | Op::DayOfWeek(attr, _) => Some(attr),
+            #[cfg(feature = "custom-predicates")]
+            Op::Custom(attr, _) => Some(attr),
+            Op::True | Op::False | Op::Not | Op::And | Op::Or => None,
+        }
+    }
+}
+
+/// Fixed costs of running one program, known at compile time.
+///
+/// Every instruction runs on every evaluation, so these do not depend on
+/// the context.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct Profile {
+    /// Highest value stack height reached.
+    pub(crate) height: u8,
+    /// Attribute lookups performed (one per leaf comparison).
+    pub(crate) lookups: u16,
+}
+
+impl Profile {
+    fn of(ops: &[Op<'_>]) -> Self {
+        let (mut height, mut max, mut lookups) = (0usize, 0usize, 0usize);
+        for op in ops {
+            match op {
+                Op::True | Op::False => height += 1,
+                Op::Equals(..)
+                | Op::NotEquals(..)
+                | Op::GreaterThan(..)
+                | Op::LessThan(..)
+                | Op::IpInCidr(..)
+                | Op::TimeBetween(..)
+                | Op::DayOfWeek(..) => {
+                    height += 1;
+                    lookups += 1;
+                }
+                #[cfg(feature = "custom-predicates")]
+                Op::Custom(..) => {
+                    height += 1;
+                    lookups += 1;
+                }
+                Op::Not => {}
+                Op::And | Op::Or => height = height.saturating_sub(1),
+            }
+            max = max.max(height);
+        }
+        Profile {
+            height: u8::try_from(max).unwrap_or(u8::MAX),
+            lookups: u16::try_from(lookups).unwrap_or(u16::MAX),
+        }
+    }
+}
+
+/// The compiled conditions of every rule in a policy.
+///
+/// `ops` holds all programs back to back; `spans[i]` is the `(start, end)`
+/// range of rule `i`'s program, or `None` if the rule has no condition, and
+/// `profiles[i]` its fixed costs. `Custom` instructions look their
+/// predicate up in `predicates` by name. If the policy interns, `symbols`
+/// runs parallel to `ops`, holding the symbol of each instruction's
+/// attribute.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CompiledConditions<'a> {
+    ops: Vec<Op<'a>>,
+    symbols: Vec<Option<Symbol>>,
+    spans: Vec<Option<(u32, u32)>>,
+    profiles: Vec<Profile>,
+    #[cfg(feature = "custom-predicates")]
+    predicates: PredicateRegistry<'a>,
+}
+
+impl<'a> CompiledConditions<'a> {
+    /// Compile one condition per rule (`None` for unconditional rules).
+    pub(crate) fn compile<'b, I>(conditions: I) -> Self
+    where
+        'a: 'b,
+        I: IntoIterator<Item = Option<&'b Condition<'a>>>,
+    {
+        let mut compiled = CompiledConditions::default();
+        for condition in conditions {
+            let span = condition.map(|cond| {
+                let start = compiled.ops.len() as u32;
+                emit(cond, &mut compiled.ops);
+                (start, compiled.ops.len() as u32)
+            });
+            let profile = span.map_or(Profile::default(), |(start, end)| {
+                Profile::of(&compiled.ops[start as usize..end as usize])
+            });
+            compiled.spans.push(span);
+            compiled.profiles.push(profile);
+        }
+        compiled
+    }
+
+    /// Record the symbol of every instruction's attribute.
+    pub(crate) fn intern(&mut self, interner: &Interner<'_>) {
+        self.symbols = self
+            .ops
+            .iter()
+            .map(|op| op.attr().and_then(|attr| interner.get(attr)))
+            .collect();
+    }
+
+    /// Rule `rule`'s attribute symbols, parallel to its program, or `None`
+    /// if it has no condition or the policy does not intern.
+    pub(crate) fn symbols(&self, rule: usize) -> Option<&[Option<Symbol>]> {
+        let (start, end) = (*self.spans.get(rule)?)?;
+        self.symbols.get(start as usize..end as usize)
+    }
+
+    /// Compile new conditions, keeping the predicates of these (but not
+    /// the symbols; see `intern`).
+    pub(crate) fn recompile<'b, I>(&self, conditions: I) -> Self
+    where
+        'a: 'b,
+        I: IntoIterator<Item = Option<&'b Condition<'a>>>,
+    {
+        CompiledConditions {
+            #[cfg(feature = "custom-predicates")]
+            predicates: self.predicates.clone(),
+            ..Self::compile(conditions)
+        }
+    }
+
+    /// Check that `predicates` holds every predicate a `Custom`
+    /// instruction names.
+    ///
+    /// Fails with `UnknownPredicate` for the first rule naming one it does
+    /// not.
+    #[cfg(feature = "custom-predicates")]
+    pub(crate) fn check_predicates(
+        &self,
+        predicates: &PredicateRegistry<'_>,
+    ) -> Result<(), PolicyError> {
+        for rule in 0..self.spans.len() {
+            let unknown = self
+                .program(rule)
+                .unwrap_or(&[])
+                .iter()
+                .any(|op| matches!(op, Op::Custom(_, name) if !predicates.contains(name)));
+            if unknown {
+                return Err(PolicyError::UnknownPredicate { rule });
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolve `Custom` instructions with `predicates`, after checking them
+    /// with `check_predicates`.
+    #[cfg(feature = "custom-predicates")]
+    pub(crate) fn resolve(&mut self, predicates: PredicateRegistry<'a>) -> Result<(), PolicyError> {
+        self.check_predicates(&predicates)?;
+        self.predicates = predicates;
+        Ok(())
+    }
+
+    /// The registry `Custom` instructions are resolved with.
+    #[cfg(feature = "custom-predicates")]
+    pub(crate) fn predicates(&self) -> &PredicateRegistry<'a> {
+        &self.predicates
+    }
+
+    /// Fixed costs of rule `rule`'s program (zero if it has none).
+    pub(crate) fn profile(&self, rule: usize) -> Profile {
+        self.profiles.get(rule).copied().unwrap_or_default()
+    }
+
+    /// Number of instructions in rule `rule`'s program (0 if it has none).
+    pub(crate) fn program_len(&self, rule: usize) -> usize {
+        match self.spans.get(rule) {
+            Some(Some((start, end))) => (end - start) as usize,
+            _ => 0,
+        }
+    }
+
+    /// Rule `rule`'s program, or `None` if it has no condition.
+    pub(crate) fn program(&self, rule: usize) -> Option<&[Op<'a>]> {
+        let (start, end) = (*self.spans.get(rule)?)?;
+        self.ops.get(start as usize..end as usize)
+    }
+
+    /// Evaluate rule `rule`'s condition against the context.
+    ///
+    /// Returns `Ok(true)` for rules without a condition. Each node's result is reported to `observer`.
+    pub(crate) fn evaluate<S: AttrSource<'a>, O: Observer>(
+        &self,
+        rule: usize,
+        context: &mut S,
+        observer: &mut O,
+    ) -> Result<bool, PolicyError> {
+        let (start, end) = match self.spans.get(rule) {
+            Some(Some(span)) => *span,
+            Some(None) => return Ok(true),
+            None => return Err(PolicyError::InternalError),
+        };
+        let ops = self
+            .ops
+            .get(start as usize..end as usize)
+            .ok_or(PolicyError::InternalError)?;
+
+        let symbols = self.symbols.get(start as usize..end as usize);
+
+        let mut results: EvalStack<bool, VALUE_STACK_SIZE> = EvalStack::new();
+
+        for (node, op) in ops.iter().enumerate() {
+            let symbol = symbols.and_then(|symbols| symbols[node]);
+            let result = match op {
+                Op::True => true,
+                Op::False => false,
+                Op::Equals(attr, value) => context.compare(attr, symbol, value)?.unwrap_or(false), // Missing attr = false (fail-closed)
+                Op::NotEquals(attr, value) => context
+                    .compare(attr, symbol, value)?
+                    .map(|equal| !equal)
+                    .unwrap_or(true), // Missing attr = true for NotEquals
+                Op::GreaterThan(attr, value) => context
+                    .test(attr, symbol, |v| {
+                        v.ordering(value).is_some_and(Ordering::is_gt)
+                    })?
+                    .unwrap_or(false),
+                Op::LessThan(attr, value) => context
+                    .test(attr, symbol, |v| {
+                        v.ordering(value).is_some_and(Ordering::is_lt)
+                    })?
+                    .unwrap_or(false),
+                Op::IpInCidr(attr, cidr) => context
+                    .test(
+                        attr,
+                        symbol,
+                        |v| matches!(v, Value::Ip(ip) if cidr.contains(*ip)),
+                    )?
+                    .unwrap_or(false),
+                Op::TimeBetween(attr, start, end) => context
+                    .test(
+                        attr,
+                        symbol,
+                        |v| matches!(v, Value::Timestamp(t) if time::in_window(*t, *start, *end)),
+                    )?
+                    .unwrap_or(false),
+                Op::DayOfWeek(attr, days) => context
+                    .test(
+                        attr,
+                        symbol,
+                        |v| matches!(v, Value::Timestamp(t) if days.contains(Weekdays::of(*t))),
+                    )?
+                    .unwrap_or(false),
+                #[cfg(feature = "custom-predicates")]
+                Op::Custom(attr, name) => match self.predicates.get(name) {
+                    Some(predicate) => context.test(attr, symbol, predicate)?.unwrap_or(false),
+                    // Names are checked by `resolve`; an unknown one never
+                    // matches
+                    None => false,
+                },
+                Op::Not => !results.pop().ok_or(PolicyError::InternalError)?,
+                Op::And => {
+                    let b = results.pop().ok_or(PolicyError::InternalError)?;
+                    let a = results.pop().ok_or(PolicyError::InternalError)?;
+                    a && b
+                }
+                Op::Or => {
+                    let b = results.pop().ok_or(PolicyError::InternalError)?;
+                    let a = results.pop().ok_or(PolicyError::InternalError)?;
+                    a || b
+                }
+            };
+            observer.node_result(rule, node, result);
+            results.push(result)?;
+        }
+
+        // Final result should be the only item on the stack
+        results.pop().ok_or(PolicyError::InternalError)
+    }
+}
+
+/// Append the postfix program for `cond` to `ops`.
+///
+/// Non-recursive, using the same traversal order as `Condition::evaluate`.
+fn emit<'a>(cond: &Condition<'a>, ops: &mut Vec<Op<'a>>) {
+    enum Work<'a, 'b> {
+        Visit(&'b Condition<'a>),
+        Emit(Op<'a>),
+    }
+
+    let mut stack = vec![Work::Visit(cond)];
+
+    while let Some(item) = stack.pop() {
+        match item {
+            Work::Emit(op) => ops.push(op),
+            Work::Visit(cond) => match cond {
+                Condition::True => ops.push(Op::True),
+                Condition::False => ops.push(Op::False),
+                Condition::Equals { attr, value } => ops.push(Op::Equals(attr, value.clone())),
+                Condition::NotEquals { attr, value } => {
+                    ops.push(Op::NotEquals(attr, value.clone()))
+                }
+                Condition::GreaterThan { attr, value } => {
+                    ops.push(Op::GreaterThan(attr, value.clone()))
+                }
+                Condition::LessThan { attr, value } => ops.push(Op::LessThan(attr, value.clone())),
+                // Blocks are validated before compiling; an invalid one
+                // could never match
+                Condition::IpInCidr { attr, cidr } => ops.push(match Cidr::parse(cidr) {
+                    Ok(cidr) => Op::IpInCidr(attr, cidr),
+                    Err(_) => Op::False,
+                }),
+                Condition::TimeBetween { attr, start, end } => {
+                    ops.push(Op::TimeBetween(attr, *start, *end))
+                }
+                Condition::DayOfWeek { attr, days } => ops.push(Op::DayOfWeek(attr, *days)),
+                #[cfg(feature = "custom-predicates")]
+                Condition::Custom { attr, name } => ops.push(Op::Custom(attr, name)),
+                Condition::Not(inner) => {
+                    stack.push(Work::Emit(Op::Not));
+                    stack.push(Work::Visit(inner));
+                }
+                Condition::And(a, b) => {
+                    stack.push(Work::Emit(Op::And));
+                    stack.push(Work::Visit(b));
+                    stack.push(Work::Visit(a));
+                }
+                Condition::Or(a, b) => {
+                    stack.push(Work::Emit(Op::Or));
+                    stack.push(Work::Visit(b));
+                    stack.push(Work::Visit(a));
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::boxed::Box;
+
+    fn eq(attr: &'static str, value: bool) -> Condition<'static> {
+        Condition::Equals {
+            attr,
+            value: Value::Bool(value),
+        }
+    }
+
+    #[test]
+    fn test_postfix_layout() {
+        // (a AND NOT b) OR c
+        let cond = Condition::Or(
+            Box::new(Condition::And(
+                Box::new(eq("a", true)),
+                Box::new(Condition::Not(Box::new(eq("b", true)))),
+            )),
+            Box::new(eq("c", true)),
+        );
+        let compiled = CompiledConditions::compile([None, Some(&cond)]);
+
+        assert_eq!(
+            compiled.ops,
+            vec![
+                Op::Equals("a", Value::Bool(true)),
+                Op::Equals("b", Value::Bool(true)),
+                Op::Not,
+                Op::And,
+                Op::Equals("c", Value::Bool(true)),
+                Op::Or,
+            ]
+        );
+        assert_eq!(compiled.spans, vec![None, Some((0, 6))]);
+        assert_eq!(compiled.program_len(0), 0);
+        assert_eq!(compiled.program_len(1), 6);
+    }
+
+    #[test]
+    fn test_matches_tree_evaluation() {
+        let cond = Condition::Or(
+            Box::new(Condition::And(
+                Box::new(eq("a", true)),
+                Box::new(Condition::NotEquals {
+                    attr: "b",
+                    value: Value::Int(1),
+                }),
+            )),
+            Box::new(Condition::Not(Box::new(eq("c", true)))),
+        );
+        let compiled = CompiledConditions::compile([Some(&cond)]);
+
+        let contexts: &[&[(&str, Value)]] = &[
+            &[],
+            &[("a", Value::Bool(true))],
+            &[("a", Value::Bool(true)), ("b", Value::Int(1))],
+            &[("b", Value::Int(2)), ("c", Value::Bool(true))],
+            &[("c", Value::Bool(true))],
+        ];
+        for ctx in contexts {
+            assert_eq!(
+                compiled.evaluate(0, &mut &ctx[..], &mut ()),
+                cond.evaluate(ctx)
+            );
+        }
+    }
+
+    #[test]
+    fn test_unconditional_and_out_of_range() {
+        let mut no_context: &[(&str, Value)] = &[];
+        let compiled = CompiledConditions::compile([None]);
+        assert_eq!(compiled.evaluate(0, &mut no_context, &mut ()), Ok(true));
+        assert_eq!(
+            compiled.evaluate(1, &mut no_context, &mut ()),
+            Err(PolicyError::InternalError)
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        // Left-leaning: (x && y) || !z
+        let cond = Condition::Or(
+            Box::new(Condition::And(
+                Box::new(eq("x", true)),
+                Box::new(eq("y", true)),
+            )),
+            Box::new(Condition::Not(Box::new(eq("z", true)))),
+        );
+        let right = Condition::And(
+            Box::new(Condition::True),
+            Box::new(Condition::And(
+                Box::new(Condition::True),
+                Box::new(eq("x", true)),
+            )),
+        );
+        let compiled = CompiledConditions::compile([Some(&cond), None, Some(&right)]);
+        assert_eq!(
+            compiled.profile(0),
+            Profile {
+                height: 2,
+                lookups: 3
+            }
+        );
+        assert_eq!(compiled.profile(1), Profile::default());
+        assert_eq!(
+            compiled.profile(2),
+            Profile {
+                height: 3,
+                lookups: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_right_leaning_max_depth_fits_stack() {
+        // Right-leaning chains maximise the postfix stack height
+        let mut cond = eq("x", true);
+        for _ in 1..crate::condition::ABSOLUTE_MAX_CONDITION_DEPTH {
+            cond = Condition::And(Box::new(Condition::True), Box::new(cond));
+        }
+        let compiled = CompiledConditions::compile([Some(&cond)]);
+        assert_eq!(
+            compiled.evaluate(0, &mut &[("x", Value::Bool(true))][..], &mut ()),
+            Ok(true)
+        );
+    }
+}
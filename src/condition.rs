@@ -1,6 +1,8 @@
 //! Boolean condition evaluation.
 //!
-//! Minimal expression language: Equals, NotEquals, And, Or, Not.
+//! Minimal expression language: Equals, NotEquals, GreaterThan, LessThan,
+//! IpInCidr, TimeBetween, DayOfWeek, And, Or, Not, and, with the
+//! `custom-predicates` feature, Custom.
 //! Depth is checked at construction time.
 //! Evaluation is stack-based (non-recursive) to guarantee termination.
 //!
@@ -16,20 +18,52 @@
 //! - **Results stack**: At most `D + 2` items.
 //!   Proof: Each operator consumes its children before parent is processed.
 
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
 use crate::error::PolicyError;
-use crate::fixed_stack::FixedStack;
+use crate::fixed_stack::EvalStack;
+use crate::net::Cidr;
+use crate::time::{self, Weekdays};
 use crate::value::Value;
 
 /// Hard compile-time cap on condition depth.
 /// PolicyConfig::max_condition_depth must be <= this value.
 /// This enables const-generic stack sizing for zero-allocation evaluation.
+///
+/// With the `heap-stack` feature the cap is lifted: evaluation stacks spill
+/// to the heap beyond this depth, so only deeper conditions allocate.
 pub const ABSOLUTE_MAX_CONDITION_DEPTH: usize = 16;
 
 /// Traversal stack size: 2*D + 2 (proven O(depth) bound).
 const TRAVERSAL_STACK_SIZE: usize = 2 * ABSOLUTE_MAX_CONDITION_DEPTH + 2;
 
 /// Results stack size: D + 2 (proven O(depth) bound).
-const VALUE_STACK_SIZE: usize = ABSOLUTE_MAX_CONDITION_DEPTH + 2;
+pub(crate) const VALUE_STACK_SIZE: usize = ABSOLUTE_MAX_CONDITION_DEPTH + 2;
+
+/// Check a configured `max_condition_depth` against the evaluation stacks.
+#[cfg(not(feature = "heap-stack"))]
+pub(crate) fn check_depth_limit(max_depth: usize) -> Result<(), PolicyError> {
+    if max_depth > ABSOLUTE_MAX_CONDITION_DEPTH {
+        return Err(PolicyError::ConditionTooDeep {
+            max: ABSOLUTE_MAX_CONDITION_DEPTH,
+            actual: max_depth,
+            location: None,
+        });
+    }
+    Ok(())
+}
+
+/// Check a configured `max_condition_depth` against the evaluation stacks.
+///
+/// The stacks spill to the heap, so any depth is accepted.
+#[cfg(feature = "heap-stack")]
+pub(crate) fn check_depth_limit(_max_depth: usize) -> Result<(), PolicyError> {
+    Ok(())
+}
 
 /// A boolean condition that can be evaluated against request context.
 #[derive(Debug, Clone, PartialEq)]
@@ -52,6 +86,74 @@ pub enum Condition<'a> {
         /// The value to compare against.
         value: Value<'a>,
     },
+    /// True if the attribute is greater than the value.
+    ///
+    /// Integers, timestamps, and durations are ordered, and only against
+    /// their own type (see `Value::ordering`); comparing against any other
+    /// value fails the build. A missing attribute or one of another type is
+    /// false.
+    GreaterThan {
+        /// The attribute name to look up in context.
+        attr: &'a str,
+        /// The value to compare against.
+        value: Value<'a>,
+    },
+    /// True if the attribute is less than the value; see `GreaterThan`.
+    LessThan {
+        /// The attribute name to look up in context.
+        attr: &'a str,
+        /// The value to compare against.
+        value: Value<'a>,
+    },
+    /// True if the attribute is an IP address inside the CIDR block, e.g.
+    /// `"10.0.0.0/8"`.
+    ///
+    /// The block is parsed when the policy is built and an invalid one
+    /// fails the build. A missing attribute, a value that is not a
+    /// `Value::Ip`, or an address of the other IP version is false.
+    IpInCidr {
+        /// The attribute name to look up in context.
+        attr: &'a str,
+        /// The CIDR block, as text.
+        cidr: &'a str,
+    },
+    /// True if the attribute is a `Value::Timestamp` whose time of day (UTC)
+    /// is in `[start, end)`, both in seconds since midnight. A window with
+    /// `start > end` wraps past midnight, e.g. 22:00 to 06:00.
+    ///
+    /// `start` must be before `SECONDS_PER_DAY` and `end` at most it;
+    /// otherwise the policy fails to build. A missing attribute or a value
+    /// that is not a timestamp is false.
+    TimeBetween {
+        /// The attribute name to look up in context.
+        attr: &'a str,
+        /// The first second of the window.
+        start: u32,
+        /// The first second after the window.
+        end: u32,
+    },
+    /// True if the attribute is a `Value::Timestamp` falling on one of
+    /// `days` (UTC). A missing attribute or a value that is not a timestamp
+    /// is false.
+    DayOfWeek {
+        /// The attribute name to look up in context.
+        attr: &'a str,
+        /// The days that match.
+        days: Weekdays,
+    },
+    /// True if the predicate registered as `name` returns true for the
+    /// attribute (see `PredicateRegistry`).
+    ///
+    /// Building a policy fails with `PolicyError::UnknownPredicate` if
+    /// `name` is not registered. A missing attribute is false, and so is
+    /// every `Custom` under `Condition::evaluate`, which has no registry.
+    #[cfg(feature = "custom-predicates")]
+    Custom {
+        /// The attribute name to look up in context.
+        attr: &'a str,
+        /// The registered predicate's name.
+        name: &'a str,
+    },
     /// True if both conditions are true.
     And(Box<Condition<'a>>, Box<Condition<'a>>),
     /// True if either condition is true.
@@ -82,9 +184,16 @@ impl<'a> Condition<'a> {
                     Condition::True
                     | Condition::False
                     | Condition::Equals { .. }
-                    | Condition::NotEquals { .. } => {
+                    | Condition::NotEquals { .. }
+                    | Condition::GreaterThan { .. }
+                    | Condition::LessThan { .. }
+                    | Condition::IpInCidr { .. }
+                    | Condition::TimeBetween { .. }
+                    | Condition::DayOfWeek { .. } => {
                         results.push(1);
                     }
+                    #[cfg(feature = "custom-predicates")]
+                    Condition::Custom { .. } => results.push(1),
                     Condition::Not(inner) => {
                         stack.push(DepthItem::Computed(1));
                         stack.push(DepthItem::Visit(inner));
@@ -114,7 +223,9 @@ impl<'a> Condition<'a> {
     /// Validate that this condition does not exceed the maximum depth
     /// and that all strings are within length limits.
     ///
-    /// This implementation is non-recursive.
+    /// Limit errors record the offending node (see `RulePart::Condition`);
+    /// a depth error records the root. This implementation is
+    /// non-recursive.
     pub fn validate(&self, max_depth: usize, max_string_len: usize) -> Result<(), PolicyError> {
         // First check depth (already non-recursive)
         let actual_depth = self.depth();
@@ -122,28 +233,234 @@ impl<'a> Condition<'a> {
             return Err(PolicyError::ConditionTooDeep {
                 max: max_depth,
                 actual: actual_depth,
-            });
+                location: None,
+            }
+            .at_node(0));
         }
 
-        // Then check string lengths non-recursively
+        // Then check string lengths non-recursively, numbering nodes in
+        // pre-order
         let mut stack = vec![self];
+        let mut node = 0;
         while let Some(cond) = stack.pop() {
+            cond.validate_node(max_string_len)
+                .map_err(|err| err.at_node(node))?;
             match cond {
-                Condition::True | Condition::False => {}
-                Condition::Equals { attr, value } | Condition::NotEquals { attr, value } => {
-                    validate_str(attr, max_string_len)?;
-                    if let Value::String(s) = value {
-                        validate_str(s, max_string_len)?;
+                Condition::Not(inner) => stack.push(inner),
+                Condition::And(a, b) | Condition::Or(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                _ => {}
+            }
+            node += 1;
+        }
+        Ok(())
+    }
+
+    /// Validate this node's own strings and values, not its operands'.
+    fn validate_node(&self, max_string_len: usize) -> Result<(), PolicyError> {
+        match self {
+            Condition::True | Condition::False => {}
+            Condition::Equals { attr, value } | Condition::NotEquals { attr, value } => {
+                validate_str(attr, max_string_len)?;
+                if let Value::String(s) = value {
+                    validate_str(s, max_string_len)?;
+                }
+            }
+            Condition::GreaterThan { attr, value } | Condition::LessThan { attr, value } => {
+                validate_str(attr, max_string_len)?;
+                if !value.value_type().is_ordered() {
+                    return Err(PolicyError::UnorderedValue);
+                }
+            }
+            Condition::IpInCidr { attr, cidr } => {
+                validate_str(attr, max_string_len)?;
+                validate_str(cidr, max_string_len)?;
+                Cidr::parse(cidr)?;
+            }
+            Condition::TimeBetween { attr, start, end } => {
+                validate_str(attr, max_string_len)?;
+                time::validate_window(*start, *end)?;
+            }
+            Condition::DayOfWeek { attr, .. } => {
+                validate_str(attr, max_string_len)?;
+            }
+            #[cfg(feature = "custom-predicates")]
+            Condition::Custom { attr, name } => {
+                validate_str(attr, max_string_len)?;
+                validate_str(name, max_string_len)?;
+            }
+            Condition::Not(_) | Condition::And(..) | Condition::Or(..) => {}
+        }
+        Ok(())
+    }
+
+    /// An equivalent condition with trivially reducible parts folded away.
+    ///
+    /// Evaluates exactly like `self` against every context. Applied bottom
+    /// up:
+    ///
+    /// - `True`/`False` operands of `And`, `Or`, and `Not` are folded.
+    /// - `Not(Not(x))` becomes `x`; `Not(Equals)` becomes `NotEquals` and
+    ///   vice versa.
+    /// - `And(x, x)` and `Or(x, x)` become `x`.
+    /// - `And` of a comparison and its negation, or of two `Equals` on one
+    ///   attribute with different values, becomes `False`; `Or` of a
+    ///   comparison and its negation becomes `True`.
+    ///
+    /// Duplicates and negations are only recognized between leaves (or a
+    /// negated leaf), so each step stays constant-time. Non-recursive.
+    pub fn normalize(&self) -> Condition<'a> {
+        enum Work<'a, 'b> {
+            Visit(&'b Condition<'a>),
+            BuildNot,
+            BuildAnd,
+            BuildOr,
+        }
+
+        let mut stack = vec![Work::Visit(self)];
+        let mut results: Vec<Condition<'a>> = Vec::new();
+
+        while let Some(item) = stack.pop() {
+            match item {
+                Work::Visit(cond) => match cond {
+                    Condition::Not(inner) => {
+                        stack.push(Work::BuildNot);
+                        stack.push(Work::Visit(inner));
+                    }
+                    Condition::And(a, b) => {
+                        stack.push(Work::BuildAnd);
+                        stack.push(Work::Visit(b));
+                        stack.push(Work::Visit(a));
                     }
+                    Condition::Or(a, b) => {
+                        stack.push(Work::BuildOr);
+                        stack.push(Work::Visit(b));
+                        stack.push(Work::Visit(a));
+                    }
+                    leaf => results.push(leaf.clone()),
+                },
+                Work::BuildNot => {
+                    let inner = results.pop().unwrap_or(Condition::False);
+                    results.push(fold_not(inner));
+                }
+                Work::BuildAnd | Work::BuildOr => {
+                    let b = results.pop().unwrap_or(Condition::False);
+                    let a = results.pop().unwrap_or(Condition::False);
+                    results.push(if matches!(item, Work::BuildAnd) {
+                        fold_and(a, b)
+                    } else {
+                        fold_or(a, b)
+                    });
+                }
+            }
+        }
+
+        results.pop().unwrap_or(Condition::False)
+    }
+
+    /// Every context attribute this condition reads, sorted and without
+    /// duplicates. Non-recursive.
+    ///
+    /// Together with `depth()` and `node_count()`, lets tools check a
+    /// condition, or report what it depends on, without building a policy.
+    pub fn referenced_attrs(&self) -> Vec<&'a str> {
+        let mut attrs = BTreeSet::new();
+        let mut stack = vec![self];
+        while let Some(cond) = stack.pop() {
+            match cond {
+                Condition::Not(inner) => stack.push(inner),
+                Condition::And(a, b) | Condition::Or(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                leaf => attrs.extend(leaf.attr()),
+            }
+        }
+        attrs.into_iter().collect()
+    }
+
+    /// The attribute a leaf reads, if any.
+    fn attr(&self) -> Option<&'a str> {
+        match self {
+            Condition::Equals { attr, .. }
+            | Condition::NotEquals { attr, .. }
+            | Condition::GreaterThan { attr, .. }
+            | Condition::LessThan { attr, .. }
+            | Condition::IpInCidr { attr, .. }
+            | Condition::TimeBetween { attr, .. }
+            | Condition::DayOfWeek { attr, .. } => Some(attr),
+            #[cfg(feature = "custom-predicates")]
+            Condition::Custom { attr, .. } => Some(attr),
+            Condition::True
+            | Condition::False
+            | Condition::Not(_)
+            | Condition::And(..)
+            | Condition::Or(..) => None,
+        }
+    }
+
+    /// Returns `true` for conditions without children.
+    fn is_leaf(&self) -> bool {
+        !matches!(
+            self,
+            Condition::Not(_) | Condition::And(..) | Condition::Or(..)
+        )
+    }
+
+    /// Number of nodes in this condition tree. Non-recursive.
+    pub fn node_count(&self) -> usize {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(cond) = stack.pop() {
+            count += 1;
+            match cond {
+                Condition::Not(inner) => stack.push(inner),
+                Condition::And(a, b) | Condition::Or(a, b) => {
+                    stack.push(b);
+                    stack.push(a);
+                }
+                _ => {}
+            }
+        }
+        count
+    }
+
+    /// Validate attribute name and string value lengths. Non-recursive.
+    pub(crate) fn validate_attrs(
+        &self,
+        max_name_len: usize,
+        max_value_len: usize,
+    ) -> Result<(), PolicyError> {
+        let mut stack = vec![self];
+        let mut node = 0;
+        while let Some(cond) = stack.pop() {
+            let at = |err: PolicyError| err.at_node(node);
+            match cond {
+                Condition::Equals { attr, value }
+                | Condition::NotEquals { attr, value }
+                | Condition::GreaterThan { attr, value }
+                | Condition::LessThan { attr, value } => {
+                    validate_attr(attr, value, max_name_len, max_value_len).map_err(at)?;
+                }
+                Condition::IpInCidr { attr, .. }
+                | Condition::TimeBetween { attr, .. }
+                | Condition::DayOfWeek { attr, .. } => {
+                    validate_attr_name(attr, max_name_len).map_err(at)?;
                 }
-                Condition::Not(inner) => {
-                    stack.push(inner);
+                #[cfg(feature = "custom-predicates")]
+                Condition::Custom { attr, .. } => {
+                    validate_attr_name(attr, max_name_len).map_err(at)?
                 }
+                Condition::Not(inner) => stack.push(inner),
                 Condition::And(a, b) | Condition::Or(a, b) => {
                     stack.push(b);
                     stack.push(a);
                 }
+                Condition::True | Condition::False => {}
             }
+            node += 1;
         }
         Ok(())
     }
@@ -170,8 +487,8 @@ impl<'a> Condition<'a> {
         }
 
         // Fixed-size stacks with proven O(depth) bounds.
-        let mut stack: FixedStack<StackItem<'a, '_>, TRAVERSAL_STACK_SIZE> = FixedStack::new();
-        let mut results: FixedStack<bool, VALUE_STACK_SIZE> = FixedStack::new();
+        let mut stack: EvalStack<StackItem<'a, '_>, TRAVERSAL_STACK_SIZE> = EvalStack::new();
+        let mut results: EvalStack<bool, VALUE_STACK_SIZE> = EvalStack::new();
 
         stack.push(StackItem::Eval(self))?;
 
@@ -192,6 +509,43 @@ impl<'a> Condition<'a> {
                             .unwrap_or(true); // Missing attr = true for NotEquals
                         results.push(result)?;
                     }
+                    Condition::GreaterThan { attr, value } => {
+                        let result = lookup_attr(context, attr)
+                            .and_then(|v| v.ordering(value))
+                            .is_some_and(Ordering::is_gt); // Missing or unordered = false
+                        results.push(result)?;
+                    }
+                    Condition::LessThan { attr, value } => {
+                        let result = lookup_attr(context, attr)
+                            .and_then(|v| v.ordering(value))
+                            .is_some_and(Ordering::is_lt); // Missing or unordered = false
+                        results.push(result)?;
+                    }
+                    Condition::IpInCidr { attr, cidr } => {
+                        let result = match (lookup_attr(context, attr), Cidr::parse(cidr)) {
+                            (Some(Value::Ip(ip)), Ok(cidr)) => cidr.contains(*ip),
+                            _ => false, // Missing, not an address, or invalid block
+                        };
+                        results.push(result)?;
+                    }
+                    Condition::TimeBetween { attr, start, end } => {
+                        let result = matches!(
+                            lookup_attr(context, attr),
+                            Some(Value::Timestamp(t)) if time::in_window(*t, *start, *end)
+                        );
+                        results.push(result)?;
+                    }
+                    Condition::DayOfWeek { attr, days } => {
+                        let result = matches!(
+                            lookup_attr(context, attr),
+                            Some(Value::Timestamp(t)) if days.contains(Weekdays::of(*t))
+                        );
+                        results.push(result)?;
+                    }
+                    // Predicates are resolved by the policy; there is no
+                    // registry here
+                    #[cfg(feature = "custom-predicates")]
+                    Condition::Custom { .. } => results.push(false)?,
                     Condition::Not(inner) => {
                         stack.push(StackItem::ApplyNot)?;
                         stack.push(StackItem::Eval(inner))?;
@@ -237,11 +591,11 @@ impl<'a> Drop for Condition<'a> {
 
         match self {
             Condition::And(a, b) | Condition::Or(a, b) => {
-                stack.push(std::mem::replace(a, Box::new(Condition::True)));
-                stack.push(std::mem::replace(b, Box::new(Condition::True)));
+                stack.push(core::mem::replace(a, Box::new(Condition::True)));
+                stack.push(core::mem::replace(b, Box::new(Condition::True)));
             }
             Condition::Not(inner) => {
-                stack.push(std::mem::replace(inner, Box::new(Condition::True)));
+                stack.push(core::mem::replace(inner, Box::new(Condition::True)));
             }
             _ => return,
         }
@@ -249,11 +603,11 @@ impl<'a> Drop for Condition<'a> {
         while let Some(mut boxed_cond) = stack.pop() {
             match *boxed_cond {
                 Condition::And(ref mut a, ref mut b) | Condition::Or(ref mut a, ref mut b) => {
-                    stack.push(std::mem::replace(a, Box::new(Condition::True)));
-                    stack.push(std::mem::replace(b, Box::new(Condition::True)));
+                    stack.push(core::mem::replace(a, Box::new(Condition::True)));
+                    stack.push(core::mem::replace(b, Box::new(Condition::True)));
                 }
                 Condition::Not(ref mut inner) => {
-                    stack.push(std::mem::replace(inner, Box::new(Condition::True)));
+                    stack.push(core::mem::replace(inner, Box::new(Condition::True)));
                 }
                 _ => {}
             }
@@ -262,16 +616,126 @@ impl<'a> Drop for Condition<'a> {
 }
 
 /// Look up an attribute in the context by name.
-fn lookup_attr<'a, 'b>(context: &'b [(&'b str, Value<'a>)], name: &str) -> Option<&'b Value<'a>> {
+pub(crate) fn lookup_attr<'a, 'b>(
+    context: &'b [(&'b str, Value<'a>)],
+    name: &str,
+) -> Option<&'b Value<'a>> {
     context.iter().find(|(k, _)| *k == name).map(|(_, v)| v)
 }
 
+/// Validate an attribute name and, for strings, its value against the
+/// attribute length limits.
+pub(crate) fn validate_attr(
+    name: &str,
+    value: &Value<'_>,
+    max_name_len: usize,
+    max_value_len: usize,
+) -> Result<(), PolicyError> {
+    validate_attr_name(name, max_name_len)?;
+    match value {
+        Value::String(s) if s.len() > max_value_len => Err(PolicyError::AttrValueTooLong {
+            max: max_value_len,
+            actual: s.len(),
+            location: None,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// `Not(cond)`, folded (see `Condition::normalize`).
+fn fold_not(mut cond: Condition<'_>) -> Condition<'_> {
+    match cond {
+        Condition::True => Condition::False,
+        Condition::False => Condition::True,
+        Condition::Equals { attr, ref value } => Condition::NotEquals {
+            attr,
+            value: value.clone(),
+        },
+        Condition::NotEquals { attr, ref value } => Condition::Equals {
+            attr,
+            value: value.clone(),
+        },
+        // `Condition` implements `Drop`, so the inner box is swapped out
+        Condition::Not(ref mut inner) => core::mem::replace(&mut **inner, Condition::True),
+        _ => Condition::Not(Box::new(cond)),
+    }
+}
+
+/// `And(a, b)`, folded (see `Condition::normalize`).
+fn fold_and<'a>(a: Condition<'a>, b: Condition<'a>) -> Condition<'a> {
+    match (&a, &b) {
+        (Condition::False, _) | (_, Condition::False) => Condition::False,
+        (Condition::True, _) => b,
+        (_, Condition::True) => a,
+        _ if a.is_leaf() && a == b => a,
+        _ if complementary(&a, &b) => Condition::False,
+        (
+            Condition::Equals { attr, value },
+            Condition::Equals {
+                attr: other,
+                value: other_value,
+            },
+        ) if attr == other && value != other_value => Condition::False,
+        _ => Condition::And(Box::new(a), Box::new(b)),
+    }
+}
+
+/// `Or(a, b)`, folded (see `Condition::normalize`).
+fn fold_or<'a>(a: Condition<'a>, b: Condition<'a>) -> Condition<'a> {
+    match (&a, &b) {
+        (Condition::True, _) | (_, Condition::True) => Condition::True,
+        (Condition::False, _) => b,
+        (_, Condition::False) => a,
+        _ if a.is_leaf() && a == b => a,
+        _ if complementary(&a, &b) => Condition::True,
+        _ => Condition::Or(Box::new(a), Box::new(b)),
+    }
+}
+
+/// Returns `true` if `b` is the negation of leaf `a` or the other way
+/// round, so exactly one of them holds in every context.
+fn complementary(a: &Condition<'_>, b: &Condition<'_>) -> bool {
+    match (a, b) {
+        (
+            Condition::Equals { attr, value },
+            Condition::NotEquals {
+                attr: other,
+                value: other_value,
+            },
+        )
+        | (
+            Condition::NotEquals { attr, value },
+            Condition::Equals {
+                attr: other,
+                value: other_value,
+            },
+        ) => attr == other && value == other_value,
+        (Condition::Not(inner), leaf) | (leaf, Condition::Not(inner)) => {
+            leaf.is_leaf() && **inner == *leaf
+        }
+        _ => false,
+    }
+}
+
+/// Validate an attribute name against the attribute name limit.
+fn validate_attr_name(name: &str, max_name_len: usize) -> Result<(), PolicyError> {
+    if name.len() > max_name_len {
+        return Err(PolicyError::AttrNameTooLong {
+            max: max_name_len,
+            actual: name.len(),
+            location: None,
+        });
+    }
+    Ok(())
+}
+
 /// Validate that a string does not exceed the maximum allowed length.
 fn validate_str(s: &str, max_len: usize) -> Result<(), PolicyError> {
     if s.len() > max_len {
         Err(PolicyError::StringTooLong {
             max: max_len,
             actual: s.len(),
+            location: None,
         })
     } else {
         Ok(())
@@ -281,6 +745,7 @@ fn validate_str(s: &str, max_len: usize) -> Result<(), PolicyError> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::error::{Location, RulePart};
 
     #[test]
     fn test_condition_true() {
@@ -331,6 +796,96 @@ mod tests {
         assert_eq!(c.evaluate(&[]), Ok(true));
     }
 
+    #[test]
+    fn test_condition_ip_in_cidr() {
+        let c = Condition::IpInCidr {
+            attr: "source_ip",
+            cidr: "10.0.0.0/8",
+        };
+        assert_eq!(c.depth(), 1);
+
+        let ctx: &[(&str, Value)] = &[("source_ip", Value::Ip([10, 9, 8, 7].into()))];
+        assert_eq!(c.evaluate(ctx), Ok(true));
+
+        let ctx: &[(&str, Value)] = &[("source_ip", Value::Ip([172, 16, 0, 1].into()))];
+        assert_eq!(c.evaluate(ctx), Ok(false));
+
+        // Other types and missing attributes are false
+        let ctx: &[(&str, Value)] = &[("source_ip", Value::String("10.0.0.1"))];
+        assert_eq!(c.evaluate(ctx), Ok(false));
+        assert_eq!(c.evaluate(&[]), Ok(false));
+
+        assert!(c.validate(10, 256).is_ok());
+        let bad = Condition::IpInCidr {
+            attr: "source_ip",
+            cidr: "10.0.0.0/40",
+        };
+        assert_eq!(bad.validate(10, 256), Err(PolicyError::InvalidCidr));
+    }
+
+    #[test]
+    fn test_condition_time() {
+        let hours = Condition::TimeBetween {
+            attr: "now",
+            start: 22 * 3600,
+            end: 6 * 3600,
+        };
+        let weekend = Condition::DayOfWeek {
+            attr: "now",
+            days: Weekdays::WEEKEND,
+        };
+
+        // Saturday 2024-01-06 23:00, then 12:00
+        let ctx: &[(&str, Value)] = &[("now", Value::Timestamp(1_704_582_000))];
+        assert_eq!(hours.evaluate(ctx), Ok(true));
+        assert_eq!(weekend.evaluate(ctx), Ok(true));
+        let ctx: &[(&str, Value)] = &[("now", Value::Timestamp(1_704_542_400))];
+        assert_eq!(hours.evaluate(ctx), Ok(false));
+
+        // Plain integers are not timestamps
+        let ctx: &[(&str, Value)] = &[("now", Value::Int(1_704_582_000))];
+        assert_eq!(hours.evaluate(ctx), Ok(false));
+        assert_eq!(weekend.evaluate(&[]), Ok(false));
+
+        let bad = Condition::TimeBetween {
+            attr: "now",
+            start: 0,
+            end: 90_000,
+        };
+        assert_eq!(bad.validate(10, 256), Err(PolicyError::InvalidTime));
+    }
+
+    #[test]
+    fn test_condition_ordering() {
+        let long = Condition::GreaterThan {
+            attr: "session",
+            value: Value::Duration(3600),
+        };
+        let short = Condition::LessThan {
+            attr: "session",
+            value: Value::Duration(3600),
+        };
+
+        let ctx: &[(&str, Value)] = &[("session", Value::Duration(7200))];
+        assert_eq!(long.evaluate(ctx), Ok(true));
+        assert_eq!(short.evaluate(ctx), Ok(false));
+        let ctx: &[(&str, Value)] = &[("session", Value::Duration(3600))];
+        assert_eq!(long.evaluate(ctx), Ok(false));
+        assert_eq!(short.evaluate(ctx), Ok(false));
+
+        // Other types and missing attributes are false either way
+        let ctx: &[(&str, Value)] = &[("session", Value::Int(7200))];
+        assert_eq!(long.evaluate(ctx), Ok(false));
+        assert_eq!(short.evaluate(ctx), Ok(false));
+        assert_eq!(short.evaluate(&[]), Ok(false));
+
+        let bad = Condition::LessThan {
+            attr: "role",
+            value: Value::String("admin"),
+        };
+        assert_eq!(bad.validate(10, 256), Err(PolicyError::UnorderedValue));
+    }
+
     #[test]
     fn test_condition_not() {
         let c = Condition::Not(Box::new(Condition::True));
@@ -381,6 +936,26 @@ mod tests {
         assert!(c.validate(2, 256).is_ok());
     }
 
+    #[test]
+    fn test_validate_reports_node() {
+        // Pre-order: And 0, True 1, Not 2, Equals 3
+        let c = Condition::And(
+            Box::new(Condition::True),
+            Box::new(Condition::Not(Box::new(Condition::Equals {
+                attr: "much-too-long",
+                value: Value::Bool(true),
+            }))),
+        );
+        let at = |node| {
+            Some(Location {
+                rule: None,
+                part: RulePart::Condition { node },
+            })
+        };
+        assert_eq!(c.validate(10, 8).unwrap_err().location(), at(3));
+        assert_eq!(c.validate_attrs(8, 8).unwrap_err().location(), at(3));
+    }
+
     #[test]
     fn test_validate_depth_exceeds() {
         let c = Condition::And(
@@ -390,7 +965,17 @@ mod tests {
         // Depth is 3
         assert!(c.validate(2, 256).is_err());
         let err = c.validate(2, 256).unwrap_err();
-        assert_eq!(err, PolicyError::ConditionTooDeep { max: 2, actual: 3 });
+        assert_eq!(
+            err,
+            PolicyError::ConditionTooDeep {
+                max: 2,
+                actual: 3,
+                location: Some(Location {
+                    rule: None,
+                    part: RulePart::Condition { node: 0 }
+                })
+            }
+        );
     }
 
     #[test]
@@ -416,4 +1001,76 @@ mod tests {
         let ctx: &[(&str, Value)] = &[("role", Value::String("user")), ("level", Value::Int(3))];
         assert_eq!(c.evaluate(ctx), Ok(false));
     }
+
+    #[test]
+    fn test_normalize() {
+        let eq = |v| Condition::Equals {
+            attr: "level",
+            value: Value::Int(v),
+        };
+        let neq = |v| Condition::NotEquals {
+            attr: "level",
+            value: Value::Int(v),
+        };
+        let and = |a, b| Condition::And(Box::new(a), Box::new(b));
+        let or = |a, b| Condition::Or(Box::new(a), Box::new(b));
+        let not = |c| Condition::Not(Box::new(c));
+
+        assert_eq!(and(Condition::True, eq(1)).normalize(), eq(1));
+        assert_eq!(or(eq(1), Condition::False).normalize(), eq(1));
+        assert_eq!(not(not(eq(1))).normalize(), eq(1));
+        assert_eq!(not(eq(1)).normalize(), neq(1));
+        assert_eq!(and(eq(1), eq(1)).normalize(), eq(1));
+        assert_eq!(and(eq(1), neq(1)).normalize(), Condition::False);
+        assert_eq!(and(eq(1), eq(2)).normalize(), Condition::False);
+        assert_eq!(or(neq(1), not(neq(1))).normalize(), Condition::True);
+        assert_eq!(and(eq(1), neq(2)).normalize(), and(eq(1), neq(2)));
+
+        // Folding works bottom up through the whole tree
+        let cond = or(
+            and(not(Condition::False), not(eq(1))),
+            and(eq(2), not(not(neq(2)))),
+        );
+        let folded = cond.normalize();
+        assert_eq!(folded, neq(1));
+        for ctx in [
+            &[][..],
+            &[("level", Value::Int(1))][..],
+            &[("level", Value::Int(2))][..],
+            &[("level", Value::String("1"))][..],
+        ] {
+            assert_eq!(folded.evaluate(ctx), cond.evaluate(ctx));
+        }
+
+        // Deep trees are folded without recursion
+        let mut deep = eq(1);
+        for _ in 0..10_000 {
+            deep = and(Condition::True, deep);
+        }
+        assert_eq!(deep.normalize(), eq(1));
+    }
+
+    #[test]
+    fn test_introspection() {
+        let cond = Condition::Or(
+            Box::new(Condition::And(
+                Box::new(Condition::Equals {
+                    attr: "role",
+                    value: Value::String("admin"),
+                }),
+                Box::new(Condition::Not(Box::new(Condition::GreaterThan {
+                    attr: "age",
+                    value: Value::Int(3),
+                }))),
+            )),
+            Box::new(Condition::Equals {
+                attr: "role",
+                value: Value::String("owner"),
+            }),
+        );
+        assert_eq!(cond.referenced_attrs(), ["age", "role"]);
+        assert_eq!(cond.depth(), 4);
+        assert_eq!(cond.node_count(), 6);
+        assert!(Condition::True.referenced_attrs().is_empty());
+    }
 }
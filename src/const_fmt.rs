@@ -0,0 +1,64 @@
+//! Panic messages with numbers in them, for compile-time checks.
+//!
+//! `panic!` in a `const fn` can only format a `&str`, so checks that want to
+//! name the offending rule or code build the text in a fixed buffer first.
+
+/// A fixed-size message buffer; text past its capacity is dropped.
+pub(crate) struct ConstMessage {
+    buf: [u8; 128],
+    len: usize,
+}
+
+impl ConstMessage {
+    pub(crate) const fn new() -> Self {
+        ConstMessage {
+            buf: [0; 128],
+            len: 0,
+        }
+    }
+
+    pub(crate) const fn str(mut self, s: &str) -> Self {
+        let bytes = s.as_bytes();
+        let mut i = 0;
+        // Stop at a character boundary so the buffer stays valid UTF-8
+        while i < bytes.len() && self.len + utf8_len(bytes[i]) <= self.buf.len() {
+            let end = i + utf8_len(bytes[i]);
+            while i < end {
+                self.buf[self.len] = bytes[i];
+                self.len += 1;
+                i += 1;
+            }
+        }
+        self
+    }
+
+    pub(crate) const fn int(mut self, n: u64) -> Self {
+        let mut divisor = 1;
+        while divisor <= n / 10 {
+            divisor *= 10;
+        }
+        while divisor > 0 && self.len < self.buf.len() {
+            self.buf[self.len] = b'0' + (n / divisor % 10) as u8;
+            self.len += 1;
+            divisor /= 10;
+        }
+        self
+    }
+
+    /// Panic with the message.
+    pub(crate) const fn panic(&self) -> ! {
+        match core::str::from_utf8(self.buf.split_at(self.len).0) {
+            Ok(message) => panic!("{}", message),
+            Err(_) => panic!("compile-time check failed"),
+        }
+    }
+}
+
+const fn utf8_len(first: u8) -> usize {
+    match first {
+        0xF0..=0xFF => 4,
+        0xE0..=0xEF => 3,
+        0xC0..=0xDF => 2,
+        _ => 1,
+    }
+}
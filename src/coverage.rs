@@ -0,0 +1,371 @@
+//! Rule and condition coverage for policy test suites.
+//!
+//! A `CoverageTracker` evaluates requests exactly like `Policy::evaluate`
+//! while recording which rules matched and which outcomes each attribute
+//! comparison produced. After running a test corpus through it, the report
+//! lists every rule that never matched and every comparison that was never
+//! seen both true and false, so CI can fail when a rule lands without a test.
+//!
+//! # Branches
+//!
+//! Branch coverage is tracked for attribute comparisons (the leaves that
+//! read an attribute, such as `Equals` or `IpInCidr`). Constants cannot take both outcomes, and the result
+//! of `And`/`Or`/`Not` follows from their leaves, so neither is reported.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::compiled::Op;
+use crate::error::PolicyError;
+use crate::observer::Observer;
+use crate::policy::Policy;
+use crate::types::{Decision, Effect, ReasonCode, Request};
+
+/// Outcomes seen for one condition node.
+#[derive(Debug, Clone, Copy, Default)]
+struct Seen {
+    true_: bool,
+    false_: bool,
+}
+
+/// Counters for one rule.
+#[derive(Debug, Clone, Default)]
+struct RuleCounts {
+    target_matches: u64,
+    matches: u64,
+    nodes: Vec<Seen>,
+}
+
+/// Records rule and branch coverage across many evaluations.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{CoverageTracker, Policy, ReasonCode, Request, Rule, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(Target::any(), ReasonCode(1)))
+///     .build()
+///     .unwrap();
+///
+/// let mut tracker = CoverageTracker::new(&policy);
+/// tracker.evaluate(&Request::new("alice", "read", "doc")).unwrap();
+///
+/// let report = tracker.report();
+/// assert!(report.is_complete(), "{}", report);
+/// ```
+#[derive(Debug)]
+pub struct CoverageTracker<'p, 'a> {
+    policy: &'p Policy<'a>,
+    rules: Vec<RuleCounts>,
+}
+
+impl<'p, 'a> CoverageTracker<'p, 'a> {
+    /// Create a tracker for `policy` with no coverage recorded.
+    pub fn new(policy: &'p Policy<'a>) -> Self {
+        let compiled = policy.compiled();
+        let rules = (0..policy.rule_count())
+            .map(|i| RuleCounts {
+                nodes: vec![Seen::default(); compiled.program_len(i)],
+                ..RuleCounts::default()
+            })
+            .collect();
+        CoverageTracker { policy, rules }
+    }
+
+    /// Evaluate `request` and record what it exercised.
+    ///
+    /// Returns the same result as `Policy::evaluate`.
+    pub fn evaluate(&mut self, request: &Request<'_>) -> Result<Decision, PolicyError> {
+        let mut observer = Recorder {
+            rules: &mut self.rules,
+        };
+        self.policy
+            .evaluate_observed(request, &mut observer)
+            .map(|(decision, _)| decision)
+    }
+
+    /// Forget all recorded coverage.
+    pub fn reset(&mut self) {
+        for rule in &mut self.rules {
+            rule.target_matches = 0;
+            rule.matches = 0;
+            rule.nodes.fill(Seen::default());
+        }
+    }
+
+    /// Summarize the coverage recorded so far.
+    pub fn report(&self) -> CoverageReport<'a> {
+        let compiled = self.policy.compiled();
+        let rules = self
+            .policy
+            .rules()
+            .iter()
+            .zip(&self.rules)
+            .enumerate()
+            .map(|(index, (rule, counts))| {
+                let program = compiled.program(index).unwrap_or(&[]);
+                let branches = program
+                    .iter()
+                    .zip(&counts.nodes)
+                    .enumerate()
+                    .filter_map(|(node, (op, seen))| match op {
+                        Op::Equals(attr, _)
+                        | Op::NotEquals(attr, _)
+                        | Op::GreaterThan(attr, _)
+                        | Op::LessThan(attr, _)
+                        | Op::IpInCidr(attr, _)
+                        | Op::TimeBetween(attr, ..)
+                        | Op::DayOfWeek(attr, _) => Some(BranchCoverage {
+                            node,
+                            attr,
+                            seen_true: seen.true_,
+                            seen_false: seen.false_,
+                        }),
+                        #[cfg(feature = "custom-predicates")]
+                        Op::Custom(attr, _) => Some(BranchCoverage {
+                            node,
+                            attr,
+                            seen_true: seen.true_,
+                            seen_false: seen.false_,
+                        }),
+                        _ => None,
+                    })
+                    .collect();
+
+                RuleCoverage {
+                    index,
+                    effect: rule.effect,
+                    reason: rule.reason,
+                    target_matches: counts.target_matches,
+                    matches: counts.matches,
+                    branches,
+                }
+            })
+            .collect();
+
+        CoverageReport { rules }
+    }
+}
+
+/// Observer that writes into a tracker's counters.
+struct Recorder<'r> {
+    rules: &'r mut [RuleCounts],
+}
+
+impl Observer for Recorder<'_> {
+    fn target_matched(&mut self, rule: usize) {
+        if let Some(counts) = self.rules.get_mut(rule) {
+            counts.target_matches += 1;
+        }
+    }
+
+    fn node_result(&mut self, rule: usize, node: usize, result: bool) {
+        if let Some(seen) = self.rules.get_mut(rule).and_then(|c| c.nodes.get_mut(node)) {
+            if result {
+                seen.true_ = true;
+            } else {
+                seen.false_ = true;
+            }
+        }
+    }
+
+    fn rule_matched(&mut self, rule: usize) {
+        if let Some(counts) = self.rules.get_mut(rule) {
+            counts.matches += 1;
+        }
+    }
+}
+
+/// Coverage of a single attribute comparison.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchCoverage<'a> {
+    /// Position of the comparison in the rule's condition (postfix order).
+    pub node: usize,
+    /// The attribute being compared.
+    pub attr: &'a str,
+    /// Whether the comparison was ever true.
+    pub seen_true: bool,
+    /// Whether the comparison was ever false.
+    pub seen_false: bool,
+}
+
+impl BranchCoverage<'_> {
+    /// Returns `true` if both outcomes were exercised.
+    pub fn is_covered(&self) -> bool {
+        self.seen_true && self.seen_false
+    }
+}
+
+/// Coverage of a single rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleCoverage<'a> {
+    /// Index of the rule in the policy.
+    pub index: usize,
+    /// The rule's effect.
+    pub effect: Effect,
+    /// The rule's reason code.
+    pub reason: ReasonCode,
+    /// Number of evaluations where the rule's target matched.
+    pub target_matches: u64,
+    /// Number of evaluations where the whole rule matched.
+    pub matches: u64,
+    /// Coverage of each attribute comparison in the rule's condition.
+    pub branches: Vec<BranchCoverage<'a>>,
+}
+
+impl RuleCoverage<'_> {
+    /// Returns `true` if the rule matched and every branch was exercised.
+    pub fn is_covered(&self) -> bool {
+        self.matches > 0 && self.branches.iter().all(BranchCoverage::is_covered)
+    }
+}
+
+/// Coverage summary produced by `CoverageTracker::report`.
+///
+/// `Display` lists every gap, one per line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverageReport<'a> {
+    /// Coverage of every rule, in declared order.
+    pub rules: Vec<RuleCoverage<'a>>,
+}
+
+impl<'a> CoverageReport<'a> {
+    /// Rules that never matched or have unexercised branches.
+    pub fn uncovered(&self) -> impl Iterator<Item = &RuleCoverage<'a>> {
+        self.rules.iter().filter(|r| !r.is_covered())
+    }
+
+    /// Returns `true` if every rule is fully covered.
+    pub fn is_complete(&self) -> bool {
+        self.uncovered().next().is_none()
+    }
+}
+
+impl fmt::Display for CoverageReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_complete() {
+            return write!(f, "all {} rules covered", self.rules.len());
+        }
+
+        for rule in self.uncovered() {
+            let label = match rule.effect {
+                Effect::Allow => "allow",
+                Effect::Deny => "deny",
+            };
+            if rule.matches == 0 {
+                if rule.target_matches == 0 {
+                    writeln!(
+                        f,
+                        "rule {} ({}, reason {}): target never matched",
+                        rule.index, label, rule.reason.0
+                    )?;
+                } else {
+                    writeln!(
+                        f,
+                        "rule {} ({}, reason {}): condition never true",
+                        rule.index, label, rule.reason.0
+                    )?;
+                }
+            }
+            for branch in rule.branches.iter().filter(|b| !b.is_covered()) {
+                let missing = if branch.seen_true { "false" } else { "true" };
+                writeln!(
+                    f,
+                    "rule {} ({}, reason {}): comparison on '{}' (node {}) never {}",
+                    rule.index, label, rule.reason.0, branch.attr, branch.node, missing
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Matcher, Rule, Target, Value};
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+
+    fn policy() -> Policy<'static> {
+        Policy::builder()
+            .rule(Rule::deny(
+                Target {
+                    principal: Matcher::Exact("mallory"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::And(
+                    Box::new(Condition::Equals {
+                        attr: "mfa",
+                        value: Value::Bool(true),
+                    }),
+                    Box::new(Condition::True),
+                )),
+                ReasonCode(2),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_gaps_reported() {
+        let policy = policy();
+        let mut tracker = CoverageTracker::new(&policy);
+
+        let ctx: &[(&str, Value)] = &[("mfa", Value::Bool(true))];
+        tracker
+            .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+            .unwrap();
+
+        let report = tracker.report();
+        assert!(!report.is_complete());
+
+        let uncovered: Vec<usize> = report.uncovered().map(|r| r.index).collect();
+        assert_eq!(uncovered, vec![0, 1]);
+        assert_eq!(report.rules[1].matches, 1);
+        assert_eq!(
+            report.rules[1].branches,
+            vec![BranchCoverage {
+                node: 0,
+                attr: "mfa",
+                seen_true: true,
+                seen_false: false,
+            }]
+        );
+
+        let text = report.to_string();
+        assert!(text.contains("rule 0 (deny, reason 1): target never matched"));
+        assert!(text.contains("comparison on 'mfa' (node 0) never false"));
+    }
+
+    #[test]
+    fn test_complete_coverage_and_reset() {
+        let policy = policy();
+        let mut tracker = CoverageTracker::new(&policy);
+
+        let ctx: &[(&str, Value)] = &[("mfa", Value::Bool(true))];
+        let requests = [
+            Request::new("mallory", "read", "doc"),
+            Request::with_context("alice", "read", "doc", ctx),
+            Request::new("alice", "read", "doc"),
+        ];
+        for request in &requests {
+            assert_eq!(tracker.evaluate(request), policy.evaluate(request));
+        }
+
+        let report = tracker.report();
+        assert!(report.is_complete(), "{}", report);
+        assert_eq!(report.to_string(), "all 2 rules covered");
+
+        tracker.reset();
+        assert!(!tracker.report().is_complete());
+    }
+}
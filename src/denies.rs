@@ -0,0 +1,197 @@
+//! Collecting every matching deny reason.
+//!
+//! Deny-overrides reports only the first matching deny. To show users every
+//! reason access was refused, `Policy::evaluate_all_denies` also returns the
+//! reasons of all matching deny rules in a fixed-size buffer, so evaluation
+//! stays allocation-free.
+
+use core::fmt;
+
+use crate::error::PolicyError;
+use crate::observer::Observer;
+use crate::policy::Policy;
+use crate::types::{Decision, Effect, ReasonCode, Request, NO_MATCHING_RULE};
+
+/// Maximum number of deny reasons kept by `DenyReasons`.
+pub const MAX_DENY_REASONS: usize = 16;
+
+/// Reasons of every matching deny rule, in declared order.
+///
+/// Holds at most `MAX_DENY_REASONS`; `is_truncated()` reports whether more
+/// deny rules matched than were kept.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct DenyReasons {
+    reasons: [ReasonCode; MAX_DENY_REASONS],
+    len: usize,
+    matched: usize,
+}
+
+impl DenyReasons {
+    /// An empty set of reasons.
+    pub const fn new() -> Self {
+        DenyReasons {
+            reasons: [NO_MATCHING_RULE; MAX_DENY_REASONS],
+            len: 0,
+            matched: 0,
+        }
+    }
+
+    fn push(&mut self, reason: ReasonCode) {
+        if let Some(slot) = self.reasons.get_mut(self.len) {
+            *slot = reason;
+            self.len += 1;
+        }
+        self.matched += 1;
+    }
+
+    /// The kept reasons, in declared rule order.
+    pub fn as_slice(&self) -> &[ReasonCode] {
+        &self.reasons[..self.len]
+    }
+
+    /// Number of kept reasons.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if no deny rule matched.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of deny rules that matched, including any not kept.
+    pub fn matched(&self) -> usize {
+        self.matched
+    }
+
+    /// Returns `true` if more deny rules matched than could be kept.
+    pub fn is_truncated(&self) -> bool {
+        self.matched > self.len
+    }
+
+    /// Iterate over the kept reasons.
+    pub fn iter(&self) -> impl Iterator<Item = ReasonCode> + '_ {
+        self.as_slice().iter().copied()
+    }
+}
+
+impl Default for DenyReasons {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DenyReasons {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DenyReasons")
+            .field("reasons", &self.as_slice())
+            .field("matched", &self.matched)
+            .finish()
+    }
+}
+
+/// Observer that records matching deny rules.
+struct DenyCollector<'p, 'a> {
+    policy: &'p Policy<'a>,
+    reasons: DenyReasons,
+}
+
+impl Observer for DenyCollector<'_, '_> {
+    fn rule_matched(&mut self, rule: usize) {
+        if let Some(rule) = self.policy.rules().get(rule) {
+            if rule.effect == Effect::Deny {
+                self.reasons.push(rule.reason);
+            }
+        }
+    }
+}
+
+impl Policy<'_> {
+    /// Evaluate this policy and also collect every matching deny reason.
+    ///
+    /// The decision is identical to `evaluate()`; when it is a deny from a
+    /// rule, its reason is the first entry of the returned `DenyReasons`.
+    /// Allocation-free.
+    pub fn evaluate_all_denies(
+        &self,
+        request: &Request<'_>,
+    ) -> Result<(Decision, DenyReasons), PolicyError> {
+        let mut collector = DenyCollector {
+            policy: self,
+            reasons: DenyReasons::new(),
+        };
+        let (decision, _) = self.evaluate_observed(request, &mut collector)?;
+        Ok((decision, collector.reasons))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Matcher, Rule, Target, Value};
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_collects_all_denies_in_order() {
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .rule(Rule::deny(
+                Target {
+                    principal: Matcher::Exact("mallory"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(2),
+            ))
+            .rule(Rule::new(
+                Effect::Deny,
+                Target::any(),
+                Some(Condition::Equals {
+                    attr: "suspended",
+                    value: Value::Bool(true),
+                }),
+                ReasonCode(3),
+            ))
+            .rule(Rule::deny(
+                Target {
+                    principal: Matcher::Any,
+                    action: Matcher::Exact("delete"),
+                    resource: Matcher::Any,
+                },
+                ReasonCode(4),
+            ))
+            .build()
+            .unwrap();
+
+        let ctx: &[(&str, Value)] = &[("suspended", Value::Bool(true))];
+        let request = Request::with_context("mallory", "read", "doc", ctx);
+        let (decision, denies) = policy.evaluate_all_denies(&request).unwrap();
+
+        assert_eq!(decision, policy.evaluate(&request).unwrap());
+        assert_eq!(denies.as_slice(), &[ReasonCode(2), ReasonCode(3)]);
+        assert_eq!(decision.reason, denies.as_slice()[0]);
+        assert!(!denies.is_truncated());
+
+        let (decision, denies) = policy
+            .evaluate_all_denies(&Request::new("alice", "read", "doc"))
+            .unwrap();
+        assert!(decision.is_allow());
+        assert!(denies.is_empty());
+    }
+
+    #[test]
+    fn test_truncates_at_capacity() {
+        let rules: Vec<Rule> = (0..MAX_DENY_REASONS as u32 + 2)
+            .map(|i| Rule::deny(Target::any(), ReasonCode(i + 1)))
+            .collect();
+        let policy = Policy::new(rules).unwrap();
+
+        let (_, denies) = policy
+            .evaluate_all_denies(&Request::new("alice", "read", "doc"))
+            .unwrap();
+        assert_eq!(denies.len(), MAX_DENY_REASONS);
+        assert_eq!(denies.matched(), MAX_DENY_REASONS + 2);
+        assert!(denies.is_truncated());
+        assert_eq!(denies.iter().next(), Some(ReasonCode(1)));
+    }
+}
@@ -1,9 +1,10 @@
 //! Error types for the policy engine.
 //!
 //! Hand-written Display implementation (no thiserror dependency).
+//! `std::error::Error` is implemented when the `std` feature is enabled.
 //! All errors are explicit and typed - no string-based errors.
 
-use std::fmt;
+use core::fmt;
 
 /// Errors that can occur during policy construction or evaluation.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -14,6 +15,8 @@ pub enum PolicyError {
         max: usize,
         /// The actual depth of the condition.
         actual: usize,
+        /// Where in the policy, if found while building one.
+        location: Option<Location>,
     },
 
     /// The policy contains too many rules.
@@ -48,12 +51,26 @@ pub enum PolicyError {
         actual: &'static str,
     },
 
+    /// A request context contains the same key more than once.
+    DuplicateContextKey {
+        /// Position of the repeated key in the context.
+        index: usize,
+    },
+
+    /// A condition uses a context attribute the declared schema omits.
+    UndeclaredAttribute {
+        /// Index of the rule whose condition uses the attribute.
+        rule: usize,
+    },
+
     /// A matcher (OneOf) contains too many options.
     TooManyMatcherOptions {
         /// The configured maximum number of options.
         max: usize,
         /// The actual number of options.
         actual: usize,
+        /// Where in the policy, if found while building one.
+        location: Option<Location>,
     },
 
     /// A string (identifier or value) exceeds the maximum allowed length.
@@ -62,6 +79,8 @@ pub enum PolicyError {
         max: usize,
         /// The actual length of the string.
         actual: usize,
+        /// Where in the policy, if found while building one.
+        location: Option<Location>,
     },
 
     /// The evaluation stack overflowed during condition evaluation.
@@ -71,14 +90,265 @@ pub enum PolicyError {
         max: usize,
     },
 
+    /// Evaluation would exceed the configured step budget.
+    StepBudgetExceeded {
+        /// The configured maximum number of steps.
+        max: usize,
+        /// The step count at which evaluation stopped.
+        actual: usize,
+    },
+
+    /// A principal reaches a group through too many levels of nesting.
+    GroupTooDeep {
+        /// The configured maximum depth.
+        max: usize,
+        /// The depth at which expansion stopped.
+        actual: usize,
+    },
+
+    /// A `PolicyStore` has no policy for the requested tenant.
+    UnknownTenant,
+
+    /// Adding a tenant would exceed a `PolicyStore`'s limit.
+    TooManyTenants {
+        /// The configured maximum number of tenants.
+        max: usize,
+        /// The tenant count that was attempted.
+        actual: usize,
+    },
+
+    /// Two entries in a `ReasonRegistry` use the same reason code.
+    DuplicateReasonCode {
+        /// The duplicated code.
+        code: u32,
+    },
+
+    /// Two entries in a `ReasonRegistry` use the same name.
+    DuplicateReasonName {
+        /// The code of the later entry reusing the name.
+        code: u32,
+    },
+
+    /// The policy's conditions have more nodes in total than allowed.
+    TooManyConditionNodes {
+        /// The configured maximum node count.
+        max: usize,
+        /// The actual node count.
+        actual: usize,
+    },
+
+    /// An attribute name exceeds the maximum allowed length.
+    AttrNameTooLong {
+        /// The configured maximum length.
+        max: usize,
+        /// The actual length.
+        actual: usize,
+        /// Where in the policy, if found while building one.
+        location: Option<Location>,
+    },
+
+    /// A string attribute value exceeds the maximum allowed length.
+    AttrValueTooLong {
+        /// The configured maximum length.
+        max: usize,
+        /// The actual length.
+        actual: usize,
+        /// Where in the policy, if found while building one.
+        location: Option<Location>,
+    },
+
+    /// A `PolicyBuilder` edit referred to a rule that does not exist.
+    RuleIndexOutOfRange {
+        /// The index that was used.
+        index: usize,
+        /// The number of rules at the time of the edit.
+        len: usize,
+    },
+
+    /// A property query would explore more requests than its limit.
+    QueryTooLarge {
+        /// The configured maximum number of requests.
+        max: usize,
+        /// The number of requests the query needs, saturated at `usize::MAX`.
+        actual: usize,
+    },
+
+    /// A CIDR block is malformed, has a prefix longer than its address, or
+    /// has host bits set.
+    InvalidCidr,
+
+    /// A time of day is malformed or past the end of the day, or a day of
+    /// the week is not one of `mon` to `sun`.
+    InvalidTime,
+
+    /// A duration is malformed or overflows `u64` seconds.
+    InvalidDuration,
+
+    /// `GreaterThan` or `LessThan` compares against a value without an
+    /// order (a boolean, string, or address).
+    UnorderedValue,
+
+    /// A `Condition::Custom` names a predicate the policy's
+    /// `PredicateRegistry` does not hold.
+    UnknownPredicate {
+        /// Index of the rule whose condition names the predicate.
+        rule: usize,
+    },
+
+    /// `Policy::query` cannot search a policy whose conditions call custom
+    /// predicates, since their classes of values are unknown.
+    UndecidableQuery {
+        /// Index of the first rule whose condition calls one.
+        rule: usize,
+    },
+
+    /// A built policy's derived state disagrees with its rules, found by
+    /// `Policy::verify_invariants`.
+    InvariantViolated {
+        /// Which derived state disagrees: `"compiled_conditions"`,
+        /// `"target_index"`, `"interner"`, or `"fingerprint"`.
+        invariant: &'static str,
+    },
+
     /// Internal invariant violation. Should never occur in correct usage.
     InternalError,
 }
 
+impl PolicyError {
+    /// A stable snake_case name for the variant, without its fields.
+    ///
+    /// Suitable as a metric label or log field: unlike `Display`, it never
+    /// contains request data and has few distinct values.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PolicyError::ConditionTooDeep { .. } => "condition_too_deep",
+            PolicyError::TooManyRules { .. } => "too_many_rules",
+            PolicyError::ContextTooLarge { .. } => "context_too_large",
+            PolicyError::AttributeNotFound { .. } => "attribute_not_found",
+            PolicyError::TypeMismatch { .. } => "type_mismatch",
+            PolicyError::DuplicateContextKey { .. } => "duplicate_context_key",
+            PolicyError::UndeclaredAttribute { .. } => "undeclared_attribute",
+            PolicyError::TooManyMatcherOptions { .. } => "too_many_matcher_options",
+            PolicyError::StringTooLong { .. } => "string_too_long",
+            PolicyError::EvalStackOverflow { .. } => "eval_stack_overflow",
+            PolicyError::StepBudgetExceeded { .. } => "step_budget_exceeded",
+            PolicyError::GroupTooDeep { .. } => "group_too_deep",
+            PolicyError::UnknownTenant => "unknown_tenant",
+            PolicyError::TooManyTenants { .. } => "too_many_tenants",
+            PolicyError::DuplicateReasonCode { .. } => "duplicate_reason_code",
+            PolicyError::DuplicateReasonName { .. } => "duplicate_reason_name",
+            PolicyError::TooManyConditionNodes { .. } => "too_many_condition_nodes",
+            PolicyError::AttrNameTooLong { .. } => "attr_name_too_long",
+            PolicyError::AttrValueTooLong { .. } => "attr_value_too_long",
+            PolicyError::RuleIndexOutOfRange { .. } => "rule_index_out_of_range",
+            PolicyError::QueryTooLarge { .. } => "query_too_large",
+            PolicyError::InvalidCidr => "invalid_cidr",
+            PolicyError::InvalidTime => "invalid_time",
+            PolicyError::InvalidDuration => "invalid_duration",
+            PolicyError::UnorderedValue => "unordered_value",
+            PolicyError::UnknownPredicate { .. } => "unknown_predicate",
+            PolicyError::UndecidableQuery { .. } => "undecidable_query",
+            PolicyError::InvariantViolated { .. } => "invariant_violated",
+            PolicyError::InternalError => "internal_error",
+        }
+    }
+
+    /// Where in the policy a limit was exceeded, for limit errors found
+    /// while building one.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            PolicyError::ConditionTooDeep { location, .. }
+            | PolicyError::TooManyMatcherOptions { location, .. }
+            | PolicyError::StringTooLong { location, .. }
+            | PolicyError::AttrNameTooLong { location, .. }
+            | PolicyError::AttrValueTooLong { location, .. } => *location,
+            _ => None,
+        }
+    }
+
+    /// Record that this error was found at condition node `node`.
+    pub(crate) fn at_node(self, node: usize) -> Self {
+        self.at(None, RulePart::Condition { node })
+    }
+
+    /// Record that this error was found in `part` of rule `rule`, keeping a
+    /// condition node already recorded.
+    pub(crate) fn in_rule(self, rule: usize, part: RulePart) -> Self {
+        let part = self.location().map_or(part, |location| location.part);
+        self.at(Some(rule), part)
+    }
+
+    fn at(mut self, rule: Option<usize>, part: RulePart) -> Self {
+        match &mut self {
+            PolicyError::ConditionTooDeep { location, .. }
+            | PolicyError::TooManyMatcherOptions { location, .. }
+            | PolicyError::StringTooLong { location, .. }
+            | PolicyError::AttrNameTooLong { location, .. }
+            | PolicyError::AttrValueTooLong { location, .. } => {
+                *location = Some(Location { rule, part });
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+/// Where in a policy a limit error was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    /// Index of the rule, or `None` if the condition or matcher was
+    /// validated on its own.
+    pub rule: Option<usize>,
+    /// The part of the rule.
+    pub part: RulePart,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(rule) = self.rule {
+            write!(f, "rule {}, ", rule)?;
+        }
+        match self.part {
+            RulePart::Principal => write!(f, "principal"),
+            RulePart::Action => write!(f, "action"),
+            RulePart::Resource => write!(f, "resource"),
+            RulePart::Condition { node } => write!(f, "condition node {}", node),
+        }
+    }
+}
+
+/// A part of a rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RulePart {
+    /// The principal matcher.
+    Principal,
+    /// The action matcher.
+    Action,
+    /// The resource matcher.
+    Resource,
+    /// A node of the condition, numbered in pre-order: the root is 0, and
+    /// a left operand's nodes come before the right operand's.
+    Condition {
+        /// The node's number.
+        node: usize,
+    },
+}
+
 impl fmt::Display for PolicyError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.message(f)?;
+        match self.location() {
+            Some(location) => write!(f, " ({})", location),
+            None => Ok(()),
+        }
+    }
+}
+
+impl PolicyError {
+    /// The `Display` text, without the location.
+    pub(crate) fn message(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            PolicyError::ConditionTooDeep { max, actual } => {
+            PolicyError::ConditionTooDeep { max, actual, .. } => {
                 write!(
                     f,
                     "condition exceeds maximum depth of {}, got {}",
@@ -113,14 +383,24 @@ impl fmt::Display for PolicyError {
                     attr, expected, actual
                 )
             }
-            PolicyError::TooManyMatcherOptions { max, actual } => {
+            PolicyError::DuplicateContextKey { index } => {
+                write!(f, "context key at position {} is a duplicate", index)
+            }
+            PolicyError::UndeclaredAttribute { rule } => {
+                write!(
+                    f,
+                    "rule {} uses an attribute not declared in the schema",
+                    rule
+                )
+            }
+            PolicyError::TooManyMatcherOptions { max, actual, .. } => {
                 write!(
                     f,
                     "matcher exceeds maximum options of {}, got {}",
                     max, actual
                 )
             }
-            PolicyError::StringTooLong { max, actual } => {
+            PolicyError::StringTooLong { max, actual, .. } => {
                 write!(
                     f,
                     "string exceeds maximum length of {}, got {}",
@@ -130,6 +410,85 @@ impl fmt::Display for PolicyError {
             PolicyError::EvalStackOverflow { max } => {
                 write!(f, "evaluation stack overflow (max: {})", max)
             }
+            PolicyError::StepBudgetExceeded { max, actual } => {
+                write!(
+                    f,
+                    "evaluation exceeds step budget of {}, got {}",
+                    max, actual
+                )
+            }
+            PolicyError::GroupTooDeep { max, actual } => {
+                write!(
+                    f,
+                    "group nesting exceeds maximum depth of {}, got {}",
+                    max, actual
+                )
+            }
+            PolicyError::UnknownTenant => write!(f, "no policy for tenant"),
+            PolicyError::TooManyTenants { max, actual } => {
+                write!(f, "tenant count exceeds maximum of {}, got {}", max, actual)
+            }
+            PolicyError::DuplicateReasonCode { code } => {
+                write!(f, "reason code {} is registered more than once", code)
+            }
+            PolicyError::DuplicateReasonName { code } => {
+                write!(
+                    f,
+                    "reason code {} reuses a name that is already registered",
+                    code
+                )
+            }
+            PolicyError::TooManyConditionNodes { max, actual } => {
+                write!(
+                    f,
+                    "conditions exceed maximum total nodes of {}, got {}",
+                    max, actual
+                )
+            }
+            PolicyError::AttrNameTooLong { max, actual, .. } => {
+                write!(
+                    f,
+                    "attribute name exceeds maximum length of {}, got {}",
+                    max, actual
+                )
+            }
+            PolicyError::AttrValueTooLong { max, actual, .. } => {
+                write!(
+                    f,
+                    "attribute value exceeds maximum length of {}, got {}",
+                    max, actual
+                )
+            }
+            PolicyError::RuleIndexOutOfRange { index, len } => {
+                write!(f, "rule index {} is out of range for {} rules", index, len)
+            }
+            PolicyError::QueryTooLarge { max, actual } => {
+                write!(
+                    f,
+                    "query explores more than {} requests, needs {}",
+                    max, actual
+                )
+            }
+            PolicyError::InvalidCidr => write!(f, "invalid CIDR block"),
+            PolicyError::InvalidTime => write!(f, "invalid time of day or day of the week"),
+            PolicyError::InvalidDuration => write!(f, "invalid duration"),
+            PolicyError::UnorderedValue => write!(
+                f,
+                "ordering comparison needs an integer, duration, or timestamp"
+            ),
+            PolicyError::UnknownPredicate { rule } => {
+                write!(f, "rule {} uses an unregistered predicate", rule)
+            }
+            PolicyError::UndecidableQuery { rule } => {
+                write!(
+                    f,
+                    "rule {} uses a custom predicate, which queries cannot search",
+                    rule
+                )
+            }
+            PolicyError::InvariantViolated { invariant } => {
+                write!(f, "policy invariant violated: {}", invariant)
+            }
             PolicyError::InternalError => {
                 write!(f, "internal error: stack invariant violation")
             }
@@ -137,6 +496,7 @@ impl fmt::Display for PolicyError {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for PolicyError {}
 
 #[cfg(test)]
@@ -148,11 +508,16 @@ mod tests {
         let err = PolicyError::ConditionTooDeep {
             max: 10,
             actual: 15,
+            location: None,
         };
         assert_eq!(
             err.to_string(),
             "condition exceeds maximum depth of 10, got 15"
         );
+        assert_eq!(
+            err.in_rule(17, RulePart::Condition { node: 0 }).to_string(),
+            "condition exceeds maximum depth of 10, got 15 (rule 17, condition node 0)"
+        );
     }
 
     #[test]
@@ -199,6 +564,23 @@ mod tests {
     }
 
     #[test]
+    fn test_step_budget_exceeded_display() {
+        let err = PolicyError::StepBudgetExceeded { max: 8, actual: 9 };
+        assert_eq!(
+            err.to_string(),
+            "evaluation exceeds step budget of 8, got 9"
+        );
+    }
+
+    #[test]
+    fn test_kind() {
+        let err = PolicyError::StepBudgetExceeded { max: 1, actual: 2 };
+        assert_eq!(err.kind(), "step_budget_exceeded");
+        assert_eq!(PolicyError::InternalError.kind(), "internal_error");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
     fn test_error_trait() {
         let err: Box<dyn std::error::Error> =
             Box::new(PolicyError::AttributeNotFound { attr: "test" });
@@ -0,0 +1,326 @@
+//! Canonical hashing of policies and requests.
+//!
+//! Both are serialized to a canonical byte stream (every string is length
+//! prefixed, every variant tagged) and hashed with 64-bit FNV-1a. FNV is
+//! used instead of `std::hash` because the result must be stable across
+//! processes, platforms, and compiler versions: fingerprints are meant to be
+//! logged and compared, not just used as map keys.
+
+use core::net::IpAddr;
+
+use crate::compiled::Op;
+use crate::policy::{Policy, PolicyConfig};
+use crate::target::Matcher;
+use crate::types::{Effect, Request};
+use crate::value::Value;
+
+/// Destination for a canonical byte stream.
+pub(crate) trait Sink {
+    fn bytes(&mut self, bytes: &[u8]);
+
+    fn u8(&mut self, v: u8) {
+        self.bytes(&[v]);
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes());
+    }
+
+    fn str(&mut self, s: &str) {
+        self.u64(s.len() as u64);
+        self.bytes(s.as_bytes());
+    }
+}
+
+impl Sink for alloc::vec::Vec<u8> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.extend_from_slice(bytes);
+    }
+}
+
+/// 64-bit FNV-1a hasher.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv64(u64);
+
+impl Fnv64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub(crate) const fn new() -> Self {
+        Fnv64(Self::OFFSET)
+    }
+
+    pub(crate) const fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+impl Sink for Fnv64 {
+    fn bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 ^= b as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+/// Hash a byte slice with FNV-1a.
+#[cfg_attr(not(feature = "cache"), allow(dead_code))]
+pub(crate) fn fnv64(bytes: &[u8]) -> u64 {
+    let mut hasher = Fnv64::new();
+    hasher.bytes(bytes);
+    hasher.finish()
+}
+
+/// Fingerprint everything that can influence a decision: limits, rules in
+/// order, their compiled conditions and cache hints, group memberships, and
+/// the resource hierarchy.
+pub(crate) fn policy_fingerprint(policy: &Policy<'_>) -> u64 {
+    let (rules, conditions, config) = (policy.rules(), policy.compiled(), policy.config());
+    let mut h = Fnv64::new();
+
+    for limit in [
+        config.max_rules,
+        config.max_condition_depth,
+        config.max_context_attrs,
+        config.max_matcher_options,
+        config.max_string_len,
+        config.max_eval_steps,
+    ] {
+        h.u64(limit as u64);
+    }
+
+    h.u64(rules.len() as u64);
+    for (i, rule) in rules.iter().enumerate() {
+        h.u8(match rule.effect {
+            Effect::Allow => 0,
+            Effect::Deny => 1,
+        });
+        h.u64(rule.reason.value() as u64);
+        write_matcher(&mut h, &rule.target.principal);
+        write_matcher(&mut h, &rule.target.action);
+        write_matcher(&mut h, &rule.target.resource);
+
+        match conditions.program(i) {
+            None => h.u8(0),
+            Some(ops) => {
+                h.u8(1);
+                h.u64(ops.len() as u64);
+                for op in ops {
+                    write_op(&mut h, op);
+                }
+            }
+        }
+    }
+
+    // Optional parts are omitted when unset, so policies that do not use
+    // them keep their fingerprint
+    if !policy.groups().is_empty() {
+        h.u8(1);
+        policy.groups().write(&mut h);
+    }
+    if let Some(separator) = policy.resource_separator() {
+        h.u8(2);
+        h.u64(separator as u64);
+    }
+    let defaults = PolicyConfig::default();
+    let extra_limits = [
+        (config.max_condition_nodes, defaults.max_condition_nodes),
+        (config.max_attr_name_len, defaults.max_attr_name_len),
+        (config.max_attr_value_len, defaults.max_attr_value_len),
+    ];
+    if extra_limits.iter().any(|(limit, default)| limit != default) {
+        h.u8(3);
+        for (limit, _) in extra_limits {
+            h.u64(limit as u64);
+        }
+    }
+    if config.default_effect != defaults.default_effect {
+        h.u8(4);
+        h.u8(match config.default_effect {
+            Effect::Allow => 0,
+            Effect::Deny => 1,
+        });
+    }
+    // Cache hints are part of every decision, so they change it too
+    if rules.iter().any(|rule| rule.cache.is_some()) {
+        h.u8(5);
+        for rule in rules {
+            match rule.cache {
+                None => h.u8(0),
+                Some(hint) => {
+                    h.u8(1);
+                    match hint.ttl {
+                        None => h.u8(0),
+                        Some(ttl) => {
+                            h.u8(1);
+                            h.u64(ttl);
+                        }
+                    }
+                    h.u8(hint.per_context as u8);
+                }
+            }
+        }
+    }
+    #[cfg(feature = "custom-predicates")]
+    if policy.predicates().version() != 0 {
+        h.u8(6);
+        h.u64(policy.predicates().version());
+    }
+
+    h.finish()
+}
+
+/// Hash a request's canonical encoding without allocating.
+pub(crate) fn request_hash(request: &Request<'_>) -> u64 {
+    let mut hasher = Fnv64::new();
+    write_request(&mut hasher, request);
+    hasher.finish()
+}
+
+/// Write the canonical encoding of a request.
+pub(crate) fn write_request<S: Sink>(sink: &mut S, request: &Request<'_>) {
+    sink.str(request.principal);
+    sink.str(request.action);
+    sink.str(request.resource);
+    sink.u64(request.context.len() as u64);
+    for (key, value) in request.context {
+        sink.str(key);
+        write_value(sink, value);
+    }
+}
+
+fn write_matcher<S: Sink>(sink: &mut S, matcher: &Matcher<'_>) {
+    let options: &[&str] = match matcher {
+        Matcher::Any => return sink.u8(0),
+        Matcher::Exact(s) => {
+            sink.u8(1);
+            return sink.str(s);
+        }
+        Matcher::OneOf(options) => options,
+        Matcher::OneOfVec(options) => options,
+        Matcher::Group(group) => {
+            sink.u8(3);
+            return sink.str(group);
+        }
+    };
+    // OneOf and OneOfVec behave identically, so they hash identically
+    sink.u8(2);
+    sink.u64(options.len() as u64);
+    for option in options {
+        sink.str(option);
+    }
+}
+
+fn write_value<S: Sink>(sink: &mut S, value: &Value<'_>) {
+    match value {
+        Value::Bool(b) => {
+            sink.u8(0);
+            sink.u8(*b as u8);
+        }
+        Value::Int(i) => {
+            sink.u8(1);
+            sink.bytes(&i.to_le_bytes());
+        }
+        Value::String(s) => {
+            sink.u8(2);
+            sink.str(s);
+        }
+        Value::Ip(IpAddr::V4(ip)) => {
+            sink.u8(3);
+            sink.u8(4);
+            sink.bytes(&ip.octets());
+        }
+        Value::Ip(IpAddr::V6(ip)) => {
+            sink.u8(3);
+            sink.u8(6);
+            sink.bytes(&ip.octets());
+        }
+        Value::Timestamp(t) => {
+            sink.u8(4);
+            sink.bytes(&t.to_le_bytes());
+        }
+        Value::Duration(d) => {
+            sink.u8(5);
+            sink.bytes(&d.to_le_bytes());
+        }
+    }
+}
+
+fn write_op<S: Sink>(sink: &mut S, op: &Op<'_>) {
+    match op {
+        Op::True => sink.u8(0),
+        Op::False => sink.u8(1),
+        Op::Equals(attr, value) => {
+            sink.u8(2);
+            sink.str(attr);
+            write_value(sink, value);
+        }
+        Op::NotEquals(attr, value) => {
+            sink.u8(3);
+            sink.str(attr);
+            write_value(sink, value);
+        }
+        Op::Not => sink.u8(4),
+        Op::And => sink.u8(5),
+        Op::Or => sink.u8(6),
+        Op::IpInCidr(attr, cidr) => {
+            sink.u8(7);
+            sink.str(attr);
+            write_value(sink, &Value::Ip(cidr.addr()));
+            sink.u8(cidr.prefix());
+        }
+        Op::TimeBetween(attr, start, end) => {
+            sink.u8(8);
+            sink.str(attr);
+            sink.bytes(&start.to_le_bytes());
+            sink.bytes(&end.to_le_bytes());
+        }
+        Op::DayOfWeek(attr, days) => {
+            sink.u8(9);
+            sink.str(attr);
+            sink.u8(days.bits());
+        }
+        Op::GreaterThan(attr, value) => {
+            sink.u8(10);
+            sink.str(attr);
+            write_value(sink, value);
+        }
+        Op::LessThan(attr, value) => {
+            sink.u8(11);
+            sink.str(attr);
+            write_value(sink, value);
+        }
+        #[cfg(feature = "custom-predicates")]
+        Op::Custom(attr, name) => {
+            sink.u8(12);
+            sink.str(attr);
+            sink.str(name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_fnv_known_vectors() {
+        assert_eq!(fnv64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn test_request_encoding_is_unambiguous() {
+        // Length prefixes keep ("ab", "c") distinct from ("a", "bc")
+        let mut a = Vec::new();
+        write_request(&mut a, &Request::new("ab", "c", "r"));
+        let mut b = Vec::new();
+        write_request(&mut b, &Request::new("a", "bc", "r"));
+        assert_ne!(a, b);
+
+        // Streaming hash agrees with hashing the encoded bytes
+        assert_eq!(request_hash(&Request::new("ab", "c", "r")), fnv64(&a));
+    }
+}
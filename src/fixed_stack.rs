@@ -2,9 +2,19 @@
 //!
 //! This module provides a `FixedStack` that uses `MaybeUninit` to avoid
 //! default initialization costs. All operations are panic-free and return
-//! `Result` types.
+//! `Result` or `Option` types. The evaluator's stack is exported as-is, so
+//! downstream panic-free code can use the same audited primitive instead of
+//! copying the unsafe code.
+//!
+//! With the `heap-stack` feature, evaluators use `SpillStack` instead: the
+//! same fixed buffer, with a heap-backed overflow for conditions deeper than
+//! `ABSOLUTE_MAX_CONDITION_DEPTH`.
 
+#[cfg(feature = "heap-stack")]
+use alloc::vec::Vec;
+use core::fmt;
 use core::mem::MaybeUninit;
+use core::slice;
 
 use crate::error::PolicyError;
 
@@ -12,6 +22,23 @@ use crate::error::PolicyError;
 ///
 /// Uses `MaybeUninit` to avoid initialization overhead.
 /// All operations are panic-free and return explicit errors.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{FixedStack, PolicyError};
+///
+/// let mut stack: FixedStack<u32, 2> = FixedStack::new();
+/// stack.push(1)?;
+/// stack.push(2)?;
+/// assert_eq!(stack.push(3), Err(PolicyError::EvalStackOverflow { max: 2 }));
+/// assert_eq!(stack.peek(), Some(&2));
+/// assert_eq!(stack.as_slice(), [1, 2]);
+///
+/// let copy = FixedStack::<u32, 2>::try_from(stack.as_slice())?;
+/// assert_eq!(copy.iter().sum::<u32>(), 3);
+/// # Ok::<(), PolicyError>(())
+/// ```
 pub struct FixedStack<T, const N: usize> {
     buf: [MaybeUninit<T>; N],
     len: usize,
@@ -55,6 +82,39 @@ impl<T, const N: usize> FixedStack<T, N> {
         Some(unsafe { self.buf[self.len].assume_init_read() })
     }
 
+    /// The top item, without removing it.
+    ///
+    /// Returns `None` if the stack is empty.
+    #[inline]
+    pub fn peek(&self) -> Option<&T> {
+        self.as_slice().last()
+    }
+
+    /// The items, bottom of the stack first.
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        // SAFETY: elements 0..len are initialized, and MaybeUninit<T> has
+        // the same layout as T
+        unsafe { slice::from_raw_parts(self.buf.as_ptr().cast::<T>(), self.len) }
+    }
+
+    /// Iterate over the items, bottom of the stack first.
+    #[inline]
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    /// Remove and drop every item.
+    pub fn clear(&mut self) {
+        while self.pop().is_some() {}
+    }
+
+    /// Maximum number of items the stack holds (N).
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
     /// Returns the current number of items in the stack.
     #[inline]
     pub fn len(&self) -> usize {
@@ -68,6 +128,46 @@ impl<T, const N: usize> FixedStack<T, N> {
     }
 }
 
+impl<T, const N: usize> Default for FixedStack<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for FixedStack<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Copy a slice into a new stack, bottom first.
+///
+/// Returns `Err(PolicyError::EvalStackOverflow)` if the slice is longer
+/// than N.
+impl<T: Clone, const N: usize> TryFrom<&[T]> for FixedStack<T, N> {
+    type Error = PolicyError;
+
+    fn try_from(items: &[T]) -> Result<Self, PolicyError> {
+        if items.len() > N {
+            return Err(PolicyError::EvalStackOverflow { max: N });
+        }
+        let mut stack = FixedStack::new();
+        for item in items {
+            stack.push(item.clone())?;
+        }
+        Ok(stack)
+    }
+}
+
+impl<'s, T, const N: usize> IntoIterator for &'s FixedStack<T, N> {
+    type Item = &'s T;
+    type IntoIter = slice::Iter<'s, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
 impl<T, const N: usize> Drop for FixedStack<T, N> {
     fn drop(&mut self) {
         // Drop only the initialized elements
@@ -80,6 +180,100 @@ impl<T, const N: usize> Drop for FixedStack<T, N> {
     }
 }
 
+// ============================================================================
+// SpillStack: heap fallback beyond the fixed capacity
+// ============================================================================
+
+/// The stack type the evaluators use.
+#[cfg(not(feature = "heap-stack"))]
+pub(crate) type EvalStack<T, const N: usize> = FixedStack<T, N>;
+
+/// The stack type the evaluators use.
+#[cfg(feature = "heap-stack")]
+pub(crate) type EvalStack<T, const N: usize> = SpillStack<T, N>;
+
+/// A `FixedStack` that spills to the heap once its N slots are full.
+///
+/// The first N items never allocate, so conditions within
+/// `ABSOLUTE_MAX_CONDITION_DEPTH` keep the zero-allocation guarantee. Deeper
+/// conditions allocate; their size is still bounded by
+/// `PolicyConfig::max_condition_depth`, checked at construction.
+#[cfg(feature = "heap-stack")]
+pub(crate) struct SpillStack<T, const N: usize> {
+    fixed: FixedStack<T, N>,
+    spill: Vec<T>,
+}
+
+#[cfg(feature = "heap-stack")]
+impl<T, const N: usize> SpillStack<T, N> {
+    /// Create a new empty stack. Does not allocate.
+    #[inline]
+    pub fn new() -> Self {
+        SpillStack {
+            fixed: FixedStack::new(),
+            spill: Vec::new(),
+        }
+    }
+
+    /// Push an item onto the stack, spilling to the heap when full.
+    #[inline]
+    pub fn push(&mut self, value: T) -> Result<(), PolicyError> {
+        if self.fixed.len() < N {
+            self.fixed.push(value)
+        } else {
+            self.spill.push(value);
+            Ok(())
+        }
+    }
+
+    /// Pop an item from the stack.
+    ///
+    /// Returns `None` if the stack is empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        self.spill.pop().or_else(|| self.fixed.pop())
+    }
+
+    /// Returns the current number of items in the stack.
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.fixed.len() + self.spill.len()
+    }
+
+    /// Returns true if the stack is empty.
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(feature = "heap-stack")]
+impl<T, const N: usize> Default for SpillStack<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, feature = "heap-stack"))]
+mod spill_tests {
+    use super::*;
+
+    #[test]
+    fn test_spill_past_capacity() {
+        let mut stack: SpillStack<i32, 2> = SpillStack::new();
+        for i in 0..5 {
+            stack.push(i).unwrap();
+        }
+        assert_eq!(stack.len(), 5);
+        assert_eq!(stack.spill.len(), 3);
+        for i in (0..5).rev() {
+            assert_eq!(stack.pop(), Some(i));
+        }
+        assert_eq!(stack.pop(), None);
+        assert!(stack.is_empty());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,6 +326,36 @@ mod tests {
         // Both items should have been dropped
         assert_eq!(counter.get(), 2);
     }
+
+    #[test]
+    fn test_peek_slice_clear() {
+        let mut stack: FixedStack<i32, 4> = FixedStack::default();
+        assert_eq!(stack.peek(), None);
+        assert!(stack.as_slice().is_empty());
+        assert_eq!(stack.capacity(), 4);
+
+        stack.push(1).unwrap();
+        stack.push(2).unwrap();
+        assert_eq!(stack.peek(), Some(&2));
+        assert_eq!(stack.as_slice(), [1, 2]);
+        assert_eq!(stack.iter().copied().collect::<Vec<_>>(), [1, 2]);
+        assert_eq!(format!("{:?}", stack), "[1, 2]");
+
+        stack.clear();
+        assert!(stack.is_empty());
+        stack.push(3).unwrap();
+        assert_eq!(stack.as_slice(), [3]);
+    }
+
+    #[test]
+    fn test_try_from_slice() {
+        let stack = FixedStack::<i32, 3>::try_from(&[1, 2, 3][..]).unwrap();
+        assert_eq!(stack.len(), 3);
+        assert_eq!(stack.peek(), Some(&3));
+
+        let err = FixedStack::<i32, 2>::try_from(&[1, 2, 3][..]).unwrap_err();
+        assert_eq!(err, PolicyError::EvalStackOverflow { max: 2 });
+    }
 }
 
 // ============================================================================
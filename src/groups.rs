@@ -0,0 +1,254 @@
+//! Principal groups and group nesting.
+//!
+//! A `GroupMap` records which groups each principal belongs to and which
+//! groups are nested in other groups. Nesting is expanded once, when the map
+//! is built, so `Matcher::Group` checks membership with a single lookup and
+//! evaluation stays bounded. Chains longer than the configured depth are
+//! rejected at build time; cycles are expanded once and then stop.
+//!
+//! Keeping groups in the engine, instead of flattening them into context
+//! booleans outside it, keeps the real policy visible in the rules.
+
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec::Vec;
+
+use crate::error::PolicyError;
+use crate::fingerprint::Sink;
+
+/// Default maximum group nesting depth.
+pub const DEFAULT_MAX_GROUP_DEPTH: usize = 8;
+
+/// Expanded principal-to-group memberships.
+///
+/// # Example
+///
+/// ```
+/// use gate0::GroupMap;
+///
+/// let groups = GroupMap::builder()
+///     .member("alice", "sre")
+///     .subgroup("sre", "engineering")
+///     .build()
+///     .unwrap();
+///
+/// assert!(groups.is_member("alice", "sre"));
+/// assert!(groups.is_member("alice", "engineering"));
+/// assert!(!groups.is_member("bob", "sre"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupMap<'a> {
+    /// Every group of each principal, direct or nested, sorted.
+    memberships: BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+impl<'a> GroupMap<'a> {
+    /// Create a new group map builder.
+    pub fn builder() -> GroupMapBuilder<'a> {
+        GroupMapBuilder::new()
+    }
+
+    /// Returns `true` if `principal` belongs to `group`, directly or through
+    /// nested groups.
+    pub fn is_member(&self, principal: &str, group: &str) -> bool {
+        self.groups_of(principal).binary_search(&group).is_ok()
+    }
+
+    /// Every group `principal` belongs to, sorted.
+    pub fn groups_of(&self, principal: &str) -> &[&'a str] {
+        self.memberships
+            .get(principal)
+            .map_or(&[][..], Vec::as_slice)
+    }
+
+    /// Returns `true` if no principal belongs to any group.
+    pub fn is_empty(&self) -> bool {
+        self.memberships.is_empty()
+    }
+
+    /// Every principal with at least one group, sorted.
+    pub(crate) fn principals(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.memberships.keys().copied()
+    }
+
+    /// Write the canonical encoding of these memberships.
+    pub(crate) fn write<S: Sink>(&self, sink: &mut S) {
+        sink.u64(self.memberships.len() as u64);
+        for (principal, groups) in &self.memberships {
+            sink.str(principal);
+            sink.u64(groups.len() as u64);
+            for group in groups {
+                sink.str(group);
+            }
+        }
+    }
+}
+
+/// Builder for constructing group maps.
+#[derive(Debug, Clone)]
+pub struct GroupMapBuilder<'a> {
+    members: Vec<(&'a str, &'a str)>,
+    subgroups: Vec<(&'a str, &'a str)>,
+    max_depth: usize,
+}
+
+impl<'a> GroupMapBuilder<'a> {
+    /// Create a new group map builder.
+    pub fn new() -> Self {
+        GroupMapBuilder {
+            members: Vec::new(),
+            subgroups: Vec::new(),
+            max_depth: DEFAULT_MAX_GROUP_DEPTH,
+        }
+    }
+
+    /// Add `principal` to `group`.
+    pub fn member(mut self, principal: &'a str, group: &'a str) -> Self {
+        self.members.push((principal, group));
+        self
+    }
+
+    /// Nest `group` in `parent`: members of `group` also belong to `parent`.
+    pub fn subgroup(mut self, group: &'a str, parent: &'a str) -> Self {
+        self.subgroups.push((group, parent));
+        self
+    }
+
+    /// Set the maximum nesting depth (default: `DEFAULT_MAX_GROUP_DEPTH`).
+    ///
+    /// A direct membership has depth 1; each nesting step adds 1.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Expand nesting and build the map.
+    ///
+    /// Returns `GroupTooDeep` if a principal reaches a group only through
+    /// more than `max_depth` levels.
+    pub fn build(self) -> Result<GroupMap<'a>, PolicyError> {
+        let mut parents: BTreeMap<&'a str, Vec<&'a str>> = BTreeMap::new();
+        for (group, parent) in &self.subgroups {
+            parents.entry(*group).or_default().push(*parent);
+        }
+
+        let mut direct: BTreeMap<&'a str, Vec<&'a str>> = BTreeMap::new();
+        for (principal, group) in &self.members {
+            direct.entry(*principal).or_default().push(*group);
+        }
+
+        let mut memberships = BTreeMap::new();
+        for (principal, groups) in direct {
+            // Breadth-first, so each group is reached at its shortest depth
+            let mut seen: BTreeSet<&'a str> = BTreeSet::new();
+            let mut frontier = groups;
+            let mut depth = 0;
+            while !frontier.is_empty() {
+                depth += 1;
+                let mut next = Vec::new();
+                for group in frontier {
+                    if !seen.insert(group) {
+                        continue;
+                    }
+                    if depth > self.max_depth {
+                        return Err(PolicyError::GroupTooDeep {
+                            max: self.max_depth,
+                            actual: depth,
+                        });
+                    }
+                    next.extend(parents.get(group).into_iter().flatten().copied());
+                }
+                frontier = next;
+            }
+            memberships.insert(principal, seen.into_iter().collect());
+        }
+
+        Ok(GroupMap { memberships })
+    }
+}
+
+impl<'a> Default for GroupMapBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_membership() {
+        let groups = GroupMap::builder()
+            .member("alice", "sre")
+            .member("bob", "interns")
+            .subgroup("sre", "engineering")
+            .subgroup("engineering", "staff")
+            .subgroup("interns", "staff")
+            // Cycles expand once and stop
+            .subgroup("staff", "engineering")
+            .build()
+            .unwrap();
+
+        assert_eq!(groups.groups_of("alice"), &["engineering", "sre", "staff"]);
+        assert!(groups.is_member("bob", "engineering"));
+        assert!(!groups.is_member("bob", "sre"));
+        assert!(groups.groups_of("carol").is_empty());
+    }
+
+    #[test]
+    fn test_group_matcher_in_policy() {
+        use crate::{Matcher, Policy, ReasonCode, Request, Rule, Target};
+
+        let groups = GroupMap::builder()
+            .member("alice", "sre")
+            .subgroup("sre", "admins")
+            .build()
+            .unwrap();
+        let rules = || {
+            alloc::vec![Rule::allow(
+                Target {
+                    principal: Matcher::Group("admins"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            )]
+        };
+
+        for indexed in [false, true] {
+            let policy = Policy::builder()
+                .rule(rules()[0].clone())
+                .groups(groups.clone())
+                .target_index(indexed)
+                .build()
+                .unwrap();
+            let alice = policy.evaluate(&Request::new("alice", "read", "doc"));
+            let bob = policy.evaluate(&Request::new("bob", "read", "doc"));
+            assert!(alice.unwrap().is_allow());
+            assert!(bob.unwrap().is_deny());
+        }
+
+        // Without groups the matcher fails closed, and the fingerprint differs
+        let bare = Policy::new(rules()).unwrap();
+        assert!(bare
+            .evaluate(&Request::new("alice", "read", "doc"))
+            .unwrap()
+            .is_deny());
+        let grouped = Policy::new(rules()).unwrap().with_groups(groups);
+        assert_ne!(bare.fingerprint(), grouped.fingerprint());
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let builder = GroupMap::builder()
+            .member("alice", "g1")
+            .subgroup("g1", "g2")
+            .subgroup("g2", "g3");
+
+        assert!(builder.clone().max_depth(3).build().is_ok());
+        assert_eq!(
+            builder.max_depth(2).build().unwrap_err(),
+            PolicyError::GroupTooDeep { max: 2, actual: 3 }
+        );
+    }
+}
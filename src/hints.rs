@@ -0,0 +1,289 @@
+//! Denial hints: single changes that would flip a deny.
+//!
+//! When a request is denied, support teams want to know what the caller is
+//! missing. `Policy::denial_hints` tries every single change that could
+//! matter and reports the ones that turn the deny into an allow: setting or
+//! removing one context attribute, or replacing the principal, action, or
+//! resource.
+//!
+//! Candidates come from the policy itself (constants in target matchers,
+//! grouped principals, and for each attribute one value per class its
+//! conditions tell apart, as in `Policy::query`), so the search is bounded
+//! by the size of the policy and every hint has been confirmed by a real
+//! evaluation. Custom predicates do not suggest values: an attribute only
+//! they test is only ever removed.
+
+use alloc::vec::Vec;
+
+use crate::error::PolicyError;
+use crate::policy::Policy;
+use crate::query::{matcher_constants, representatives};
+use crate::types::{Decision, Request};
+use crate::value::Value;
+
+/// One change to a request.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<'a> {
+    /// Set attribute `attr` to `value`, adding it if missing.
+    SetAttr {
+        /// The attribute.
+        attr: &'a str,
+        /// Its new value.
+        value: Value<'a>,
+    },
+    /// Remove attribute `attr`.
+    RemoveAttr {
+        /// The attribute.
+        attr: &'a str,
+    },
+    /// Use a different principal.
+    Principal(&'a str),
+    /// Use a different action.
+    Action(&'a str),
+    /// Use a different resource.
+    Resource(&'a str),
+}
+
+/// A change that turns a denied request into an allowed one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenialHint<'a> {
+    /// The change.
+    pub change: Change<'a>,
+    /// The decision the changed request gets.
+    pub decision: Decision,
+}
+
+impl<'a> Policy<'a> {
+    /// Find single changes that would have allowed a denied request.
+    ///
+    /// Attribute changes come first, in the order the policy compares the
+    /// attributes, followed by principal, action, and resource changes in
+    /// sorted order. Returns no hints for an allowed request, and the
+    /// request's own error if evaluating it fails.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gate0::{Change, Condition, Effect, Policy, ReasonCode, Request, Rule, Target, Value};
+    ///
+    /// let policy = Policy::builder()
+    ///     .rule(Rule::new(
+    ///         Effect::Allow,
+    ///         Target::any(),
+    ///         Some(Condition::Equals { attr: "mfa", value: Value::Bool(true) }),
+    ///         ReasonCode(1),
+    ///     ))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let hints = policy.denial_hints(&Request::new("alice", "read", "doc")).unwrap();
+    /// assert_eq!(hints[0].change, Change::SetAttr { attr: "mfa", value: Value::Bool(true) });
+    /// ```
+    pub fn denial_hints<'r>(
+        &self,
+        request: &Request<'r>,
+    ) -> Result<Vec<DenialHint<'r>>, PolicyError>
+    where
+        'a: 'r,
+    {
+        let mut hints = Vec::new();
+        if self.evaluate(request)?.is_allow() {
+            return Ok(hints);
+        }
+        let mut try_change = |change: Change<'r>, changed: &Request<'_>| {
+            if let Ok(decision) = self.evaluate(changed) {
+                if decision.is_allow() {
+                    hints.push(DenialHint { change, decision });
+                }
+            }
+        };
+
+        for (attr, values) in representatives(self) {
+            let others = || request.context.iter().filter(|(k, _)| *k != attr).cloned();
+            let current = request.context.iter().find(|(k, _)| *k == attr);
+
+            for value in values {
+                if current.is_some_and(|(_, v)| *v == value) {
+                    continue;
+                }
+                let context: Vec<_> = others().chain([(attr, value.clone())]).collect();
+                let changed = Request {
+                    context: &context,
+                    ..*request
+                };
+                try_change(Change::SetAttr { attr, value }, &changed);
+            }
+            if current.is_some() {
+                let context: Vec<_> = others().collect();
+                let changed = Request {
+                    context: &context,
+                    ..*request
+                };
+                try_change(Change::RemoveAttr { attr }, &changed);
+            }
+        }
+
+        let rules = self.rules();
+        let groups = self.groups();
+        for principal in matcher_constants(rules.iter().map(|r| &r.target.principal), groups) {
+            if principal != request.principal {
+                let changed = Request {
+                    principal,
+                    ..*request
+                };
+                try_change(Change::Principal(principal), &changed);
+            }
+        }
+        for action in matcher_constants(rules.iter().map(|r| &r.target.action), groups) {
+            if action != request.action {
+                let changed = Request { action, ..*request };
+                try_change(Change::Action(action), &changed);
+            }
+        }
+        for resource in matcher_constants(rules.iter().map(|r| &r.target.resource), groups) {
+            if resource != request.resource {
+                let changed = Request {
+                    resource,
+                    ..*request
+                };
+                try_change(Change::Resource(resource), &changed);
+            }
+        }
+
+        Ok(hints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, GroupMap, Matcher, ReasonCode, Rule, Target};
+    use alloc::boxed::Box;
+
+    fn policy() -> Policy<'static> {
+        Policy::builder()
+            .groups(
+                GroupMap::builder()
+                    .member("bob", "editors")
+                    .build()
+                    .unwrap(),
+            )
+            .rule(Rule::new(
+                Effect::Deny,
+                Target::any(),
+                Some(Condition::Equals {
+                    attr: "suspended",
+                    value: Value::Bool(true),
+                }),
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target {
+                    principal: Matcher::Any,
+                    action: Matcher::Exact("read"),
+                    resource: Matcher::Any,
+                },
+                Some(Condition::Or(
+                    Box::new(Condition::Equals {
+                        attr: "role",
+                        value: Value::String("viewer"),
+                    }),
+                    Box::new(Condition::Equals {
+                        attr: "role",
+                        value: Value::String("admin"),
+                    }),
+                )),
+                ReasonCode(2),
+            ))
+            .rule(Rule::allow(
+                Target {
+                    principal: Matcher::Group("editors"),
+                    action: Matcher::Any,
+                    resource: Matcher::Exact("drafts"),
+                },
+                ReasonCode(3),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_hints_for_denied_request() {
+        let policy = policy();
+        let ctx: &[(&str, Value)] = &[
+            ("role", Value::String("guest")),
+            ("suspended", Value::Bool(false)),
+        ];
+        let request = Request::with_context("alice", "read", "doc", ctx);
+        let hints = policy.denial_hints(&request).unwrap();
+
+        let changes: Vec<_> = hints.iter().map(|h| h.change.clone()).collect();
+        assert_eq!(
+            changes,
+            [
+                Change::SetAttr {
+                    attr: "role",
+                    value: Value::String("viewer"),
+                },
+                Change::SetAttr {
+                    attr: "role",
+                    value: Value::String("admin"),
+                },
+            ]
+        );
+        assert_eq!(hints[0].decision, Decision::allow(ReasonCode(2)));
+
+        // Editing drafts needs group membership, so bob is the hint
+        let request = Request::new("alice", "write", "drafts");
+        let hints = policy.denial_hints(&request).unwrap();
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].change, Change::Principal("bob"));
+    }
+
+    #[test]
+    fn test_no_hints_when_allowed_or_blocked() {
+        let policy = policy();
+        let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+        let allowed = Request::with_context("alice", "read", "doc", ctx);
+        assert!(policy.denial_hints(&allowed).unwrap().is_empty());
+
+        // Removing the suspension flag is a single change
+        let ctx: &[(&str, Value)] = &[
+            ("role", Value::String("admin")),
+            ("suspended", Value::Bool(true)),
+        ];
+        let suspended = Request::with_context("alice", "read", "doc", ctx);
+        let hints = policy.denial_hints(&suspended).unwrap();
+        assert_eq!(hints[0].change, Change::RemoveAttr { attr: "suspended" });
+        assert_eq!(hints.len(), 1);
+    }
+
+    #[test]
+    fn test_hints_for_ordered_conditions() {
+        let policy = Policy::builder()
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::GreaterThan {
+                    attr: "level",
+                    value: Value::Int(5),
+                }),
+                ReasonCode(1),
+            ))
+            .build()
+            .unwrap();
+
+        let ctx: &[(&str, Value)] = &[("level", Value::Int(2))];
+        let request = Request::with_context("alice", "read", "doc", ctx);
+        let hints = policy.denial_hints(&request).unwrap();
+        assert_eq!(
+            hints[0].change,
+            Change::SetAttr {
+                attr: "level",
+                value: Value::Int(6),
+            }
+        );
+        assert_eq!(hints.len(), 1);
+    }
+}
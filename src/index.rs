@@ -0,0 +1,187 @@
+//! Optional build-time target index.
+//!
+//! Large policies spend most of their time rejecting rules whose target
+//! cannot match. The index buckets rules by their exact principal (or, for
+//! rules with a wildcard principal, their exact action) so evaluation only
+//! visits rules that might match.
+//!
+//! # Ordering Guarantee
+//!
+//! Every rule lands in exactly one bucket kind: principal, action, or
+//! wildcard. Each bucket stores rule indices in ascending order, and
+//! `Candidates` merges the three relevant lists by index. Rules are
+//! therefore visited in declared order, which keeps first-match and
+//! deny-overrides semantics identical to the linear evaluator.
+//!
+//! Candidate rules still go through the full target check; the index only
+//! skips rules that are guaranteed not to match.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::policy::Rule;
+use crate::target::Matcher;
+
+/// Rule indices bucketed by exact principal and action values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct TargetIndex<'a> {
+    by_principal: BTreeMap<&'a str, Vec<u32>>,
+    by_action: BTreeMap<&'a str, Vec<u32>>,
+    wildcard: Vec<u32>,
+}
+
+impl<'a> TargetIndex<'a> {
+    /// Build the index for the given rules.
+    pub(crate) fn build(rules: &[Rule<'a>]) -> Self {
+        let mut index = TargetIndex::default();
+
+        for (i, rule) in rules.iter().enumerate() {
+            let i = i as u32;
+            if let Some(keys) = exact_keys(&rule.target.principal) {
+                insert_all(&mut index.by_principal, keys, i);
+            } else if let Some(keys) = exact_keys(&rule.target.action) {
+                insert_all(&mut index.by_action, keys, i);
+            } else {
+                index.wildcard.push(i);
+            }
+        }
+
+        index
+    }
+
+    /// Rules that may match a request with the given principal and action.
+    pub(crate) fn lookup(&self, principal: &str, action: &str) -> [&[u32]; 3] {
+        [
+            self.by_principal
+                .get(principal)
+                .map_or(&[][..], Vec::as_slice),
+            self.by_action.get(action).map_or(&[][..], Vec::as_slice),
+            &self.wildcard,
+        ]
+    }
+}
+
+/// The exact values a matcher accepts, or `None` for `Matcher::Any` and
+/// `Matcher::Group` (which can match values not named in the rule).
+fn exact_keys<'m, 'a>(matcher: &'m Matcher<'a>) -> Option<&'m [&'a str]> {
+    match matcher {
+        Matcher::Any | Matcher::Group(_) => None,
+        Matcher::Exact(s) => Some(core::slice::from_ref(s)),
+        Matcher::OneOf(options) => Some(options),
+        Matcher::OneOfVec(options) => Some(options),
+    }
+}
+
+/// Add rule `i` to the bucket of every key, once per bucket.
+fn insert_all<'a>(map: &mut BTreeMap<&'a str, Vec<u32>>, keys: &[&'a str], i: u32) {
+    for key in keys {
+        let bucket = map.entry(*key).or_default();
+        // Duplicate options within one rule would otherwise list it twice
+        if bucket.last() != Some(&i) {
+            bucket.push(i);
+        }
+    }
+}
+
+/// Iterator over the rules to check for a request, in declared order.
+///
+/// Yields each candidate together with its index in the policy.
+///
+/// Allocation-free: either walks all rules, or merges the three sorted
+/// bucket lists from `TargetIndex::lookup`.
+pub(crate) enum Candidates<'p, 'a> {
+    /// No index: every rule is a candidate.
+    All(core::iter::Enumerate<core::slice::Iter<'p, Rule<'a>>>),
+    /// Indexed: merge of principal, action, and wildcard buckets.
+    Indexed {
+        rules: &'p [Rule<'a>],
+        lists: [&'p [u32]; 3],
+    },
+}
+
+impl<'p, 'a> Iterator for Candidates<'p, 'a> {
+    type Item = (usize, &'p Rule<'a>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Candidates::All(iter) => iter.next(),
+            Candidates::Indexed { rules, lists } => {
+                // Pick the list whose head is the smallest rule index
+                let mut best: Option<usize> = None;
+                for (slot, list) in lists.iter().enumerate() {
+                    if let Some(&head) = list.first() {
+                        match best {
+                            Some(b) if lists[b][0] <= head => {}
+                            _ => best = Some(slot),
+                        }
+                    }
+                }
+
+                let rules: &'p [Rule<'a>] = rules;
+                let slot = best?;
+                let (&head, rest) = lists[slot].split_first()?;
+                lists[slot] = rest;
+                let head = head as usize;
+                rules.get(head).map(|rule| (head, rule))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::target::Target;
+    use crate::types::ReasonCode;
+
+    fn rule(principal: Matcher<'static>, action: Matcher<'static>, code: u32) -> Rule<'static> {
+        Rule::allow(
+            Target {
+                principal,
+                action,
+                resource: Matcher::Any,
+            },
+            ReasonCode(code),
+        )
+    }
+
+    #[test]
+    fn test_buckets() {
+        let rules = vec![
+            rule(Matcher::Exact("alice"), Matcher::Any, 0),
+            rule(Matcher::Any, Matcher::Exact("read"), 1),
+            rule(Matcher::Any, Matcher::Any, 2),
+            rule(Matcher::OneOf(&["alice", "bob", "alice"]), Matcher::Any, 3),
+        ];
+        let index = TargetIndex::build(&rules);
+
+        assert_eq!(index.lookup("alice", "read"), [&[0, 3][..], &[1], &[2]]);
+        assert_eq!(index.lookup("bob", "write"), [&[3][..], &[], &[2]]);
+        assert_eq!(index.lookup("carol", "write"), [&[][..], &[], &[2]]);
+    }
+
+    #[test]
+    fn test_candidates_in_declared_order() {
+        let rules = vec![
+            rule(Matcher::Any, Matcher::Any, 0),
+            rule(Matcher::Exact("alice"), Matcher::Any, 1),
+            rule(Matcher::Any, Matcher::Exact("read"), 2),
+            rule(Matcher::Exact("alice"), Matcher::Any, 3),
+            rule(Matcher::Any, Matcher::Any, 4),
+            rule(Matcher::Exact("bob"), Matcher::Any, 5),
+        ];
+        let index = TargetIndex::build(&rules);
+
+        let visited: Vec<u32> = Candidates::Indexed {
+            rules: &rules,
+            lists: index.lookup("alice", "read"),
+        }
+        .map(|(i, r)| {
+            assert_eq!(rules[i].reason, r.reason);
+            r.reason.value()
+        })
+        .collect();
+
+        assert_eq!(visited, vec![0, 1, 2, 3, 4]);
+    }
+}
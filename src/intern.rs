@@ -0,0 +1,401 @@
+//! String interning for evaluation (opt-in, `PolicyBuilder::intern`).
+//!
+//! Without interning, each leaf condition finds its attribute by comparing
+//! names byte by byte against the request's context, and each matcher
+//! compares strings. With wide contexts, those comparisons dominate the
+//! cost of a request.
+//!
+//! An interning policy numbers every attribute name and matcher string its
+//! rules use when it is built. `Policy::intern` looks a request's context
+//! keys and principal, action, and resource up once, and
+//! `Policy::evaluate_interned` then finds attributes by index and matches
+//! targets by comparing numbers. Decisions are identical to `evaluate`.
+//!
+//! Interning a request allocates one slot per interned string, so it pays
+//! off when the same request is evaluated more than once or the context is
+//! wide. Evaluating an interned request does not allocate.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::compiled::AttrSource;
+use crate::condition::lookup_attr;
+use crate::error::PolicyError;
+use crate::groups::GroupMap;
+use crate::policy::Rule;
+use crate::target::Matcher;
+use crate::types::Request;
+use crate::value::Value;
+
+/// The number an `Interner` gives a string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Position of the string in the interner.
+    #[inline]
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// The attribute names and matcher strings of a policy's rules, numbered.
+///
+/// Built by `PolicyBuilder::intern`; see `Policy::interner`. Group names
+/// are not interned, since groups are resolved through the `GroupMap`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Interner<'a> {
+    /// Sorted, without duplicates; a symbol is an index.
+    strings: Vec<&'a str>,
+}
+
+impl<'a> Interner<'a> {
+    /// Intern every attribute name and matcher string in `rules`.
+    pub(crate) fn build(rules: &[Rule<'a>]) -> Self {
+        let mut strings = Vec::new();
+        for rule in rules {
+            for matcher in [
+                &rule.target.principal,
+                &rule.target.action,
+                &rule.target.resource,
+            ] {
+                strings.extend(options(matcher));
+            }
+            if let Some(condition) = &rule.condition {
+                strings.extend(condition.referenced_attrs());
+            }
+        }
+        strings.sort_unstable();
+        strings.dedup();
+        Interner { strings }
+    }
+
+    /// The symbol of `s`, if it was interned.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.strings
+            .binary_search(&s)
+            .ok()
+            .map(|i| Symbol(i as u32))
+    }
+
+    /// The string `symbol` stands for.
+    pub fn resolve(&self, symbol: Symbol) -> Option<&'a str> {
+        self.strings.get(symbol.index()).copied()
+    }
+
+    /// Number of interned strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns `true` if nothing was interned.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+/// The strings an `Exact` or list matcher compares against.
+fn options<'m, 'a>(matcher: &'m Matcher<'a>) -> &'m [&'a str] {
+    match matcher {
+        Matcher::Exact(value) => core::slice::from_ref(value),
+        Matcher::OneOf(options) => options,
+        Matcher::OneOfVec(options) => options,
+        Matcher::Any | Matcher::Group(_) => &[],
+    }
+}
+
+/// A matcher over symbols.
+#[derive(Debug, Clone, PartialEq)]
+enum SymbolMatcher<'a> {
+    Any,
+    Exact(Symbol),
+    /// `options[start..end]` of the enclosing `InternedTargets`.
+    OneOf(u32, u32),
+    Group(&'a str),
+}
+
+/// Every rule's target as symbol matchers, built with the interner.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct InternedTargets<'a> {
+    interner: Interner<'a>,
+    /// Principal, action, and resource matcher of each rule.
+    targets: Vec<[SymbolMatcher<'a>; 3]>,
+    /// The options of every `OneOf` matcher, back to back.
+    options: Vec<Symbol>,
+}
+
+impl<'a> InternedTargets<'a> {
+    /// Intern `rules` and translate their targets.
+    pub(crate) fn build(rules: &[Rule<'a>]) -> Self {
+        let interner = Interner::build(rules);
+        let mut options = Vec::new();
+        let mut translate = |matcher: &Matcher<'a>| match matcher {
+            Matcher::Any => SymbolMatcher::Any,
+            Matcher::Group(group) => SymbolMatcher::Group(group),
+            Matcher::Exact(value) => SymbolMatcher::Exact(symbol(&interner, value)),
+            Matcher::OneOf(_) | Matcher::OneOfVec(_) => {
+                let start = options.len() as u32;
+                options.extend(options_of(&interner, matcher));
+                SymbolMatcher::OneOf(start, options.len() as u32)
+            }
+        };
+        let targets = rules
+            .iter()
+            .map(|rule| {
+                [
+                    translate(&rule.target.principal),
+                    translate(&rule.target.action),
+                    translate(&rule.target.resource),
+                ]
+            })
+            .collect();
+        InternedTargets {
+            interner,
+            targets,
+            options,
+        }
+    }
+
+    /// The interner the targets were translated with.
+    pub(crate) fn interner(&self) -> &Interner<'a> {
+        &self.interner
+    }
+
+    /// Check field `field` (0 principal, 1 action, 2 resource) of rule
+    /// `rule`'s target against `value`, interned as `symbol`.
+    #[inline]
+    pub(crate) fn matches(
+        &self,
+        rule: usize,
+        field: usize,
+        symbol: Option<Symbol>,
+        value: &str,
+        groups: &GroupMap<'_>,
+    ) -> bool {
+        let Some(matchers) = self.targets.get(rule) else {
+            return false;
+        };
+        match &matchers[field] {
+            SymbolMatcher::Any => true,
+            SymbolMatcher::Exact(expected) => symbol == Some(*expected),
+            SymbolMatcher::OneOf(start, end) => symbol.is_some_and(|symbol| {
+                self.options[*start as usize..*end as usize].contains(&symbol)
+            }),
+            SymbolMatcher::Group(group) => groups.is_member(value, group),
+        }
+    }
+}
+
+/// The symbol of a string `Interner::build` interned.
+fn symbol(interner: &Interner<'_>, s: &str) -> Symbol {
+    // Every matcher string was interned; `Symbol(u32::MAX)` never matches
+    interner.get(s).unwrap_or(Symbol(u32::MAX))
+}
+
+/// The symbols of a list matcher's options.
+fn options_of<'i>(
+    interner: &'i Interner<'_>,
+    matcher: &'i Matcher<'_>,
+) -> impl Iterator<Item = Symbol> + 'i {
+    options(matcher).iter().map(|s| symbol(interner, s))
+}
+
+/// A request interned for one policy (see `Policy::intern`).
+#[derive(Debug, Clone)]
+pub struct InternedRequest<'r> {
+    pub(crate) request: Request<'r>,
+    /// Fingerprint of the policy whose interner was used, or `None` if it
+    /// does not intern.
+    pub(crate) fingerprint: Option<u64>,
+    /// Principal, action, and resource symbols.
+    pub(crate) fields: [Option<Symbol>; 3],
+    /// The first context value of each interned attribute, by symbol.
+    pub(crate) values: Vec<Option<&'r Value<'r>>>,
+}
+
+impl<'r> InternedRequest<'r> {
+    /// Look every string of `request` up in `interner`.
+    pub(crate) fn new(
+        request: &Request<'r>,
+        interner: Option<&Interner<'_>>,
+        fingerprint: u64,
+    ) -> Self {
+        let Some(interner) = interner else {
+            return InternedRequest {
+                request: request.clone(),
+                fingerprint: None,
+                fields: [None; 3],
+                values: Vec::new(),
+            };
+        };
+        let mut values = vec![None; interner.len()];
+        for (key, value) in request.context {
+            if let Some(symbol) = interner.get(key) {
+                // The first occurrence wins, as in `lookup_attr`
+                values[symbol.index()].get_or_insert(value);
+            }
+        }
+        InternedRequest {
+            request: request.clone(),
+            fingerprint: Some(fingerprint),
+            fields: [
+                interner.get(request.principal),
+                interner.get(request.action),
+                interner.get(request.resource),
+            ],
+            values,
+        }
+    }
+
+    /// The request this was interned from.
+    pub fn request(&self) -> &Request<'r> {
+        &self.request
+    }
+
+    /// The context value of the attribute `symbol` (or `attr` if the
+    /// attribute was not interned).
+    #[inline]
+    pub(crate) fn lookup(&self, attr: &str, symbol: Option<Symbol>) -> Option<&'r Value<'r>> {
+        match symbol {
+            Some(symbol) => self.values.get(symbol.index()).copied().flatten(),
+            None => lookup_attr(self.request.context, attr),
+        }
+    }
+}
+
+/// Attributes by symbol and targets by field symbols. Only used for requests
+/// interned for the evaluating policy.
+impl<'k> AttrSource<'k> for &InternedRequest<'_> {
+    #[inline]
+    fn test<F: FnOnce(&Value<'_>) -> bool>(
+        &mut self,
+        attr: &'k str,
+        symbol: Option<Symbol>,
+        test: F,
+    ) -> Result<Option<bool>, PolicyError> {
+        Ok(self.lookup(attr, symbol).map(test))
+    }
+
+    fn fields(&self) -> Option<[Option<Symbol>; 3]> {
+        Some(self.fields)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, Policy, ReasonCode, Target};
+    use alloc::boxed::Box;
+
+    fn rules() -> Vec<Rule<'static>> {
+        vec![
+            Rule::new(
+                Effect::Allow,
+                Target {
+                    principal: Matcher::OneOf(&["alice", "bob"]),
+                    action: Matcher::Exact("read"),
+                    resource: Matcher::Exact("project"),
+                },
+                Some(Condition::And(
+                    Box::new(Condition::Equals {
+                        attr: "role",
+                        value: Value::String("admin"),
+                    }),
+                    Box::new(Condition::NotEquals {
+                        attr: "suspended",
+                        value: Value::Bool(true),
+                    }),
+                )),
+                ReasonCode(1),
+            ),
+            Rule::deny(
+                Target {
+                    principal: Matcher::Group("contractors"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(2),
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_interner() {
+        let interner = Interner::build(&rules());
+        let strings: Vec<_> = (0..interner.len() as u32)
+            .filter_map(|i| interner.resolve(Symbol(i)))
+            .collect();
+        assert_eq!(
+            strings,
+            ["alice", "bob", "project", "read", "role", "suspended"]
+        );
+        assert_eq!(
+            interner.resolve(interner.get("read").unwrap()),
+            Some("read")
+        );
+        assert_eq!(interner.get("contractors"), None);
+        assert!(Interner::default().is_empty());
+    }
+
+    #[test]
+    fn test_interned_evaluation_matches_plain() {
+        let groups = GroupMap::builder()
+            .member("carol", "contractors")
+            .build()
+            .unwrap();
+        let build = |intern| {
+            Policy::builder()
+                .rules_from_iter(rules())
+                .groups(groups.clone())
+                .resource_separator('/')
+                .intern(intern)
+                .build()
+                .unwrap()
+        };
+        let (plain, interned) = (build(false), build(true));
+        assert!(!plain.is_interned() && interned.is_interned());
+        assert_eq!(plain.fingerprint(), interned.fingerprint());
+
+        let admin: &[(&str, Value)] = &[
+            ("role", Value::String("admin")),
+            ("role", Value::String("guest")),
+        ];
+        let suspended: &[(&str, Value)] = &[
+            ("extra", Value::Int(1)),
+            ("role", Value::String("admin")),
+            ("suspended", Value::Bool(true)),
+        ];
+        let requests = [
+            Request::with_context("alice", "read", "project/doc", admin),
+            Request::with_context("bob", "read", "project", suspended),
+            Request::with_context("carol", "read", "project", admin),
+            Request::with_context("dave", "read", "project", admin),
+            Request::with_context("alice", "write", "project", admin),
+            Request::with_context("alice", "read", "other/doc", admin),
+        ];
+        for request in &requests {
+            let expected = plain.evaluate(request);
+            assert_eq!(
+                interned.evaluate_interned(&interned.intern(request)),
+                expected
+            );
+            assert_eq!(interned.evaluate(request), expected);
+            // Interned for a policy that does not intern, or for another
+            // policy: evaluated by name
+            assert_eq!(plain.evaluate_interned(&plain.intern(request)), expected);
+            assert_eq!(plain.evaluate_interned(&interned.intern(request)), expected);
+        }
+        assert_eq!(
+            interned.evaluate_interned(&interned.intern(&requests[0])),
+            Ok(crate::Decision::allow(ReasonCode(1)))
+        );
+
+        // Requests are validated as usual
+        let long = "x".repeat(300);
+        let request = Request::new(&long, "read", "project");
+        assert!(matches!(
+            interned.evaluate_interned(&interned.intern(&request)),
+            Err(PolicyError::StringTooLong { .. })
+        ));
+    }
+}
@@ -0,0 +1,219 @@
+//! JSON Lines decision log (features `serde` and `std`).
+//!
+//! Every service that audits decisions ends up inventing the same log line.
+//! `JsonDecisionLogger` is that line, written to any `io::Write`:
+//!
+//! ```json
+//! {"request_hash":"9f1c1a1d0e5b7c2a","effect":"allow","reason":1,"policy":"0c4d27e1a5f3b968","stats":{"rules_checked":2,"max_depth_reached":1,"condition_evals":1,"matcher_comparisons":5,"context_lookups":1,"steps":4,"resolutions":0,"ancestors_walked":0,"termination":"exhausted_rules"}}
+//! ```
+//!
+//! The format is canonical: keys always appear in this order, there is no
+//! whitespace, and one decision is one line. `request_hash` is the request's
+//! canonical hash (the same one `AuditRing` and `DecisionCache` use) and
+//! `policy` is `Policy::fingerprint`; both are 64-bit values written as 16
+//! lowercase hex digits, so JSON parsers that read numbers as doubles cannot
+//! lose precision. Request strings are never logged.
+
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use crate::fingerprint::request_hash;
+use crate::sink::DecisionSink;
+use crate::stats::{EvaluationStats, Termination};
+use crate::types::{Decision, Effect, Request};
+
+/// A `DecisionSink` that writes one JSON line per decision.
+///
+/// Writes are serialized through a mutex, so the logger can be attached to a
+/// policy shared across threads. Sinks cannot fail evaluation, so I/O errors
+/// are counted (see `write_errors`) instead of returned. Wrap the writer in
+/// a `BufWriter` for files and sockets, and call `flush` before shutdown.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{JsonDecisionLogger, Policy, ReasonCode, Request, Rule, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(Target::any(), ReasonCode(1)))
+///     .build()
+///     .unwrap();
+/// let logger = JsonDecisionLogger::new(Vec::new(), policy.fingerprint());
+///
+/// policy
+///     .evaluate_with_sink(&Request::new("alice", "read", "doc"), &logger)
+///     .unwrap();
+///
+/// let log = String::from_utf8(logger.into_inner()).unwrap();
+/// assert!(log.starts_with(r#"{"request_hash":""#));
+/// assert!(log.contains(r#","effect":"allow","reason":1,"#));
+/// assert_eq!(log.lines().count(), 1);
+/// ```
+#[derive(Debug)]
+pub struct JsonDecisionLogger<W> {
+    writer: Mutex<W>,
+    fingerprint: u64,
+    write_errors: AtomicU64,
+}
+
+impl<W: Write> JsonDecisionLogger<W> {
+    /// Create a logger writing to `writer`, tagging every line with the
+    /// fingerprint of the policy that made the decisions.
+    pub fn new(writer: W, fingerprint: u64) -> Self {
+        JsonDecisionLogger {
+            writer: Mutex::new(writer),
+            fingerprint,
+            write_errors: AtomicU64::new(0),
+        }
+    }
+
+    /// Write the line for one decision.
+    pub fn write_decision(
+        &self,
+        request: &Request<'_>,
+        decision: &Decision,
+        stats: &EvaluationStats,
+    ) -> io::Result<()> {
+        let line = format_line(request_hash(request), decision, self.fingerprint, stats);
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        writer.write_all(line.as_bytes())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&self) -> io::Result<()> {
+        self.writer
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .flush()
+    }
+
+    /// Number of lines that failed to write since creation.
+    pub fn write_errors(&self) -> u64 {
+        self.write_errors.load(Ordering::Relaxed)
+    }
+
+    /// The fingerprint written on every line.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Consume the logger and return the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<W: Write> DecisionSink for JsonDecisionLogger<W> {
+    fn record(&self, request: &Request<'_>, decision: &Decision, stats: &EvaluationStats) {
+        if self.write_decision(request, decision, stats).is_err() {
+            self.write_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// One canonical log line, including the trailing newline.
+pub(crate) fn format_line(
+    request_hash: u64,
+    decision: &Decision,
+    fingerprint: u64,
+    stats: &EvaluationStats,
+) -> String {
+    let effect = match decision.effect {
+        Effect::Allow => "allow",
+        Effect::Deny => "deny",
+    };
+    let termination = match stats.termination {
+        Termination::ExhaustedRules => "exhausted_rules",
+        Termination::ExhaustedCandidates => "exhausted_candidates",
+        Termination::Cached => "cached",
+    };
+    format!(
+        concat!(
+            r#"{{"request_hash":"{:016x}","effect":"{}","reason":{},"policy":"{:016x}","#,
+            r#""stats":{{"rules_checked":{},"max_depth_reached":{},"condition_evals":{},"#,
+            r#""matcher_comparisons":{},"context_lookups":{},"steps":{},"resolutions":{},"#,
+            r#""ancestors_walked":{},"termination":"{}"}}}}"#,
+            "\n"
+        ),
+        request_hash,
+        effect,
+        decision.reason.value(),
+        fingerprint,
+        stats.rules_checked,
+        stats.max_depth_reached,
+        stats.condition_evals,
+        stats.matcher_comparisons,
+        stats.context_lookups,
+        stats.steps,
+        stats.resolutions,
+        stats.ancestors_walked,
+        termination,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, ReasonCode, Rule, Target};
+
+    #[test]
+    fn test_lines_are_canonical_json() {
+        let policy = Policy::builder()
+            .rule(Rule::deny(Target::any(), ReasonCode(7)))
+            .build()
+            .unwrap();
+        let logger = JsonDecisionLogger::new(Vec::new(), policy.fingerprint());
+        let alice = Request::new("alice", "read", "doc");
+        let bob = Request::new("bob", "read", "doc");
+        for request in [&alice, &bob, &alice] {
+            policy.evaluate_with_sink(request, &logger).unwrap();
+        }
+        assert_eq!(logger.write_errors(), 0);
+
+        let log = String::from_utf8(logger.into_inner()).unwrap();
+        let lines: Vec<_> = log.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // Same request, same line
+        assert_eq!(lines[0], lines[2]);
+        assert_ne!(lines[0], lines[1]);
+
+        let line: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(line["effect"], "deny");
+        assert_eq!(line["reason"], 7);
+        assert_eq!(
+            line["request_hash"],
+            format!("{:016x}", request_hash(&alice))
+        );
+        assert_eq!(line["policy"], format!("{:016x}", policy.fingerprint()));
+        assert_eq!(line["stats"]["rules_checked"], 1);
+        assert_eq!(line["stats"]["termination"], "exhausted_rules");
+    }
+
+    #[test]
+    fn test_write_errors_are_counted() {
+        struct Broken;
+        impl Write for Broken {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("disk full"))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .build()
+            .unwrap();
+        let logger = JsonDecisionLogger::new(Broken, policy.fingerprint());
+        // The decision is still returned
+        let decision = policy
+            .evaluate_with_sink(&Request::new("a", "b", "c"), &logger)
+            .unwrap();
+        assert!(decision.is_allow());
+        assert_eq!(logger.write_errors(), 1);
+    }
+}
@@ -22,7 +22,127 @@
 //! - **Determinism**: Ordered evaluation, stable conflict resolution
 //! - **No panics**: All operations return `Result`
 //! - **Explicit errors**: Typed `PolicyError` enum
-//! - **Zero dependencies**: Pure `std` only
+//! - **Zero dependencies**: Only `core` and `alloc`; `std` is optional
+//!
+//! ## `no_std`
+//!
+//! The crate is `#![no_std]` when the default `std` feature is disabled.
+//! Policy construction needs `alloc` (rules live in a `Vec`, conditions are
+//! boxed); evaluation itself never allocates. Without `std`, `PolicyError`
+//! implements `Display` but not `std::error::Error`.
+//!
+//! `Rule::new`, `Rule::allow`, `Rule::deny`, `Rule::named`, `Target::new`,
+//! `Target::any`, `Request::new`, and `Request::with_context` are `const fn`,
+//! so rules with leaf conditions (which are plain enum literals) and fixed
+//! requests can be `static` data. `Not`, `And`, and `Or` box their operands
+//! and cannot be built in a constant, and `Policy` still compiles its rules
+//! at construction.
+//!
+//! ## Batch Evaluation
+//!
+//! `Policy::evaluate_batch` evaluates a slice of requests in order. With the
+//! opt-in `parallel` feature, `Policy::evaluate_batch_parallel` spreads the
+//! batch across rayon's thread pool for offline audits over large request
+//! sets. Each request is still evaluated independently and deterministically,
+//! and results come back in input order, so both methods return identical
+//! vectors.
+//!
+//! ## Interning
+//!
+//! `PolicyBuilder::intern(true)` numbers every attribute name and matcher
+//! string when the policy is built. A request interned once with
+//! `Policy::intern` is then evaluated with `Policy::evaluate_interned`,
+//! which finds attributes and matches targets by comparing symbols instead
+//! of strings. Decisions are the same as `Policy::evaluate`.
+//!
+//! ## Decision Cache
+//!
+//! With the opt-in `cache` feature, `DecisionCache` memoizes decisions for
+//! repeated requests. It is bounded, evicts in insertion order, and clears
+//! itself whenever it sees a policy with a different `Policy::fingerprint`.
+//!
+//! ## Cache Hints
+//!
+//! Rules may declare a `CacheHint` with `Rule::cache`: a TTL, or that
+//! decisions hold only while the request context is unchanged. Each
+//! `Decision` carries the strictest hint of the rules that could have
+//! changed it, so callers caching decisions outside `DecisionCache` need
+//! not guess.
+//!
+//! ## Hot Swapping
+//!
+//! With the opt-in `hot-swap` feature, `PolicyCell` lets readers evaluate
+//! lock-free while an updater installs a new policy; decisions carry the
+//! generation of the policy that made them.
+//!
+//! ## JSON Requests
+//!
+//! With the opt-in `serde` feature, `RequestData` is an owned request with a
+//! stable JSON shape: `{principal, action, resource, context: {...}}`.
+//! With `std` as well, `JsonDecisionLogger` is a `DecisionSink` that writes
+//! one canonical JSON line per decision to any `io::Write`.
+//!
+//! ## Text Policies
+//!
+//! With the opt-in `text-dsl` feature, `parse_policy` reads policies written
+//! as text (`ALLOW ("alice" "read" *) WHERE { (role EQ "admin") } => 3;`)
+//! into an `OwnedPolicy`, reporting errors with line and column. With `std`,
+//! `static_policy!` declares a `static` policy in the same syntax, with its
+//! limits checked, and duplicate or shadowed rules rejected, at compile time.
+//!
+//! ## Command Line
+//!
+//! The opt-in `cli` feature builds the `gate0` binary, which evaluates
+//! `RequestData` JSON lines from stdin against an `OwnedPolicy` JSON file
+//! and prints one decision line per request.
+//!
+//! ## WebAssembly
+//!
+//! With the opt-in `wasm` feature, `WasmPolicy` exposes policy loading and
+//! evaluation to JavaScript through `wasm-bindgen`. Policies and requests
+//! cross as JSON, and decisions come back as `JsonDecisionLogger` lines, so
+//! they can be compared byte for byte with a native backend's log.
+//!
+//! ## HTTP Middleware
+//!
+//! With the opt-in `tower` feature, `AuthorizationLayer` authorizes
+//! `http::Request`s for any tower service, axum included. A
+//! `RequestExtractor` names the principal, action, and resource; denied
+//! requests are answered with `403 Forbidden` and the reason code in the
+//! `x-gate0-reason` header, and evaluation errors fail closed.
+//!
+//! ## Metrics
+//!
+//! With the opt-in `metrics` feature, `MetricsSink` reports decisions by
+//! effect and reason code, rules checked, and evaluation errors through the
+//! `metrics` facade.
+//!
+//! ## Tracing
+//!
+//! With the opt-in `tracing` feature, every evaluation runs in an info-level
+//! `gate0.evaluate` span carrying the policy fingerprint and, once decided,
+//! the winning rule index, effect, and reason. Each matched rule emits a
+//! debug-level `rule matched` event.
+//!
+//! ## Fuzzing
+//!
+//! With the opt-in `arbitrary` feature, `Policy`, `Rule`, `Condition`,
+//! `Target`, `Matcher`, `Value`, and `Request` implement
+//! `arbitrary::Arbitrary`. Generated policies stay within the default
+//! `PolicyConfig` limits, so they always build.
+//!
+//! ## Property Testing
+//!
+//! With the opt-in `testing` feature, `gate0::testing` offers the proptest
+//! strategies for valid policies and requests that the crate's own tests
+//! use, and its invariants (such as deny-overrides) as reusable checks.
+//!
+//! ## Custom Predicates
+//!
+//! With the opt-in `custom-predicates` feature, `Condition::Custom` calls a
+//! named `fn(&Value) -> bool` from a `PredicateRegistry`, for checks the
+//! built-in conditions cannot express. Predicates must be deterministic and
+//! bounded; unregistered names are rejected when the policy is built.
 //!
 //! ## Example
 //!
@@ -69,25 +189,145 @@
 //! 2. Collect all matching rules
 //! 3. If any Deny matches → return first Deny's reason
 //! 4. Else if any Allow matches → return first Allow's reason
-//! 5. Else → Deny with `NO_MATCHING_RULE`
+//! 5. Else → Deny with `NO_MATCHING_RULE`, or Allow with `DEFAULT_ALLOW` if
+//!    `PolicyConfig::default_effect` is `Allow`
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+extern crate alloc;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls;
+mod audit;
+#[cfg(feature = "cache")]
+mod cache;
+#[cfg(feature = "hot-swap")]
+mod cell;
+mod compiled;
 mod condition;
+mod const_fmt;
+mod coverage;
+mod denies;
 mod error;
+mod fingerprint;
 mod fixed_stack;
+mod groups;
+mod hints;
+mod index;
+mod intern;
+#[cfg(all(feature = "serde", feature = "std"))]
+mod json_log;
+#[cfg(feature = "metrics")]
+mod metrics_sink;
+#[cfg(feature = "tower")]
+mod middleware;
+mod net;
+mod observer;
+mod optimize;
+mod owned;
+#[cfg(feature = "parallel")]
+mod parallel;
 mod policy;
+#[cfg(feature = "custom-predicates")]
+mod predicates;
+mod provider;
+mod query;
+mod reasons;
+mod redundancy;
+mod request;
+#[cfg(feature = "serde")]
+mod request_data;
+mod schema;
+mod sink;
+#[cfg(feature = "std")]
+mod static_policy;
 mod stats;
+#[cfg(feature = "std")]
+mod store;
+mod suite;
 mod target;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "text-dsl")]
+mod text_dsl;
+mod time;
+mod trace;
 mod types;
 mod value;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod whatif;
 
 // Public API exports
+pub use audit::{AuditEntry, AuditRing};
+#[cfg(feature = "cache")]
+pub use cache::DecisionCache;
+#[cfg(feature = "hot-swap")]
+pub use cell::{GenerationalDecision, PolicyCell, PolicySnapshot};
 pub use condition::Condition;
-pub use error::PolicyError;
-pub use policy::{Policy, PolicyBuilder, PolicyConfig, Rule};
-pub use stats::EvaluationStats;
+pub use coverage::{BranchCoverage, CoverageReport, CoverageTracker, RuleCoverage};
+pub use denies::{DenyReasons, MAX_DENY_REASONS};
+pub use error::{Location, PolicyError, RulePart};
+pub use fixed_stack::FixedStack;
+pub use groups::{GroupMap, GroupMapBuilder, DEFAULT_MAX_GROUP_DEPTH};
+pub use hints::{Change, DenialHint};
+pub use intern::{InternedRequest, Interner, Symbol};
+#[cfg(all(feature = "serde", feature = "std"))]
+pub use json_log::JsonDecisionLogger;
+#[cfg(feature = "metrics")]
+pub use metrics_sink::{MetricsSink, DECISIONS_METRIC, ERRORS_METRIC, RULES_CHECKED_METRIC};
+#[cfg(feature = "tower")]
+pub use middleware::{
+    Authorization, AuthorizationLayer, RequestExtractor, ResponseFuture, ERROR_HEADER,
+    REASON_HEADER,
+};
+pub use net::Cidr;
+pub use optimize::{OptimizationReport, RuleMerge};
+pub use owned::{
+    OwnedCondition, OwnedMatcher, OwnedPolicy, OwnedRule, OwnedTarget, OwnedValue, SharedPolicy,
+};
+pub use policy::{Policy, PolicyBuilder, PolicyConfig, Rule, RuleViolation};
+#[cfg(feature = "custom-predicates")]
+pub use predicates::{Predicate, PredicateRegistry};
+pub use provider::ContextProvider;
+pub use query::{Query, QueryResult, Witness, DEFAULT_MAX_QUERY_REQUESTS};
+pub use reasons::{DescribedDecision, ReasonInfo, ReasonRegistry, ReasonRegistryBuilder};
+pub use redundancy::RuleWarning;
+pub use request::RequestBuilder;
+#[cfg(feature = "serde")]
+pub use request_data::RequestData;
+pub use schema::{ContextSchema, SchemaAttr, SchemaViolation};
+pub use sink::DecisionSink;
+pub use stats::{EvaluationStats, StatSummary, StatsAggregator, StatsSnapshot, Termination};
+#[cfg(feature = "std")]
+pub use store::{PolicyStore, DEFAULT_MAX_TENANTS};
+pub use suite::{CaseFailure, Expectation, Expected, PolicyTestSuite, TestCase, TestReport};
 pub use target::{Matcher, Target};
-pub use types::{Decision, Effect, ReasonCode, Request, NO_MATCHING_RULE};
-pub use value::Value;
+#[cfg(feature = "text-dsl")]
+pub use text_dsl::{parse_policy, parse_policy_with_config, ParseError, ParseErrorKind};
+pub use time::{parse_duration, parse_time_of_day, Weekdays, SECONDS_PER_DAY};
+pub use trace::{ConditionOutcome, EvaluationTrace, RuleTrace, TraceOutcome};
+pub use types::{
+    CacheHint, Decision, Effect, ReasonCode, ReasonDomain, Request, DEFAULT_ALLOW, NO_MATCHING_RULE,
+};
+pub use value::{Value, ValueType};
+#[cfg(feature = "wasm")]
+pub use wasm::WasmPolicy;
+pub use whatif::{Domain, WhatIf, WhatIfRow};
+
+/// Support for macro expansions; not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::string::String;
+    pub use alloc::vec::Vec;
+
+    #[cfg(feature = "std")]
+    pub use crate::static_policy::{
+        build, cache_hint, check, ordered, Lit, StaticCondition, StaticRule,
+    };
+    #[cfg(feature = "std")]
+    pub use std::sync::LazyLock;
+}
 
 #[cfg(test)]
 mod integration_tests {
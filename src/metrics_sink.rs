@@ -0,0 +1,160 @@
+//! Decision metrics through the `metrics` facade (feature `metrics`).
+//!
+//! `MetricsSink` is a `DecisionSink` that reports every decision to
+//! whichever `metrics` recorder the application installed (Prometheus,
+//! StatsD, ...), so deny rates per reason code can be graphed without an
+//! adapter:
+//!
+//! | Metric | Kind | Labels |
+//! |--------|------|--------|
+//! | `gate0_decisions_total` | counter | `effect` (`allow`/`deny`), `reason` (the code) |
+//! | `gate0_rules_checked` | histogram | none; cached decisions are skipped |
+//! | `gate0_evaluation_errors_total` | counter | `error` (`PolicyError::kind`) |
+//!
+//! Sinks only see decisions, so errors are reported by calling
+//! `MetricsSink::record_error` where the error is handled. Labels never
+//! contain request data.
+
+use alloc::string::ToString;
+
+use crate::error::PolicyError;
+use crate::sink::DecisionSink;
+use crate::stats::{EvaluationStats, Termination};
+use crate::types::{Decision, Effect, Request};
+
+/// Counter of decisions, labelled by `effect` and `reason`.
+pub const DECISIONS_METRIC: &str = "gate0_decisions_total";
+
+/// Histogram of rules checked per decision.
+pub const RULES_CHECKED_METRIC: &str = "gate0_rules_checked";
+
+/// Counter of evaluation errors, labelled by `error`.
+pub const ERRORS_METRIC: &str = "gate0_evaluation_errors_total";
+
+/// A `DecisionSink` that emits metrics for every decision.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{MetricsSink, Policy, ReasonCode, Request, Rule, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::deny(Target::any(), ReasonCode(1)))
+///     .sink(MetricsSink)
+///     .build()
+///     .unwrap();
+///
+/// // Counted as gate0_decisions_total{effect="deny",reason="1"}
+/// let result = policy.evaluate(&Request::new("alice", "read", "doc"));
+/// if let Err(error) = &result {
+///     MetricsSink.record_error(error);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsSink;
+
+impl MetricsSink {
+    /// Count one evaluation error, labelled by its `PolicyError::kind`.
+    pub fn record_error(&self, error: &PolicyError) {
+        metrics::counter!(ERRORS_METRIC, "error" => error.kind()).increment(1);
+    }
+}
+
+impl DecisionSink for MetricsSink {
+    fn record(&self, _request: &Request<'_>, decision: &Decision, stats: &EvaluationStats) {
+        let effect = match decision.effect {
+            Effect::Allow => "allow",
+            Effect::Deny => "deny",
+        };
+        metrics::counter!(
+            DECISIONS_METRIC,
+            "effect" => effect,
+            "reason" => decision.reason.value().to_string()
+        )
+        .increment(1);
+        if stats.termination != Termination::Cached {
+            metrics::histogram!(RULES_CHECKED_METRIC).record(f64::from(stats.rules_checked));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Matcher, Policy, ReasonCode, Rule, Target};
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use metrics_util::MetricKind;
+
+    #[test]
+    fn test_decisions_and_errors() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        let policy = Policy::builder()
+            .rule(Rule::deny(
+                Target {
+                    principal: Matcher::Exact("mallory"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(9),
+            ))
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .sink(MetricsSink)
+            .build()
+            .unwrap();
+
+        metrics::with_local_recorder(&recorder, || {
+            for principal in ["alice", "bob", "mallory"] {
+                policy
+                    .evaluate(&Request::new(principal, "read", "doc"))
+                    .unwrap();
+            }
+            MetricsSink.record_error(&PolicyError::InternalError);
+        });
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let value = |kind: MetricKind, name: &str, labels: &[(&str, &str)]| {
+            snapshot
+                .iter()
+                .find(|(key, ..)| {
+                    key.kind() == kind
+                        && key.key().name() == name
+                        && key
+                            .key()
+                            .labels()
+                            .map(|l| (l.key(), l.value()))
+                            .eq(labels.iter().copied())
+                })
+                .map(|(.., value)| value)
+        };
+
+        assert_eq!(
+            value(
+                MetricKind::Counter,
+                DECISIONS_METRIC,
+                &[("effect", "allow"), ("reason", "1")]
+            ),
+            Some(&DebugValue::Counter(2))
+        );
+        assert_eq!(
+            value(
+                MetricKind::Counter,
+                DECISIONS_METRIC,
+                &[("effect", "deny"), ("reason", "9")]
+            ),
+            Some(&DebugValue::Counter(1))
+        );
+        assert_eq!(
+            value(
+                MetricKind::Counter,
+                ERRORS_METRIC,
+                &[("error", "internal_error")]
+            ),
+            Some(&DebugValue::Counter(1))
+        );
+        match value(MetricKind::Histogram, RULES_CHECKED_METRIC, &[]) {
+            Some(DebugValue::Histogram(values)) => assert_eq!(values.len(), 3),
+            other => panic!("unexpected histogram: {:?}", other),
+        }
+    }
+}
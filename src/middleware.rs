@@ -0,0 +1,390 @@
+//! Tower authorization middleware (feature `tower`).
+//!
+//! `AuthorizationLayer` wraps any `tower::Service` taking `http::Request`s,
+//! which includes axum routers and handlers. For each request a
+//! `RequestExtractor` names the principal, action, and resource (and,
+//! optionally, context), and the shared policy decides:
+//!
+//! - Allowed requests reach the inner service, with the `Decision` in the
+//!   request's extensions.
+//! - Denied requests get an empty `403 Forbidden` with the reason code in
+//!   the `x-gate0-reason` header. The inner service never sees them.
+//! - Requests that fail evaluation fail closed: `403 Forbidden` with the
+//!   `PolicyError::kind` name in the `x-gate0-error` header.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use http::header::{HeaderName, HeaderValue};
+use http::{Response, StatusCode};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::policy::Policy;
+use crate::types::Request;
+use crate::value::Value;
+
+/// Header carrying the reason code of a denial.
+pub const REASON_HEADER: HeaderName = HeaderName::from_static("x-gate0-reason");
+
+/// Header carrying the `PolicyError::kind` name of a failed evaluation.
+pub const ERROR_HEADER: HeaderName = HeaderName::from_static("x-gate0-error");
+
+/// Maps an HTTP request to the names the policy matches on.
+///
+/// Only `principal` is required. The action defaults to the method and the
+/// resource to the path. Return a fixed principal (such as `"anonymous"`)
+/// for unauthenticated requests, so the policy decides what they may do.
+///
+/// # Example
+///
+/// ```
+/// use gate0::RequestExtractor;
+///
+/// struct UserHeader;
+///
+/// impl<B> RequestExtractor<B> for UserHeader {
+///     fn principal<'r>(&self, request: &'r http::Request<B>) -> &'r str {
+///         request
+///             .headers()
+///             .get("x-user")
+///             .and_then(|v| v.to_str().ok())
+///             .unwrap_or("anonymous")
+///     }
+/// }
+/// ```
+pub trait RequestExtractor<B> {
+    /// The principal making the request.
+    fn principal<'r>(&self, request: &'r http::Request<B>) -> &'r str;
+
+    /// The action requested. Defaults to the method (`"GET"`, `"POST"`, ...).
+    fn action<'r>(&self, request: &'r http::Request<B>) -> &'r str {
+        request.method().as_str()
+    }
+
+    /// The resource requested. Defaults to the URI path.
+    fn resource<'r>(&self, request: &'r http::Request<B>) -> &'r str {
+        request.uri().path()
+    }
+
+    /// Push context attributes for the request. Defaults to none.
+    fn context<'r>(
+        &self,
+        _request: &'r http::Request<B>,
+        _context: &mut Vec<(&'r str, Value<'r>)>,
+    ) {
+    }
+}
+
+/// A `tower::Layer` that authorizes requests against a shared policy.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use gate0::{AuthorizationLayer, Policy, ReasonCode, Rule, Target};
+/// # use gate0::RequestExtractor;
+/// # struct UserHeader;
+/// # impl<B> RequestExtractor<B> for UserHeader {
+/// #     fn principal<'r>(&self, _: &'r http::Request<B>) -> &'r str { "anonymous" }
+/// # }
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(Target::any(), ReasonCode(1)))
+///     .build()
+///     .unwrap();
+/// let layer = AuthorizationLayer::new(Arc::new(policy), UserHeader);
+/// // With axum: `Router::new().route(..).layer(layer)`
+/// ```
+#[derive(Debug)]
+pub struct AuthorizationLayer<E> {
+    policy: Arc<Policy<'static>>,
+    extractor: Arc<E>,
+}
+
+impl<E> AuthorizationLayer<E> {
+    /// Authorize requests against `policy`, naming them with `extractor`.
+    pub fn new(policy: Arc<Policy<'static>>, extractor: E) -> Self {
+        AuthorizationLayer {
+            policy,
+            extractor: Arc::new(extractor),
+        }
+    }
+}
+
+impl<E> Clone for AuthorizationLayer<E> {
+    fn clone(&self) -> Self {
+        AuthorizationLayer {
+            policy: Arc::clone(&self.policy),
+            extractor: Arc::clone(&self.extractor),
+        }
+    }
+}
+
+impl<S, E> Layer<S> for AuthorizationLayer<E> {
+    type Service = Authorization<S, E>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Authorization {
+            inner,
+            policy: Arc::clone(&self.policy),
+            extractor: Arc::clone(&self.extractor),
+        }
+    }
+}
+
+/// The service built by `AuthorizationLayer`.
+#[derive(Debug)]
+pub struct Authorization<S, E> {
+    inner: S,
+    policy: Arc<Policy<'static>>,
+    extractor: Arc<E>,
+}
+
+impl<S: Clone, E> Clone for Authorization<S, E> {
+    fn clone(&self) -> Self {
+        Authorization {
+            inner: self.inner.clone(),
+            policy: Arc::clone(&self.policy),
+            extractor: Arc::clone(&self.extractor),
+        }
+    }
+}
+
+impl<S, E> Authorization<S, E> {
+    /// The wrapped service.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The policy requests are authorized against.
+    pub fn policy(&self) -> &Policy<'static> {
+        &self.policy
+    }
+}
+
+impl<S, E, ReqBody, ResBody> Service<http::Request<ReqBody>> for Authorization<S, E>
+where
+    S: Service<http::Request<ReqBody>, Response = Response<ResBody>>,
+    E: RequestExtractor<ReqBody>,
+    ResBody: Default,
+{
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, ResBody>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<ReqBody>) -> Self::Future {
+        let result = {
+            let extractor = &*self.extractor;
+            let mut context = Vec::new();
+            extractor.context(&request, &mut context);
+            self.policy.evaluate(&Request::with_context(
+                extractor.principal(&request),
+                extractor.action(&request),
+                extractor.resource(&request),
+                &context,
+            ))
+        };
+
+        let (header, value) = match result {
+            Ok(decision) if decision.is_allow() => {
+                request.extensions_mut().insert(decision);
+                return ResponseFuture::Inner {
+                    future: self.inner.call(request),
+                };
+            }
+            Ok(decision) => (REASON_HEADER, HeaderValue::from(decision.reason.value())),
+            Err(e) => (ERROR_HEADER, HeaderValue::from_static(e.kind())),
+        };
+        let mut response = Response::new(ResBody::default());
+        *response.status_mut() = StatusCode::FORBIDDEN;
+        response.headers_mut().insert(header, value);
+        ResponseFuture::Forbidden {
+            response: Some(response),
+        }
+    }
+}
+
+pin_project! {
+    /// The response future of `Authorization`.
+    #[project = ResponseFutureProj]
+    pub enum ResponseFuture<F, B> {
+        /// The request was allowed and is being handled.
+        Inner {
+            #[pin]
+            future: F,
+        },
+        /// The request was rejected; the response is ready.
+        Forbidden {
+            response: Option<Response<B>>,
+        },
+    }
+}
+
+impl<F, B, Error> Future for ResponseFuture<F, B>
+where
+    F: Future<Output = Result<Response<B>, Error>>,
+{
+    type Output = Result<Response<B>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            ResponseFutureProj::Inner { future } => future.poll(cx),
+            ResponseFutureProj::Forbidden { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::Infallible;
+    use std::future::Ready;
+    use std::task::Waker;
+
+    use crate::condition::Condition;
+    use crate::policy::Rule;
+    use crate::target::{Matcher, Target};
+    use crate::types::{Decision, Effect, ReasonCode};
+
+    struct UserHeader;
+
+    impl<B> RequestExtractor<B> for UserHeader {
+        fn principal<'r>(&self, request: &'r http::Request<B>) -> &'r str {
+            request
+                .headers()
+                .get("x-user")
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("anonymous")
+        }
+
+        fn context<'r>(
+            &self,
+            request: &'r http::Request<B>,
+            context: &mut Vec<(&'r str, Value<'r>)>,
+        ) {
+            if let Some(role) = request.headers().get("x-role") {
+                context.push(("role", Value::String(role.to_str().unwrap())));
+            }
+        }
+    }
+
+    /// Echoes the decision the middleware attached.
+    struct Echo;
+
+    impl Service<http::Request<()>> for Echo {
+        type Response = Response<String>;
+        type Error = Infallible;
+        type Future = Ready<Result<Response<String>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: http::Request<()>) -> Self::Future {
+            let decision = request.extensions().get::<Decision>().unwrap();
+            std::future::ready(Ok(Response::new(format!("{}", decision.reason.value()))))
+        }
+    }
+
+    fn service() -> Authorization<Echo, UserHeader> {
+        let policy = Policy::builder()
+            .rule(Rule::allow(
+                Target {
+                    principal: Matcher::Exact("alice"),
+                    action: Matcher::Exact("GET"),
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::new(Matcher::Any, Matcher::Exact("DELETE"), Matcher::Any),
+                Some(Condition::Equals {
+                    attr: "role",
+                    value: Value::String("admin"),
+                }),
+                ReasonCode(2),
+            ))
+            .build()
+            .unwrap();
+        AuthorizationLayer::new(Arc::new(policy), UserHeader).layer(Echo)
+    }
+
+    fn call(
+        service: &mut Authorization<Echo, UserHeader>,
+        request: http::Request<()>,
+    ) -> Response<String> {
+        let mut future = std::pin::pin!(service.call(request));
+        match future
+            .as_mut()
+            .poll(&mut Context::from_waker(Waker::noop()))
+        {
+            Poll::Ready(Ok(response)) => response,
+            _ => panic!("response not ready"),
+        }
+    }
+
+    fn request(method: &str, user: &str) -> http::Request<()> {
+        http::Request::builder()
+            .method(method)
+            .uri("/docs/1")
+            .header("x-user", user)
+            .body(())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_allowed_requests_reach_inner_service() {
+        let mut service = service();
+        let response = call(&mut service, request("GET", "alice"));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "1");
+        assert!(response.headers().get(REASON_HEADER).is_none());
+    }
+
+    #[test]
+    fn test_denied_requests_get_403_with_reason() {
+        let mut service = service();
+        let response = call(&mut service, request("GET", "bob"));
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()[REASON_HEADER], "0");
+        assert_eq!(response.body(), "");
+    }
+
+    #[test]
+    fn test_extractor_context() {
+        let mut service = service();
+        let response = call(&mut service, request("DELETE", "bob"));
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let mut admin = request("DELETE", "bob");
+        admin
+            .headers_mut()
+            .insert("x-role", HeaderValue::from_static("admin"));
+        let response = call(&mut service, admin);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.body(), "2");
+    }
+
+    #[test]
+    fn test_evaluation_errors_fail_closed() {
+        let mut service = service();
+        let mut bad = request("DELETE", "bob");
+        let long = "a".repeat(service.policy().config().max_string_len + 1);
+        bad.headers_mut()
+            .insert("x-role", HeaderValue::from_str(&long).unwrap());
+        let response = call(&mut service, bad);
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(response.headers()[ERROR_HEADER], "string_too_long");
+    }
+}
@@ -0,0 +1,364 @@
+//! IP addresses and CIDR blocks.
+//!
+//! Source-address restrictions compare a request's `Value::Ip` against a
+//! block such as `10.0.0.0/8` (`Condition::IpInCidr`). Blocks are written as
+//! text in policies and parsed when the policy is built, so a typo fails the
+//! build instead of silently never matching.
+//!
+//! The parser is a `const fn`, so `static_policy!` checks blocks at compile
+//! time with the same code the runtime uses.
+
+use core::fmt;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use core::str::FromStr;
+
+use crate::error::PolicyError;
+
+/// A CIDR block: an address and a prefix length.
+///
+/// Host bits below the prefix must be zero, so `10.0.0.1/8` is rejected
+/// rather than read as `10.0.0.0/8`.
+///
+/// # Example
+///
+/// ```
+/// use gate0::Cidr;
+///
+/// let block: Cidr = "10.0.0.0/8".parse().unwrap();
+/// assert!(block.contains("10.1.2.3".parse().unwrap()));
+/// assert!(!block.contains("192.168.0.1".parse().unwrap()));
+/// assert!(!block.contains("::ffff:10.1.2.3".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Create a block, checking the prefix length and that host bits are
+    /// zero.
+    pub const fn new(addr: IpAddr, prefix: u8) -> Result<Self, PolicyError> {
+        let valid = match addr {
+            IpAddr::V4(a) => prefix <= 32 && a.to_bits() & !mask32(prefix) == 0,
+            IpAddr::V6(a) => prefix <= 128 && a.to_bits() & !mask128(prefix) == 0,
+        };
+        if valid {
+            Ok(Cidr { addr, prefix })
+        } else {
+            Err(PolicyError::InvalidCidr)
+        }
+    }
+
+    /// Parse `address/prefix`, e.g. `"10.0.0.0/8"` or `"2001:db8::/32"`.
+    pub const fn parse(s: &str) -> Result<Self, PolicyError> {
+        let bytes = s.as_bytes();
+        let mut slash = 0;
+        while slash < bytes.len() && bytes[slash] != b'/' {
+            slash += 1;
+        }
+        let Some(prefix) = parse_decimal(bytes, slash + 1, bytes.len(), 128) else {
+            return Err(PolicyError::InvalidCidr);
+        };
+        let addr = match parse_ipv4(bytes, 0, slash) {
+            Some(bits) => IpAddr::V4(Ipv4Addr::from_bits(bits)),
+            None => match parse_ipv6(bytes, 0, slash) {
+                Some(bits) => IpAddr::V6(Ipv6Addr::from_bits(bits)),
+                None => return Err(PolicyError::InvalidCidr),
+            },
+        };
+        Cidr::new(addr, prefix as u8)
+    }
+
+    /// The network address.
+    pub const fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    /// The prefix length in bits.
+    pub const fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    /// Returns `true` if `ip` is inside the block.
+    ///
+    /// Addresses of the other IP version never match; IPv4-mapped IPv6
+    /// addresses are not converted.
+    pub const fn contains(&self, ip: IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                ip.to_bits() & mask32(self.prefix) == net.to_bits()
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                ip.to_bits() & mask128(self.prefix) == net.to_bits()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = PolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Cidr::parse(s)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+const fn mask32(prefix: u8) -> u32 {
+    match prefix {
+        0 => 0,
+        p if p >= 32 => u32::MAX,
+        p => u32::MAX << (32 - p),
+    }
+}
+
+const fn mask128(prefix: u8) -> u128 {
+    match prefix {
+        0 => 0,
+        p if p >= 128 => u128::MAX,
+        p => u128::MAX << (128 - p),
+    }
+}
+
+/// A decimal number in `bytes[start..end]` without leading zeros, at most
+/// `max`.
+const fn parse_decimal(bytes: &[u8], start: usize, end: usize, max: u32) -> Option<u32> {
+    if start >= end || end - start > 3 || (bytes[start] == b'0' && end - start > 1) {
+        return None;
+    }
+    let mut value = 0;
+    let mut i = start;
+    while i < end {
+        if !bytes[i].is_ascii_digit() {
+            return None;
+        }
+        value = value * 10 + (bytes[i] - b'0') as u32;
+        i += 1;
+    }
+    if value > max {
+        return None;
+    }
+    Some(value)
+}
+
+/// A dotted-quad address in `bytes[start..end]`.
+const fn parse_ipv4(bytes: &[u8], start: usize, end: usize) -> Option<u32> {
+    let mut bits = 0;
+    let mut octets = 0;
+    let mut i = start;
+    while octets < 4 {
+        let mut dot = i;
+        while dot < end && bytes[dot] != b'.' {
+            dot += 1;
+        }
+        let Some(octet) = parse_decimal(bytes, i, dot, 255) else {
+            return None;
+        };
+        bits = bits << 8 | octet;
+        octets += 1;
+        if octets < 4 && dot == end {
+            return None;
+        }
+        i = dot + 1;
+    }
+    // The fourth octet must end the input
+    if i != end + 1 {
+        return None;
+    }
+    Some(bits)
+}
+
+/// One to four hex digits in `bytes[start..end]`.
+const fn parse_group(bytes: &[u8], start: usize, end: usize) -> Option<u16> {
+    if start >= end || end - start > 4 {
+        return None;
+    }
+    let mut value = 0u16;
+    let mut i = start;
+    while i < end {
+        let digit = match bytes[i] {
+            b'0'..=b'9' => bytes[i] - b'0',
+            b'a'..=b'f' => bytes[i] - b'a' + 10,
+            b'A'..=b'F' => bytes[i] - b'A' + 10,
+            _ => return None,
+        };
+        value = value << 4 | digit as u16;
+        i += 1;
+    }
+    Some(value)
+}
+
+/// An IPv6 address in `bytes[start..end]`, with optional `::` and an
+/// optional dotted-quad tail.
+const fn parse_ipv6(bytes: &[u8], start: usize, end: usize) -> Option<u128> {
+    // Groups before and after `::`
+    let mut head = 0u128;
+    let mut head_len = 0;
+    let mut tail = 0u128;
+    let mut tail_len = 0;
+    let mut compressed = false;
+
+    let mut i = start;
+    if end - start >= 2 && bytes[i] == b':' && bytes[i + 1] == b':' {
+        compressed = true;
+        i += 2;
+    }
+    while i < end {
+        let mut colon = i;
+        while colon < end && bytes[colon] != b':' && bytes[colon] != b'.' {
+            colon += 1;
+        }
+        let (groups, bits, next) = if colon < end && bytes[colon] == b'.' {
+            // An embedded IPv4 address must end the input
+            match parse_ipv4(bytes, i, end) {
+                Some(v4) => (2, v4 as u128, end),
+                None => return None,
+            }
+        } else {
+            match parse_group(bytes, i, colon) {
+                Some(group) => (1, group as u128, colon),
+                None => return None,
+            }
+        };
+        if compressed {
+            tail = tail << (16 * groups) | bits;
+            tail_len += groups;
+        } else {
+            head = head << (16 * groups) | bits;
+            head_len += groups;
+        }
+        if head_len + tail_len > 8 {
+            return None;
+        }
+
+        i = next;
+        if i == end {
+            break;
+        }
+        // `:` between groups, or `::` once
+        i += 1;
+        if i < end && bytes[i] == b':' {
+            if compressed {
+                return None;
+            }
+            compressed = true;
+            i += 1;
+        } else if i == end {
+            return None;
+        }
+    }
+
+    let total = head_len + tail_len;
+    if compressed {
+        // `::` stands for at least one zero group
+        if total > 7 {
+            return None;
+        }
+        let shift = 16 * (8 - head_len);
+        Some(if shift == 128 { 0 } else { head << shift } | tail)
+    } else if total == 8 {
+        Some(head)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_contains() {
+        let block = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(block.contains(IpAddr::V4(Ipv4Addr::new(10, 255, 0, 1))));
+        assert!(!block.contains(IpAddr::V4(Ipv4Addr::new(11, 0, 0, 0))));
+
+        let all = Cidr::parse("0.0.0.0/0").unwrap();
+        assert!(all.contains(IpAddr::V4(Ipv4Addr::BROADCAST)));
+        assert!(!all.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+        let host = Cidr::parse("::1/128").unwrap();
+        assert!(host.contains(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+        assert!(!host.contains(IpAddr::V6(Ipv6Addr::UNSPECIFIED)));
+
+        let v6 = Cidr::parse("2001:db8::/32").unwrap();
+        assert!(v6.contains("2001:db8:ffff::1".parse().unwrap()));
+        assert!(!v6.contains("2001:db9::".parse().unwrap()));
+        assert_eq!(v6.to_string(), "2001:db8::/32");
+    }
+
+    #[test]
+    fn test_rejects_invalid_blocks() {
+        for s in [
+            "10.0.0.1/8",
+            "10.0.0.0/33",
+            "10.0.0.0",
+            "10.0.0.0/",
+            "10.0.0.0/08",
+            "10.0.0/8",
+            "10.0.0.0.0/8",
+            "256.0.0.0/8",
+            "::1/129",
+            "2001:db8::1/32",
+            "/8",
+            "",
+        ] {
+            assert_eq!(Cidr::parse(s), Err(PolicyError::InvalidCidr), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_address_parsing_matches_core() {
+        let cases = [
+            "0.0.0.0",
+            "255.255.255.255",
+            "1.2.3.4",
+            "01.2.3.4",
+            "1.2.3",
+            "1.2.3.4.",
+            ".1.2.3.4",
+            "1..2.3",
+            "::",
+            "::1",
+            "1::",
+            "1:2:3:4:5:6:7:8",
+            "1:2:3:4:5:6:7::",
+            "::2:3:4:5:6:7:8",
+            "1:2:3:4:5:6:7:8::",
+            "1:2:3:4::5:6:7:8",
+            "1::2::3",
+            ":1::2",
+            "1::2:",
+            "1:2:3:4:5:6:7",
+            "12345::",
+            "fe80::1:2",
+            "FFFF::",
+            "::ffff:1.2.3.4",
+            "1:2:3:4:5:6:1.2.3.4",
+            "1:2:3:4:5:6:7:1.2.3.4",
+            "::1.2.3.4:5",
+            "::1.2.3",
+            "g::",
+            ":",
+            ":::",
+        ];
+        for case in cases {
+            let bytes = case.as_bytes();
+            let ours = parse_ipv4(bytes, 0, bytes.len())
+                .map(|bits| IpAddr::V4(Ipv4Addr::from_bits(bits)))
+                .or_else(|| {
+                    parse_ipv6(bytes, 0, bytes.len())
+                        .map(|bits| IpAddr::V6(Ipv6Addr::from_bits(bits)))
+                });
+            assert_eq!(ours, case.parse::<IpAddr>().ok(), "{}", case);
+        }
+    }
+}
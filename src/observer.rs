@@ -0,0 +1,33 @@
+//! Internal evaluation hooks.
+//!
+//! The evaluation loop is generic over an `Observer`, so features that need
+//! to see inside an evaluation (coverage, traces, deny collection) share one implementation of
+//! the semantics. Every hook has an empty default and is statically
+//! dispatched, so plain `evaluate()` (which observes with `()`) compiles to
+//! the same loop as before.
+
+/// Receives events from `Policy::evaluate_observed`.
+pub(crate) trait Observer {
+    /// Rule `rule` is about to have its target checked.
+    #[inline]
+    fn rule_checked(&mut self, _rule: usize) {}
+
+    /// Rule `rule`'s target matched the request.
+    #[inline]
+    fn target_matched(&mut self, _rule: usize) {}
+
+    /// Node `node` (postfix position) of rule `rule`'s condition produced
+    /// `result`.
+    #[inline]
+    fn node_result(&mut self, _rule: usize, _node: usize, _result: bool) {}
+
+    /// Rule `rule`'s condition evaluated to `result`.
+    #[inline]
+    fn condition_result(&mut self, _rule: usize, _result: bool) {}
+
+    /// Rule `rule` matched (target and condition).
+    #[inline]
+    fn rule_matched(&mut self, _rule: usize) {}
+}
+
+impl Observer for () {}
@@ -0,0 +1,383 @@
+//! Semantics-preserving rule merging.
+//!
+//! Policies are written for readability: one rule per principal, repeated
+//! targets, related rules far apart. Every candidate rule costs a target
+//! check on every evaluation, so `Policy::optimize` merges rules that can
+//! be expressed as one, moving a rule up to its merge partner only when it
+//! can prove the move changes no decision and no reason.
+//!
+//! # What is proven
+//!
+//! Two rules merge when they have the same effect, reason, condition, and
+//! cache hint, and their targets differ in at most one field, where both
+//! matchers are finite lists; the merged rule matches exactly when either
+//! did. Moving rule `j` up to an earlier rule `i` is safe when every rule of
+//! the same effect in between has the same reason and cache hint as `j` or a
+//! target that can never match a request `j` matches. Rules of the other
+//! effect never affect which reason or hint wins, so they are ignored.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::policy::{Policy, Rule};
+use crate::target::{Matcher, Target};
+
+/// One merge performed by `Policy::optimize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleMerge {
+    /// Declared index of the rule that absorbed the other.
+    pub kept: usize,
+    /// Declared index of the absorbed rule.
+    pub absorbed: usize,
+}
+
+/// What `Policy::optimize` changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OptimizationReport {
+    /// Rule count before optimizing.
+    pub rules_before: usize,
+    /// Rule count after optimizing.
+    pub rules_after: usize,
+    /// Merges, in the order they were made.
+    pub merges: Vec<RuleMerge>,
+}
+
+impl OptimizationReport {
+    /// Returns `true` if nothing changed.
+    pub fn is_unchanged(&self) -> bool {
+        self.merges.is_empty()
+    }
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rules -> {} rules",
+            self.rules_before, self.rules_after
+        )?;
+        for merge in &self.merges {
+            write!(
+                f,
+                "\n  merged rule {} into rule {}",
+                merge.absorbed, merge.kept
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Policy<'a> {
+    /// Merge rules without changing any decision or reason.
+    ///
+    /// Config, groups, resource hierarchy, schema, sink, and target index
+    /// are kept. The fingerprint changes if any rule was merged. Fewer rules
+    /// mean fewer evaluation steps, so a request that exceeded
+    /// `max_eval_steps` before may now be decided.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use gate0::{Matcher, Policy, ReasonCode, Request, Rule, Target};
+    ///
+    /// let read = |principal| Target {
+    ///     principal: Matcher::Exact(principal),
+    ///     action: Matcher::Exact("read"),
+    ///     resource: Matcher::Any,
+    /// };
+    /// let policy = Policy::builder()
+    ///     .rule(Rule::allow(read("alice"), ReasonCode(1)))
+    ///     .rule(Rule::allow(read("bob"), ReasonCode(1)))
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// let (optimized, report) = policy.optimize();
+    /// assert_eq!(optimized.rule_count(), 1);
+    /// assert_eq!(report.to_string(), "2 rules -> 1 rules\n  merged rule 1 into rule 0");
+    /// assert!(optimized.evaluate(&Request::new("bob", "read", "doc")).unwrap().is_allow());
+    /// ```
+    pub fn optimize(self) -> (Policy<'a>, OptimizationReport) {
+        let separator = self.resource_separator();
+        let max_options = self.config().max_matcher_options;
+        let mut rules: Vec<(usize, Rule<'a>)> = self.rules().iter().cloned().enumerate().collect();
+        let mut report = OptimizationReport {
+            rules_before: rules.len(),
+            ..OptimizationReport::default()
+        };
+
+        let mut i = 0;
+        while i < rules.len() {
+            let mut j = i + 1;
+            while j < rules.len() {
+                let (kept, absorbed) = (&rules[i].1, &rules[j].1);
+                let movable = rules[i + 1..j].iter().all(|(_, between)| {
+                    between.effect != absorbed.effect
+                        || (between.reason == absorbed.reason && between.cache == absorbed.cache)
+                        || disjoint(&between.target, &absorbed.target, separator)
+                });
+                let target = (kept.effect == absorbed.effect
+                    && kept.reason == absorbed.reason
+                    && kept.condition == absorbed.condition
+                    && kept.cache == absorbed.cache
+                    && movable)
+                    .then(|| merge_targets(&kept.target, &absorbed.target, max_options))
+                    .flatten();
+                match target {
+                    Some(target) => {
+                        let (index, _) = rules.remove(j);
+                        rules[i].1.target = target;
+                        report.merges.push(RuleMerge {
+                            kept: rules[i].0,
+                            absorbed: index,
+                        });
+                    }
+                    None => j += 1,
+                }
+            }
+            i += 1;
+        }
+
+        report.rules_after = rules.len();
+        if report.is_unchanged() {
+            return (self, report);
+        }
+        let rules = rules.into_iter().map(|(_, rule)| rule).collect();
+        (self.with_rules(rules), report)
+    }
+}
+
+/// The values a matcher accepts, if it is a finite list.
+pub(crate) fn finite<'a>(matcher: &Matcher<'a>) -> Option<Vec<&'a str>> {
+    match matcher {
+        Matcher::Exact(value) => Some(alloc::vec![*value]),
+        Matcher::OneOf(options) => Some(options.to_vec()),
+        Matcher::OneOfVec(options) => Some(options.clone()),
+        Matcher::Any | Matcher::Group(_) => None,
+    }
+}
+
+/// A target matching exactly what `a` or `b` matches, if one exists with at
+/// most `max_options` options per field.
+fn merge_targets<'a>(a: &Target<'a>, b: &Target<'a>, max_options: usize) -> Option<Target<'a>> {
+    let fields = [
+        (&a.principal, &b.principal),
+        (&a.action, &b.action),
+        (&a.resource, &b.resource),
+    ];
+    let mut differing = fields.iter().enumerate().filter(|(_, (x, y))| x != y);
+    let Some((field, (x, y))) = differing.next() else {
+        return Some(a.clone());
+    };
+    if differing.next().is_some() {
+        return None;
+    }
+
+    let mut options = finite(x)?;
+    for value in finite(y)? {
+        if !options.contains(&value) {
+            options.push(value);
+        }
+    }
+    if options.len() > max_options {
+        return None;
+    }
+    let matcher = match options[..] {
+        [value] => Matcher::Exact(value),
+        _ => Matcher::OneOfVec(options),
+    };
+
+    let mut merged = a.clone();
+    match field {
+        0 => merged.principal = matcher,
+        1 => merged.action = matcher,
+        _ => merged.resource = matcher,
+    }
+    Some(merged)
+}
+
+/// Returns `true` if no request can match both targets.
+fn disjoint(a: &Target<'_>, b: &Target<'_>, separator: Option<char>) -> bool {
+    let exclusive = |x: &Matcher<'_>, y: &Matcher<'_>, sep: Option<char>| {
+        let (Some(xs), Some(ys)) = (finite(x), finite(y)) else {
+            return false;
+        };
+        // With a hierarchy, a value also matches its descendants
+        let overlap = |p: &str, q: &str| {
+            p == q
+                || sep.is_some_and(|sep| {
+                    let nested = |outer: &str, inner: &str| {
+                        inner
+                            .strip_prefix(outer)
+                            .is_some_and(|rest| rest.starts_with(sep))
+                    };
+                    nested(p, q) || nested(q, p)
+                })
+        };
+        xs.iter().all(|p| ys.iter().all(|q| !overlap(p, q)))
+    };
+    exclusive(&a.principal, &b.principal, None)
+        || exclusive(&a.action, &b.action, None)
+        || exclusive(&a.resource, &b.resource, separator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CacheHint, Condition, Domain, Effect, ReasonCode, Value};
+    use alloc::vec;
+
+    fn target(
+        principal: Matcher<'static>,
+        action: Matcher<'static>,
+        resource: Matcher<'static>,
+    ) -> Target<'static> {
+        Target {
+            principal,
+            action,
+            resource,
+        }
+    }
+
+    /// Every request in a small domain gets the same result from both.
+    fn assert_equivalent(a: &Policy<'_>, b: &Policy<'_>) {
+        let domain = Domain::new()
+            .principals(&["alice", "bob", "carol", "dave"])
+            .actions(&["read", "write"])
+            .resources(&["docs", "docs/a", "wiki"])
+            .attr("mfa", &[Value::Bool(true), Value::Bool(false)]);
+        for (x, y) in a.what_if(&domain).zip(b.what_if(&domain)) {
+            assert_eq!(x, y);
+        }
+    }
+
+    #[test]
+    fn test_merges_across_provably_unaffected_rules() {
+        let mfa = Some(Condition::Equals {
+            attr: "mfa",
+            value: Value::Bool(true),
+        });
+        let rules = vec![
+            Rule::allow(
+                target(
+                    Matcher::Exact("alice"),
+                    Matcher::Exact("read"),
+                    Matcher::Any,
+                ),
+                ReasonCode(1),
+            ),
+            // Different reason, but never matches bob
+            Rule::allow(
+                target(Matcher::Exact("carol"), Matcher::Any, Matcher::Any),
+                ReasonCode(2),
+            ),
+            Rule::deny(
+                target(Matcher::Exact("dave"), Matcher::Any, Matcher::Any),
+                ReasonCode(3),
+            ),
+            Rule::allow(
+                target(Matcher::Exact("bob"), Matcher::Exact("read"), Matcher::Any),
+                ReasonCode(1),
+            ),
+            Rule::new(
+                Effect::Deny,
+                target(
+                    Matcher::Any,
+                    Matcher::Exact("write"),
+                    Matcher::Exact("docs"),
+                ),
+                mfa.clone(),
+                ReasonCode(4),
+            ),
+            Rule::new(
+                Effect::Deny,
+                target(
+                    Matcher::Any,
+                    Matcher::Exact("write"),
+                    Matcher::Exact("wiki"),
+                ),
+                mfa,
+                ReasonCode(4),
+            ),
+        ];
+        let build = || {
+            Policy::new(rules.clone())
+                .unwrap()
+                .with_resource_separator('/')
+        };
+        let original = build();
+        let (optimized, report) = build().optimize();
+
+        assert_eq!(
+            report.merges,
+            [
+                RuleMerge {
+                    kept: 0,
+                    absorbed: 3
+                },
+                RuleMerge {
+                    kept: 4,
+                    absorbed: 5
+                },
+            ]
+        );
+        assert_eq!(report.rules_after, 4);
+        assert_eq!(
+            optimized.rules()[0].target.principal,
+            Matcher::OneOfVec(vec!["alice", "bob"])
+        );
+        assert_ne!(original.fingerprint(), optimized.fingerprint());
+        assert_equivalent(&original, &optimized);
+    }
+
+    #[test]
+    fn test_refuses_unsafe_moves() {
+        let rules = || {
+            vec![
+                Rule::allow(
+                    target(
+                        Matcher::Exact("alice"),
+                        Matcher::Any,
+                        Matcher::Exact("docs"),
+                    ),
+                    ReasonCode(1),
+                ),
+                // Overlaps bob's rule below with a different reason
+                Rule::allow(
+                    target(Matcher::Any, Matcher::Any, Matcher::Exact("docs/a")),
+                    ReasonCode(2),
+                ),
+                Rule::allow(
+                    target(Matcher::Exact("bob"), Matcher::Any, Matcher::Exact("docs")),
+                    ReasonCode(1),
+                ),
+            ]
+        };
+
+        // Through the hierarchy, "docs" covers "docs/a"
+        let policy = Policy::new(rules()).unwrap().with_resource_separator('/');
+        let (optimized, report) = policy.optimize();
+        assert!(report.is_unchanged());
+        assert_eq!(optimized.rule_count(), 3);
+
+        // Without it, the two resources never meet and the merge is safe
+        let flat = Policy::new(rules()).unwrap();
+        let (optimized, report) = Policy::new(rules()).unwrap().optimize();
+        assert_eq!(report.rules_after, 2);
+        assert_equivalent(&flat, &optimized);
+
+        // A merged rule could only carry one of two different cache hints
+        let hinted = Policy::new(vec![
+            Rule::allow(
+                target(Matcher::Exact("alice"), Matcher::Any, Matcher::Any),
+                ReasonCode(1),
+            )
+            .cache(CacheHint::ttl(60)),
+            Rule::allow(
+                target(Matcher::Exact("bob"), Matcher::Any, Matcher::Any),
+                ReasonCode(1),
+            ),
+        ])
+        .unwrap();
+        assert!(hinted.optimize().1.is_unchanged());
+    }
+}
@@ -0,0 +1,1053 @@
+//! Owned policy types for runtime-loaded policies.
+//!
+//! The core types borrow their strings, which is ideal for policies written
+//! in code but awkward for policies read from a config file: the only way to
+//! get a `Policy<'static>` out of a `String` is to leak it.
+//!
+//! The types here own their data. An `OwnedPolicy` lends out a borrowed
+//! `Policy<'_>` that is evaluated exactly like any other policy, so there is
+//! a single evaluator and a single set of semantics.
+//!
+//! # JSON
+//!
+//! With the `serde` feature the owned types (de)serialize, giving runtime
+//! policies a stable interchange format for bindings and tools:
+//!
+//! ```json
+//! {
+//!   "rules": [
+//!     {
+//!       "effect": "allow",
+//!       "target": { "principal": { "exact": "alice" }, "action": "any", "resource": "any" },
+//!       "condition": { "equals": { "attr": "mfa", "value": true } },
+//!       "reason": 1
+//!     }
+//!   ],
+//!   "config": { "max_rules": 100 }
+//! }
+//! ```
+//!
+//! Matchers are `"any"`, `{"exact": ..}`, `{"one_of": [..]}`, or
+//! `{"group": ..}`; conditions are `"true"`, `"false"`, `{"equals": {attr,
+//! value}}`, `{"not_equals": ..}`, `{"greater_than": ..}`, `{"less_than": ..}`,
+//! `{"ip_in_cidr": {attr, cidr}}`,
+//! `{"time_between": {attr, start, end}}` (seconds since midnight),
+//! `{"day_of_week": {attr, days: ["mon", ..]}}`, `{"custom": {attr, name}}`
+//! (with `custom-predicates`), `{"and": [a, b]}`,
+//! `{"or": [a, b]}`, or `{"not": c}`; values are plain JSON booleans,
+//! integers, and strings, or `{"ip": ..}` for addresses and
+//! `{"timestamp": ..}` and `{"duration": ..}` (seconds) for times.
+//! Rules may carry a `"name"` for audits and a `"cache"` hint, `{"ttl":
+//! 300, "per_context": true}` (either field may be omitted). `name`,
+//! `cache`, `condition`, and `config` (and any config field) may be
+//! omitted. Limits are checked by `policy()`, not while parsing.
+//!
+//! # Sharing
+//!
+//! A `Policy<'_>` borrows its `OwnedPolicy`, so the two cannot be stored
+//! side by side in one value without help. `SharedPolicy` is that help: it
+//! owns both, builds the policy once, and hands out `&Policy<'_>` for as
+//! long as it lives. Bindings and servers keep one per loaded policy.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::net::IpAddr;
+use core::ptr::NonNull;
+
+use crate::condition::Condition;
+use crate::error::PolicyError;
+use crate::policy::{Policy, PolicyConfig, Rule};
+#[cfg(feature = "custom-predicates")]
+use crate::predicates::PredicateRegistry;
+use crate::target::{Matcher, Target};
+use crate::time::Weekdays;
+use crate::types::{CacheHint, Effect, ReasonCode};
+use crate::value::Value;
+
+/// Owned counterpart of `Value`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OwnedValue {
+    /// Boolean value.
+    Bool(bool),
+    /// 64-bit signed integer.
+    Int(i64),
+    /// Owned string.
+    String(String),
+    /// IP address.
+    Ip(IpAddr),
+    /// Seconds since the Unix epoch, UTC.
+    Timestamp(i64),
+    /// A length of time in whole seconds.
+    Duration(u64),
+}
+
+impl OwnedValue {
+    /// Borrow this value as a `Value`.
+    pub fn as_value(&self) -> Value<'_> {
+        match self {
+            OwnedValue::Bool(b) => Value::Bool(*b),
+            OwnedValue::Int(i) => Value::Int(*i),
+            OwnedValue::String(s) => Value::String(s),
+            OwnedValue::Ip(ip) => Value::Ip(*ip),
+            OwnedValue::Timestamp(t) => Value::Timestamp(*t),
+            OwnedValue::Duration(d) => Value::Duration(*d),
+        }
+    }
+}
+
+impl From<bool> for OwnedValue {
+    fn from(b: bool) -> Self {
+        OwnedValue::Bool(b)
+    }
+}
+
+impl From<i64> for OwnedValue {
+    fn from(i: i64) -> Self {
+        OwnedValue::Int(i)
+    }
+}
+
+impl From<&str> for OwnedValue {
+    fn from(s: &str) -> Self {
+        OwnedValue::String(s.to_string())
+    }
+}
+
+impl From<IpAddr> for OwnedValue {
+    fn from(ip: IpAddr) -> Self {
+        OwnedValue::Ip(ip)
+    }
+}
+
+impl From<String> for OwnedValue {
+    fn from(s: String) -> Self {
+        OwnedValue::String(s)
+    }
+}
+
+impl From<Value<'_>> for OwnedValue {
+    fn from(value: Value<'_>) -> Self {
+        OwnedValue::from(&value)
+    }
+}
+
+impl From<&Value<'_>> for OwnedValue {
+    fn from(value: &Value<'_>) -> Self {
+        match value {
+            Value::Bool(b) => OwnedValue::Bool(*b),
+            Value::Int(i) => OwnedValue::Int(*i),
+            Value::String(s) => OwnedValue::String((*s).to_string()),
+            Value::Ip(ip) => OwnedValue::Ip(*ip),
+            Value::Timestamp(t) => OwnedValue::Timestamp(*t),
+            Value::Duration(d) => OwnedValue::Duration(*d),
+        }
+    }
+}
+
+/// Owned counterpart of `Matcher`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OwnedMatcher {
+    /// Matches any value.
+    Any,
+    /// Matches exactly the specified string.
+    Exact(String),
+    /// Matches any value in the list.
+    OneOf(Vec<String>),
+    /// Matches members of the group.
+    Group(String),
+}
+
+impl OwnedMatcher {
+    /// Borrow this matcher as a `Matcher`.
+    ///
+    /// `OneOf` lists are lowered to `Matcher::OneOfVec`, which allocates the
+    /// list of borrowed options once.
+    pub fn as_matcher(&self) -> Matcher<'_> {
+        match self {
+            OwnedMatcher::Any => Matcher::Any,
+            OwnedMatcher::Exact(s) => Matcher::Exact(s),
+            OwnedMatcher::OneOf(options) => {
+                Matcher::OneOfVec(options.iter().map(String::as_str).collect())
+            }
+            OwnedMatcher::Group(group) => Matcher::Group(group),
+        }
+    }
+}
+
+impl From<&Matcher<'_>> for OwnedMatcher {
+    fn from(matcher: &Matcher<'_>) -> Self {
+        match matcher {
+            Matcher::Any => OwnedMatcher::Any,
+            Matcher::Exact(s) => OwnedMatcher::Exact((*s).to_string()),
+            Matcher::OneOf(options) => {
+                OwnedMatcher::OneOf(options.iter().map(|s| (*s).to_string()).collect())
+            }
+            Matcher::OneOfVec(options) => {
+                OwnedMatcher::OneOf(options.iter().map(|s| (*s).to_string()).collect())
+            }
+            Matcher::Group(group) => OwnedMatcher::Group((*group).to_string()),
+        }
+    }
+}
+
+/// Owned counterpart of `Target`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct OwnedTarget {
+    /// Matcher for the principal.
+    pub principal: OwnedMatcher,
+    /// Matcher for the action.
+    pub action: OwnedMatcher,
+    /// Matcher for the resource.
+    pub resource: OwnedMatcher,
+}
+
+impl OwnedTarget {
+    /// Create a target that matches everything.
+    pub fn any() -> Self {
+        OwnedTarget {
+            principal: OwnedMatcher::Any,
+            action: OwnedMatcher::Any,
+            resource: OwnedMatcher::Any,
+        }
+    }
+
+    /// Borrow this target as a `Target`.
+    pub fn as_target(&self) -> Target<'_> {
+        Target {
+            principal: self.principal.as_matcher(),
+            action: self.action.as_matcher(),
+            resource: self.resource.as_matcher(),
+        }
+    }
+}
+
+impl From<&Target<'_>> for OwnedTarget {
+    fn from(target: &Target<'_>) -> Self {
+        OwnedTarget {
+            principal: OwnedMatcher::from(&target.principal),
+            action: OwnedMatcher::from(&target.action),
+            resource: OwnedMatcher::from(&target.resource),
+        }
+    }
+}
+
+/// Owned counterpart of `Condition`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OwnedCondition {
+    /// Always evaluates to true.
+    True,
+    /// Always evaluates to false.
+    False,
+    /// True if the attribute equals the value.
+    Equals {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The value to compare against.
+        value: OwnedValue,
+    },
+    /// True if the attribute does not equal the value.
+    NotEquals {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The value to compare against.
+        value: OwnedValue,
+    },
+    /// True if the attribute is greater than the value; see
+    /// `Condition::GreaterThan`.
+    GreaterThan {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The value to compare against.
+        value: OwnedValue,
+    },
+    /// True if the attribute is less than the value.
+    LessThan {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The value to compare against.
+        value: OwnedValue,
+    },
+    /// True if the attribute is an IP address inside the CIDR block.
+    IpInCidr {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The CIDR block, as text.
+        cidr: String,
+    },
+    /// True if the attribute is a timestamp whose time of day is in the
+    /// window; see `Condition::TimeBetween`.
+    TimeBetween {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The first second of the window, since midnight.
+        start: u32,
+        /// The first second after the window, since midnight.
+        end: u32,
+    },
+    /// True if the attribute is a timestamp on one of the days.
+    DayOfWeek {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The days that match.
+        days: Weekdays,
+    },
+    /// True if the named predicate holds for the attribute; see
+    /// `Condition::Custom`.
+    #[cfg(feature = "custom-predicates")]
+    Custom {
+        /// The attribute name to look up in context.
+        attr: String,
+        /// The registered predicate's name.
+        name: String,
+    },
+    /// True if both conditions are true.
+    And(Box<OwnedCondition>, Box<OwnedCondition>),
+    /// True if either condition is true.
+    Or(Box<OwnedCondition>, Box<OwnedCondition>),
+    /// True if the inner condition is false.
+    Not(Box<OwnedCondition>),
+}
+
+impl OwnedCondition {
+    /// Borrow this condition as a `Condition`.
+    ///
+    /// This implementation is non-recursive, so it is safe to call on
+    /// unvalidated trees. Depth limits are enforced when the resulting
+    /// condition is built into a `Policy`.
+    pub fn as_condition(&self) -> Condition<'_> {
+        enum Work<'b> {
+            Visit(&'b OwnedCondition),
+            BuildNot,
+            BuildAnd,
+            BuildOr,
+        }
+
+        let mut stack = vec![Work::Visit(self)];
+        let mut results: Vec<Condition<'_>> = Vec::new();
+
+        while let Some(item) = stack.pop() {
+            match item {
+                Work::Visit(cond) => match cond {
+                    OwnedCondition::True => results.push(Condition::True),
+                    OwnedCondition::False => results.push(Condition::False),
+                    OwnedCondition::Equals { attr, value } => results.push(Condition::Equals {
+                        attr,
+                        value: value.as_value(),
+                    }),
+                    OwnedCondition::NotEquals { attr, value } => {
+                        results.push(Condition::NotEquals {
+                            attr,
+                            value: value.as_value(),
+                        })
+                    }
+                    OwnedCondition::GreaterThan { attr, value } => {
+                        results.push(Condition::GreaterThan {
+                            attr,
+                            value: value.as_value(),
+                        })
+                    }
+                    OwnedCondition::LessThan { attr, value } => results.push(Condition::LessThan {
+                        attr,
+                        value: value.as_value(),
+                    }),
+                    OwnedCondition::IpInCidr { attr, cidr } => {
+                        results.push(Condition::IpInCidr { attr, cidr })
+                    }
+                    OwnedCondition::TimeBetween { attr, start, end } => {
+                        results.push(Condition::TimeBetween {
+                            attr,
+                            start: *start,
+                            end: *end,
+                        })
+                    }
+                    OwnedCondition::DayOfWeek { attr, days } => {
+                        results.push(Condition::DayOfWeek { attr, days: *days })
+                    }
+                    #[cfg(feature = "custom-predicates")]
+                    OwnedCondition::Custom { attr, name } => {
+                        results.push(Condition::Custom { attr, name })
+                    }
+                    OwnedCondition::Not(inner) => {
+                        stack.push(Work::BuildNot);
+                        stack.push(Work::Visit(inner));
+                    }
+                    OwnedCondition::And(a, b) => {
+                        stack.push(Work::BuildAnd);
+                        stack.push(Work::Visit(b));
+                        stack.push(Work::Visit(a));
+                    }
+                    OwnedCondition::Or(a, b) => {
+                        stack.push(Work::BuildOr);
+                        stack.push(Work::Visit(b));
+                        stack.push(Work::Visit(a));
+                    }
+                },
+                Work::BuildNot => {
+                    let inner = results.pop().unwrap_or(Condition::False);
+                    results.push(Condition::Not(Box::new(inner)));
+                }
+                Work::BuildAnd | Work::BuildOr => {
+                    let b = results.pop().unwrap_or(Condition::False);
+                    let a = results.pop().unwrap_or(Condition::False);
+                    results.push(if matches!(item, Work::BuildAnd) {
+                        Condition::And(Box::new(a), Box::new(b))
+                    } else {
+                        Condition::Or(Box::new(a), Box::new(b))
+                    });
+                }
+            }
+        }
+
+        results.pop().unwrap_or(Condition::False)
+    }
+}
+
+impl From<&Condition<'_>> for OwnedCondition {
+    /// Non-recursive, mirroring `OwnedCondition::as_condition`.
+    fn from(cond: &Condition<'_>) -> Self {
+        enum Work<'a, 'b> {
+            Visit(&'b Condition<'a>),
+            BuildNot,
+            BuildAnd,
+            BuildOr,
+        }
+
+        let mut stack = vec![Work::Visit(cond)];
+        let mut results: Vec<OwnedCondition> = Vec::new();
+
+        while let Some(item) = stack.pop() {
+            match item {
+                Work::Visit(cond) => match cond {
+                    Condition::True => results.push(OwnedCondition::True),
+                    Condition::False => results.push(OwnedCondition::False),
+                    Condition::Equals { attr, value } => results.push(OwnedCondition::Equals {
+                        attr: (*attr).to_string(),
+                        value: OwnedValue::from(value),
+                    }),
+                    Condition::NotEquals { attr, value } => {
+                        results.push(OwnedCondition::NotEquals {
+                            attr: (*attr).to_string(),
+                            value: OwnedValue::from(value),
+                        })
+                    }
+                    Condition::GreaterThan { attr, value } => {
+                        results.push(OwnedCondition::GreaterThan {
+                            attr: (*attr).to_string(),
+                            value: OwnedValue::from(value),
+                        })
+                    }
+                    Condition::LessThan { attr, value } => results.push(OwnedCondition::LessThan {
+                        attr: (*attr).to_string(),
+                        value: OwnedValue::from(value),
+                    }),
+                    Condition::IpInCidr { attr, cidr } => results.push(OwnedCondition::IpInCidr {
+                        attr: (*attr).to_string(),
+                        cidr: (*cidr).to_string(),
+                    }),
+                    Condition::TimeBetween { attr, start, end } => {
+                        results.push(OwnedCondition::TimeBetween {
+                            attr: (*attr).to_string(),
+                            start: *start,
+                            end: *end,
+                        })
+                    }
+                    Condition::DayOfWeek { attr, days } => {
+                        results.push(OwnedCondition::DayOfWeek {
+                            attr: (*attr).to_string(),
+                            days: *days,
+                        })
+                    }
+                    #[cfg(feature = "custom-predicates")]
+                    Condition::Custom { attr, name } => results.push(OwnedCondition::Custom {
+                        attr: (*attr).to_string(),
+                        name: (*name).to_string(),
+                    }),
+                    Condition::Not(inner) => {
+                        stack.push(Work::BuildNot);
+                        stack.push(Work::Visit(inner));
+                    }
+                    Condition::And(a, b) => {
+                        stack.push(Work::BuildAnd);
+                        stack.push(Work::Visit(b));
+                        stack.push(Work::Visit(a));
+                    }
+                    Condition::Or(a, b) => {
+                        stack.push(Work::BuildOr);
+                        stack.push(Work::Visit(b));
+                        stack.push(Work::Visit(a));
+                    }
+                },
+                Work::BuildNot => {
+                    let inner = results.pop().unwrap_or(OwnedCondition::False);
+                    results.push(OwnedCondition::Not(Box::new(inner)));
+                }
+                Work::BuildAnd | Work::BuildOr => {
+                    let b = results.pop().unwrap_or(OwnedCondition::False);
+                    let a = results.pop().unwrap_or(OwnedCondition::False);
+                    results.push(if matches!(item, Work::BuildAnd) {
+                        OwnedCondition::And(Box::new(a), Box::new(b))
+                    } else {
+                        OwnedCondition::Or(Box::new(a), Box::new(b))
+                    });
+                }
+            }
+        }
+
+        results.pop().unwrap_or(OwnedCondition::False)
+    }
+}
+
+/// Manual Drop implementation to prevent stack overflows on deep trees.
+impl Drop for OwnedCondition {
+    fn drop(&mut self) {
+        let mut stack = Vec::new();
+
+        match self {
+            OwnedCondition::And(a, b) | OwnedCondition::Or(a, b) => {
+                stack.push(core::mem::replace(a, Box::new(OwnedCondition::True)));
+                stack.push(core::mem::replace(b, Box::new(OwnedCondition::True)));
+            }
+            OwnedCondition::Not(inner) => {
+                stack.push(core::mem::replace(inner, Box::new(OwnedCondition::True)));
+            }
+            _ => return,
+        }
+
+        while let Some(mut boxed_cond) = stack.pop() {
+            match *boxed_cond {
+                OwnedCondition::And(ref mut a, ref mut b)
+                | OwnedCondition::Or(ref mut a, ref mut b) => {
+                    stack.push(core::mem::replace(a, Box::new(OwnedCondition::True)));
+                    stack.push(core::mem::replace(b, Box::new(OwnedCondition::True)));
+                }
+                OwnedCondition::Not(ref mut inner) => {
+                    stack.push(core::mem::replace(inner, Box::new(OwnedCondition::True)));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Owned counterpart of `Rule`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct OwnedRule {
+    /// The effect if this rule matches (Allow or Deny).
+    pub effect: Effect,
+    /// The target that determines if this rule applies.
+    pub target: OwnedTarget,
+    /// Optional condition for additional matching logic.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub condition: Option<OwnedCondition>,
+    /// The reason code for this rule's decision.
+    pub reason: ReasonCode,
+    /// Optional name for audits; see `Rule::name`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub name: Option<String>,
+    /// Optional cache hint; see `Rule::cache`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub cache: Option<CacheHint>,
+}
+
+impl OwnedRule {
+    /// Create a new rule.
+    pub fn new(
+        effect: Effect,
+        target: OwnedTarget,
+        condition: Option<OwnedCondition>,
+        reason: ReasonCode,
+    ) -> Self {
+        OwnedRule {
+            effect,
+            target,
+            condition,
+            reason,
+            name: None,
+            cache: None,
+        }
+    }
+
+    /// Name this rule.
+    pub fn named(mut self, name: &str) -> Self {
+        self.name = Some(name.to_string());
+        self
+    }
+
+    /// Declare a cache hint; see `Rule::cache`.
+    pub fn cache(mut self, hint: CacheHint) -> Self {
+        self.cache = Some(hint);
+        self
+    }
+
+    /// Create an Allow rule with no condition.
+    pub fn allow(target: OwnedTarget, reason: ReasonCode) -> Self {
+        OwnedRule::new(Effect::Allow, target, None, reason)
+    }
+
+    /// Create a Deny rule with no condition.
+    pub fn deny(target: OwnedTarget, reason: ReasonCode) -> Self {
+        OwnedRule::new(Effect::Deny, target, None, reason)
+    }
+
+    /// Borrow this rule as a `Rule`.
+    pub fn as_rule(&self) -> Rule<'_> {
+        Rule {
+            name: self.name.as_deref(),
+            cache: self.cache,
+            ..Rule::new(
+                self.effect,
+                self.target.as_target(),
+                self.condition.as_ref().map(OwnedCondition::as_condition),
+                self.reason,
+            )
+        }
+    }
+}
+
+impl From<&Rule<'_>> for OwnedRule {
+    fn from(rule: &Rule<'_>) -> Self {
+        OwnedRule {
+            effect: rule.effect,
+            target: OwnedTarget::from(&rule.target),
+            condition: rule.condition.as_ref().map(OwnedCondition::from),
+            reason: rule.reason,
+            name: rule.name.map(str::to_string),
+            cache: rule.cache,
+        }
+    }
+}
+
+/// A policy that owns all of its strings.
+///
+/// Build one from data loaded at runtime, then call `policy()` to obtain a
+/// borrowed `Policy<'_>` for evaluation:
+///
+/// ```
+/// use gate0::{OwnedMatcher, OwnedPolicy, OwnedRule, OwnedTarget, ReasonCode, Request};
+///
+/// // e.g. read from a config file
+/// let user = String::from("alice");
+///
+/// let owned = OwnedPolicy::new(vec![OwnedRule::allow(
+///     OwnedTarget {
+///         principal: OwnedMatcher::Exact(user),
+///         action: OwnedMatcher::OneOf(vec!["read".into(), "list".into()]),
+///         resource: OwnedMatcher::Any,
+///     },
+///     ReasonCode(1),
+/// )]);
+///
+/// let policy = owned.policy().expect("valid policy");
+/// let decision = policy.evaluate(&Request::new("alice", "read", "doc")).unwrap();
+/// assert!(decision.is_allow());
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct OwnedPolicy {
+    rules: Vec<OwnedRule>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    config: PolicyConfig,
+}
+
+impl OwnedPolicy {
+    /// Create an owned policy with the given rules and default config.
+    pub fn new(rules: Vec<OwnedRule>) -> Self {
+        Self::with_config(rules, PolicyConfig::default())
+    }
+
+    /// Create an owned policy with the given rules and config.
+    ///
+    /// Limits are checked when the policy is borrowed via `policy()`.
+    pub fn with_config(rules: Vec<OwnedRule>, config: PolicyConfig) -> Self {
+        OwnedPolicy { rules, config }
+    }
+
+    /// Get the number of rules in this policy.
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Get a reference to the rules in this policy.
+    pub fn rules(&self) -> &[OwnedRule] {
+        &self.rules
+    }
+
+    /// Get the configuration for this policy.
+    pub fn config(&self) -> &PolicyConfig {
+        &self.config
+    }
+
+    /// Build a borrowed `Policy` from this owned policy.
+    ///
+    /// Runs the same validation as `Policy::with_config`. Build once and keep
+    /// the result for as long as `self` lives; evaluation itself stays
+    /// allocation-free.
+    pub fn policy(&self) -> Result<Policy<'_>, PolicyError> {
+        let rules = self.rules.iter().map(OwnedRule::as_rule).collect();
+        Policy::with_config(rules, self.config)
+    }
+
+    /// Build a borrowed `Policy` whose `Custom` conditions are resolved with
+    /// `predicates` (see `PolicyBuilder::predicates`).
+    #[cfg(feature = "custom-predicates")]
+    pub fn policy_with_predicates<'p>(
+        &'p self,
+        predicates: PredicateRegistry<'p>,
+    ) -> Result<Policy<'p>, PolicyError> {
+        Policy::builder()
+            .config(self.config)
+            .rules_from_iter(self.rules.iter().map(OwnedRule::as_rule))
+            .predicates(predicates)
+            .build()
+    }
+}
+
+/// An `OwnedPolicy` together with the `Policy` built from it.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Effect, OwnedMatcher, OwnedPolicy, OwnedRule, OwnedTarget, ReasonCode, Request, SharedPolicy};
+///
+/// let target = OwnedTarget {
+///     principal: OwnedMatcher::Exact("alice".to_string()),
+///     action: OwnedMatcher::Any,
+///     resource: OwnedMatcher::Any,
+/// };
+/// let owned = OwnedPolicy::new(vec![OwnedRule::new(Effect::Allow, target, None, ReasonCode(1))]);
+/// let shared = SharedPolicy::new(owned).unwrap();
+///
+/// let decision = shared.policy().evaluate(&Request::new("alice", "read", "doc")).unwrap();
+/// assert!(decision.is_allow());
+/// assert_eq!(shared.owned().rules().len(), 1);
+/// ```
+pub struct SharedPolicy {
+    // Borrows from the allocation behind `owned`, which `Drop` frees last.
+    // A `Box` would assert unique access when moved, invalidating the
+    // borrows, so the allocation is held as a raw pointer.
+    policy: ManuallyDrop<Policy<'static>>,
+    owned: NonNull<OwnedPolicy>,
+}
+
+// SAFETY: `owned` is never mutated after construction and is only freed in
+// `Drop`, so the handle is a `&OwnedPolicy` and a `Policy` borrowing it,
+// both of which are `Send` and `Sync` (checked below).
+unsafe impl Send for SharedPolicy {}
+unsafe impl Sync for SharedPolicy {}
+
+const _: () = {
+    const fn thread_safe<T: Send + Sync>() {}
+    thread_safe::<OwnedPolicy>();
+    thread_safe::<Policy<'static>>();
+};
+
+impl SharedPolicy {
+    /// Take ownership of `owned` and build its policy (see
+    /// `OwnedPolicy::policy`).
+    pub fn new(owned: OwnedPolicy) -> Result<Self, PolicyError> {
+        Self::build(owned, OwnedPolicy::policy)
+    }
+
+    /// Take ownership of `owned` and build its policy with `predicates`
+    /// (see `OwnedPolicy::policy_with_predicates`).
+    #[cfg(feature = "custom-predicates")]
+    pub fn with_predicates(
+        owned: OwnedPolicy,
+        predicates: PredicateRegistry<'static>,
+    ) -> Result<Self, PolicyError> {
+        Self::build(owned, |owned| owned.policy_with_predicates(predicates))
+    }
+
+    fn build(
+        owned: OwnedPolicy,
+        build: impl FnOnce(&'static OwnedPolicy) -> Result<Policy<'static>, PolicyError>,
+    ) -> Result<Self, PolicyError> {
+        let owned = NonNull::from(Box::leak(Box::new(owned)));
+        // SAFETY: the allocation lives until `Drop`, after `policy` is gone,
+        // and `policy()` and `owned()` shorten the borrow to `&self`
+        let borrowed: &'static OwnedPolicy = unsafe { owned.as_ref() };
+        match build(borrowed) {
+            Ok(policy) => Ok(SharedPolicy {
+                policy: ManuallyDrop::new(policy),
+                owned,
+            }),
+            Err(error) => {
+                // SAFETY: the failed build left no borrows behind
+                drop(unsafe { Box::from_raw(owned.as_ptr()) });
+                Err(error)
+            }
+        }
+    }
+
+    /// The built policy.
+    pub fn policy(&self) -> &Policy<'_> {
+        &self.policy
+    }
+
+    /// The owned policy it was built from.
+    pub fn owned(&self) -> &OwnedPolicy {
+        // SAFETY: the allocation lives as long as `self`
+        unsafe { self.owned.as_ref() }
+    }
+}
+
+impl fmt::Debug for SharedPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedPolicy")
+            .field("owned", self.owned())
+            .field("fingerprint", &self.policy.fingerprint())
+            .finish()
+    }
+}
+
+impl Drop for SharedPolicy {
+    fn drop(&mut self) {
+        // SAFETY: drop the borrower first, then the allocation it borrows
+        unsafe {
+            ManuallyDrop::drop(&mut self.policy);
+            drop(Box::from_raw(self.owned.as_ptr()));
+        }
+    }
+}
+
+impl From<&Policy<'_>> for OwnedPolicy {
+    fn from(policy: &Policy<'_>) -> Self {
+        OwnedPolicy {
+            rules: policy.rules().iter().map(OwnedRule::from).collect(),
+            config: *policy.config(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{Location, RulePart};
+    use crate::types::Request;
+
+    #[test]
+    fn test_owned_policy_from_runtime_strings() {
+        // Strings that only exist at runtime
+        let principal = String::from("alice");
+        let attr = String::from("role");
+        let role = String::from("admin");
+
+        let owned = OwnedPolicy::new(vec![OwnedRule::new(
+            Effect::Allow,
+            OwnedTarget {
+                principal: OwnedMatcher::Exact(principal),
+                action: OwnedMatcher::OneOf(vec!["read".to_string(), "write".to_string()]),
+                resource: OwnedMatcher::Any,
+            },
+            Some(OwnedCondition::Equals {
+                attr,
+                value: OwnedValue::String(role),
+            }),
+            ReasonCode(7),
+        )]);
+
+        let policy = owned.policy().unwrap();
+
+        let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+        let decision = policy
+            .evaluate(&Request::with_context("alice", "write", "doc", ctx))
+            .unwrap();
+        assert!(decision.is_allow());
+        assert_eq!(decision.reason, ReasonCode(7));
+
+        let decision = policy
+            .evaluate(&Request::with_context("alice", "delete", "doc", ctx))
+            .unwrap();
+        assert!(decision.is_deny());
+    }
+
+    #[test]
+    fn test_owned_policy_validates_limits() {
+        let config = PolicyConfig {
+            max_matcher_options: 1,
+            ..PolicyConfig::default()
+        };
+        let owned = OwnedPolicy::with_config(
+            vec![OwnedRule::allow(
+                OwnedTarget {
+                    principal: OwnedMatcher::OneOf(vec!["a".into(), "b".into()]),
+                    action: OwnedMatcher::Any,
+                    resource: OwnedMatcher::Any,
+                },
+                ReasonCode(1),
+            )],
+            config,
+        );
+
+        let expected = PolicyError::TooManyMatcherOptions {
+            max: 1,
+            actual: 2,
+            location: Some(Location {
+                rule: Some(0),
+                part: RulePart::Principal,
+            }),
+        };
+        assert_eq!(owned.policy().unwrap_err(), expected);
+        assert_eq!(SharedPolicy::new(owned).unwrap_err(), expected);
+    }
+
+    #[test]
+    fn test_shared_policy_outlives_its_builder() {
+        let owned = OwnedPolicy::new(vec![OwnedRule::allow(
+            OwnedTarget {
+                principal: OwnedMatcher::Exact(String::from("alice")),
+                action: OwnedMatcher::Any,
+                resource: OwnedMatcher::Any,
+            },
+            ReasonCode(1),
+        )]);
+        let fingerprint = owned.policy().unwrap().fingerprint();
+        let shared = std::sync::Arc::new(SharedPolicy::new(owned.clone()).unwrap());
+
+        // Moved into another thread, away from where it was built
+        let remote = std::sync::Arc::clone(&shared);
+        let decision = std::thread::spawn(move || {
+            remote
+                .policy()
+                .evaluate(&Request::new("alice", "read", "doc"))
+                .unwrap()
+        })
+        .join()
+        .unwrap();
+        assert!(decision.is_allow());
+        assert_eq!(shared.policy().fingerprint(), fingerprint);
+        assert_eq!(shared.owned().rules(), owned.rules());
+    }
+
+    #[test]
+    fn test_owned_round_trip() {
+        let actions: &[&str] = &["read", "list"];
+        let rule = Rule::new(
+            Effect::Deny,
+            Target {
+                principal: Matcher::Exact("bob"),
+                action: Matcher::OneOf(actions),
+                resource: Matcher::Any,
+            },
+            Some(Condition::Or(
+                Box::new(Condition::Not(Box::new(Condition::True))),
+                Box::new(Condition::NotEquals {
+                    attr: "level",
+                    value: Value::Int(3),
+                }),
+            )),
+            ReasonCode(2),
+        );
+
+        let owned = OwnedRule::from(&rule);
+        let borrowed = owned.as_rule();
+
+        assert_eq!(borrowed.effect, rule.effect);
+        assert_eq!(borrowed.reason, rule.reason);
+        assert_eq!(borrowed.condition, rule.condition);
+        assert!(borrowed.target.matches("bob", "list", "x"));
+        assert!(!borrowed.target.matches("bob", "write", "x"));
+        assert_eq!(OwnedRule::from(&borrowed), owned);
+    }
+
+    #[test]
+    fn test_owned_deep_condition_is_stack_safe() {
+        let mut cond = OwnedCondition::True;
+        for _ in 0..100_000 {
+            cond = OwnedCondition::Not(Box::new(cond));
+        }
+
+        let owned = OwnedPolicy::new(vec![OwnedRule::new(
+            Effect::Allow,
+            OwnedTarget::any(),
+            Some(cond),
+            ReasonCode(1),
+        )]);
+
+        // Rejected by the depth limit, and neither lowering nor drop overflows
+        assert!(matches!(
+            owned.policy(),
+            Err(PolicyError::ConditionTooDeep { .. })
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_owned_policy_json() {
+        let json = r#"{
+            "rules": [
+                {
+                    "effect": "deny",
+                    "target": { "principal": { "group": "suspended" }, "action": "any", "resource": "any" },
+                    "reason": 2
+                },
+                {
+                    "effect": "allow",
+                    "target": {
+                        "principal": "any",
+                        "action": { "one_of": ["read", "list"] },
+                        "resource": { "exact": "doc" }
+                    },
+                    "condition": { "and": [
+                        { "and": [
+                            { "equals": { "attr": "mfa", "value": true } },
+                            { "ip_in_cidr": { "attr": "source_ip", "cidr": "10.0.0.0/8" } }
+                        ] },
+                        { "not": { "equals": { "attr": "role", "value": "guest" } } }
+                    ] },
+                    "reason": 1,
+                    "cache": { "ttl": 60 }
+                }
+            ],
+            "config": { "max_rules": 8, "default_effect": "deny" }
+        }"#;
+        let owned: OwnedPolicy = serde_json::from_str(json).unwrap();
+        assert_eq!(owned.rule_count(), 2);
+        assert_eq!(owned.config().max_rules, 8);
+        assert_eq!(
+            owned.config().max_string_len,
+            PolicyConfig::default().max_string_len
+        );
+
+        let policy = owned.policy().unwrap();
+        let ctx: &[(&str, Value)] = &[
+            ("mfa", Value::Bool(true)),
+            ("source_ip", Value::Ip([10, 0, 0, 1].into())),
+        ];
+        let request = crate::Request::with_context("alice", "read", "doc", ctx);
+        assert_eq!(
+            policy.evaluate(&request).unwrap(),
+            crate::Decision::allow(ReasonCode(1)).with_cache(Some(CacheHint::ttl(60)))
+        );
+
+        // Serializing and parsing again gives the same policy
+        let round_trip: OwnedPolicy =
+            serde_json::from_str(&serde_json::to_string(&owned).unwrap()).unwrap();
+        assert_eq!(round_trip.rules(), owned.rules());
+
+        let unknown = r#"{ "rules": [], "config": { "max_rulez": 1 } }"#;
+        assert!(serde_json::from_str::<OwnedPolicy>(unknown).is_err());
+    }
+}
@@ -3,18 +3,54 @@
 //! The core of the authorization system.
 //! Evaluates rules in order, applies deny-overrides conflict resolution.
 
-use crate::condition::Condition;
-use crate::error::PolicyError;
-use crate::target::Target;
-use crate::types::{Decision, Effect, ReasonCode, Request, NO_MATCHING_RULE};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::compiled::{AttrSource, CompiledConditions};
+use crate::condition::{validate_attr, Condition};
+use crate::error::{PolicyError, RulePart};
+use crate::groups::GroupMap;
+use crate::index::{Candidates, TargetIndex};
+use crate::intern::{InternedRequest, InternedTargets, Interner, Symbol};
+use crate::observer::Observer;
+#[cfg(feature = "custom-predicates")]
+use crate::predicates::PredicateRegistry;
+use crate::redundancy::RuleWarning;
+use crate::schema::ContextSchema;
+use crate::sink::{AttachedSink, DecisionSink};
+use crate::stats::{EvaluationStats, Termination};
+use crate::target::{parent, Matcher, Target};
+use crate::types::{
+    CacheHint, Decision, Effect, ReasonCode, Request, DEFAULT_ALLOW, NO_MATCHING_RULE,
+};
 use crate::value::Value;
 
 /// Configuration limits for policy construction and evaluation.
+///
+/// Together these bound everything the engine does with one policy:
+///
+/// | Limit | Bounds | Error |
+/// |---|---|---|
+/// | `max_rules` | rules per policy | `TooManyRules` |
+/// | `max_condition_depth` | nesting of one condition | `ConditionTooDeep` |
+/// | `max_condition_nodes` | condition nodes across the policy | `TooManyConditionNodes` |
+/// | `max_matcher_options` | entries in a `OneOf` list | `TooManyMatcherOptions` |
+/// | `max_string_len` | any string in rules or requests | `StringTooLong` |
+/// | `max_attr_name_len` | attribute names | `AttrNameTooLong` |
+/// | `max_attr_value_len` | string attribute values | `AttrValueTooLong` |
+/// | `max_context_attrs` | context entries per request | `ContextTooLarge` |
+/// | `max_eval_steps` | work per evaluation | `StepBudgetExceeded` |
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct PolicyConfig {
     /// Maximum number of rules allowed in a policy.
     pub max_rules: usize,
     /// Maximum depth of nested conditions (default: 10).
+    ///
+    /// At most `ABSOLUTE_MAX_CONDITION_DEPTH` unless the `heap-stack`
+    /// feature is enabled.
     pub max_condition_depth: usize,
     /// Maximum number of attributes allowed in request context (default: 64).
     pub max_context_attrs: usize,
@@ -22,17 +58,48 @@ pub struct PolicyConfig {
     pub max_matcher_options: usize,
     /// Maximum length of any string identifier or value (default: 256).
     pub max_string_len: usize,
+    /// Maximum evaluation steps per request (default: unbounded).
+    ///
+    /// Checking a rule's target costs one step (plus one per resource
+    /// ancestor walked, if a hierarchy is enabled), and evaluating its
+    /// condition costs one step per node. Exceeding the budget fails evaluation with
+    /// `PolicyError::StepBudgetExceeded`.
+    pub max_eval_steps: usize,
+    /// Maximum total number of condition nodes across all rules (default:
+    /// 65536).
+    pub max_condition_nodes: usize,
+    /// Maximum length of an attribute name, in conditions and in request
+    /// context (default: 256).
+    pub max_attr_name_len: usize,
+    /// Maximum length of a string attribute value, in conditions, request
+    /// context, and values resolved by a `ContextProvider` (default: 256).
+    pub max_attr_value_len: usize,
+    /// Effect when no rule matches (default: `Deny`).
+    ///
+    /// `Deny` decides with `NO_MATCHING_RULE`. `Allow`, for monitoring-only
+    /// deployments, decides with `DEFAULT_ALLOW`.
+    pub default_effect: Effect,
+}
+
+impl PolicyConfig {
+    /// The default config, usable in `const` items.
+    pub const DEFAULT: PolicyConfig = PolicyConfig {
+        max_rules: 1000,
+        max_condition_depth: 10,
+        max_context_attrs: 64,
+        max_matcher_options: 64,
+        max_string_len: 256,
+        max_eval_steps: usize::MAX,
+        max_condition_nodes: 65536,
+        max_attr_name_len: 256,
+        max_attr_value_len: 256,
+        default_effect: Effect::Deny,
+    };
 }
 
 impl Default for PolicyConfig {
     fn default() -> Self {
-        PolicyConfig {
-            max_rules: 1000,
-            max_condition_depth: 10,
-            max_context_attrs: 64,
-            max_matcher_options: 64,
-            max_string_len: 256,
-        }
+        PolicyConfig::DEFAULT
     }
 }
 
@@ -47,11 +114,20 @@ pub struct Rule<'a> {
     pub condition: Option<Condition<'a>>,
     /// The reason code for this rule's decision.
     pub reason: ReasonCode,
+    /// Optional name for audits, e.g. `"admins can do anything"`.
+    ///
+    /// Names do not affect evaluation, the fingerprint, or duplicate
+    /// detection; `Policy::optimize` keeps the name of the rule it merges
+    /// others into.
+    pub name: Option<&'a str>,
+    /// Optional cache hint for decisions this rule can change; see
+    /// `Decision::cache`.
+    pub cache: Option<CacheHint>,
 }
 
 impl<'a> Rule<'a> {
     /// Create a new rule.
-    pub fn new(
+    pub const fn new(
         effect: Effect,
         target: Target<'a>,
         condition: Option<Condition<'a>>,
@@ -62,18 +138,107 @@ impl<'a> Rule<'a> {
             target,
             condition,
             reason,
+            name: None,
+            cache: None,
         }
     }
 
+    /// Name this rule.
+    pub const fn named(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Declare how long decisions this rule can change may be cached.
+    pub const fn cache(mut self, hint: CacheHint) -> Self {
+        self.cache = Some(hint);
+        self
+    }
+
     /// Create an Allow rule with no condition.
-    pub fn allow(target: Target<'a>, reason: ReasonCode) -> Self {
+    pub const fn allow(target: Target<'a>, reason: ReasonCode) -> Self {
         Rule::new(Effect::Allow, target, None, reason)
     }
 
     /// Create a Deny rule with no condition.
-    pub fn deny(target: Target<'a>, reason: ReasonCode) -> Self {
+    pub const fn deny(target: Target<'a>, reason: ReasonCode) -> Self {
         Rule::new(Effect::Deny, target, None, reason)
     }
+
+    /// Check each matcher, then the condition, against the config limits.
+    ///
+    /// Limit errors record that this is rule `index` and where in it.
+    fn checks(&self, index: usize, config: &PolicyConfig) -> [Result<(), PolicyError>; 5] {
+        let matcher = |m: &Matcher<'_>, part| {
+            m.validate(config.max_matcher_options, config.max_string_len)
+                .map_err(|err| err.in_rule(index, part))
+        };
+        let in_condition = |err: PolicyError| err.in_rule(index, RulePart::Condition { node: 0 });
+        [
+            matcher(&self.target.principal, RulePart::Principal),
+            matcher(&self.target.action, RulePart::Action),
+            matcher(&self.target.resource, RulePart::Resource),
+            self.condition.as_ref().map_or(Ok(()), |cond| {
+                cond.validate(config.max_condition_depth, config.max_string_len)
+                    .map_err(in_condition)
+            }),
+            self.condition.as_ref().map_or(Ok(()), |cond| {
+                cond.validate_attrs(config.max_attr_name_len, config.max_attr_value_len)
+                    .map_err(in_condition)
+            }),
+        ]
+    }
+}
+
+/// Check the total number of condition nodes across `rules`.
+fn check_condition_nodes(rules: &[Rule<'_>], config: &PolicyConfig) -> Result<(), PolicyError> {
+    let nodes: usize = rules
+        .iter()
+        .filter_map(|r| r.condition.as_ref())
+        .map(Condition::node_count)
+        .sum();
+    if nodes > config.max_condition_nodes {
+        return Err(PolicyError::TooManyConditionNodes {
+            max: config.max_condition_nodes,
+            actual: nodes,
+        });
+    }
+    Ok(())
+}
+
+/// A violation for `error`, attributed to the rule it names, if any.
+fn violation(error: PolicyError) -> RuleViolation {
+    let rule = match error {
+        PolicyError::UndeclaredAttribute { rule } | PolicyError::UnknownPredicate { rule } => {
+            Some(rule)
+        }
+        _ => None,
+    };
+    RuleViolation { rule, error }
+}
+
+/// A problem found by `PolicyBuilder::build_all_errors` or
+/// `Policy::verify_invariants`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleViolation {
+    /// Index of the offending rule, or `None` for policy-wide problems.
+    pub rule: Option<usize>,
+    /// The problem.
+    pub error: PolicyError,
+}
+
+impl fmt::Display for RuleViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.rule, self.error.location()) {
+            // "rule 3, principal: ..." rather than repeating the rule
+            (_, Some(location)) if location.rule.is_some() => {
+                write!(f, "{}: ", location)?;
+                self.error.message(f)
+            }
+            (Some(rule), _) => write!(f, "rule {}: {}", rule, self.error),
+            (None, _) => write!(f, "policy: {}", self.error),
+        }
+    }
 }
 
 /// A policy is an ordered collection of rules.
@@ -81,6 +246,14 @@ impl<'a> Rule<'a> {
 pub struct Policy<'a> {
     rules: Vec<Rule<'a>>,
     config: PolicyConfig,
+    conditions: CompiledConditions<'a>,
+    index: Option<TargetIndex<'a>>,
+    interned: Option<InternedTargets<'a>>,
+    fingerprint: u64,
+    schema: ContextSchema<'a>,
+    groups: GroupMap<'a>,
+    resource_separator: Option<char>,
+    sink: Option<AttachedSink<'a>>,
 }
 
 impl<'a> Policy<'a> {
@@ -97,17 +270,25 @@ impl<'a> Policy<'a> {
     /// Create a policy with the given rules and config.
     ///
     /// Returns an error if:
-    /// - `config.max_condition_depth > ABSOLUTE_MAX_CONDITION_DEPTH` (hard cap for zero-allocation evaluation)
+    /// - `config.max_condition_depth > ABSOLUTE_MAX_CONDITION_DEPTH` (hard cap for zero-allocation evaluation,
+    ///   lifted by the `heap-stack` feature)
     /// - Rule count exceeds `config.max_rules`
     /// - Any rule violates matcher/string/depth limits
+    /// - Any rule has a `Condition::Custom` (no predicates are registered;
+    ///   see `PolicyBuilder::predicates`)
     pub fn with_config(rules: Vec<Rule<'a>>, config: PolicyConfig) -> Result<Self, PolicyError> {
+        let policy = Self::assemble(rules, config)?;
+        #[cfg(feature = "custom-predicates")]
+        policy
+            .conditions
+            .check_predicates(&PredicateRegistry::new())?;
+        Ok(policy)
+    }
+
+    /// Validate and compile `rules`, leaving predicates unresolved.
+    fn assemble(rules: Vec<Rule<'a>>, config: PolicyConfig) -> Result<Self, PolicyError> {
         // Enforce hard cap for zero-allocation evaluation
-        if config.max_condition_depth > crate::condition::ABSOLUTE_MAX_CONDITION_DEPTH {
-            return Err(PolicyError::ConditionTooDeep {
-                max: crate::condition::ABSOLUTE_MAX_CONDITION_DEPTH,
-                actual: config.max_condition_depth,
-            });
-        }
+        crate::condition::check_depth_limit(config.max_condition_depth)?;
 
         // Validate rule count
         if rules.len() > config.max_rules {
@@ -118,25 +299,144 @@ impl<'a> Policy<'a> {
         }
 
         // Validate rules and condition depths
-        for rule in &rules {
-            // Validate matcher options and string lengths
-            rule.target
-                .principal
-                .validate(config.max_matcher_options, config.max_string_len)?;
-            rule.target
-                .action
-                .validate(config.max_matcher_options, config.max_string_len)?;
-            rule.target
-                .resource
-                .validate(config.max_matcher_options, config.max_string_len)?;
-
-            // Validate condition depth and string lengths
-            if let Some(cond) = &rule.condition {
-                cond.validate(config.max_condition_depth, config.max_string_len)?;
+        for (index, rule) in rules.iter().enumerate() {
+            for check in rule.checks(index, &config) {
+                check?;
             }
         }
+        check_condition_nodes(&rules, &config)?;
+
+        // Flatten conditions into postfix bytecode for evaluation
+        let conditions = CompiledConditions::compile(rules.iter().map(|r| r.condition.as_ref()));
+        let schema = ContextSchema::derive(&conditions, rules.len());
+
+        let mut policy = Policy {
+            rules,
+            config,
+            conditions,
+            index: None,
+            interned: None,
+            fingerprint: 0,
+            schema,
+            groups: GroupMap::default(),
+            resource_separator: None,
+            sink: None,
+        };
+        policy.fingerprint = crate::fingerprint::policy_fingerprint(&policy);
+        Ok(policy)
+    }
+
+    /// Build the target index for this policy (see `PolicyBuilder::target_index`).
+    fn with_target_index(mut self) -> Self {
+        self.index = Some(TargetIndex::build(&self.rules));
+        self
+    }
+
+    /// Intern this policy's strings (see `PolicyBuilder::intern`).
+    fn with_interning(mut self) -> Self {
+        let interned = InternedTargets::build(&self.rules);
+        self.conditions.intern(interned.interner());
+        self.interned = Some(interned);
+        self
+    }
+
+    /// Replace the rules, keeping the config, groups, separator, schema,
+    /// predicates, sink, and index and interning settings.
+    ///
+    /// The new rules must satisfy the config limits and compare the same
+    /// attributes as the old ones.
+    pub(crate) fn with_rules(mut self, rules: Vec<Rule<'a>>) -> Self {
+        self.conditions = self
+            .conditions
+            .recompile(rules.iter().map(|r| r.condition.as_ref()));
+        self.rules = rules;
+        if self.index.is_some() {
+            self.index = Some(TargetIndex::build(&self.rules));
+        }
+        if self.interned.is_some() {
+            self = self.with_interning();
+        }
+        self.fingerprint = crate::fingerprint::policy_fingerprint(&self);
+        self
+    }
+
+    /// Attach a sink that receives every decision this policy makes.
+    ///
+    /// Replaces any previously attached sink. Evaluation errors are not
+    /// recorded.
+    pub fn with_sink(mut self, sink: impl DecisionSink + Send + Sync + 'a) -> Self {
+        self.sink = Some(AttachedSink::new(sink));
+        self
+    }
+
+    /// Use `groups` to resolve `Matcher::Group`, replacing any previous map.
+    ///
+    /// Group memberships are part of the fingerprint.
+    pub fn with_groups(mut self, groups: GroupMap<'a>) -> Self {
+        self.groups = groups;
+        self.fingerprint = crate::fingerprint::policy_fingerprint(&self);
+        self
+    }
+
+    /// The group memberships used to resolve `Matcher::Group`.
+    pub fn groups(&self) -> &GroupMap<'a> {
+        &self.groups
+    }
+
+    /// The predicates used to resolve `Condition::Custom`.
+    #[cfg(feature = "custom-predicates")]
+    pub fn predicates(&self) -> &PredicateRegistry<'a> {
+        self.conditions.predicates()
+    }
+
+    /// Treat resources as a hierarchy split by `separator`.
+    ///
+    /// A rule whose resource matcher matches an ancestor also applies to its
+    /// descendants: with `'/'`, a rule on `project:alpha` applies to
+    /// `project:alpha/doc1`. Ancestors are tried nearest first, each costing
+    /// one evaluation step, and the number walked is reported in
+    /// `EvaluationStats::ancestors_walked`. The separator is part of the
+    /// fingerprint.
+    pub fn with_resource_separator(mut self, separator: char) -> Self {
+        self.resource_separator = Some(separator);
+        self.fingerprint = crate::fingerprint::policy_fingerprint(&self);
+        self
+    }
+
+    /// The resource hierarchy separator, if enabled.
+    pub fn resource_separator(&self) -> Option<char> {
+        self.resource_separator
+    }
+
+    /// Returns `true` if this policy was built with a target index.
+    pub fn is_indexed(&self) -> bool {
+        self.index.is_some()
+    }
+
+    /// Returns `true` if this policy was built with interning.
+    pub fn is_interned(&self) -> bool {
+        self.interned.is_some()
+    }
+
+    /// The interned strings, if this policy was built with interning.
+    pub fn interner(&self) -> Option<&Interner<'a>> {
+        self.interned.as_ref().map(InternedTargets::interner)
+    }
+
+    /// The compiled condition programs, one per rule.
+    pub(crate) fn compiled(&self) -> &CompiledConditions<'a> {
+        &self.conditions
+    }
 
-        Ok(Policy { rules, config })
+    /// Rules that may match the request, in declared order.
+    fn candidates<'p>(&'p self, request: &Request<'_>) -> Candidates<'p, 'a> {
+        match &self.index {
+            None => Candidates::All(self.rules.iter().enumerate()),
+            Some(index) => Candidates::Indexed {
+                rules: &self.rules,
+                lists: index.lookup(request.principal, request.action),
+            },
+        }
     }
 
     /// Get the number of rules in this policy.
@@ -144,6 +444,20 @@ impl<'a> Policy<'a> {
         self.rules.len()
     }
 
+    /// Every context attribute any rule's condition reads, sorted and
+    /// without duplicates (see `Condition::referenced_attrs`).
+    pub fn referenced_attrs(&self) -> Vec<&'a str> {
+        let mut attrs: Vec<&'a str> = self
+            .rules
+            .iter()
+            .filter_map(|rule| rule.condition.as_ref())
+            .flat_map(Condition::referenced_attrs)
+            .collect();
+        attrs.sort_unstable();
+        attrs.dedup();
+        attrs
+    }
+
     /// Get a reference to the rules in this policy.
     ///
     /// Useful for tooling, testing, and policy introspection.
@@ -156,6 +470,110 @@ impl<'a> Policy<'a> {
         &self.config
     }
 
+    /// The context attributes this policy expects (see `ContextSchema`).
+    ///
+    /// Derived from the rules' conditions unless declared with
+    /// `PolicyBuilder::schema`.
+    pub fn schema(&self) -> &ContextSchema<'a> {
+        &self.schema
+    }
+
+    /// A stable 64-bit fingerprint of this policy.
+    ///
+    /// Covers the config limits and every rule's effect, target, condition,
+    /// and reason, in order. Two policies with the same fingerprint make the
+    /// same decisions, provided custom predicates only change along with
+    /// their registry's version (`PredicateRegistry::with_version`). The
+    /// value is stable across processes and platforms, so it can be logged
+    /// to identify which policy version decided.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    /// Re-check this policy against its config limits and its derived state
+    /// against its rules, reporting every violation.
+    ///
+    /// Building already guarantees all of this; the check is defense in
+    /// depth for policies that crossed a trust boundary or a crate upgrade,
+    /// for example after loading an `OwnedPolicy`. It re-runs the checks of
+    /// `PolicyBuilder::build_all_errors`, checks that every condition's
+    /// predicates are registered and its attributes fit the schema, and
+    /// recompiles the conditions, target index, and fingerprint to compare
+    /// them with the stored ones (`PolicyError::InvariantViolated`).
+    ///
+    /// Costs about as much as building the policy again.
+    pub fn verify_invariants(&self) -> Result<(), Vec<RuleViolation>> {
+        let policy_wide = |error| RuleViolation { rule: None, error };
+        let invariant = |invariant| PolicyError::InvariantViolated { invariant };
+        let mut violations = Vec::new();
+
+        if let Err(error) = crate::condition::check_depth_limit(self.config.max_condition_depth) {
+            violations.push(policy_wide(error));
+        }
+        if self.rules.len() > self.config.max_rules {
+            violations.push(policy_wide(PolicyError::TooManyRules {
+                max: self.config.max_rules,
+                actual: self.rules.len(),
+            }));
+        }
+        if let Err(error) = check_condition_nodes(&self.rules, &self.config) {
+            violations.push(policy_wide(error));
+        }
+        let mut compiled = self
+            .conditions
+            .recompile(self.rules.iter().map(|r| r.condition.as_ref()));
+        let interned = self
+            .interned
+            .as_ref()
+            .map(|_| InternedTargets::build(&self.rules));
+        if let Some(interned) = &interned {
+            compiled.intern(interned.interner());
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            let rule_errors = rule
+                .checks(index, &self.config)
+                .into_iter()
+                .filter_map(Result::err)
+                .chain(
+                    (compiled.program(index) != self.conditions.program(index)
+                        || compiled.symbols(index) != self.conditions.symbols(index))
+                    .then(|| invariant("compiled_conditions")),
+                );
+            violations.extend(rule_errors.map(|error| RuleViolation {
+                rule: Some(index),
+                error,
+            }));
+        }
+        if self.conditions.program(self.rules.len()).is_some() {
+            violations.push(policy_wide(invariant("compiled_conditions")));
+        }
+
+        #[cfg(feature = "custom-predicates")]
+        if let Err(error) = compiled.check_predicates(compiled.predicates()) {
+            violations.push(violation(error));
+        }
+        if let Err(error) = self.schema.check_conditions(&compiled, self.rules.len()) {
+            violations.push(violation(error));
+        }
+        if let Some(index) = &self.index {
+            if *index != TargetIndex::build(&self.rules) {
+                violations.push(policy_wide(invariant("target_index")));
+            }
+        }
+        if self.interned != interned {
+            violations.push(policy_wide(invariant("interner")));
+        }
+        if self.fingerprint != crate::fingerprint::policy_fingerprint(self) {
+            violations.push(policy_wide(invariant("fingerprint")));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
     /// Evaluate this policy against a request.
     ///
     /// Semantics:
@@ -164,89 +582,119 @@ impl<'a> Policy<'a> {
     /// 3. Collect all matching (effect, reason) pairs
     /// 4. If any Deny exists → return first Deny's reason
     /// 5. Else if any Allow exists → return first Allow's reason
-    /// 6. Else → Deny with NO_MATCHING_RULE
+    /// 6. Else → Deny with NO_MATCHING_RULE (or Allow with DEFAULT_ALLOW
+    ///    if `PolicyConfig::default_effect` is `Allow`)
+    ///
+    /// Returns `PolicyError::StepBudgetExceeded` if evaluation would take
+    /// more than `PolicyConfig::max_eval_steps` steps.
     pub fn evaluate(&self, request: &Request<'_>) -> Result<Decision, PolicyError> {
-        // 1. Validate request string lengths
-        validate_str(request.principal, self.config.max_string_len)?;
-        validate_str(request.action, self.config.max_string_len)?;
-        validate_str(request.resource, self.config.max_string_len)?;
-
-        // 2. Validate context size
-        if request.context.len() > self.config.max_context_attrs {
-            return Err(PolicyError::ContextTooLarge {
-                max: self.config.max_context_attrs,
-                actual: request.context.len(),
-            });
-        }
-
-        // 3. Validate context key/value lengths
-        for (key, value) in request.context {
-            validate_str(key, self.config.max_string_len)?;
-            if let Value::String(s) = value {
-                validate_str(s, self.config.max_string_len)?;
-            }
-        }
+        self.evaluate_observed(request, &mut ())
+            .map(|(decision, _)| decision)
+    }
 
-        let mut first_allow: Option<ReasonCode> = None;
-        let mut first_deny: Option<ReasonCode> = None;
+    /// Evaluate this policy against a request, returning observable stats.
+    ///
+    /// Same semantics as `evaluate()`, but also returns `EvaluationStats`
+    /// showing how close the evaluation got to its configured limits.
+    ///
+    /// Use this when you need visibility into the evaluation cost, e.g. for
+    /// monitoring, debugging, or capacity planning.
+    pub fn evaluate_with_stats(
+        &self,
+        request: &Request<'_>,
+    ) -> Result<(Decision, EvaluationStats), PolicyError> {
+        self.evaluate_observed(request, &mut ())
+    }
 
-        // Evaluate rules in order
-        for rule in &self.rules {
-            // Check if target matches
-            if !rule
-                .target
-                .matches(request.principal, request.action, request.resource)
-            {
-                continue;
-            }
+    /// Evaluate this policy against a request and pass the decision to `sink`.
+    ///
+    /// Same semantics as `evaluate()`. The sink is called only when a
+    /// decision is reached, after any sink attached to the policy.
+    pub fn evaluate_with_sink(
+        &self,
+        request: &Request<'_>,
+        sink: &dyn DecisionSink,
+    ) -> Result<Decision, PolicyError> {
+        let (decision, stats) = self.evaluate_observed(request, &mut ())?;
+        sink.record(request, &decision, &stats);
+        Ok(decision)
+    }
 
-            // Check if condition matches (if present)
-            let condition_matches = match &rule.condition {
-                None => true,
-                Some(cond) => cond.evaluate(request.context)?,
-            };
+    /// Evaluate a batch of requests, returning one result per request in
+    /// input order.
+    ///
+    /// Equivalent to calling `evaluate()` on each request. An error for one
+    /// request does not affect the others.
+    pub fn evaluate_batch(&self, requests: &[Request<'_>]) -> Vec<Result<Decision, PolicyError>> {
+        requests
+            .iter()
+            .map(|request| self.evaluate(request))
+            .collect()
+    }
 
-            if !condition_matches {
-                continue;
-            }
+    /// Look `request`'s strings up in this policy's interner, for
+    /// `evaluate_interned`.
+    ///
+    /// Allocates one slot per interned string. If the policy does not
+    /// intern, the result just wraps the request.
+    pub fn intern<'r>(&self, request: &Request<'r>) -> InternedRequest<'r> {
+        InternedRequest::new(request, self.interner(), self.fingerprint)
+    }
 
-            // Rule matches - record the effect
-            match rule.effect {
-                Effect::Allow => {
-                    if first_allow.is_none() {
-                        first_allow = Some(rule.reason);
-                    }
-                }
-                Effect::Deny => {
-                    if first_deny.is_none() {
-                        first_deny = Some(rule.reason);
-                    }
-                }
-            }
-        }
-        // Apply deny-overrides: Deny wins if any Deny matched
-        if let Some(reason) = first_deny {
-            Ok(Decision::deny(reason))
-        } else if let Some(reason) = first_allow {
-            Ok(Decision::allow(reason))
+    /// Evaluate a request interned with `intern`.
+    ///
+    /// Makes the same decision as `evaluate`, comparing symbols instead of
+    /// strings. A request interned for a different policy, or for one that
+    /// does not intern, is evaluated by name.
+    pub fn evaluate_interned(
+        &self,
+        request: &InternedRequest<'_>,
+    ) -> Result<Decision, PolicyError> {
+        if self.interned.is_some() && request.fingerprint == Some(self.fingerprint) {
+            let mut source = request;
+            self.evaluate_from(&request.request, &mut source, &mut ())
+                .map(|(decision, _)| decision)
         } else {
-            // No matching rules - default deny
-            Ok(Decision::deny(NO_MATCHING_RULE))
+            self.evaluate(&request.request)
         }
     }
 
-    /// Evaluate this policy against a request, returning observable stats.
+    /// The evaluation loop, reporting events to `observer`.
     ///
-    /// Same semantics as `evaluate()`, but also returns `EvaluationStats`
-    /// showing how close the evaluation got to its configured limits.
+    /// Every public evaluation method goes through here, so they all share
+    /// one implementation of the semantics, and the attached sink (if any)
+    /// sees every decision.
+    pub(crate) fn evaluate_observed<O: Observer>(
+        &self,
+        request: &Request<'_>,
+        observer: &mut O,
+    ) -> Result<(Decision, EvaluationStats), PolicyError> {
+        let mut context = request.context;
+        self.evaluate_from(request, &mut context, observer)
+    }
+
+    /// The evaluation loop, reading condition attributes from `source`.
     ///
-    /// Use this when you need visibility into the evaluation cost, e.g. for
-    /// monitoring, debugging, or capacity planning.
-    pub fn evaluate_with_stats(
+    /// `request.context` is validated either way; `source` decides whether
+    /// conditions see only it or also lazily resolved attributes.
+    pub(crate) fn evaluate_from<S: AttrSource<'a>, O: Observer>(
         &self,
         request: &Request<'_>,
-    ) -> Result<(Decision, crate::stats::EvaluationStats), PolicyError> {
-        let mut stats = crate::stats::EvaluationStats::new();
+        source: &mut S,
+        observer: &mut O,
+    ) -> Result<(Decision, EvaluationStats), PolicyError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "gate0.evaluate",
+            fingerprint = self.fingerprint,
+            rule = tracing::field::Empty,
+            effect = tracing::field::Empty,
+            reason = tracing::field::Empty,
+        );
+        #[cfg(feature = "tracing")]
+        let _entered = span.enter();
+
+        let mut stats = EvaluationStats::new();
 
         // 1. Validate request string lengths
         validate_str(request.principal, self.config.max_string_len)?;
@@ -267,29 +715,63 @@ impl<'a> Policy<'a> {
             if let Value::String(s) = value {
                 validate_str(s, self.config.max_string_len)?;
             }
+            validate_attr(
+                key,
+                value,
+                self.config.max_attr_name_len,
+                self.config.max_attr_value_len,
+            )?;
         }
 
-        let mut first_allow: Option<ReasonCode> = None;
-        let mut first_deny: Option<ReasonCode> = None;
+        // First matching rule of each effect: (index, reason)
+        let mut first_allow: Option<(usize, ReasonCode)> = None;
+        let mut first_deny: Option<(usize, ReasonCode)> = None;
+        // Strictest cache hint of each effect's rules up to its first match
+        let mut allow_cache: Option<CacheHint> = None;
+        let mut deny_cache: Option<CacheHint> = None;
+        let mut steps: usize = 0;
+        // Unlike `stats.rules_checked`, does not saturate
+        let mut checked: usize = 0;
 
         // Evaluate rules in order
-        for rule in &self.rules {
+        for (index, rule) in self.candidates(request) {
             stats.inc_rules();
+            checked += 1;
+            observer.rule_checked(index);
+
+            // One step for the target check
+            steps = self.charge_steps(steps, 1)?;
 
             // Check if target matches
-            if !rule
-                .target
-                .matches(request.principal, request.action, request.resource)
-            {
+            let fields = source.fields();
+            if !self.target_matches(index, &rule.target, request, fields, &mut stats, &mut steps)? {
                 continue;
             }
+            observer.target_matched(index);
+
+            if let Some(hint) = rule.cache {
+                let (first, cache) = match rule.effect {
+                    Effect::Allow => (first_allow, &mut allow_cache),
+                    Effect::Deny => (first_deny, &mut deny_cache),
+                };
+                if first.is_none() {
+                    *cache = Some(cache.map_or(hint, |cache| cache.min(hint)));
+                }
+            }
 
             // Check if condition matches (if present)
             let condition_matches = match &rule.condition {
                 None => true,
-                Some(cond) => {
+                Some(_) => {
+                    // One step per instruction, charged before running any
+                    steps = self.charge_steps(steps, self.conditions.program_len(index))?;
+                    let result = self.conditions.evaluate(index, source, observer)?;
+                    let profile = self.conditions.profile(index);
                     stats.inc_condition_evals();
-                    cond.evaluate(request.context)?
+                    stats.update_depth(profile.height);
+                    stats.add_context_lookups(profile.lookups);
+                    observer.condition_result(index, result);
+                    result
                 }
             };
 
@@ -298,33 +780,154 @@ impl<'a> Policy<'a> {
             }
 
             // Rule matches - record the effect
+            observer.rule_matched(index);
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                rule = index,
+                effect = effect_name(rule.effect),
+                reason = rule.reason.value(),
+                "rule matched"
+            );
             match rule.effect {
                 Effect::Allow => {
                     if first_allow.is_none() {
-                        first_allow = Some(rule.reason);
+                        first_allow = Some((index, rule.reason));
                     }
                 }
                 Effect::Deny => {
                     if first_deny.is_none() {
-                        first_deny = Some(rule.reason);
+                        first_deny = Some((index, rule.reason));
                     }
                 }
             }
         }
+        stats.set_steps(steps);
+        stats.set_resolutions(source.resolutions());
+        if checked < self.rules.len() {
+            stats.termination = Termination::ExhaustedCandidates;
+        }
 
         // Apply deny-overrides: Deny wins if any Deny matched
-        let decision = if let Some(reason) = first_deny {
-            Decision::deny(reason)
-        } else if let Some(reason) = first_allow {
-            Decision::allow(reason)
+        // Any Deny could override an Allow, so every Deny's hint applies to it
+        let either_cache = match (allow_cache, deny_cache) {
+            (Some(a), Some(d)) => Some(a.min(d)),
+            (a, d) => a.or(d),
+        };
+        let decision = if let Some((_, reason)) = first_deny {
+            Decision::deny(reason).with_cache(deny_cache)
+        } else if let Some((_, reason)) = first_allow {
+            Decision::allow(reason).with_cache(either_cache)
         } else {
-            Decision::deny(NO_MATCHING_RULE)
+            // No matching rules - the configured default (deny unless set)
+            let decision = match self.config.default_effect {
+                Effect::Deny => Decision::deny(NO_MATCHING_RULE),
+                Effect::Allow => Decision::allow(DEFAULT_ALLOW),
+            };
+            decision.with_cache(either_cache)
         };
 
+        #[cfg(feature = "tracing")]
+        {
+            if let Some((index, _)) = first_deny.or(first_allow) {
+                span.record("rule", index);
+            }
+            span.record("effect", effect_name(decision.effect));
+            span.record("reason", decision.reason.value());
+        }
+
+        if let Some(sink) = &self.sink {
+            sink.record(request, &decision, &stats);
+        }
         Ok((decision, stats))
     }
+
+    /// Pass a decision served from a cache to the attached sink, if any.
+    #[cfg(feature = "cache")]
+    pub(crate) fn record_cached(&self, request: &Request<'_>, decision: &Decision) {
+        if let Some(sink) = &self.sink {
+            let stats = EvaluationStats {
+                termination: Termination::Cached,
+                ..EvaluationStats::new()
+            };
+            sink.record(request, decision, &stats);
+        }
+    }
+
+    /// Check rule `rule`'s target, walking resource ancestors if a
+    /// hierarchy is enabled.
+    ///
+    /// Compares symbols if the request's `fields` were interned for this
+    /// policy.
+    fn target_matches(
+        &self,
+        rule: usize,
+        target: &Target<'_>,
+        request: &Request<'_>,
+        fields: Option<[Option<Symbol>; 3]>,
+        stats: &mut EvaluationStats,
+        steps: &mut usize,
+    ) -> Result<bool, PolicyError> {
+        let interned = self.interned.as_ref().zip(fields);
+        let matches = |field: usize, matcher: &Matcher<'_>, value: &str, symbol| match interned {
+            Some((interned, _)) => interned.matches(rule, field, symbol, value, &self.groups),
+            None => matcher.matches_in(value, &self.groups),
+        };
+        let symbols = fields.unwrap_or_default();
+
+        stats.inc_matcher_comparisons();
+        if !matches(0, &target.principal, request.principal, symbols[0]) {
+            return Ok(false);
+        }
+        stats.inc_matcher_comparisons();
+        if !matches(1, &target.action, request.action, symbols[1]) {
+            return Ok(false);
+        }
+
+        let mut resource = request.resource;
+        let mut symbol = symbols[2];
+        loop {
+            stats.inc_matcher_comparisons();
+            if matches(2, &target.resource, resource, symbol) {
+                return Ok(true);
+            }
+            // Bounded: every ancestor is strictly shorter
+            match self
+                .resource_separator
+                .and_then(|sep| parent(resource, sep))
+            {
+                Some(ancestor) => {
+                    *steps = self.charge_steps(*steps, 1)?;
+                    stats.inc_ancestors();
+                    resource = ancestor;
+                    symbol = interned.and_then(|(interned, _)| interned.interner().get(ancestor));
+                }
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Add `cost` to `steps`, failing if the total exceeds the budget.
+    #[inline]
+    fn charge_steps(&self, steps: usize, cost: usize) -> Result<usize, PolicyError> {
+        let total = steps.saturating_add(cost);
+        if total > self.config.max_eval_steps {
+            return Err(PolicyError::StepBudgetExceeded {
+                max: self.config.max_eval_steps,
+                actual: total,
+            });
+        }
+        Ok(total)
+    }
 }
 
+/// Lowercase effect name for tracing fields.
+#[cfg(feature = "tracing")]
+fn effect_name(effect: Effect) -> &'static str {
+    match effect {
+        Effect::Allow => "allow",
+        Effect::Deny => "deny",
+    }
+}
 
 /// Validate that a string does not exceed the maximum allowed length.
 fn validate_str(s: &str, max_len: usize) -> Result<(), PolicyError> {
@@ -332,6 +935,7 @@ fn validate_str(s: &str, max_len: usize) -> Result<(), PolicyError> {
         Err(PolicyError::StringTooLong {
             max: max_len,
             actual: s.len(),
+            location: None,
         })
     } else {
         Ok(())
@@ -343,6 +947,17 @@ fn validate_str(s: &str, max_len: usize) -> Result<(), PolicyError> {
 pub struct PolicyBuilder<'a> {
     rules: Vec<Rule<'a>>,
     config: PolicyConfig,
+    target_index: bool,
+    intern: bool,
+    normalize: bool,
+    schema: Option<ContextSchema<'a>>,
+    groups: Option<GroupMap<'a>>,
+    #[cfg(feature = "custom-predicates")]
+    predicates: PredicateRegistry<'a>,
+    resource_separator: Option<char>,
+    sink: Option<AttachedSink<'a>>,
+    /// The first failed rule edit, reported by `build()`.
+    edit_error: Option<PolicyError>,
 }
 
 impl<'a> PolicyBuilder<'a> {
@@ -351,6 +966,16 @@ impl<'a> PolicyBuilder<'a> {
         PolicyBuilder {
             rules: Vec::new(),
             config: PolicyConfig::default(),
+            target_index: false,
+            intern: false,
+            normalize: false,
+            schema: None,
+            groups: None,
+            #[cfg(feature = "custom-predicates")]
+            predicates: PredicateRegistry::new(),
+            resource_separator: None,
+            sink: None,
+            edit_error: None,
         }
     }
 
@@ -366,37 +991,267 @@ impl<'a> PolicyBuilder<'a> {
         self
     }
 
-    /// Build the policy.
-    pub fn build(self) -> Result<Policy<'a>, PolicyError> {
-        Policy::with_config(self.rules, self.config)
+    /// Add every rule from `rules`, in order.
+    pub fn rules_from_iter(mut self, rules: impl IntoIterator<Item = Rule<'a>>) -> Self {
+        self.rules.extend(rules);
+        self
     }
-}
 
-impl<'a> Default for PolicyBuilder<'a> {
-    fn default() -> Self {
-        Self::new()
+    /// Insert a rule at `index`, shifting later rules down.
+    ///
+    /// `index` may equal the rule count, to append. An index past the end
+    /// leaves the rules unchanged and makes `build()` fail with
+    /// `RuleIndexOutOfRange`.
+    pub fn insert_rule(mut self, index: usize, rule: Rule<'a>) -> Self {
+        if index <= self.rules.len() {
+            self.rules.insert(index, rule);
+        } else {
+            self.fail_edit(index);
+        }
+        self
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::target::Matcher;
-    use crate::value::Value;
-
-    const REASON_ADMIN_ACCESS: ReasonCode = ReasonCode(1);
-    const REASON_BLOCKED_USER: ReasonCode = ReasonCode(2);
-    const REASON_PUBLIC_READ: ReasonCode = ReasonCode(3);
-    const REASON_WRITE_ALLOWED: ReasonCode = ReasonCode(4);
-
-    #[test]
-    fn test_empty_policy_denies() {
-        let policy = Policy::new(vec![]).unwrap();
-        let request = Request::new("alice", "read", "document.txt");
-        let decision = policy.evaluate(&request).unwrap();
 
-        assert!(decision.is_deny());
-        assert_eq!(decision.reason, NO_MATCHING_RULE);
+    /// Remove the rule at `index`, shifting later rules up.
+    ///
+    /// An index past the end makes `build()` fail with
+    /// `RuleIndexOutOfRange`.
+    pub fn remove_rule(mut self, index: usize) -> Self {
+        if index < self.rules.len() {
+            self.rules.remove(index);
+        } else {
+            self.fail_edit(index);
+        }
+        self
+    }
+
+    /// Replace the rule at `index`.
+    ///
+    /// An index past the end makes `build()` fail with
+    /// `RuleIndexOutOfRange`.
+    pub fn replace_rule(mut self, index: usize, rule: Rule<'a>) -> Self {
+        match self.rules.get_mut(index) {
+            Some(slot) => *slot = rule,
+            None => self.fail_edit(index),
+        }
+        self
+    }
+
+    /// The rules added so far, in order.
+    pub fn rules(&self) -> &[Rule<'a>] {
+        &self.rules
+    }
+
+    /// Record an out-of-range edit; only the first is kept.
+    fn fail_edit(&mut self, index: usize) {
+        if self.edit_error.is_none() {
+            self.edit_error = Some(PolicyError::RuleIndexOutOfRange {
+                index,
+                len: self.rules.len(),
+            });
+        }
+    }
+
+    /// Build a target index so evaluation skips rules that cannot match.
+    ///
+    /// Worth enabling for large policies whose rules mostly target exact
+    /// principals or actions. Decisions are identical with or without the
+    /// index; only `EvaluationStats::rules_checked` drops.
+    pub fn target_index(mut self, enabled: bool) -> Self {
+        self.target_index = enabled;
+        self
+    }
+
+    /// Intern attribute names and matcher strings, so
+    /// `Policy::evaluate_interned` compares them by symbol.
+    ///
+    /// Worth enabling when requests have wide contexts or are evaluated
+    /// more than once; see `Policy::intern`. Decisions are identical with
+    /// or without interning, and `evaluate` is unaffected.
+    pub fn intern(mut self, enabled: bool) -> Self {
+        self.intern = enabled;
+        self
+    }
+
+    /// Replace every condition with `Condition::normalize()` before
+    /// validating.
+    ///
+    /// Worth enabling for generated policies: decisions are unchanged, but
+    /// folded conditions use less depth and node budget and evaluate in
+    /// fewer steps. `Policy::rules()` and the fingerprint reflect the folded
+    /// conditions, and parts folded away are not validated.
+    pub fn normalize(mut self, enabled: bool) -> Self {
+        self.normalize = enabled;
+        self
+    }
+
+    /// Normalize the rules' conditions once, if enabled.
+    fn normalize_conditions(&mut self) {
+        if core::mem::take(&mut self.normalize) {
+            for rule in &mut self.rules {
+                if let Some(cond) = &rule.condition {
+                    rule.condition = Some(cond.normalize());
+                }
+            }
+        }
+    }
+
+    /// Declare the context attributes the policy expects.
+    ///
+    /// Replaces the schema derived from conditions. `build()` fails with
+    /// `PolicyError::UndeclaredAttribute` if any condition compares an
+    /// attribute, or a type, the schema does not declare.
+    pub fn schema(mut self, schema: ContextSchema<'a>) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Resolve `Matcher::Group` with these memberships.
+    pub fn groups(mut self, groups: GroupMap<'a>) -> Self {
+        self.groups = Some(groups);
+        self
+    }
+
+    /// Resolve `Condition::Custom` with these predicates.
+    ///
+    /// `build()` fails with `PolicyError::UnknownPredicate` if a condition
+    /// names a predicate the registry does not hold.
+    #[cfg(feature = "custom-predicates")]
+    pub fn predicates(mut self, predicates: PredicateRegistry<'a>) -> Self {
+        self.predicates = predicates;
+        self
+    }
+
+    /// Treat resources as a hierarchy split by `separator` (see
+    /// `Policy::with_resource_separator`).
+    pub fn resource_separator(mut self, separator: char) -> Self {
+        self.resource_separator = Some(separator);
+        self
+    }
+
+    /// Attach a sink that receives every decision (see `Policy::with_sink`).
+    pub fn sink(mut self, sink: impl DecisionSink + Send + Sync + 'a) -> Self {
+        self.sink = Some(AttachedSink::new(sink));
+        self
+    }
+
+    /// Build the policy, reporting every violation instead of the first.
+    ///
+    /// Checks the rule count, the config, and every matcher and condition
+    /// of every rule before giving up, so one pass shows everything to fix.
+    /// Problems that only show up once the rules are valid, such as an
+    /// undeclared schema attribute or an unknown predicate, are reported
+    /// alone.
+    pub fn build_all_errors(mut self) -> Result<Policy<'a>, Vec<RuleViolation>> {
+        self.normalize_conditions();
+        let policy_wide = |error| RuleViolation { rule: None, error };
+        let mut violations: Vec<_> = self
+            .edit_error
+            .clone()
+            .map(policy_wide)
+            .into_iter()
+            .collect();
+        if let Err(error) = crate::condition::check_depth_limit(self.config.max_condition_depth) {
+            violations.push(policy_wide(error));
+        }
+        if self.rules.len() > self.config.max_rules {
+            violations.push(policy_wide(PolicyError::TooManyRules {
+                max: self.config.max_rules,
+                actual: self.rules.len(),
+            }));
+        }
+        if let Err(error) = check_condition_nodes(&self.rules, &self.config) {
+            violations.push(policy_wide(error));
+        }
+        for (index, rule) in self.rules.iter().enumerate() {
+            for check in rule.checks(index, &self.config) {
+                if let Err(error) = check {
+                    violations.push(RuleViolation {
+                        rule: Some(index),
+                        error,
+                    });
+                }
+            }
+        }
+
+        if !violations.is_empty() {
+            return Err(violations);
+        }
+        self.build().map_err(|error| vec![violation(error)])
+    }
+
+    /// Build the policy and report rules that can never decide a request.
+    ///
+    /// Opt-in check for duplicates and for rules shadowed by an earlier
+    /// rule with the same effect (see `RuleWarning`). Warnings do not fail
+    /// the build; errors are the same as `build()`.
+    pub fn build_with_warnings(self) -> Result<(Policy<'a>, Vec<RuleWarning>), PolicyError> {
+        let policy = self.build()?;
+        let warnings = crate::redundancy::find(&policy.rules, policy.resource_separator);
+        Ok((policy, warnings))
+    }
+
+    /// Build the policy.
+    ///
+    /// Fails with the first out-of-range rule edit, if there was one.
+    pub fn build(mut self) -> Result<Policy<'a>, PolicyError> {
+        if let Some(err) = self.edit_error {
+            return Err(err);
+        }
+        self.normalize_conditions();
+        let mut policy = Policy::assemble(self.rules, self.config)?;
+        #[cfg(feature = "custom-predicates")]
+        {
+            policy.conditions.resolve(self.predicates)?;
+            policy.fingerprint = crate::fingerprint::policy_fingerprint(&policy);
+        }
+        if let Some(schema) = self.schema {
+            schema.check_conditions(&policy.conditions, policy.rules.len())?;
+            policy.schema = schema;
+        }
+        if let Some(groups) = self.groups {
+            policy = policy.with_groups(groups);
+        }
+        if let Some(separator) = self.resource_separator {
+            policy = policy.with_resource_separator(separator);
+        }
+        policy.sink = self.sink;
+        if self.intern {
+            policy = policy.with_interning();
+        }
+        if self.target_index {
+            Ok(policy.with_target_index())
+        } else {
+            Ok(policy)
+        }
+    }
+}
+
+impl<'a> Default for PolicyBuilder<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Location;
+    use crate::target::Matcher;
+    use crate::value::Value;
+
+    const REASON_ADMIN_ACCESS: ReasonCode = ReasonCode(1);
+    const REASON_BLOCKED_USER: ReasonCode = ReasonCode(2);
+    const REASON_PUBLIC_READ: ReasonCode = ReasonCode(3);
+    const REASON_WRITE_ALLOWED: ReasonCode = ReasonCode(4);
+
+    #[test]
+    fn test_empty_policy_denies() {
+        let policy = Policy::new(vec![]).unwrap();
+        let request = Request::new("alice", "read", "document.txt");
+        let decision = policy.evaluate(&request).unwrap();
+
+        assert!(decision.is_deny());
+        assert_eq!(decision.reason, NO_MATCHING_RULE);
     }
 
     #[test]
@@ -547,7 +1402,14 @@ mod tests {
         let err = result.unwrap_err();
         assert!(matches!(
             err,
-            PolicyError::TooManyMatcherOptions { max: 2, actual: 3 }
+            PolicyError::TooManyMatcherOptions {
+                max: 2,
+                actual: 3,
+                location: Some(Location {
+                    rule: Some(0),
+                    part: RulePart::Principal
+                })
+            }
         ));
     }
 
@@ -571,7 +1433,14 @@ mod tests {
         let err = result.unwrap_err();
         assert!(matches!(
             err,
-            PolicyError::StringTooLong { max: 5, actual: 15 }
+            PolicyError::StringTooLong {
+                max: 5,
+                actual: 15,
+                location: Some(Location {
+                    rule: Some(0),
+                    part: RulePart::Principal
+                })
+            }
         ));
     }
 
@@ -594,7 +1463,14 @@ mod tests {
         let err = result.unwrap_err();
         assert!(matches!(
             err,
-            PolicyError::StringTooLong { max: 5, actual: 13 }
+            PolicyError::StringTooLong {
+                max: 5,
+                actual: 13,
+                location: Some(Location {
+                    rule: Some(0),
+                    part: RulePart::Condition { node: 0 }
+                })
+            }
         ));
     }
 
@@ -668,7 +1544,14 @@ mod tests {
 
         assert_eq!(
             result.unwrap_err(),
-            PolicyError::ConditionTooDeep { max: 2, actual: 3 }
+            PolicyError::ConditionTooDeep {
+                max: 2,
+                actual: 3,
+                location: Some(Location {
+                    rule: Some(0),
+                    part: RulePart::Condition { node: 0 }
+                })
+            }
         );
     }
 
@@ -745,6 +1628,257 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_step_budget() {
+        // Two rules: 1 step + 1 step + (a AND b) = 3 instructions → 5 steps
+        let rules = vec![
+            Rule::allow(Target::any(), ReasonCode(1)),
+            Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::And(
+                    Box::new(Condition::True),
+                    Box::new(Condition::True),
+                )),
+                ReasonCode(2),
+            ),
+        ];
+        let request = Request::new("alice", "read", "doc");
+
+        let policy = Policy::new(rules.clone()).unwrap();
+        let (_, stats) = policy.evaluate_with_stats(&request).unwrap();
+        assert_eq!(stats.steps, 5);
+
+        let exact = PolicyConfig {
+            max_eval_steps: 5,
+            ..PolicyConfig::default()
+        };
+        let policy = Policy::with_config(rules.clone(), exact).unwrap();
+        assert!(policy.evaluate(&request).is_ok());
+
+        let tight = PolicyConfig {
+            max_eval_steps: 4,
+            ..PolicyConfig::default()
+        };
+        let policy = Policy::with_config(rules, tight).unwrap();
+        assert_eq!(
+            policy.evaluate(&request),
+            Err(PolicyError::StepBudgetExceeded { max: 4, actual: 5 })
+        );
+    }
+
+    #[test]
+    fn test_referenced_attrs() {
+        let cond = |attr| {
+            Some(Condition::Equals {
+                attr,
+                value: Value::Bool(true),
+            })
+        };
+        let policy = Policy::builder()
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                cond("mfa"),
+                ReasonCode(1),
+            ))
+            .rule(Rule::allow(Target::any(), ReasonCode(2)))
+            .rule(Rule::new(
+                Effect::Deny,
+                Target::any(),
+                cond("banned"),
+                ReasonCode(3),
+            ))
+            .rule(Rule::new(
+                Effect::Deny,
+                Target::any(),
+                cond("mfa"),
+                ReasonCode(4),
+            ))
+            .build()
+            .unwrap();
+        assert_eq!(policy.referenced_attrs(), ["banned", "mfa"]);
+    }
+
+    #[test]
+    fn test_normalize_frees_depth_budget() {
+        let mut cond = Condition::Equals {
+            attr: "mfa",
+            value: Value::Bool(true),
+        };
+        for _ in 0..PolicyConfig::default().max_condition_depth {
+            cond = Condition::Not(Box::new(Condition::Not(Box::new(cond))));
+        }
+        let rule = Rule::new(Effect::Allow, Target::any(), Some(cond), ReasonCode(1));
+
+        let err = Policy::builder().rule(rule.clone()).build().unwrap_err();
+        assert!(matches!(err, PolicyError::ConditionTooDeep { .. }));
+
+        let policy = Policy::builder()
+            .normalize(true)
+            .rule(rule)
+            .build()
+            .unwrap();
+        assert_eq!(
+            policy.rules()[0].condition,
+            Some(Condition::Equals {
+                attr: "mfa",
+                value: Value::Bool(true),
+            })
+        );
+        let ctx: &[(&str, Value)] = &[("mfa", Value::Bool(true))];
+        assert!(policy
+            .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+            .unwrap()
+            .is_allow());
+    }
+
+    #[test]
+    fn test_target_index_skips_rules() {
+        let build = |indexed: bool| {
+            Policy::builder()
+                .target_index(indexed)
+                .rule(Rule::allow(
+                    Target {
+                        principal: Matcher::Exact("alice"),
+                        action: Matcher::Any,
+                        resource: Matcher::Any,
+                    },
+                    REASON_ADMIN_ACCESS,
+                ))
+                .rule(Rule::deny(
+                    Target {
+                        principal: Matcher::Exact("bob"),
+                        action: Matcher::Any,
+                        resource: Matcher::Any,
+                    },
+                    REASON_BLOCKED_USER,
+                ))
+                .rule(Rule::allow(
+                    Target {
+                        principal: Matcher::Any,
+                        action: Matcher::Exact("read"),
+                        resource: Matcher::Any,
+                    },
+                    REASON_PUBLIC_READ,
+                ))
+                .build()
+                .unwrap()
+        };
+        let linear = build(false);
+        let indexed = build(true);
+        assert!(!linear.is_indexed());
+        assert!(indexed.is_indexed());
+
+        let request = Request::new("alice", "write", "doc");
+        let (decision, stats) = indexed.evaluate_with_stats(&request).unwrap();
+        assert_eq!(decision, linear.evaluate(&request).unwrap());
+        assert_eq!(decision.reason, REASON_ADMIN_ACCESS);
+        // Only alice's rule is a candidate
+        assert_eq!(stats.rules_checked, 1);
+        assert_eq!(stats.termination, Termination::ExhaustedCandidates);
+
+        let request = Request::new("bob", "read", "doc");
+        let (decision, stats) = indexed.evaluate_with_stats(&request).unwrap();
+        assert_eq!(decision, linear.evaluate(&request).unwrap());
+        assert_eq!(decision.reason, REASON_BLOCKED_USER);
+        assert_eq!(stats.rules_checked, 2);
+    }
+
+    #[test]
+    fn test_termination_past_stats_range() {
+        let rules = usize::from(u16::MAX) + 2;
+        let config = PolicyConfig {
+            max_rules: rules,
+            max_eval_steps: rules,
+            ..PolicyConfig::default()
+        };
+        let rule = Rule::allow(
+            Target {
+                principal: Matcher::Exact("bob"),
+                action: Matcher::Any,
+                resource: Matcher::Any,
+            },
+            ReasonCode(1),
+        );
+        let policy = Policy::with_config(vec![rule; rules], config).unwrap();
+
+        let (_, stats) = policy
+            .evaluate_with_stats(&Request::new("alice", "read", "doc"))
+            .unwrap();
+        assert_eq!(stats.rules_checked, u16::MAX);
+        assert_eq!(stats.termination, Termination::ExhaustedRules);
+    }
+
+    #[test]
+    fn test_policy_borrows_runtime_buffer() {
+        // Strings loaded at runtime: the policy borrows them, no 'static needed
+        let buffer = String::from("alice read secrets");
+        let mut fields = buffer.split(' ');
+        let (principal, action, resource) = (
+            fields.next().unwrap(),
+            fields.next().unwrap(),
+            fields.next().unwrap(),
+        );
+
+        let policy = Policy::builder()
+            .rule(Rule::allow(
+                Target {
+                    principal: Matcher::Exact(principal),
+                    action: Matcher::Exact(action),
+                    resource: Matcher::Exact(resource),
+                },
+                REASON_PUBLIC_READ,
+            ))
+            .build()
+            .unwrap();
+
+        let decision = policy
+            .evaluate(&Request::new("alice", "read", "secrets"))
+            .unwrap();
+        assert!(decision.is_allow());
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let options: &[&str] = &["alice", "bob"];
+        let rule = Rule::allow(
+            Target {
+                principal: Matcher::OneOf(options),
+                action: Matcher::Any,
+                resource: Matcher::Any,
+            },
+            REASON_PUBLIC_READ,
+        );
+        let base = Policy::new(vec![rule.clone()]).unwrap();
+
+        // Same rules, same fingerprint, even with owned option lists
+        let mut vec_rule = rule.clone();
+        vec_rule.target.principal = Matcher::OneOfVec(options.to_vec());
+        assert_eq!(
+            Policy::new(vec![vec_rule]).unwrap().fingerprint(),
+            base.fingerprint()
+        );
+
+        // Any change to rules or limits changes it
+        let mut other = rule.clone();
+        other.reason = REASON_BLOCKED_USER;
+        assert_ne!(
+            Policy::new(vec![other]).unwrap().fingerprint(),
+            base.fingerprint()
+        );
+        let config = PolicyConfig {
+            max_eval_steps: 10,
+            ..PolicyConfig::default()
+        };
+        assert_ne!(
+            Policy::with_config(vec![rule], config)
+                .unwrap()
+                .fingerprint(),
+            base.fingerprint()
+        );
+    }
+
     #[test]
     fn test_evaluate_with_stats() {
         use crate::condition::Condition;
@@ -787,5 +1921,486 @@ mod tests {
         assert_eq!(stats.rules_checked, 3);
         // Rule 2 has a condition that was evaluated
         assert_eq!(stats.condition_evals, 1);
+        assert_eq!(stats.context_lookups, 1);
+        assert_eq!(stats.max_depth_reached, 1);
+        // Rule 1 stops at the principal; rules 2 and 3 check all three fields
+        assert_eq!(stats.matcher_comparisons, 7);
+        assert_eq!(stats.termination, Termination::ExhaustedRules);
+    }
+
+    #[test]
+    fn test_resource_hierarchy() {
+        let rule = Rule::allow(
+            Target {
+                principal: Matcher::Any,
+                action: Matcher::Any,
+                resource: Matcher::Exact("project:alpha"),
+            },
+            ReasonCode(1),
+        );
+        let flat = Policy::new(vec![rule.clone()]).unwrap();
+        let tree = Policy::builder()
+            .rule(rule)
+            .resource_separator('/')
+            .build()
+            .unwrap();
+        assert_ne!(flat.fingerprint(), tree.fingerprint());
+
+        let nested = Request::new("alice", "read", "project:alpha/docs/doc1");
+        assert!(flat.evaluate(&nested).unwrap().is_deny());
+
+        let (decision, stats) = tree.evaluate_with_stats(&nested).unwrap();
+        assert!(decision.is_allow());
+        assert_eq!(stats.ancestors_walked, 2);
+        assert_eq!(stats.steps, 3);
+
+        // Siblings and prefixes that are not ancestors do not match
+        for resource in ["project:alphabet/doc1", "project:beta/doc1", "project"] {
+            let request = Request::new("alice", "read", resource);
+            assert!(tree.evaluate(&request).unwrap().is_deny(), "{}", resource);
+        }
+    }
+
+    #[test]
+    fn test_builder_rule_editing() {
+        let rule = |reason| Rule::allow(Target::any(), ReasonCode(reason));
+        let reasons = |builder: PolicyBuilder<'_>| {
+            let policy = builder.build().unwrap();
+            policy
+                .rules()
+                .iter()
+                .map(|r| r.reason.value())
+                .collect::<Vec<_>>()
+        };
+
+        let builder = Policy::builder()
+            .rules_from_iter((1..=3).map(rule))
+            .insert_rule(0, rule(10))
+            .insert_rule(4, rule(11))
+            .remove_rule(2)
+            .replace_rule(1, rule(12));
+        assert_eq!(builder.rules().len(), 4);
+        assert_eq!(reasons(builder), [10, 12, 3, 11]);
+
+        // The first bad edit is reported; later valid edits still apply
+        let result = Policy::builder()
+            .rule(rule(1))
+            .remove_rule(1)
+            .insert_rule(5, rule(2))
+            .replace_rule(0, rule(3))
+            .build();
+        assert_eq!(
+            result.unwrap_err(),
+            PolicyError::RuleIndexOutOfRange { index: 1, len: 1 }
+        );
+    }
+
+    #[test]
+    fn test_build_all_errors() {
+        let config = PolicyConfig {
+            max_rules: 2,
+            max_matcher_options: 2,
+            max_string_len: 8,
+            ..PolicyConfig::default()
+        };
+        let bad = Rule::new(
+            Effect::Allow,
+            Target {
+                principal: Matcher::OneOf(&["a", "b", "c"]),
+                action: Matcher::Any,
+                resource: Matcher::Exact("much-too-long"),
+            },
+            Some(Condition::Equals {
+                attr: "also-much-too-long",
+                value: Value::Bool(true),
+            }),
+            ReasonCode(1),
+        );
+        let violations = Policy::builder()
+            .config(config)
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .rule(bad)
+            .rule(Rule::allow(Target::any(), ReasonCode(2)))
+            .remove_rule(7)
+            .build_all_errors()
+            .unwrap_err();
+
+        let rules: Vec<_> = violations.iter().map(|v| v.rule).collect();
+        assert_eq!(rules, [None, None, Some(1), Some(1), Some(1)]);
+        assert_eq!(
+            violations[1].error,
+            PolicyError::TooManyRules { max: 2, actual: 3 }
+        );
+        assert_eq!(
+            violations[2].to_string(),
+            "rule 1, principal: matcher exceeds maximum options of 2, got 3"
+        );
+        assert_eq!(
+            violations[3].to_string(),
+            "rule 1, resource: string exceeds maximum length of 8, got 13"
+        );
+        assert_eq!(
+            violations[4].to_string(),
+            "rule 1, condition node 0: string exceeds maximum length of 8, got 18"
+        );
+
+        // A valid policy builds as usual
+        let policy = Policy::builder()
+            .config(config)
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .build_all_errors()
+            .unwrap();
+        assert_eq!(policy.rule_count(), 1);
+    }
+
+    #[test]
+    fn test_attribute_and_node_limits() {
+        let config = PolicyConfig {
+            max_condition_nodes: 3,
+            max_attr_name_len: 4,
+            max_attr_value_len: 5,
+            ..PolicyConfig::default()
+        };
+        let eq = |attr, value| Condition::Equals { attr, value };
+        let rule = |cond| Rule::new(Effect::Allow, Target::any(), Some(cond), ReasonCode(1));
+
+        // Three nodes fit, four do not
+        let pair = Condition::And(
+            Box::new(eq("role", Value::Bool(true))),
+            Box::new(Condition::True),
+        );
+        assert_eq!(pair.node_count(), 3);
+        assert!(Policy::with_config(vec![rule(pair.clone())], config).is_ok());
+        assert_eq!(
+            Policy::with_config(vec![rule(pair), rule(Condition::True)], config).unwrap_err(),
+            PolicyError::TooManyConditionNodes { max: 3, actual: 4 }
+        );
+
+        assert_eq!(
+            Policy::with_config(vec![rule(eq("level", Value::Int(1)))], config).unwrap_err(),
+            PolicyError::AttrNameTooLong {
+                max: 4,
+                actual: 5,
+                location: Some(Location {
+                    rule: Some(0),
+                    part: RulePart::Condition { node: 0 }
+                })
+            }
+        );
+        assert_eq!(
+            Policy::with_config(vec![rule(eq("role", Value::String("viewer")))], config)
+                .unwrap_err(),
+            PolicyError::AttrValueTooLong {
+                max: 5,
+                actual: 6,
+                location: Some(Location {
+                    rule: Some(0),
+                    part: RulePart::Condition { node: 0 }
+                })
+            }
+        );
+
+        // Request context is held to the same limits
+        let policy = Policy::with_config(vec![rule(Condition::True)], config).unwrap();
+        let ctx: &[(&str, Value)] = &[("role", Value::String("viewer"))];
+        assert_eq!(
+            policy.evaluate(&Request::with_context("a", "b", "c", ctx)),
+            Err(PolicyError::AttrValueTooLong {
+                max: 5,
+                actual: 6,
+                location: None
+            })
+        );
+        let ctx: &[(&str, Value)] = &[("level", Value::Int(1))];
+        assert_eq!(
+            policy.evaluate(&Request::with_context("a", "b", "c", ctx)),
+            Err(PolicyError::AttrNameTooLong {
+                max: 4,
+                actual: 5,
+                location: None
+            })
+        );
+
+        // Non-default limits are part of the fingerprint; defaults are not
+        let default = Policy::new(vec![]).unwrap();
+        let limited = Policy::with_config(vec![], config).unwrap();
+        assert_ne!(default.fingerprint(), limited.fingerprint());
+    }
+
+    #[test]
+    fn test_depth_beyond_fixed_stacks() {
+        use crate::condition::ABSOLUTE_MAX_CONDITION_DEPTH;
+
+        let depth = 2 * ABSOLUTE_MAX_CONDITION_DEPTH;
+        let config = PolicyConfig {
+            max_condition_depth: depth,
+            ..PolicyConfig::default()
+        };
+        let mut cond = Condition::Equals {
+            attr: "x",
+            value: Value::Bool(true),
+        };
+        for _ in 1..depth {
+            cond = Condition::And(Box::new(Condition::True), Box::new(cond));
+        }
+        let rules = vec![Rule::new(
+            Effect::Allow,
+            Target::any(),
+            Some(cond),
+            ReasonCode(1),
+        )];
+
+        #[cfg(not(feature = "heap-stack"))]
+        assert_eq!(
+            Policy::with_config(rules, config).unwrap_err(),
+            PolicyError::ConditionTooDeep {
+                max: ABSOLUTE_MAX_CONDITION_DEPTH,
+                actual: depth,
+                location: None,
+            }
+        );
+
+        // Deeper conditions spill to the heap instead of overflowing
+        #[cfg(feature = "heap-stack")]
+        {
+            let policy = Policy::with_config(rules, config).unwrap();
+            let ctx: &[(&str, Value)] = &[("x", Value::Bool(true))];
+            let request = Request::with_context("a", "b", "c", ctx);
+            assert!(policy.evaluate(&request).unwrap().is_allow());
+            let cond = policy.rules()[0].condition.as_ref().unwrap();
+            assert_eq!(cond.evaluate(ctx), Ok(true));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "tracing")]
+    fn test_tracing_span_and_events() {
+        use std::sync::{Arc, Mutex};
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        #[derive(Clone, Default)]
+        struct Buffer(Arc<Mutex<Vec<u8>>>);
+        impl std::io::Write for Buffer {
+            fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(bytes)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buffer = Buffer::default();
+        let writer = buffer.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .rule(Rule::deny(Target::any(), ReasonCode(2)))
+            .build()
+            .unwrap();
+        tracing::subscriber::with_default(subscriber, || {
+            policy
+                .evaluate(&Request::new("alice", "read", "doc"))
+                .unwrap();
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.len(), 3, "{}", output);
+        assert!(lines[0].contains("rule matched rule=0 effect=\"allow\" reason=1"));
+        assert!(lines[1].contains("rule matched rule=1 effect=\"deny\" reason=2"));
+        // The span closes with the winning rule
+        assert!(lines[2].contains(&format!("fingerprint={}", policy.fingerprint())));
+        assert!(lines[2].contains("rule=1 effect=\"deny\" reason=2"));
+    }
+
+    #[test]
+    fn test_cache_hints() {
+        let mallory = Target::new(Matcher::Exact("mallory"), Matcher::Any, Matcher::Any);
+        let policy = Policy::builder()
+            .rule(
+                Rule::new(
+                    Effect::Deny,
+                    Target::any(),
+                    Some(Condition::GreaterThan {
+                        attr: "risk",
+                        value: Value::Int(50),
+                    }),
+                    ReasonCode(1),
+                )
+                .cache(CacheHint::PER_CONTEXT),
+            )
+            .rule(Rule::allow(Target::any(), ReasonCode(2)).cache(CacheHint::ttl(300)))
+            .rule(Rule::allow(Target::any(), ReasonCode(3)).cache(CacheHint::ttl(60)))
+            .rule(Rule::deny(mallory, ReasonCode(4)).cache(CacheHint::ttl(30)))
+            .rule(Rule::deny(Target::any(), ReasonCode(5)).cache(CacheHint::ttl(0)))
+            .build()
+            .unwrap();
+        let decide = |principal, risk| {
+            let ctx = [("risk", Value::Int(risk))];
+            policy
+                .evaluate(&Request::with_context(principal, "read", "doc", &ctx))
+                .unwrap()
+        };
+
+        // The volatile deny applies whether or not it matched; the Allow
+        // after the first matching one does not, and mallory's deny does
+        // not target alice
+        let hint = CacheHint::ttl(0).min(CacheHint::ttl(300)).per_context();
+        assert_eq!(decide("alice", 10).cache, Some(hint));
+
+        // Nothing after the first matching Deny can change a Deny
+        assert_eq!(
+            decide("alice", 90),
+            Decision::deny(ReasonCode(1)).with_cache(Some(CacheHint::PER_CONTEXT))
+        );
+        assert_eq!(
+            decide("mallory", 10),
+            Decision::deny(ReasonCode(4)).with_cache(Some(CacheHint::ttl(30).per_context()))
+        );
+
+        // Without hints, decisions carry none
+        let plain = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .build()
+            .unwrap();
+        assert_eq!(
+            plain
+                .evaluate(&Request::new("alice", "read", "doc"))
+                .unwrap(),
+            Decision::allow(ReasonCode(1))
+        );
+    }
+
+    #[test]
+    fn test_default_allow() {
+        let config = PolicyConfig {
+            default_effect: Effect::Allow,
+            ..PolicyConfig::default()
+        };
+        let policy = Policy::with_config(
+            vec![Rule::deny(
+                Target {
+                    principal: Matcher::Exact("mallory"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            )],
+            config,
+        )
+        .unwrap();
+
+        assert_eq!(
+            policy.evaluate(&Request::new("alice", "read", "doc")),
+            Ok(Decision::allow(DEFAULT_ALLOW))
+        );
+        // Explicit denies still win
+        assert_eq!(
+            policy.evaluate(&Request::new("mallory", "read", "doc")),
+            Ok(Decision::deny(ReasonCode(1)))
+        );
+
+        let deny_default = Policy::new(policy.rules().to_vec()).unwrap();
+        assert_ne!(deny_default.fingerprint(), policy.fingerprint());
+    }
+
+    #[test]
+    fn test_verify_invariants() {
+        let rules = vec![
+            Rule::new(
+                Effect::Allow,
+                Target {
+                    principal: Matcher::Exact("alice"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                Some(Condition::Equals {
+                    attr: "role",
+                    value: Value::String("admin"),
+                }),
+                REASON_ADMIN_ACCESS,
+            ),
+            Rule::deny(Target::any(), REASON_BLOCKED_USER),
+        ];
+        let mut policy = Policy::builder()
+            .rules_from_iter(rules)
+            .target_index(true)
+            .build()
+            .unwrap();
+        assert_eq!(policy.verify_invariants(), Ok(()));
+
+        // Tamper with the rules behind the derived state's back
+        policy.rules[0].condition = Some(Condition::True);
+        policy.rules[1].target.principal = Matcher::Exact("bob");
+        policy.config.max_rules = 1;
+        let violations = policy.verify_invariants().unwrap_err();
+        let found: Vec<_> = violations
+            .iter()
+            .map(|v| (v.rule, v.error.clone()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (None, PolicyError::TooManyRules { max: 1, actual: 2 }),
+                (
+                    Some(0),
+                    PolicyError::InvariantViolated {
+                        invariant: "compiled_conditions"
+                    }
+                ),
+                (
+                    None,
+                    PolicyError::InvariantViolated {
+                        invariant: "target_index"
+                    }
+                ),
+                (
+                    None,
+                    PolicyError::InvariantViolated {
+                        invariant: "fingerprint"
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            violations[1].to_string(),
+            "rule 0: policy invariant violated: compiled_conditions"
+        );
+    }
+
+    #[test]
+    fn test_const_rules() {
+        static RULES: [Rule<'static>; 2] = [
+            Rule::deny(
+                Target::new(Matcher::Exact("mallory"), Matcher::Any, Matcher::Any),
+                REASON_BLOCKED_USER,
+            ),
+            Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::Equals {
+                    attr: "role",
+                    value: Value::String("admin"),
+                }),
+                REASON_ADMIN_ACCESS,
+            )
+            .named("admins"),
+        ];
+        static CONTEXT: [(&str, Value<'static>); 1] = [("role", Value::String("admin"))];
+        static REQUEST: Request<'static> = Request::with_context("alice", "read", "doc", &CONTEXT);
+
+        assert_eq!(RULES[1].name, Some("admins"));
+        let policy = Policy::new(RULES.to_vec()).unwrap();
+        assert_eq!(
+            policy.evaluate(&REQUEST),
+            Ok(Decision::allow(REASON_ADMIN_ACCESS))
+        );
     }
 }
@@ -0,0 +1,252 @@
+//! Registered custom predicates.
+//!
+//! Some checks, such as validating that an attribute is a well-formed ULID,
+//! cannot be written with the built-in conditions. A `PredicateRegistry`
+//! names plain functions that `Condition::Custom` can call on an attribute.
+//! The registry is given to `PolicyBuilder::predicates`, and building fails
+//! with `PolicyError::UnknownPredicate` if a condition names a predicate the
+//! registry does not hold.
+//!
+//! # Contract
+//!
+//! The engine's guarantees (deterministic decisions, bounded evaluation, no
+//! allocation) only hold if every predicate keeps them too. A predicate must
+//! be pure, returning the same result for the same value, and must finish in
+//! time bounded by the size of the value. It must not panic.
+//!
+//! Predicate names are part of the policy fingerprint, but their
+//! implementations cannot be hashed. Give the registry a version with
+//! `PredicateRegistry::with_version` and bump
+//! it whenever a predicate's behaviour changes, so that fingerprints, and
+//! the `DecisionCache` entries keyed on them, change too.
+
+use alloc::vec::Vec;
+
+use crate::value::Value;
+
+/// A custom predicate: pure, bounded, and non-panicking (see module docs).
+pub type Predicate = fn(&Value<'_>) -> bool;
+
+/// Named predicates for `Condition::Custom`.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{PredicateRegistry, Value};
+///
+/// fn is_ulid(value: &Value<'_>) -> bool {
+///     const ALPHABET: &str = "0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+///     matches!(value, Value::String(s)
+///         if s.len() == 26 && s.chars().all(|c| ALPHABET.contains(c)))
+/// }
+///
+/// let predicates = PredicateRegistry::new().register("ulid", is_ulid);
+///
+/// let check = predicates.get("ulid").unwrap();
+/// assert!(check(&Value::String("01ARZ3NDEKTSV4RRFFQ69G5FAV")));
+/// assert!(!check(&Value::Int(1)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PredicateRegistry<'a> {
+    /// Sorted by name; no duplicate names.
+    entries: Vec<(&'a str, Predicate)>,
+    /// Caller-supplied version of the implementations.
+    version: u64,
+}
+
+impl<'a> PredicateRegistry<'a> {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        PredicateRegistry {
+            entries: Vec::new(),
+            version: 0,
+        }
+    }
+
+    /// Set the version of the registered implementations, mixed into the
+    /// fingerprint of policies built with this registry (see module docs).
+    pub const fn with_version(mut self, version: u64) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// The version set with `with_version`, 0 by default.
+    pub const fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Register `predicate` under `name`, replacing any predicate already
+    /// registered under it.
+    pub fn register(mut self, name: &'a str, predicate: Predicate) -> Self {
+        match self.entries.binary_search_by(|(n, _)| (*n).cmp(name)) {
+            Ok(i) => self.entries[i].1 = predicate,
+            Err(i) => self.entries.insert(i, (name, predicate)),
+        }
+        self
+    }
+
+    /// The predicate registered under `name`.
+    pub fn get(&self, name: &str) -> Option<Predicate> {
+        self.entries
+            .binary_search_by(|(n, _)| (*n).cmp(name))
+            .ok()
+            .map(|i| self.entries[i].1)
+    }
+
+    /// Returns `true` if a predicate is registered under `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Every registered name, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.entries.iter().map(|(n, _)| *n)
+    }
+
+    /// Returns `true` if no predicate is registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Condition, ContextSchema, Effect, OwnedPolicy, Policy, PolicyError, Query, ReasonCode,
+        Request, Rule, Target, ValueType,
+    };
+
+    fn positive(value: &Value<'_>) -> bool {
+        matches!(value, Value::Int(n) if *n > 0)
+    }
+
+    fn nonempty(value: &Value<'_>) -> bool {
+        matches!(value, Value::String(s) if !s.is_empty())
+    }
+
+    #[test]
+    fn test_register_and_lookup() {
+        let registry = PredicateRegistry::new()
+            .register("positive", positive)
+            .register("nonempty", nonempty);
+
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            ["nonempty", "positive"]
+        );
+        assert!(registry.contains("positive"));
+        assert!(!registry.contains("ulid"));
+        assert!(registry.get("positive").unwrap()(&Value::Int(3)));
+
+        // Re-registering a name replaces the predicate
+        let registry = registry.register("positive", nonempty);
+        assert_eq!(registry.names().count(), 2);
+        assert!(!registry.get("positive").unwrap()(&Value::Int(3)));
+        assert!(PredicateRegistry::new().is_empty());
+    }
+
+    fn rule(name: &'static str) -> Rule<'static> {
+        Rule::new(
+            Effect::Allow,
+            Target::any(),
+            Some(Condition::Not(Box::new(Condition::Custom {
+                attr: "count",
+                name,
+            }))),
+            ReasonCode(1),
+        )
+    }
+
+    #[test]
+    fn test_policy_resolves_predicates() {
+        let registry = PredicateRegistry::new().register("positive", positive);
+        let policy = Policy::builder()
+            .rule(rule("positive"))
+            .predicates(registry.clone())
+            .build()
+            .unwrap();
+
+        let decide = |ctx: &[(&str, Value)]| {
+            policy
+                .evaluate(&Request::with_context("a", "b", "c", ctx))
+                .unwrap()
+                .is_allow()
+        };
+        assert!(!decide(&[("count", Value::Int(2))]));
+        assert!(decide(&[("count", Value::Int(0))]));
+        assert!(decide(&[("count", Value::String("2"))]));
+        // Missing attribute = false, so its negation allows
+        assert!(decide(&[]));
+
+        // The tree evaluator has no registry
+        let cond = Condition::Custom {
+            attr: "count",
+            name: "positive",
+        };
+        assert_eq!(cond.evaluate(&[("count", Value::Int(2))]), Ok(false));
+
+        // Owned policies round-trip, given the same registry
+        let owned = OwnedPolicy::from(&policy);
+        assert_eq!(
+            owned.policy().unwrap_err(),
+            PolicyError::UnknownPredicate { rule: 0 }
+        );
+        let rebuilt = owned.policy_with_predicates(registry.clone()).unwrap();
+        assert_eq!(rebuilt.fingerprint(), policy.fingerprint());
+
+        // A new implementation version is a new fingerprint
+        let bumped = owned
+            .policy_with_predicates(registry.clone().with_version(2))
+            .unwrap();
+        assert_ne!(bumped.fingerprint(), policy.fingerprint());
+        assert_eq!(bumped.verify_invariants(), Ok(()));
+
+        // The derived schema accepts any type for the attribute
+        let ctx: &[(&str, Value)] = &[("count", Value::Bool(true))];
+        assert!(policy
+            .validate_request(&Request::with_context("a", "b", "c", ctx))
+            .is_empty());
+        let schema = ContextSchema::new().required("other", ValueType::Int);
+        let err = Policy::builder()
+            .rule(rule("positive"))
+            .predicates(registry)
+            .schema(schema)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, PolicyError::UndeclaredAttribute { rule: 0 });
+    }
+
+    #[test]
+    fn test_unknown_predicates_fail_the_build() {
+        let registry = PredicateRegistry::new().register("positive", positive);
+        let err = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(1)))
+            .rule(rule("ulid"))
+            .predicates(registry)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, PolicyError::UnknownPredicate { rule: 1 });
+        assert_eq!(err.kind(), "unknown_predicate");
+
+        // Without a registry every predicate is unknown
+        let err = Policy::new(vec![rule("positive")]).unwrap_err();
+        assert_eq!(err, PolicyError::UnknownPredicate { rule: 0 });
+    }
+
+    #[test]
+    fn test_queries_refuse_predicates() {
+        let registry = PredicateRegistry::new().register("positive", positive);
+        let policy = Policy::builder()
+            .rule(Rule::deny(Target::any(), ReasonCode(2)))
+            .rule(rule("positive"))
+            .predicates(registry)
+            .build()
+            .unwrap();
+
+        // Even where a search could not go wrong, none is made
+        let err = policy.query(&Query::new(Effect::Allow)).unwrap_err();
+        assert_eq!(err, PolicyError::UndecidableQuery { rule: 1 });
+        assert_eq!(err.kind(), "undecidable_query");
+    }
+}
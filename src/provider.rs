@@ -0,0 +1,258 @@
+//! Lazy context attributes.
+//!
+//! Some attributes are expensive to compute (group membership, device
+//! posture) and only some rules need them. Instead of resolving every
+//! attribute up front, pass a `ContextProvider` to
+//! `Policy::evaluate_with_provider`: an attribute is resolved only when a
+//! condition actually compares it, at most once per evaluation.
+//!
+//! Resolutions count against `PolicyConfig::max_context_attrs` together with
+//! the request's inline context, so the effective context stays bounded, and
+//! the count is reported in `EvaluationStats::resolutions`.
+
+use alloc::vec::Vec;
+
+use crate::compiled::AttrSource;
+use crate::condition::{lookup_attr, validate_attr};
+use crate::error::PolicyError;
+use crate::intern::Symbol;
+use crate::policy::Policy;
+use crate::stats::EvaluationStats;
+use crate::types::{Decision, Request};
+use crate::value::Value;
+
+/// Resolves context attributes on demand.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Condition, ContextProvider, Effect, Policy, ReasonCode, Request, Rule, Target, Value};
+///
+/// struct Directory;
+///
+/// impl ContextProvider for Directory {
+///     fn get(&self, attr: &str) -> Option<Value<'_>> {
+///         match attr {
+///             "group" => Some(Value::String("admins")),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::new(
+///         Effect::Allow,
+///         Target::any(),
+///         Some(Condition::Equals { attr: "group", value: Value::String("admins") }),
+///         ReasonCode(1),
+///     ))
+///     .build()
+///     .unwrap();
+///
+/// let request = Request::new("alice", "read", "doc");
+/// let (decision, stats) = policy.evaluate_with_provider(&request, &Directory).unwrap();
+/// assert!(decision.is_allow());
+/// assert_eq!(stats.resolutions, 1);
+/// ```
+pub trait ContextProvider {
+    /// Resolve attribute `attr`, or `None` if it does not exist.
+    fn get(&self, attr: &str) -> Option<Value<'_>>;
+}
+
+/// Attributes looked up in the inline context first, then resolved through
+/// the provider and remembered.
+struct Resolver<'k, 'c, 'p, P: ?Sized> {
+    inline: &'c [(&'c str, Value<'c>)],
+    provider: &'p P,
+    resolved: Vec<(&'k str, Option<Value<'p>>)>,
+    max_attrs: usize,
+    max_string_len: usize,
+    max_attr_name_len: usize,
+    max_attr_value_len: usize,
+}
+
+impl<'k, P: ContextProvider + ?Sized> AttrSource<'k> for Resolver<'k, '_, '_, P> {
+    fn test<F: FnOnce(&Value<'_>) -> bool>(
+        &mut self,
+        attr: &'k str,
+        _symbol: Option<Symbol>,
+        test: F,
+    ) -> Result<Option<bool>, PolicyError> {
+        if let Some(v) = lookup_attr(self.inline, attr) {
+            return Ok(Some(test(v)));
+        }
+        if let Some((_, v)) = self.resolved.iter().find(|(k, _)| *k == attr) {
+            return Ok(v.as_ref().map(test));
+        }
+
+        // Every call counts, including ones that find nothing
+        let total = self.inline.len() + self.resolved.len() + 1;
+        if total > self.max_attrs {
+            return Err(PolicyError::ContextTooLarge {
+                max: self.max_attrs,
+                actual: total,
+            });
+        }
+        let resolved = self.provider.get(attr);
+        if let Some(value) = &resolved {
+            if let Value::String(s) = value {
+                if s.len() > self.max_string_len {
+                    return Err(PolicyError::StringTooLong {
+                        max: self.max_string_len,
+                        actual: s.len(),
+                        location: None,
+                    });
+                }
+            }
+            validate_attr(attr, value, self.max_attr_name_len, self.max_attr_value_len)?;
+        }
+        let result = resolved.as_ref().map(test);
+        self.resolved.push((attr, resolved));
+        Ok(result)
+    }
+
+    fn resolutions(&self) -> usize {
+        self.resolved.len()
+    }
+}
+
+impl<'a> Policy<'a> {
+    /// Evaluate this policy, resolving attributes missing from the request's
+    /// context through `provider`.
+    ///
+    /// The request's inline context takes precedence. Each other attribute
+    /// a condition compares is requested from the provider once; rules that
+    /// are never reached, or whose target does not match, resolve nothing.
+    /// Returns `ContextTooLarge` if the inline and resolved attributes
+    /// together would exceed `PolicyConfig::max_context_attrs`.
+    pub fn evaluate_with_provider<P: ContextProvider + ?Sized>(
+        &self,
+        request: &Request<'_>,
+        provider: &P,
+    ) -> Result<(Decision, EvaluationStats), PolicyError> {
+        let mut resolver = Resolver {
+            inline: request.context,
+            provider,
+            resolved: Vec::new(),
+            max_attrs: self.config().max_context_attrs,
+            max_string_len: self.config().max_string_len,
+            max_attr_name_len: self.config().max_attr_name_len,
+            max_attr_value_len: self.config().max_attr_value_len,
+        };
+        self.evaluate_from(request, &mut resolver, &mut ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, Matcher, PolicyConfig, ReasonCode, Rule, Target};
+    use alloc::boxed::Box;
+    use alloc::string::{String, ToString};
+    use core::cell::RefCell;
+
+    /// Provider that records which attributes were requested.
+    struct Recording<'a> {
+        attrs: &'a [(&'a str, Value<'a>)],
+        calls: RefCell<Vec<String>>,
+    }
+
+    impl ContextProvider for Recording<'_> {
+        fn get(&self, attr: &str) -> Option<Value<'_>> {
+            self.calls.borrow_mut().push(attr.to_string());
+            lookup_attr(self.attrs, attr).cloned()
+        }
+    }
+
+    fn eq(attr: &'static str, value: Value<'static>) -> Option<Condition<'static>> {
+        Some(Condition::Equals { attr, value })
+    }
+
+    #[test]
+    fn test_resolves_only_referenced_attributes_once() {
+        let policy = Policy::builder()
+            .rule(Rule::new(
+                Effect::Deny,
+                Target {
+                    principal: Matcher::Any,
+                    action: Matcher::Exact("delete"),
+                    resource: Matcher::Any,
+                },
+                eq("posture", Value::String("compromised")),
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::Or(
+                    Box::new(Condition::Equals {
+                        attr: "group",
+                        value: Value::String("admins"),
+                    }),
+                    Box::new(Condition::Equals {
+                        attr: "group",
+                        value: Value::String("editors"),
+                    }),
+                )),
+                ReasonCode(2),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                eq("role", Value::String("owner")),
+                ReasonCode(3),
+            ))
+            .build()
+            .unwrap();
+
+        let provider = Recording {
+            attrs: &[
+                ("group", Value::String("editors")),
+                ("posture", Value::String("compromised")),
+            ],
+            calls: RefCell::new(Vec::new()),
+        };
+
+        // "role" is inline, "posture" is behind a non-matching target
+        let ctx: &[(&str, Value)] = &[("role", Value::String("guest"))];
+        let request = Request::with_context("alice", "read", "doc", ctx);
+        let (decision, stats) = policy.evaluate_with_provider(&request, &provider).unwrap();
+
+        assert_eq!(decision, Decision::allow(ReasonCode(2)));
+        assert_eq!(*provider.calls.borrow(), ["group"]);
+        assert_eq!(stats.resolutions, 1);
+    }
+
+    #[test]
+    fn test_resolutions_bounded_by_context_limit() {
+        let policy = Policy::builder()
+            .config(PolicyConfig {
+                max_context_attrs: 1,
+                ..PolicyConfig::default()
+            })
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                eq("group", Value::Bool(true)),
+                ReasonCode(1),
+            ))
+            .build()
+            .unwrap();
+        let provider = Recording {
+            attrs: &[],
+            calls: RefCell::new(Vec::new()),
+        };
+
+        let (decision, stats) = policy
+            .evaluate_with_provider(&Request::new("a", "b", "c"), &provider)
+            .unwrap();
+        assert!(decision.is_deny());
+        assert_eq!(stats.resolutions, 1);
+
+        let ctx: &[(&str, Value)] = &[("other", Value::Bool(true))];
+        assert_eq!(
+            policy.evaluate_with_provider(&Request::with_context("a", "b", "c", ctx), &provider),
+            Err(PolicyError::ContextTooLarge { max: 1, actual: 2 })
+        );
+    }
+}
@@ -0,0 +1,886 @@
+//! Property queries over policies.
+//!
+//! A `Query` asks whether any request satisfying some constraints reaches a
+//! given effect, for example "can anyone with `role=intern` be allowed on
+//! anything under `secrets`?". `Policy::query` answers with a witness
+//! request, or with proof that none exists.
+//!
+//! # Why exhaustive search is complete
+//!
+//! Every test a rule makes compares one request component with constants
+//! that appear in the policy or the query, so each component has finitely
+//! many classes of values that no rule can tell apart:
+//!
+//! - Matchers compare strings for equality. Two strings that equal no
+//!   constant, and have none of them as an ancestor, are indistinguishable:
+//!   one class per constant plus one fresh value.
+//! - Conditions compare strings and booleans for equality: one class per
+//!   constant.
+//! - Conditions compare integers, durations, and timestamps for equality
+//!   and order (`GT`, `LT`). Their constants cut each number line into
+//!   points and the open intervals between them; every constant and its two
+//!   neighbours reach each of those.
+//! - Conditions compare addresses for equality and test them against CIDR
+//!   blocks (`IN_CIDR`). A block is a range of one IP version's addresses,
+//!   so the same holds with its first and last address as the constants.
+//! - Conditions test timestamps against times of day (`BETWEEN`) and days
+//!   of the week (`ON`), which repeat every week. Between two consecutive
+//!   timestamp constants, one week of days, each tried at midnight and at
+//!   every window edge with their neighbours, reaches every class.
+//! - A missing attribute fails every test but `NotEquals`, exactly like a
+//!   value of a type no test looks at, so absence stands for everything
+//!   else.
+//!
+//! Custom predicates are arbitrary functions, so nothing bounds their
+//! classes: querying a policy that calls one fails with `UndecidableQuery`
+//! rather than claiming a proof.
+//!
+//! Checking one representative per combination of classes covers every
+//! possible request. `Unreachable` is the result of that check, not of
+//! sampling.
+
+use alloc::borrow::Cow;
+use alloc::collections::BTreeSet;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::compiled::Op;
+use crate::error::PolicyError;
+use crate::groups::GroupMap;
+use crate::net::Cidr;
+use crate::policy::Policy;
+use crate::target::{parent, Matcher};
+use crate::time::SECONDS_PER_DAY;
+use crate::types::{Decision, Effect, ReasonCode, Request};
+use crate::value::Value;
+use crate::whatif::advance;
+
+/// Default maximum number of requests a query may evaluate.
+pub const DEFAULT_MAX_QUERY_REQUESTS: usize = 1 << 20;
+
+/// A question about which requests reach an effect.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Condition, Effect, Matcher, Policy, Query, QueryResult, ReasonCode, Rule, Target, Value};
+///
+/// let policy = Policy::builder()
+///     .resource_separator('/')
+///     .rule(Rule::new(
+///         Effect::Allow,
+///         Target {
+///             principal: Matcher::Any,
+///             action: Matcher::Exact("read"),
+///             resource: Matcher::Exact("secrets"),
+///         },
+///         Some(Condition::Equals { attr: "role", value: Value::String("admin") }),
+///         ReasonCode(1),
+///     ))
+///     .build()
+///     .unwrap();
+///
+/// // Interns can never read secrets
+/// let interns = Query::new(Effect::Allow)
+///     .resource(Matcher::Exact("secrets"))
+///     .context("role", Value::String("intern"));
+/// assert!(policy.query(&interns).unwrap().is_unreachable());
+///
+/// // Admins can, and the witness shows how
+/// let admins = Query::new(Effect::Allow)
+///     .resource(Matcher::Exact("secrets"))
+///     .context("role", Value::String("admin"));
+/// let witness = policy.query(&admins).unwrap().witness().cloned().unwrap();
+/// assert_eq!(witness.action, "read");
+/// assert!(policy.evaluate(&witness.request()).unwrap().is_allow());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query<'a> {
+    effect: Effect,
+    reason: Option<ReasonCode>,
+    principal: Matcher<'a>,
+    action: Matcher<'a>,
+    resource: Matcher<'a>,
+    context: Vec<(&'a str, Value<'a>)>,
+    max_requests: usize,
+}
+
+impl<'a> Query<'a> {
+    /// Ask whether any request reaches `effect`.
+    ///
+    /// Without further constraints, every principal, action, resource, and
+    /// context is considered.
+    pub fn new(effect: Effect) -> Self {
+        Query {
+            effect,
+            reason: None,
+            principal: Matcher::Any,
+            action: Matcher::Any,
+            resource: Matcher::Any,
+            context: Vec::new(),
+            max_requests: DEFAULT_MAX_QUERY_REQUESTS,
+        }
+    }
+
+    /// Only count decisions with this reason.
+    pub fn reason(mut self, reason: ReasonCode) -> Self {
+        self.reason = Some(reason);
+        self
+    }
+
+    /// Only consider principals matching `matcher`.
+    pub fn principal(mut self, matcher: Matcher<'a>) -> Self {
+        self.principal = matcher;
+        self
+    }
+
+    /// Only consider actions matching `matcher`.
+    pub fn action(mut self, matcher: Matcher<'a>) -> Self {
+        self.action = matcher;
+        self
+    }
+
+    /// Only consider resources matching `matcher`, including descendants
+    /// if the policy has a resource hierarchy.
+    pub fn resource(mut self, matcher: Matcher<'a>) -> Self {
+        self.resource = matcher;
+        self
+    }
+
+    /// Only consider requests where `attr` is exactly `value`.
+    pub fn context(mut self, attr: &'a str, value: Value<'a>) -> Self {
+        self.context.retain(|(k, _)| *k != attr);
+        self.context.push((attr, value));
+        self
+    }
+
+    /// Set the maximum number of requests to evaluate (default:
+    /// `DEFAULT_MAX_QUERY_REQUESTS`).
+    pub fn max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    fn accepts(&self, decision: &Decision) -> bool {
+        decision.effect == self.effect && self.reason.is_none_or(|r| r == decision.reason)
+    }
+}
+
+/// A request that satisfies a query, and the decision it gets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Witness<'a> {
+    /// The principal; a fresh name if no constant one was needed.
+    pub principal: Cow<'a, str>,
+    /// The action; a fresh name if no constant one was needed.
+    pub action: Cow<'a, str>,
+    /// The resource; a fresh name if no constant one was needed.
+    pub resource: Cow<'a, str>,
+    /// The attributes present in the request.
+    pub context: Vec<(&'a str, Value<'a>)>,
+    /// The decision the request gets.
+    pub decision: Decision,
+}
+
+impl Witness<'_> {
+    /// The witness as a request.
+    pub fn request(&self) -> Request<'_> {
+        Request::with_context(&self.principal, &self.action, &self.resource, &self.context)
+    }
+}
+
+/// The answer to a `Query`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryResult<'a> {
+    /// A request reaching the effect.
+    Witness(Witness<'a>),
+    /// No request reaches the effect.
+    Unreachable {
+        /// Number of representative requests checked.
+        explored: usize,
+    },
+}
+
+impl<'a> QueryResult<'a> {
+    /// The witness, if one was found.
+    pub fn witness(&self) -> Option<&Witness<'a>> {
+        match self {
+            QueryResult::Witness(witness) => Some(witness),
+            QueryResult::Unreachable { .. } => None,
+        }
+    }
+
+    /// Returns `true` if no request reaches the effect.
+    pub fn is_unreachable(&self) -> bool {
+        matches!(self, QueryResult::Unreachable { .. })
+    }
+}
+
+/// Representatives of every class of one request component.
+struct Candidates<'a> {
+    values: Vec<Cow<'a, str>>,
+}
+
+impl<'a> Candidates<'a> {
+    /// Constants from `matchers`, then a fresh value. Keeps only
+    /// candidates accepted by `keep`.
+    fn collect<'m>(
+        matchers: impl Iterator<Item = &'m Matcher<'a>>,
+        policy: &Policy<'a>,
+        keep: impl Fn(&str) -> bool,
+    ) -> Self
+    where
+        'a: 'm,
+    {
+        let constants = matcher_constants(matchers, policy.groups());
+        let fresh = fresh(&constants, policy.resource_separator());
+        let values = constants
+            .into_iter()
+            .map(Cow::Borrowed)
+            .chain([Cow::Owned(fresh)])
+            .filter(|value| keep(value))
+            .collect();
+        Candidates { values }
+    }
+}
+
+/// Every string `matchers` compare against and, if any matcher resolves
+/// groups, every grouped principal.
+pub(crate) fn matcher_constants<'a, 'm>(
+    matchers: impl Iterator<Item = &'m Matcher<'a>>,
+    groups: &GroupMap<'a>,
+) -> BTreeSet<&'a str>
+where
+    'a: 'm,
+{
+    let mut constants = BTreeSet::new();
+    let mut grouped = false;
+    for matcher in matchers {
+        match matcher {
+            Matcher::Any => {}
+            Matcher::Exact(value) => {
+                constants.insert(*value);
+            }
+            Matcher::OneOf(options) => constants.extend(options.iter().copied()),
+            Matcher::OneOfVec(options) => constants.extend(options.iter().copied()),
+            Matcher::Group(_) => grouped = true,
+        }
+    }
+    if grouped {
+        constants.extend(groups.principals());
+    }
+    constants
+}
+
+/// Every attribute the policy's conditions test, with one value per class
+/// of values the tests tell apart (absence aside), in rule order.
+pub(crate) fn representatives<'a>(policy: &Policy<'a>) -> Vec<(&'a str, Vec<Value<'a>>)> {
+    let mut attrs: Vec<(&'a str, Breakpoints<'a>)> = Vec::new();
+    for rule in 0..policy.rule_count() {
+        for op in policy.compiled().program(rule).into_iter().flatten() {
+            let (Op::Equals(attr, value)
+            | Op::NotEquals(attr, value)
+            | Op::GreaterThan(attr, value)
+            | Op::LessThan(attr, value)) = op
+            else {
+                match op {
+                    Op::IpInCidr(attr, cidr) => breakpoints(&mut attrs, attr).add_block(cidr),
+                    Op::TimeBetween(attr, start, end) => {
+                        breakpoints(&mut attrs, attr).add_times(&[*start, *end]);
+                    }
+                    Op::DayOfWeek(attr, _) => breakpoints(&mut attrs, attr).add_times(&[0]),
+                    _ => {}
+                }
+                continue;
+            };
+            breakpoints(&mut attrs, attr).add(value);
+        }
+    }
+    attrs
+        .into_iter()
+        .map(|(attr, breakpoints)| (attr, breakpoints.representatives()))
+        .collect()
+}
+
+/// The breakpoints of `attr`, added after the others if new.
+fn breakpoints<'a, 'b>(
+    attrs: &'b mut Vec<(&'a str, Breakpoints<'a>)>,
+    attr: &'a str,
+) -> &'b mut Breakpoints<'a> {
+    let index = match attrs.iter().position(|(k, _)| *k == attr) {
+        Some(index) => index,
+        None => {
+            attrs.push((attr, Breakpoints::default()));
+            attrs.len() - 1
+        }
+    };
+    &mut attrs[index].1
+}
+
+/// The constants one attribute is tested against.
+#[derive(Default)]
+struct Breakpoints<'a> {
+    /// Strings and booleans, only ever compared for equality.
+    constants: Vec<Value<'a>>,
+    ints: BTreeSet<i64>,
+    timestamps: BTreeSet<i64>,
+    durations: BTreeSet<u64>,
+    v4: BTreeSet<u32>,
+    v6: BTreeSet<u128>,
+    /// Window edges, in seconds since midnight, if any test is periodic.
+    times_of_day: BTreeSet<u32>,
+}
+
+impl<'a> Breakpoints<'a> {
+    fn add(&mut self, value: &Value<'a>) {
+        match *value {
+            Value::Int(i) => {
+                self.ints.insert(i);
+            }
+            Value::Timestamp(t) => {
+                self.timestamps.insert(t);
+            }
+            Value::Duration(d) => {
+                self.durations.insert(d);
+            }
+            Value::Ip(IpAddr::V4(ip)) => {
+                self.v4.insert(ip.to_bits());
+            }
+            Value::Ip(IpAddr::V6(ip)) => {
+                self.v6.insert(ip.to_bits());
+            }
+            _ => {
+                if !self.constants.contains(value) {
+                    self.constants.push(value.clone());
+                }
+            }
+        }
+    }
+
+    /// Add the first and last address of `cidr`.
+    fn add_block(&mut self, cidr: &Cidr) {
+        let prefix = u32::from(cidr.prefix());
+        match cidr.addr() {
+            IpAddr::V4(net) => {
+                let first = net.to_bits();
+                self.v4.insert(first);
+                self.v4
+                    .insert(first | u32::MAX.checked_shr(prefix).unwrap_or(0));
+            }
+            IpAddr::V6(net) => {
+                let first = net.to_bits();
+                self.v6.insert(first);
+                self.v6
+                    .insert(first | u128::MAX.checked_shr(prefix).unwrap_or(0));
+            }
+        }
+    }
+
+    /// Add window edges of a periodic timestamp test.
+    fn add_times(&mut self, times: &[u32]) {
+        // Days start at midnight
+        self.times_of_day.insert(0);
+        self.times_of_day
+            .extend(times.iter().map(|t| t % SECONDS_PER_DAY));
+    }
+
+    /// Each constant, then each breakpoint with its neighbours in order.
+    fn representatives(self) -> Vec<Value<'a>> {
+        let mut values = self.constants;
+        let i64s = |p: i64| [p.checked_sub(1), Some(p), p.checked_add(1)];
+        values.extend(around(&self.ints, i64s).map(Value::Int));
+        let timestamps = if self.times_of_day.is_empty() {
+            around(&self.timestamps, i64s).collect()
+        } else {
+            calendar(&self.timestamps, &self.times_of_day)
+        };
+        values.extend(timestamps.into_iter().map(Value::Timestamp));
+        let u64s = |p: u64| [p.checked_sub(1), Some(p), p.checked_add(1)];
+        values.extend(around(&self.durations, u64s).map(Value::Duration));
+        let v4 = |p: u32| [p.checked_sub(1), Some(p), p.checked_add(1)];
+        values.extend(around(&self.v4, v4).map(|ip| Value::Ip(Ipv4Addr::from_bits(ip).into())));
+        let v6 = |p: u128| [p.checked_sub(1), Some(p), p.checked_add(1)];
+        values.extend(around(&self.v6, v6).map(|ip| Value::Ip(Ipv6Addr::from_bits(ip).into())));
+        values
+    }
+}
+
+/// Timestamps reaching every class of periodic tests with window edges
+/// `times`, split by the ordered constants `points`.
+///
+/// Each constant is its own class. In every gap between constants (and
+/// before the first and after the last), the classes repeat weekly, so
+/// the gap's first week is tried: its ends, and each day at every edge
+/// and its neighbours.
+fn calendar(points: &BTreeSet<i64>, times: &BTreeSet<u32>) -> BTreeSet<i64> {
+    const WEEK: i64 = 7 * SECONDS_PER_DAY as i64;
+    let day = i64::from(SECONDS_PER_DAY);
+    let offsets: BTreeSet<i64> = times
+        .iter()
+        .flat_map(|&t| {
+            let t = i64::from(t);
+            [(t + day - 1) % day, t, (t + 1) % day]
+        })
+        .collect();
+
+    let mut gaps = Vec::new();
+    match (points.first(), points.last()) {
+        (Some(&first), Some(&last)) => {
+            gaps.push((first.saturating_sub(WEEK + 1), first.saturating_sub(1)));
+            let mut prev = first;
+            for &p in points.iter().skip(1) {
+                if prev + 1 < p {
+                    gaps.push((prev + 1, (p - 1).min(prev.saturating_add(WEEK + 1))));
+                }
+                prev = p;
+            }
+            gaps.push((last.saturating_add(1), last.saturating_add(WEEK + 1)));
+        }
+        _ => gaps.push((0, WEEK)),
+    }
+
+    let mut values = points.clone();
+    for (from, to) in gaps {
+        if from > to {
+            continue;
+        }
+        values.extend([from, to]);
+        for d in from.div_euclid(day)..=to.div_euclid(day) {
+            let midnight = d * day;
+            values.extend(
+                offsets
+                    .iter()
+                    .map(|o| midnight + o)
+                    .filter(|t| (from..=to).contains(t)),
+            );
+        }
+    }
+    values
+}
+
+/// `points` and their neighbours, sorted and without duplicates.
+fn around<T: Ord + Copy, const N: usize>(
+    points: &BTreeSet<T>,
+    near: impl Fn(T) -> [Option<T>; N],
+) -> impl Iterator<Item = T> {
+    let values: BTreeSet<T> = points.iter().flat_map(|&p| near(p)).flatten().collect();
+    values.into_iter()
+}
+
+/// A value equal to no constant and without ancestors: the shortest run of
+/// a character other than the separator.
+fn fresh(constants: &BTreeSet<&str>, separator: Option<char>) -> String {
+    let c = if separator == Some('_') { '~' } else { '_' };
+    let mut value = String::from(c);
+    while constants.contains(value.as_str()) {
+        value.push(c);
+    }
+    value
+}
+
+impl<'a> Policy<'a> {
+    /// Search for a request that satisfies `query`.
+    ///
+    /// Evaluates one representative request per class of equivalent
+    /// requests; see the module documentation. Requests whose evaluation
+    /// fails are not witnesses. Returns `QueryTooLarge` if more than the
+    /// query's `max_requests` would be needed, and `UndecidableQuery` if a
+    /// condition calls a custom predicate.
+    pub fn query<'q>(&self, query: &Query<'q>) -> Result<QueryResult<'q>, PolicyError>
+    where
+        'a: 'q,
+    {
+        #[cfg(feature = "custom-predicates")]
+        if let Some(rule) = (0..self.rule_count()).find(|&rule| {
+            let mut ops = self.compiled().program(rule).into_iter().flatten();
+            ops.any(|op| matches!(op, Op::Custom(..)))
+        }) {
+            return Err(PolicyError::UndecidableQuery { rule });
+        }
+
+        let rules = self.rules();
+        let separator = self.resource_separator();
+        let groups = self.groups();
+
+        let principals = Candidates::collect(
+            rules
+                .iter()
+                .map(|r| &r.target.principal)
+                .chain([&query.principal]),
+            self,
+            |v| query.principal.matches_in(v, groups),
+        );
+        let actions = Candidates::collect(
+            rules
+                .iter()
+                .map(|r| &r.target.action)
+                .chain([&query.action]),
+            self,
+            |v| query.action.matches_in(v, groups),
+        );
+        let resources = Candidates::collect(
+            rules
+                .iter()
+                .map(|r| &r.target.resource)
+                .chain([&query.resource]),
+            self,
+            |v| {
+                let mut resource = v;
+                loop {
+                    if query.resource.matches_in(resource, groups) {
+                        return true;
+                    }
+                    match separator.and_then(|sep| parent(resource, sep)) {
+                        Some(ancestor) => resource = ancestor,
+                        None => return false,
+                    }
+                }
+            },
+        );
+
+        // Each free attribute is absent or one representative per class
+        let attrs: Vec<(&'q str, Vec<Option<Value<'q>>>)> = representatives(self)
+            .into_iter()
+            .filter(|(attr, _)| query.context.iter().all(|(k, _)| k != attr))
+            .map(|(attr, values)| {
+                let values = [None].into_iter().chain(values.into_iter().map(Some));
+                (attr, values.collect())
+            })
+            .collect();
+
+        let dims: Vec<usize> = [
+            principals.values.len(),
+            actions.values.len(),
+            resources.values.len(),
+        ]
+        .into_iter()
+        .chain(attrs.iter().map(|(_, values)| values.len()))
+        .collect();
+        let total = dims
+            .iter()
+            .try_fold(1usize, |acc, len| acc.checked_mul(*len))
+            .unwrap_or(usize::MAX);
+        if total > query.max_requests {
+            return Err(PolicyError::QueryTooLarge {
+                max: query.max_requests,
+                actual: total,
+            });
+        }
+        if total == 0 {
+            return Ok(QueryResult::Unreachable { explored: 0 });
+        }
+
+        let mut cursor = vec![0; dims.len()];
+        let mut explored = 0;
+        loop {
+            let mut context = query.context.clone();
+            context.extend(
+                attrs
+                    .iter()
+                    .zip(&cursor[3..])
+                    .filter_map(|((attr, values), &i)| Some((*attr, values[i].clone()?))),
+            );
+            let principal = &principals.values[cursor[0]];
+            let action = &actions.values[cursor[1]];
+            let resource = &resources.values[cursor[2]];
+
+            explored += 1;
+            let request = Request::with_context(principal, action, resource, &context);
+            if let Ok(decision) = self.evaluate(&request) {
+                if query.accepts(&decision) {
+                    return Ok(QueryResult::Witness(Witness {
+                        principal: principal.clone(),
+                        action: action.clone(),
+                        resource: resource.clone(),
+                        context,
+                        decision,
+                    }));
+                }
+            }
+
+            if !advance(&mut cursor, |i| dims[i]) {
+                return Ok(QueryResult::Unreachable { explored });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, GroupMap, Rule, Target, Weekdays, NO_MATCHING_RULE};
+    use alloc::boxed::Box;
+
+    fn target(
+        principal: Matcher<'static>,
+        action: Matcher<'static>,
+        resource: Matcher<'static>,
+    ) -> Target<'static> {
+        Target {
+            principal,
+            action,
+            resource,
+        }
+    }
+
+    #[test]
+    fn test_invariant_holds_and_breaks() {
+        let build = |protected: &'static str| {
+            Policy::builder()
+                .resource_separator('/')
+                .rule(Rule::new(
+                    Effect::Deny,
+                    target(Matcher::Any, Matcher::Any, Matcher::Exact(protected)),
+                    Some(Condition::NotEquals {
+                        attr: "role",
+                        value: Value::String("admin"),
+                    }),
+                    ReasonCode(1),
+                ))
+                .rule(Rule::allow(
+                    target(Matcher::Any, Matcher::Exact("read"), Matcher::Any),
+                    ReasonCode(2),
+                ))
+                .build()
+                .unwrap()
+        };
+        let query = Query::new(Effect::Allow)
+            .resource(Matcher::Exact("secrets"))
+            .context("role", Value::String("intern"));
+
+        let policy = build("secrets");
+        assert_eq!(
+            policy.query(&query),
+            Ok(QueryResult::Unreachable { explored: 2 })
+        );
+
+        // Reading public data is fine, and the witness is a fresh resource
+        let public = Query::new(Effect::Allow).context("role", Value::String("intern"));
+        let witness = policy.query(&public).unwrap().witness().cloned().unwrap();
+        assert_eq!(witness.decision, Decision::allow(ReasonCode(2)));
+        assert_eq!(witness.resource, "_");
+
+        // Protecting only part of the tree breaks the invariant
+        let policy = build("secrets/private");
+        let witness = policy.query(&query).unwrap().witness().cloned().unwrap();
+        assert_eq!(witness.action, "read");
+        assert_eq!(witness.resource, "secrets");
+        assert_eq!(witness.context, [("role", Value::String("intern"))]);
+    }
+
+    #[test]
+    fn test_witness_uses_groups_hierarchy_and_absent_attributes() {
+        let policy = Policy::builder()
+            .resource_separator('/')
+            .groups(GroupMap::builder().member("bob", "staff").build().unwrap())
+            .rule(Rule::new(
+                Effect::Deny,
+                target(Matcher::Any, Matcher::Any, Matcher::Any),
+                Some(Condition::Equals {
+                    attr: "locked",
+                    value: Value::Bool(true),
+                }),
+                ReasonCode(1),
+            ))
+            .rule(Rule::allow(
+                target(
+                    Matcher::Group("staff"),
+                    Matcher::OneOf(&["write"]),
+                    Matcher::Exact("secrets/shared"),
+                ),
+                ReasonCode(2),
+            ))
+            .build()
+            .unwrap();
+
+        let query = Query::new(Effect::Allow).resource(Matcher::Exact("secrets"));
+        let witness = policy.query(&query).unwrap().witness().cloned().unwrap();
+        assert_eq!(witness.principal, "bob");
+        assert_eq!(witness.action, "write");
+        assert_eq!(witness.resource, "secrets/shared");
+        // "locked" is absent rather than false
+        assert!(witness.context.is_empty());
+        assert_eq!(policy.evaluate(&witness.request()), Ok(witness.decision));
+
+        // Reason filters and no-match denials are reachable too
+        let fallback = Query::new(Effect::Deny).reason(NO_MATCHING_RULE);
+        assert!(policy.query(&fallback).unwrap().witness().is_some());
+        let unused = Query::new(Effect::Allow).reason(ReasonCode(9));
+        assert!(policy.query(&unused).unwrap().is_unreachable());
+
+        assert_eq!(
+            policy.query(&query.max_requests(2)),
+            Err(PolicyError::QueryTooLarge { max: 2, actual: 16 })
+        );
+    }
+
+    #[test]
+    fn test_ordered_conditions() {
+        let gt = |value| Condition::GreaterThan {
+            attr: "level",
+            value,
+        };
+        let lt = |value| Condition::LessThan {
+            attr: "level",
+            value,
+        };
+        let build = |condition| {
+            Policy::builder()
+                .rule(Rule::new(
+                    Effect::Allow,
+                    Target::any(),
+                    Some(condition),
+                    ReasonCode(1),
+                ))
+                .build()
+                .unwrap()
+        };
+        let allowed = Query::new(Effect::Allow);
+        let witness_level = |policy: &Policy<'static>| {
+            let witness = policy.query(&allowed).unwrap().witness().cloned().unwrap();
+            assert!(policy.evaluate(&witness.request()).unwrap().is_allow());
+            witness.context
+        };
+
+        let policy = build(gt(Value::Int(5)));
+        assert_eq!(witness_level(&policy), [("level", Value::Int(6))]);
+
+        // Only the gap between the bounds is allowed
+        let policy = build(Condition::And(
+            Box::new(gt(Value::Duration(60))),
+            Box::new(lt(Value::Duration(62))),
+        ));
+        assert_eq!(witness_level(&policy), [("level", Value::Duration(61))]);
+
+        let policy = build(Condition::And(
+            Box::new(gt(Value::Int(5))),
+            Box::new(lt(Value::Int(6))),
+        ));
+        assert!(policy.query(&allowed).unwrap().is_unreachable());
+
+        // An equality constant splits an interval
+        let policy = build(Condition::And(
+            Box::new(gt(Value::Timestamp(10))),
+            Box::new(Condition::NotEquals {
+                attr: "level",
+                value: Value::Timestamp(11),
+            }),
+        ));
+        assert_eq!(witness_level(&policy), [("level", Value::Timestamp(12))]);
+    }
+
+    #[test]
+    fn test_cidr_conditions() {
+        let cidr = |block| Condition::IpInCidr {
+            attr: "source_ip",
+            cidr: block,
+        };
+        // Inside the office network, but not its guest subnet
+        let policy = Policy::builder()
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::And(
+                    Box::new(cidr("10.0.0.0/8")),
+                    Box::new(Condition::Not(Box::new(cidr("10.9.0.0/16")))),
+                )),
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Deny,
+                Target::any(),
+                Some(cidr("::/0")),
+                ReasonCode(2),
+            ))
+            .build()
+            .unwrap();
+
+        let witness = policy
+            .query(&Query::new(Effect::Allow))
+            .unwrap()
+            .witness()
+            .cloned()
+            .unwrap();
+        assert_eq!(
+            witness.context,
+            [("source_ip", Value::Ip([10, 0, 0, 0].into()))]
+        );
+        let guest = Query::new(Effect::Allow).context("source_ip", Value::Ip([10, 9, 1, 1].into()));
+        assert!(policy.query(&guest).unwrap().is_unreachable());
+
+        // The IPv6 deny is reachable too
+        let denied = Query::new(Effect::Deny).reason(ReasonCode(2));
+        let witness = policy.query(&denied).unwrap().witness().cloned().unwrap();
+        assert!(matches!(
+            witness.context[..],
+            [("source_ip", Value::Ip(IpAddr::V6(_)))]
+        ));
+    }
+
+    #[test]
+    fn test_time_conditions() {
+        // 2024-01-06 was a Saturday
+        const SATURDAY: i64 = 1_704_499_200;
+        let late = Condition::TimeBetween {
+            attr: "now",
+            start: 22 * 3600,
+            end: 2 * 3600,
+        };
+        let weekend = Condition::DayOfWeek {
+            attr: "now",
+            days: Weekdays::SATURDAY | Weekdays::SUNDAY,
+        };
+        let after = |t| Condition::GreaterThan {
+            attr: "now",
+            value: Value::Timestamp(t),
+        };
+        let before = |t| Condition::LessThan {
+            attr: "now",
+            value: Value::Timestamp(t),
+        };
+        let all = |conditions: Vec<Condition<'static>>| {
+            let mut conditions = conditions.into_iter();
+            let first = conditions.next().unwrap();
+            conditions.fold(first, |a, b| Condition::And(Box::new(a), Box::new(b)))
+        };
+        let query = |condition| {
+            let policy = Policy::builder()
+                .rule(Rule::new(
+                    Effect::Allow,
+                    Target::any(),
+                    Some(condition),
+                    ReasonCode(1),
+                ))
+                .build()
+                .unwrap();
+            let result = policy.query(&Query::new(Effect::Allow)).unwrap();
+            if let Some(witness) = result.witness() {
+                assert!(policy.evaluate(&witness.request()).unwrap().is_allow());
+            }
+            result.witness().map(|w| w.context.clone())
+        };
+
+        // Late on a weekend night, with no absolute bounds
+        assert!(query(all(vec![late.clone(), weekend.clone()])).is_some());
+
+        // Weekdays only, within one Saturday: nothing
+        let saturday = vec![after(SATURDAY), before(SATURDAY + 86_400)];
+        let weekdays = Condition::DayOfWeek {
+            attr: "now",
+            days: Weekdays::WORKDAYS,
+        };
+        assert_eq!(
+            query(all([saturday.clone(), vec![weekdays]].concat())),
+            None
+        );
+
+        // Office hours on that Saturday
+        let office = Condition::TimeBetween {
+            attr: "now",
+            start: 9 * 3600,
+            end: 17 * 3600,
+        };
+        assert!(query(all([saturday, vec![office, weekend]].concat())).is_some());
+    }
+}
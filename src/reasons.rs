@@ -0,0 +1,556 @@
+//! Human-readable names for reason codes.
+//!
+//! A `ReasonRegistry` maps each `ReasonCode` to a stable identifier (for
+//! logs and dashboards) and a description (for on-call engineers). Codes and
+//! identifiers are checked for duplicates when the registry is built, so a
+//! registry can never give two meanings to one code.
+//!
+//! `declare_reasons!` declares the codes themselves as constants and checks
+//! them for duplicates at compile time:
+//!
+//! ```
+//! gate0::declare_reasons! {
+//!     /// Reasons used by the document service.
+//!     pub REASONS = {
+//!         BLOCKED_USER = 1 => "User is on the blocklist",
+//!         PUBLIC_READ = 2 => "Public read access",
+//!     };
+//! }
+//!
+//! assert_eq!(BLOCKED_USER, gate0::ReasonCode(1));
+//! let registry = gate0::ReasonRegistry::from_lists(&[REASONS]).unwrap();
+//! assert_eq!(registry.name(PUBLIC_READ), Some("PUBLIC_READ"));
+//! ```
+//!
+//! Codes declared in different modules can collide without either module
+//! noticing. Listing every module's reasons in one `assert_unique` catches
+//! that at compile time too:
+//!
+//! ```compile_fail
+//! mod auth {
+//!     gate0::declare_reasons! {
+//!         pub REASONS = { BLOCKED_USER = 1 => "User is on the blocklist" };
+//!     }
+//! }
+//! mod docs {
+//!     gate0::declare_reasons! {
+//!         pub REASONS = { PUBLIC_READ = 1 => "Public read access" };
+//!     }
+//! }
+//!
+//! // error: reason code 1 is declared more than once (PUBLIC_READ)
+//! const _: () = gate0::ReasonInfo::assert_unique(&[auth::REASONS, docs::REASONS]);
+//! ```
+//!
+//! Better still, give each module its own `ReasonDomain`: its codes are
+//! then namespaced (`REASONS in DOMAIN = { .. }`), and only modules sharing
+//! a domain have to agree on numbers.
+//!
+//! ```
+//! mod auth {
+//!     pub const DOMAIN: gate0::ReasonDomain = gate0::ReasonDomain(1);
+//!     gate0::declare_reasons! {
+//!         pub REASONS in DOMAIN = { BLOCKED_USER = 1 => "User is on the blocklist" };
+//!     }
+//! }
+//! mod docs {
+//!     pub const DOMAIN: gate0::ReasonDomain = gate0::ReasonDomain(2);
+//!     gate0::declare_reasons! {
+//!         pub REASONS in DOMAIN = { PUBLIC_READ = 1 => "Public read access" };
+//!     }
+//! }
+//!
+//! const _: () = gate0::ReasonInfo::assert_unique(&[auth::REASONS, docs::REASONS]);
+//! assert_ne!(auth::BLOCKED_USER, docs::PUBLIC_READ);
+//! ```
+
+use alloc::collections::BTreeSet;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::const_fmt::ConstMessage;
+use crate::error::PolicyError;
+use crate::types::{Decision, Effect, ReasonCode, DEFAULT_ALLOW, NO_MATCHING_RULE};
+
+/// Name and description of one reason code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReasonInfo<'a> {
+    /// The reason code.
+    pub code: ReasonCode,
+    /// Stable identifier, e.g. `"blocked_user"`.
+    pub name: &'a str,
+    /// Human description, e.g. `"User is on the block list"`.
+    pub description: &'a str,
+}
+
+impl ReasonInfo<'_> {
+    /// Panic if two entries across `lists` share a code or a name.
+    ///
+    /// Meant for `const` items, where the panic fails the build; the
+    /// message names the later of the two entries.
+    pub const fn assert_unique(lists: &[&[ReasonInfo<'_>]]) {
+        let mut i = 0;
+        while i < lists.len() {
+            let mut j = 0;
+            while j < lists[i].len() {
+                let entry = &lists[i][j];
+                if let Some(earlier) = find_earlier(lists, i, j) {
+                    let what = if earlier.code.0 == entry.code.0 {
+                        " is declared more than once ("
+                    } else {
+                        " reuses the name of another code ("
+                    };
+                    ConstMessage::new()
+                        .str("reason code ")
+                        .int(entry.code.0 as u64)
+                        .str(what)
+                        .str(entry.name)
+                        .str(")")
+                        .panic();
+                }
+                j += 1;
+            }
+            i += 1;
+        }
+    }
+}
+
+/// The first entry before `lists[list][index]` with the same code or name.
+const fn find_earlier<'r, 'a>(
+    lists: &'r [&'r [ReasonInfo<'a>]],
+    list: usize,
+    index: usize,
+) -> Option<&'r ReasonInfo<'a>> {
+    let entry = &lists[list][index];
+    let mut i = 0;
+    while i <= list {
+        let end = if i == list { index } else { lists[i].len() };
+        let mut j = 0;
+        while j < end {
+            let other = &lists[i][j];
+            if other.code.0 == entry.code.0 || str_eq(other.name, entry.name) {
+                return Some(other);
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+    None
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+impl fmt::Display for ReasonInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}): {}",
+            self.name,
+            self.code.value(),
+            self.description
+        )
+    }
+}
+
+/// Declare reason codes as constants, with a list of their `ReasonInfo`
+/// entries for building a `ReasonRegistry`.
+///
+/// ```
+/// gate0::declare_reasons! {
+///     pub REASONS = {
+///         BLOCKED_USER = 1 => "User is on the blocklist",
+///         PUBLIC_READ = 2 => "Public read access",
+///     };
+/// }
+/// ```
+///
+/// expands to `pub const BLOCKED_USER: ReasonCode = ReasonCode(1);` (with
+/// the description as its doc comment), the same for `PUBLIC_READ`, and
+/// `pub const REASONS: &[ReasonInfo<'static>]`. Each entry's name is the
+/// constant's name. Duplicate codes or names fail the build; see
+/// `ReasonInfo::assert_unique` for checking across invocations.
+///
+/// `REASONS in DOMAIN` declares the codes in a `ReasonDomain`, so modules
+/// that each number their reasons from 1 do not collide:
+///
+/// ```
+/// const BILLING: gate0::ReasonDomain = gate0::ReasonDomain(3);
+///
+/// gate0::declare_reasons! {
+///     pub REASONS in BILLING = {
+///         INVOICE_OVERDUE = 1 => "Account has an overdue invoice",
+///     };
+/// }
+///
+/// assert_eq!(INVOICE_OVERDUE, gate0::ReasonCode::namespaced(3, 1));
+/// ```
+#[macro_export]
+macro_rules! declare_reasons {
+    (
+        $(#[$meta:meta])*
+        $vis:vis $list:ident in $domain:path = {
+            $(
+                $(#[$entry_meta:meta])*
+                $name:ident = $code:literal => $description:literal
+            ),* $(,)?
+        };
+    ) => {
+        $(
+            #[doc = $description]
+            $(#[$entry_meta])*
+            $vis const $name: $crate::ReasonCode = $domain.reason($code);
+        )*
+
+        $(#[$meta])*
+        $vis const $list: &[$crate::ReasonInfo<'static>] = &[
+            $(
+                $crate::ReasonInfo {
+                    code: $name,
+                    name: stringify!($name),
+                    description: $description,
+                },
+            )*
+        ];
+
+        const _: () = $crate::ReasonInfo::assert_unique(&[$list]);
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis $list:ident = {
+            $(
+                $(#[$entry_meta:meta])*
+                $name:ident = $code:literal => $description:literal
+            ),* $(,)?
+        };
+    ) => {
+        $(
+            #[doc = $description]
+            $(#[$entry_meta])*
+            $vis const $name: $crate::ReasonCode = $crate::ReasonCode($code);
+        )*
+
+        $(#[$meta])*
+        $vis const $list: &[$crate::ReasonInfo<'static>] = &[
+            $(
+                $crate::ReasonInfo {
+                    code: $name,
+                    name: stringify!($name),
+                    description: $description,
+                },
+            )*
+        ];
+
+        const _: () = $crate::ReasonInfo::assert_unique(&[$list]);
+    };
+}
+
+/// Built-in entry used when `NO_MATCHING_RULE` is not registered.
+const NO_MATCHING_RULE_INFO: ReasonInfo<'static> = ReasonInfo {
+    code: NO_MATCHING_RULE,
+    name: "no_matching_rule",
+    description: "No rule matched the request",
+};
+
+/// Built-in entry used when `DEFAULT_ALLOW` is not registered.
+const DEFAULT_ALLOW_INFO: ReasonInfo<'static> = ReasonInfo {
+    code: DEFAULT_ALLOW,
+    name: "default_allow",
+    description: "No rule matched and the policy allows by default",
+};
+
+/// A validated mapping from reason codes to names and descriptions.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Decision, ReasonCode, ReasonRegistry};
+///
+/// const BLOCKED: ReasonCode = ReasonCode(1);
+///
+/// let registry = ReasonRegistry::builder()
+///     .reason(BLOCKED, "blocked_user", "User is on the block list")
+///     .build()
+///     .unwrap();
+///
+/// let text = Decision::deny(BLOCKED).describe(&registry).to_string();
+/// assert_eq!(text, "DENY blocked_user (1): User is on the block list");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ReasonRegistry<'a> {
+    /// Sorted by code.
+    entries: Vec<ReasonInfo<'a>>,
+}
+
+impl<'a> ReasonRegistry<'a> {
+    /// Create a new registry builder.
+    pub fn builder() -> ReasonRegistryBuilder<'a> {
+        ReasonRegistryBuilder::new()
+    }
+
+    /// Create a registry from entries.
+    ///
+    /// Returns an error if two entries share a code or a name.
+    pub fn new(mut entries: Vec<ReasonInfo<'a>>) -> Result<Self, PolicyError> {
+        let mut names = BTreeSet::new();
+        for entry in &entries {
+            if !names.insert(entry.name) {
+                return Err(PolicyError::DuplicateReasonName {
+                    code: entry.code.value(),
+                });
+            }
+        }
+
+        entries.sort_by_key(|e| e.code.value());
+        if let Some(pair) = entries.windows(2).find(|w| w[0].code == w[1].code) {
+            return Err(PolicyError::DuplicateReasonCode {
+                code: pair[0].code.value(),
+            });
+        }
+
+        Ok(ReasonRegistry { entries })
+    }
+
+    /// Create a registry from several lists of entries, such as the lists
+    /// `declare_reasons!` declares in each module.
+    ///
+    /// Returns an error if two entries share a code or a name.
+    pub fn from_lists(lists: &[&[ReasonInfo<'a>]]) -> Result<Self, PolicyError> {
+        ReasonRegistry::new(lists.iter().flat_map(|list| list.iter().copied()).collect())
+    }
+
+    /// Look up a reason code.
+    ///
+    /// `NO_MATCHING_RULE` and `DEFAULT_ALLOW` resolve to built-in entries
+    /// unless registered.
+    pub fn get(&self, code: ReasonCode) -> Option<&ReasonInfo<'a>> {
+        match self
+            .entries
+            .binary_search_by_key(&code.value(), |e| e.code.value())
+        {
+            Ok(i) => self.entries.get(i),
+            Err(_) if code == NO_MATCHING_RULE => Some(&NO_MATCHING_RULE_INFO),
+            Err(_) if code == DEFAULT_ALLOW => Some(&DEFAULT_ALLOW_INFO),
+            Err(_) => None,
+        }
+    }
+
+    /// The stable identifier for a reason code.
+    pub fn name(&self, code: ReasonCode) -> Option<&'a str> {
+        self.get(code).map(|e| e.name)
+    }
+
+    /// The description for a reason code.
+    pub fn description(&self, code: ReasonCode) -> Option<&'a str> {
+        self.get(code).map(|e| e.description)
+    }
+
+    /// Number of registered reasons.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no reasons are registered.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Registered reasons, ordered by code.
+    pub fn iter(&self) -> impl Iterator<Item = &ReasonInfo<'a>> {
+        self.entries.iter()
+    }
+}
+
+/// Builder for constructing reason registries.
+#[derive(Debug, Default)]
+pub struct ReasonRegistryBuilder<'a> {
+    entries: Vec<ReasonInfo<'a>>,
+}
+
+impl<'a> ReasonRegistryBuilder<'a> {
+    /// Create a new registry builder.
+    pub fn new() -> Self {
+        ReasonRegistryBuilder {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Register a reason code.
+    pub fn reason(mut self, code: ReasonCode, name: &'a str, description: &'a str) -> Self {
+        self.entries.push(ReasonInfo {
+            code,
+            name,
+            description,
+        });
+        self
+    }
+
+    /// Build the registry, rejecting duplicate codes and names.
+    pub fn build(self) -> Result<ReasonRegistry<'a>, PolicyError> {
+        ReasonRegistry::new(self.entries)
+    }
+}
+
+impl Decision {
+    /// Pair this decision with its registry entry for display.
+    ///
+    /// Formats as `DENY blocked_user (1): User is on the block list`, or
+    /// `DENY reason 42 (unregistered)` when the code is unknown.
+    pub fn describe<'d, 'r, 'a>(
+        &'d self,
+        registry: &'r ReasonRegistry<'a>,
+    ) -> DescribedDecision<'d, 'r, 'a> {
+        DescribedDecision {
+            decision: self,
+            info: registry.get(self.reason),
+        }
+    }
+}
+
+/// A decision with its reason resolved; see `Decision::describe`.
+#[derive(Debug, Clone, Copy)]
+pub struct DescribedDecision<'d, 'r, 'a> {
+    decision: &'d Decision,
+    info: Option<&'r ReasonInfo<'a>>,
+}
+
+impl DescribedDecision<'_, '_, '_> {
+    /// The registry entry, if the reason code is registered.
+    pub fn info(&self) -> Option<&ReasonInfo<'_>> {
+        self.info
+    }
+}
+
+impl fmt::Display for DescribedDecision<'_, '_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let effect = match self.decision.effect {
+            Effect::Allow => "ALLOW",
+            Effect::Deny => "DENY",
+        };
+        let code = self.decision.reason.value();
+        match self.info {
+            Some(info) => write!(f, "{} {}", effect, info),
+            None => write!(f, "{} reason {} (unregistered)", effect, code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_lookup_and_describe() {
+        let registry = ReasonRegistry::builder()
+            .reason(ReasonCode(20), "read_ok", "Public read access")
+            .reason(ReasonCode(10), "blocked", "User is blocked")
+            .build()
+            .unwrap();
+
+        assert_eq!(registry.len(), 2);
+        assert_eq!(registry.name(ReasonCode(10)), Some("blocked"));
+        assert_eq!(
+            registry.description(ReasonCode(20)),
+            Some("Public read access")
+        );
+        assert_eq!(registry.get(ReasonCode(30)), None);
+        let codes: Vec<u32> = registry.iter().map(|e| e.code.value()).collect();
+        assert_eq!(codes, [10, 20]);
+
+        assert_eq!(
+            Decision::allow(ReasonCode(20))
+                .describe(&registry)
+                .to_string(),
+            "ALLOW read_ok (20): Public read access"
+        );
+        assert_eq!(
+            Decision::deny(ReasonCode(42))
+                .describe(&registry)
+                .to_string(),
+            "DENY reason 42 (unregistered)"
+        );
+        assert_eq!(
+            Decision::deny(NO_MATCHING_RULE)
+                .describe(&registry)
+                .to_string(),
+            "DENY no_matching_rule (0): No rule matched the request"
+        );
+    }
+
+    mod declared {
+        crate::declare_reasons! {
+            pub REASONS = {
+                BLOCKED_USER = 1 => "User is on the blocklist",
+                PUBLIC_READ = 2 => "Public read access",
+            };
+        }
+    }
+
+    #[test]
+    fn test_declare_reasons() {
+        use declared::*;
+
+        assert_eq!(BLOCKED_USER, ReasonCode(1));
+        assert_eq!(PUBLIC_READ, ReasonCode(2));
+        const OTHER: &[ReasonInfo<'static>] = &[ReasonInfo {
+            code: ReasonCode(3),
+            name: "OTHER",
+            description: "Other",
+        }];
+        const _: () = ReasonInfo::assert_unique(&[REASONS, OTHER]);
+
+        let registry = ReasonRegistry::from_lists(&[REASONS, OTHER]).unwrap();
+        assert_eq!(registry.len(), 3);
+        assert_eq!(
+            registry.get(BLOCKED_USER).unwrap().to_string(),
+            "BLOCKED_USER (1): User is on the blocklist"
+        );
+        assert_eq!(
+            ReasonRegistry::from_lists(&[REASONS, REASONS]).unwrap_err(),
+            PolicyError::DuplicateReasonName { code: 1 }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "reason code 2 reuses the name of another code (BLOCKED_USER)")]
+    fn test_assert_unique_names() {
+        ReasonInfo::assert_unique(&[
+            declared::REASONS,
+            &[ReasonInfo {
+                code: ReasonCode(2),
+                name: "BLOCKED_USER",
+                description: "",
+            }],
+        ]);
+    }
+
+    #[test]
+    fn test_duplicates_rejected() {
+        let err = ReasonRegistry::builder()
+            .reason(ReasonCode(1), "a", "")
+            .reason(ReasonCode(1), "b", "")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, PolicyError::DuplicateReasonCode { code: 1 });
+
+        let err = ReasonRegistry::builder()
+            .reason(ReasonCode(1), "same", "")
+            .reason(ReasonCode(2), "same", "")
+            .build()
+            .unwrap_err();
+        assert_eq!(err, PolicyError::DuplicateReasonName { code: 2 });
+    }
+}
@@ -0,0 +1,209 @@
+//! Duplicate and redundant rule detection.
+//!
+//! Generated policies accumulate rules that can never decide anything:
+//! exact copies, and rules whose every match is already matched by an
+//! earlier rule of the same effect. Such a rule never supplies the winning
+//! reason, yet it is checked on every evaluation. `PolicyBuilder::
+//! build_with_warnings` reports them without rejecting the policy.
+//!
+//! The check is conservative: a warning is only issued when coverage is
+//! certain from the rules alone (an `Any` or larger option list in every
+//! target field, and no condition or the same condition on the earlier
+//! rule). Rules that are redundant for subtler reasons are not reported.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::optimize::finite;
+use crate::policy::Rule;
+use crate::target::Matcher;
+
+/// A rule that can never decide a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleWarning {
+    /// The rule is identical to an earlier rule.
+    Duplicate {
+        /// Index of the duplicate.
+        rule: usize,
+        /// Index of the earlier identical rule.
+        original: usize,
+    },
+    /// Every request the rule matches is matched by an earlier rule with
+    /// the same effect.
+    Shadowed {
+        /// Index of the shadowed rule.
+        rule: usize,
+        /// Index of the earlier rule that covers it.
+        by: usize,
+    },
+}
+
+impl RuleWarning {
+    /// Index of the rule the warning is about.
+    pub fn rule(&self) -> usize {
+        match self {
+            RuleWarning::Duplicate { rule, .. } | RuleWarning::Shadowed { rule, .. } => *rule,
+        }
+    }
+}
+
+impl fmt::Display for RuleWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuleWarning::Duplicate { rule, original } => {
+                write!(f, "rule {} duplicates rule {}", rule, original)
+            }
+            RuleWarning::Shadowed { rule, by } => {
+                write!(f, "rule {} is shadowed by rule {}", rule, by)
+            }
+        }
+    }
+}
+
+/// Warnings for every redundant rule, in rule order.
+pub(crate) fn find(rules: &[Rule<'_>], separator: Option<char>) -> Vec<RuleWarning> {
+    let mut warnings = Vec::new();
+    for (index, rule) in rules.iter().enumerate() {
+        let earlier = rules[..index].iter().enumerate();
+        if let Some((original, _)) = earlier.clone().find(|(_, e)| identical(e, rule)) {
+            warnings.push(RuleWarning::Duplicate {
+                rule: index,
+                original,
+            });
+        } else if let Some((by, _)) = earlier.clone().find(|(_, e)| covers(e, rule, separator)) {
+            warnings.push(RuleWarning::Shadowed { rule: index, by });
+        }
+    }
+    warnings
+}
+
+fn identical(a: &Rule<'_>, b: &Rule<'_>) -> bool {
+    a.effect == b.effect
+        && a.reason == b.reason
+        && a.target == b.target
+        && a.condition == b.condition
+}
+
+/// Returns `true` if `earlier` matches every request `later` matches.
+fn covers(earlier: &Rule<'_>, later: &Rule<'_>, separator: Option<char>) -> bool {
+    earlier.effect == later.effect
+        && (earlier.condition.is_none() || earlier.condition == later.condition)
+        && matcher_covers(&earlier.target.principal, &later.target.principal, None)
+        && matcher_covers(&earlier.target.action, &later.target.action, None)
+        && matcher_covers(&earlier.target.resource, &later.target.resource, separator)
+}
+
+fn matcher_covers(outer: &Matcher<'_>, inner: &Matcher<'_>, separator: Option<char>) -> bool {
+    if matches!(outer, Matcher::Any) || outer == inner {
+        return true;
+    }
+    let (Some(outer), Some(inner)) = (finite(outer), finite(inner)) else {
+        return false;
+    };
+    // With a hierarchy, a value is also covered by any of its ancestors
+    inner.iter().all(|value| {
+        outer.iter().any(|o| {
+            value == o
+                || separator.is_some_and(|sep| {
+                    value
+                        .strip_prefix(o)
+                        .is_some_and(|rest| rest.starts_with(sep))
+                })
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, Policy, ReasonCode, Target, Value};
+
+    fn rule(
+        effect: Effect,
+        principal: Matcher<'static>,
+        resource: Matcher<'static>,
+        reason: u32,
+    ) -> Rule<'static> {
+        Rule::new(
+            effect,
+            Target {
+                principal,
+                action: Matcher::Any,
+                resource,
+            },
+            None,
+            ReasonCode(reason),
+        )
+    }
+
+    #[test]
+    fn test_duplicates_and_shadowed_rules() {
+        let admin = Condition::Equals {
+            attr: "role",
+            value: Value::String("admin"),
+        };
+        let mut guarded = rule(Effect::Allow, Matcher::Any, Matcher::Exact("docs"), 3);
+        guarded.condition = Some(admin.clone());
+        let mut guarded_child = rule(Effect::Allow, Matcher::Any, Matcher::Exact("docs/a"), 4);
+        guarded_child.condition = Some(admin);
+
+        let (policy, warnings) = Policy::builder()
+            .resource_separator('/')
+            .rule(rule(
+                Effect::Allow,
+                Matcher::OneOf(&["alice", "bob"]),
+                Matcher::Any,
+                1,
+            ))
+            .rule(rule(
+                Effect::Allow,
+                Matcher::Exact("alice"),
+                Matcher::Any,
+                2,
+            ))
+            // Different effect: never covered by an allow
+            .rule(rule(Effect::Deny, Matcher::Exact("alice"), Matcher::Any, 2))
+            .rule(guarded.clone())
+            .rule(guarded_child)
+            .rule(guarded)
+            .build_with_warnings()
+            .unwrap();
+
+        assert_eq!(policy.rule_count(), 6);
+        assert_eq!(
+            warnings,
+            [
+                RuleWarning::Shadowed { rule: 1, by: 0 },
+                RuleWarning::Shadowed { rule: 4, by: 3 },
+                RuleWarning::Duplicate {
+                    rule: 5,
+                    original: 3
+                },
+            ]
+        );
+        assert_eq!(warnings[2].to_string(), "rule 5 duplicates rule 3");
+    }
+
+    #[test]
+    fn test_conditions_and_partial_overlap_are_not_shadowing() {
+        let mut conditional = rule(Effect::Allow, Matcher::Any, Matcher::Any, 1);
+        conditional.condition = Some(Condition::Equals {
+            attr: "mfa",
+            value: Value::Bool(true),
+        });
+        let rules = [
+            conditional,
+            rule(Effect::Allow, Matcher::Exact("alice"), Matcher::Any, 2),
+            rule(
+                Effect::Allow,
+                Matcher::OneOf(&["alice", "bob"]),
+                Matcher::Any,
+                3,
+            ),
+            // Without a hierarchy, "docs" says nothing about "docs/a"
+            rule(Effect::Deny, Matcher::Any, Matcher::Exact("docs"), 4),
+            rule(Effect::Deny, Matcher::Any, Matcher::Exact("docs/a"), 5),
+        ];
+        assert!(find(&rules, None).is_empty());
+    }
+}
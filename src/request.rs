@@ -0,0 +1,238 @@
+//! Typed request construction.
+//!
+//! Hand-assembled context slices make it easy to repeat a key (only the
+//! first occurrence is ever read) or exceed the context limit without
+//! noticing until evaluation. `RequestBuilder` collects typed attributes and
+//! checks both rules when the request is built.
+//!
+//! For contexts written inline, `ctx!` builds the slice directly:
+//!
+//! ```
+//! use gate0::{ctx, Request, Value};
+//!
+//! let tenant = String::from("acme");
+//! let context = ctx! {
+//!     "role" => "admin",
+//!     "mfa" => true,
+//!     "level" => 3,
+//!     "tenant" => tenant.as_str(),
+//!     "region" => Value::String("eu"),
+//! };
+//! let request = Request::with_context("alice", "read", "doc", context);
+//! assert_eq!(request.get_attr("tenant"), Some(&Value::String("acme")));
+//! ```
+
+use alloc::vec::Vec;
+
+use crate::error::PolicyError;
+use crate::policy::PolicyConfig;
+use crate::types::Request;
+use crate::value::Value;
+
+/// Build a request context inline.
+///
+/// `ctx! { "key" => value, .. }` is a `&[(&str, Value)]` (an array
+/// reference that coerces to one), with each value converted by
+/// `Value::from`: a `bool`, `i64`, `&str`, or `Value`. Values can be any
+/// expression. Keys are not checked for duplicates; use `RequestBuilder`
+/// when the keys are not fixed.
+///
+/// `ctx!(owned "key" => value, ..)` builds a `Vec<(String, OwnedValue)>`
+/// instead, as stored in `RequestData::context`, converting each value with
+/// `OwnedValue::from` (which also accepts `String`).
+///
+/// ```
+/// use gate0::{ctx, OwnedValue};
+///
+/// let context = ctx!(owned "role" => String::from("admin"), "mfa" => true);
+/// assert_eq!(context[1], ("mfa".to_string(), OwnedValue::Bool(true)));
+/// ```
+#[macro_export]
+macro_rules! ctx {
+    (owned $($key:expr => $value:expr),* $(,)?) => {
+        $crate::__private::Vec::<($crate::__private::String, $crate::OwnedValue)>::from([$(
+            (
+                $crate::__private::String::from($key),
+                $crate::OwnedValue::from($value),
+            ),
+        )*])
+    };
+    ($($key:expr => $value:expr),* $(,)?) => {
+        &[$(($key, $crate::Value::from($value))),*]
+    };
+}
+
+/// Builder for requests with typed context attributes.
+///
+/// `build()` borrows the builder, so keep the builder alive while the
+/// request is in use.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Request, RequestBuilder};
+///
+/// let builder = Request::builder("alice", "read", "doc")
+///     .attr_str("role", "admin")
+///     .attr_bool("mfa", true)
+///     .attr_i64("tenant", 42);
+/// let request = builder.build().unwrap();
+///
+/// assert_eq!(request.context.len(), 3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RequestBuilder<'a> {
+    principal: &'a str,
+    action: &'a str,
+    resource: &'a str,
+    context: Vec<(&'a str, Value<'a>)>,
+    max_context_attrs: usize,
+}
+
+impl<'a> RequestBuilder<'a> {
+    /// Create a builder for a request with no context.
+    ///
+    /// The context limit defaults to `PolicyConfig::default().max_context_attrs`.
+    pub fn new(principal: &'a str, action: &'a str, resource: &'a str) -> Self {
+        RequestBuilder {
+            principal,
+            action,
+            resource,
+            context: Vec::new(),
+            max_context_attrs: PolicyConfig::default().max_context_attrs,
+        }
+    }
+
+    /// Set the maximum number of context attributes.
+    ///
+    /// Use the same value as the policy's `PolicyConfig::max_context_attrs`.
+    pub fn max_context_attrs(mut self, max: usize) -> Self {
+        self.max_context_attrs = max;
+        self
+    }
+
+    /// Add a context attribute.
+    pub fn attr(mut self, key: &'a str, value: Value<'a>) -> Self {
+        self.context.push((key, value));
+        self
+    }
+
+    /// Add a string attribute.
+    pub fn attr_str(self, key: &'a str, value: &'a str) -> Self {
+        self.attr(key, Value::String(value))
+    }
+
+    /// Add a boolean attribute.
+    pub fn attr_bool(self, key: &'a str, value: bool) -> Self {
+        self.attr(key, Value::Bool(value))
+    }
+
+    /// Add an integer attribute.
+    pub fn attr_i64(self, key: &'a str, value: i64) -> Self {
+        self.attr(key, Value::Int(value))
+    }
+
+    /// Build the request.
+    ///
+    /// Returns an error if:
+    /// - The context has more than `max_context_attrs` attributes
+    /// - A key appears more than once
+    pub fn build(&self) -> Result<Request<'_>, PolicyError> {
+        if self.context.len() > self.max_context_attrs {
+            return Err(PolicyError::ContextTooLarge {
+                max: self.max_context_attrs,
+                actual: self.context.len(),
+            });
+        }
+
+        // Context is bounded, so a quadratic scan is fine and allocation-free
+        for (index, (key, _)) in self.context.iter().enumerate() {
+            if self.context[..index].iter().any(|(k, _)| k == key) {
+                return Err(PolicyError::DuplicateContextKey { index });
+            }
+        }
+
+        Ok(Request::with_context(
+            self.principal,
+            self.action,
+            self.resource,
+            &self.context,
+        ))
+    }
+}
+
+impl<'a> Request<'a> {
+    /// Create a request builder (see `RequestBuilder`).
+    pub fn builder(principal: &'a str, action: &'a str, resource: &'a str) -> RequestBuilder<'a> {
+        RequestBuilder::new(principal, action, resource)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_typed_context() {
+        let builder = Request::builder("alice", "read", "doc")
+            .attr_str("role", "admin")
+            .attr_bool("mfa", true)
+            .attr_i64("level", 3);
+        let request = builder.build().unwrap();
+
+        assert_eq!(request.principal, "alice");
+        assert_eq!(request.get_attr("role"), Some(&Value::String("admin")));
+        assert_eq!(request.get_attr("mfa"), Some(&Value::Bool(true)));
+        assert_eq!(request.get_attr("level"), Some(&Value::Int(3)));
+    }
+
+    #[test]
+    fn test_rejects_duplicate_keys() {
+        let builder = Request::builder("alice", "read", "doc")
+            .attr_str("role", "admin")
+            .attr_bool("mfa", true)
+            .attr_str("role", "viewer");
+        assert_eq!(
+            builder.build().unwrap_err(),
+            PolicyError::DuplicateContextKey { index: 2 }
+        );
+    }
+
+    #[test]
+    fn test_enforces_context_limit() {
+        let builder = Request::builder("alice", "read", "doc")
+            .max_context_attrs(1)
+            .attr_bool("a", true)
+            .attr_bool("b", true);
+        assert_eq!(
+            builder.build().unwrap_err(),
+            PolicyError::ContextTooLarge { max: 1, actual: 2 }
+        );
+    }
+
+    #[test]
+    fn test_ctx_macro() {
+        let tenant = alloc::string::String::from("acme");
+        let level = 2;
+        let context = crate::ctx! {
+            "role" => "admin",
+            "level" => level + 1,
+            "tenant" => tenant.as_str(),
+            "mfa" => Value::Bool(true),
+        };
+        let request = Request::with_context("alice", "read", "doc", context);
+        assert_eq!(request.context.len(), 4);
+        assert_eq!(request.get_attr("level"), Some(&Value::Int(3)));
+        assert_eq!(request.get_attr("tenant"), Some(&Value::String("acme")));
+
+        let empty: &[(&str, Value)] = crate::ctx! {};
+        assert!(empty.is_empty());
+
+        let owned = crate::ctx!(owned "role" => "admin", "level" => 3, "tenant" => tenant.clone(),);
+        let borrowed: Vec<(&str, Value)> = owned
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_value()))
+            .collect();
+        assert_eq!(borrowed, context[..3]);
+    }
+}
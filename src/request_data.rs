@@ -0,0 +1,317 @@
+//! Owned requests with a stable JSON shape (feature `serde`).
+//!
+//! Services that receive authorization requests over the wire all need the
+//! same mapping from JSON to a `Request`. `RequestData` is that mapping:
+//!
+//! ```json
+//! {
+//!   "principal": "alice",
+//!   "action": "read",
+//!   "resource": "doc",
+//!   "context": { "role": "admin", "mfa": true, "tenant": 42 }
+//! }
+//! ```
+//!
+//! Context values map to `Value` by JSON type: booleans to `Bool`, integers
+//! that fit in `i64` to `Int`, strings to `String`. IP addresses and
+//! times are tagged objects: `{"ip": "10.0.0.1"}`,
+//! `{"timestamp": 1704531600}` (seconds since the epoch), and
+//! `{"duration": 3600}` (seconds). Any other value
+//! (floats, null, arrays, other objects) is rejected, as are repeated
+//! context keys.
+//! `context` may be omitted.
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::net::IpAddr;
+
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use serde::ser::{SerializeMap, Serializer};
+use serde::{Deserialize, Serialize};
+
+use crate::owned::OwnedValue;
+use crate::request::RequestBuilder;
+use crate::types::Request;
+
+/// An owned request, serializable as JSON.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{RequestData, Value};
+///
+/// let json = r#"{
+///     "principal": "alice",
+///     "action": "read",
+///     "resource": "doc",
+///     "context": { "role": "admin", "mfa": true }
+/// }"#;
+/// let data: RequestData = serde_json::from_str(json).unwrap();
+///
+/// let builder = data.builder();
+/// let request = builder.build().unwrap();
+/// assert_eq!(request.get_attr("mfa"), Some(&Value::Bool(true)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RequestData {
+    /// The principal making the request.
+    pub principal: String,
+    /// The action being requested.
+    pub action: String,
+    /// The resource being accessed.
+    pub resource: String,
+    /// Context attributes, in document order.
+    #[serde(default, with = "context")]
+    pub context: Vec<(String, OwnedValue)>,
+}
+
+impl RequestData {
+    /// Create request data with no context.
+    pub fn new(principal: &str, action: &str, resource: &str) -> Self {
+        RequestData {
+            principal: principal.to_string(),
+            action: action.to_string(),
+            resource: resource.to_string(),
+            context: Vec::new(),
+        }
+    }
+
+    /// A `RequestBuilder` borrowing this data.
+    ///
+    /// Call `build()` on the result to get a `Request`; the builder applies
+    /// its usual context-size check.
+    pub fn builder(&self) -> RequestBuilder<'_> {
+        self.context.iter().fold(
+            RequestBuilder::new(&self.principal, &self.action, &self.resource),
+            |builder, (key, value)| builder.attr(key, value.as_value()),
+        )
+    }
+}
+
+impl From<&Request<'_>> for RequestData {
+    fn from(request: &Request<'_>) -> Self {
+        RequestData {
+            principal: request.principal.to_string(),
+            action: request.action.to_string(),
+            resource: request.resource.to_string(),
+            context: request
+                .context
+                .iter()
+                .map(|(key, value)| (key.to_string(), OwnedValue::from(value)))
+                .collect(),
+        }
+    }
+}
+
+impl Serialize for OwnedValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OwnedValue::Bool(b) => serializer.serialize_bool(*b),
+            OwnedValue::Int(i) => serializer.serialize_i64(*i),
+            OwnedValue::String(s) => serializer.serialize_str(s),
+            OwnedValue::Ip(ip) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("ip", &IpString(*ip))?;
+                map.end()
+            }
+            OwnedValue::Timestamp(t) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("timestamp", t)?;
+                map.end()
+            }
+            OwnedValue::Duration(d) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("duration", d)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OwnedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// An address serialized as its text form.
+struct IpString(IpAddr);
+
+impl Serialize for IpString {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = OwnedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(
+            "a boolean, a 64-bit signed integer, a string, {\"ip\": ..}, {\"timestamp\": ..}, or {\"duration\": ..}",
+        )
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::Bool(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::Int(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<OwnedValue, E> {
+        i64::try_from(v)
+            .map(OwnedValue::Int)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::String(v.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<OwnedValue, E> {
+        Ok(OwnedValue::String(v))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OwnedValue, A::Error> {
+        let value = match map.next_key::<String>()?.as_deref() {
+            Some("ip") => {
+                let ip = map.next_value::<String>()?;
+                OwnedValue::Ip(ip.parse().map_err(|_| {
+                    de::Error::invalid_value(de::Unexpected::Str(&ip), &"an IP address")
+                })?)
+            }
+            Some("timestamp") => OwnedValue::Timestamp(map.next_value()?),
+            Some("duration") => OwnedValue::Duration(map.next_value()?),
+            _ => return Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        };
+        if map.next_key::<String>()?.is_some() {
+            return Err(de::Error::invalid_type(de::Unexpected::Map, &self));
+        }
+        Ok(value)
+    }
+}
+
+/// The context is a JSON object, kept as an ordered list of pairs.
+mod context {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        context: &[(String, OwnedValue)],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(context.len()))?;
+        for (key, value) in context {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<(String, OwnedValue)>, D::Error> {
+        deserializer.deserialize_map(ContextVisitor)
+    }
+
+    struct ContextVisitor;
+
+    impl<'de> Visitor<'de> for ContextVisitor {
+        type Value = Vec<(String, OwnedValue)>;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("a map of context attributes")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+            let mut context: Vec<(String, OwnedValue)> = Vec::new();
+            while let Some((key, value)) = map.next_entry::<String, OwnedValue>()? {
+                // A repeated key would be shadowed by its first occurrence
+                if context.iter().any(|(k, _)| *k == key) {
+                    return Err(de::Error::custom(format_args!(
+                        "duplicate context key '{}'",
+                        key
+                    )));
+                }
+                context.push((key, value));
+            }
+            Ok(context)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Value;
+    use core::net::Ipv6Addr;
+
+    #[test]
+    fn test_parses_typed_context() {
+        let json = r#"{
+            "principal": "alice",
+            "action": "read",
+            "resource": "doc",
+            "context": { "role": "admin", "mfa": true, "tenant": -7, "source": { "ip": "::1" }, "now": { "timestamp": 1704531600 }, "ttl": { "duration": 900 } }
+        }"#;
+        let data: RequestData = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            data.context,
+            [
+                ("role".to_string(), OwnedValue::String("admin".to_string())),
+                ("mfa".to_string(), OwnedValue::Bool(true)),
+                ("tenant".to_string(), OwnedValue::Int(-7)),
+                (
+                    "source".to_string(),
+                    OwnedValue::Ip(Ipv6Addr::LOCALHOST.into())
+                ),
+                ("now".to_string(), OwnedValue::Timestamp(1_704_531_600)),
+                ("ttl".to_string(), OwnedValue::Duration(900)),
+            ]
+        );
+
+        let builder = data.builder();
+        let request = builder.build().unwrap();
+        assert_eq!(request.principal, "alice");
+        assert_eq!(request.get_attr("role"), Some(&Value::String("admin")));
+
+        // Round trip through JSON
+        let back: RequestData =
+            serde_json::from_str(&serde_json::to_string(&data).unwrap()).unwrap();
+        assert_eq!(back, data);
+        assert_eq!(RequestData::from(&request), data);
+
+        let bare: RequestData =
+            serde_json::from_str(r#"{"principal":"a","action":"b","resource":"c"}"#).unwrap();
+        assert_eq!(bare, RequestData::new("a", "b", "c"));
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        let bad = [
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":1.5}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":null}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":[1]}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":9223372036854775808}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":1,"x":2}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":{"ip":"10.0.0"}}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":{"addr":"::1"}}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":{"timestamp":"9:00"}}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":{"duration":-1}}}"#,
+            r#"{"principal":"a","action":"b","resource":"c","context":{"x":{"ip":"::1","timestamp":0}}}"#,
+            r#"{"principal":"a","action":"b"}"#,
+            r#"{"principal":"a","action":"b","resource":"c","extra":1}"#,
+        ];
+        for json in bad {
+            assert!(
+                serde_json::from_str::<RequestData>(json).is_err(),
+                "{}",
+                json
+            );
+        }
+    }
+}
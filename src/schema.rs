@@ -0,0 +1,363 @@
+//! Context schemas.
+//!
+//! A condition on a misspelled or wrongly typed attribute does not fail, it
+//! just never matches. A `ContextSchema` lists the attributes a policy
+//! expects and their types, so requests can be checked with
+//! `Policy::validate_request` before they silently evaluate to non-matches.
+//!
+//! By default a policy derives its schema from its conditions: every
+//! attribute compared by any rule is expected, with the type(s) it is
+//! compared against, or with every type if a custom predicate reads it. A
+//! schema declared explicitly with
+//! `PolicyBuilder::schema` replaces the derived one, and building fails if a
+//! condition uses an attribute or type the schema does not declare.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::compiled::{CompiledConditions, Op};
+use crate::error::PolicyError;
+use crate::policy::Policy;
+use crate::types::Request;
+use crate::value::ValueType;
+
+/// One expected context attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SchemaAttr<'a> {
+    /// The attribute name.
+    pub name: &'a str,
+    /// The expected type.
+    pub ty: ValueType,
+    /// Whether requests must provide the attribute.
+    pub required: bool,
+}
+
+/// The context attributes a policy expects.
+///
+/// An attribute may be listed with several types if conditions compare it
+/// against values of different types; a request value of any listed type
+/// is accepted.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContextSchema<'a> {
+    /// Sorted by name, then type; no duplicate (name, type) pairs.
+    attrs: Vec<SchemaAttr<'a>>,
+}
+
+impl<'a> ContextSchema<'a> {
+    /// Create an empty schema.
+    pub fn new() -> Self {
+        ContextSchema { attrs: Vec::new() }
+    }
+
+    /// Declare an attribute that every request must provide.
+    pub fn required(self, name: &'a str, ty: ValueType) -> Self {
+        self.with(SchemaAttr {
+            name,
+            ty,
+            required: true,
+        })
+    }
+
+    /// Declare an attribute that requests may omit.
+    pub fn optional(self, name: &'a str, ty: ValueType) -> Self {
+        self.with(SchemaAttr {
+            name,
+            ty,
+            required: false,
+        })
+    }
+
+    fn with(mut self, attr: SchemaAttr<'a>) -> Self {
+        let key = |a: &SchemaAttr<'a>| (a.name, a.ty);
+        match self.attrs.binary_search_by(|a| key(a).cmp(&key(&attr))) {
+            // Redeclaring keeps the stricter requirement
+            Ok(i) => self.attrs[i].required |= attr.required,
+            Err(i) => self.attrs.insert(i, attr),
+        }
+        self
+    }
+
+    /// Derive a schema from compiled conditions: every compared attribute
+    /// is required, with each type it is compared against.
+    pub(crate) fn derive(conditions: &CompiledConditions<'a>, rule_count: usize) -> Self {
+        let mut schema = ContextSchema::new();
+        for rule in 0..rule_count {
+            for op in conditions.program(rule).unwrap_or(&[]) {
+                if let Some((attr, ty)) = compared(op) {
+                    schema = schema.required(attr, ty);
+                }
+                // A predicate may accept a value of any type
+                #[cfg(feature = "custom-predicates")]
+                if let Op::Custom(attr, _) = op {
+                    for ty in ValueType::ALL {
+                        schema = schema.required(attr, ty);
+                    }
+                }
+            }
+        }
+        schema
+    }
+
+    /// Check that every attribute compared by `conditions` is declared.
+    pub(crate) fn check_conditions(
+        &self,
+        conditions: &CompiledConditions<'_>,
+        rule_count: usize,
+    ) -> Result<(), PolicyError> {
+        for rule in 0..rule_count {
+            for op in conditions.program(rule).unwrap_or(&[]) {
+                if let Some((attr, ty)) = compared(op) {
+                    if !self.allows(attr, ty) {
+                        return Err(PolicyError::UndeclaredAttribute { rule });
+                    }
+                }
+                #[cfg(feature = "custom-predicates")]
+                if let Op::Custom(attr, _) = op {
+                    if self.entries(attr).next().is_none() {
+                        return Err(PolicyError::UndeclaredAttribute { rule });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// All declared attributes, sorted by name.
+    pub fn attrs(&self) -> &[SchemaAttr<'a>] {
+        &self.attrs
+    }
+
+    /// Returns `true` if `name` is declared with type `ty`.
+    pub fn allows(&self, name: &str, ty: ValueType) -> bool {
+        self.entries(name).any(|a| a.ty == ty)
+    }
+
+    fn entries<'s>(&'s self, name: &'s str) -> impl Iterator<Item = &'s SchemaAttr<'a>> + 's {
+        let start = self.attrs.partition_point(|a| a.name < name);
+        self.attrs[start..]
+            .iter()
+            .take_while(move |a| a.name == name)
+    }
+
+    /// Check a request against this schema.
+    ///
+    /// Returns every violation found; an empty list means the request is
+    /// valid.
+    pub fn validate<'r>(&'r self, request: &Request<'r>) -> Vec<SchemaViolation<'r>> {
+        let mut violations = Vec::new();
+
+        for (key, value) in request.context {
+            let mut declared = self.entries(key).peekable();
+            match declared.peek() {
+                None => violations.push(SchemaViolation::Unknown { attr: key }),
+                Some(first) => {
+                    let expected = first.ty;
+                    if !declared.any(|a| a.ty == value.value_type()) {
+                        violations.push(SchemaViolation::WrongType {
+                            attr: key,
+                            expected,
+                            actual: value.value_type(),
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut previous: Option<&str> = None;
+        for attr in &self.attrs {
+            // Attributes with several types are reported once
+            if previous == Some(attr.name) {
+                continue;
+            }
+            previous = Some(attr.name);
+
+            let required = self.entries(attr.name).any(|a| a.required);
+            if required && request.get_attr(attr.name).is_none() {
+                violations.push(SchemaViolation::Missing {
+                    attr: attr.name,
+                    expected: attr.ty,
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+/// The attribute an operation reads and the type it expects.
+fn compared<'a>(op: &Op<'a>) -> Option<(&'a str, ValueType)> {
+    match op {
+        Op::Equals(attr, value)
+        | Op::NotEquals(attr, value)
+        | Op::GreaterThan(attr, value)
+        | Op::LessThan(attr, value) => Some((attr, value.value_type())),
+        Op::IpInCidr(attr, _) => Some((attr, ValueType::Ip)),
+        Op::TimeBetween(attr, ..) | Op::DayOfWeek(attr, _) => Some((attr, ValueType::Timestamp)),
+        _ => None,
+    }
+}
+
+/// A way a request does not match a schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaViolation<'a> {
+    /// A required attribute is absent.
+    Missing {
+        /// The attribute name.
+        attr: &'a str,
+        /// Its expected type.
+        expected: ValueType,
+    },
+    /// An attribute has a type the schema does not allow.
+    WrongType {
+        /// The attribute name.
+        attr: &'a str,
+        /// A type the schema allows.
+        expected: ValueType,
+        /// The type in the request.
+        actual: ValueType,
+    },
+    /// The request carries an attribute the schema does not declare.
+    Unknown {
+        /// The attribute name.
+        attr: &'a str,
+    },
+}
+
+impl fmt::Display for SchemaViolation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaViolation::Missing { attr, expected } => {
+                write!(f, "missing attribute '{}' ({})", attr, expected.name())
+            }
+            SchemaViolation::WrongType {
+                attr,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "attribute '{}' has type {}, expected {}",
+                attr,
+                actual.name(),
+                expected.name()
+            ),
+            SchemaViolation::Unknown { attr } => write!(f, "unknown attribute '{}'", attr),
+        }
+    }
+}
+
+impl<'a> Policy<'a> {
+    /// Check a request's context against this policy's schema.
+    ///
+    /// Returns every missing, wrongly typed, or unknown attribute; an empty
+    /// list means the request is valid. Does not evaluate the request.
+    pub fn validate_request<'r>(&'r self, request: &Request<'r>) -> Vec<SchemaViolation<'r>> {
+        self.schema().validate(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, Policy, ReasonCode, Rule, Target, Value};
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn policy() -> Policy<'static> {
+        Policy::builder()
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::And(
+                    Box::new(Condition::Equals {
+                        attr: "role",
+                        value: Value::String("admin"),
+                    }),
+                    Box::new(Condition::Equals {
+                        attr: "mfa",
+                        value: Value::Bool(true),
+                    }),
+                )),
+                ReasonCode(1),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_derived_schema() {
+        let policy = policy();
+        let names: Vec<&str> = policy.schema().attrs().iter().map(|a| a.name).collect();
+        assert_eq!(names, ["mfa", "role"]);
+
+        let ok: &[(&str, Value)] = &[("role", Value::String("dev")), ("mfa", Value::Bool(false))];
+        assert!(policy
+            .validate_request(&Request::with_context("a", "b", "c", ok))
+            .is_empty());
+
+        let bad: &[(&str, Value)] = &[("rloe", Value::String("admin")), ("mfa", Value::Int(1))];
+        let violations = policy.validate_request(&Request::with_context("a", "b", "c", bad));
+        assert_eq!(
+            violations,
+            vec![
+                SchemaViolation::Unknown { attr: "rloe" },
+                SchemaViolation::WrongType {
+                    attr: "mfa",
+                    expected: ValueType::Bool,
+                    actual: ValueType::Int,
+                },
+                SchemaViolation::Missing {
+                    attr: "role",
+                    expected: ValueType::String,
+                },
+            ]
+        );
+        assert_eq!(violations[0].to_string(), "unknown attribute 'rloe'");
+    }
+
+    #[test]
+    fn test_explicit_schema() {
+        let schema = ContextSchema::new()
+            .required("role", ValueType::String)
+            .optional("mfa", ValueType::Bool);
+
+        let rule = Rule::new(
+            Effect::Allow,
+            Target::any(),
+            Some(Condition::Equals {
+                attr: "role",
+                value: Value::String("admin"),
+            }),
+            ReasonCode(1),
+        );
+        let policy = Policy::builder()
+            .rule(rule.clone())
+            .schema(schema.clone())
+            .build()
+            .unwrap();
+
+        // Optional attributes may be omitted
+        let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+        assert!(policy
+            .validate_request(&Request::with_context("a", "b", "c", ctx))
+            .is_empty());
+
+        // Conditions must only use declared attributes
+        let stray = Rule::new(
+            Effect::Deny,
+            Target::any(),
+            Some(Condition::Equals {
+                attr: "tenant",
+                value: Value::Int(1),
+            }),
+            ReasonCode(2),
+        );
+        let err = Policy::builder()
+            .rule(rule)
+            .rule(stray)
+            .schema(schema)
+            .build()
+            .unwrap_err();
+        assert_eq!(err, PolicyError::UndeclaredAttribute { rule: 1 });
+    }
+}
@@ -0,0 +1,146 @@
+//! Decision sinks for audit pipelines.
+//!
+//! A `DecisionSink` receives every decision together with the request and
+//! its evaluation stats. Attach one to a policy with
+//! `PolicyBuilder::sink` / `Policy::with_sink` to record every decision
+//! without touching call sites, or pass one per call to
+//! `Policy::evaluate_with_sink`. Decisions a `DecisionCache` serves still
+//! reach the attached sink, marked `Termination::Cached`.
+//!
+//! Sinks take `&self` so a policy can be shared across threads; use interior
+//! mutability (a channel, a mutex, an atomic counter) to record.
+
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::stats::EvaluationStats;
+use crate::types::{Decision, Request};
+
+/// Receives decisions after evaluation.
+///
+/// Implemented for closures taking the same arguments as `record`.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use gate0::{Decision, EvaluationStats, Policy, ReasonCode, Request, Rule, Target};
+///
+/// static DENIES: AtomicUsize = AtomicUsize::new(0);
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::deny(Target::any(), ReasonCode(1)))
+///     .sink(|_: &Request, d: &Decision, _: &EvaluationStats| {
+///         if d.is_deny() {
+///             DENIES.fetch_add(1, Ordering::Relaxed);
+///         }
+///     })
+///     .build()
+///     .unwrap();
+///
+/// policy.evaluate(&Request::new("alice", "read", "doc")).unwrap();
+/// assert_eq!(DENIES.load(Ordering::Relaxed), 1);
+/// ```
+pub trait DecisionSink {
+    /// Record one decision.
+    ///
+    /// Called synchronously on the evaluating thread; keep it cheap.
+    fn record(&self, request: &Request<'_>, decision: &Decision, stats: &EvaluationStats);
+}
+
+impl<F> DecisionSink for F
+where
+    F: Fn(&Request<'_>, &Decision, &EvaluationStats),
+{
+    fn record(&self, request: &Request<'_>, decision: &Decision, stats: &EvaluationStats) {
+        self(request, decision, stats)
+    }
+}
+
+/// A sink owned by a policy.
+pub(crate) struct AttachedSink<'a>(Box<dyn DecisionSink + Send + Sync + 'a>);
+
+impl<'a> AttachedSink<'a> {
+    pub(crate) fn new(sink: impl DecisionSink + Send + Sync + 'a) -> Self {
+        AttachedSink(Box::new(sink))
+    }
+
+    #[inline]
+    pub(crate) fn record(
+        &self,
+        request: &Request<'_>,
+        decision: &Decision,
+        stats: &EvaluationStats,
+    ) {
+        self.0.record(request, decision, stats)
+    }
+}
+
+impl fmt::Debug for AttachedSink<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DecisionSink")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Policy, ReasonCode, Rule, Target};
+    use core::cell::Cell;
+    use std::sync::{Arc, Mutex};
+    use std::vec::Vec;
+
+    struct Collect(Arc<Mutex<Vec<u32>>>);
+
+    impl DecisionSink for Collect {
+        fn record(&self, _: &Request<'_>, decision: &Decision, _: &EvaluationStats) {
+            self.0.lock().unwrap().push(decision.reason.value());
+        }
+    }
+
+    #[test]
+    fn test_attached_sink_sees_every_path() {
+        let collected = Arc::new(Mutex::new(Vec::new()));
+        let policy = Policy::builder()
+            .rule(Rule::allow(Target::any(), ReasonCode(7)))
+            .sink(Collect(collected.clone()))
+            .build()
+            .unwrap();
+
+        let request = Request::new("alice", "read", "doc");
+        policy.evaluate(&request).unwrap();
+        policy.evaluate_with_stats(&request).unwrap();
+        let _ = policy.evaluate_with_trace(&request);
+
+        assert_eq!(*collected.lock().unwrap(), vec![7, 7, 7]);
+    }
+
+    #[test]
+    fn test_evaluate_with_sink() {
+        let policy = Policy::builder()
+            .rule(Rule::deny(Target::any(), ReasonCode(3)))
+            .build()
+            .unwrap();
+
+        let rules_checked = Cell::new(0);
+        let sink = |_: &Request<'_>, d: &Decision, s: &EvaluationStats| {
+            assert!(d.is_deny());
+            rules_checked.set(s.rules_checked);
+        };
+        policy
+            .evaluate_with_sink(&Request::new("alice", "read", "doc"), &sink)
+            .unwrap();
+        assert_eq!(rules_checked.get(), 1);
+
+        // Errors are not recorded
+        let long = "x".repeat(300);
+        let calls = Cell::new(0);
+        let counting = |_: &Request<'_>, _: &Decision, _: &EvaluationStats| {
+            calls.set(calls.get() + 1);
+        };
+        assert!(policy
+            .evaluate_with_sink(&Request::new(&long, "read", "doc"), &counting)
+            .is_err());
+        assert_eq!(calls.get(), 0);
+    }
+}
@@ -0,0 +1,1088 @@
+//! Policies checked at compile time (the `static_policy!` macro).
+//!
+//! `static_policy!` declares a policy in the text policy syntax as a
+//! `static`:
+//!
+//! ```
+//! gate0::static_policy! {
+//!     /// The service's policy.
+//!     pub static POLICY = {
+//!         DENY (@"contractors" * "payroll") => 9;
+//!         ALLOW ("alice" ["read" "write"] *) WHERE { (role EQ "admin") } => 3;
+//!     };
+//! }
+//!
+//! use gate0::{Request, Value};
+//!
+//! let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+//! let decision = POLICY
+//!     .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+//!     .unwrap();
+//! assert_eq!(decision.reason.value(), 3);
+//! ```
+//!
+//! The macro expands the rules into constant data and checks that data
+//! against the config in a `const` item: rule count, matcher options,
+//! string lengths, condition depth, and condition nodes. A policy that
+//! would fail `Policy::with_config` fails the build instead:
+//!
+//! ```compile_fail
+//! gate0::static_policy! {
+//!     static POLICY = {
+//!         ALLOW (* * *) WHERE { NOT NOT NOT NOT NOT NOT NOT NOT NOT NOT (a EQ 1) } => 1;
+//!     };
+//! }
+//! ```
+//!
+//! Rules that can never decide a request also fail the build, naming the
+//! rule by index: exact duplicates, and rules covered by an earlier rule
+//! with the same effect (the checks `PolicyBuilder::build_with_warnings`
+//! reports at runtime):
+//!
+//! ```compile_fail
+//! gate0::static_policy! {
+//!     static POLICY = {
+//!         ALLOW (* "read" *) => 1;
+//!         // error: static_policy!: rule 1 is shadowed by rule 0
+//!         ALLOW ("alice" "read" *) => 2;
+//!     };
+//! }
+//! ```
+//!
+//! CIDR blocks in `IN_CIDR`, times in `BETWEEN`, and days in `ON` are
+//! parsed by the same `const fn`s as at runtime, so an invalid one fails the
+//! build too:
+//!
+//! ```compile_fail
+//! gate0::static_policy! {
+//!     static POLICY = {
+//!         // error: static_policy!: invalid CIDR block
+//!         ALLOW (* * *) WHERE { (source_ip IN_CIDR "10.0.0.1/8") } => 1;
+//!     };
+//! }
+//! ```
+//!
+//! Rules, conditions, and matchers are written exactly as for
+//! `parse_policy` (see the `text-dsl` feature), and the two produce the same
+//! `Policy`, fingerprint included. `static NAME with CONFIG = {..}` checks
+//! against a `const` config instead of the default.
+//!
+//! Wherever the text syntax takes a literal, the macro also takes a constant
+//! or a `{ .. }` block of constant expression. A constant matcher is `Exact`
+//! if it is a `&str` and `OneOf` if it is a slice or array of them; a
+//! constant value may be a `bool`, `i64`, `&str`, or `Value`. Attribute
+//! names written as identifiers are the names themselves, so a constant
+//! attribute name goes in a block:
+//!
+//! ```
+//! const ADMINS: &[&str] = &["alice", "bob"];
+//! const MFA: &str = "mfa";
+//! const MIN_LEVEL: i64 = 3;
+//!
+//! gate0::static_policy! {
+//!     static POLICY = {
+//!         ALLOW (ADMINS * *) WHERE { ({MFA} EQ true) AND (level IN [MIN_LEVEL {MIN_LEVEL + 1}]) } => 1;
+//!     };
+//! }
+//! # assert_eq!(POLICY.rule_count(), 1);
+//! ```
+//!
+//! Building a `Policy` allocates, so the static is a `LazyLock` built on
+//! first use. Because the checks already passed, building cannot fail.
+
+use alloc::boxed::Box;
+use core::net::IpAddr;
+
+use crate::condition::Condition;
+use crate::const_fmt::ConstMessage;
+use crate::net::Cidr;
+use crate::policy::{Policy, PolicyConfig, Rule};
+use crate::target::{Matcher, Target};
+use crate::time::{parse_duration, parse_time_of_day, Weekdays, SECONDS_PER_DAY};
+use crate::types::{CacheHint, Effect, ReasonCode};
+use crate::value::Value;
+
+/// Declare a `static` policy whose limits are checked at compile time.
+///
+/// See the module docs of `static_policy` for the syntax.
+#[macro_export]
+macro_rules! static_policy {
+    () => {};
+    (
+        $(#[$meta:meta])*
+        $vis:vis static $name:ident $(with $config:path)? = { $($body:tt)* };
+        $($rest:tt)*
+    ) => {
+        $(#[$meta])*
+        $vis static $name: $crate::__private::LazyLock<$crate::Policy<'static>> = {
+            const CONFIG: $crate::PolicyConfig = $crate::static_policy!(@config $($config)?);
+            const RULES: &[$crate::__private::StaticRule] = $crate::static_policy!(@rules $($body)*);
+            const _: () = $crate::__private::check(RULES, &CONFIG);
+            $crate::__private::LazyLock::new(|| $crate::__private::build(RULES, CONFIG))
+        };
+        $crate::static_policy!($($rest)*);
+    };
+
+    (@config) => { $crate::PolicyConfig::DEFAULT };
+    (@config $config:path) => { $config };
+
+    (@rules $(
+        $effect:ident $($name:literal)? ($($target:tt)*) $(WHERE { $($condition:tt)+ })?
+            => $reason:literal $(CACHE $cache:tt $($context:ident)?)? ;
+    )*) => {
+        &[$(
+            $crate::__private::StaticRule {
+                name: $crate::static_policy!(@name $($name)?),
+                effect: $crate::static_policy!(@effect $effect),
+                target: $crate::static_policy!(@target [] $($target)*),
+                condition: $crate::static_policy!(@where $($($condition)+)?),
+                reason: $crate::ReasonCode($reason),
+                cache: $crate::static_policy!(@cache $($cache $($context)?)?),
+            },
+        )*]
+    };
+
+    // A TTL such as `5m` is not a Rust expression, so the hint is parsed
+    // from its text
+    (@cache) => { None };
+    (@cache $cache:tt $($context:ident)?) => {
+        Some($crate::__private::cache_hint(stringify!($cache), stringify!($($context)?)))
+    };
+
+    (@name) => { None };
+    (@name $name:literal) => { Some($name) };
+
+    (@effect ALLOW) => { $crate::Effect::Allow };
+    (@effect DENY) => { $crate::Effect::Deny };
+
+    (@target [$($m:tt)*] * $($rest:tt)*) => {
+        $crate::static_policy!(@target [$($m)* {$crate::Matcher::Any}] $($rest)*)
+    };
+    (@target [$($m:tt)*] @ $group:tt $($rest:tt)*) => {
+        $crate::static_policy!(@target [$($m)* {$crate::Matcher::Group($group)}] $($rest)*)
+    };
+    (@target [$($m:tt)*] [$($option:tt)*] $($rest:tt)*) => {
+        $crate::static_policy!(@target [$($m)* {$crate::Matcher::OneOf(&[$($option),*])}] $($rest)*)
+    };
+    // A string is `Exact`; a constant is `Exact` or `OneOf` by its type
+    (@target [$($m:tt)*] $matcher:tt $($rest:tt)*) => {
+        $crate::static_policy!(@target [$($m)* {$crate::__private::Lit($matcher).matcher()}] $($rest)*)
+    };
+    (@target [{$($principal:tt)*} {$($action:tt)*} {$($resource:tt)*}]) => {
+        $crate::Target {
+            principal: $($principal)*,
+            action: $($action)*,
+            resource: $($resource)*,
+        }
+    };
+
+    (@where) => { None };
+    (@where $($condition:tt)+) => {
+        Some(&$crate::static_policy!(@or [] [] $($condition)+))
+    };
+
+    // `OR` binds loosest: split on it first, then on `AND`
+    (@or [$($done:tt)*] [$($current:tt)+] OR $($rest:tt)+) => {
+        $crate::static_policy!(@or [$($done)* [$($current)+]] [] $($rest)+)
+    };
+    (@or [$($done:tt)*] [$($current:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::static_policy!(@or [$($done)*] [$($current)* $token] $($rest)*)
+    };
+    (@or [$([$($operand:tt)+])*] [$($last:tt)+]) => {
+        $crate::__private::StaticCondition::Any(&[
+            $($crate::static_policy!(@and [] [] $($operand)+),)*
+            $crate::static_policy!(@and [] [] $($last)+),
+        ])
+    };
+
+    (@and [$($done:tt)*] [$($current:tt)+] AND $($rest:tt)+) => {
+        $crate::static_policy!(@and [$($done)* [$($current)+]] [] $($rest)+)
+    };
+    (@and [$($done:tt)*] [$($current:tt)*] $token:tt $($rest:tt)*) => {
+        $crate::static_policy!(@and [$($done)*] [$($current)* $token] $($rest)*)
+    };
+    (@and [$([$($operand:tt)+])*] [$($last:tt)+]) => {
+        $crate::__private::StaticCondition::All(&[
+            $($crate::static_policy!(@unary $($operand)+),)*
+            $crate::static_policy!(@unary $($last)+),
+        ])
+    };
+
+    (@unary NOT $($rest:tt)+) => {
+        $crate::__private::StaticCondition::Not(&$crate::static_policy!(@unary $($rest)+))
+    };
+    (@unary TRUE) => { $crate::__private::StaticCondition::True };
+    (@unary FALSE) => { $crate::__private::StaticCondition::False };
+    (@unary ($attr:tt EQ $($value:tt)+)) => {
+        $crate::__private::StaticCondition::Equals(
+            $crate::static_policy!(@attr $attr),
+            $crate::__private::Lit($($value)+).value(),
+        )
+    };
+    (@unary ($attr:tt NEQ $($value:tt)+)) => {
+        $crate::__private::StaticCondition::NotEquals(
+            $crate::static_policy!(@attr $attr),
+            $crate::__private::Lit($($value)+).value(),
+        )
+    };
+    // A literal after `GT` or `LT` may be a duration such as `1h30m`, which
+    // is not a Rust expression, so it is parsed from its text
+    (@unary ($attr:tt GT $value:literal)) => {
+        $crate::__private::StaticCondition::GreaterThan(
+            $crate::static_policy!(@attr $attr),
+            $crate::__private::ordered(stringify!($value)),
+        )
+    };
+    (@unary ($attr:tt GT $($value:tt)+)) => {
+        $crate::__private::StaticCondition::GreaterThan(
+            $crate::static_policy!(@attr $attr),
+            $crate::__private::Lit($($value)+).value(),
+        )
+    };
+    (@unary ($attr:tt LT $value:literal)) => {
+        $crate::__private::StaticCondition::LessThan(
+            $crate::static_policy!(@attr $attr),
+            $crate::__private::ordered(stringify!($value)),
+        )
+    };
+    (@unary ($attr:tt LT $($value:tt)+)) => {
+        $crate::__private::StaticCondition::LessThan(
+            $crate::static_policy!(@attr $attr),
+            $crate::__private::Lit($($value)+).value(),
+        )
+    };
+    (@unary ($attr:tt IN_CIDR $cidr:tt)) => {
+        $crate::__private::StaticCondition::IpInCidr($crate::static_policy!(@attr $attr), $cidr)
+    };
+    (@unary ($attr:tt BETWEEN $start:tt $end:tt)) => {
+        $crate::__private::StaticCondition::TimeBetween(
+            $crate::static_policy!(@attr $attr),
+            $start,
+            $end,
+        )
+    };
+    (@unary ($attr:tt ON [$($day:tt)*])) => {
+        $crate::__private::StaticCondition::DayOfWeek(
+            $crate::static_policy!(@attr $attr),
+            &[$($day),*],
+        )
+    };
+    (@unary ($attr:tt IN [])) => { $crate::__private::StaticCondition::False };
+    (@unary ($attr:tt IN [$($values:tt)+])) => {
+        $crate::static_policy!(@in $attr [] $($values)+)
+    };
+    (@unary ($($inner:tt)+)) => {
+        $crate::static_policy!(@or [] [] $($inner)+)
+    };
+
+    // An attribute name is an identifier (its own name), a string, or a
+    // `{ .. }` block evaluating to a `&'static str`
+    (@attr $attr:ident) => { stringify!($attr) };
+    (@attr $attr:tt) => { $attr };
+
+    // Group `-` with the number after it, then emit one `EQ` per value
+    (@in $attr:tt [$($done:tt)*] - $value:tt $($rest:tt)*) => {
+        $crate::static_policy!(@in $attr [$($done)* (-$value)] $($rest)*)
+    };
+    (@in $attr:tt [$($done:tt)*] $value:tt $($rest:tt)*) => {
+        $crate::static_policy!(@in $attr [$($done)* ($value)] $($rest)*)
+    };
+    (@in $attr:tt [$(($($value:tt)+))+]) => {
+        $crate::__private::StaticCondition::Any(&[$(
+            $crate::__private::StaticCondition::Equals(
+                $crate::static_policy!(@attr $attr),
+                $crate::__private::Lit($($value)+).value(),
+            ),
+        )+])
+    };
+}
+
+/// A rule as constant data.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct StaticRule {
+    pub name: Option<&'static str>,
+    pub effect: Effect,
+    pub target: Target<'static>,
+    pub condition: Option<&'static StaticCondition>,
+    pub reason: ReasonCode,
+    pub cache: Option<CacheHint>,
+}
+
+/// A condition as constant data. `All` and `Any` hold the operands of an
+/// `AND` or `OR` chain, which become a balanced tree of `And` or `Or`.
+#[doc(hidden)]
+#[derive(Debug)]
+pub enum StaticCondition {
+    True,
+    False,
+    Equals(&'static str, Value<'static>),
+    NotEquals(&'static str, Value<'static>),
+    GreaterThan(&'static str, Value<'static>),
+    LessThan(&'static str, Value<'static>),
+    IpInCidr(&'static str, &'static str),
+    TimeBetween(&'static str, &'static str, &'static str),
+    DayOfWeek(&'static str, &'static [&'static str]),
+    All(&'static [StaticCondition]),
+    Any(&'static [StaticCondition]),
+    Not(&'static StaticCondition),
+}
+
+/// A condition value or matcher written in the macro; `value()` and
+/// `matcher()` are defined for each type they accept.
+#[doc(hidden)]
+pub struct Lit<T>(pub T);
+
+impl Lit<&'static str> {
+    pub const fn matcher(self) -> Matcher<'static> {
+        Matcher::Exact(self.0)
+    }
+}
+
+impl Lit<&'static [&'static str]> {
+    pub const fn matcher(self) -> Matcher<'static> {
+        Matcher::OneOf(self.0)
+    }
+}
+
+impl<const N: usize> Lit<&'static [&'static str; N]> {
+    pub const fn matcher(self) -> Matcher<'static> {
+        Matcher::OneOf(self.0)
+    }
+}
+
+impl Lit<Value<'static>> {
+    pub const fn value(self) -> Value<'static> {
+        self.0
+    }
+}
+
+impl Lit<bool> {
+    pub const fn value(self) -> Value<'static> {
+        Value::Bool(self.0)
+    }
+}
+
+impl Lit<i64> {
+    pub const fn value(self) -> Value<'static> {
+        Value::Int(self.0)
+    }
+}
+
+impl Lit<&'static str> {
+    pub const fn value(self) -> Value<'static> {
+        Value::String(self.0)
+    }
+}
+
+/// The value of an integer or duration literal after `GT` or `LT`, from its
+/// text.
+#[doc(hidden)]
+pub const fn ordered(text: &str) -> Value<'static> {
+    let bytes = text.as_bytes();
+    let negative = !bytes.is_empty() && bytes[0] == b'-';
+    let mut value: i64 = 0;
+    let mut i = if negative { 1 } else { 0 };
+    while i < bytes.len() && bytes[i].is_ascii_digit() {
+        value = match value.checked_mul(10) {
+            Some(v) => match v.checked_add((bytes[i] - b'0') as i64) {
+                Some(v) => v,
+                None => panic!("static_policy!: integer out of range"),
+            },
+            None => panic!("static_policy!: integer out of range"),
+        };
+        i += 1;
+    }
+    if i == bytes.len() && i > negative as usize {
+        return Value::Int(if negative { -value } else { value });
+    }
+    match parse_duration(text) {
+        Ok(seconds) => Value::Duration(seconds),
+        Err(_) => panic!("static_policy!: GT and LT need an integer, duration, or timestamp"),
+    }
+}
+
+/// The hint written after `CACHE`: a duration, `CONTEXT`, or a duration
+/// and `CONTEXT`.
+#[doc(hidden)]
+pub const fn cache_hint(first: &str, context: &str) -> CacheHint {
+    if !context.is_empty() && !str_eq(context, "CONTEXT") {
+        panic!("static_policy!: CACHE needs a duration, CONTEXT, or both");
+    }
+    if str_eq(first, "CONTEXT") && context.is_empty() {
+        return CacheHint::PER_CONTEXT;
+    }
+    let hint = match parse_duration(first) {
+        Ok(seconds) => CacheHint::ttl(seconds),
+        Err(_) => panic!("static_policy!: CACHE needs a duration, CONTEXT, or both"),
+    };
+    if context.is_empty() {
+        hint
+    } else {
+        hint.per_context()
+    }
+}
+
+/// Panic (failing the build in a `const`) if `rules` would not pass
+/// `Policy::with_config(.., *config)`.
+#[doc(hidden)]
+pub const fn check(rules: &[StaticRule], config: &PolicyConfig) {
+    #[cfg(not(feature = "heap-stack"))]
+    assert!(
+        config.max_condition_depth <= crate::condition::ABSOLUTE_MAX_CONDITION_DEPTH,
+        "static_policy!: max_condition_depth exceeds ABSOLUTE_MAX_CONDITION_DEPTH"
+    );
+    assert!(
+        rules.len() <= config.max_rules,
+        "static_policy!: more rules than max_rules"
+    );
+    let mut nodes = 0;
+    let mut i = 0;
+    while i < rules.len() {
+        let rule = &rules[i];
+        check_matcher(&rule.target.principal, config);
+        check_matcher(&rule.target.action, config);
+        check_matcher(&rule.target.resource, config);
+        if let Some(condition) = rule.condition {
+            assert!(
+                depth(condition) <= config.max_condition_depth,
+                "static_policy!: condition deeper than max_condition_depth"
+            );
+            check_attrs(condition, config);
+            nodes += node_count(condition);
+        }
+        i += 1;
+    }
+    assert!(
+        nodes <= config.max_condition_nodes,
+        "static_policy!: more condition nodes than max_condition_nodes"
+    );
+    check_redundancy(rules);
+}
+
+/// Reject rules that can never decide a request, as
+/// `PolicyBuilder::build_with_warnings` would report them: exact duplicates,
+/// and rules covered by an earlier rule with the same effect.
+///
+/// Like the runtime check this is conservative; conditions are compared as
+/// written, so equal conditions spelled differently are not detected.
+const fn check_redundancy(rules: &[StaticRule]) {
+    let mut i = 0;
+    while i < rules.len() {
+        let mut j = 0;
+        while j < i {
+            if rule_eq(&rules[j], &rules[i]) {
+                fail_rule(i, " duplicates rule ", j);
+            }
+            j += 1;
+        }
+        j = 0;
+        while j < i {
+            if rule_covers(&rules[j], &rules[i]) {
+                fail_rule(i, " is shadowed by rule ", j);
+            }
+            j += 1;
+        }
+        i += 1;
+    }
+}
+
+/// Fail with `static_policy!: rule {rule}{what}{other}`.
+const fn fail_rule(rule: usize, what: &str, other: usize) {
+    ConstMessage::new()
+        .str("static_policy!: rule ")
+        .int(rule as u64)
+        .str(what)
+        .int(other as u64)
+        .panic()
+}
+
+const fn rule_eq(a: &StaticRule, b: &StaticRule) -> bool {
+    effect_eq(a.effect, b.effect)
+        && a.reason.0 == b.reason.0
+        && matcher_eq(&a.target.principal, &b.target.principal)
+        && matcher_eq(&a.target.action, &b.target.action)
+        && matcher_eq(&a.target.resource, &b.target.resource)
+        && option_condition_eq(a.condition, b.condition)
+}
+
+/// Returns `true` if `earlier` matches every request `later` matches.
+const fn rule_covers(earlier: &StaticRule, later: &StaticRule) -> bool {
+    effect_eq(earlier.effect, later.effect)
+        && (earlier.condition.is_none() || option_condition_eq(earlier.condition, later.condition))
+        && matcher_covers(&earlier.target.principal, &later.target.principal)
+        && matcher_covers(&earlier.target.action, &later.target.action)
+        && matcher_covers(&earlier.target.resource, &later.target.resource)
+}
+
+const fn effect_eq(a: Effect, b: Effect) -> bool {
+    matches!(
+        (a, b),
+        (Effect::Allow, Effect::Allow) | (Effect::Deny, Effect::Deny)
+    )
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// The values a matcher accepts, if it is a finite list.
+const fn options<'a>(matcher: &'a Matcher<'static>) -> Option<&'a [&'static str]> {
+    match matcher {
+        Matcher::Exact(value) => Some(core::slice::from_ref(value)),
+        Matcher::OneOf(options) => Some(options),
+        _ => None,
+    }
+}
+
+const fn contains(options: &[&str], value: &str) -> bool {
+    let mut i = 0;
+    while i < options.len() {
+        if str_eq(options[i], value) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn matcher_eq(a: &Matcher<'static>, b: &Matcher<'static>) -> bool {
+    match (a, b) {
+        (Matcher::Any, Matcher::Any) => true,
+        (Matcher::Exact(a), Matcher::Exact(b)) | (Matcher::Group(a), Matcher::Group(b)) => {
+            str_eq(a, b)
+        }
+        (Matcher::OneOf(a), Matcher::OneOf(b)) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            let mut i = 0;
+            while i < a.len() {
+                if !str_eq(a[i], b[i]) {
+                    return false;
+                }
+                i += 1;
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+const fn matcher_covers(outer: &Matcher<'static>, inner: &Matcher<'static>) -> bool {
+    if matches!(outer, Matcher::Any) || matcher_eq(outer, inner) {
+        return true;
+    }
+    let (Some(outer), Some(inner)) = (options(outer), options(inner)) else {
+        return false;
+    };
+    let mut i = 0;
+    while i < inner.len() {
+        if !contains(outer, inner[i]) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn option_condition_eq(a: Option<&StaticCondition>, b: Option<&StaticCondition>) -> bool {
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => condition_eq(a, b),
+        _ => false,
+    }
+}
+
+/// Skip the one-operand chains that parentheses and single terms produce.
+const fn unwrap_single(mut condition: &StaticCondition) -> &StaticCondition {
+    while let StaticCondition::All([inner]) | StaticCondition::Any([inner]) = condition {
+        condition = inner;
+    }
+    condition
+}
+
+const fn value_eq(a: &Value<'_>, b: &Value<'_>) -> bool {
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => *a == *b,
+        (Value::Int(a), Value::Int(b)) => *a == *b,
+        (Value::String(a), Value::String(b)) => str_eq(a, b),
+        (Value::Ip(IpAddr::V4(a)), Value::Ip(IpAddr::V4(b))) => a.to_bits() == b.to_bits(),
+        (Value::Ip(IpAddr::V6(a)), Value::Ip(IpAddr::V6(b))) => a.to_bits() == b.to_bits(),
+        (Value::Timestamp(a), Value::Timestamp(b)) => *a == *b,
+        (Value::Duration(a), Value::Duration(b)) => *a == *b,
+        _ => false,
+    }
+}
+
+const fn condition_eq(a: &StaticCondition, b: &StaticCondition) -> bool {
+    match (unwrap_single(a), unwrap_single(b)) {
+        (StaticCondition::True, StaticCondition::True)
+        | (StaticCondition::False, StaticCondition::False) => true,
+        (StaticCondition::Equals(a, x), StaticCondition::Equals(b, y))
+        | (StaticCondition::NotEquals(a, x), StaticCondition::NotEquals(b, y))
+        | (StaticCondition::GreaterThan(a, x), StaticCondition::GreaterThan(b, y))
+        | (StaticCondition::LessThan(a, x), StaticCondition::LessThan(b, y)) => {
+            str_eq(a, b) && value_eq(x, y)
+        }
+        (StaticCondition::IpInCidr(a, x), StaticCondition::IpInCidr(b, y)) => {
+            str_eq(a, b) && str_eq(x, y)
+        }
+        (StaticCondition::TimeBetween(a, x, y), StaticCondition::TimeBetween(b, z, w)) => {
+            str_eq(a, b) && str_eq(x, z) && str_eq(y, w)
+        }
+        (StaticCondition::DayOfWeek(a, x), StaticCondition::DayOfWeek(b, y)) => {
+            str_eq(a, b)
+                && match (Weekdays::parse_days(x), Weekdays::parse_days(y)) {
+                    (Ok(x), Ok(y)) => x.bits() == y.bits(),
+                    _ => false,
+                }
+        }
+        (StaticCondition::All(a), StaticCondition::All(b))
+        | (StaticCondition::Any(a), StaticCondition::Any(b)) => {
+            if a.len() != b.len() {
+                return false;
+            }
+            let mut i = 0;
+            while i < a.len() {
+                if !condition_eq(&a[i], &b[i]) {
+                    return false;
+                }
+                i += 1;
+            }
+            true
+        }
+        (StaticCondition::Not(a), StaticCondition::Not(b)) => condition_eq(a, b),
+        _ => false,
+    }
+}
+
+const fn check_str(s: &str, config: &PolicyConfig) {
+    assert!(
+        s.len() <= config.max_string_len,
+        "static_policy!: string longer than max_string_len"
+    );
+}
+
+const fn check_matcher(matcher: &Matcher<'static>, config: &PolicyConfig) {
+    match matcher {
+        Matcher::Any | Matcher::OneOfVec(_) => {}
+        Matcher::Exact(s) | Matcher::Group(s) => check_str(s, config),
+        Matcher::OneOf(options) => {
+            assert!(
+                options.len() <= config.max_matcher_options,
+                "static_policy!: more matcher options than max_matcher_options"
+            );
+            let mut i = 0;
+            while i < options.len() {
+                check_str(options[i], config);
+                i += 1;
+            }
+        }
+    }
+}
+
+const fn check_attr_name(attr: &str, config: &PolicyConfig) {
+    check_str(attr, config);
+    assert!(
+        attr.len() <= config.max_attr_name_len,
+        "static_policy!: attribute name longer than max_attr_name_len"
+    );
+}
+
+const fn check_attrs(condition: &StaticCondition, config: &PolicyConfig) {
+    match condition {
+        StaticCondition::True | StaticCondition::False => {}
+        StaticCondition::Equals(attr, value) | StaticCondition::NotEquals(attr, value) => {
+            check_attr_name(attr, config);
+            if let Value::String(s) = value {
+                check_str(s, config);
+                assert!(
+                    s.len() <= config.max_attr_value_len,
+                    "static_policy!: attribute value longer than max_attr_value_len"
+                );
+            }
+        }
+        StaticCondition::GreaterThan(attr, value) | StaticCondition::LessThan(attr, value) => {
+            check_attr_name(attr, config);
+            assert!(
+                matches!(
+                    value,
+                    Value::Int(_) | Value::Timestamp(_) | Value::Duration(_)
+                ),
+                "static_policy!: GT and LT need an integer, duration, or timestamp"
+            );
+        }
+        StaticCondition::IpInCidr(attr, cidr) => {
+            check_attr_name(attr, config);
+            check_str(cidr, config);
+            assert!(
+                Cidr::parse(cidr).is_ok(),
+                "static_policy!: invalid CIDR block"
+            );
+        }
+        StaticCondition::TimeBetween(attr, start, end) => {
+            check_attr_name(attr, config);
+            assert!(
+                matches!(parse_time_of_day(start), Ok(start) if start < SECONDS_PER_DAY)
+                    && parse_time_of_day(end).is_ok(),
+                "static_policy!: invalid time of day"
+            );
+        }
+        StaticCondition::DayOfWeek(attr, days) => {
+            check_attr_name(attr, config);
+            assert!(
+                Weekdays::parse_days(days).is_ok(),
+                "static_policy!: invalid day of the week"
+            );
+        }
+        StaticCondition::All(operands) | StaticCondition::Any(operands) => {
+            let mut i = 0;
+            while i < operands.len() {
+                check_attrs(&operands[i], config);
+                i += 1;
+            }
+        }
+        StaticCondition::Not(inner) => check_attrs(inner, config),
+    }
+}
+
+/// `Condition::depth` of the condition `to_condition` builds.
+const fn depth(condition: &StaticCondition) -> usize {
+    match condition {
+        StaticCondition::All(operands) | StaticCondition::Any(operands) => balanced_depth(operands),
+        StaticCondition::Not(inner) => 1 + depth(inner),
+        _ => 1,
+    }
+}
+
+const fn balanced_depth(operands: &[StaticCondition]) -> usize {
+    if operands.len() == 1 {
+        return depth(&operands[0]);
+    }
+    let (left, right) = operands.split_at(operands.len() / 2);
+    let (left, right) = (balanced_depth(left), balanced_depth(right));
+    1 + if left > right { left } else { right }
+}
+
+/// `Condition::node_count` of the condition `to_condition` builds.
+const fn node_count(condition: &StaticCondition) -> usize {
+    match condition {
+        StaticCondition::All(operands) | StaticCondition::Any(operands) => {
+            // n operands are joined by n - 1 binary nodes
+            let mut count = operands.len() - 1;
+            let mut i = 0;
+            while i < operands.len() {
+                count += node_count(&operands[i]);
+                i += 1;
+            }
+            count
+        }
+        StaticCondition::Not(inner) => 1 + node_count(inner),
+        _ => 1,
+    }
+}
+
+/// Build the policy for checked rules.
+#[doc(hidden)]
+pub fn build(rules: &'static [StaticRule], config: PolicyConfig) -> Policy<'static> {
+    let rules = rules
+        .iter()
+        .map(|rule| Rule {
+            name: rule.name,
+            cache: rule.cache,
+            ..Rule::new(
+                rule.effect,
+                rule.target.clone(),
+                rule.condition.map(to_condition),
+                rule.reason,
+            )
+        })
+        .collect();
+    Policy::with_config(rules, config).expect("static_policy! checks limits at compile time")
+}
+
+fn to_condition(condition: &'static StaticCondition) -> Condition<'static> {
+    match condition {
+        StaticCondition::True => Condition::True,
+        StaticCondition::False => Condition::False,
+        StaticCondition::Equals(attr, value) => Condition::Equals {
+            attr,
+            value: value.clone(),
+        },
+        StaticCondition::NotEquals(attr, value) => Condition::NotEquals {
+            attr,
+            value: value.clone(),
+        },
+        StaticCondition::GreaterThan(attr, value) => Condition::GreaterThan {
+            attr,
+            value: value.clone(),
+        },
+        StaticCondition::LessThan(attr, value) => Condition::LessThan {
+            attr,
+            value: value.clone(),
+        },
+        StaticCondition::IpInCidr(attr, cidr) => Condition::IpInCidr { attr, cidr },
+        StaticCondition::TimeBetween(attr, start, end) => Condition::TimeBetween {
+            attr,
+            start: parse_time_of_day(start).expect("checked at compile time"),
+            end: parse_time_of_day(end).expect("checked at compile time"),
+        },
+        StaticCondition::DayOfWeek(attr, days) => Condition::DayOfWeek {
+            attr,
+            days: Weekdays::parse_days(days).expect("checked at compile time"),
+        },
+        StaticCondition::All(operands) => balanced(operands, Condition::And),
+        StaticCondition::Any(operands) => balanced(operands, Condition::Or),
+        StaticCondition::Not(inner) => Condition::Not(Box::new(to_condition(inner))),
+    }
+}
+
+/// Join `operands` as a balanced tree, split the same way as `parse_policy`.
+fn balanced(
+    operands: &'static [StaticCondition],
+    join: fn(Box<Condition<'static>>, Box<Condition<'static>>) -> Condition<'static>,
+) -> Condition<'static> {
+    if operands.len() == 1 {
+        return to_condition(&operands[0]);
+    }
+    let (left, right) = operands.split_at(operands.len() / 2);
+    join(
+        Box::new(balanced(left, join)),
+        Box::new(balanced(right, join)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Request, Value};
+
+    const STRICT: crate::PolicyConfig = crate::PolicyConfig {
+        max_rules: 2,
+        ..crate::PolicyConfig::DEFAULT
+    };
+
+    crate::static_policy! {
+        static POLICY = {
+            DENY "contractors never see payroll" (@"contractors" * "payroll") => 9;
+            DENY (* "delete" *) WHERE { (region IN ["eu" "uk"]) OR ("tier" IN []) } => 4 CACHE 1m CONTEXT;
+            ALLOW ("alice" ["read" "write"] *)
+                WHERE { (role EQ "admin") AND NOT ("suspended" EQ true) OR (level NEQ -1) } => 3;
+            ALLOW (* "read" *) WHERE { TRUE } => 1 CACHE 5m;
+        };
+
+        static SMALL with STRICT = {
+            ALLOW (* * *) => 1;
+        };
+    }
+
+    #[test]
+    fn test_static_policy() {
+        assert_eq!(POLICY.rule_count(), 4);
+        assert_eq!(
+            POLICY.rules()[0].name,
+            Some("contractors never see payroll")
+        );
+        assert_eq!(POLICY.rules()[0].cache, None);
+        assert_eq!(
+            POLICY.rules()[1].cache,
+            Some(crate::CacheHint::ttl(60).per_context())
+        );
+        assert_eq!(POLICY.rules()[3].cache, Some(crate::CacheHint::ttl(300)));
+        let ctx: &[(&str, Value)] = &[
+            ("role", Value::String("admin")),
+            ("suspended", Value::Bool(false)),
+        ];
+        let decision = POLICY
+            .evaluate(&Request::with_context("alice", "write", "doc", ctx))
+            .unwrap();
+        assert_eq!(decision.reason.value(), 3);
+        let ctx: &[(&str, Value)] = &[("region", Value::String("uk"))];
+        let decision = POLICY
+            .evaluate(&Request::with_context("bob", "delete", "doc", ctx))
+            .unwrap();
+        assert_eq!(decision.reason.value(), 4);
+        assert_eq!(SMALL.config().max_rules, 2);
+    }
+
+    #[test]
+    fn test_constants() {
+        const ADMIN: &str = "alice";
+        const WRITES: &[&str] = &["write", "delete"];
+        const LEVEL: i64 = 3;
+        const DOCS: &str = "docs";
+        mod attrs {
+            pub const MFA: &str = "mfa";
+        }
+
+        crate::static_policy! {
+            static CONSTS = {
+                ALLOW (ADMIN WRITES *) WHERE { ({attrs::MFA} EQ true) AND (level IN [LEVEL -1]) } => 1;
+                ALLOW (* ["read" ADMIN] @DOCS) WHERE { (level NEQ {LEVEL + 1}) } => 2;
+            };
+        }
+
+        let rules = CONSTS.rules();
+        assert_eq!(rules[0].target.principal, crate::Matcher::Exact("alice"));
+        assert_eq!(rules[0].target.action, crate::Matcher::OneOf(WRITES));
+        assert_eq!(
+            rules[1].target.action,
+            crate::Matcher::OneOf(&["read", "alice"])
+        );
+        assert_eq!(rules[1].target.resource, crate::Matcher::Group("docs"));
+
+        let ctx: &[(&str, Value)] = &[("mfa", Value::Bool(true)), ("level", Value::Int(-1))];
+        let decision = CONSTS
+            .evaluate(&Request::with_context("alice", "delete", "doc", ctx))
+            .unwrap();
+        assert_eq!(decision.reason.value(), 1);
+        assert_eq!(
+            rules[1].condition,
+            Some(crate::Condition::NotEquals {
+                attr: "level",
+                value: Value::Int(4),
+            })
+        );
+    }
+
+    #[test]
+    fn test_ip_in_cidr() {
+        const OFFICE: &str = "192.168.10.0/24";
+
+        crate::static_policy! {
+            static NETWORK = {
+                ALLOW (* * *) WHERE { (source_ip IN_CIDR "10.0.0.0/8") OR (source_ip IN_CIDR OFFICE) } => 1;
+            };
+        }
+
+        let request = |ip: [u8; 4]| {
+            let ctx: &[(&str, Value)] = &[("source_ip", Value::Ip(ip.into()))];
+            NETWORK
+                .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+                .unwrap()
+                .is_allow()
+        };
+        assert!(request([10, 0, 0, 1]));
+        assert!(request([192, 168, 10, 7]));
+        assert!(!request([192, 168, 11, 7]));
+    }
+
+    #[test]
+    fn test_time_windows() {
+        const WEEKEND: &[&str] = &["sat", "sun"];
+
+        crate::static_policy! {
+            static HOURS = {
+                ALLOW (* * *) WHERE { (now BETWEEN "09:00" "17:00") AND NOT (now ON ["sat" "sun"]) } => 1;
+            };
+        }
+
+        assert_eq!(
+            HOURS.rules()[0].condition,
+            Some(crate::Condition::And(
+                alloc::boxed::Box::new(crate::Condition::TimeBetween {
+                    attr: "now",
+                    start: 9 * 3600,
+                    end: 17 * 3600,
+                }),
+                alloc::boxed::Box::new(crate::Condition::Not(alloc::boxed::Box::new(
+                    crate::Condition::DayOfWeek {
+                        attr: "now",
+                        days: crate::Weekdays::parse_days(WEEKEND).unwrap(),
+                    }
+                ))),
+            ))
+        );
+
+        // Friday 2024-01-05 09:00 and Saturday 2024-01-06 09:00
+        let allowed = |t| {
+            let ctx: &[(&str, Value)] = &[("now", Value::Timestamp(t))];
+            HOURS
+                .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+                .unwrap()
+                .is_allow()
+        };
+        assert!(allowed(1_704_445_200));
+        assert!(!allowed(1_704_531_600));
+    }
+
+    #[test]
+    fn test_ordering() {
+        const MAX_SESSION: Value<'static> = Value::Duration(8 * 3600);
+
+        crate::static_policy! {
+            static SESSIONS = {
+                DENY (* "login" *) WHERE { (session GT 1h30m) AND (level LT -1) } => 1;
+                DENY (* "sudo" *) WHERE { (session GT MAX_SESSION) OR (level LT 3) } => 2;
+            };
+        }
+
+        let rules = SESSIONS.rules();
+        assert_eq!(
+            rules[0].condition,
+            Some(crate::Condition::And(
+                alloc::boxed::Box::new(crate::Condition::GreaterThan {
+                    attr: "session",
+                    value: Value::Duration(5400),
+                }),
+                alloc::boxed::Box::new(crate::Condition::LessThan {
+                    attr: "level",
+                    value: Value::Int(-1),
+                }),
+            ))
+        );
+        assert_eq!(
+            rules[1].condition,
+            Some(crate::Condition::Or(
+                alloc::boxed::Box::new(crate::Condition::GreaterThan {
+                    attr: "session",
+                    value: MAX_SESSION,
+                }),
+                alloc::boxed::Box::new(crate::Condition::LessThan {
+                    attr: "level",
+                    value: Value::Int(3),
+                }),
+            ))
+        );
+    }
+
+    #[test]
+    fn test_balanced_chains_fit_default_depth() {
+        crate::static_policy! {
+            static CHAIN = {
+                ALLOW (* * *) WHERE {
+                    (a EQ 1) AND (b EQ 2) AND (c EQ 3) AND (d EQ 4) AND (e EQ 5) AND (f EQ 6)
+                    AND (g EQ 7) AND (h EQ 8) AND (i EQ 9) AND (j EQ 10) AND (k EQ 11)
+                } => 1;
+            };
+        }
+        assert_eq!(CHAIN.rules()[0].condition.as_ref().unwrap().depth(), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "text-dsl")]
+    fn test_same_policy_as_text() {
+        let text = crate::parse_policy(
+            r#"
+            DENY "contractors never see payroll" (@"contractors" * "payroll") => 9;
+            DENY (* "delete" *) WHERE { (region IN ["eu" "uk"]) OR ("tier" IN []) } => 4 CACHE 1m CONTEXT;
+            ALLOW ("alice" ["read" "write"] *)
+                WHERE { (role EQ "admin") AND NOT ("suspended" EQ true) OR (level NEQ -1) } => 3;
+            ALLOW (* "read" *) WHERE { TRUE } => 1 CACHE 5m;
+            "#,
+        )
+        .unwrap();
+        assert_eq!(text.policy().unwrap().fingerprint(), POLICY.fingerprint());
+    }
+}
@@ -1,7 +1,13 @@
 //! Evaluation statistics for observable bound usage.
 //!
 //! This module provides the `EvaluationStats` struct which tracks
-//! how close an evaluation got to its configured limits.
+//! how close an evaluation got to its configured limits, and the
+//! `StatsAggregator` which accumulates them across many evaluations.
+
+use alloc::collections::BTreeMap;
+
+use crate::error::PolicyError;
+use crate::types::{Decision, ReasonCode};
 
 /// Observable bound usage during policy evaluation.
 ///
@@ -14,21 +20,65 @@
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct EvaluationStats {
     /// Number of rules checked before reaching a decision.
-    /// 
-    /// For deny-overrides semantics, this may be less than the total
-    /// rule count if an early deny is found.
+    ///
+    /// Every candidate rule is checked, so this is the rule count unless a
+    /// target index excluded some rules (see `termination`).
     pub rules_checked: u16,
 
     /// Maximum stack depth reached during condition evaluation.
-    /// 
+    ///
     /// Compare against `ABSOLUTE_MAX_CONDITION_DEPTH` to see how
     /// close you got to the limit.
     pub max_depth_reached: u8,
 
-    /// Total number of condition nodes evaluated.
-    /// 
-    /// Includes all And, Or, Not, Equals, NotEquals nodes visited.
+    /// Number of conditions evaluated (one per rule whose target matched
+    /// and that has a condition).
     pub condition_evals: u16,
+
+    /// Number of matchers checked against request fields: one per target
+    /// field checked, plus one per resource ancestor tried.
+    pub matcher_comparisons: u16,
+
+    /// Number of context attribute lookups made by conditions.
+    pub context_lookups: u16,
+
+    /// Evaluation steps consumed, as counted against
+    /// `PolicyConfig::max_eval_steps`.
+    pub steps: u32,
+
+    /// Number of context attributes resolved through a `ContextProvider`.
+    ///
+    /// Always zero for evaluations without a provider.
+    pub resolutions: u16,
+
+    /// Number of resource ancestors walked while matching targets.
+    ///
+    /// Always zero unless the policy has a resource hierarchy.
+    pub ancestors_walked: u16,
+
+    /// Why evaluation stopped checking rules.
+    pub termination: Termination,
+}
+
+/// Why an evaluation stopped checking rules.
+///
+/// Evaluation never stops early on a match: every candidate rule is
+/// checked, so that stats, traces, and observers are complete and the cost
+/// of a request does not depend on which rules match. Running out of step
+/// budget is not a termination reason either, since it returns
+/// `PolicyError::StepBudgetExceeded` instead of a decision.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Termination {
+    /// Every rule in the policy was checked.
+    #[default]
+    ExhaustedRules,
+    /// Every candidate from the target index was checked; the index
+    /// excluded the remaining rules without checking them.
+    ExhaustedCandidates,
+    /// No rule was checked: a `DecisionCache` answered with a decision it
+    /// stored earlier. Every counter is zero.
+    Cached,
 }
 
 impl EvaluationStats {
@@ -39,6 +89,12 @@ impl EvaluationStats {
             rules_checked: 0,
             max_depth_reached: 0,
             condition_evals: 0,
+            steps: 0,
+            matcher_comparisons: 0,
+            context_lookups: 0,
+            resolutions: 0,
+            ancestors_walked: 0,
+            termination: Termination::ExhaustedRules,
         }
     }
 
@@ -61,6 +117,174 @@ impl EvaluationStats {
     pub fn inc_condition_evals(&mut self) {
         self.condition_evals = self.condition_evals.saturating_add(1);
     }
+
+    /// Record the number of evaluation steps consumed.
+    #[inline]
+    pub fn set_steps(&mut self, steps: usize) {
+        self.steps = u32::try_from(steps).unwrap_or(u32::MAX);
+    }
+
+    /// Increment the matcher comparisons counter.
+    #[inline]
+    pub fn inc_matcher_comparisons(&mut self) {
+        self.matcher_comparisons = self.matcher_comparisons.saturating_add(1);
+    }
+
+    /// Add to the context lookups counter.
+    #[inline]
+    pub fn add_context_lookups(&mut self, lookups: u16) {
+        self.context_lookups = self.context_lookups.saturating_add(lookups);
+    }
+
+    /// Increment the ancestors walked counter.
+    #[inline]
+    pub fn inc_ancestors(&mut self) {
+        self.ancestors_walked = self.ancestors_walked.saturating_add(1);
+    }
+
+    /// Record the number of lazily resolved attributes.
+    #[inline]
+    pub fn set_resolutions(&mut self, resolutions: usize) {
+        self.resolutions = u16::try_from(resolutions).unwrap_or(u16::MAX);
+    }
+}
+
+/// Minimum, maximum, and total of one counter across evaluations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatSummary {
+    /// Number of values recorded.
+    pub count: u64,
+    /// Smallest value recorded (0 if none).
+    pub min: u64,
+    /// Largest value recorded (0 if none).
+    pub max: u64,
+    /// Sum of all values recorded, saturating.
+    pub total: u64,
+}
+
+impl StatSummary {
+    /// Mean of the recorded values, or `None` if nothing was recorded.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.total as f64 / self.count as f64)
+    }
+
+    fn record(&mut self, value: u64) {
+        self.min = if self.count == 0 {
+            value
+        } else {
+            self.min.min(value)
+        };
+        self.max = self.max.max(value);
+        self.total = self.total.saturating_add(value);
+        self.count += 1;
+    }
+}
+
+/// Totals accumulated by a `StatsAggregator`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StatsSnapshot {
+    /// Evaluations recorded, including errors.
+    pub evaluations: u64,
+    /// Evaluations that returned an error.
+    pub errors: u64,
+    /// Decisions that allowed.
+    pub allows: u64,
+    /// Decisions that denied.
+    pub denies: u64,
+    /// Rules checked per decision.
+    pub rules_checked: StatSummary,
+    /// Conditions evaluated per decision (one per rule whose target
+    /// matched and that has a condition).
+    pub condition_evals: StatSummary,
+    /// Evaluation steps per decision.
+    pub steps: StatSummary,
+    /// Deepest condition stack reached by any decision.
+    pub max_depth_reached: u8,
+    /// Number of decisions per reason code.
+    pub reasons: BTreeMap<ReasonCode, u64>,
+}
+
+/// Accumulates `EvaluationStats` across many evaluations.
+///
+/// Recording takes `&mut self`; wrap the aggregator in a `Mutex` (or keep
+/// one per thread) to share it.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Policy, ReasonCode, Request, Rule, StatsAggregator, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(Target::any(), ReasonCode(1)))
+///     .build()
+///     .unwrap();
+///
+/// let mut stats = StatsAggregator::new();
+/// for principal in ["alice", "bob"] {
+///     stats.record_result(&policy.evaluate_with_stats(&Request::new(principal, "read", "doc")));
+/// }
+///
+/// let snapshot = stats.take();
+/// assert_eq!(snapshot.allows, 2);
+/// assert_eq!(snapshot.reasons[&ReasonCode(1)], 2);
+/// assert_eq!(snapshot.rules_checked.mean(), Some(1.0));
+/// assert_eq!(stats.snapshot().evaluations, 0);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StatsAggregator {
+    totals: StatsSnapshot,
+}
+
+impl StatsAggregator {
+    /// Create an empty aggregator.
+    pub fn new() -> Self {
+        StatsAggregator::default()
+    }
+
+    /// Record one decision and its stats.
+    pub fn record(&mut self, decision: &Decision, stats: &EvaluationStats) {
+        let totals = &mut self.totals;
+        totals.evaluations += 1;
+        if decision.is_allow() {
+            totals.allows += 1;
+        } else {
+            totals.denies += 1;
+        }
+        totals.rules_checked.record(stats.rules_checked.into());
+        totals.condition_evals.record(stats.condition_evals.into());
+        totals.steps.record(stats.steps.into());
+        totals.max_depth_reached = totals.max_depth_reached.max(stats.max_depth_reached);
+        *totals.reasons.entry(decision.reason).or_insert(0) += 1;
+    }
+
+    /// Record an evaluation that returned an error.
+    pub fn record_error(&mut self) {
+        self.totals.evaluations += 1;
+        self.totals.errors += 1;
+    }
+
+    /// Record the result of `Policy::evaluate_with_stats`.
+    pub fn record_result(&mut self, result: &Result<(Decision, EvaluationStats), PolicyError>) {
+        match result {
+            Ok((decision, stats)) => self.record(decision, stats),
+            Err(_) => self.record_error(),
+        }
+    }
+
+    /// The totals so far.
+    pub fn snapshot(&self) -> StatsSnapshot {
+        self.totals.clone()
+    }
+
+    /// Discard everything recorded.
+    pub fn reset(&mut self) {
+        self.totals = StatsSnapshot::default();
+    }
+
+    /// The totals so far, resetting the aggregator.
+    pub fn take(&mut self) -> StatsSnapshot {
+        core::mem::take(&mut self.totals)
+    }
 }
 
 #[cfg(test)]
@@ -73,12 +297,18 @@ mod tests {
         assert_eq!(stats.rules_checked, 0);
         assert_eq!(stats.max_depth_reached, 0);
         assert_eq!(stats.condition_evals, 0);
+        assert_eq!(stats.steps, 0);
+        assert_eq!(stats.resolutions, 0);
+        assert_eq!(stats.ancestors_walked, 0);
+        assert_eq!(stats.matcher_comparisons, 0);
+        assert_eq!(stats.context_lookups, 0);
+        assert_eq!(stats.termination, Termination::ExhaustedRules);
     }
 
     #[test]
     fn test_stats_increment() {
         let mut stats = EvaluationStats::new();
-        
+
         stats.inc_rules();
         stats.inc_rules();
         assert_eq!(stats.rules_checked, 2);
@@ -90,5 +320,65 @@ mod tests {
 
         stats.inc_condition_evals();
         assert_eq!(stats.condition_evals, 1);
+
+        stats.set_steps(usize::MAX);
+        assert_eq!(stats.steps, u32::MAX);
+
+        stats.inc_ancestors();
+        assert_eq!(stats.ancestors_walked, 1);
+
+        stats.set_resolutions(3);
+        assert_eq!(stats.resolutions, 3);
+
+        stats.inc_matcher_comparisons();
+        assert_eq!(stats.matcher_comparisons, 1);
+
+        stats.add_context_lookups(u16::MAX);
+        stats.add_context_lookups(1);
+        assert_eq!(stats.context_lookups, u16::MAX);
+    }
+
+    #[test]
+    fn test_aggregator() {
+        let mut agg = StatsAggregator::new();
+        assert_eq!(agg.snapshot().rules_checked.mean(), None);
+
+        let stats = |rules_checked, steps| EvaluationStats {
+            rules_checked,
+            steps,
+            max_depth_reached: rules_checked as u8,
+            ..EvaluationStats::new()
+        };
+        agg.record(&Decision::allow(ReasonCode(1)), &stats(2, 4));
+        agg.record(&Decision::deny(ReasonCode(2)), &stats(6, 9));
+        agg.record(&Decision::allow(ReasonCode(1)), &stats(4, 5));
+        agg.record_result(&Err(PolicyError::InternalError));
+
+        let snapshot = agg.snapshot();
+        assert_eq!(snapshot.evaluations, 4);
+        assert_eq!(
+            (snapshot.allows, snapshot.denies, snapshot.errors),
+            (2, 1, 1)
+        );
+        assert_eq!(
+            snapshot.rules_checked,
+            StatSummary {
+                count: 3,
+                min: 2,
+                max: 6,
+                total: 12
+            }
+        );
+        assert_eq!(snapshot.rules_checked.mean(), Some(4.0));
+        assert_eq!(snapshot.steps.max, 9);
+        assert_eq!(snapshot.max_depth_reached, 6);
+        assert_eq!(snapshot.reasons[&ReasonCode(1)], 2);
+        assert_eq!(snapshot.reasons[&ReasonCode(2)], 1);
+
+        assert_eq!(agg.take(), snapshot);
+        assert_eq!(agg.snapshot(), StatsSnapshot::default());
+        agg.record(&Decision::deny(ReasonCode(3)), &stats(1, 1));
+        agg.reset();
+        assert!(agg.snapshot().reasons.is_empty());
     }
 }
@@ -0,0 +1,208 @@
+//! Many policies keyed by tenant (requires `std`).
+//!
+//! Multi-tenant services keep one compiled policy per tenant or namespace.
+//! `PolicyStore` holds them behind a read-write lock; evaluation takes a
+//! cheap `Arc` snapshot of the tenant's policy and evaluates outside the
+//! lock, so replacing one tenant's policy is atomic: in-flight evaluations
+//! finish on the old policy, new ones see the new policy, and no other
+//! tenant is affected.
+//!
+//! Each tenant's policy carries its own `PolicyConfig`, so limits can differ
+//! per tenant.
+
+use std::collections::BTreeMap;
+use std::string::{String, ToString};
+use std::sync::{Arc, PoisonError, RwLock};
+
+use crate::error::PolicyError;
+use crate::policy::Policy;
+use crate::types::{Decision, Request};
+
+/// Default maximum number of tenants in a store.
+pub const DEFAULT_MAX_TENANTS: usize = 10_000;
+
+/// Compiled policies keyed by tenant.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Policy, PolicyStore, ReasonCode, Request, Rule, Target};
+///
+/// let store = PolicyStore::new();
+/// store
+///     .insert("acme", Policy::new(vec![Rule::allow(Target::any(), ReasonCode(1))]).unwrap())
+///     .unwrap();
+///
+/// let request = Request::new("alice", "read", "doc");
+/// assert!(store.evaluate("acme", &request).unwrap().is_allow());
+/// assert!(store.evaluate("globex", &request).is_err());
+/// ```
+#[derive(Debug)]
+pub struct PolicyStore<'a> {
+    tenants: RwLock<BTreeMap<String, Arc<Policy<'a>>>>,
+    max_tenants: usize,
+}
+
+impl<'a> PolicyStore<'a> {
+    /// Create an empty store holding at most `DEFAULT_MAX_TENANTS` tenants.
+    pub fn new() -> Self {
+        Self::with_max_tenants(DEFAULT_MAX_TENANTS)
+    }
+
+    /// Create an empty store holding at most `max_tenants` tenants.
+    pub fn with_max_tenants(max_tenants: usize) -> Self {
+        PolicyStore {
+            tenants: RwLock::new(BTreeMap::new()),
+            max_tenants,
+        }
+    }
+
+    /// Install or atomically replace a tenant's policy.
+    ///
+    /// Returns the previous policy, if any. Fails with `TooManyTenants` if
+    /// adding a new tenant would exceed the store's limit.
+    pub fn insert(
+        &self,
+        tenant: &str,
+        policy: Policy<'a>,
+    ) -> Result<Option<Arc<Policy<'a>>>, PolicyError> {
+        let mut tenants = self.tenants.write().unwrap_or_else(PoisonError::into_inner);
+        if !tenants.contains_key(tenant) && tenants.len() >= self.max_tenants {
+            return Err(PolicyError::TooManyTenants {
+                max: self.max_tenants,
+                actual: tenants.len() + 1,
+            });
+        }
+        Ok(tenants.insert(tenant.to_string(), Arc::new(policy)))
+    }
+
+    /// Remove a tenant, returning its policy.
+    pub fn remove(&self, tenant: &str) -> Option<Arc<Policy<'a>>> {
+        self.tenants
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(tenant)
+    }
+
+    /// A snapshot of a tenant's current policy.
+    pub fn get(&self, tenant: &str) -> Option<Arc<Policy<'a>>> {
+        self.tenants
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(tenant)
+            .cloned()
+    }
+
+    /// Evaluate a request against a tenant's policy.
+    ///
+    /// Fails with `UnknownTenant` if the tenant has no policy. The lock is
+    /// held only long enough to take a snapshot.
+    pub fn evaluate(&self, tenant: &str, request: &Request<'_>) -> Result<Decision, PolicyError> {
+        self.get(tenant)
+            .ok_or(PolicyError::UnknownTenant)?
+            .evaluate(request)
+    }
+
+    /// Returns `true` if the tenant has a policy.
+    pub fn contains(&self, tenant: &str) -> bool {
+        self.tenants
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains_key(tenant)
+    }
+
+    /// Number of tenants.
+    pub fn len(&self) -> usize {
+        self.tenants
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    /// Returns `true` if the store has no tenants.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of tenants.
+    pub fn max_tenants(&self) -> usize {
+        self.max_tenants
+    }
+}
+
+impl Default for PolicyStore<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PolicyConfig, ReasonCode, Rule, Target};
+    use std::vec;
+
+    fn allow(reason: u32) -> Policy<'static> {
+        Policy::new(vec![Rule::allow(Target::any(), ReasonCode(reason))]).unwrap()
+    }
+
+    #[test]
+    fn test_tenants_are_isolated_and_replaceable() {
+        let store = PolicyStore::new();
+        store.insert("acme", allow(1)).unwrap();
+        store
+            .insert(
+                "globex",
+                Policy::with_config(
+                    vec![Rule::allow(Target::any(), ReasonCode(2))],
+                    PolicyConfig {
+                        max_string_len: 4,
+                        ..PolicyConfig::default()
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap();
+
+        let request = Request::new("alice", "read", "doc");
+        assert_eq!(
+            store.evaluate("acme", &request).unwrap().reason,
+            ReasonCode(1)
+        );
+        // Per-tenant limits: "alice" is too long for globex only
+        assert!(matches!(
+            store.evaluate("globex", &request),
+            Err(PolicyError::StringTooLong { max: 4, .. })
+        ));
+        assert_eq!(
+            store.evaluate("initech", &request),
+            Err(PolicyError::UnknownTenant)
+        );
+
+        // A snapshot taken before replacement keeps the old policy
+        let before = store.get("acme").unwrap();
+        let previous = store.insert("acme", allow(3)).unwrap();
+        assert!(Arc::ptr_eq(&before, &previous.unwrap()));
+        assert_eq!(before.evaluate(&request).unwrap().reason, ReasonCode(1));
+        assert_eq!(
+            store.evaluate("acme", &request).unwrap().reason,
+            ReasonCode(3)
+        );
+
+        assert!(store.remove("acme").is_some());
+        assert!(!store.contains("acme"));
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn test_tenant_limit() {
+        let store = PolicyStore::with_max_tenants(1);
+        store.insert("a", allow(1)).unwrap();
+        // Replacing an existing tenant is always allowed
+        store.insert("a", allow(2)).unwrap();
+        assert_eq!(
+            store.insert("b", allow(1)).unwrap_err(),
+            PolicyError::TooManyTenants { max: 1, actual: 2 }
+        );
+    }
+}
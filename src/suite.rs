@@ -0,0 +1,363 @@
+//! Policy regression tests that live next to the policy.
+//!
+//! A `PolicyTestSuite` is a list of requests with the decision each should
+//! get. Running it against a policy evaluates every case and reports each
+//! mismatch as an expected/actual diff, so a suite can ship in the same
+//! module as the policy it protects and run from any `#[test]`.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::PolicyError;
+use crate::policy::Policy;
+use crate::types::{Decision, Effect, ReasonCode, Request};
+use crate::value::Value;
+
+/// The outcome a case expects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expected {
+    /// This exact decision.
+    Decision(Decision),
+    /// Evaluation fails with this error.
+    Error(PolicyError),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Decision(decision) => write_decision(f, decision),
+            Expected::Error(err) => write!(f, "error: {}", err),
+        }
+    }
+}
+
+fn write_decision(f: &mut fmt::Formatter<'_>, decision: &Decision) -> fmt::Result {
+    let effect = match decision.effect {
+        Effect::Allow => "ALLOW",
+        Effect::Deny => "DENY",
+    };
+    write!(f, "{} (reason {})", effect, decision.reason.value())
+}
+
+/// One expected decision.
+#[derive(Debug, Clone)]
+pub struct TestCase<'a> {
+    /// Optional description, shown in reports.
+    pub name: Option<&'a str>,
+    /// The request to evaluate.
+    pub request: Request<'a>,
+    /// The expected outcome.
+    pub expected: Expected,
+}
+
+/// A list of requests and the decisions they should get.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Policy, PolicyTestSuite, ReasonCode, Rule, Target, Value, NO_MATCHING_RULE};
+///
+/// const ADMIN: ReasonCode = ReasonCode(1);
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::new(
+///         gate0::Effect::Allow,
+///         Target::any(),
+///         Some(gate0::Condition::Equals { attr: "role", value: Value::String("admin") }),
+///         ADMIN,
+///     ))
+///     .build()
+///     .unwrap();
+///
+/// let mut suite = PolicyTestSuite::new();
+/// suite
+///     .expect("alice", "read", "doc")
+///     .with_ctx(&[("role", Value::String("admin"))])
+///     .to_allow(ADMIN);
+/// suite.expect("bob", "read", "doc").to_deny(NO_MATCHING_RULE);
+///
+/// suite.run(&policy).assert_passed();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PolicyTestSuite<'a> {
+    cases: Vec<TestCase<'a>>,
+}
+
+impl<'a> PolicyTestSuite<'a> {
+    /// Create an empty suite.
+    pub fn new() -> Self {
+        PolicyTestSuite { cases: Vec::new() }
+    }
+
+    /// Start a case for a request; finish it with `to_allow`, `to_deny`, or
+    /// `to_fail`.
+    pub fn expect(
+        &mut self,
+        principal: &'a str,
+        action: &'a str,
+        resource: &'a str,
+    ) -> Expectation<'_, 'a> {
+        Expectation {
+            suite: self,
+            name: None,
+            request: Request::new(principal, action, resource),
+        }
+    }
+
+    /// Add a fully built case.
+    pub fn case(&mut self, case: TestCase<'a>) -> &mut Self {
+        self.cases.push(case);
+        self
+    }
+
+    /// The declared cases, in order.
+    pub fn cases(&self) -> &[TestCase<'a>] {
+        &self.cases
+    }
+
+    /// Number of cases.
+    pub fn len(&self) -> usize {
+        self.cases.len()
+    }
+
+    /// Returns `true` if the suite has no cases.
+    pub fn is_empty(&self) -> bool {
+        self.cases.is_empty()
+    }
+
+    /// Evaluate every case against `policy`.
+    pub fn run(&self, policy: &Policy<'_>) -> TestReport<'_, 'a> {
+        let failures = self
+            .cases
+            .iter()
+            .enumerate()
+            .filter_map(|(index, case)| {
+                let actual = policy.evaluate(&case.request);
+                let passed = match (&case.expected, &actual) {
+                    (Expected::Decision(want), Ok(got)) => want == got,
+                    (Expected::Error(want), Err(got)) => want == got,
+                    _ => false,
+                };
+                (!passed).then_some(CaseFailure {
+                    index,
+                    case,
+                    actual,
+                })
+            })
+            .collect();
+        TestReport {
+            total: self.cases.len(),
+            failures,
+        }
+    }
+}
+
+/// A case being declared; see `PolicyTestSuite::expect`.
+#[must_use = "finish the case with to_allow, to_deny, or to_fail"]
+#[derive(Debug)]
+pub struct Expectation<'s, 'a> {
+    suite: &'s mut PolicyTestSuite<'a>,
+    name: Option<&'a str>,
+    request: Request<'a>,
+}
+
+impl<'a> Expectation<'_, 'a> {
+    /// Describe the case in reports.
+    pub fn named(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Set the request context.
+    pub fn with_ctx(mut self, context: &'a [(&'a str, Value<'a>)]) -> Self {
+        self.request.context = context;
+        self
+    }
+
+    /// Expect an allow with `reason`.
+    pub fn to_allow(self, reason: ReasonCode) {
+        self.finish(Expected::Decision(Decision::allow(reason)));
+    }
+
+    /// Expect a deny with `reason`.
+    pub fn to_deny(self, reason: ReasonCode) {
+        self.finish(Expected::Decision(Decision::deny(reason)));
+    }
+
+    /// Expect evaluation to fail with `error`.
+    pub fn to_fail(self, error: PolicyError) {
+        self.finish(Expected::Error(error));
+    }
+
+    fn finish(self, expected: Expected) {
+        self.suite.cases.push(TestCase {
+            name: self.name,
+            request: self.request,
+            expected,
+        });
+    }
+}
+
+/// A case whose outcome differed from the expectation.
+#[derive(Debug, Clone)]
+pub struct CaseFailure<'s, 'a> {
+    /// Position of the case in the suite.
+    pub index: usize,
+    /// The case.
+    pub case: &'s TestCase<'a>,
+    /// What evaluation actually returned.
+    pub actual: Result<Decision, PolicyError>,
+}
+
+impl fmt::Display for CaseFailure<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let request = &self.case.request;
+        write!(f, "case {}", self.index)?;
+        if let Some(name) = self.case.name {
+            write!(f, " ({})", name)?;
+        }
+        write!(
+            f,
+            ": {} {} {}",
+            request.principal, request.action, request.resource
+        )?;
+        for (key, value) in request.context {
+            write!(f, " {}={}", key, value)?;
+        }
+        write!(f, "\n  expected: {}\n  actual:   ", self.case.expected)?;
+        match &self.actual {
+            Ok(decision) => write_decision(f, decision),
+            Err(err) => write!(f, "error: {}", err),
+        }
+    }
+}
+
+/// The result of running a suite.
+#[derive(Debug, Clone)]
+pub struct TestReport<'s, 'a> {
+    total: usize,
+    failures: Vec<CaseFailure<'s, 'a>>,
+}
+
+impl TestReport<'_, '_> {
+    /// Returns `true` if every case passed.
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Number of cases run.
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Cases that did not get their expected outcome, in suite order.
+    pub fn failures(&self) -> &[CaseFailure<'_, '_>] {
+        &self.failures
+    }
+
+    /// Panic with the full report if any case failed.
+    #[track_caller]
+    pub fn assert_passed(&self) {
+        if !self.passed() {
+            panic!("{}", self);
+        }
+    }
+}
+
+impl fmt::Display for TestReport<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} policy test cases failed",
+            self.failures.len(),
+            self.total
+        )?;
+        for failure in &self.failures {
+            write!(f, "\n{}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Matcher, Rule, Target, NO_MATCHING_RULE};
+    use alloc::string::ToString;
+
+    fn policy() -> Policy<'static> {
+        Policy::builder()
+            .rule(Rule::deny(
+                Target {
+                    principal: Matcher::Exact("mallory"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::Equals {
+                    attr: "role",
+                    value: Value::String("admin"),
+                }),
+                ReasonCode(2),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_passing_suite() {
+        let mut suite = PolicyTestSuite::new();
+        suite
+            .expect("alice", "read", "doc")
+            .with_ctx(&[("role", Value::String("admin"))])
+            .to_allow(ReasonCode(2));
+        suite
+            .expect("mallory", "read", "doc")
+            .to_deny(ReasonCode(1));
+        suite
+            .expect("bob", "read", "doc")
+            .named("no role")
+            .to_deny(NO_MATCHING_RULE);
+
+        let report = suite.run(&policy());
+        assert!(report.passed());
+        assert_eq!(report.total(), 3);
+        report.assert_passed();
+    }
+
+    #[test]
+    fn test_failure_report() {
+        let mut suite = PolicyTestSuite::new();
+        suite.expect("alice", "read", "doc").to_allow(ReasonCode(2));
+        suite
+            .expect("bob", "read", "doc")
+            .named("admins read")
+            .with_ctx(&[("role", Value::String("admin"))])
+            .to_deny(ReasonCode(1));
+        suite
+            .expect("carol", "read", "doc")
+            .to_fail(PolicyError::UnknownTenant);
+
+        let report = suite.run(&policy());
+        assert!(!report.passed());
+        assert_eq!(report.failures().len(), 3);
+        assert_eq!(report.failures()[1].index, 1);
+        assert_eq!(
+            report.to_string(),
+            "3 of 3 policy test cases failed\n\
+             case 0: alice read doc\n  \
+             expected: ALLOW (reason 2)\n  \
+             actual:   DENY (reason 0)\n\
+             case 1 (admins read): bob read doc role=\"admin\"\n  \
+             expected: DENY (reason 1)\n  \
+             actual:   ALLOW (reason 2)\n\
+             case 2: carol read doc\n  \
+             expected: error: no policy for tenant\n  \
+             actual:   DENY (reason 0)"
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! No Prefix matcher - intentionally omitted to avoid footguns.
 
+use alloc::vec::Vec;
+
 use crate::error::PolicyError;
+use crate::groups::GroupMap;
 
 /// A target specifies which requests a rule applies to.
 #[derive(Debug, Clone, PartialEq)]
@@ -14,8 +17,17 @@ pub struct Target<'a> {
 }
 
 impl<'a> Target<'a> {
+    /// Create a target from its three matchers.
+    pub const fn new(principal: Matcher<'a>, action: Matcher<'a>, resource: Matcher<'a>) -> Self {
+        Target {
+            principal,
+            action,
+            resource,
+        }
+    }
+
     /// Create a target that matches everything.
-    pub fn any() -> Self {
+    pub const fn any() -> Self {
         Target {
             principal: Matcher::Any,
             action: Matcher::Any,
@@ -24,15 +36,34 @@ impl<'a> Target<'a> {
     }
 
     /// Check if this target matches the given request fields.
+    ///
+    /// `Matcher::Group` never matches here; use `matches_in` to resolve it.
     pub fn matches(&self, principal: &str, action: &str, resource: &str) -> bool {
         self.principal.matches(principal)
             && self.action.matches(action)
             && self.resource.matches(resource)
     }
+
+    /// Check if this target matches, resolving `Matcher::Group` in `groups`.
+    pub fn matches_in(
+        &self,
+        principal: &str,
+        action: &str,
+        resource: &str,
+        groups: &GroupMap<'_>,
+    ) -> bool {
+        self.principal.matches_in(principal, groups)
+            && self.action.matches_in(action, groups)
+            && self.resource.matches_in(resource, groups)
+    }
 }
 
 /// A matcher for a single field (principal, action, or resource).
+///
+/// New kinds of matcher may be added, so matches on it outside this crate
+/// need a wildcard arm.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Matcher<'a> {
     /// Matches any value.
     Any,
@@ -40,15 +71,36 @@ pub enum Matcher<'a> {
     Exact(&'a str),
     /// Matches any value in the list.
     OneOf(&'a [&'a str]),
+    /// Matches any value in an owned list.
+    ///
+    /// Same semantics as `OneOf`, for option lists built at runtime where
+    /// there is no borrowed slice to point at (see `OwnedPolicy`).
+    OneOfVec(Vec<&'a str>),
+    /// Matches any value that belongs to the group, directly or through
+    /// nesting, according to the policy's `GroupMap`.
+    ///
+    /// Usually used for principals. A policy without a group map has no
+    /// members, so this matcher fails closed.
+    Group(&'a str),
 }
 
 impl<'a> Matcher<'a> {
     /// Check if this matcher matches the given value.
+    ///
+    /// `Group` never matches here; use `matches_in` to resolve it.
     pub fn matches(&self, value: &str) -> bool {
+        self.matches_in(value, &GroupMap::default())
+    }
+
+    /// Check if this matcher matches the given value, resolving `Group`
+    /// membership in `groups`.
+    pub fn matches_in(&self, value: &str, groups: &GroupMap<'_>) -> bool {
         match self {
             Matcher::Any => true,
             Matcher::Exact(expected) => value == *expected,
             Matcher::OneOf(options) => options.contains(&value),
+            Matcher::OneOfVec(options) => options.contains(&value),
+            Matcher::Group(group) => groups.is_member(value, group),
         }
     }
 
@@ -57,29 +109,45 @@ impl<'a> Matcher<'a> {
     pub fn validate(&self, max_options: usize, max_string_len: usize) -> Result<(), PolicyError> {
         match self {
             Matcher::Any => Ok(()),
-            Matcher::Exact(s) => validate_str(s, max_string_len),
-            Matcher::OneOf(options) => {
-                if options.len() > max_options {
-                    return Err(PolicyError::TooManyMatcherOptions {
-                        max: max_options,
-                        actual: options.len(),
-                    });
-                }
-                for opt in *options {
-                    validate_str(opt, max_string_len)?;
-                }
-                Ok(())
-            }
+            Matcher::Exact(s) | Matcher::Group(s) => validate_str(s, max_string_len),
+            Matcher::OneOf(options) => validate_options(options, max_options, max_string_len),
+            Matcher::OneOfVec(options) => validate_options(options, max_options, max_string_len),
         }
     }
 }
 
+/// The parent of a hierarchical value: everything before the last
+/// `separator`, or `None` at the root.
+pub(crate) fn parent(value: &str, separator: char) -> Option<&str> {
+    value.rfind(separator).map(|i| &value[..i])
+}
+
+/// Validate the option count and string lengths of a `OneOf`-style list.
+fn validate_options(
+    options: &[&str],
+    max_options: usize,
+    max_string_len: usize,
+) -> Result<(), PolicyError> {
+    if options.len() > max_options {
+        return Err(PolicyError::TooManyMatcherOptions {
+            max: max_options,
+            actual: options.len(),
+            location: None,
+        });
+    }
+    for opt in options {
+        validate_str(opt, max_string_len)?;
+    }
+    Ok(())
+}
+
 /// Validate that a string does not exceed the maximum allowed length.
 fn validate_str(s: &str, max_len: usize) -> Result<(), PolicyError> {
     if s.len() > max_len {
         Err(PolicyError::StringTooLong {
             max: max_len,
             actual: s.len(),
+            location: None,
         })
     } else {
         Ok(())
@@ -161,6 +229,13 @@ mod tests {
         assert!(!t.matches("user", "invoke", "api/v1/health"));
     }
 
+    #[test]
+    fn test_parent() {
+        assert_eq!(parent("a/b/c", '/'), Some("a/b"));
+        assert_eq!(parent("a/b", '/'), Some("a"));
+        assert_eq!(parent("a", '/'), None);
+    }
+
     #[test]
     fn test_matcher_too_many_options() {
         let options = vec!["a", "b", "c"];
@@ -173,7 +248,11 @@ mod tests {
         let err = m.validate(2, 256).unwrap_err();
         assert!(matches!(
             err,
-            PolicyError::TooManyMatcherOptions { max: 2, actual: 3 }
+            PolicyError::TooManyMatcherOptions {
+                max: 2,
+                actual: 3,
+                location: None
+            }
         ));
     }
 }
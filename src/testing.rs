@@ -0,0 +1,293 @@
+//! Proptest strategies and invariant checks for code built on Gate0.
+//!
+//! The strategies generate valid policies and requests over a small
+//! vocabulary (`NAMES` for targets, `ATTRS` for context attributes), so
+//! generated requests hit generated rules often. Every generated policy
+//! builds with the default `PolicyConfig`.
+//!
+//! The checks state the engine's invariants as reusable assertions that
+//! return `TestCaseError`, so they compose with `prop_assert!` and `?`:
+//!
+//! ```
+//! use gate0::testing::{self, check_deny_overrides, check_deterministic};
+//! use proptest::test_runner::{Config, TestRunner};
+//!
+//! let mut runner = TestRunner::new(Config::with_cases(16));
+//! runner
+//!     .run(&(testing::policy(), testing::request()), |(policy, req)| {
+//!         check_deterministic(&policy, &req.request())?;
+//!         check_deny_overrides(&policy, &req.request())
+//!     })
+//!     .unwrap();
+//! ```
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use proptest::prelude::*;
+use proptest::test_runner::TestCaseError;
+
+use crate::condition::Condition;
+use crate::policy::{Policy, PolicyConfig, Rule};
+use crate::target::{parent, Matcher, Target};
+use crate::types::{Decision, Effect, ReasonCode, Request, DEFAULT_ALLOW, NO_MATCHING_RULE};
+use crate::value::Value;
+
+/// Principals, actions, and resources used by generated targets and requests.
+pub const NAMES: &[&str] = &["alice", "bob", "carol", "read", "write", "doc"];
+
+/// Context attributes used by generated conditions and requests.
+pub const ATTRS: &[&str] = &["a", "b", "c"];
+
+/// A generated request. Owns its context, so borrow it with `request()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestRequest {
+    /// The principal, from `NAMES`.
+    pub principal: &'static str,
+    /// The action, from `NAMES`.
+    pub action: &'static str,
+    /// The resource, from `NAMES`.
+    pub resource: &'static str,
+    /// Context entries, with distinct keys from `ATTRS`.
+    pub context: Vec<(&'static str, Value<'static>)>,
+}
+
+impl TestRequest {
+    /// Borrow this as a `Request`.
+    pub fn request(&self) -> Request<'_> {
+        Request::with_context(self.principal, self.action, self.resource, &self.context)
+    }
+}
+
+/// Either effect.
+pub fn effect() -> impl Strategy<Value = Effect> {
+    prop_oneof![Just(Effect::Allow), Just(Effect::Deny)]
+}
+
+/// A reason code usable by rules (neither `NO_MATCHING_RULE` nor
+/// `DEFAULT_ALLOW`).
+pub fn reason() -> impl Strategy<Value = ReasonCode> {
+    (1u32..1000).prop_map(ReasonCode)
+}
+
+/// `Any`, an exact name, or a list of names from `NAMES`.
+pub fn matcher() -> impl Strategy<Value = Matcher<'static>> {
+    prop_oneof![
+        Just(Matcher::Any),
+        prop::sample::select(NAMES).prop_map(Matcher::Exact),
+        prop::sample::subsequence(NAMES, 0..=3).prop_map(Matcher::OneOfVec),
+    ]
+}
+
+/// A target of three `matcher()`s.
+pub fn target() -> impl Strategy<Value = Target<'static>> {
+    (matcher(), matcher(), matcher()).prop_map(|(principal, action, resource)| Target {
+        principal,
+        action,
+        resource,
+    })
+}
+
+/// A boolean, a small integer, or a name from `NAMES`.
+pub fn value() -> impl Strategy<Value = Value<'static>> {
+    prop_oneof![
+        any::<bool>().prop_map(Value::Bool),
+        (-2i64..=2).prop_map(Value::Int),
+        prop::sample::select(NAMES).prop_map(Value::String),
+    ]
+}
+
+/// A condition over `ATTRS` no deeper than `max_depth` (at least 1).
+pub fn condition(max_depth: u32) -> impl Strategy<Value = Condition<'static>> {
+    let attr = || prop::sample::select(ATTRS);
+    let leaf = prop_oneof![
+        Just(Condition::True),
+        Just(Condition::False),
+        (attr(), value()).prop_map(|(attr, value)| Condition::Equals { attr, value }),
+        (attr(), value()).prop_map(|(attr, value)| Condition::NotEquals { attr, value }),
+        (attr(), -2i64..=2).prop_map(|(attr, n)| Condition::GreaterThan {
+            attr,
+            value: Value::Int(n),
+        }),
+        (attr(), -2i64..=2).prop_map(|(attr, n)| Condition::LessThan {
+            attr,
+            value: Value::Int(n),
+        }),
+    ];
+    leaf.prop_recursive(max_depth.saturating_sub(1), 32, 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|c| Condition::Not(Box::new(c))),
+            (inner.clone(), inner.clone())
+                .prop_map(|(a, b)| Condition::And(Box::new(a), Box::new(b))),
+            (inner.clone(), inner).prop_map(|(a, b)| Condition::Or(Box::new(a), Box::new(b))),
+        ]
+    })
+}
+
+/// A rule with a `target()` and, sometimes, a condition of depth at most 4.
+pub fn rule() -> impl Strategy<Value = Rule<'static>> {
+    (effect(), target(), prop::option::of(condition(4)), reason()).prop_map(
+        |(effect, target, condition, reason)| Rule::new(effect, target, condition, reason),
+    )
+}
+
+/// A policy of up to 20 `rule()`s with the default config.
+pub fn policy() -> impl Strategy<Value = Policy<'static>> {
+    prop::collection::vec(rule(), 0..20).prop_map(|rules| {
+        Policy::new(rules).expect("generated rules are within the default limits")
+    })
+}
+
+/// A request over `NAMES` whose context sets some of `ATTRS`.
+pub fn request() -> impl Strategy<Value = TestRequest> {
+    let names = || prop::sample::select(NAMES);
+    let context =
+        prop::collection::vec(prop::option::of(value()), ATTRS.len()).prop_map(|values| {
+            ATTRS
+                .iter()
+                .zip(values)
+                .filter_map(|(attr, value)| value.map(|v| (*attr, v)))
+                .collect()
+        });
+    (names(), names(), names(), context).prop_map(|(principal, action, resource, context)| {
+        TestRequest {
+            principal,
+            action,
+            resource,
+            context,
+        }
+    })
+}
+
+/// Check that evaluating `request` twice gives the same result.
+pub fn check_deterministic(
+    policy: &Policy<'_>,
+    request: &Request<'_>,
+) -> Result<(), TestCaseError> {
+    prop_assert_eq!(policy.evaluate(request), policy.evaluate(request));
+    Ok(())
+}
+
+/// Check `policy`'s decision against a reference model of deny-overrides:
+/// the first matching deny wins, else the first matching allow, else the
+/// default effect.
+///
+/// The model matches targets with `Target::matches_in` (walking resource
+/// ancestors if the policy has a separator) and conditions with
+/// `Condition::evaluate`. Evaluation errors are not checked, and policies
+/// with `Condition::Custom` are outside the model.
+pub fn check_deny_overrides(
+    policy: &Policy<'_>,
+    request: &Request<'_>,
+) -> Result<(), TestCaseError> {
+    let Ok(decision) = policy.evaluate(request) else {
+        return Ok(());
+    };
+
+    let matches = |rule: &&Rule<'_>| {
+        let mut resource = Some(request.resource);
+        let mut target = false;
+        while let Some(r) = resource {
+            if rule
+                .target
+                .matches_in(request.principal, request.action, r, policy.groups())
+            {
+                target = true;
+                break;
+            }
+            resource = policy.resource_separator().and_then(|sep| parent(r, sep));
+        }
+        target
+            && rule
+                .condition
+                .as_ref()
+                .map_or(Ok(true), |c| c.evaluate(request.context))
+                .unwrap_or(false)
+    };
+    let matching: Vec<&Rule<'_>> = policy.rules().iter().filter(matches).collect();
+    let first = |effect| matching.iter().find(|r| r.effect == effect);
+
+    let expected = match (first(Effect::Deny), first(Effect::Allow)) {
+        (Some(rule), _) | (None, Some(rule)) => Decision::new(rule.effect, rule.reason),
+        (None, None) => match policy.config().default_effect {
+            Effect::Deny => Decision::new(Effect::Deny, NO_MATCHING_RULE),
+            Effect::Allow => Decision::new(Effect::Allow, DEFAULT_ALLOW),
+        },
+    };
+    prop_assert_eq!(
+        decision,
+        expected,
+        "deny-overrides violated for {:?}",
+        request
+    );
+    Ok(())
+}
+
+/// Check that `Condition::normalize` keeps `condition`'s result in
+/// `context`, and stays within the limits the original met.
+pub fn check_normalize_preserves(
+    condition: &Condition<'_>,
+    context: &[(&str, Value<'_>)],
+) -> Result<(), TestCaseError> {
+    let normalized = condition.normalize();
+    prop_assert_eq!(normalized.evaluate(context), condition.evaluate(context));
+    prop_assert!(
+        normalized.depth() <= condition.depth(),
+        "normalizing deepened {:?}",
+        condition
+    );
+    let config = PolicyConfig::default();
+    if condition
+        .validate(config.max_condition_depth, config.max_string_len)
+        .is_ok()
+    {
+        prop_assert!(normalized
+            .validate(config.max_condition_depth, config.max_string_len)
+            .is_ok());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn prop_invariants_hold(policy in policy(), request in request()) {
+            check_deterministic(&policy, &request.request())?;
+            check_deny_overrides(&policy, &request.request())?;
+        }
+
+        #[test]
+        fn prop_normalize_preserves(condition in condition(6), request in request()) {
+            check_normalize_preserves(&condition, &request.context)?;
+        }
+    }
+
+    #[test]
+    fn test_model_covers_default_effect_and_hierarchies() {
+        let config = PolicyConfig {
+            default_effect: Effect::Allow,
+            ..PolicyConfig::default()
+        };
+        let rule = Rule::deny(
+            Target {
+                principal: Matcher::Any,
+                action: Matcher::Any,
+                resource: Matcher::Exact("project"),
+            },
+            ReasonCode(2),
+        );
+        let policy = Policy::with_config(alloc::vec![rule], config)
+            .unwrap()
+            .with_resource_separator('/');
+
+        for resource in ["project/doc", "other/doc"] {
+            let request = Request::new("alice", "read", resource);
+            assert!(check_deny_overrides(&policy, &request).is_ok());
+        }
+    }
+}
@@ -0,0 +1,996 @@
+//! Runtime parser for the text policy syntax (feature `text-dsl`).
+//!
+//! Operators write policies as text; `parse_policy` turns them into an
+//! `OwnedPolicy`:
+//!
+//! ```text
+//! // Comments run to the end of the line
+//! DENY (@"contractors" * "payroll") => 9;
+//! ALLOW ("alice" ["read" "write"] *) WHERE { (role EQ "admin") AND NOT (suspended EQ true) } => 3;
+//! ALLOW (* "read" *) => 1;
+//! ```
+//!
+//! A rule is an effect (`ALLOW` or `DENY`), an optional name for audits
+//! (`ALLOW "admins can do anything" (..) => 1;`), a target of three matchers
+//! (principal, action, resource), an optional `WHERE { .. }` condition, and
+//! `=> reason;`. Matchers are `*` (any), a string (exact), a bracketed list
+//! of strings (one of), or `@"name"` (group). A cache hint may follow the
+//! reason: `=> 1 CACHE 5m;` for a TTL, `=> 1 CACHE CONTEXT;` for decisions
+//! that hold only while the context is unchanged, or both, `CACHE 5m
+//! CONTEXT`.
+//!
+//! Conditions compare an attribute with a value, `(attr EQ value)`,
+//! `(attr NEQ value)`, `(attr GT value)`, or `(attr LT value)` (integers and
+//! durations are ordered), or test it against a list, `(attr IN ["a" "b"])`,
+//! which is shorthand for the `OR` of one `EQ` per value (an empty list is
+//! `FALSE`), or test an address against a CIDR block,
+//! `(source_ip IN_CIDR "10.0.0.0/8")`, or a timestamp against a time of day
+//! or days of the week (UTC), `(now BETWEEN "09:00" "17:00")` and
+//! `(now ON ["sat" "sun"])`. Blocks, times, and days are checked as they are
+//! parsed. They combine with `NOT`, `AND`, and `OR` (in order of
+//! precedence) and parentheses; `TRUE` and `FALSE` are constants. Values are
+//! strings, integers, durations such as `1h30m`, `true`, and `false`.
+//! Attribute names are identifiers
+//! or strings. Chains of `AND` or `OR` are built as balanced trees, so a
+//! long chain costs logarithmic depth against `max_condition_depth`.
+//!
+//! Only operators with a matching `Condition` are accepted; `EXISTS` and
+//! `CONTAINS` are parse errors until conditions for them exist.
+//!
+//! Errors carry the line and column where parsing stopped. Limits are
+//! checked when the result is turned into a `Policy` with
+//! `OwnedPolicy::policy`, except nesting, which the parser bounds by the
+//! config's `max_condition_depth` (and never beyond 256 levels) so hostile
+//! input cannot exhaust the stack.
+
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::net::Cidr;
+use crate::owned::{OwnedCondition, OwnedMatcher, OwnedPolicy, OwnedRule, OwnedTarget, OwnedValue};
+use crate::policy::PolicyConfig;
+use crate::time::{parse_duration, parse_time_of_day, Weekdays};
+use crate::types::{CacheHint, Effect, ReasonCode};
+
+/// Nesting limit for the parser, whatever the configured depth (which the
+/// `heap-stack` feature lets exceed what a recursive parser should recurse).
+const MAX_PARSE_DEPTH: usize = 256;
+
+/// Keywords that follow the attribute in a comparison.
+const COMPARISONS: &[&str] = &["EQ", "NEQ", "GT", "LT", "IN", "IN_CIDR", "BETWEEN", "ON"];
+
+/// Parse a policy with the default config.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{parse_policy, Request, Value};
+///
+/// let owned = parse_policy(r#"
+///     ALLOW ("alice" "read" *) WHERE { (role EQ "admin") } => 3;
+/// "#).unwrap();
+/// let policy = owned.policy().unwrap();
+///
+/// let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+/// let decision = policy
+///     .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+///     .unwrap();
+/// assert_eq!(decision.reason.value(), 3);
+///
+/// let error = parse_policy("ALLOW (* * *) => 1").unwrap_err();
+/// assert_eq!(
+///     error.to_string(),
+///     "line 1, column 19: expected `CACHE` or `;`, found end of input"
+/// );
+/// ```
+pub fn parse_policy(source: &str) -> Result<OwnedPolicy, ParseError> {
+    parse_policy_with_config(source, PolicyConfig::default())
+}
+
+/// Parse a policy with the given config.
+pub fn parse_policy_with_config(
+    source: &str,
+    config: PolicyConfig,
+) -> Result<OwnedPolicy, ParseError> {
+    let mut parser = Parser {
+        tokens: lex(source)?,
+        next: 0,
+        max_depth: config.max_condition_depth.min(MAX_PARSE_DEPTH),
+    };
+    let mut rules = Vec::new();
+    while parser.peek().0 != Token::Eof {
+        rules.push(parser.rule()?);
+    }
+    Ok(OwnedPolicy::with_config(rules, config))
+}
+
+/// A parse error and where it occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// 1-based line.
+    pub line: u32,
+    /// 1-based column, in characters.
+    pub column: u32,
+    /// What went wrong.
+    pub kind: ParseErrorKind,
+}
+
+/// The kind of a `ParseError`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// A character that cannot start a token.
+    UnexpectedChar(char),
+    /// A string literal without its closing quote.
+    UnterminatedString,
+    /// An unsupported escape sequence in a string literal.
+    InvalidEscape(char),
+    /// An integer that does not fit its type (`i64` for values, `u32` for
+    /// reason codes).
+    IntegerOutOfRange,
+    /// A number followed by letters that is not a duration such as `1h30m`.
+    InvalidDuration(String),
+    /// A token other than the one the grammar requires.
+    Unexpected {
+        /// What the grammar required.
+        expected: &'static str,
+        /// What was found, as written.
+        found: String,
+    },
+    /// Conditions nested deeper than `max_condition_depth`.
+    TooDeep {
+        /// The configured limit.
+        max: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::UnexpectedChar(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            ParseErrorKind::InvalidEscape(c) => write!(f, "invalid escape \\{}", c),
+            ParseErrorKind::IntegerOutOfRange => write!(f, "integer out of range"),
+            ParseErrorKind::InvalidDuration(s) => write!(f, "invalid duration `{}`", s),
+            ParseErrorKind::Unexpected { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ParseErrorKind::TooDeep { max } => {
+                write!(f, "condition nested deeper than {}", max)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    LBrace,
+    RBrace,
+    LBracket,
+    RBracket,
+    Star,
+    At,
+    Arrow,
+    Semi,
+    Str(String),
+    Int(i64),
+    /// A duration in seconds, written with its text for messages.
+    Duration(u64, String),
+    Ident(String),
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LParen => write!(f, "`(`"),
+            Token::RParen => write!(f, "`)`"),
+            Token::LBrace => write!(f, "`{{`"),
+            Token::RBrace => write!(f, "`}}`"),
+            Token::LBracket => write!(f, "`[`"),
+            Token::RBracket => write!(f, "`]`"),
+            Token::Star => write!(f, "`*`"),
+            Token::At => write!(f, "`@`"),
+            Token::Arrow => write!(f, "`=>`"),
+            Token::Semi => write!(f, "`;`"),
+            Token::Str(s) => write!(f, "{:?}", s),
+            Token::Int(i) => write!(f, "`{}`", i),
+            Token::Duration(_, s) => write!(f, "`{}`", s),
+            Token::Ident(s) => write!(f, "`{}`", s),
+            Token::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Pos {
+    line: u32,
+    column: u32,
+}
+
+impl Pos {
+    fn error(self, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            kind,
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':' | '-')
+}
+
+fn lex(source: &str) -> Result<Vec<(Token, Pos)>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = source.chars().peekable();
+    let mut pos = Pos { line: 1, column: 1 };
+    let advance = |c: char, pos: &mut Pos| {
+        if c == '\n' {
+            pos.line = pos.line.saturating_add(1);
+            pos.column = 1;
+        } else {
+            pos.column = pos.column.saturating_add(1);
+        }
+    };
+
+    while let Some(&c) = chars.peek() {
+        let start = pos;
+        if c.is_whitespace() {
+            chars.next();
+            advance(c, &mut pos);
+            continue;
+        }
+        chars.next();
+        advance(c, &mut pos);
+        let token = match c {
+            '/' if chars.peek() == Some(&'/') => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                    advance(c, &mut pos);
+                }
+                continue;
+            }
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '[' => Token::LBracket,
+            ']' => Token::RBracket,
+            '*' => Token::Star,
+            '@' => Token::At,
+            ';' => Token::Semi,
+            '=' if chars.peek() == Some(&'>') => {
+                chars.next();
+                advance('>', &mut pos);
+                Token::Arrow
+            }
+            '"' => {
+                let mut s = String::new();
+                loop {
+                    let Some(c) = chars.next() else {
+                        return Err(start.error(ParseErrorKind::UnterminatedString));
+                    };
+                    let at = pos;
+                    advance(c, &mut pos);
+                    match c {
+                        '"' => break,
+                        '\\' => {
+                            let Some(e) = chars.next() else {
+                                return Err(start.error(ParseErrorKind::UnterminatedString));
+                            };
+                            advance(e, &mut pos);
+                            s.push(match e {
+                                '"' => '"',
+                                '\\' => '\\',
+                                'n' => '\n',
+                                't' => '\t',
+                                other => return Err(at.error(ParseErrorKind::InvalidEscape(other))),
+                            });
+                        }
+                        '\n' => return Err(start.error(ParseErrorKind::UnterminatedString)),
+                        c => s.push(c),
+                    }
+                }
+                Token::Str(s)
+            }
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut digits = String::from(c);
+                while let Some(&d) = chars.peek().filter(|d| d.is_ascii_digit()) {
+                    chars.next();
+                    advance(d, &mut pos);
+                    digits.push(d);
+                }
+                if digits == "-" {
+                    return Err(start.error(ParseErrorKind::UnexpectedChar('-')));
+                }
+                // Letters after the digits make a duration, e.g. `1h30m`
+                if chars.peek().is_some_and(char::is_ascii_alphabetic) {
+                    while let Some(&d) = chars.peek().filter(|d| d.is_ascii_alphanumeric()) {
+                        chars.next();
+                        advance(d, &mut pos);
+                        digits.push(d);
+                    }
+                    match parse_duration(&digits) {
+                        Ok(seconds) => Token::Duration(seconds, digits),
+                        Err(_) => {
+                            return Err(start.error(ParseErrorKind::InvalidDuration(digits)));
+                        }
+                    }
+                } else {
+                    let value = digits
+                        .parse()
+                        .map_err(|_| start.error(ParseErrorKind::IntegerOutOfRange))?;
+                    Token::Int(value)
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut ident = String::from(c);
+                while let Some(&d) = chars.peek().filter(|d| is_ident_char(**d)) {
+                    chars.next();
+                    advance(d, &mut pos);
+                    ident.push(d);
+                }
+                Token::Ident(ident)
+            }
+            other => return Err(start.error(ParseErrorKind::UnexpectedChar(other))),
+        };
+        tokens.push((token, start));
+    }
+    tokens.push((Token::Eof, pos));
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<(Token, Pos)>,
+    next: usize,
+    max_depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &(Token, Pos) {
+        &self.tokens[self.next]
+    }
+
+    fn peek_at(&self, ahead: usize) -> &Token {
+        let index = (self.next + ahead).min(self.tokens.len() - 1);
+        &self.tokens[index].0
+    }
+
+    fn bump(&mut self) -> (Token, Pos) {
+        let token = self.tokens[self.next].clone();
+        if token.0 != Token::Eof {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn unexpected<T>(&self, expected: &'static str) -> Result<T, ParseError> {
+        let (token, pos) = self.peek();
+        Err(pos.error(ParseErrorKind::Unexpected {
+            expected,
+            found: token.to_string(),
+        }))
+    }
+
+    fn expect(&mut self, token: Token, expected: &'static str) -> Result<(), ParseError> {
+        if self.peek().0 == token {
+            self.bump();
+            Ok(())
+        } else {
+            self.unexpected(expected)
+        }
+    }
+
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().0, Token::Ident(s) if s == keyword)
+    }
+
+    fn rule(&mut self) -> Result<OwnedRule, ParseError> {
+        let effect = if self.keyword("ALLOW") {
+            Effect::Allow
+        } else if self.keyword("DENY") {
+            Effect::Deny
+        } else {
+            return self.unexpected("`ALLOW` or `DENY`");
+        };
+        self.bump();
+
+        let name = match self.peek().0.clone() {
+            Token::Str(name) => {
+                self.bump();
+                Some(name)
+            }
+            _ => None,
+        };
+
+        self.expect(Token::LParen, "a rule name or `(`")?;
+        let principal = self.matcher()?;
+        let action = self.matcher()?;
+        let resource = self.matcher()?;
+        self.expect(Token::RParen, "`)`")?;
+
+        let condition = if self.keyword("WHERE") {
+            self.bump();
+            self.expect(Token::LBrace, "`{`")?;
+            let condition = self.or(0)?;
+            self.expect(Token::RBrace, "`}`")?;
+            Some(condition)
+        } else {
+            None
+        };
+
+        self.expect(Token::Arrow, "`=>`")?;
+        let reason = match self.peek().clone() {
+            (Token::Int(n), pos) => {
+                self.bump();
+                u32::try_from(n).map_err(|_| pos.error(ParseErrorKind::IntegerOutOfRange))?
+            }
+            _ => return self.unexpected("a reason code"),
+        };
+        let cache = if self.keyword("CACHE") {
+            self.bump();
+            Some(self.cache_hint()?)
+        } else {
+            None
+        };
+        self.expect(Token::Semi, "`CACHE` or `;`")?;
+
+        Ok(OwnedRule {
+            name,
+            cache,
+            ..OwnedRule::new(
+                effect,
+                OwnedTarget {
+                    principal,
+                    action,
+                    resource,
+                },
+                condition,
+                ReasonCode(reason),
+            )
+        })
+    }
+
+    /// The hint after `CACHE`: a duration, `CONTEXT`, or both.
+    fn cache_hint(&mut self) -> Result<CacheHint, ParseError> {
+        let mut hint = match self.peek().0 {
+            Token::Duration(seconds, _) => {
+                self.bump();
+                CacheHint::ttl(seconds)
+            }
+            _ => CacheHint::UNLIMITED,
+        };
+        if self.keyword("CONTEXT") {
+            self.bump();
+            hint = hint.per_context();
+        } else if hint.ttl.is_none() {
+            return self.unexpected("a duration or `CONTEXT`");
+        }
+        Ok(hint)
+    }
+
+    fn matcher(&mut self) -> Result<OwnedMatcher, ParseError> {
+        match self.peek().0.clone() {
+            Token::Star => {
+                self.bump();
+                Ok(OwnedMatcher::Any)
+            }
+            Token::Str(s) => {
+                self.bump();
+                Ok(OwnedMatcher::Exact(s))
+            }
+            Token::LBracket => {
+                self.bump();
+                let mut options = Vec::new();
+                while let Token::Str(s) = &self.peek().0 {
+                    options.push(s.clone());
+                    self.bump();
+                }
+                self.expect(Token::RBracket, "a string or `]`")?;
+                Ok(OwnedMatcher::OneOf(options))
+            }
+            Token::At => {
+                self.bump();
+                match self.peek().0.clone() {
+                    Token::Str(s) => {
+                        self.bump();
+                        Ok(OwnedMatcher::Group(s))
+                    }
+                    _ => self.unexpected("a group name"),
+                }
+            }
+            _ => self.unexpected("a matcher (`*`, a string, `[..]`, or `@\"group\"`)"),
+        }
+    }
+
+    fn enter(&self, depth: usize) -> Result<usize, ParseError> {
+        if depth >= self.max_depth {
+            let (_, pos) = self.peek();
+            return Err(pos.error(ParseErrorKind::TooDeep {
+                max: self.max_depth,
+            }));
+        }
+        Ok(depth + 1)
+    }
+
+    fn or(&mut self, depth: usize) -> Result<OwnedCondition, ParseError> {
+        let depth = self.enter(depth)?;
+        let mut operands = vec![self.and(depth)?];
+        while self.keyword("OR") {
+            self.bump();
+            operands.push(self.and(depth)?);
+        }
+        Ok(balanced(operands, OwnedCondition::Or))
+    }
+
+    fn and(&mut self, depth: usize) -> Result<OwnedCondition, ParseError> {
+        let mut operands = vec![self.unary(depth)?];
+        while self.keyword("AND") {
+            self.bump();
+            operands.push(self.unary(depth)?);
+        }
+        Ok(balanced(operands, OwnedCondition::And))
+    }
+
+    fn unary(&mut self, depth: usize) -> Result<OwnedCondition, ParseError> {
+        if self.keyword("NOT") {
+            self.bump();
+            let depth = self.enter(depth)?;
+            return Ok(OwnedCondition::Not(Box::new(self.unary(depth)?)));
+        }
+        if self.keyword("TRUE") {
+            self.bump();
+            return Ok(OwnedCondition::True);
+        }
+        if self.keyword("FALSE") {
+            self.bump();
+            return Ok(OwnedCondition::False);
+        }
+        self.expect(Token::LParen, "a condition")?;
+        // An attribute starts a comparison; keywords start a nested
+        // condition unless they are themselves compared
+        let is_comparison = match self.peek_at(0) {
+            Token::Str(_) => true,
+            Token::Ident(s) => {
+                !matches!(s.as_str(), "NOT" | "TRUE" | "FALSE")
+                    || matches!(self.peek_at(1), Token::Ident(op) if COMPARISONS.contains(&op.as_str()))
+            }
+            _ => false,
+        };
+        let condition = if is_comparison {
+            self.comparison()?
+        } else {
+            self.or(depth)?
+        };
+        self.expect(Token::RParen, "`)`")?;
+        Ok(condition)
+    }
+
+    fn comparison(&mut self) -> Result<OwnedCondition, ParseError> {
+        let attr = match self.bump().0 {
+            Token::Ident(s) | Token::Str(s) => s,
+            _ => unreachable!("checked by the caller"),
+        };
+        let build: fn(String, OwnedValue) -> OwnedCondition = if self.keyword("EQ") {
+            |attr, value| OwnedCondition::Equals { attr, value }
+        } else if self.keyword("NEQ") {
+            |attr, value| OwnedCondition::NotEquals { attr, value }
+        } else if self.keyword("GT") {
+            |attr, value| OwnedCondition::GreaterThan { attr, value }
+        } else if self.keyword("LT") {
+            |attr, value| OwnedCondition::LessThan { attr, value }
+        } else if self.keyword("IN") {
+            self.bump();
+            return self.one_of(attr);
+        } else if self.keyword("IN_CIDR") {
+            self.bump();
+            return self.in_cidr(attr);
+        } else if self.keyword("BETWEEN") {
+            self.bump();
+            let start = self.time_of_day()?;
+            let end = self.time_of_day()?;
+            return Ok(OwnedCondition::TimeBetween { attr, start, end });
+        } else if self.keyword("ON") {
+            self.bump();
+            return self.days(attr);
+        } else {
+            return self.unexpected("`EQ`, `NEQ`, `GT`, `LT`, `IN`, `IN_CIDR`, `BETWEEN`, or `ON`");
+        };
+        self.bump();
+        let value = self.value()?;
+        Ok(build(attr, value))
+    }
+
+    /// `[v1 v2 ..]` after `attr IN`, as an `OR` of equalities.
+    fn one_of(&mut self, attr: String) -> Result<OwnedCondition, ParseError> {
+        self.expect(Token::LBracket, "`[`")?;
+        let mut operands = Vec::new();
+        while self.peek().0 != Token::RBracket {
+            let value = self.value()?;
+            operands.push(OwnedCondition::Equals {
+                attr: attr.clone(),
+                value,
+            });
+        }
+        self.bump();
+        if operands.is_empty() {
+            return Ok(OwnedCondition::False);
+        }
+        Ok(balanced(operands, OwnedCondition::Or))
+    }
+
+    /// A block after `attr IN_CIDR`, checked here so a typo is reported
+    /// where it was written.
+    fn in_cidr(&mut self, attr: String) -> Result<OwnedCondition, ParseError> {
+        let cidr = match &self.peek().0 {
+            Token::Str(s) if Cidr::parse(s).is_ok() => s.clone(),
+            _ => return self.unexpected("a CIDR block"),
+        };
+        self.bump();
+        Ok(OwnedCondition::IpInCidr { attr, cidr })
+    }
+
+    /// `"HH:MM"` or `"HH:MM:SS"`, in seconds since midnight.
+    fn time_of_day(&mut self) -> Result<u32, ParseError> {
+        let seconds = match &self.peek().0 {
+            Token::Str(s) => parse_time_of_day(s).ok(),
+            _ => None,
+        };
+        let Some(seconds) = seconds else {
+            return self.unexpected("a time of day (\"HH:MM\")");
+        };
+        self.bump();
+        Ok(seconds)
+    }
+
+    /// `["mon" "tue" ..]` after `attr ON`.
+    fn days(&mut self, attr: String) -> Result<OwnedCondition, ParseError> {
+        self.expect(Token::LBracket, "`[`")?;
+        let mut days = Weekdays::NONE;
+        while self.peek().0 != Token::RBracket {
+            let day = match &self.peek().0 {
+                Token::Str(s) => Weekdays::parse_day(s).ok(),
+                _ => None,
+            };
+            let Some(day) = day else {
+                return self.unexpected("a day of the week (\"mon\" to \"sun\")");
+            };
+            days = days | day;
+            self.bump();
+        }
+        self.bump();
+        Ok(OwnedCondition::DayOfWeek { attr, days })
+    }
+
+    fn value(&mut self) -> Result<OwnedValue, ParseError> {
+        let value = match self.peek().0.clone() {
+            Token::Str(s) => OwnedValue::String(s),
+            Token::Int(i) => OwnedValue::Int(i),
+            Token::Duration(d, _) => OwnedValue::Duration(d),
+            Token::Ident(s) if s == "true" => OwnedValue::Bool(true),
+            Token::Ident(s) if s == "false" => OwnedValue::Bool(false),
+            _ => return self.unexpected("a value (string, integer, duration, `true`, or `false`)"),
+        };
+        self.bump();
+        Ok(value)
+    }
+}
+
+/// Join `operands` with a binary connective as a balanced tree.
+fn balanced(
+    mut operands: Vec<OwnedCondition>,
+    join: fn(Box<OwnedCondition>, Box<OwnedCondition>) -> OwnedCondition,
+) -> OwnedCondition {
+    if operands.len() == 1 {
+        return operands.pop().expect("one operand");
+    }
+    let right = operands.split_off(operands.len() / 2);
+    join(
+        Box::new(balanced(operands, join)),
+        Box::new(balanced(right, join)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Request, Value};
+
+    #[test]
+    fn test_parse_rules() {
+        let owned = parse_policy(
+            r#"
+            DENY "contractors never see payroll" (@"contractors" * "payroll") => 9;
+            ALLOW ("alice" ["read" "write"] *)
+                WHERE { (role EQ "admin") AND NOT ("suspended" EQ true) } => 3;
+            ALLOW (* "read" *) WHERE { TRUE OR (level NEQ -1) } => 1;
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(owned.rule_count(), 3);
+        let rules = owned.rules();
+        assert_eq!(rules[0].effect, Effect::Deny);
+        assert_eq!(
+            rules[0].name.as_deref(),
+            Some("contractors never see payroll")
+        );
+        assert_eq!(rules[1].name, None);
+        assert_eq!(
+            rules[0].target.principal,
+            OwnedMatcher::Group("contractors".into())
+        );
+        assert_eq!(
+            rules[1].target.action,
+            OwnedMatcher::OneOf(vec!["read".into(), "write".into()])
+        );
+        assert_eq!(
+            rules[1].condition,
+            Some(OwnedCondition::And(
+                Box::new(OwnedCondition::Equals {
+                    attr: "role".into(),
+                    value: OwnedValue::String("admin".into()),
+                }),
+                Box::new(OwnedCondition::Not(Box::new(OwnedCondition::Equals {
+                    attr: "suspended".into(),
+                    value: OwnedValue::Bool(true),
+                }))),
+            ))
+        );
+        assert_eq!(rules[2].reason, ReasonCode(1));
+
+        let policy = owned.policy().unwrap();
+        let ctx: &[(&str, Value)] = &[
+            ("role", Value::String("admin")),
+            ("suspended", Value::Bool(false)),
+        ];
+        let decision = policy
+            .evaluate(&Request::with_context("alice", "write", "doc", ctx))
+            .unwrap();
+        assert_eq!(decision.reason, ReasonCode(3));
+    }
+
+    #[test]
+    fn test_in_lists() {
+        let owned =
+            parse_policy(r#"ALLOW (* * *) WHERE { (level IN [1 2 3]) AND ("a" IN []) } => 1;"#)
+                .unwrap();
+        let level = |i| OwnedCondition::Equals {
+            attr: "level".into(),
+            value: OwnedValue::Int(i),
+        };
+        assert_eq!(
+            owned.rules()[0].condition,
+            Some(OwnedCondition::And(
+                Box::new(OwnedCondition::Or(
+                    Box::new(level(1)),
+                    Box::new(OwnedCondition::Or(Box::new(level(2)), Box::new(level(3)))),
+                )),
+                Box::new(OwnedCondition::False),
+            ))
+        );
+
+        assert_eq!(
+            parse_policy("ALLOW (* * *) WHERE { (a IN [1 x]) } => 1;")
+                .unwrap_err()
+                .to_string(),
+            "line 1, column 32: expected a value (string, integer, duration, `true`, or `false`), found `x`"
+        );
+    }
+
+    #[test]
+    fn test_in_cidr() {
+        let owned =
+            parse_policy(r#"ALLOW (* * *) WHERE { (source_ip IN_CIDR "10.0.0.0/8") } => 1;"#)
+                .unwrap();
+        assert_eq!(
+            owned.rules()[0].condition,
+            Some(OwnedCondition::IpInCidr {
+                attr: "source_ip".into(),
+                cidr: "10.0.0.0/8".into(),
+            })
+        );
+
+        let policy = owned.policy().unwrap();
+        let inside: &[(&str, Value)] = &[("source_ip", Value::Ip([10, 1, 2, 3].into()))];
+        let outside: &[(&str, Value)] = &[("source_ip", Value::Ip([11, 0, 0, 1].into()))];
+        let request = |ctx| Request::with_context("alice", "read", "doc", ctx);
+        assert!(policy.evaluate(&request(inside)).unwrap().is_allow());
+        assert!(!policy.evaluate(&request(outside)).unwrap().is_allow());
+
+        assert_eq!(
+            parse_policy(r#"ALLOW (* * *) WHERE { (ip IN_CIDR "10.0.0.1/8") } => 1;"#)
+                .unwrap_err()
+                .to_string(),
+            "line 1, column 35: expected a CIDR block, found \"10.0.0.1/8\""
+        );
+    }
+
+    #[test]
+    fn test_time_windows() {
+        let owned = parse_policy(
+            r#"ALLOW (* * *) WHERE { (now BETWEEN "09:00" "17:30") AND NOT (now ON ["sat" "sun"]) } => 1;"#,
+        )
+        .unwrap();
+        assert_eq!(
+            owned.rules()[0].condition,
+            Some(OwnedCondition::And(
+                Box::new(OwnedCondition::TimeBetween {
+                    attr: "now".into(),
+                    start: 9 * 3600,
+                    end: 17 * 3600 + 30 * 60,
+                }),
+                Box::new(OwnedCondition::Not(Box::new(OwnedCondition::DayOfWeek {
+                    attr: "now".into(),
+                    days: Weekdays::WEEKEND,
+                }))),
+            ))
+        );
+
+        // 2024-01-05 was a Friday, 2024-01-06 a Saturday
+        let policy = owned.policy().unwrap();
+        let allowed = |t| {
+            let ctx: &[(&str, Value)] = &[("now", Value::Timestamp(t))];
+            policy
+                .evaluate(&Request::with_context("alice", "read", "doc", ctx))
+                .unwrap()
+                .is_allow()
+        };
+        assert!(allowed(1_704_445_200)); // Friday 09:00
+        assert!(!allowed(1_704_477_600)); // Friday 18:00
+        assert!(!allowed(1_704_531_600)); // Saturday 09:00
+
+        let error = |source: &str| parse_policy(source).unwrap_err().to_string();
+        assert_eq!(
+            error(r#"ALLOW (* * *) WHERE { (now BETWEEN "9:00" "17:00") } => 1;"#),
+            "line 1, column 36: expected a time of day (\"HH:MM\"), found \"9:00\""
+        );
+        assert_eq!(
+            error(r#"ALLOW (* * *) WHERE { (now ON ["sat" "Sun"]) } => 1;"#),
+            "line 1, column 38: expected a day of the week (\"mon\" to \"sun\"), found \"Sun\""
+        );
+    }
+
+    #[test]
+    fn test_ordering_and_durations() {
+        let owned =
+            parse_policy(r#"DENY (* "login" *) WHERE { (session GT 1h) OR (level LT -2) } => 1;"#)
+                .unwrap();
+        assert_eq!(
+            owned.rules()[0].condition,
+            Some(OwnedCondition::Or(
+                Box::new(OwnedCondition::GreaterThan {
+                    attr: "session".into(),
+                    value: OwnedValue::Duration(3600),
+                }),
+                Box::new(OwnedCondition::LessThan {
+                    attr: "level".into(),
+                    value: OwnedValue::Int(-2),
+                }),
+            ))
+        );
+
+        let policy = owned.policy().unwrap();
+        let denied = |session| {
+            let ctx: &[(&str, Value)] = &[("session", Value::Duration(session))];
+            !policy
+                .evaluate(&Request::with_context("alice", "login", "vpn", ctx))
+                .unwrap()
+                .is_allow()
+        };
+        assert!(denied(3601));
+
+        let error = |source: &str| parse_policy(source).unwrap_err();
+        assert_eq!(
+            error("ALLOW (* * *) WHERE { (ttl LT 30m1h) } => 1;").kind,
+            ParseErrorKind::InvalidDuration("30m1h".into())
+        );
+        assert_eq!(
+            error("ALLOW (* * *) WHERE { (ttl LT -1h) } => 1;").to_string(),
+            "line 1, column 31: invalid duration `-1h`"
+        );
+        assert_eq!(
+            parse_policy(r#"ALLOW (* * *) WHERE { (role GT "admin") } => 1;"#)
+                .unwrap()
+                .policy()
+                .unwrap_err(),
+            crate::PolicyError::UnorderedValue
+        );
+    }
+
+    #[test]
+    fn test_cache_hints() {
+        let owned = parse_policy(
+            r#"
+            DENY (* * *) WHERE { (risk GT 50) } => 2 CACHE 0s CONTEXT;
+            ALLOW (* "read" *) => 1 CACHE 5m;
+            ALLOW (* "list" *) => 3 CACHE CONTEXT;
+            ALLOW (* "write" *) => 4;
+            "#,
+        )
+        .unwrap();
+        let hints: Vec<_> = owned.rules().iter().map(|rule| rule.cache).collect();
+        assert_eq!(
+            hints,
+            [
+                Some(CacheHint::ttl(0).per_context()),
+                Some(CacheHint::ttl(300)),
+                Some(CacheHint::PER_CONTEXT),
+                None,
+            ]
+        );
+
+        let error = |source: &str| parse_policy(source).unwrap_err().to_string();
+        assert_eq!(
+            error("ALLOW (* * *) => 1 CACHE;"),
+            "line 1, column 25: expected a duration or `CONTEXT`, found `;`"
+        );
+        assert_eq!(
+            error("ALLOW (* * *) => 1 TTL 5m;"),
+            "line 1, column 20: expected `CACHE` or `;`, found `TTL`"
+        );
+    }
+
+    #[test]
+    fn test_long_chains_are_balanced() {
+        // 16 terms in a left-leaning tree would exceed the default depth of 10
+        let terms: Vec<String> = (0..16).map(|i| format!("(a{} EQ {})", i, i)).collect();
+        let source = format!("ALLOW (* * *) WHERE {{ {} }} => 1;", terms.join(" AND "));
+        let owned = parse_policy(&source).unwrap();
+        assert!(owned.policy().is_ok());
+    }
+
+    #[test]
+    fn test_errors_have_positions() {
+        let error = |source: &str| parse_policy(source).unwrap_err();
+
+        assert_eq!(
+            error("ALLOW (* * *) => 1;\nPERMIT (* * *) => 2;"),
+            ParseError {
+                line: 2,
+                column: 1,
+                kind: ParseErrorKind::Unexpected {
+                    expected: "`ALLOW` or `DENY`",
+                    found: "`PERMIT`".into(),
+                },
+            }
+        );
+        assert_eq!(
+            error("ALLOW (* * *) WHERE { (role CONTAINS 1) } => 1;").to_string(),
+            "line 1, column 29: expected `EQ`, `NEQ`, `GT`, `LT`, `IN`, `IN_CIDR`, `BETWEEN`, or `ON`, found `CONTAINS`"
+        );
+        assert_eq!(
+            error("ALLOW (\"a) => 1;").kind,
+            ParseErrorKind::UnterminatedString
+        );
+        assert_eq!(
+            error("ALLOW (* * *) => 4294967296;").kind,
+            ParseErrorKind::IntegerOutOfRange
+        );
+        assert_eq!(
+            error("ALLOW (* * ?) => 1;").kind,
+            ParseErrorKind::UnexpectedChar('?')
+        );
+
+        // Nesting is bounded before recursion gets deep
+        let deep = format!(
+            "ALLOW (* * *) WHERE {{ {} TRUE }} => 1;",
+            "NOT ".repeat(100_000)
+        );
+        assert_eq!(error(&deep).kind, ParseErrorKind::TooDeep { max: 10 });
+    }
+}
@@ -0,0 +1,481 @@
+//! Timestamps, times of day, days of the week, and durations.
+//!
+//! The engine never reads a clock: the caller puts the time in the context
+//! as a `Value::Timestamp` (seconds since the Unix epoch, UTC), so the same
+//! request always gets the same decision. `Condition::TimeBetween` and
+//! `Condition::DayOfWeek` take the time of day and the weekday from that
+//! timestamp in UTC; to check local business hours, add the zone's offset to
+//! the timestamp before putting it in the context.
+//!
+//! Durations (`Value::Duration`) are whole seconds, written in policies as
+//! `1h30m`: days, hours, minutes, and seconds (`d`, `h`, `m`, `s`), largest
+//! first, each at most once.
+
+use core::fmt;
+use core::ops::BitOr;
+
+use crate::error::PolicyError;
+
+/// Seconds in a day.
+pub const SECONDS_PER_DAY: u32 = 86_400;
+
+/// Seconds since midnight UTC of `timestamp`.
+pub(crate) const fn time_of_day(timestamp: i64) -> u32 {
+    timestamp.rem_euclid(SECONDS_PER_DAY as i64) as u32
+}
+
+/// A set of days of the week.
+///
+/// # Example
+///
+/// ```
+/// use gate0::Weekdays;
+///
+/// let days = Weekdays::MONDAY | Weekdays::FRIDAY;
+/// assert!(days.contains(Weekdays::FRIDAY));
+/// assert!(!days.contains(Weekdays::SUNDAY));
+/// assert_eq!(days.to_string(), "mon fri");
+///
+/// // 2024-01-06 was a Saturday
+/// assert_eq!(Weekdays::of(1_704_499_200), Weekdays::SATURDAY);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Weekdays(u8);
+
+/// Day names in bit order, as parsed and displayed.
+const NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+impl Weekdays {
+    /// No days.
+    pub const NONE: Weekdays = Weekdays(0);
+    /// Monday.
+    pub const MONDAY: Weekdays = Weekdays(1);
+    /// Tuesday.
+    pub const TUESDAY: Weekdays = Weekdays(1 << 1);
+    /// Wednesday.
+    pub const WEDNESDAY: Weekdays = Weekdays(1 << 2);
+    /// Thursday.
+    pub const THURSDAY: Weekdays = Weekdays(1 << 3);
+    /// Friday.
+    pub const FRIDAY: Weekdays = Weekdays(1 << 4);
+    /// Saturday.
+    pub const SATURDAY: Weekdays = Weekdays(1 << 5);
+    /// Sunday.
+    pub const SUNDAY: Weekdays = Weekdays(1 << 6);
+    /// Monday to Friday.
+    pub const WORKDAYS: Weekdays = Weekdays(0b001_1111);
+    /// Saturday and Sunday.
+    pub const WEEKEND: Weekdays = Weekdays(0b110_0000);
+    /// Every day.
+    pub const ALL: Weekdays = Weekdays(0b111_1111);
+
+    /// The set from its bits, Monday in the lowest; bits above Sunday are
+    /// ignored.
+    pub const fn from_bits(bits: u8) -> Self {
+        Weekdays(bits & Weekdays::ALL.0)
+    }
+
+    /// The set as bits, Monday in the lowest.
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// The day `timestamp` falls on, in UTC.
+    pub const fn of(timestamp: i64) -> Self {
+        // 1970-01-01 was a Thursday
+        let day = (timestamp.div_euclid(SECONDS_PER_DAY as i64) + 3).rem_euclid(7);
+        Weekdays(1 << day)
+    }
+
+    /// Both sets' days.
+    pub const fn union(self, other: Weekdays) -> Self {
+        Weekdays(self.0 | other.0)
+    }
+
+    /// Returns `true` if every day in `days` is in the set.
+    pub const fn contains(self, days: Weekdays) -> bool {
+        self.0 & days.0 == days.0
+    }
+
+    /// Returns `true` if the set has no days.
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The day named `name`: `mon`, `tue`, `wed`, `thu`, `fri`, `sat`, or
+    /// `sun`.
+    pub const fn parse_day(name: &str) -> Result<Self, PolicyError> {
+        let mut day = 0;
+        while day < NAMES.len() {
+            if str_eq(NAMES[day], name) {
+                return Ok(Weekdays(1 << day));
+            }
+            day += 1;
+        }
+        Err(PolicyError::InvalidTime)
+    }
+
+    /// The days named in `names`, as for `parse_day`.
+    pub const fn parse_days(names: &[&str]) -> Result<Self, PolicyError> {
+        let mut days = Weekdays::NONE;
+        let mut i = 0;
+        while i < names.len() {
+            match Weekdays::parse_day(names[i]) {
+                Ok(day) => days = days.union(day),
+                Err(e) => return Err(e),
+            }
+            i += 1;
+        }
+        Ok(days)
+    }
+
+    /// The names of the days in the set, Monday first.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        NAMES
+            .iter()
+            .enumerate()
+            .filter(move |(day, _)| self.0 & (1 << day) != 0)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl BitOr for Weekdays {
+    type Output = Weekdays;
+
+    fn bitor(self, other: Weekdays) -> Weekdays {
+        self.union(other)
+    }
+}
+
+/// Serializes as a list of day names, e.g. `["mon", "fri"]`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Weekdays {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.names())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Weekdays {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let names = alloc::vec::Vec::<alloc::string::String>::deserialize(deserializer)?;
+        names.iter().try_fold(Weekdays::NONE, |days, name| {
+            Weekdays::parse_day(name)
+                .map(|day| days | day)
+                .map_err(|_| {
+                    serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(name),
+                        &"a day of the week (`mon` to `sun`)",
+                    )
+                })
+        })
+    }
+}
+
+/// Formats the day names separated by spaces, e.g. `mon fri`.
+impl fmt::Display for Weekdays {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, name) in self.names().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse `HH:MM` or `HH:MM:SS` into seconds since midnight. `24:00` is the
+/// end of the day.
+pub const fn parse_time_of_day(s: &str) -> Result<u32, PolicyError> {
+    let bytes = s.as_bytes();
+    if bytes.len() != 5 && bytes.len() != 8 {
+        return Err(PolicyError::InvalidTime);
+    }
+    let Some(hours) = two_digits(bytes, 0) else {
+        return Err(PolicyError::InvalidTime);
+    };
+    let Some(minutes) = two_digits(bytes, 3) else {
+        return Err(PolicyError::InvalidTime);
+    };
+    let seconds = if bytes.len() == 8 {
+        match two_digits(bytes, 6) {
+            Some(seconds) if bytes[5] == b':' => seconds,
+            _ => return Err(PolicyError::InvalidTime),
+        }
+    } else {
+        0
+    };
+    if bytes[2] != b':' || minutes > 59 || seconds > 59 {
+        return Err(PolicyError::InvalidTime);
+    }
+    let total = hours * 3600 + minutes * 60 + seconds;
+    if total > SECONDS_PER_DAY {
+        return Err(PolicyError::InvalidTime);
+    }
+    Ok(total)
+}
+
+/// Parse a duration such as `15m`, `1h30m`, or `2d` into seconds.
+///
+/// # Example
+///
+/// ```
+/// assert_eq!(gate0::parse_duration("1h30m"), Ok(5400));
+/// assert!(gate0::parse_duration("30m1h").is_err());
+/// ```
+pub const fn parse_duration(s: &str) -> Result<u64, PolicyError> {
+    let bytes = s.as_bytes();
+    if bytes.is_empty() {
+        return Err(PolicyError::InvalidDuration);
+    }
+    let mut total: u64 = 0;
+    // Units must appear largest first, so each is below the previous
+    let mut previous = u64::MAX;
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        let mut count: u64 = 0;
+        while i < bytes.len() && bytes[i].is_ascii_digit() {
+            count = match count.checked_mul(10) {
+                Some(c) => match c.checked_add((bytes[i] - b'0') as u64) {
+                    Some(c) => c,
+                    None => return Err(PolicyError::InvalidDuration),
+                },
+                None => return Err(PolicyError::InvalidDuration),
+            };
+            i += 1;
+        }
+        if i == start || i == bytes.len() {
+            return Err(PolicyError::InvalidDuration);
+        }
+        let unit = match bytes[i] {
+            b'd' => 86_400,
+            b'h' => 3600,
+            b'm' => 60,
+            b's' => 1,
+            _ => return Err(PolicyError::InvalidDuration),
+        };
+        if unit >= previous {
+            return Err(PolicyError::InvalidDuration);
+        }
+        previous = unit;
+        total = match count.checked_mul(unit) {
+            Some(seconds) => match total.checked_add(seconds) {
+                Some(total) => total,
+                None => return Err(PolicyError::InvalidDuration),
+            },
+            None => return Err(PolicyError::InvalidDuration),
+        };
+        i += 1;
+    }
+    Ok(total)
+}
+
+/// Format a duration the way `parse_duration` reads it, e.g. `1h30m`.
+pub(crate) struct DurationText(pub u64);
+
+impl fmt::Display for DurationText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 == 0 {
+            return f.write_str("0s");
+        }
+        let mut rest = self.0;
+        for (unit, name) in [(86_400, 'd'), (3600, 'h'), (60, 'm'), (1, 's')] {
+            if rest >= unit {
+                write!(f, "{}{}", rest / unit, name)?;
+                rest %= unit;
+            }
+        }
+        Ok(())
+    }
+}
+
+const fn two_digits(bytes: &[u8], at: usize) -> Option<u32> {
+    let (a, b) = (bytes[at], bytes[at + 1]);
+    if !a.is_ascii_digit() || !b.is_ascii_digit() {
+        return None;
+    }
+    Some((a - b'0') as u32 * 10 + (b - b'0') as u32)
+}
+
+/// Check a `TimeBetween` window: a start before the end of the day and an
+/// end no later than it.
+pub(crate) const fn validate_window(start: u32, end: u32) -> Result<(), PolicyError> {
+    if start < SECONDS_PER_DAY && end <= SECONDS_PER_DAY {
+        Ok(())
+    } else {
+        Err(PolicyError::InvalidTime)
+    }
+}
+
+/// Returns `true` if `timestamp`'s time of day is in `[start, end)`; a
+/// window with `start > end` wraps past midnight.
+pub(crate) const fn in_window(timestamp: i64, start: u32, end: u32) -> bool {
+    let t = time_of_day(timestamp);
+    if start <= end {
+        start <= t && t < end
+    } else {
+        t >= start || t < end
+    }
+}
+
+/// Format a timestamp as RFC 3339 in UTC, e.g. `2024-01-06T00:00:00Z`.
+pub(crate) struct Rfc3339(pub i64);
+
+impl fmt::Display for Rfc3339 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = self.0.div_euclid(SECONDS_PER_DAY as i64);
+        let t = time_of_day(self.0);
+        // Civil date from days since the epoch (Howard Hinnant's algorithm)
+        let z = days as i128 + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year,
+            month,
+            day,
+            t / 3600,
+            t / 60 % 60,
+            t % 60
+        )
+    }
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_weekdays() {
+        // 1970-01-01 was a Thursday, 1969-12-31 a Wednesday
+        assert_eq!(Weekdays::of(0), Weekdays::THURSDAY);
+        assert_eq!(Weekdays::of(-1), Weekdays::WEDNESDAY);
+        assert_eq!(Weekdays::of(1_704_585_599), Weekdays::SATURDAY);
+        assert_eq!(Weekdays::of(1_704_585_600), Weekdays::SUNDAY);
+
+        assert!(Weekdays::WORKDAYS.contains(Weekdays::of(0)));
+        assert_eq!(Weekdays::WORKDAYS | Weekdays::WEEKEND, Weekdays::ALL);
+        assert_eq!(Weekdays::parse_days(&["sat", "sun"]), Ok(Weekdays::WEEKEND));
+        assert_eq!(
+            Weekdays::parse_days(&["mon", "Tue"]),
+            Err(PolicyError::InvalidTime)
+        );
+        assert_eq!(Weekdays::WEEKEND.to_string(), "sat sun");
+        assert_eq!(Weekdays::from_bits(0xff), Weekdays::ALL);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_weekdays_json() {
+        let days = Weekdays::MONDAY | Weekdays::SUNDAY;
+        assert_eq!(serde_json::to_string(&days).unwrap(), r#"["mon","sun"]"#);
+        assert_eq!(
+            serde_json::from_str::<Weekdays>(r#"["sun","mon"]"#).unwrap(),
+            days
+        );
+        assert!(serde_json::from_str::<Weekdays>(r#"["monday"]"#).is_err());
+    }
+
+    #[test]
+    fn test_times_of_day() {
+        assert_eq!(parse_time_of_day("09:00"), Ok(9 * 3600));
+        assert_eq!(parse_time_of_day("17:30:15"), Ok(17 * 3600 + 30 * 60 + 15));
+        assert_eq!(parse_time_of_day("24:00"), Ok(SECONDS_PER_DAY));
+        for bad in ["9:00", "24:01", "12:60", "12:00:60", "12-00", "12:00:", ""] {
+            assert_eq!(
+                parse_time_of_day(bad),
+                Err(PolicyError::InvalidTime),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(parse_duration("15m"), Ok(900));
+        assert_eq!(parse_duration("1d2h3m4s"), Ok(93_784));
+        assert_eq!(parse_duration("90m"), Ok(5400));
+        assert_eq!(parse_duration("0s"), Ok(0));
+        for bad in [
+            "",
+            "15",
+            "m",
+            "1h1h",
+            "30m1h",
+            "1x",
+            "-1h",
+            "1 h",
+            "99999999999999999999s",
+        ] {
+            assert_eq!(
+                parse_duration(bad),
+                Err(PolicyError::InvalidDuration),
+                "{}",
+                bad
+            );
+        }
+
+        for (seconds, text) in [
+            (0, "0s"),
+            (5400, "1h30m"),
+            (93_784, "1d2h3m4s"),
+            (86_400, "1d"),
+        ] {
+            assert_eq!(DurationText(seconds).to_string(), text);
+            assert_eq!(parse_duration(text), Ok(seconds));
+        }
+    }
+
+    #[test]
+    fn test_windows() {
+        let nine = 9 * 3600;
+        let five = 17 * 3600;
+        assert!(in_window(nine as i64, nine, five));
+        assert!(!in_window(five as i64, nine, five));
+        // 16:00 and 17:00 the day before the epoch
+        assert!(in_window(-8 * 3600, nine, five));
+        assert!(!in_window(-7 * 3600, nine, five));
+
+        // Overnight windows wrap
+        assert!(in_window(23 * 3600, 22 * 3600, 6 * 3600));
+        assert!(in_window(3600, 22 * 3600, 6 * 3600));
+        assert!(!in_window(12 * 3600, 22 * 3600, 6 * 3600));
+
+        assert!(validate_window(0, SECONDS_PER_DAY).is_ok());
+        assert!(validate_window(SECONDS_PER_DAY, 0).is_err());
+    }
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(Rfc3339(0).to_string(), "1970-01-01T00:00:00Z");
+        assert_eq!(Rfc3339(1_704_499_200).to_string(), "2024-01-06T00:00:00Z");
+        assert_eq!(Rfc3339(951_782_400).to_string(), "2000-02-29T00:00:00Z");
+        assert_eq!(Rfc3339(-1).to_string(), "1969-12-31T23:59:59Z");
+    }
+}
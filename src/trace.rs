@@ -0,0 +1,325 @@
+//! Evaluation traces for explaining decisions.
+//!
+//! `Policy::evaluate_with_trace` records, for every rule the evaluator
+//! considered, whether its target matched and how its condition evaluated,
+//! followed by the outcome: which rule decided, or which error stopped
+//! evaluation. Rules skipped by the target index are not listed; they
+//! could not have matched.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::error::PolicyError;
+use crate::observer::Observer;
+use crate::policy::Policy;
+use crate::types::{Decision, Effect, ReasonCode, Request};
+
+/// How a rule's condition evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConditionOutcome {
+    /// The target did not match, so the condition was not evaluated.
+    NotEvaluated,
+    /// The rule has no condition.
+    Unconditional,
+    /// The condition evaluated to true.
+    True,
+    /// The condition evaluated to false.
+    False,
+}
+
+/// What happened to one rule during evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RuleTrace {
+    /// Index of the rule in the policy.
+    pub index: usize,
+    /// The rule's effect.
+    pub effect: Effect,
+    /// The rule's reason code.
+    pub reason: ReasonCode,
+    /// Whether the rule's target matched the request.
+    pub target_matched: bool,
+    /// How the rule's condition evaluated.
+    pub condition: ConditionOutcome,
+}
+
+impl RuleTrace {
+    /// Returns `true` if the rule matched (target and condition).
+    pub fn matched(&self) -> bool {
+        self.target_matched
+            && matches!(
+                self.condition,
+                ConditionOutcome::Unconditional | ConditionOutcome::True
+            )
+    }
+}
+
+/// Why evaluation stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TraceOutcome {
+    /// All candidate rules were checked and a decision was reached.
+    Decided {
+        /// The decision.
+        decision: Decision,
+        /// Index of the rule that determined the decision, or `None` if no
+        /// rule matched and the default deny applied.
+        rule: Option<usize>,
+    },
+    /// Evaluation failed; rules after the last traced one were not checked.
+    Failed(PolicyError),
+}
+
+/// A complete record of one evaluation.
+///
+/// `Display` renders one line per traced rule followed by the outcome.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvaluationTrace {
+    /// Every rule considered, in evaluation order.
+    pub rules: Vec<RuleTrace>,
+    /// Why evaluation stopped.
+    pub outcome: TraceOutcome,
+}
+
+impl EvaluationTrace {
+    /// The result `Policy::evaluate` returns for the same request.
+    pub fn result(&self) -> Result<Decision, PolicyError> {
+        match &self.outcome {
+            TraceOutcome::Decided { decision, .. } => Ok(*decision),
+            TraceOutcome::Failed(err) => Err(err.clone()),
+        }
+    }
+}
+
+impl fmt::Display for EvaluationTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rule in &self.rules {
+            let effect = match rule.effect {
+                Effect::Allow => "allow",
+                Effect::Deny => "deny",
+            };
+            let detail = match (rule.target_matched, rule.condition) {
+                (false, _) => "target did not match",
+                (true, ConditionOutcome::Unconditional) => "matched (no condition)",
+                (true, ConditionOutcome::True) => "matched (condition true)",
+                (true, ConditionOutcome::False) => "condition false",
+                (true, ConditionOutcome::NotEvaluated) => "condition not evaluated",
+            };
+            writeln!(
+                f,
+                "rule {} ({}, reason {}): {}",
+                rule.index, effect, rule.reason.0, detail
+            )?;
+        }
+
+        match &self.outcome {
+            TraceOutcome::Decided { decision, rule } => {
+                let effect = if decision.is_allow() { "ALLOW" } else { "DENY" };
+                match rule {
+                    Some(index) => write!(
+                        f,
+                        "=> {} (reason {}) by rule {}",
+                        effect, decision.reason.0, index
+                    ),
+                    None => write!(
+                        f,
+                        "=> {} (reason {}) by default, no rule matched",
+                        effect, decision.reason.0
+                    ),
+                }
+            }
+            TraceOutcome::Failed(err) => write!(f, "=> error: {}", err),
+        }
+    }
+}
+
+/// Observer that builds the per-rule trace.
+struct Tracer<'p, 'a> {
+    policy: &'p Policy<'a>,
+    rules: Vec<RuleTrace>,
+}
+
+impl Tracer<'_, '_> {
+    fn current(&mut self, index: usize) -> Option<&mut RuleTrace> {
+        self.rules.last_mut().filter(|t| t.index == index)
+    }
+}
+
+impl Observer for Tracer<'_, '_> {
+    fn rule_checked(&mut self, index: usize) {
+        if let Some(rule) = self.policy.rules().get(index) {
+            self.rules.push(RuleTrace {
+                index,
+                effect: rule.effect,
+                reason: rule.reason,
+                target_matched: false,
+                condition: ConditionOutcome::NotEvaluated,
+            });
+        }
+    }
+
+    fn target_matched(&mut self, index: usize) {
+        let unconditional = self
+            .policy
+            .rules()
+            .get(index)
+            .is_some_and(|r| r.condition.is_none());
+        if let Some(trace) = self.current(index) {
+            trace.target_matched = true;
+            if unconditional {
+                trace.condition = ConditionOutcome::Unconditional;
+            }
+        }
+    }
+
+    fn condition_result(&mut self, index: usize, result: bool) {
+        if let Some(trace) = self.current(index) {
+            trace.condition = if result {
+                ConditionOutcome::True
+            } else {
+                ConditionOutcome::False
+            };
+        }
+    }
+}
+
+impl Policy<'_> {
+    /// Evaluate this policy against a request, recording a trace.
+    ///
+    /// Same semantics as `evaluate()`; `trace.result()` returns exactly what
+    /// `evaluate()` would. Unlike `evaluate()`, a failed evaluation still
+    /// returns the rules checked before the error.
+    ///
+    /// Allocates the trace, so use `evaluate()` on hot paths.
+    pub fn evaluate_with_trace(&self, request: &Request<'_>) -> EvaluationTrace {
+        let mut tracer = Tracer {
+            policy: self,
+            rules: Vec::new(),
+        };
+        let result = self.evaluate_observed(request, &mut tracer);
+        let rules = tracer.rules;
+
+        let outcome = match result {
+            Ok((decision, _)) => {
+                // Deny-overrides: the first matching rule with the winning effect
+                let rule = rules
+                    .iter()
+                    .find(|t| t.matched() && t.effect == decision.effect)
+                    .map(|t| t.index);
+                TraceOutcome::Decided { decision, rule }
+            }
+            Err(err) => TraceOutcome::Failed(err),
+        };
+
+        EvaluationTrace { rules, outcome }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Matcher, PolicyConfig, Rule, Target, Value, NO_MATCHING_RULE};
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn policy(config: PolicyConfig) -> Policy<'static> {
+        Policy::with_config(
+            vec![
+                Rule::allow(Target::any(), ReasonCode(1)),
+                Rule::new(
+                    Effect::Deny,
+                    Target::any(),
+                    Some(Condition::Equals {
+                        attr: "suspended",
+                        value: Value::Bool(true),
+                    }),
+                    ReasonCode(2),
+                ),
+                Rule::deny(
+                    Target {
+                        principal: Matcher::Exact("mallory"),
+                        action: Matcher::Any,
+                        resource: Matcher::Any,
+                    },
+                    ReasonCode(3),
+                ),
+            ],
+            config,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_trace_records_each_rule() {
+        let policy = policy(PolicyConfig::default());
+        let ctx: &[(&str, Value)] = &[("suspended", Value::Bool(true))];
+        let request = Request::with_context("alice", "read", "doc", ctx);
+
+        let trace = policy.evaluate_with_trace(&request);
+        assert_eq!(trace.result(), policy.evaluate(&request));
+
+        let outcomes: Vec<(bool, ConditionOutcome)> = trace
+            .rules
+            .iter()
+            .map(|t| (t.target_matched, t.condition))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (true, ConditionOutcome::Unconditional),
+                (true, ConditionOutcome::True),
+                (false, ConditionOutcome::NotEvaluated),
+            ]
+        );
+        assert_eq!(
+            trace.outcome,
+            TraceOutcome::Decided {
+                decision: Decision::deny(ReasonCode(2)),
+                rule: Some(1),
+            }
+        );
+        assert!(trace.to_string().ends_with("=> DENY (reason 2) by rule 1"));
+    }
+
+    #[test]
+    fn test_trace_default_deny() {
+        let policy = Policy::builder()
+            .rule(Rule::allow(
+                Target {
+                    principal: Matcher::Exact("admin"),
+                    action: Matcher::Any,
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            ))
+            .build()
+            .unwrap();
+
+        let trace = policy.evaluate_with_trace(&Request::new("alice", "read", "doc"));
+        assert_eq!(
+            trace.outcome,
+            TraceOutcome::Decided {
+                decision: Decision::deny(NO_MATCHING_RULE),
+                rule: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_trace_keeps_rules_before_error() {
+        let config = PolicyConfig {
+            max_eval_steps: 2,
+            ..PolicyConfig::default()
+        };
+        let policy = policy(config);
+
+        let trace = policy.evaluate_with_trace(&Request::new("alice", "read", "doc"));
+        assert_eq!(trace.rules.len(), 2);
+        assert!(matches!(
+            trace.outcome,
+            TraceOutcome::Failed(PolicyError::StepBudgetExceeded { max: 2, .. })
+        ));
+        assert_eq!(
+            trace.result(),
+            policy.evaluate(&Request::new("alice", "read", "doc"))
+        );
+    }
+}
@@ -6,6 +6,8 @@ use crate::value::Value;
 
 /// The effect of a policy decision.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum Effect {
     /// Access is allowed.
     Allow,
@@ -34,7 +36,14 @@ impl Effect {
 /// - Stability across versions
 /// - No typos in reason strings
 /// - Efficient storage and comparison
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Codes may be namespaced: `ReasonCode::namespaced(domain, code)` puts the
+/// domain in the high 16 bits and the code in the low 16, so policy modules
+/// written independently and composed into one policy cannot collide as
+/// long as their domains differ. Plain codes below 65536 are in domain 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct ReasonCode(pub u32);
 
 impl ReasonCode {
@@ -49,11 +58,79 @@ impl ReasonCode {
     pub const fn value(&self) -> u32 {
         self.0
     }
+
+    /// Create reason code `code` in `domain`.
+    ///
+    /// ```
+    /// use gate0::{ReasonCode, ReasonDomain};
+    ///
+    /// let code = ReasonCode::namespaced(7, 1);
+    /// assert_eq!(code.domain(), ReasonDomain(7));
+    /// assert_eq!(code.code(), 1);
+    /// assert_eq!(code.value(), 7 << 16 | 1);
+    /// ```
+    #[inline]
+    pub const fn namespaced(domain: u16, code: u16) -> Self {
+        ReasonCode((domain as u32) << 16 | code as u32)
+    }
+
+    /// The domain of this reason code (the high 16 bits).
+    #[inline]
+    pub const fn domain(&self) -> ReasonDomain {
+        ReasonDomain((self.0 >> 16) as u16)
+    }
+
+    /// The code within its domain (the low 16 bits).
+    #[inline]
+    pub const fn code(&self) -> u16 {
+        self.0 as u16
+    }
+}
+
+/// A namespace of reason codes, owned by one policy module.
+///
+/// Domain 0 holds the plain codes below 65536, `NO_MATCHING_RULE`
+/// included, and `DEFAULT_ALLOW` is the last code of domain `u16::MAX`;
+/// give modules domains from 1 up.
+///
+/// ```
+/// use gate0::{ReasonCode, ReasonDomain};
+///
+/// const BILLING: ReasonDomain = ReasonDomain(3);
+/// const INVOICE_OVERDUE: ReasonCode = BILLING.reason(1);
+///
+/// assert!(BILLING.contains(INVOICE_OVERDUE));
+/// assert_ne!(INVOICE_OVERDUE, ReasonDomain(4).reason(1));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct ReasonDomain(pub u16);
+
+impl ReasonDomain {
+    /// Reason code `code` in this domain.
+    #[inline]
+    pub const fn reason(self, code: u16) -> ReasonCode {
+        ReasonCode::namespaced(self.0, code)
+    }
+
+    /// Returns `true` if `reason` is in this domain.
+    #[inline]
+    pub const fn contains(self, reason: ReasonCode) -> bool {
+        reason.domain().0 == self.0
+    }
 }
 
 /// Reason code returned when no rules match the request.
 pub const NO_MATCHING_RULE: ReasonCode = ReasonCode(0);
 
+/// Reason code returned when no rules match and the policy's
+/// `PolicyConfig::default_effect` is `Allow`.
+///
+/// Kept distinct from `NO_MATCHING_RULE` so audits can tell a default
+/// allow from an explicit one. Do not use it for rules.
+pub const DEFAULT_ALLOW: ReasonCode = ReasonCode(u32::MAX);
+
 /// An authorization request.
 ///
 /// All fields are borrowed to avoid allocation during evaluation.
@@ -77,7 +154,7 @@ pub struct Request<'a> {
 
 impl<'a> Request<'a> {
     /// Create a new request with no context.
-    pub fn new(principal: &'a str, action: &'a str, resource: &'a str) -> Self {
+    pub const fn new(principal: &'a str, action: &'a str, resource: &'a str) -> Self {
         Request {
             principal,
             action,
@@ -87,7 +164,7 @@ impl<'a> Request<'a> {
     }
 
     /// Create a new request with context.
-    pub fn with_context(
+    pub const fn with_context(
         principal: &'a str,
         action: &'a str,
         resource: &'a str,
@@ -112,6 +189,81 @@ impl<'a> Request<'a> {
     }
 }
 
+/// How long, and for which requests, a decision may be reused.
+///
+/// Rules declare hints with `Rule::cache`; a decision carries the strictest
+/// hint of the rules that could have changed it (see `Decision::cache`).
+/// The engine never reads a clock, so honoring the hint is up to the
+/// caller.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct CacheHint {
+    /// Seconds the decision may be reused, or `None` for no limit. Zero
+    /// means it must not be reused at all.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub ttl: Option<u64>,
+    /// Whether the decision holds only while the request context is
+    /// unchanged.
+    ///
+    /// A cache keyed on principal, action, and resource alone must not
+    /// reuse it for a request with a different context.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "is_false"))]
+    pub per_context: bool,
+}
+
+#[cfg(feature = "serde")]
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl CacheHint {
+    /// No limit: the decision may be reused for as long as the policy is.
+    pub const UNLIMITED: CacheHint = CacheHint {
+        ttl: None,
+        per_context: false,
+    };
+
+    /// Reuse only for requests with the same context.
+    pub const PER_CONTEXT: CacheHint = CacheHint {
+        ttl: None,
+        per_context: true,
+    };
+
+    /// Reuse for at most `seconds`.
+    pub const fn ttl(seconds: u64) -> Self {
+        CacheHint {
+            ttl: Some(seconds),
+            per_context: false,
+        }
+    }
+
+    /// This hint, reusable only for requests with the same context.
+    pub const fn per_context(mut self) -> Self {
+        self.per_context = true;
+        self
+    }
+
+    /// The stricter of two hints: the shorter TTL, per context if either is.
+    pub const fn min(self, other: CacheHint) -> Self {
+        let ttl = match (self.ttl, other.ttl) {
+            (Some(a), Some(b)) => Some(if a < b { a } else { b }),
+            (Some(t), None) | (None, Some(t)) => Some(t),
+            (None, None) => None,
+        };
+        CacheHint {
+            ttl,
+            per_context: self.per_context || other.per_context,
+        }
+    }
+}
+
+impl Default for CacheHint {
+    fn default() -> Self {
+        CacheHint::UNLIMITED
+    }
+}
+
 /// The result of evaluating a policy against a request.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Decision {
@@ -119,13 +271,32 @@ pub struct Decision {
     pub effect: Effect,
     /// The reason code explaining the decision.
     pub reason: ReasonCode,
+    /// The strictest cache hint of the rules that could have changed this
+    /// decision, or `None` if none of them declares one.
+    ///
+    /// Those are the rules whose target matched the request, up to the
+    /// first matching rule of each effect: a later Deny could still
+    /// override an Allow, but nothing after the first matching Deny can
+    /// change a Deny.
+    pub cache: Option<CacheHint>,
 }
 
 impl Decision {
     /// Create a new decision.
     #[inline]
     pub const fn new(effect: Effect, reason: ReasonCode) -> Self {
-        Decision { effect, reason }
+        Decision {
+            effect,
+            reason,
+            cache: None,
+        }
+    }
+
+    /// Attach a cache hint.
+    #[inline]
+    pub const fn with_cache(mut self, cache: Option<CacheHint>) -> Self {
+        self.cache = cache;
+        self
     }
 
     /// Create an Allow decision with the given reason.
@@ -172,6 +343,20 @@ mod tests {
         assert_eq!(NO_MATCHING_RULE.value(), 0);
     }
 
+    #[test]
+    fn test_namespaced_reason_code() {
+        let code = ReasonDomain(2).reason(5);
+        assert_eq!(code, ReasonCode::namespaced(2, 5));
+        assert_eq!(code.value(), 0x0002_0005);
+        assert_eq!((code.domain(), code.code()), (ReasonDomain(2), 5));
+
+        // Plain codes are in domain 0
+        assert_eq!(ReasonCode(42).domain(), ReasonDomain(0));
+        assert_eq!(ReasonCode(42), ReasonDomain(0).reason(42));
+        assert!(ReasonDomain(0).contains(NO_MATCHING_RULE));
+        assert_eq!(DEFAULT_ALLOW, ReasonDomain(u16::MAX).reason(u16::MAX));
+    }
+
     #[test]
     fn test_request_no_context() {
         let req = Request::new("alice", "read", "document.txt");
@@ -203,5 +388,20 @@ mod tests {
         assert!(!deny.is_allow());
         assert!(deny.is_deny());
         assert_eq!(deny.reason.value(), 2);
+        assert_eq!(deny.cache, None);
+    }
+
+    #[test]
+    fn test_cache_hint_min() {
+        let hint = CacheHint::ttl(300).min(CacheHint::ttl(60));
+        assert_eq!(hint, CacheHint::ttl(60));
+        assert_eq!(
+            CacheHint::ttl(60).min(CacheHint::PER_CONTEXT),
+            CacheHint::ttl(60).per_context()
+        );
+        assert_eq!(
+            CacheHint::UNLIMITED.min(CacheHint::UNLIMITED),
+            CacheHint::UNLIMITED
+        );
     }
 }
@@ -1,8 +1,14 @@
 //! Context value types.
 //!
-//! Minimal set: Bool, Int, String only.
+//! Minimal set: Bool, Int, String, Ip, Timestamp, and Duration.
 //! No Float, List, or Null - smaller surface = stronger guarantees.
 
+use core::cmp::Ordering;
+use core::fmt;
+use core::net::IpAddr;
+
+use crate::time::{DurationText, Rfc3339};
+
 /// A value that can appear in request context.
 ///
 /// Intentionally minimal to reduce complexity and attack surface.
@@ -14,6 +20,37 @@ pub enum Value<'a> {
     Int(i64),
     /// Borrowed string slice.
     String(&'a str),
+    /// IP address, e.g. the request's source address.
+    Ip(IpAddr),
+    /// Seconds since the Unix epoch, UTC, supplied by the caller (the
+    /// engine never reads a clock).
+    Timestamp(i64),
+    /// A length of time in whole seconds, e.g. a requested session length.
+    Duration(u64),
+}
+
+impl From<bool> for Value<'_> {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<i64> for Value<'_> {
+    fn from(i: i64) -> Self {
+        Value::Int(i)
+    }
+}
+
+impl From<IpAddr> for Value<'_> {
+    fn from(ip: IpAddr) -> Self {
+        Value::Ip(ip)
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(s: &'a str) -> Self {
+        Value::String(s)
+    }
 }
 
 impl<'a> Value<'a> {
@@ -62,14 +99,131 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Returns the address if this is an `Ip`, otherwise `None`.
+    #[inline]
+    pub fn as_ip(&self) -> Option<IpAddr> {
+        match self {
+            Value::Ip(ip) => Some(*ip),
+            _ => None,
+        }
+    }
+
+    /// Returns the seconds since the epoch if this is a `Timestamp`,
+    /// otherwise `None`.
+    #[inline]
+    pub fn as_timestamp(&self) -> Option<i64> {
+        match self {
+            Value::Timestamp(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    /// Returns the seconds if this is a `Duration`, otherwise `None`.
+    #[inline]
+    pub fn as_duration(&self) -> Option<u64> {
+        match self {
+            Value::Duration(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    /// Order two values of the same ordered type (`Int`, `Timestamp`, or
+    /// `Duration`); `None` for other types or mixed types.
+    ///
+    /// This is the order `GreaterThan` and `LessThan` use. It is not a
+    /// `PartialOrd` impl because equal strings would have no order.
+    pub fn ordering(&self, other: &Value<'_>) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+            (Value::Duration(a), Value::Duration(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
     /// Returns a string describing the type of this value.
     pub fn type_name(&self) -> &'static str {
+        self.value_type().name()
+    }
+
+    /// Returns the type of this value.
+    #[inline]
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            Value::Bool(_) => ValueType::Bool,
+            Value::Int(_) => ValueType::Int,
+            Value::String(_) => ValueType::String,
+            Value::Ip(_) => ValueType::Ip,
+            Value::Timestamp(_) => ValueType::Timestamp,
+            Value::Duration(_) => ValueType::Duration,
+        }
+    }
+}
+
+/// Formats booleans, integers, and addresses plainly, strings quoted, e.g.
+/// `"admin"`, timestamps as RFC 3339 in UTC, e.g. `2024-01-06T09:00:00Z`, and
+/// durations in units, e.g. `1h30m`.
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Int(i) => write!(f, "{}", i),
+            Value::String(s) => write!(f, "{:?}", s),
+            Value::Ip(ip) => write!(f, "{}", ip),
+            Value::Timestamp(t) => write!(f, "{}", Rfc3339(*t)),
+            Value::Duration(d) => write!(f, "{}", DurationText(*d)),
+        }
+    }
+}
+
+/// The type of a `Value`, without its payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValueType {
+    /// `Value::Bool`.
+    Bool,
+    /// `Value::Int`.
+    Int,
+    /// `Value::String`.
+    String,
+    /// `Value::Ip`.
+    Ip,
+    /// `Value::Timestamp`.
+    Timestamp,
+    /// `Value::Duration`.
+    Duration,
+}
+
+impl ValueType {
+    /// Every value type.
+    pub const ALL: [ValueType; 6] = [
+        ValueType::Bool,
+        ValueType::Int,
+        ValueType::String,
+        ValueType::Ip,
+        ValueType::Timestamp,
+        ValueType::Duration,
+    ];
+
+    /// The type's name, as returned by `Value::type_name`.
+    pub fn name(&self) -> &'static str {
         match self {
-            Value::Bool(_) => "Bool",
-            Value::Int(_) => "Int",
-            Value::String(_) => "String",
+            ValueType::Bool => "Bool",
+            ValueType::Int => "Int",
+            ValueType::String => "String",
+            ValueType::Ip => "Ip",
+            ValueType::Timestamp => "Timestamp",
+            ValueType::Duration => "Duration",
         }
     }
+
+    /// Returns `true` if values of this type can be compared with
+    /// `GreaterThan` and `LessThan`.
+    pub fn is_ordered(&self) -> bool {
+        matches!(
+            self,
+            ValueType::Int | ValueType::Timestamp | ValueType::Duration
+        )
+    }
 }
 
 #[cfg(test)]
@@ -86,6 +240,7 @@ mod tests {
         assert_eq!(v.as_int(), None);
         assert_eq!(v.as_str(), None);
         assert_eq!(v.type_name(), "Bool");
+        assert_eq!(v.value_type(), ValueType::Bool);
     }
 
     #[test]
@@ -112,6 +267,45 @@ mod tests {
         assert_eq!(v.type_name(), "String");
     }
 
+    #[test]
+    fn test_value_ip() {
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let v = Value::Ip(ip);
+        assert!(!v.is_string());
+        assert_eq!(v.as_ip(), Some(ip));
+        assert_eq!(v.as_str(), None);
+        assert_eq!(v.type_name(), "Ip");
+        assert_eq!(alloc::format!("{}", v), "10.0.0.1");
+        assert_eq!(Value::String("a").as_ip(), None);
+    }
+
+    #[test]
+    fn test_value_timestamp() {
+        let v = Value::Timestamp(1_704_531_600);
+        assert!(!v.is_int());
+        assert_eq!(v.as_timestamp(), Some(1_704_531_600));
+        assert_eq!(v.as_int(), None);
+        assert_eq!(v.type_name(), "Timestamp");
+        assert_eq!(alloc::format!("{}", v), "2024-01-06T09:00:00Z");
+        assert_ne!(v, Value::Int(1_704_531_600));
+    }
+
+    #[test]
+    fn test_value_duration() {
+        let v = Value::Duration(5400);
+        assert_eq!(v.as_duration(), Some(5400));
+        assert_eq!(v.as_int(), None);
+        assert_eq!(v.type_name(), "Duration");
+        assert_eq!(alloc::format!("{}", v), "1h30m");
+
+        assert_eq!(v.ordering(&Value::Duration(60)), Some(Ordering::Greater));
+        assert_eq!(Value::Int(1).ordering(&Value::Int(2)), Some(Ordering::Less));
+        assert_eq!(v.ordering(&Value::Int(5400)), None);
+        assert_eq!(Value::String("a").ordering(&Value::String("a")), None);
+        assert!(ValueType::Duration.is_ordered());
+        assert!(!ValueType::String.is_ordered());
+    }
+
     #[test]
     fn test_value_equality() {
         assert_eq!(Value::Bool(true), Value::Bool(true));
@@ -0,0 +1,158 @@
+//! JavaScript bindings via `wasm-bindgen` (feature `wasm`).
+//!
+//! `WasmPolicy` loads the JSON form of `OwnedPolicy` and evaluates
+//! `RequestData` JSON, returning the same canonical line
+//! `JsonDecisionLogger` writes (without the newline):
+//!
+//! ```js
+//! import { WasmPolicy } from "./gate0.js";
+//!
+//! const policy = new WasmPolicy(policyJson);
+//! const line = policy.evaluate(JSON.stringify({
+//!   principal: "alice", action: "read", resource: "doc", context: { mfa: true },
+//! }));
+//! const { effect, reason } = JSON.parse(line);
+//! ```
+//!
+//! The evaluator is the one native code uses, and nothing it hashes or
+//! compares depends on pointer width or host hash seeds, so a policy with a
+//! given `fingerprint()` decides a request identically in a browser, a
+//! Worker, and a backend. Because decisions come back as decision-log lines,
+//! that claim can be checked by diffing a Worker's output against the
+//! backend's log.
+//!
+//! Strings cross the boundary as JSON so the module needs nothing beyond
+//! `wasm-bindgen`. 64-bit values (fingerprints, request hashes) are hex
+//! strings, which JavaScript numbers could not hold exactly.
+
+use alloc::string::{String, ToString};
+
+use wasm_bindgen::prelude::*;
+
+use crate::fingerprint::request_hash;
+use crate::json_log::format_line;
+use crate::owned::{OwnedPolicy, SharedPolicy};
+use crate::request_data::RequestData;
+
+/// A loaded policy, exported to JavaScript as `WasmPolicy`.
+#[wasm_bindgen]
+pub struct WasmPolicy {
+    shared: SharedPolicy,
+}
+
+#[wasm_bindgen]
+impl WasmPolicy {
+    /// Load a policy from the JSON form of `OwnedPolicy`.
+    ///
+    /// Throws if the JSON is malformed or the policy violates its limits.
+    #[wasm_bindgen(constructor)]
+    pub fn new(json: &str) -> Result<WasmPolicy, JsError> {
+        WasmPolicy::from_json(json).map_err(|e| JsError::new(&e))
+    }
+
+    /// Evaluate a `RequestData` JSON request, returning the decision as a
+    /// canonical decision-log line.
+    ///
+    /// Throws if the request is malformed or evaluation fails.
+    pub fn evaluate(&self, request: &str) -> Result<String, JsError> {
+        self.evaluate_json(request).map_err(|e| JsError::new(&e))
+    }
+
+    /// The policy fingerprint, as 16 lowercase hex digits.
+    pub fn fingerprint(&self) -> String {
+        format!("{:016x}", self.shared.policy().fingerprint())
+    }
+
+    /// Number of rules in the policy.
+    #[wasm_bindgen(js_name = ruleCount)]
+    pub fn rule_count(&self) -> usize {
+        self.shared.policy().rule_count()
+    }
+}
+
+impl WasmPolicy {
+    fn from_json(json: &str) -> Result<WasmPolicy, String> {
+        let owned: OwnedPolicy =
+            serde_json::from_str(json).map_err(|e| format!("invalid policy JSON: {}", e))?;
+        let shared = SharedPolicy::new(owned).map_err(|e| format!("invalid policy: {}", e))?;
+        Ok(WasmPolicy { shared })
+    }
+
+    fn evaluate_json(&self, request: &str) -> Result<String, String> {
+        let data: RequestData =
+            serde_json::from_str(request).map_err(|e| format!("invalid request JSON: {}", e))?;
+        let builder = data.builder();
+        let request = builder.build().map_err(|e| e.to_string())?;
+        let policy = self.shared.policy();
+        let (decision, stats) = policy
+            .evaluate_with_stats(&request)
+            .map_err(|e| e.to_string())?;
+        let mut line = format_line(
+            request_hash(&request),
+            &decision,
+            policy.fingerprint(),
+            &stats,
+        );
+        line.pop();
+        Ok(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{JsonDecisionLogger, Request, Value};
+
+    const POLICY: &str = r#"{
+        "rules": [
+            {
+                "effect": "allow",
+                "target": { "principal": "any", "action": { "exact": "read" }, "resource": "any" },
+                "condition": { "equals": { "attr": "mfa", "value": true } },
+                "reason": 1
+            }
+        ]
+    }"#;
+
+    #[test]
+    fn test_matches_native_decision_log() {
+        let wasm = WasmPolicy::from_json(POLICY).unwrap();
+        assert_eq!(wasm.rule_count(), 1);
+        let line = wasm
+            .evaluate_json(
+                r#"{"principal":"alice","action":"read","resource":"doc","context":{"mfa":true}}"#,
+            )
+            .unwrap();
+
+        // The same policy evaluated natively and logged
+        let owned: OwnedPolicy = serde_json::from_str(POLICY).unwrap();
+        let policy = owned.policy().unwrap();
+        let logger = JsonDecisionLogger::new(Vec::new(), policy.fingerprint());
+        let ctx: &[(&str, Value)] = &[("mfa", Value::Bool(true))];
+        policy
+            .evaluate_with_sink(&Request::with_context("alice", "read", "doc", ctx), &logger)
+            .unwrap();
+        let log = String::from_utf8(logger.into_inner()).unwrap();
+
+        assert_eq!(format!("{}\n", line), log);
+        assert_eq!(wasm.fingerprint(), format!("{:016x}", policy.fingerprint()));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = WasmPolicy::from_json("[]").err().unwrap();
+        assert!(error.starts_with("invalid policy JSON"), "{}", error);
+
+        let too_many = POLICY.replace("\"rules\"", "\"config\": { \"max_rules\": 0 }, \"rules\"");
+        assert!(WasmPolicy::from_json(&too_many)
+            .err()
+            .unwrap()
+            .starts_with("invalid policy:"));
+
+        let wasm = WasmPolicy::from_json(POLICY).unwrap();
+        let error = wasm
+            .evaluate_json(r#"{"principal":"alice","action":"read"}"#)
+            .unwrap_err();
+        assert!(error.starts_with("invalid request JSON"), "{}", error);
+    }
+}
@@ -0,0 +1,313 @@
+//! What-if enumeration over finite domains.
+//!
+//! Access reviews ask questions like "what can alice do?" or "who can
+//! delete invoices?". Given finite sets of principals, actions, resources,
+//! and candidate values for context attributes, `Policy::what_if` evaluates
+//! every combination and yields one row per request, so the full decision
+//! table, or any slice of it, can be produced without leaving the crate.
+//!
+//! Rows are produced lazily in a fixed order: principals outermost, then
+//! actions, resources, and attributes in declared order, each varying
+//! fastest at the end. `Domain::size` reports the table size up front so
+//! callers can refuse domains that are too large to enumerate.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::error::PolicyError;
+use crate::policy::Policy;
+use crate::types::{Decision, Request};
+use crate::value::Value;
+
+/// Finite sets of request components to enumerate.
+///
+/// # Example
+///
+/// ```
+/// use gate0::{Domain, Matcher, Policy, ReasonCode, Rule, Target};
+///
+/// let policy = Policy::builder()
+///     .rule(Rule::allow(
+///         Target {
+///             principal: Matcher::Exact("alice"),
+///             action: Matcher::Exact("read"),
+///             resource: Matcher::Any,
+///         },
+///         ReasonCode(1),
+///     ))
+///     .build()
+///     .unwrap();
+///
+/// // Everything alice can do
+/// let domain = Domain::new()
+///     .principals(&["alice"])
+///     .actions(&["read", "write"])
+///     .resources(&["doc", "wiki"]);
+/// assert_eq!(domain.size(), Some(4));
+///
+/// let allowed: Vec<_> = policy
+///     .what_if(&domain)
+///     .filter(|row| row.is_allow())
+///     .map(|row| (row.action, row.resource))
+///     .collect();
+/// assert_eq!(allowed, [("read", "doc"), ("read", "wiki")]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Domain<'a> {
+    principals: Vec<&'a str>,
+    actions: Vec<&'a str>,
+    resources: Vec<&'a str>,
+    attrs: Vec<(&'a str, Vec<Value<'a>>)>,
+}
+
+impl<'a> Domain<'a> {
+    /// Create an empty domain.
+    pub fn new() -> Self {
+        Domain::default()
+    }
+
+    /// Add principals to enumerate.
+    pub fn principals(mut self, principals: &[&'a str]) -> Self {
+        self.principals.extend_from_slice(principals);
+        self
+    }
+
+    /// Add actions to enumerate.
+    pub fn actions(mut self, actions: &[&'a str]) -> Self {
+        self.actions.extend_from_slice(actions);
+        self
+    }
+
+    /// Add resources to enumerate.
+    pub fn resources(mut self, resources: &[&'a str]) -> Self {
+        self.resources.extend_from_slice(resources);
+        self
+    }
+
+    /// Add a context attribute and the candidate values it takes.
+    ///
+    /// Every row carries exactly one candidate per declared attribute.
+    /// Attributes that are not declared are absent from every row.
+    pub fn attr(mut self, name: &'a str, values: &[Value<'a>]) -> Self {
+        self.attrs.push((name, values.to_vec()));
+        self
+    }
+
+    /// Number of rows the domain enumerates, or `None` on overflow.
+    ///
+    /// Zero if any set, or any attribute's candidates, is empty.
+    pub fn size(&self) -> Option<usize> {
+        self.attrs.iter().try_fold(
+            self.principals
+                .len()
+                .checked_mul(self.actions.len())?
+                .checked_mul(self.resources.len())?,
+            |acc, (_, values)| acc.checked_mul(values.len()),
+        )
+    }
+
+    /// Number of dimensions: principal, action, resource, then attributes.
+    fn dims(&self) -> usize {
+        3 + self.attrs.len()
+    }
+
+    /// Length of dimension `i`.
+    fn dim_len(&self, i: usize) -> usize {
+        match i {
+            0 => self.principals.len(),
+            1 => self.actions.len(),
+            2 => self.resources.len(),
+            _ => self.attrs.get(i - 3).map_or(0, |(_, values)| values.len()),
+        }
+    }
+}
+
+/// One evaluated combination from a `Domain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhatIfRow<'d> {
+    /// The principal.
+    pub principal: &'d str,
+    /// The action.
+    pub action: &'d str,
+    /// The resource.
+    pub resource: &'d str,
+    /// One candidate value per declared attribute, in declared order.
+    pub context: Vec<(&'d str, Value<'d>)>,
+    /// The result of evaluating the request.
+    pub result: Result<Decision, PolicyError>,
+}
+
+impl WhatIfRow<'_> {
+    /// The request this row evaluated.
+    pub fn request(&self) -> Request<'_> {
+        Request::with_context(self.principal, self.action, self.resource, &self.context)
+    }
+
+    /// Returns `true` if the request was allowed.
+    pub fn is_allow(&self) -> bool {
+        matches!(&self.result, Ok(decision) if decision.is_allow())
+    }
+}
+
+/// Iterator over the rows of a what-if enumeration; see `Policy::what_if`.
+#[derive(Debug)]
+pub struct WhatIf<'p, 'a, 'd> {
+    policy: &'p Policy<'a>,
+    domain: &'d Domain<'d>,
+    /// Position in each dimension, or `None` once exhausted.
+    cursor: Option<Vec<usize>>,
+}
+
+impl<'d> Iterator for WhatIf<'_, '_, 'd> {
+    type Item = WhatIfRow<'d>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let cursor = self.cursor.as_mut()?;
+        let domain = self.domain;
+        let context: Vec<_> = domain
+            .attrs
+            .iter()
+            .zip(&cursor[3..])
+            .map(|((name, values), &i)| (*name, values[i].clone()))
+            .collect();
+        let (principal, action, resource) = (
+            domain.principals[cursor[0]],
+            domain.actions[cursor[1]],
+            domain.resources[cursor[2]],
+        );
+        let result = self.policy.evaluate(&Request::with_context(
+            principal, action, resource, &context,
+        ));
+
+        if !advance(cursor, |i| domain.dim_len(i)) {
+            self.cursor = None;
+        }
+
+        Some(WhatIfRow {
+            principal,
+            action,
+            resource,
+            context,
+            result,
+        })
+    }
+}
+
+/// Step a mixed-radix counter, last position fastest.
+///
+/// Returns `false`, leaving every position at 0, once all combinations have
+/// been visited.
+pub(crate) fn advance(cursor: &mut [usize], len: impl Fn(usize) -> usize) -> bool {
+    for (i, pos) in cursor.iter_mut().enumerate().rev() {
+        *pos += 1;
+        if *pos < len(i) {
+            return true;
+        }
+        *pos = 0;
+    }
+    false
+}
+
+impl<'a> Policy<'a> {
+    /// Evaluate every request in `domain`, lazily and in a fixed order.
+    ///
+    /// Yields `domain.size()` rows; filter the iterator for a slice of the
+    /// table, such as everything one principal is allowed to do.
+    pub fn what_if<'p, 'd>(&'p self, domain: &'d Domain<'d>) -> WhatIf<'p, 'a, 'd> {
+        let cursor = (domain.size() != Some(0)).then(|| vec![0; domain.dims()]);
+        WhatIf {
+            policy: self,
+            domain,
+            cursor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Condition, Effect, Matcher, ReasonCode, Rule, Target, NO_MATCHING_RULE};
+
+    fn policy() -> Policy<'static> {
+        Policy::builder()
+            .rule(Rule::deny(
+                Target {
+                    principal: Matcher::Any,
+                    action: Matcher::Exact("delete"),
+                    resource: Matcher::Any,
+                },
+                ReasonCode(1),
+            ))
+            .rule(Rule::new(
+                Effect::Allow,
+                Target::any(),
+                Some(Condition::Equals {
+                    attr: "role",
+                    value: Value::String("admin"),
+                }),
+                ReasonCode(2),
+            ))
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_full_table_order() {
+        let domain = Domain::new()
+            .principals(&["alice", "bob"])
+            .actions(&["read", "delete"])
+            .resources(&["doc"])
+            .attr("role", &[Value::String("admin"), Value::String("guest")]);
+        assert_eq!(domain.size(), Some(8));
+
+        let policy = policy();
+        let rows: Vec<_> = policy.what_if(&domain).collect();
+        assert_eq!(rows.len(), 8);
+
+        let summary: Vec<_> = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.principal,
+                    row.action,
+                    row.context[0].1.clone(),
+                    row.result.clone().unwrap().reason,
+                )
+            })
+            .collect();
+        let admin = Value::String("admin");
+        let guest = Value::String("guest");
+        assert_eq!(
+            summary[..4],
+            [
+                ("alice", "read", admin.clone(), ReasonCode(2)),
+                ("alice", "read", guest.clone(), NO_MATCHING_RULE),
+                ("alice", "delete", admin, ReasonCode(1)),
+                ("alice", "delete", guest, ReasonCode(1)),
+            ]
+        );
+        assert_eq!(rows[4].principal, "bob");
+        assert_eq!(policy.evaluate(&rows[4].request()), rows[4].result.clone());
+    }
+
+    #[test]
+    fn test_empty_dimension_yields_nothing() {
+        let policy = policy();
+        let domain = Domain::new().principals(&["alice"]).actions(&["read"]);
+        assert_eq!(domain.size(), Some(0));
+        assert_eq!(policy.what_if(&domain).count(), 0);
+
+        let domain = domain.resources(&["doc"]).attr("role", &[]);
+        assert_eq!(policy.what_if(&domain).count(), 0);
+
+        // Without attributes, the context is empty
+        let domain = Domain::new()
+            .principals(&["alice"])
+            .actions(&["read"])
+            .resources(&["doc"]);
+        let rows: Vec<_> = policy.what_if(&domain).collect();
+        assert_eq!(rows.len(), 1);
+        assert!(rows[0].context.is_empty());
+        assert!(!rows[0].is_allow());
+    }
+}
@@ -12,7 +12,9 @@
 use std::alloc::{GlobalAlloc, Layout, System};
 use std::sync::atomic::{AtomicUsize, Ordering};
 
-use gate0::{Condition, Effect, Matcher, Policy, ReasonCode, Request, Rule, Target, Value};
+use gate0::{
+    AuditRing, Condition, Effect, Matcher, Policy, ReasonCode, Request, Rule, Target, Value,
+};
 
 /// A counting allocator that wraps the system allocator.
 struct CountingAllocator;
@@ -210,3 +212,88 @@ fn test_zero_allocations_max_depth_condition() {
         "evaluate() with deep condition should perform zero allocations, but performed {count}"
     );
 }
+
+#[test]
+fn test_zero_allocations_audit_ring() {
+    let policy = Policy::builder()
+        .rule(Rule::allow(Target::any(), ReasonCode(1)))
+        .build()
+        .unwrap();
+    let ring: AuditRing<8> = AuditRing::new();
+
+    let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+    let request = Request::with_context("alice", "read", "doc", ctx);
+
+    // Warm-up
+    let _ = policy.evaluate_with_sink(&request, &ring);
+
+    reset_alloc_count();
+    for _ in 0..1000 {
+        let _ = policy.evaluate_with_sink(&request, &ring);
+    }
+    let count = get_alloc_count();
+
+    assert_eq!(
+        count, 0,
+        "recording into AuditRing should perform zero allocations, but performed {count}"
+    );
+}
+
+#[test]
+fn test_zero_allocations_all_denies() {
+    let policy = Policy::builder()
+        .rule(Rule::deny(Target::any(), ReasonCode(1)))
+        .rule(Rule::deny(Target::any(), ReasonCode(2)))
+        .build()
+        .unwrap();
+
+    let request = Request::new("alice", "read", "doc");
+
+    // Warm-up
+    let _ = policy.evaluate_all_denies(&request);
+
+    reset_alloc_count();
+    for _ in 0..1000 {
+        let _ = policy.evaluate_all_denies(&request);
+    }
+    let count = get_alloc_count();
+
+    assert_eq!(
+        count, 0,
+        "evaluate_all_denies() should perform zero allocations, but performed {count}"
+    );
+}
+
+#[test]
+fn test_zero_allocations_interned() {
+    let policy = Policy::builder()
+        .rule(Rule::new(
+            Effect::Allow,
+            Target::any(),
+            Some(Condition::Equals {
+                attr: "role",
+                value: Value::String("admin"),
+            }),
+            ReasonCode(1),
+        ))
+        .intern(true)
+        .build()
+        .unwrap();
+
+    let ctx: &[(&str, Value)] = &[("role", Value::String("admin"))];
+    let request = policy.intern(&Request::with_context("alice", "read", "doc", ctx));
+
+    // Warm-up
+    let _ = policy.evaluate_interned(&request);
+
+    reset_alloc_count();
+    for _ in 0..1000 {
+        let _ = policy.evaluate_interned(&request);
+    }
+    let count = get_alloc_count();
+
+    assert_eq!(
+        count, 0,
+        "evaluate_interned() should perform zero allocations, but performed {count}"
+    );
+}
@@ -0,0 +1,109 @@
+//! End-to-end tests for the `gate0` binary.
+
+#![cfg(feature = "cli")]
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const POLICY: &str = r#"{
+    "rules": [
+        {
+            "effect": "deny",
+            "target": { "principal": { "exact": "mallory" }, "action": "any", "resource": "any" },
+            "reason": 2
+        },
+        {
+            "effect": "allow",
+            "target": { "principal": "any", "action": { "exact": "read" }, "resource": "any" },
+            "reason": 1
+        }
+    ]
+}"#;
+
+/// Run the binary on `policy` with `input` on stdin.
+fn run(args: &[&str], policy: &str, input: &str) -> (i32, String, String) {
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+    let path = std::env::temp_dir().join(format!(
+        "gate0-cli-{}-{}.json",
+        std::process::id(),
+        RUNS.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::write(&path, policy).unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gate0"))
+        .args(args)
+        .arg(&path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_decisions_per_line() {
+    let input = concat!(
+        r#"{"principal":"alice","action":"read","resource":"doc"}"#,
+        "\n\n",
+        r#"{"principal":"mallory","action":"read","resource":"doc"}"#,
+        "\n",
+    );
+    let (code, stdout, stderr) = run(&["--summary"], POLICY, input);
+    assert_eq!(code, 0, "{}", stderr);
+
+    let lines: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        (&lines[0]["effect"], &lines[0]["reason"]),
+        (&"allow".into(), &1.into())
+    );
+    assert_eq!(
+        (&lines[1]["effect"], &lines[1]["reason"]),
+        (&"deny".into(), &2.into())
+    );
+    assert_eq!(lines[1]["stats"]["rules_checked"], 2);
+    assert!(
+        stderr.starts_with("2 evaluated: 1 allowed, 1 denied"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_bad_lines_and_policies() {
+    let input = concat!(
+        "not json\n",
+        r#"{"principal":"alice","action":"read","resource":"doc"}"#,
+        "\n",
+    );
+    let (code, stdout, _) = run(&[], POLICY, input);
+    assert_eq!(code, 1);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(
+        lines[0].starts_with(r#"{"line":1,"error":"#),
+        "{}",
+        lines[0]
+    );
+    assert!(lines[1].contains(r#""effect":"allow""#));
+
+    let (code, stdout, stderr) = run(&[], "{", "");
+    assert_eq!(code, 2);
+    assert!(stdout.is_empty());
+    assert!(stderr.starts_with("gate0: cannot load"), "{}", stderr);
+}
@@ -72,9 +72,77 @@ fn arb_rule_simple() -> impl Strategy<Value = Rule<'static>> {
             target,
             condition,
             reason,
+            name: None,
+            cache: None,
         })
 }
 
+/// Generate a condition over boolean attributes `a`, `b`, and `c`.
+fn arb_condition_attrs(max_depth: usize) -> impl Strategy<Value = Condition<'static>> {
+    let leaf = prop_oneof![
+        Just(Condition::True),
+        Just(Condition::False),
+        (prop::sample::select(&["a", "b", "c"][..]), any::<bool>()).prop_map(|(attr, b)| {
+            Condition::Equals {
+                attr,
+                value: Value::Bool(b),
+            }
+        }),
+        (prop::sample::select(&["a", "b", "c"][..]), any::<bool>()).prop_map(|(attr, b)| {
+            Condition::NotEquals {
+                attr,
+                value: Value::Bool(b),
+            }
+        }),
+    ];
+    leaf.prop_recursive(max_depth as u32, 32, 2, |inner| {
+        prop_oneof![
+            inner.clone().prop_map(|c| Condition::Not(Box::new(c))),
+            (inner.clone(), inner.clone())
+                .prop_map(|(a, b)| Condition::And(Box::new(a), Box::new(b))),
+            (inner.clone(), inner).prop_map(|(a, b)| Condition::Or(Box::new(a), Box::new(b))),
+        ]
+    })
+}
+
+/// Small fixed vocabulary so generated targets and requests collide often.
+const NAMES: &[&str] = &["alice", "bob", "carol", "read", "write"];
+
+/// Generate a matcher over `NAMES`.
+fn arb_matcher() -> impl Strategy<Value = Matcher<'static>> {
+    prop_oneof![
+        Just(Matcher::Any),
+        prop::sample::select(NAMES).prop_map(Matcher::Exact),
+        prop::sample::subsequence(NAMES, 0..=3).prop_map(Matcher::OneOfVec),
+    ]
+}
+
+/// Generate a rule with a random target over `NAMES`.
+fn arb_rule_targeted() -> impl Strategy<Value = Rule<'static>> {
+    (
+        arb_effect(),
+        arb_matcher(),
+        arb_matcher(),
+        arb_matcher(),
+        prop::option::of(arb_condition(3)),
+        arb_reason(),
+    )
+        .prop_map(
+            |(effect, principal, action, resource, condition, reason)| Rule {
+                effect,
+                target: Target {
+                    principal,
+                    action,
+                    resource,
+                },
+                condition,
+                reason,
+                name: None,
+                cache: None,
+            },
+        )
+}
+
 // =============================================================================
 // Property tests (bounded for safety)
 // =============================================================================
@@ -186,6 +254,11 @@ proptest! {
             max_context_attrs: 64,
             max_matcher_options: 64,
             max_string_len: 256,
+            max_eval_steps: usize::MAX,
+            max_condition_nodes: 65536,
+            max_attr_name_len: 256,
+            max_attr_value_len: 256,
+            default_effect: Effect::Deny,
         };
 
         let rule = Rule::new(
@@ -203,6 +276,102 @@ proptest! {
         }
     }
 
+    /// Invariant: The target index never changes a decision.
+    #[test]
+    fn prop_target_index_matches_linear(
+        rules in prop::collection::vec(arb_rule_targeted(), 0..30),
+        principal in prop::sample::select(NAMES),
+        action in prop::sample::select(NAMES),
+        resource in prop::sample::select(NAMES),
+    ) {
+        let build = |indexed: bool| {
+            rules
+                .iter()
+                .cloned()
+                .fold(Policy::builder().target_index(indexed), |b, r| b.rule(r))
+                .build()
+                .unwrap()
+        };
+        let linear = build(false);
+        let indexed = build(true);
+
+        let request = Request::new(principal, action, resource);
+        let (decision, stats) = indexed.evaluate_with_stats(&request).unwrap();
+
+        prop_assert_eq!(decision, linear.evaluate(&request).unwrap());
+        prop_assert!(stats.rules_checked as usize <= rules.len());
+    }
+
+    /// Invariant: Interning never changes a decision.
+    #[test]
+    fn prop_interning_matches_plain(
+        rules in prop::collection::vec(arb_rule_targeted(), 0..20),
+        conditions in prop::collection::vec(prop::option::of(arb_condition_attrs(3)), 20),
+        principal in prop::sample::select(NAMES),
+        action in prop::sample::select(NAMES),
+        resource in prop::sample::select(NAMES),
+        ctx_values in prop::collection::vec(prop::option::of(any::<bool>()), 3),
+    ) {
+        let ctx: Vec<(&str, Value)> = ["a", "b", "c"]
+            .iter()
+            .zip(&ctx_values)
+            .filter_map(|(k, v)| v.map(|b| (*k, Value::Bool(b))))
+            .collect();
+        let rules: Vec<Rule> = rules
+            .into_iter()
+            .zip(conditions)
+            .map(|(rule, condition)| Rule { condition, ..rule })
+            .collect();
+        let plain = Policy::new(rules.clone()).unwrap();
+        let interned = Policy::builder().rules_from_iter(rules).intern(true).build().unwrap();
+
+        let request = Request::with_context(principal, action, resource, &ctx);
+        prop_assert_eq!(
+            interned.evaluate_interned(&interned.intern(&request)),
+            plain.evaluate(&request)
+        );
+    }
+
+    /// Invariant: Compiled conditions decide exactly like the condition trees.
+    #[test]
+    fn prop_compiled_matches_tree(
+        conditions in prop::collection::vec(arb_condition_attrs(4), 1..10),
+        effects in prop::collection::vec(arb_effect(), 10),
+        ctx_values in prop::collection::vec(prop::option::of(any::<bool>()), 3),
+    ) {
+        let ctx: Vec<(&str, Value)> = ["a", "b", "c"]
+            .iter()
+            .zip(&ctx_values)
+            .filter_map(|(k, v)| v.map(|b| (*k, Value::Bool(b))))
+            .collect();
+
+        let rules: Vec<Rule> = conditions
+            .iter()
+            .zip(&effects)
+            .enumerate()
+            .map(|(i, (c, e))| Rule::new(*e, Target::any(), Some(c.clone()), ReasonCode(i as u32)))
+            .collect();
+
+        // Reference model: deny-overrides over tree evaluation
+        let matching: Vec<&Rule> = rules
+            .iter()
+            .filter(|r| r.condition.as_ref().unwrap().evaluate(&ctx).unwrap())
+            .collect();
+        let expected = matching
+            .iter()
+            .find(|r| r.effect.is_deny())
+            .or_else(|| matching.first())
+            .map(|r| (r.effect, r.reason))
+            .unwrap_or((Effect::Deny, NO_MATCHING_RULE));
+
+        let policy = Policy::new(rules.clone()).unwrap();
+        let decision = policy
+            .evaluate(&Request::with_context("p", "a", "r", &ctx))
+            .unwrap();
+
+        prop_assert_eq!((decision.effect, decision.reason), expected);
+    }
+
     /// Invariant: Rule count is enforced.
     #[test]
     fn prop_rule_count_enforced(
@@ -214,6 +383,11 @@ proptest! {
             max_context_attrs: 64,
             max_matcher_options: 64,
             max_string_len: 256,
+            max_eval_steps: usize::MAX,
+            max_condition_nodes: 65536,
+            max_attr_name_len: 256,
+            max_attr_value_len: 256,
+            default_effect: Effect::Deny,
         };
 
         let rules: Vec<Rule> = (0..rule_count)
@@ -243,6 +417,11 @@ fn test_worst_case_policy() {
         max_context_attrs: 64,
         max_matcher_options: 64,
         max_string_len: 256,
+        max_eval_steps: usize::MAX,
+        max_condition_nodes: 65536,
+        max_attr_name_len: 256,
+        max_attr_value_len: 256,
+        default_effect: Effect::Deny,
     };
 
     // Create a policy with maximum rules
@@ -302,6 +481,11 @@ fn test_context_too_large() {
         max_context_attrs: 5, // Very small limit
         max_matcher_options: 64,
         max_string_len: 256,
+        max_eval_steps: usize::MAX,
+        max_condition_nodes: 65536,
+        max_attr_name_len: 256,
+        max_attr_value_len: 256,
+        default_effect: Effect::Deny,
     };
 
     let policy =
@@ -351,7 +535,8 @@ fn test_max_condition_depth() {
         result,
         Err(PolicyError::ConditionTooDeep {
             max: 10,
-            actual: 11
+            actual: 11,
+            ..
         })
     ));
 }